@@ -1,3 +1,23 @@
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
 fn main() {
+  // Build metadata for get_app_info
+  let commit = Command::new("git")
+    .args(["rev-parse", "--short", "HEAD"])
+    .output()
+    .ok()
+    .filter(|output| output.status.success())
+    .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    .unwrap_or_else(|| "unknown".to_string());
+  let timestamp = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|elapsed| elapsed.as_secs())
+    .unwrap_or_default();
+  println!("cargo:rustc-env=HUSH_GIT_COMMIT={}", commit);
+  println!("cargo:rustc-env=HUSH_BUILD_TIMESTAMP={}", timestamp);
+  println!("cargo:rerun-if-changed=../.git/HEAD");
+  println!("cargo:rerun-if-changed=../.git/refs/heads");
+
   tauri_build::build()
 }
@@ -96,6 +96,12 @@ dependencies {
     // Lifecycle for foreground detection
     implementation("androidx.lifecycle:lifecycle-process:2.8.7")
 
+    // Keystore-backed EncryptedSharedPreferences for SecureStore
+    implementation("androidx.security:security-crypto:1.1.0-alpha06")
+
+    // BiometricPrompt for biometric app unlock
+    implementation("androidx.biometric:biometric:1.1.0")
+
     testImplementation("junit:junit:4.13.2")
     androidTestImplementation("androidx.test.ext:junit:1.1.4")
     androidTestImplementation("androidx.test.espresso:espresso-core:3.5.0")
@@ -0,0 +1,97 @@
+package social.hushnetwork
+
+import android.content.Context
+import android.util.Log
+import androidx.biometric.BiometricManager
+import androidx.biometric.BiometricPrompt
+import androidx.core.content.ContextCompat
+import androidx.fragment.app.FragmentActivity
+import java.util.concurrent.CountDownLatch
+import java.util.concurrent.TimeUnit
+
+/**
+ * Biometric prompt for HushNetwork
+ *
+ * Shows BiometricPrompt for `unlock_with_biometrics`. Rust calls [authenticate]
+ * from a background thread, which blocks until the prompt is answered. Results
+ * are plain ints so they cross JNI easily; keep them in step with
+ * `biometrics.rs`. Called from Rust over JNI.
+ */
+object BiometricHelper {
+
+    private const val TAG = "BiometricHelper"
+
+    const val SUCCESS = 0
+    const val USER_CANCEL = 1
+    const val UNAVAILABLE = 2
+    const val LOCKOUT = 3
+
+    private const val AUTHENTICATORS = BiometricManager.Authenticators.BIOMETRIC_STRONG or
+        BiometricManager.Authenticators.BIOMETRIC_WEAK
+
+    /** Give up on a prompt nobody answers */
+    private const val TIMEOUT_MINUTES = 5L
+
+    /**
+     * @return [SUCCESS] if a biometric is enrolled and usable, else why not
+     */
+    @JvmStatic
+    fun availability(context: Context): Int {
+        return when (BiometricManager.from(context).canAuthenticate(AUTHENTICATORS)) {
+            BiometricManager.BIOMETRIC_SUCCESS -> SUCCESS
+            else -> UNAVAILABLE
+        }
+    }
+
+    /**
+     * Show the prompt and wait for the answer. Must not be called on the main thread.
+     */
+    @JvmStatic
+    fun authenticate(context: Context, title: String, subtitle: String): Int {
+        val activity = context as? FragmentActivity ?: return UNAVAILABLE
+        if (availability(activity) != SUCCESS) {
+            return UNAVAILABLE
+        }
+
+        val latch = CountDownLatch(1)
+        var result = USER_CANCEL
+        val callback = object : BiometricPrompt.AuthenticationCallback() {
+            override fun onAuthenticationSucceeded(authResult: BiometricPrompt.AuthenticationResult) {
+                result = SUCCESS
+                latch.countDown()
+            }
+
+            override fun onAuthenticationError(errorCode: Int, errString: CharSequence) {
+                Log.d(TAG, "Biometric prompt ended: $errorCode $errString")
+                result = when (errorCode) {
+                    BiometricPrompt.ERROR_LOCKOUT,
+                    BiometricPrompt.ERROR_LOCKOUT_PERMANENT -> LOCKOUT
+                    BiometricPrompt.ERROR_HW_NOT_PRESENT,
+                    BiometricPrompt.ERROR_HW_UNAVAILABLE,
+                    BiometricPrompt.ERROR_NO_BIOMETRICS -> UNAVAILABLE
+                    else -> USER_CANCEL
+                }
+                latch.countDown()
+            }
+
+            // A single failed read leaves the prompt open for another try
+            override fun onAuthenticationFailed() {}
+        }
+
+        activity.runOnUiThread {
+            val info = BiometricPrompt.PromptInfo.Builder()
+                .setTitle(title)
+                .setSubtitle(subtitle)
+                .setAllowedAuthenticators(AUTHENTICATORS)
+                .setNegativeButtonText(activity.getString(android.R.string.cancel))
+                .build()
+            BiometricPrompt(activity, ContextCompat.getMainExecutor(activity), callback)
+                .authenticate(info)
+        }
+
+        if (!latch.await(TIMEOUT_MINUTES, TimeUnit.MINUTES)) {
+            return USER_CANCEL
+        }
+        return result
+    }
+}
@@ -9,6 +9,9 @@ import com.google.firebase.messaging.FirebaseMessaging
 import com.google.firebase.messaging.FirebaseMessagingService
 import com.google.firebase.messaging.RemoteMessage
 import kotlinx.coroutines.tasks.await
+import org.json.JSONArray
+import org.json.JSONException
+import org.json.JSONObject
 
 /**
  * Firebase Cloud Messaging Service for HushNetwork
@@ -23,7 +26,14 @@ class FcmService : FirebaseMessagingService() {
         private const val PREFS_NAME = "hush_fcm_prefs"
         private const val KEY_FCM_TOKEN = "fcm_token"
         private const val KEY_TOKEN_CHANGED = "token_changed"
+        /** JSON array of `{"feed_id", "post_id", "kind"}`, oldest first */
+        private const val KEY_PENDING_NAVIGATION_QUEUE = "pending_navigation_queue"
+        // Single-slot keys written by older versions, read once and removed
         private const val KEY_PENDING_NAVIGATION = "pending_feed_navigation"
+        private const val KEY_PENDING_NAVIGATION_POST_ID = "pending_post_navigation"
+        private const val KEY_PENDING_NAVIGATION_KIND = "pending_navigation_kind"
+        /** Taps kept while the app is closed; older ones are dropped */
+        private const val MAX_PENDING_NAVIGATIONS = 50
         private const val KEY_PENDING_DEEP_LINK = "pending_deep_link_path"
 
         // Default values for missing notification fields
@@ -32,6 +42,9 @@ class FcmService : FirebaseMessagingService() {
         private const val MAX_BODY_LENGTH = 255
         private const val MAX_TITLE_LENGTH = 100
 
+        /** Guards the pending navigation keys so a take can't race a tap */
+        private val navigationLock = Any()
+
         /**
          * Get the stored FCM token from SharedPreferences
          * @return The stored token, or null if not available
@@ -115,42 +128,180 @@ class FcmService : FirebaseMessagingService() {
         }
 
         /**
-         * Set pending feed navigation from notification tap.
+         * Read the pending navigation queue, including a tap stored in the
+         * single slot by an older version. Call with [navigationLock] held.
+         */
+        private fun readNavigationQueue(prefs: SharedPreferences): JSONArray {
+            val queue = try {
+                JSONArray(prefs.getString(KEY_PENDING_NAVIGATION_QUEUE, null) ?: "[]")
+            } catch (e: JSONException) {
+                Log.w(TAG, "Ignoring corrupt pending navigation queue", e)
+                JSONArray()
+            }
+            val legacyFeedId = prefs.getString(KEY_PENDING_NAVIGATION, null)
+            if (legacyFeedId != null) {
+                val legacy = navigationEntry(
+                    legacyFeedId,
+                    prefs.getString(KEY_PENDING_NAVIGATION_POST_ID, null),
+                    prefs.getString(KEY_PENDING_NAVIGATION_KIND, null)
+                )
+                val merged = JSONArray().put(legacy)
+                for (i in 0 until queue.length()) {
+                    merged.put(queue.get(i))
+                }
+                return merged
+            }
+            return queue
+        }
+
+        private fun navigationEntry(feedId: String, postId: String?, kind: String?): JSONObject {
+            return JSONObject()
+                .put("feed_id", feedId)
+                .put("post_id", postId ?: JSONObject.NULL)
+                .put("kind", kind ?: JSONObject.NULL)
+        }
+
+        /** The oldest pending navigation, if any. */
+        private fun headOfQueue(context: Context): JSONObject? {
+            synchronized(navigationLock) {
+                val queue = readNavigationQueue(getPrefs(context))
+                return if (queue.length() > 0) queue.getJSONObject(0) else null
+            }
+        }
+
+        /**
+         * Queue a pending feed navigation from a notification tap.
          * Called by MainActivity when a notification tap intent is received.
+         * Every tap is kept until taken, so several taps while the app is
+         * closed all reach the app.
          *
          * @param context The application context
          * @param feedId The feed ID to navigate to
+         * @param postId Optional post ID within the feed
+         * @param kind Optional navigation kind ("feed", "post", "mention", "dm")
          */
-        fun setPendingNavigation(context: Context, feedId: String) {
-            val prefs = getPrefs(context)
-            prefs.edit().putString(KEY_PENDING_NAVIGATION, feedId).apply()
-            Log.d(TAG, "Pending navigation set: ${feedId.take(8)}...")
+        fun setPendingNavigation(
+            context: Context,
+            feedId: String,
+            postId: String? = null,
+            kind: String? = null
+        ) {
+            synchronized(navigationLock) {
+                val prefs = getPrefs(context)
+                val queue = readNavigationQueue(prefs).put(navigationEntry(feedId, postId, kind))
+                val kept = JSONArray()
+                for (i in maxOf(0, queue.length() - MAX_PENDING_NAVIGATIONS) until queue.length()) {
+                    kept.put(queue.get(i))
+                }
+                prefs.edit()
+                    .putString(KEY_PENDING_NAVIGATION_QUEUE, kept.toString())
+                    .remove(KEY_PENDING_NAVIGATION)
+                    .remove(KEY_PENDING_NAVIGATION_POST_ID)
+                    .remove(KEY_PENDING_NAVIGATION_KIND)
+                    .commit()
+            }
+            Log.d(TAG, "Pending navigation queued: ${feedId.take(8)}... (kind: ${kind ?: "feed"})")
         }
 
         /**
-         * Get pending feed navigation from notification tap.
-         * Called by Tauri command to retrieve the feedId for navigation.
+         * Get the feedId of the oldest pending navigation.
+         * Kept for the JavaScript bridge; the Rust side takes the whole queue.
          *
          * @param context The application context
          * @return The pending feedId, or null if none pending
          */
         fun getPendingNavigation(context: Context): String? {
-            val prefs = getPrefs(context)
-            return prefs.getString(KEY_PENDING_NAVIGATION, null)
+            return headOfQueue(context)?.optString("feed_id")
+        }
+
+        /**
+         * Get the post ID attached to the oldest pending navigation, if any.
+         */
+        fun getPendingNavigationPostId(context: Context): String? {
+            return headOfQueue(context)?.takeUnless { it.isNull("post_id") }?.optString("post_id")
+        }
+
+        /**
+         * Get the navigation kind attached to the oldest pending navigation, if any.
+         */
+        fun getPendingNavigationKind(context: Context): String? {
+            return headOfQueue(context)?.takeUnless { it.isNull("kind") }?.optString("kind")
         }
 
         /**
-         * Clear pending feed navigation after TypeScript has processed it.
+         * Clear every pending navigation after TypeScript has processed it.
          * Called by Tauri command after navigation is complete.
          *
          * @param context The application context
          */
+        @JvmStatic
         fun clearPendingNavigation(context: Context) {
-            val prefs = getPrefs(context)
-            prefs.edit().remove(KEY_PENDING_NAVIGATION).apply()
+            synchronized(navigationLock) {
+                getPrefs(context).edit()
+                    .remove(KEY_PENDING_NAVIGATION_QUEUE)
+                    .remove(KEY_PENDING_NAVIGATION)
+                    .remove(KEY_PENDING_NAVIGATION_POST_ID)
+                    .remove(KEY_PENDING_NAVIGATION_KIND)
+                    .commit()
+            }
             Log.d(TAG, "Pending navigation cleared")
         }
 
+        /**
+         * Drop the oldest pending navigation once TypeScript has handled it
+         * through the JavaScript bridge, keeping taps queued behind it.
+         * Read and write happen in one edit under [navigationLock].
+         *
+         * @param context The application context
+         */
+        fun removeOldestPendingNavigation(context: Context) {
+            synchronized(navigationLock) {
+                val prefs = getPrefs(context)
+                val queue = readNavigationQueue(prefs)
+                val rest = JSONArray()
+                for (i in 1 until queue.length()) {
+                    rest.put(queue.get(i))
+                }
+                prefs.edit()
+                    .putString(KEY_PENDING_NAVIGATION_QUEUE, rest.toString())
+                    .remove(KEY_PENDING_NAVIGATION)
+                    .remove(KEY_PENDING_NAVIGATION_POST_ID)
+                    .remove(KEY_PENDING_NAVIGATION_KIND)
+                    .commit()
+            }
+            Log.d(TAG, "Oldest pending navigation removed")
+        }
+
+        /**
+         * Read and delete every pending navigation in one step: the read and
+         * the clearing edit happen under [navigationLock], the same lock a
+         * tap is queued under, so a tap is either in this result or left for
+         * the next take.
+         * Called from the Rust side before it reads its own queue.
+         *
+         * @param context The application context
+         * @return A JSON array of `{"feed_id", "post_id", "kind"}`, oldest
+         *   first, or empty string if none pending
+         */
+        @JvmStatic
+        fun takePendingNavigation(context: Context): String {
+            synchronized(navigationLock) {
+                val prefs = getPrefs(context)
+                val queue = readNavigationQueue(prefs)
+                if (queue.length() == 0) {
+                    return ""
+                }
+                prefs.edit()
+                    .remove(KEY_PENDING_NAVIGATION_QUEUE)
+                    .remove(KEY_PENDING_NAVIGATION)
+                    .remove(KEY_PENDING_NAVIGATION_POST_ID)
+                    .remove(KEY_PENDING_NAVIGATION_KIND)
+                    .commit()
+                Log.d(TAG, "Pending navigations taken: ${queue.length()}")
+                return queue.toString()
+            }
+        }
+
         /**
          * Set pending deep link path from App Link URL.
          * Called by MainActivity when the app is launched via a deep link.
@@ -250,13 +401,22 @@ class FcmService : FirebaseMessagingService() {
             return
         }
 
+        // Skip feeds the user has muted
+        val feedId = notificationData.feedId
+        if (feedId != null && NotificationHelper.isFeedMuted(applicationContext, feedId)) {
+            Log.i(TAG, "Feed is muted, suppressing notification for feed: ${feedId.take(8)}...")
+            return
+        }
+
         // Show the notification
         Log.i(TAG, "App is in background/killed, showing notification for feed: ${notificationData.feedId?.take(8) ?: "unknown"}...")
         NotificationHelper.showNotification(
             context = applicationContext,
             title = notificationData.title,
             body = notificationData.body,
-            feedId = notificationData.feedId ?: ""
+            feedId = notificationData.feedId ?: "",
+            postId = notificationData.postId,
+            kind = notificationData.type
         )
     }
 
@@ -289,6 +449,7 @@ class FcmService : FirebaseMessagingService() {
         var title = data["title"]?.trim()
         var body = data["body"]?.trim()
         val feedId = data["feedId"]?.trim()
+        val postId = data["postId"]?.trim()?.takeIf { it.isNotEmpty() }
         val type = data["type"]?.trim()
 
         // Log what we extracted
@@ -325,6 +486,7 @@ class FcmService : FirebaseMessagingService() {
             title = title,
             body = body,
             feedId = feedId,
+            postId = postId,
             type = type
         )
     }
@@ -336,6 +498,7 @@ class FcmService : FirebaseMessagingService() {
         val title: String,
         val body: String,
         val feedId: String?,
+        val postId: String?,
         val type: String?
     )
 }
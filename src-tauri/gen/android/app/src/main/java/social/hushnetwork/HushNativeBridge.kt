@@ -121,13 +121,45 @@ class HushNativeBridge(private val context: Context) {
     }
 
     /**
-     * Clear pending feed navigation after TypeScript has processed it.
+     * Get the post ID attached to the pending navigation.
+     *
+     * @return The postId, or empty string if the notification targeted the whole feed
+     */
+    @JavascriptInterface
+    fun getPendingNavigationPostId(): String {
+        return FcmService.getPendingNavigationPostId(context) ?: ""
+    }
+
+    /**
+     * Get the navigation kind attached to the pending navigation.
+     *
+     * @return "feed", "post", "mention", "dm", or empty string if unknown
+     */
+    @JavascriptInterface
+    fun getPendingNavigationKind(): String {
+        return FcmService.getPendingNavigationKind(context) ?: ""
+    }
+
+    /**
+     * Clear the pending navigation returned above after TypeScript has
+     * processed it. Taps queued after it stay pending.
      * Call this after navigating to prevent re-navigation on next app open.
      */
     @JavascriptInterface
     fun clearPendingNavigation() {
         Log.d(TAG, "clearPendingNavigation called")
-        FcmService.clearPendingNavigation(context)
+        FcmService.removeOldestPendingNavigation(context)
+    }
+
+    /**
+     * Read and delete every pending navigation in one step.
+     *
+     * @return A JSON array of `{"feed_id", "post_id", "kind"}`, oldest first,
+     *   or empty string if none pending
+     */
+    @JavascriptInterface
+    fun takePendingNavigation(): String {
+        return FcmService.takePendingNavigation(context)
     }
 
     /**
@@ -56,6 +56,9 @@ class MainActivity : TauriActivity() {
         WindowCompat.setDecorFitsSystemWindows(window, true)
         super.onCreate(savedInstanceState)
 
+        // Block screenshots and the recents thumbnail before the first frame if enabled
+        ScreenSecurityHelper.applySaved(this)
+
         // Create notification channel (safe to call multiple times)
         NotificationHelper.createChannel(this)
 
@@ -90,9 +93,11 @@ class MainActivity : TauriActivity() {
             if (fromNotification) {
                 val feedId = it.getStringExtra(NotificationHelper.EXTRA_FEED_ID)
                 if (!feedId.isNullOrEmpty()) {
+                    val postId = it.getStringExtra(NotificationHelper.EXTRA_POST_ID)
+                    val kind = it.getStringExtra(NotificationHelper.EXTRA_KIND)
                     Log.d(TAG, "Notification tap with feedId: ${feedId.take(8)}...")
                     // Store for TypeScript to consume via Tauri command
-                    FcmService.setPendingNavigation(this, feedId)
+                    FcmService.setPendingNavigation(this, feedId, postId, kind)
                 } else {
                     Log.d(TAG, "Notification tap without feedId")
                 }
@@ -111,6 +116,21 @@ class MainActivity : TauriActivity() {
             // Only handle VIEW actions with data URI
             if (it.action == Intent.ACTION_VIEW && it.data != null) {
                 val uri: Uri = it.data!!
+
+                // hush://feed/<feedId>[/post/<postId>] is a feed navigation, not a route
+                if (uri.scheme == "hush" && uri.host == "feed") {
+                    val segments = uri.pathSegments
+                    val feedId = segments.getOrNull(0)
+                    if (!feedId.isNullOrEmpty()) {
+                        val postId = if (segments.getOrNull(1) == "post") segments.getOrNull(2) else null
+                        Log.d(TAG, "Feed deep link received: ${feedId.take(8)}...")
+                        FcmService.setPendingNavigation(this, feedId, postId, if (postId != null) "post" else "feed")
+                    } else {
+                        Log.w(TAG, "Malformed feed deep link dropped")
+                    }
+                    return
+                }
+
                 val path = when {
                     uri.scheme == "hushfeeds" && uri.host == "social" && uri.path?.startsWith("/post/") == true -> {
                         "/social${uri.path}"
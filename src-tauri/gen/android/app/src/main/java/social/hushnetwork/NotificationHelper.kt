@@ -5,10 +5,15 @@ import android.app.NotificationManager
 import android.app.PendingIntent
 import android.content.Context
 import android.content.Intent
+import android.media.AudioAttributes
+import android.net.Uri
 import android.os.Build
 import android.util.Log
 import androidx.core.app.NotificationCompat
 import androidx.core.app.NotificationManagerCompat
+import androidx.core.app.RemoteInput
+import org.json.JSONException
+import org.json.JSONObject
 
 /**
  * Notification Helper for HushNetwork
@@ -25,13 +30,73 @@ object NotificationHelper {
     private const val CHANNEL_NAME = "Messages"
     private const val CHANNEL_DESCRIPTION = "Message notifications from Hush Feeds"
 
+    // Sound selection, written by the Rust `set_notification_sound` command
+    private const val PREFS_NAME = "hush_notification_prefs"
+    private const val KEY_NOTIFICATION_SOUND = "notification_sound"
+    private const val KEY_MUTED_FEEDS = "muted_feeds"
+    private const val SILENT_CHANNEL_ID = "hush_messages_silent"
+
     // Intent extras for notification tap handling
     const val EXTRA_FEED_ID = "feed_id"
+    const val EXTRA_POST_ID = "post_id"
+    const val EXTRA_KIND = "kind"
     const val EXTRA_FROM_NOTIFICATION = "from_notification"
 
+    // RemoteInput key of the inline reply text, read by ReplyReceiver
+    const val KEY_REPLY_TEXT = "reply_text"
+
     // Notification color (Violet-400: #8B5CF6)
     private const val NOTIFICATION_COLOR = 0xFF8B5CF6.toInt()
 
+    /**
+     * Selected sound name: null for the system default, "" for silent,
+     * otherwise the name of a res/raw resource
+     */
+    private fun selectedSound(context: Context): String? {
+        return context.getSharedPreferences(PREFS_NAME, Context.MODE_PRIVATE)
+            .getString(KEY_NOTIFICATION_SOUND, null)
+    }
+
+    /**
+     * Check whether a feed is muted, as set by the Rust `mute_feed` command
+     * The list is a JSON object of feed ID -> expiry (Unix ms) or null for indefinite
+     */
+    fun isFeedMuted(context: Context, feedId: String): Boolean {
+        val json = context.getSharedPreferences(PREFS_NAME, Context.MODE_PRIVATE)
+            .getString(KEY_MUTED_FEEDS, null) ?: return false
+
+        return try {
+            val mutes = JSONObject(json)
+            if (!mutes.has(feedId)) return false
+            mutes.isNull(feedId) || mutes.getLong(feedId) > System.currentTimeMillis()
+        } catch (e: JSONException) {
+            Log.w(TAG, "Invalid muted feeds list, ignoring", e)
+            false
+        }
+    }
+
+    /**
+     * Raw resource URI for a custom sound, or null if it isn't bundled
+     */
+    private fun customSoundUri(context: Context, sound: String): Uri? {
+        val resId = context.resources.getIdentifier(sound, "raw", context.packageName)
+        if (resId == 0) {
+            Log.w(TAG, "Notification sound not bundled: $sound")
+            return null
+        }
+        return Uri.parse("android.resource://${context.packageName}/$resId")
+    }
+
+    /**
+     * Channel ID for the selected sound
+     * Channel sounds are fixed once created, so each sound gets its own channel
+     */
+    private fun channelIdFor(context: Context): String {
+        val sound = selectedSound(context) ?: return CHANNEL_ID
+        if (sound.isEmpty()) return SILENT_CHANNEL_ID
+        return if (customSoundUri(context, sound) != null) "${CHANNEL_ID}_$sound" else CHANNEL_ID
+    }
+
     /**
      * Create the notification channel for messages
      * Must be called before showing any notification (Android 8.0+)
@@ -39,8 +104,10 @@ object NotificationHelper {
      */
     fun createChannel(context: Context) {
         if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.O) {
+            val channelId = channelIdFor(context)
+            val sound = selectedSound(context)
             val channel = NotificationChannel(
-                CHANNEL_ID,
+                channelId,
                 CHANNEL_NAME,
                 NotificationManager.IMPORTANCE_HIGH
             ).apply {
@@ -49,11 +116,20 @@ object NotificationHelper {
                 lightColor = NOTIFICATION_COLOR
                 enableVibration(true)
                 setShowBadge(true)
+                when {
+                    channelId == SILENT_CHANNEL_ID -> setSound(null, null)
+                    channelId != CHANNEL_ID && sound != null -> {
+                        val attributes = AudioAttributes.Builder()
+                            .setUsage(AudioAttributes.USAGE_NOTIFICATION)
+                            .build()
+                        setSound(customSoundUri(context, sound), attributes)
+                    }
+                }
             }
 
             val notificationManager = context.getSystemService(Context.NOTIFICATION_SERVICE) as NotificationManager
             notificationManager.createNotificationChannel(channel)
-            Log.d(TAG, "Notification channel created: $CHANNEL_ID")
+            Log.d(TAG, "Notification channel created: $channelId")
         }
     }
 
@@ -64,12 +140,23 @@ object NotificationHelper {
      * @param title Notification title (e.g., sender name)
      * @param body Notification body (e.g., message preview)
      * @param feedId The feed ID to navigate to when tapped
+     * @param postId Optional post ID for reply/mention notifications
+     * @param kind Optional navigation kind ("feed", "post", "mention", "dm")
      */
-    fun showNotification(context: Context, title: String, body: String, feedId: String) {
+    fun showNotification(
+        context: Context,
+        title: String,
+        body: String,
+        feedId: String,
+        postId: String? = null,
+        kind: String? = null
+    ) {
         // Create intent to launch MainActivity with feed ID
         val intent = Intent(context, MainActivity::class.java).apply {
             flags = Intent.FLAG_ACTIVITY_NEW_TASK or Intent.FLAG_ACTIVITY_CLEAR_TOP
             putExtra(EXTRA_FEED_ID, feedId)
+            postId?.let { putExtra(EXTRA_POST_ID, it) }
+            kind?.let { putExtra(EXTRA_KIND, it) }
             putExtra(EXTRA_FROM_NOTIFICATION, true)
         }
 
@@ -87,28 +174,88 @@ object NotificationHelper {
             pendingIntentFlags
         )
 
-        // Build the notification
-        val notification = NotificationCompat.Builder(context, CHANNEL_ID)
+        // Inline reply, handed to ReplyReceiver; RemoteInput needs a mutable intent
+        val replyIntent = Intent(context, ReplyReceiver::class.java).apply {
+            action = ReplyReceiver.ACTION_REPLY
+            putExtra(EXTRA_FEED_ID, feedId)
+            postId?.let { putExtra(EXTRA_POST_ID, it) }
+        }
+        val replyIntentFlags = if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.S) {
+            PendingIntent.FLAG_UPDATE_CURRENT or PendingIntent.FLAG_MUTABLE
+        } else {
+            PendingIntent.FLAG_UPDATE_CURRENT
+        }
+        val replyPendingIntent = PendingIntent.getBroadcast(
+            context,
+            feedId.hashCode(),
+            replyIntent,
+            replyIntentFlags
+        )
+        val remoteInput = RemoteInput.Builder(KEY_REPLY_TEXT)
+            .setLabel("Reply")
+            .build()
+        val replyAction = NotificationCompat.Action.Builder(
+            R.mipmap.ic_launcher,
+            "Reply",
+            replyPendingIntent
+        )
+            .addRemoteInput(remoteInput)
+            .setAllowGeneratedReplies(true)
+            .build()
+
+        // Build the notification on the channel for the selected sound
+        createChannel(context)
+        val channelId = channelIdFor(context)
+        val defaults = if (channelId == SILENT_CHANNEL_ID) {
+            NotificationCompat.DEFAULT_VIBRATE
+        } else {
+            NotificationCompat.DEFAULT_SOUND or NotificationCompat.DEFAULT_VIBRATE
+        }
+        val notification = NotificationCompat.Builder(context, channelId)
             .setSmallIcon(R.mipmap.ic_launcher) // App icon
             .setContentTitle(title)
             .setContentText(body)
             .setColor(NOTIFICATION_COLOR)
             .setPriority(NotificationCompat.PRIORITY_HIGH)
             .setCategory(NotificationCompat.CATEGORY_MESSAGE)
+            .setGroup("feed_$feedId") // Bundled per feed by the shade
             .setAutoCancel(true) // Dismiss on tap
             .setContentIntent(pendingIntent)
-            .setDefaults(NotificationCompat.DEFAULT_SOUND or NotificationCompat.DEFAULT_VIBRATE)
+            .addAction(replyAction)
+            .setDefaults(defaults)
+            .setSilent(channelId == SILENT_CHANNEL_ID)
             .build()
 
         // Use feedId hashCode as notification ID for replacement behavior
         val notificationId = feedId.hashCode()
 
         try {
-            NotificationManagerCompat.from(context).notify(notificationId, notification)
+            NotificationManagerCompat.from(context)
+                .notify(tagFor(feedId), notificationId, notification)
             Log.d(TAG, "Notification shown for feed: ${feedId.take(8)}...")
         } catch (e: SecurityException) {
             // Permission not granted - this is expected if user denied
             Log.w(TAG, "Cannot show notification - permission not granted", e)
         }
     }
+
+    /** Tag of the notification posted for [feedId] */
+    private fun tagFor(feedId: String) = "feed_$feedId"
+
+    /**
+     * Cancel the notification of [feedId], e.g. once it was read in the app.
+     * Called from Rust and from [ReplyReceiver].
+     */
+    @JvmStatic
+    fun cancelForFeed(context: Context, feedId: String) {
+        NotificationManagerCompat.from(context).cancel(tagFor(feedId), feedId.hashCode())
+    }
+
+    /**
+     * Cancel every notification the app posted, e.g. on logout. Called from Rust.
+     */
+    @JvmStatic
+    fun cancelAll(context: Context) {
+        NotificationManagerCompat.from(context).cancelAll()
+    }
 }
@@ -0,0 +1,99 @@
+package social.hushnetwork
+
+import android.content.Context
+import android.util.Log
+import org.json.JSONArray
+import org.json.JSONException
+import org.json.JSONObject
+import java.util.UUID
+
+/**
+ * Pending actions for HushNetwork
+ *
+ * Holds actions taken from the notification shade, such as inline replies,
+ * until the Rust side picks them up. Rust copies them into its own store and
+ * then calls [remove], so an entry is only dropped here once it is safe on
+ * the other side. Entries are written with `commit()` because the process
+ * may be killed right after a reply is submitted.
+ */
+object PendingActionStore {
+
+    private const val TAG = "PendingActionStore"
+    private const val PREFS_NAME = "hush_pending_actions"
+    private const val KEY_ACTIONS = "actions"
+
+    const val KIND_INLINE_REPLY = "inline_reply"
+
+    private val lock = Any()
+
+    private fun read(context: Context): JSONArray {
+        val json = context.getSharedPreferences(PREFS_NAME, Context.MODE_PRIVATE)
+            .getString(KEY_ACTIONS, null) ?: return JSONArray()
+        return try {
+            JSONArray(json)
+        } catch (e: JSONException) {
+            Log.w(TAG, "Invalid pending actions, dropping them", e)
+            JSONArray()
+        }
+    }
+
+    private fun write(context: Context, actions: JSONArray) {
+        context.getSharedPreferences(PREFS_NAME, Context.MODE_PRIVATE)
+            .edit()
+            .putString(KEY_ACTIONS, actions.toString())
+            .commit()
+    }
+
+    /**
+     * Queue an action and return its id.
+     */
+    fun add(context: Context, kind: String, feedId: String, postId: String?, text: String): String {
+        val id = UUID.randomUUID().toString()
+        val entry = JSONObject()
+            .put("id", id)
+            .put("kind", kind)
+            .put("feed_id", feedId)
+            .put("post_id", postId ?: JSONObject.NULL)
+            .put("text", text)
+            .put("timestamp", System.currentTimeMillis())
+        synchronized(lock) {
+            write(context, read(context).put(entry))
+        }
+        Log.d(TAG, "Pending $kind queued for feed: ${feedId.take(8)}...")
+        return id
+    }
+
+    /**
+     * Every queued action as a JSON array, oldest first. Called from Rust.
+     */
+    @JvmStatic
+    fun peekAll(context: Context): String {
+        synchronized(lock) {
+            return read(context).toString()
+        }
+    }
+
+    /**
+     * Drop the actions whose ids are in the JSON array [idsJson]. Called from
+     * Rust once it has stored them.
+     */
+    @JvmStatic
+    fun remove(context: Context, idsJson: String) {
+        val ids = try {
+            val array = JSONArray(idsJson)
+            (0 until array.length()).map { array.getString(it) }.toSet()
+        } catch (e: JSONException) {
+            Log.w(TAG, "Invalid id list", e)
+            return
+        }
+        synchronized(lock) {
+            val actions = read(context)
+            val kept = JSONArray()
+            for (i in 0 until actions.length()) {
+                val entry = actions.optJSONObject(i) ?: continue
+                if (entry.optString("id") !in ids) kept.put(entry)
+            }
+            write(context, kept)
+        }
+    }
+}
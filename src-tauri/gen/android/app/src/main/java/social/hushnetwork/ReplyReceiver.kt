@@ -0,0 +1,46 @@
+package social.hushnetwork
+
+import android.content.BroadcastReceiver
+import android.content.Context
+import android.content.Intent
+import android.util.Log
+import androidx.core.app.RemoteInput
+
+/**
+ * Receives inline replies typed into a notification
+ *
+ * The reply is queued in [PendingActionStore] for the Rust side, which hands
+ * it to the app on its next `get_pending_actions` call, even if the process
+ * was not running when the reply was sent. The notification is then removed
+ * so the shade doesn't keep showing its progress spinner.
+ */
+class ReplyReceiver : BroadcastReceiver() {
+
+    companion object {
+        private const val TAG = "ReplyReceiver"
+        const val ACTION_REPLY = "social.hushnetwork.action.REPLY"
+    }
+
+    override fun onReceive(context: Context, intent: Intent) {
+        if (intent.action != ACTION_REPLY) return
+        val feedId = intent.getStringExtra(NotificationHelper.EXTRA_FEED_ID) ?: return
+        val postId = intent.getStringExtra(NotificationHelper.EXTRA_POST_ID)
+        val text = RemoteInput.getResultsFromIntent(intent)
+            ?.getCharSequence(NotificationHelper.KEY_REPLY_TEXT)
+            ?.toString()
+            ?.trim()
+        if (text.isNullOrEmpty()) {
+            Log.d(TAG, "Ignoring empty reply")
+            return
+        }
+
+        PendingActionStore.add(
+            context,
+            PendingActionStore.KIND_INLINE_REPLY,
+            feedId,
+            postId,
+            text
+        )
+        NotificationHelper.cancelForFeed(context, feedId)
+    }
+}
@@ -0,0 +1,57 @@
+package social.hushnetwork
+
+import android.app.Activity
+import android.content.Context
+import android.util.Log
+import android.view.WindowManager
+
+/**
+ * Screen security for HushNetwork
+ *
+ * Sets or clears FLAG_SECURE on the activity window, which blanks the app in
+ * the recents screen and blocks screenshots and screen recording. The choice
+ * is kept in SharedPreferences so [applySaved] can set the flag in `onCreate`,
+ * before the first frame. Called from Rust over JNI.
+ */
+object ScreenSecurityHelper {
+
+    private const val TAG = "ScreenSecurityHelper"
+    private const val PREFS_NAME = "hush_screen_prefs"
+    private const val KEY_SECURE = "secure"
+
+    /**
+     * Persist [enabled] and apply it to the activity window right away.
+     */
+    @JvmStatic
+    fun setSecure(context: Context, enabled: Boolean) {
+        context.getSharedPreferences(PREFS_NAME, Context.MODE_PRIVATE)
+            .edit()
+            .putBoolean(KEY_SECURE, enabled)
+            .apply()
+
+        val activity = context as? Activity ?: return
+        activity.runOnUiThread { apply(activity, enabled) }
+    }
+
+    /**
+     * Apply the saved preference. Call from `onCreate`.
+     */
+    @JvmStatic
+    fun applySaved(activity: Activity) {
+        val enabled = activity.getSharedPreferences(PREFS_NAME, Context.MODE_PRIVATE)
+            .getBoolean(KEY_SECURE, false)
+        apply(activity, enabled)
+    }
+
+    private fun apply(activity: Activity, enabled: Boolean) {
+        if (enabled) {
+            activity.window.setFlags(
+                WindowManager.LayoutParams.FLAG_SECURE,
+                WindowManager.LayoutParams.FLAG_SECURE
+            )
+        } else {
+            activity.window.clearFlags(WindowManager.LayoutParams.FLAG_SECURE)
+        }
+        Log.d(TAG, "FLAG_SECURE ${if (enabled) "set" else "cleared"}")
+    }
+}
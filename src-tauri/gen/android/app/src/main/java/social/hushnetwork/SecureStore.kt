@@ -0,0 +1,54 @@
+package social.hushnetwork
+
+import android.content.Context
+import android.content.SharedPreferences
+import androidx.security.crypto.EncryptedSharedPreferences
+import androidx.security.crypto.MasterKey
+
+/**
+ * Secure Store for HushNetwork
+ *
+ * Secrets (e.g. the session token) in EncryptedSharedPreferences, encrypted with
+ * a key held in the Android Keystore. Called from Rust over JNI by the
+ * `secure_store` commands, so every entry point is a static method.
+ */
+object SecureStore {
+
+    private const val PREFS_NAME = "hush_secure_store"
+
+    @Volatile
+    private var prefs: SharedPreferences? = null
+
+    private fun prefs(context: Context): SharedPreferences {
+        prefs?.let { return it }
+        synchronized(this) {
+            prefs?.let { return it }
+            val appContext = context.applicationContext
+            val masterKey = MasterKey.Builder(appContext)
+                .setKeyScheme(MasterKey.KeyScheme.AES256_GCM)
+                .build()
+            return EncryptedSharedPreferences.create(
+                appContext,
+                PREFS_NAME,
+                masterKey,
+                EncryptedSharedPreferences.PrefKeyEncryptionScheme.AES256_SIV,
+                EncryptedSharedPreferences.PrefValueEncryptionScheme.AES256_GCM
+            ).also { prefs = it }
+        }
+    }
+
+    @JvmStatic
+    fun set(context: Context, key: String, value: String) {
+        prefs(context).edit().putString(key, value).apply()
+    }
+
+    @JvmStatic
+    fun get(context: Context, key: String): String? {
+        return prefs(context).getString(key, null)
+    }
+
+    @JvmStatic
+    fun delete(context: Context, key: String) {
+        prefs(context).edit().remove(key).apply()
+    }
+}
@@ -0,0 +1,62 @@
+package social.hushnetwork
+
+import android.content.ActivityNotFoundException
+import android.content.Context
+import android.content.Intent
+import android.util.Log
+import android.webkit.MimeTypeMap
+import androidx.core.content.FileProvider
+import java.io.File
+
+/**
+ * Share sheet for HushNetwork
+ *
+ * Opens the system chooser with an ACTION_SEND intent. Files are handed over
+ * as FileProvider content URIs, so they must live under a path listed in
+ * `res/xml/file_paths.xml`. Called from Rust over JNI by `share_content`.
+ */
+object ShareHelper {
+
+    private const val TAG = "ShareHelper"
+
+    /**
+     * @return true if the chooser was shown
+     */
+    @JvmStatic
+    fun share(context: Context, text: String?, url: String?, filePath: String?): Boolean {
+        val body = listOfNotNull(text, url).joinToString("\n").ifEmpty { null }
+        val intent = Intent(Intent.ACTION_SEND)
+
+        if (filePath != null) {
+            val file = File(filePath)
+            val uri = try {
+                FileProvider.getUriForFile(context, "${context.packageName}.fileprovider", file)
+            } catch (error: IllegalArgumentException) {
+                Log.e(TAG, "File is outside the FileProvider paths: $filePath", error)
+                return false
+            }
+            val mimeType = MimeTypeMap.getSingleton()
+                .getMimeTypeFromExtension(file.extension.lowercase())
+                ?: "application/octet-stream"
+            intent.type = mimeType
+            intent.putExtra(Intent.EXTRA_STREAM, uri)
+            intent.addFlags(Intent.FLAG_GRANT_READ_URI_PERMISSION)
+        } else {
+            intent.type = "text/plain"
+        }
+        if (body != null) {
+            intent.putExtra(Intent.EXTRA_TEXT, body)
+        }
+
+        val chooser = Intent.createChooser(intent, null).apply {
+            addFlags(Intent.FLAG_ACTIVITY_NEW_TASK)
+        }
+        return try {
+            context.startActivity(chooser)
+            true
+        } catch (error: ActivityNotFoundException) {
+            Log.e(TAG, "No activity to share with", error)
+            false
+        }
+    }
+}
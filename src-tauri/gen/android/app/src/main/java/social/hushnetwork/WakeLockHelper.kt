@@ -0,0 +1,57 @@
+package social.hushnetwork
+
+import android.content.Context
+import android.os.PowerManager
+import android.util.Log
+
+/**
+ * Wake locks for HushNetwork
+ *
+ * Holds the PowerManager wake locks taken by `acquire_wake_lock`, keyed by the
+ * id Rust assigned. Every lock is taken with a timeout, so a lock Rust never
+ * releases still lapses. Called from Rust over JNI.
+ */
+object WakeLockHelper {
+
+    private const val TAG = "WakeLockHelper"
+
+    private val locks = HashMap<Long, PowerManager.WakeLock>()
+
+    /**
+     * @param display keep the screen on, rather than just the CPU
+     * @return true if the lock was taken
+     */
+    @JvmStatic
+    @Synchronized
+    fun acquire(context: Context, id: Long, display: Boolean, reason: String, timeoutMs: Long): Boolean {
+        val power = context.getSystemService(Context.POWER_SERVICE) as? PowerManager ?: return false
+        @Suppress("DEPRECATION")
+        val level = if (display) {
+            // No Activity to set FLAG_KEEP_SCREEN_ON on from here
+            PowerManager.SCREEN_BRIGHT_WAKE_LOCK
+        } else {
+            PowerManager.PARTIAL_WAKE_LOCK
+        }
+        return try {
+            val lock = power.newWakeLock(level, "hush:wakelock-$id")
+            lock.setReferenceCounted(false)
+            lock.acquire(timeoutMs)
+            locks.put(id, lock)?.release()
+            Log.d(TAG, "Acquired wake lock $id: $reason")
+            true
+        } catch (error: SecurityException) {
+            Log.e(TAG, "Wake lock permission missing", error)
+            false
+        }
+    }
+
+    @JvmStatic
+    @Synchronized
+    fun release(id: Long) {
+        val lock = locks.remove(id) ?: return
+        if (lock.isHeld) {
+            lock.release()
+        }
+        Log.d(TAG, "Released wake lock $id")
+    }
+}
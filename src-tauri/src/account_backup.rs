@@ -0,0 +1,529 @@
+//! Passphrase-protected backups of the account keys in the secure store.
+//!
+//! `export_account_backup` reads every secret the frontend stored (see
+//! [`crate::secure_store`]; the app's own reserved entries are left out),
+//! seals them and writes the file where the save dialog says. The secrets
+//! are only ever in memory; the one file written, through a temp file and a
+//! rename, holds ciphertext. `import_account_backup` checks the file and the
+//! passphrase before writing anything back into the secure store, and puts
+//! the previous values back if a write fails part way. Both refuse to run
+//! while the app is locked.
+//!
+//! File format, version 1 (integers little-endian):
+//!
+//! | bytes | contents |
+//! |-------|----------|
+//! | 8     | [`MAGIC`] |
+//! | 1     | format version |
+//! | 12    | Argon2id memory (KiB), iterations and lanes, `u32` each |
+//! | 16    | salt |
+//! | 32    | passphrase check |
+//! | 12    | nonce |
+//! | …     | AES-256-GCM ciphertext of the JSON payload, header as associated data |
+//! | 32    | SHA-256 of everything before it |
+//!
+//! Argon2id stretches the passphrase into 64 bytes: the first half is the
+//! encryption key, the second half is stored as the passphrase check. The
+//! trailing checksum tells a damaged file (`backup_corrupted`) apart from a
+//! wrong passphrase (`wrong_passphrase`); the GCM tag then catches a file
+//! that was altered on purpose.
+
+use crate::error::{self, CommandError};
+use crate::fcm::now_unix_ms;
+use crate::lock::LockState;
+use crate::secure_store::{SecureStoreState, RESERVED_PREFIX};
+use crate::storage;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use argon2::{Algorithm, Argon2, Params, Version};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::DialogExt;
+use tokio::sync::oneshot;
+
+pub const MAGIC: &[u8; 8] = b"HUSHBAK\0";
+/// Newest format version this build writes and reads
+pub const FORMAT_VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const CHECK_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+const CHECKSUM_LEN: usize = 32;
+const HEADER_LEN: usize = MAGIC.len() + 1 + 12 + SALT_LEN + CHECK_LEN + NONCE_LEN;
+const MIN_PASSPHRASE_CHARS: usize = 8;
+const DEFAULT_FILE_NAME: &str = "hush-account.hushbackup";
+const FILE_EXTENSION: &str = "hushbackup";
+
+/// Argon2id cost, as stored in the file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct KdfParams {
+    memory_kib: u32,
+    iterations: u32,
+    lanes: u32,
+}
+
+/// Cost of new backups: 64 MiB, 3 passes
+const EXPORT_PARAMS: KdfParams = KdfParams {
+    memory_kib: 64 * 1024,
+    iterations: 3,
+    lanes: 1,
+};
+
+/// Highest cost accepted on import, so a crafted file can't exhaust memory
+const MAX_PARAMS: KdfParams = KdfParams {
+    memory_kib: 256 * 1024,
+    iterations: 16,
+    lanes: 8,
+};
+
+/// What a backup holds once opened
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BackupPayload {
+    /// Unix timestamp (ms) of the export
+    created_at: u64,
+    secrets: BTreeMap<String, String>,
+}
+
+fn corrupted(message: &str) -> CommandError {
+    CommandError::new(error::BACKUP_CORRUPTED, message)
+}
+
+/// Stretch `passphrase` into the encryption key and the passphrase check.
+fn derive(
+    passphrase: &str,
+    salt: &[u8],
+    params: KdfParams,
+) -> Result<([u8; 32], [u8; CHECK_LEN]), CommandError> {
+    let argon_params = Params::new(
+        params.memory_kib,
+        params.iterations,
+        params.lanes,
+        Some(32 + CHECK_LEN),
+    )
+    .map_err(|_| corrupted("The backup's key derivation settings are invalid"))?;
+    let mut output = [0u8; 32 + CHECK_LEN];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, argon_params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut output)
+        .map_err(|e| CommandError::new(error::INTERNAL, e.to_string()))?;
+    let mut key = [0u8; 32];
+    let mut check = [0u8; CHECK_LEN];
+    key.copy_from_slice(&output[..32]);
+    check.copy_from_slice(&output[32..]);
+    Ok((key, check))
+}
+
+/// Compare without stopping at the first difference.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Encrypt `payload` into a version 1 backup file.
+fn seal(
+    payload: &BackupPayload,
+    passphrase: &str,
+    params: KdfParams,
+) -> Result<Vec<u8>, CommandError> {
+    let salt = *uuid::Uuid::new_v4().as_bytes();
+    let (key, check) = derive(passphrase, &salt, params)?;
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+
+    let mut file = Vec::with_capacity(HEADER_LEN);
+    file.extend_from_slice(MAGIC);
+    file.push(FORMAT_VERSION);
+    for value in [params.memory_kib, params.iterations, params.lanes] {
+        file.extend_from_slice(&value.to_le_bytes());
+    }
+    file.extend_from_slice(&salt);
+    file.extend_from_slice(&check);
+    file.extend_from_slice(&nonce);
+
+    let json = serde_json::to_vec(payload).map_err(|e| e.to_string())?;
+    let ciphertext = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: &json,
+                aad: &file,
+            },
+        )
+        .map_err(|_| CommandError::new(error::INTERNAL, "Encrypting the backup failed"))?;
+    file.extend_from_slice(&ciphertext);
+    let checksum = Sha256::digest(&file);
+    file.extend_from_slice(&checksum);
+    Ok(file)
+}
+
+/// Check and decrypt a backup file.
+fn open(file: &[u8], passphrase: &str) -> Result<BackupPayload, CommandError> {
+    if !file.starts_with(MAGIC) {
+        return Err(corrupted("Not a Hush account backup"));
+    }
+    let version = file.get(MAGIC.len()).copied().unwrap_or(0);
+    if version > FORMAT_VERSION {
+        return Err(CommandError::new(
+            error::NOT_SUPPORTED,
+            format!(
+                "The backup was made by a newer version of Hush (format {})",
+                version
+            ),
+        ));
+    }
+    if version == 0 || file.len() < HEADER_LEN + TAG_LEN + CHECKSUM_LEN {
+        return Err(corrupted("The backup is damaged or incomplete"));
+    }
+    let (body, checksum) = file.split_at(file.len() - CHECKSUM_LEN);
+    if Sha256::digest(body).as_slice() != checksum {
+        return Err(corrupted("The backup is damaged or incomplete"));
+    }
+
+    let (header, ciphertext) = body.split_at(HEADER_LEN);
+    let number = |at: usize| {
+        let offset = MAGIC.len() + 1 + at * 4;
+        u32::from_le_bytes(header[offset..offset + 4].try_into().unwrap_or_default())
+    };
+    let params = KdfParams {
+        memory_kib: number(0),
+        iterations: number(1),
+        lanes: number(2),
+    };
+    if params.memory_kib > MAX_PARAMS.memory_kib
+        || params.iterations > MAX_PARAMS.iterations
+        || params.lanes > MAX_PARAMS.lanes
+    {
+        return Err(corrupted(
+            "The backup's key derivation settings are invalid",
+        ));
+    }
+    let salt_at = MAGIC.len() + 1 + 12;
+    let salt = &header[salt_at..salt_at + SALT_LEN];
+    let stored_check = &header[salt_at + SALT_LEN..salt_at + SALT_LEN + CHECK_LEN];
+    let nonce = &header[HEADER_LEN - NONCE_LEN..];
+
+    let (key, check) = derive(passphrase, salt, params)?;
+    if !constant_time_eq(&check, stored_check) {
+        return Err(CommandError::new(
+            error::WRONG_PASSPHRASE,
+            "The passphrase doesn't match this backup",
+        ));
+    }
+    let json = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: header,
+            },
+        )
+        .map_err(|_| corrupted("The backup has been altered"))?;
+    serde_json::from_slice(&json).map_err(|_| corrupted("The backup's contents are unreadable"))
+}
+
+/// The secrets to back up: everything in the secure store's key index.
+fn collect_secrets(store: &SecureStoreState) -> Result<BTreeMap<String, String>, CommandError> {
+    let mut secrets = BTreeMap::new();
+    for key in store.keys() {
+        if key.starts_with(RESERVED_PREFIX) {
+            continue;
+        }
+        // The index can outlive an entry deleted outside the app
+        if let Some(value) = store.get(&key)? {
+            secrets.insert(key, value);
+        }
+    }
+    Ok(secrets)
+}
+
+/// What a key held before the restore, to put back if the restore fails
+type Previous = Vec<(String, Option<String>)>;
+
+/// Write `secrets` into the store, skipping reserved keys. If a write fails,
+/// the keys written so far get their previous values back.
+fn restore(
+    store: &SecureStoreState,
+    app: Option<&AppHandle>,
+    secrets: &BTreeMap<String, String>,
+) -> Result<usize, CommandError> {
+    let secrets: Vec<(&String, &String)> = secrets
+        .iter()
+        .filter(|(key, _)| {
+            let reserved = key.starts_with(RESERVED_PREFIX);
+            if reserved {
+                log::warn!("Skipping reserved key in account backup");
+            }
+            !reserved
+        })
+        .collect();
+    // Read everything first, so a failing read leaves the store untouched
+    let mut previous: Previous = Vec::with_capacity(secrets.len());
+    let mut old_values = Vec::with_capacity(secrets.len());
+    for (key, _) in &secrets {
+        old_values.push(store.get(key)?);
+    }
+    for ((key, value), old) in secrets.iter().zip(old_values) {
+        if let Err(e) = store.set(key, value) {
+            roll_back(store, app, previous);
+            return Err(e.into());
+        }
+        store.track_key(app, key, true);
+        previous.push((key.to_string(), old));
+    }
+    Ok(secrets.len())
+}
+
+/// Put back what the keys in `previous` held before a failed restore.
+fn roll_back(store: &SecureStoreState, app: Option<&AppHandle>, previous: Previous) {
+    log::warn!(
+        "Account backup restore failed, rolling back {} keys",
+        previous.len()
+    );
+    for (key, old) in previous {
+        let result = match &old {
+            Some(value) => store.set(&key, value),
+            None => store.delete(&key),
+        };
+        match result {
+            Ok(()) => store.track_key(app, &key, old.is_some()),
+            Err(e) => log::error!("Failed to roll back a restored key: {}", e),
+        }
+    }
+}
+
+/// Ask where to save the backup, starting from `dest_path`. `None` if the
+/// dialog was dismissed.
+async fn pick_destination(app: &AppHandle, dest_path: &str) -> Result<Option<PathBuf>, String> {
+    let suggested = Path::new(dest_path);
+    let name = suggested
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(DEFAULT_FILE_NAME);
+    let mut dialog = app
+        .dialog()
+        .file()
+        .add_filter("Hush account backup", &[FILE_EXTENSION])
+        .set_file_name(name);
+    if let Some(dir) = suggested.parent().filter(|dir| dir.is_dir()) {
+        dialog = dialog.set_directory(dir);
+    }
+    let (sender, receiver) = oneshot::channel();
+    dialog.save_file(move |path| {
+        let _ = sender.send(path);
+    });
+    match receiver.await.ok().flatten() {
+        Some(path) => path.into_path().map(Some).map_err(|e| e.to_string()),
+        None => Ok(None),
+    }
+}
+
+/// Save the account keys to a backup file encrypted with `passphrase`.
+///
+/// The save dialog opens at `dest_path` (a directory and file name, or just
+/// a file name). Resolves to the path written, or None if the dialog was
+/// dismissed. Fails with `app_locked` while the app is locked and
+/// `not_found` when there is nothing to back up.
+#[tauri::command]
+pub async fn export_account_backup(
+    app: AppHandle,
+    passphrase: String,
+    dest_path: String,
+) -> Result<Option<String>, CommandError> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_CHARS {
+        return Err(CommandError::invalid_argument(format!(
+            "The backup passphrase needs at least {} characters",
+            MIN_PASSPHRASE_CHARS
+        )));
+    }
+    app.state::<LockState>().ensure_unlocked()?;
+    let Some(dest) = pick_destination(&app, &dest_path).await? else {
+        return Ok(None);
+    };
+
+    let secrets = collect_secrets(&app.state::<SecureStoreState>())?;
+    if secrets.is_empty() {
+        return Err(CommandError::new(
+            error::NOT_FOUND,
+            "There are no account keys to back up",
+        ));
+    }
+    let count = secrets.len();
+    let payload = BackupPayload {
+        created_at: now_unix_ms(),
+        secrets,
+    };
+    let file =
+        tauri::async_runtime::spawn_blocking(move || seal(&payload, &passphrase, EXPORT_PARAMS))
+            .await
+            .map_err(|e| e.to_string())??;
+    storage::write_atomic(&dest, &file)?;
+    log::info!("Exported {} account keys", count);
+    Ok(Some(dest.to_string_lossy().into_owned()))
+}
+
+/// Restore the account keys from a backup made by `export_account_backup`.
+/// Keys already in the secure store are overwritten. Resolves to how many
+/// keys were restored.
+///
+/// Fails with `app_locked` while the app is locked, `wrong_passphrase` when
+/// the passphrase doesn't match, `backup_corrupted` when the file is damaged
+/// or not a backup, and `not_supported` when a newer version of the app made
+/// it. Nothing is restored unless the whole file checks out, and a restore
+/// that fails part way is rolled back.
+#[tauri::command]
+pub async fn import_account_backup(
+    app: AppHandle,
+    path: String,
+    passphrase: String,
+) -> Result<usize, CommandError> {
+    app.state::<LockState>().ensure_unlocked()?;
+    let file = tokio::fs::read(&path).await?;
+    let payload = tauri::async_runtime::spawn_blocking(move || open(&file, &passphrase))
+        .await
+        .map_err(|e| e.to_string())??;
+
+    // The passphrase check can take a while; the lock may have come on
+    app.state::<LockState>().ensure_unlocked()?;
+    let restored = restore(
+        &app.state::<SecureStoreState>(),
+        Some(&app),
+        &payload.secrets,
+    )?;
+    log::info!("Restored {} account keys", restored);
+    Ok(restored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secure_store::BackendKind;
+
+    /// Cheap settings so the tests don't spend seconds in Argon2
+    const TEST_PARAMS: KdfParams = KdfParams {
+        memory_kib: 8,
+        iterations: 1,
+        lanes: 1,
+    };
+
+    fn payload() -> BackupPayload {
+        BackupPayload {
+            created_at: 1_700_000_000_000,
+            secrets: BTreeMap::from([
+                ("identity_key".to_string(), "private key bytes".to_string()),
+                ("auth_token".to_string(), "token".to_string()),
+            ]),
+        }
+    }
+
+    #[test]
+    fn backup_round_trips_without_plaintext() {
+        let file = seal(&payload(), "correct horse", TEST_PARAMS).unwrap();
+        assert!(file.starts_with(MAGIC));
+        assert_eq!(file[MAGIC.len()], FORMAT_VERSION);
+        assert!(!file
+            .windows("private key".len())
+            .any(|window| window == b"private key"));
+        assert_eq!(open(&file, "correct horse").unwrap(), payload());
+    }
+
+    #[test]
+    fn wrong_passphrase_has_its_own_code() {
+        let file = seal(&payload(), "correct horse", TEST_PARAMS).unwrap();
+        let error = open(&file, "battery staple").unwrap_err();
+        assert_eq!(error.code, error::WRONG_PASSPHRASE);
+    }
+
+    #[test]
+    fn damaged_files_are_reported_as_corrupted() {
+        let file = seal(&payload(), "correct horse", TEST_PARAMS).unwrap();
+
+        let mut flipped = file.clone();
+        flipped[HEADER_LEN + 2] ^= 1;
+        assert_eq!(
+            open(&flipped, "correct horse").unwrap_err().code,
+            error::BACKUP_CORRUPTED
+        );
+        assert_eq!(
+            open(&file[..file.len() - 10], "correct horse")
+                .unwrap_err()
+                .code,
+            error::BACKUP_CORRUPTED
+        );
+        assert_eq!(
+            open(b"{\"not\":\"a backup\"}", "x").unwrap_err().code,
+            error::BACKUP_CORRUPTED
+        );
+
+        // Altered with a matching checksum: the GCM tag still catches it
+        let mut forged = flipped[..flipped.len() - CHECKSUM_LEN].to_vec();
+        let checksum = Sha256::digest(&forged);
+        forged.extend_from_slice(&checksum);
+        assert_eq!(
+            open(&forged, "correct horse").unwrap_err().code,
+            error::BACKUP_CORRUPTED
+        );
+    }
+
+    #[test]
+    fn newer_versions_and_costly_settings_are_refused() {
+        let mut file = seal(&payload(), "correct horse", TEST_PARAMS).unwrap();
+        file[MAGIC.len()] = FORMAT_VERSION + 1;
+        assert_eq!(
+            open(&file, "correct horse").unwrap_err().code,
+            error::NOT_SUPPORTED
+        );
+
+        let costly = KdfParams {
+            memory_kib: MAX_PARAMS.memory_kib + 1,
+            ..TEST_PARAMS
+        };
+        let mut file = seal(&payload(), "correct horse", TEST_PARAMS).unwrap();
+        file[MAGIC.len() + 1..MAGIC.len() + 5].copy_from_slice(&costly.memory_kib.to_le_bytes());
+        let body_len = file.len() - CHECKSUM_LEN;
+        let checksum = Sha256::digest(&file[..body_len]);
+        file[body_len..].copy_from_slice(&checksum);
+        assert_eq!(
+            open(&file, "correct horse").unwrap_err().code,
+            error::BACKUP_CORRUPTED
+        );
+    }
+
+    #[test]
+    fn restore_overwrites_and_skips_reserved_keys() {
+        let store = SecureStoreState::with_backend(BackendKind::Memory, Default::default());
+        store.set("identity_key", "old").unwrap();
+        let mut secrets = payload().secrets;
+        secrets.insert(format!("{}app_lock__", RESERVED_PREFIX), "hash".to_string());
+
+        assert_eq!(restore(&store, None, &secrets).unwrap(), 2);
+        assert_eq!(
+            store.get("identity_key").unwrap().as_deref(),
+            Some("private key bytes")
+        );
+        assert!(store.keys().contains(&"auth_token".to_string()));
+        assert!(store
+            .keys()
+            .iter()
+            .all(|key| !key.starts_with(RESERVED_PREFIX)));
+    }
+
+    #[test]
+    fn roll_back_restores_previous_values() {
+        let store = SecureStoreState::with_backend(BackendKind::Memory, Default::default());
+        store.set("identity_key", "old").unwrap();
+        restore(&store, None, &payload().secrets).unwrap();
+
+        roll_back(
+            &store,
+            None,
+            vec![
+                ("identity_key".to_string(), Some("old".to_string())),
+                ("auth_token".to_string(), None),
+            ],
+        );
+        assert_eq!(store.get("identity_key").unwrap().as_deref(), Some("old"));
+        assert_eq!(store.get("auth_token").unwrap(), None);
+        assert!(!store.keys().contains(&"auth_token".to_string()));
+    }
+}
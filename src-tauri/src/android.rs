@@ -0,0 +1,617 @@
+//! Android JNI helpers.
+//!
+//! Thin wrappers around the JVM handed to us by `ndk-context`, so commands can
+//! read platform facts directly instead of relying on the WebView bridge.
+
+use jni::objects::{JClass, JObject, JString, JValue};
+use jni::{JNIEnv, JavaVM};
+
+/// SharedPreferences file shared with `NotificationHelper`
+const NOTIFICATION_PREFS: &str = "hush_notification_prefs";
+const KEY_NOTIFICATION_SOUND: &str = "notification_sound";
+const KEY_MUTED_FEEDS: &str = "muted_feeds";
+
+/// SharedPreferences file owned by `FcmService`
+const FCM_PREFS: &str = "hush_fcm_prefs";
+const KEY_FCM_TOKEN: &str = "fcm_token";
+/// How long to wait for Firebase to delete the token
+const DELETE_TOKEN_TIMEOUT_SECS: i64 = 10;
+
+/// Run `f` with a JNI environment attached to the current thread.
+///
+/// Any pending Java exception is cleared on failure so the next call starts clean.
+fn with_env<T>(f: impl FnOnce(&mut JNIEnv) -> jni::errors::Result<T>) -> Result<T, String> {
+    let ctx = ndk_context::android_context();
+    // SAFETY: ndk-context hands out the process-wide JavaVM pointer registered by Tauri.
+    let vm = unsafe { JavaVM::from_raw(ctx.vm().cast()) }.map_err(|e| e.to_string())?;
+    let mut env = vm.attach_current_thread().map_err(|e| e.to_string())?;
+
+    let result = f(&mut env);
+    if result.is_err() && env.exception_check().unwrap_or(false) {
+        let _ = env.exception_clear();
+    }
+    result.map_err(|e| e.to_string())
+}
+
+/// Read a static `String` field from a framework class (e.g. `android/os/Build`).
+fn static_string_field(class: &str, field: &str) -> Result<String, String> {
+    with_env(|env| {
+        let value = env
+            .get_static_field(class, field, "Ljava/lang/String;")?
+            .l()?;
+        let value = JString::from(value);
+        let value: String = env.get_string(&value)?.into();
+        Ok(value)
+    })
+}
+
+/// The activity context registered with ndk-context.
+fn app_context<'local>() -> JObject<'local> {
+    // SAFETY: ndk-context hands out a global reference to the activity that lives
+    // for the whole process; JObject does not delete it on drop.
+    unsafe { JObject::from_raw(ndk_context::android_context().context().cast()) }
+}
+
+/// Load an app (non-framework) class such as a Firebase class.
+///
+/// `FindClass` on a natively attached thread only sees framework classes, so
+/// this goes through the activity's class loader instead.
+fn load_app_class<'local>(env: &mut JNIEnv<'local>, name: &str) -> jni::errors::Result<JClass<'local>> {
+    let loader = env
+        .call_method(
+            &app_context(),
+            "getClassLoader",
+            "()Ljava/lang/ClassLoader;",
+            &[],
+        )?
+        .l()?;
+    let name = env.new_string(name)?;
+    let class = env
+        .call_method(
+            &loader,
+            "loadClass",
+            "(Ljava/lang/String;)Ljava/lang/Class;",
+            &[JValue::from(&name)],
+        )?
+        .l()?;
+    Ok(JClass::from(class))
+}
+
+/// Write (or with `None`, remove) a string in the app's SharedPreferences.
+fn set_shared_preference(prefs_name: &str, key: &str, value: Option<&str>) -> Result<(), String> {
+    with_env(|env| {
+        let context = app_context();
+        let prefs_name = env.new_string(prefs_name)?;
+        let prefs = env
+            .call_method(
+                &context,
+                "getSharedPreferences",
+                "(Ljava/lang/String;I)Landroid/content/SharedPreferences;",
+                &[JValue::from(&prefs_name), JValue::Int(0)],
+            )?
+            .l()?;
+        let editor = env
+            .call_method(
+                &prefs,
+                "edit",
+                "()Landroid/content/SharedPreferences$Editor;",
+                &[],
+            )?
+            .l()?;
+
+        let key = env.new_string(key)?;
+        match value {
+            Some(value) => {
+                let value = env.new_string(value)?;
+                env.call_method(
+                    &editor,
+                    "putString",
+                    "(Ljava/lang/String;Ljava/lang/String;)Landroid/content/SharedPreferences$Editor;",
+                    &[JValue::from(&key), JValue::from(&value)],
+                )?;
+            }
+            None => {
+                env.call_method(
+                    &editor,
+                    "remove",
+                    "(Ljava/lang/String;)Landroid/content/SharedPreferences$Editor;",
+                    &[JValue::from(&key)],
+                )?;
+            }
+        }
+        env.call_method(&editor, "apply", "()V", &[])?;
+        Ok(())
+    })
+}
+
+/// Tell `NotificationHelper` which sound its channel should use.
+///
+/// The helper maps the stored name to a notification channel; an empty string
+/// selects the silent channel and a missing key the default one.
+pub fn set_notification_sound(sound: Option<&str>) -> Result<(), String> {
+    let value = match sound {
+        None => Some(""),
+        Some(crate::notifications::DEFAULT_SOUND) => None,
+        Some(name) => Some(name),
+    };
+    set_shared_preference(NOTIFICATION_PREFS, KEY_NOTIFICATION_SOUND, value)
+}
+
+/// Share the feed mute list with `FcmService`, stored as a JSON object of
+/// feed id → expiry (Unix ms, or null for indefinite).
+pub fn set_muted_feeds(feeds: &crate::notifications::MutedFeeds) -> Result<(), String> {
+    let json = serde_json::to_string(feeds).map_err(|e| e.to_string())?;
+    set_shared_preference(NOTIFICATION_PREFS, KEY_MUTED_FEEDS, Some(&json))
+}
+
+/// Delete this device's FCM token with Firebase and forget the stored copy.
+///
+/// Blocks until Firebase confirms (or [`DELETE_TOKEN_TIMEOUT_SECS`] pass), so
+/// call it off the main thread.
+pub fn delete_fcm_token() -> Result<(), String> {
+    with_env(|env| {
+        let messaging_class = load_app_class(env, "com.google.firebase.messaging.FirebaseMessaging")?;
+        let messaging = env
+            .call_static_method(
+                &messaging_class,
+                "getInstance",
+                "()Lcom/google/firebase/messaging/FirebaseMessaging;",
+                &[],
+            )?
+            .l()?;
+        let task = env
+            .call_method(
+                &messaging,
+                "deleteToken",
+                "()Lcom/google/android/gms/tasks/Task;",
+                &[],
+            )?
+            .l()?;
+
+        let tasks_class = load_app_class(env, "com.google.android.gms.tasks.Tasks")?;
+        let seconds = env
+            .get_static_field(
+                "java/util/concurrent/TimeUnit",
+                "SECONDS",
+                "Ljava/util/concurrent/TimeUnit;",
+            )?
+            .l()?;
+        env.call_static_method(
+            &tasks_class,
+            "await",
+            "(Lcom/google/android/gms/tasks/Task;JLjava/util/concurrent/TimeUnit;)Ljava/lang/Object;",
+            &[
+                JValue::from(&task),
+                JValue::Long(DELETE_TOKEN_TIMEOUT_SECS),
+                JValue::from(&seconds),
+            ],
+        )?;
+        Ok(())
+    })?;
+    set_shared_preference(FCM_PREFS, KEY_FCM_TOKEN, None)
+}
+
+/// Drop the pending notification navigations queued by `FcmService`, under
+/// the same lock a tap is queued under.
+pub fn clear_pending_navigation() -> Result<(), String> {
+    with_env(|env| {
+        let class = load_app_class(env, "social.hushnetwork.FcmService")?;
+        env.call_static_method(
+            &class,
+            "clearPendingNavigation",
+            "(Landroid/content/Context;)V",
+            &[JValue::from(&app_context())],
+        )?;
+        Ok(())
+    })
+}
+
+/// Read and delete the navigations queued by `FcmService`, as a JSON array.
+pub fn take_pending_navigation() -> Result<Option<String>, String> {
+    with_env(|env| {
+        let class = load_app_class(env, "social.hushnetwork.FcmService")?;
+        let value = env
+            .call_static_method(
+                &class,
+                "takePendingNavigation",
+                "(Landroid/content/Context;)Ljava/lang/String;",
+                &[JValue::from(&app_context())],
+            )?
+            .l()?;
+        let value = JString::from(value);
+        let value: String = env.get_string(&value)?.into();
+        Ok(Some(value).filter(|value| !value.is_empty()))
+    })
+}
+
+/// Whether Google Play Services is installed and usable, as reported by
+/// `GoogleApiAvailability.isGooglePlayServicesAvailable`.
+pub fn play_services_available() -> Result<bool, String> {
+    /// `ConnectionResult.SUCCESS`
+    const SUCCESS: i32 = 0;
+
+    with_env(|env| {
+        let class = load_app_class(env, "com.google.android.gms.common.GoogleApiAvailability")?;
+        let availability = env
+            .call_static_method(
+                &class,
+                "getInstance",
+                "()Lcom/google/android/gms/common/GoogleApiAvailability;",
+                &[],
+            )?
+            .l()?;
+        let status = env
+            .call_method(
+                &availability,
+                "isGooglePlayServicesAvailable",
+                "(Landroid/content/Context;)I",
+                &[JValue::from(&app_context())],
+            )?
+            .i()?;
+        Ok(status == SUCCESS)
+    })
+}
+
+/// Kotlin object wrapping EncryptedSharedPreferences
+const SECURE_STORE_CLASS: &str = "social.hushnetwork.SecureStore";
+
+/// Store a secret through `SecureStore.set`.
+pub fn secure_store_set(key: &str, value: &str) -> Result<(), String> {
+    with_env(|env| {
+        let class = load_app_class(env, SECURE_STORE_CLASS)?;
+        let key = env.new_string(key)?;
+        let value = env.new_string(value)?;
+        env.call_static_method(
+            &class,
+            "set",
+            "(Landroid/content/Context;Ljava/lang/String;Ljava/lang/String;)V",
+            &[
+                JValue::from(&app_context()),
+                JValue::from(&key),
+                JValue::from(&value),
+            ],
+        )?;
+        Ok(())
+    })
+}
+
+/// Read a secret through `SecureStore.get`.
+pub fn secure_store_get(key: &str) -> Result<Option<String>, String> {
+    with_env(|env| {
+        let class = load_app_class(env, SECURE_STORE_CLASS)?;
+        let key = env.new_string(key)?;
+        let value = env
+            .call_static_method(
+                &class,
+                "get",
+                "(Landroid/content/Context;Ljava/lang/String;)Ljava/lang/String;",
+                &[JValue::from(&app_context()), JValue::from(&key)],
+            )?
+            .l()?;
+        if value.is_null() {
+            return Ok(None);
+        }
+        let value = JString::from(value);
+        let value: String = env.get_string(&value)?.into();
+        Ok(Some(value))
+    })
+}
+
+/// Delete a secret through `SecureStore.delete`.
+pub fn secure_store_delete(key: &str) -> Result<(), String> {
+    with_env(|env| {
+        let class = load_app_class(env, SECURE_STORE_CLASS)?;
+        let key = env.new_string(key)?;
+        env.call_static_method(
+            &class,
+            "delete",
+            "(Landroid/content/Context;Ljava/lang/String;)V",
+            &[JValue::from(&app_context()), JValue::from(&key)],
+        )?;
+        Ok(())
+    })
+}
+
+/// Device name built from `Build.MANUFACTURER` and `Build.MODEL`.
+///
+/// Mirrors `MainActivity.getDeviceName()`: the manufacturer is omitted when the
+/// model already starts with it (e.g. "Google Pixel 8" rather than "Google Google Pixel 8").
+pub fn device_name() -> Result<String, String> {
+    let manufacturer = static_string_field("android/os/Build", "MANUFACTURER")?;
+    let model = static_string_field("android/os/Build", "MODEL")?;
+    Ok(format_device_name(&manufacturer, &model))
+}
+
+fn format_device_name(manufacturer: &str, model: &str) -> String {
+    let manufacturer = manufacturer.trim();
+    let model = model.trim();
+
+    if model
+        .to_lowercase()
+        .starts_with(&manufacturer.to_lowercase())
+    {
+        return model.to_string();
+    }
+
+    let mut chars = manufacturer.chars();
+    let manufacturer = match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+        None => return model.to_string(),
+    };
+    format!("{} {}", manufacturer, model)
+}
+
+/// Whether the system dark theme is on, from `Configuration.uiMode`.
+pub fn is_night_mode() -> Result<bool, String> {
+    const UI_MODE_NIGHT_MASK: i32 = 0x30;
+    const UI_MODE_NIGHT_YES: i32 = 0x20;
+
+    with_env(|env| {
+        let resources = env
+            .call_method(
+                &app_context(),
+                "getResources",
+                "()Landroid/content/res/Resources;",
+                &[],
+            )?
+            .l()?;
+        let configuration = env
+            .call_method(
+                &resources,
+                "getConfiguration",
+                "()Landroid/content/res/Configuration;",
+                &[],
+            )?
+            .l()?;
+        let ui_mode = env.get_field(&configuration, "uiMode", "I")?.i()?;
+        Ok(ui_mode & UI_MODE_NIGHT_MASK == UI_MODE_NIGHT_YES)
+    })
+}
+
+/// The Material You accent (`system_accent1_500`) as ARGB. Fails before Android 12.
+pub fn accent_color() -> Result<u32, String> {
+    with_env(|env| {
+        let id = env
+            .get_static_field("android/R$color", "system_accent1_500", "I")?
+            .i()?;
+        let color = env
+            .call_method(&app_context(), "getColor", "(I)I", &[JValue::Int(id)])?
+            .i()?;
+        Ok(color as u32)
+    })
+}
+
+/// Battery state from `BatteryManager` and `PowerManager`: whether it is
+/// charging, the charge percentage, and whether battery saver is on.
+pub fn power_status() -> Result<(bool, i32, bool), String> {
+    /// `BatteryManager.BATTERY_PROPERTY_CAPACITY`
+    const BATTERY_PROPERTY_CAPACITY: i32 = 4;
+
+    with_env(|env| {
+        let context = app_context();
+        let service = env.new_string("batterymanager")?;
+        let battery = env
+            .call_method(
+                &context,
+                "getSystemService",
+                "(Ljava/lang/String;)Ljava/lang/Object;",
+                &[JValue::from(&service)],
+            )?
+            .l()?;
+        let charging = env.call_method(&battery, "isCharging", "()Z", &[])?.z()?;
+        let capacity = env
+            .call_method(
+                &battery,
+                "getIntProperty",
+                "(I)I",
+                &[JValue::Int(BATTERY_PROPERTY_CAPACITY)],
+            )?
+            .i()?;
+
+        let service = env.new_string("power")?;
+        let power = env
+            .call_method(
+                &context,
+                "getSystemService",
+                "(Ljava/lang/String;)Ljava/lang/Object;",
+                &[JValue::from(&service)],
+            )?
+            .l()?;
+        let power_save = env.call_method(&power, "isPowerSaveMode", "()Z", &[])?.z()?;
+        Ok((charging, capacity, power_save))
+    })
+}
+
+/// A Java string, or `null` for `None`.
+fn string_or_null<'local>(
+    env: &mut JNIEnv<'local>,
+    value: Option<&str>,
+) -> jni::errors::Result<JObject<'local>> {
+    match value {
+        Some(value) => Ok(env.new_string(value)?.into()),
+        None => Ok(JObject::null()),
+    }
+}
+
+/// Open the system share sheet through `ShareHelper.share`. Returns whether
+/// the sheet was shown.
+pub fn share(
+    text: Option<&str>,
+    url: Option<&str>,
+    file_path: Option<&str>,
+) -> Result<bool, String> {
+    with_env(|env| {
+        let class = load_app_class(env, "social.hushnetwork.ShareHelper")?;
+        let text = string_or_null(env, text)?;
+        let url = string_or_null(env, url)?;
+        let file_path = string_or_null(env, file_path)?;
+        env.call_static_method(
+            &class,
+            "share",
+            "(Landroid/content/Context;Ljava/lang/String;Ljava/lang/String;Ljava/lang/String;)Z",
+            &[
+                JValue::from(&app_context()),
+                JValue::from(&text),
+                JValue::from(&url),
+                JValue::from(&file_path),
+            ],
+        )?
+        .z()
+    })
+}
+
+const WAKE_LOCK_CLASS: &str = "social.hushnetwork.WakeLockHelper";
+
+/// Take a `PowerManager` wake lock through `WakeLockHelper.acquire`; screen
+/// bright when `display`, partial otherwise. Returns whether it was taken.
+pub fn acquire_wake_lock(
+    id: u64,
+    display: bool,
+    reason: &str,
+    timeout_ms: i64,
+) -> Result<bool, String> {
+    with_env(|env| {
+        let class = load_app_class(env, WAKE_LOCK_CLASS)?;
+        let reason = env.new_string(reason)?;
+        env.call_static_method(
+            &class,
+            "acquire",
+            "(Landroid/content/Context;JZLjava/lang/String;J)Z",
+            &[
+                JValue::from(&app_context()),
+                JValue::Long(id as i64),
+                JValue::Bool(display.into()),
+                JValue::from(&reason),
+                JValue::Long(timeout_ms),
+            ],
+        )?
+        .z()
+    })
+}
+
+/// Release a wake lock taken by [`acquire_wake_lock`].
+pub fn release_wake_lock(id: u64) -> Result<(), String> {
+    with_env(|env| {
+        let class = load_app_class(env, WAKE_LOCK_CLASS)?;
+        env.call_static_method(&class, "release", "(J)V", &[JValue::Long(id as i64)])?;
+        Ok(())
+    })
+}
+
+/// Set or clear `FLAG_SECURE` through `ScreenSecurityHelper.setSecure`, which
+/// also remembers the choice for the next `onCreate`.
+pub fn set_screen_secure(enabled: bool) -> Result<(), String> {
+    with_env(|env| {
+        let class = load_app_class(env, "social.hushnetwork.ScreenSecurityHelper")?;
+        env.call_static_method(
+            &class,
+            "setSecure",
+            "(Landroid/content/Context;Z)V",
+            &[JValue::from(&app_context()), JValue::Bool(enabled.into())],
+        )?;
+        Ok(())
+    })
+}
+
+const BIOMETRIC_CLASS: &str = "social.hushnetwork.BiometricHelper";
+
+/// `BiometricHelper.availability`: 0 when a biometric is enrolled and usable.
+pub fn biometric_availability() -> Result<i32, String> {
+    with_env(|env| {
+        let class = load_app_class(env, BIOMETRIC_CLASS)?;
+        env.call_static_method(
+            &class,
+            "availability",
+            "(Landroid/content/Context;)I",
+            &[JValue::from(&app_context())],
+        )?
+        .i()
+    })
+}
+
+/// Show the biometric prompt through `BiometricHelper.authenticate` and block
+/// until it is answered. Must not run on the main thread.
+pub fn biometric_authenticate(title: &str, subtitle: &str) -> Result<i32, String> {
+    with_env(|env| {
+        let class = load_app_class(env, BIOMETRIC_CLASS)?;
+        let title = env.new_string(title)?;
+        let subtitle = env.new_string(subtitle)?;
+        env.call_static_method(
+            &class,
+            "authenticate",
+            "(Landroid/content/Context;Ljava/lang/String;Ljava/lang/String;)I",
+            &[
+                JValue::from(&app_context()),
+                JValue::from(&title),
+                JValue::from(&subtitle),
+            ],
+        )?
+        .i()
+    })
+}
+
+const NOTIFICATION_HELPER_CLASS: &str = "social.hushnetwork.NotificationHelper";
+
+/// Cancel the notification `NotificationHelper` posted for `feed_id`.
+pub fn cancel_feed_notifications(feed_id: &str) -> Result<(), String> {
+    with_env(|env| {
+        let class = load_app_class(env, NOTIFICATION_HELPER_CLASS)?;
+        let feed_id = env.new_string(feed_id)?;
+        env.call_static_method(
+            &class,
+            "cancelForFeed",
+            "(Landroid/content/Context;Ljava/lang/String;)V",
+            &[JValue::from(&app_context()), JValue::from(&feed_id)],
+        )?;
+        Ok(())
+    })
+}
+
+/// Cancel every notification the app posted.
+pub fn cancel_all_notifications() -> Result<(), String> {
+    with_env(|env| {
+        let class = load_app_class(env, NOTIFICATION_HELPER_CLASS)?;
+        env.call_static_method(
+            &class,
+            "cancelAll",
+            "(Landroid/content/Context;)V",
+            &[JValue::from(&app_context())],
+        )?;
+        Ok(())
+    })
+}
+
+const PENDING_ACTION_CLASS: &str = "social.hushnetwork.PendingActionStore";
+
+/// Every action queued by `PendingActionStore`, as a JSON array.
+pub fn pending_actions_peek() -> Result<String, String> {
+    with_env(|env| {
+        let class = load_app_class(env, PENDING_ACTION_CLASS)?;
+        let value = env
+            .call_static_method(
+                &class,
+                "peekAll",
+                "(Landroid/content/Context;)Ljava/lang/String;",
+                &[JValue::from(&app_context())],
+            )?
+            .l()?;
+        let value = JString::from(value);
+        let value: String = env.get_string(&value)?.into();
+        Ok(value)
+    })
+}
+
+/// Drop the queued actions whose ids are in `ids_json` (a JSON array).
+pub fn pending_actions_remove(ids_json: &str) -> Result<(), String> {
+    with_env(|env| {
+        let class = load_app_class(env, PENDING_ACTION_CLASS)?;
+        let ids = env.new_string(ids_json)?;
+        env.call_static_method(
+            &class,
+            "remove",
+            "(Landroid/content/Context;Ljava/lang/String;)V",
+            &[JValue::from(&app_context()), JValue::from(&ids)],
+        )?;
+        Ok(())
+    })
+}
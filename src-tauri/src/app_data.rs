@@ -0,0 +1,612 @@
+//! Removing the app's data from this machine, e.g. on "log out and remove
+//! everything".
+//!
+//! `clear_app_data` takes any of the scopes below, or `all`:
+//!
+//! - `cache`: the image cache, the SQLite offline cache with the copies of
+//!   unusable ones, notification history, pending notification actions and
+//!   generated QR codes
+//! - `drafts`: the drafts directory and the outbox of unsent posts
+//! - `logs`: the log files and crash reports
+//! - `settings`: `settings.json`, pending notification navigation and the
+//!   link allowlist
+//! - `secure`: every secure store entry, including the app lock, the proxy
+//!   password and the key the cache and drafts are encrypted with (which
+//!   leaves an encrypted cache or drafts unreadable)
+//!
+//! Scopes are cleared in that order whatever order they're given, so secrets
+//! go last and a failure part way leaves the user able to sign in again. It
+//! refuses to start while an upload or download is running, or while the app
+//! is locked, and emits `app-data-cleared` with the report when done.
+//!
+//! `get_storage_usage` walks the data, config, cache and log directories on a
+//! blocking thread and reports what each store takes next to its configured
+//! quota. A directory that can't be read is reported with its error instead
+//! of failing the call. Results are reused for [`USAGE_TTL`] so the settings
+//! screen can poll; clearing app data drops the cached result.
+
+use crate::attachments::AttachmentState;
+use crate::cache::{self, CacheState};
+use crate::downloads::DownloadsState;
+use crate::drafts::DraftsState;
+use crate::error::{self, CommandError};
+use crate::fcm::PendingNavigationState;
+use crate::file_drop::FileDropState;
+use crate::image_cache::{self, ImageCacheState};
+use crate::links::LinkAllowlistState;
+use crate::lock::{self, LockState};
+use crate::notification_history::NotificationHistoryState;
+use crate::outbox::OutboxState;
+use crate::pending_actions::PendingActionsState;
+use crate::secure_store::{SecureStoreError, SecureStoreState};
+use crate::settings::SettingsState;
+use crate::{crash, encrypted_store, fcm, logging, net, qr, storage};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+/// How long a storage usage report is reused
+const USAGE_TTL: Duration = Duration::from_secs(60);
+
+/// A group of stores `clear_app_data` can remove, in the order they're cleared
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DataScope {
+    Cache,
+    Drafts,
+    Logs,
+    Settings,
+    Secure,
+}
+
+const ALL_SCOPES: [DataScope; 5] = [
+    DataScope::Cache,
+    DataScope::Drafts,
+    DataScope::Logs,
+    DataScope::Settings,
+    DataScope::Secure,
+];
+
+/// Outcome of one scope
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScopeResult {
+    pub scope: DataScope,
+    pub ok: bool,
+    pub bytes_freed: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Result of `clear_app_data` and payload of `app-data-cleared`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClearReport {
+    pub scopes: Vec<ScopeResult>,
+    pub bytes_freed: u64,
+}
+
+/// Parse the requested scope names into a sorted, deduplicated list.
+fn parse_scopes(names: &[String]) -> Result<Vec<DataScope>, CommandError> {
+    let mut scopes = Vec::new();
+    for name in names {
+        if name == "all" {
+            scopes.extend(ALL_SCOPES);
+            continue;
+        }
+        let scope = serde_json::from_value(serde_json::Value::String(name.clone()))
+            .map_err(|_| CommandError::invalid_argument(format!("Unknown scope '{}'", name)))?;
+        scopes.push(scope);
+    }
+    if scopes.is_empty() {
+        return Err(CommandError::invalid_argument("No scopes given"));
+    }
+    scopes.sort();
+    scopes.dedup();
+    Ok(scopes)
+}
+
+/// Why clearing can't start now, if anything is transferring.
+fn busy_reason(app: &AppHandle) -> Option<&'static str> {
+    if app.state::<FileDropState>().is_uploading() {
+        return Some("an upload is in progress");
+    }
+    if app.state::<DownloadsState>().has_active() || app.state::<AttachmentState>().has_active() {
+        return Some("a download is in progress");
+    }
+    None
+}
+
+/// Secure store entries the app writes itself, outside the key index that
+/// [`SecureStoreState::clear`] goes by
+const APP_OWNED_KEYS: &[&str] = &[
+    lock::HASH_KEY,
+    lock::SECRET_KEY,
+    encrypted_store::KEY_NAME,
+    net::PROXY_PASSWORD_KEY,
+];
+
+fn delete_app_owned(store: &SecureStoreState) -> Result<(), SecureStoreError> {
+    APP_OWNED_KEYS.iter().try_for_each(|key| store.delete(key))
+}
+
+/// One thing `clear_app_data` removes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Store {
+    ImageCache,
+    OfflineCache,
+    CorruptCaches,
+    NotificationHistory,
+    PendingActions,
+    QrCodes,
+    Drafts,
+    Outbox,
+    Logs,
+    CrashReports,
+    Settings,
+    PendingNavigation,
+    LinkAllowlist,
+    Secrets,
+}
+
+/// What each scope removes, in order
+fn stores(scope: DataScope) -> &'static [Store] {
+    match scope {
+        DataScope::Cache => &[
+            Store::ImageCache,
+            Store::OfflineCache,
+            Store::CorruptCaches,
+            Store::NotificationHistory,
+            Store::PendingActions,
+            Store::QrCodes,
+        ],
+        DataScope::Drafts => &[Store::Drafts, Store::Outbox],
+        DataScope::Logs => &[Store::Logs, Store::CrashReports],
+        DataScope::Settings => &[
+            Store::Settings,
+            Store::PendingNavigation,
+            Store::LinkAllowlist,
+        ],
+        DataScope::Secure => &[Store::Secrets],
+    }
+}
+
+fn clear_store(app: &AppHandle, store: Store) -> Result<u64, CommandError> {
+    match store {
+        Store::ImageCache => Ok(image_cache::clear(app, &app.state::<ImageCacheState>())?),
+        Store::OfflineCache => {
+            let path = storage::data_file(app, cache::CACHE_FILE)?;
+            let before = storage::disk_usage(&path);
+            app.state::<CacheState>().clear()?;
+            Ok(before.saturating_sub(storage::disk_usage(&path)))
+        }
+        Store::CorruptCaches => Ok(cache::remove_corrupt_copies(app)?),
+        Store::NotificationHistory => Ok(app.state::<NotificationHistoryState>().clear()?),
+        Store::PendingActions => Ok(app.state::<PendingActionsState>().clear()?),
+        Store::QrCodes => Ok(qr::clear(app)?),
+        Store::Drafts => Ok(app.state::<DraftsState>().clear()?),
+        Store::Outbox => Ok(app.state::<OutboxState>().clear()?),
+        Store::Logs => Ok(logging::clear_logs(app)?),
+        Store::CrashReports => Ok(crash::clear(app)?),
+        Store::Settings => {
+            let path = storage::config_file(app, crate::settings::SETTINGS_FILE)?;
+            let bytes = storage::disk_usage(&path);
+            app.state::<SettingsState>().reset()?;
+            Ok(bytes)
+        }
+        Store::PendingNavigation => {
+            app.state::<PendingNavigationState>().clear(None);
+            Ok(storage::remove_all(&storage::data_file(
+                app,
+                fcm::PENDING_NAVIGATION_FILE,
+            )?)?)
+        }
+        Store::LinkAllowlist => Ok(app.state::<LinkAllowlistState>().clear(app)?),
+        Store::Secrets => {
+            let store = app.state::<SecureStoreState>();
+            store.clear(app)?;
+            lock::remove(app)?;
+            delete_app_owned(&store)?;
+            Ok(0)
+        }
+    }
+}
+
+/// Clear every store of `scope`, stopping at the first failure.
+fn clear_scope(app: &AppHandle, scope: DataScope) -> Result<u64, CommandError> {
+    stores(scope)
+        .iter()
+        .try_fold(0, |freed, store| Ok(freed + clear_store(app, *store)?))
+}
+
+/// Delete the app's data for each of `scopes` ("cache", "drafts", "logs",
+/// "settings", "secure" or "all"). Returns how each scope went.
+#[tauri::command]
+pub async fn clear_app_data(
+    app: AppHandle,
+    scopes: Vec<String>,
+) -> Result<ClearReport, CommandError> {
+    let scopes = parse_scopes(&scopes)?;
+    app.state::<LockState>().ensure_unlocked()?;
+    if let Some(reason) = busy_reason(&app) {
+        return Err(CommandError::new(
+            error::UNAVAILABLE,
+            format!("Can't clear app data while {}", reason),
+        ));
+    }
+
+    let worker = app.clone();
+    let results = tauri::async_runtime::spawn_blocking(move || {
+        scopes
+            .into_iter()
+            .map(|scope| match clear_scope(&worker, scope) {
+                Ok(bytes_freed) => ScopeResult {
+                    scope,
+                    ok: true,
+                    bytes_freed,
+                    error: None,
+                },
+                Err(e) => {
+                    log::warn!("Failed to clear {:?}: {}", scope, e);
+                    ScopeResult {
+                        scope,
+                        ok: false,
+                        bytes_freed: 0,
+                        error: Some(e.message),
+                    }
+                }
+            })
+            .collect::<Vec<_>>()
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let report = ClearReport {
+        bytes_freed: results.iter().map(|result| result.bytes_freed).sum(),
+        scopes: results,
+    };
+    log::info!("Cleared app data, {} bytes freed", report.bytes_freed);
+    app.state::<StorageUsageState>().invalidate();
+    let _ = app.emit("app-data-cleared", &report);
+    Ok(report)
+}
+
+// ============= Storage usage =============
+
+/// Size of one store in a [`StorageUsage`] report
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageEntry {
+    pub bytes: u64,
+    pub files: u64,
+    /// Why the store couldn't be (fully) measured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl UsageEntry {
+    fn add(&mut self, other: UsageEntry) {
+        self.bytes += other.bytes;
+        self.files += other.files;
+        if self.error.is_none() {
+            self.error = other.error;
+        }
+    }
+}
+
+/// Configured size caps, in bytes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageQuotas {
+    pub image_cache_max_bytes: u64,
+    pub offline_cache_max_bytes: u64,
+    pub logs_max_bytes: u64,
+}
+
+/// Result of `get_storage_usage`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageUsage {
+    pub image_cache: UsageEntry,
+    /// The SQLite offline cache, with its journal
+    pub offline_cache: UsageEntry,
+    pub logs: UsageEntry,
+    pub drafts: UsageEntry,
+    /// Finished and partial files of the download queue, wherever they are
+    pub downloads: UsageEntry,
+    /// Everything else in the app's own directories
+    pub other: UsageEntry,
+    pub total_bytes: u64,
+    pub quotas: StorageQuotas,
+    /// Unix timestamp (ms) of the measurement
+    pub measured_at: u64,
+}
+
+/// Managed state caching the last usage report
+#[derive(Debug, Default)]
+pub struct StorageUsageState {
+    cached: Mutex<Option<(Instant, StorageUsage)>>,
+}
+
+impl StorageUsageState {
+    fn fresh(&self) -> Option<StorageUsage> {
+        self.cached
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+            .filter(|(at, _)| at.elapsed() < USAGE_TTL)
+            .map(|(_, usage)| usage.clone())
+    }
+
+    fn store(&self, usage: StorageUsage) {
+        *self.cached.lock().unwrap_or_else(|e| e.into_inner()) = Some((Instant::now(), usage));
+    }
+
+    fn invalidate(&self) {
+        *self.cached.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+}
+
+/// Bytes and files under `path`. A missing path is empty; unreadable
+/// directories below it are skipped, with the first error kept.
+fn measure(path: &Path) -> UsageEntry {
+    let meta = match std::fs::symlink_metadata(path) {
+        Ok(meta) => meta,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return UsageEntry::default(),
+        Err(e) => {
+            return UsageEntry {
+                error: Some(format!("{}: {}", path.display(), e)),
+                ..UsageEntry::default()
+            }
+        }
+    };
+    if !meta.is_dir() {
+        return UsageEntry {
+            bytes: meta.len(),
+            files: 1,
+            error: None,
+        };
+    }
+    let mut entry = UsageEntry::default();
+    match std::fs::read_dir(path) {
+        Ok(children) => {
+            for child in children.flatten() {
+                entry.add(measure(&child.path()));
+            }
+        }
+        Err(e) => entry.error = Some(format!("{}: {}", path.display(), e)),
+    }
+    entry
+}
+
+/// `paths` with any path inside another one (or equal to an earlier one)
+/// dropped, so nothing is counted twice.
+fn outermost(mut paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    paths.sort();
+    paths.dedup();
+    let mut roots: Vec<PathBuf> = Vec::new();
+    for path in paths {
+        if !roots.iter().any(|root| path.starts_with(root)) {
+            roots.push(path);
+        }
+    }
+    roots
+}
+
+/// Measure `path` if it could be resolved.
+fn measure_at(path: &Result<PathBuf, String>) -> UsageEntry {
+    match path {
+        Ok(path) => measure(path),
+        Err(e) => UsageEntry {
+            error: Some(e.clone()),
+            ..UsageEntry::default()
+        },
+    }
+}
+
+fn measure_usage(app: &AppHandle) -> StorageUsage {
+    let image_dir = image_cache::cache_dir(app);
+    let log_dir = logging::log_dir(app);
+    let drafts_dir = storage::data_file(app, crate::drafts::DRAFTS_DIR);
+    let cache_file = storage::data_file(app, cache::CACHE_FILE);
+
+    let image_cache = measure_at(&image_dir);
+    let logs = measure_at(&log_dir);
+    let drafts = measure_at(&drafts_dir);
+    let mut offline_cache = measure_at(&cache_file);
+    let mut store_paths: Vec<PathBuf> = [&image_dir, &log_dir, &drafts_dir, &cache_file]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect();
+    if let Ok(file) = &cache_file {
+        for suffix in ["-journal", "-wal", "-shm"] {
+            let mut path = file.clone().into_os_string();
+            path.push(suffix);
+            let path = PathBuf::from(path);
+            offline_cache.add(measure(&path));
+            store_paths.push(path);
+        }
+    }
+
+    let mut downloads = UsageEntry::default();
+    for download in app.state::<DownloadsState>().list() {
+        let dest = PathBuf::from(&download.dest);
+        let part = crate::attachments::part_path(&dest);
+        downloads.add(measure(&part));
+        downloads.add(measure(&dest));
+        store_paths.extend([part, dest]);
+    }
+
+    // Whatever else is in the app's own directories
+    let resolver = app.path();
+    let roots = outermost(
+        [
+            resolver.app_data_dir(),
+            resolver.app_local_data_dir(),
+            resolver.app_config_dir(),
+            resolver.app_cache_dir(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect(),
+    );
+    let mut other = UsageEntry::default();
+    for root in &roots {
+        other.add(measure(root));
+    }
+    for path in outermost(store_paths) {
+        if roots.iter().any(|root| path.starts_with(root)) {
+            let counted = measure(&path);
+            other.bytes = other.bytes.saturating_sub(counted.bytes);
+            other.files = other.files.saturating_sub(counted.files);
+        }
+    }
+
+    let total_bytes = [
+        &image_cache,
+        &offline_cache,
+        &logs,
+        &drafts,
+        &downloads,
+        &other,
+    ]
+    .iter()
+    .map(|entry| entry.bytes)
+    .sum();
+    StorageUsage {
+        image_cache,
+        offline_cache,
+        logs,
+        drafts,
+        downloads,
+        other,
+        total_bytes,
+        quotas: StorageQuotas {
+            image_cache_max_bytes: image_cache::max_bytes(app),
+            offline_cache_max_bytes: cache::max_bytes(app),
+            logs_max_bytes: logging::max_log_bytes(),
+        },
+        measured_at: crate::fcm::now_unix_ms(),
+    }
+}
+
+/// What the app's stores take on disk, with their quotas. Reuses a report
+/// younger than a minute.
+#[tauri::command]
+pub async fn get_storage_usage(
+    app: AppHandle,
+    state: State<'_, StorageUsageState>,
+) -> Result<StorageUsage, CommandError> {
+    if let Some(usage) = state.fresh() {
+        return Ok(usage);
+    }
+    let worker = app.clone();
+    let usage = tauri::async_runtime::spawn_blocking(move || measure_usage(&worker))
+        .await
+        .map_err(|e| e.to_string())?;
+    state.store(usage.clone());
+    Ok(usage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn app_owned_secrets_are_deleted() {
+        use crate::secure_store::BackendKind;
+
+        let store = SecureStoreState::with_backend(BackendKind::Memory, Default::default());
+        let reserved = [
+            "__hush_app_lock__",
+            "__hush_app_lock_secret__",
+            "__hush_store_key",
+            "proxy-password",
+        ];
+        for key in reserved {
+            store.set(key, "secret").unwrap();
+        }
+        delete_app_owned(&store).unwrap();
+        for key in reserved {
+            assert_eq!(store.get(key).unwrap(), None, "{} left behind", key);
+        }
+    }
+
+    #[test]
+    fn scopes_are_cleared_with_secrets_last() {
+        assert_eq!(
+            parse_scopes(&names(&["secure", "cache", "settings", "cache"])).unwrap(),
+            [DataScope::Cache, DataScope::Settings, DataScope::Secure]
+        );
+        assert_eq!(parse_scopes(&names(&["logs", "all"])).unwrap(), ALL_SCOPES);
+    }
+
+    #[test]
+    fn all_removes_every_store_with_secrets_last() {
+        let removed: Vec<Store> = ALL_SCOPES
+            .iter()
+            .flat_map(|scope| stores(*scope).iter().copied())
+            .collect();
+        assert_eq!(
+            removed,
+            [
+                Store::ImageCache,
+                Store::OfflineCache,
+                Store::CorruptCaches,
+                Store::NotificationHistory,
+                Store::PendingActions,
+                Store::QrCodes,
+                Store::Drafts,
+                Store::Outbox,
+                Store::Logs,
+                Store::CrashReports,
+                Store::Settings,
+                Store::PendingNavigation,
+                Store::LinkAllowlist,
+                Store::Secrets,
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_or_missing_scopes_are_rejected() {
+        let rejected = parse_scopes(&names(&["everything"])).unwrap_err();
+        assert_eq!(rejected.code, error::INVALID_ARGUMENT);
+        assert!(parse_scopes(&[]).is_err());
+    }
+
+    #[test]
+    fn usage_counts_files_below_a_directory() {
+        let dir = std::env::temp_dir().join(format!("hush-usage-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("nested")).unwrap();
+        std::fs::write(dir.join("a"), [0u8; 10]).unwrap();
+        std::fs::write(dir.join("nested").join("b"), [0u8; 5]).unwrap();
+
+        let usage = measure(&dir);
+        assert_eq!((usage.bytes, usage.files), (15, 2));
+        assert_eq!(usage.error, None);
+        assert_eq!(measure(&dir.join("missing")), UsageEntry::default());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn nested_roots_are_counted_once() {
+        let roots = outermost(vec![
+            PathBuf::from("/data/app/logs"),
+            PathBuf::from("/config/app"),
+            PathBuf::from("/data/app"),
+            PathBuf::from("/data/app"),
+        ]);
+        assert_eq!(
+            roots,
+            [PathBuf::from("/config/app"), PathBuf::from("/data/app")]
+        );
+    }
+}
@@ -0,0 +1,73 @@
+//! Build and runtime versions for the About screen and bug reports.
+//!
+//! The git commit and build time are baked in by `build.rs`. Everything but
+//! the update channel is fixed for the life of the process, so it's gathered
+//! once.
+
+use crate::settings::SettingsState;
+use crate::updates::UpdateChannel;
+use serde::Serialize;
+use std::sync::OnceLock;
+use tauri::{AppHandle, Manager};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppInfo {
+    pub version: &'static str,
+    /// Short git hash, or `unknown` when built outside a checkout
+    pub commit: &'static str,
+    /// RFC 3339
+    pub build_date: Option<String>,
+    pub tauri_version: &'static str,
+    /// Unknown where the webview doesn't report it
+    pub webview_version: Option<String>,
+    pub channel: UpdateChannel,
+    pub debug: bool,
+}
+
+static APP_INFO: OnceLock<AppInfo> = OnceLock::new();
+
+fn build_date(timestamp: &str) -> Option<String> {
+    let seconds = timestamp.parse().ok()?;
+    chrono::DateTime::from_timestamp(seconds, 0).map(|date| date.to_rfc3339())
+}
+
+fn collect() -> AppInfo {
+    AppInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        commit: env!("HUSH_GIT_COMMIT"),
+        build_date: build_date(env!("HUSH_BUILD_TIMESTAMP")),
+        tauri_version: tauri::VERSION,
+        webview_version: tauri::webview_version()
+            .map_err(|e| log::debug!("Webview version unavailable: {}", e))
+            .ok(),
+        channel: UpdateChannel::default(),
+        debug: cfg!(debug_assertions),
+    }
+}
+
+#[tauri::command]
+pub fn get_app_info(app: AppHandle) -> AppInfo {
+    AppInfo {
+        channel: app
+            .state::<SettingsState>()
+            .get()
+            .update_channel
+            .unwrap_or_default(),
+        ..APP_INFO.get_or_init(collect).clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_date_is_rfc3339() {
+        assert_eq!(
+            build_date("1700000000").as_deref(),
+            Some("2023-11-14T22:13:20+00:00")
+        );
+        assert_eq!(build_date(""), None);
+    }
+}
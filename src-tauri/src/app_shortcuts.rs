@@ -0,0 +1,327 @@
+//! Configurable in-app keyboard shortcuts.
+//!
+//! Each action (e.g. `newPost`) has an accelerator; [`DEFAULT_SHORTCUTS`]
+//! applies unless the user chose another one, which is kept in the
+//! `appShortcuts` setting. An empty accelerator turns the action's shortcut
+//! off. Pressing a shortcut emits `shortcut-triggered` with the action name to
+//! the main window.
+//!
+//! Like the zoom accelerators these are global shortcuts registered only
+//! while the main window has focus, so they work on any keyboard layout
+//! without taking keys from other apps. `register_app_shortcuts` rejects
+//! accelerators that clash with each other, with the zoom keys or with the
+//! toggle shortcut, and never lets [`RESERVED`] ones be taken. Desktop only.
+
+use crate::error::CommandError;
+#[cfg(desktop)]
+use crate::settings;
+use crate::settings::SettingsState;
+#[cfg(desktop)]
+use serde::Serialize;
+use std::collections::BTreeMap;
+#[cfg(desktop)]
+use std::collections::HashMap;
+#[cfg(desktop)]
+use std::sync::Mutex;
+#[cfg(desktop)]
+use tauri::Emitter;
+use tauri::{AppHandle, Manager};
+#[cfg(desktop)]
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut};
+
+/// Accelerator of each built-in action
+pub const DEFAULT_SHORTCUTS: &[(&str, &str)] = &[
+    ("newPost", "CommandOrControl+N"),
+    ("nextFeed", "Alt+ArrowDown"),
+    ("previousFeed", "Alt+ArrowUp"),
+    ("search", "CommandOrControl+K"),
+    ("openSettings", "CommandOrControl+Comma"),
+];
+
+/// Accelerators no action may use
+#[cfg_attr(mobile, allow(dead_code))]
+pub const RESERVED: &[&str] = &["CommandOrControl+Q", "CommandOrControl+W"];
+
+const MAX_ACTION_LEN: usize = 64;
+
+/// Payload of the `shortcut-triggered` event
+#[cfg(desktop)]
+#[derive(Debug, Clone, Serialize)]
+struct ShortcutTriggered {
+    action: String,
+}
+
+/// Managed state mapping the registered shortcuts to their actions
+#[cfg(desktop)]
+#[derive(Debug, Default)]
+pub struct AppShortcutState {
+    registered: Mutex<HashMap<Shortcut, String>>,
+}
+
+#[cfg(desktop)]
+impl AppShortcutState {
+    fn registered(&self) -> std::sync::MutexGuard<'_, HashMap<Shortcut, String>> {
+        self.registered.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg_attr(mobile, allow(dead_code))]
+fn validate_action(action: &str) -> Result<(), CommandError> {
+    let valid = !action.is_empty()
+        && action.len() <= MAX_ACTION_LEN
+        && action
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.');
+    if valid {
+        Ok(())
+    } else {
+        Err(CommandError::invalid_argument(format!(
+            "Invalid shortcut action: {}",
+            action
+        )))
+    }
+}
+
+/// The defaults with `overrides` applied. Disabled actions map to "".
+fn effective(overrides: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+    let mut map: BTreeMap<String, String> = DEFAULT_SHORTCUTS
+        .iter()
+        .map(|(action, accelerator)| (action.to_string(), accelerator.to_string()))
+        .collect();
+    map.extend(overrides.clone());
+    map
+}
+
+fn overrides(app: &AppHandle) -> BTreeMap<String, String> {
+    app.try_state::<SettingsState>()
+        .and_then(|state| state.get().app_shortcuts)
+        .unwrap_or_default()
+}
+
+#[cfg(desktop)]
+fn parse(accelerator: &str) -> Result<Shortcut, CommandError> {
+    accelerator.parse::<Shortcut>().map_err(|e| {
+        CommandError::invalid_argument(format!("Invalid shortcut '{}': {}", accelerator, e))
+    })
+}
+
+/// Shortcuts owned by other parts of the app, by owner
+#[cfg(desktop)]
+fn taken(app: &AppHandle) -> Vec<(String, String)> {
+    let mut taken: Vec<(String, String)> = crate::zoom::accelerators()
+        .map(|accelerator| ("zoom".to_string(), accelerator.to_string()))
+        .collect();
+    if let Some(toggle) = app
+        .try_state::<crate::shortcut::ToggleShortcutState>()
+        .and_then(|state| state.accelerator())
+    {
+        taken.push(("toggle window".to_string(), toggle));
+    }
+    taken
+}
+
+/// Check `overrides` and return them trimmed, or an error naming every
+/// invalid, reserved or clashing accelerator. `taken` lists accelerators
+/// already used elsewhere, with their owner.
+#[cfg(desktop)]
+fn validate(
+    overrides: &BTreeMap<String, String>,
+    taken: &[(String, String)],
+) -> Result<BTreeMap<String, String>, CommandError> {
+    let mut trimmed = BTreeMap::new();
+    for (action, accelerator) in overrides {
+        validate_action(action)?;
+        let accelerator = accelerator.trim();
+        if !accelerator.is_empty() {
+            let shortcut = parse(accelerator)?;
+            if RESERVED
+                .iter()
+                .any(|reserved| parse(reserved).ok() == Some(shortcut))
+            {
+                return Err(CommandError::invalid_argument(format!(
+                    "{} is reserved and can't be used for {}",
+                    accelerator, action
+                )));
+            }
+        }
+        trimmed.insert(action.clone(), accelerator.to_string());
+    }
+
+    // Group every accelerator in use by its parsed shortcut, so spellings
+    // like Ctrl and CommandOrControl are seen as the same key
+    let mut users: Vec<(Shortcut, Vec<(String, String)>)> = Vec::new();
+    let in_use = effective(&trimmed)
+        .into_iter()
+        .filter(|(_, accelerator)| !accelerator.is_empty())
+        .chain(taken.iter().cloned());
+    for (owner, accelerator) in in_use {
+        let Ok(shortcut) = parse(&accelerator) else {
+            continue;
+        };
+        match users.iter_mut().find(|(used, _)| *used == shortcut) {
+            Some((_, owners)) => owners.push((owner, accelerator)),
+            None => users.push((shortcut, vec![(owner, accelerator)])),
+        }
+    }
+    let clashes: Vec<String> = users
+        .into_iter()
+        .filter(|(_, owners)| owners.len() > 1)
+        .map(|(_, owners)| {
+            let owners: Vec<String> = owners
+                .iter()
+                .map(|(owner, accelerator)| format!("{} ({})", owner, accelerator))
+                .collect();
+            owners.join(" and ")
+        })
+        .collect();
+    if !clashes.is_empty() {
+        return Err(CommandError::invalid_argument(format!(
+            "Conflicting shortcuts: {}",
+            clashes.join("; ")
+        )));
+    }
+    Ok(trimmed)
+}
+
+/// Register the effective shortcuts; called when the main window gains focus.
+#[cfg(desktop)]
+pub fn register_accelerators(app: &AppHandle) {
+    let Some(state) = app.try_state::<AppShortcutState>() else {
+        return;
+    };
+    let mut registered = state.registered();
+    for (action, accelerator) in effective(&overrides(app)) {
+        if accelerator.is_empty() {
+            continue;
+        }
+        let Ok(shortcut) = parse(&accelerator) else {
+            log::warn!("Ignoring invalid shortcut '{}' for {}", accelerator, action);
+            continue;
+        };
+        if registered.contains_key(&shortcut) {
+            continue;
+        }
+        match app.global_shortcut().register(shortcut) {
+            Ok(()) => {
+                registered.insert(shortcut, action);
+            }
+            Err(e) => log::debug!("Shortcut {} for {} unavailable: {}", accelerator, action, e),
+        }
+    }
+}
+
+/// Release the shortcuts; called when the main window loses focus.
+#[cfg(desktop)]
+pub fn unregister_accelerators(app: &AppHandle) {
+    let Some(state) = app.try_state::<AppShortcutState>() else {
+        return;
+    };
+    for (shortcut, _) in state.registered().drain() {
+        let _ = app.global_shortcut().unregister(shortcut);
+    }
+}
+
+/// Handle a pressed app shortcut. Returns false if `shortcut` isn't one.
+#[cfg(desktop)]
+pub fn handle_shortcut(app: &AppHandle, shortcut: &Shortcut) -> bool {
+    let Some(action) = app
+        .try_state::<AppShortcutState>()
+        .and_then(|state| state.registered().get(shortcut).cloned())
+    else {
+        return false;
+    };
+    let _ = app.emit_to("main", "shortcut-triggered", ShortcutTriggered { action });
+    true
+}
+
+/// Set the accelerator of each action in `map` (action → accelerator; "" turns
+/// the shortcut off) and store it. Actions left out use their default.
+/// Returns the effective map.
+#[tauri::command]
+pub fn register_app_shortcuts(
+    app: AppHandle,
+    map: BTreeMap<String, String>,
+) -> Result<BTreeMap<String, String>, CommandError> {
+    #[cfg(desktop)]
+    {
+        let overrides = validate(&map, &taken(&app))?;
+        let value = serde_json::to_value(&overrides).map_err(|e| e.to_string())?;
+        settings::set(&app, "appShortcuts", value)?;
+        if crate::window::is_main_window_focused(&app) {
+            unregister_accelerators(&app);
+            register_accelerators(&app);
+        }
+        Ok(effective(&overrides))
+    }
+    #[cfg(mobile)]
+    {
+        let _ = (app, map);
+        Err(CommandError::new(
+            crate::error::NOT_SUPPORTED,
+            "Keyboard shortcuts are not available on this platform",
+        ))
+    }
+}
+
+/// Every action's accelerator, defaults included.
+#[tauri::command]
+pub fn get_app_shortcuts(app: AppHandle) -> BTreeMap<String, String> {
+    effective(&overrides(&app))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+        entries
+            .iter()
+            .map(|(action, accelerator)| (action.to_string(), accelerator.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn overrides_replace_defaults() {
+        let shortcuts = effective(&map(&[("newPost", "Alt+N"), ("search", "")]));
+        assert_eq!(shortcuts["newPost"], "Alt+N");
+        assert_eq!(shortcuts["search"], "");
+        assert_eq!(shortcuts["nextFeed"], "Alt+ArrowDown");
+    }
+
+    #[cfg(desktop)]
+    #[test]
+    fn defaults_are_valid_and_distinct() {
+        assert!(validate(&BTreeMap::new(), &[]).is_ok());
+        for (_, accelerator) in DEFAULT_SHORTCUTS {
+            assert!(parse(accelerator).is_ok(), "{}", accelerator);
+        }
+    }
+
+    #[cfg(desktop)]
+    #[test]
+    fn clashes_are_listed() {
+        let error = validate(&map(&[("search", "CommandOrControl+N")]), &[]).unwrap_err();
+        assert_eq!(error.code, crate::error::INVALID_ARGUMENT);
+        assert!(error.message.contains("newPost"), "{}", error.message);
+        assert!(error.message.contains("search"), "{}", error.message);
+
+        let taken = [("zoom".to_string(), "CommandOrControl+Minus".to_string())];
+        let error = validate(&map(&[("newPost", "CommandOrControl+Minus")]), &taken).unwrap_err();
+        assert!(error.message.contains("zoom"), "{}", error.message);
+    }
+
+    #[cfg(desktop)]
+    #[test]
+    fn reserved_and_invalid_accelerators_are_rejected() {
+        assert!(validate(&map(&[("newPost", "CommandOrControl+Q")]), &[]).is_err());
+        assert!(validate(&map(&[("newPost", "CommandOrControl+KeyW")]), &[]).is_err());
+        assert!(validate(&map(&[("newPost", "NotAKey+++")]), &[]).is_err());
+        assert!(validate(&map(&[("bad action", "Alt+N")]), &[]).is_err());
+        // Turning a default off frees its key for another action
+        assert!(validate(
+            &map(&[("newPost", ""), ("search", "CommandOrControl+N")]),
+            &[]
+        )
+        .is_ok());
+    }
+}
@@ -0,0 +1,377 @@
+//! Saving attachments to disk through the native save dialog.
+//!
+//! `save_attachment` asks where to save (suggesting a sanitized name),
+//! then streams the file through the shared client into `<dest>.part`,
+//! emitting `attachment-download-progress` with the download's id (the first
+//! event, at zero bytes, tells the frontend which id to pass to
+//! `cancel_download`). Dropped connections are retried up to
+//! [`MAX_ATTEMPTS`] times, resuming with an HTTP `Range` request guarded by
+//! `If-Range`; the received size is checked against the server's length before
+//! the file is renamed into place.
+
+use crate::net;
+use reqwest::header::{CONTENT_RANGE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE};
+use reqwest::StatusCode;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, State, Url};
+use tauri_plugin_dialog::DialogExt;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{oneshot, Notify};
+
+const MAX_ATTEMPTS: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+const FALLBACK_NAME: &str = "attachment";
+/// Longest file name most filesystems accept, in bytes
+const MAX_NAME_BYTES: usize = 255;
+
+/// Payload of `attachment-download-progress`
+#[derive(Debug, Clone, Serialize)]
+pub struct AttachmentProgress {
+    pub id: String,
+    pub received: u64,
+    /// `None` if the server didn't send a length
+    pub total: Option<u64>,
+}
+
+/// Why `save_attachment` failed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum AttachmentError {
+    InvalidUrl(String),
+    Cancelled(String),
+    /// Connection failure; retried with a range request
+    Network(String),
+    Http(String),
+    /// The file's size didn't match the server's length
+    LengthMismatch(String),
+    Io(String),
+}
+
+impl AttachmentError {
+    fn is_retryable(&self) -> bool {
+        matches!(self, Self::Network(_))
+    }
+}
+
+impl From<reqwest::Error> for AttachmentError {
+    fn from(e: reqwest::Error) -> Self {
+        match e.status() {
+            Some(status) if !status.is_server_error() => Self::Http(e.to_string()),
+            _ => Self::Network(e.to_string()),
+        }
+    }
+}
+
+impl From<std::io::Error> for AttachmentError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+/// Managed state holding the cancel signal of each running download
+#[derive(Default)]
+pub struct AttachmentState {
+    downloads: Mutex<HashMap<String, Arc<Notify>>>,
+}
+
+impl AttachmentState {
+    /// Whether a `save_attachment` is running.
+    pub fn has_active(&self) -> bool {
+        !self
+            .downloads
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_empty()
+    }
+}
+
+/// Make `name` safe to use as a single file name: strip any directories,
+/// control characters and (on Windows) reserved characters and device names.
+fn sanitize_file_name(name: &str, windows: bool) -> String {
+    const WINDOWS_RESERVED: &[char] = &['<', '>', ':', '"', '|', '?', '*'];
+    const WINDOWS_DEVICES: &[&str] = &[
+        "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+        "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+    ];
+
+    let base = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let mut clean: String = base
+        .chars()
+        .filter(|c| !c.is_control() && !(windows && WINDOWS_RESERVED.contains(c)))
+        .collect();
+    if windows {
+        clean = clean.trim_end_matches(['.', ' ']).to_string();
+        let stem = clean.split('.').next().unwrap_or_default();
+        if WINDOWS_DEVICES
+            .iter()
+            .any(|device| device.eq_ignore_ascii_case(stem.trim_end()))
+        {
+            clean.insert(0, '_');
+        }
+    }
+    let clean = clean.trim();
+    if clean.is_empty() || clean == "." || clean == ".." {
+        return FALLBACK_NAME.to_string();
+    }
+
+    // Shorten the stem so the extension survives
+    let mut clean = clean.to_string();
+    if clean.len() > MAX_NAME_BYTES {
+        let ext_start = clean
+            .rfind('.')
+            .filter(|dot| *dot > 0 && clean.len() - dot <= 32)
+            .unwrap_or(clean.len());
+        let (stem, ext) = clean.split_at(ext_start);
+        let mut end = MAX_NAME_BYTES.saturating_sub(ext.len());
+        while !stem.is_char_boundary(end) {
+            end -= 1;
+        }
+        clean = format!("{}{}", &stem[..end], ext);
+    }
+    clean
+}
+
+/// Total size from a `Content-Range: bytes start-end/total` header.
+pub(crate) fn content_range_total(value: &str) -> Option<u64> {
+    value.rsplit('/').next()?.trim().parse().ok()
+}
+
+pub(crate) fn part_path(dest: &Path) -> PathBuf {
+    let mut name = dest.as_os_str().to_owned();
+    name.push(".part");
+    PathBuf::from(name)
+}
+
+/// Ask where to save `suggested_name`. `None` if the dialog was dismissed.
+async fn pick_destination(
+    app: &AppHandle,
+    suggested_name: &str,
+) -> Result<Option<PathBuf>, AttachmentError> {
+    let (sender, receiver) = oneshot::channel();
+    app.dialog()
+        .file()
+        .set_file_name(suggested_name)
+        .save_file(move |path| {
+            let _ = sender.send(path);
+        });
+    match receiver.await.ok().flatten() {
+        Some(path) => path
+            .into_path()
+            .map(Some)
+            .map_err(|e| AttachmentError::Io(e.to_string())),
+        None => Ok(None),
+    }
+}
+
+/// One request, appending to `part` when the server honours the range.
+///
+/// `validator` is the ETag or Last-Modified of the first response, sent as
+/// `If-Range` so a changed file is sent whole rather than spliced.
+async fn fetch_once(
+    app: &AppHandle,
+    client: &reqwest::Client,
+    id: &str,
+    url: &Url,
+    part: &Path,
+    validator: &mut Option<String>,
+) -> Result<(), AttachmentError> {
+    let offset = tokio::fs::metadata(part)
+        .await
+        .map(|meta| meta.len())
+        .unwrap_or(0);
+    let mut request = client.get(url.clone());
+    if let (true, Some(validator)) = (offset > 0, validator.as_deref()) {
+        request = request
+            .header(RANGE, format!("bytes={}-", offset))
+            .header(IF_RANGE, validator);
+    }
+    let mut response = request.send().await?.error_for_status()?;
+
+    let resumed = offset > 0 && response.status() == StatusCode::PARTIAL_CONTENT;
+    let (mut file, mut received, total) = if resumed {
+        let total = response
+            .headers()
+            .get(CONTENT_RANGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(content_range_total);
+        let file = tokio::fs::OpenOptions::new()
+            .append(true)
+            .open(part)
+            .await?;
+        (file, offset, total)
+    } else {
+        *validator = [ETAG, LAST_MODIFIED].iter().find_map(|header| {
+            let value = response.headers().get(header)?.to_str().ok()?;
+            (!value.starts_with("W/")).then(|| value.to_string())
+        });
+        let total = response.content_length();
+        (tokio::fs::File::create(part).await?, 0, total)
+    };
+
+    let emit = |received: u64| {
+        let _ = app.emit(
+            "attachment-download-progress",
+            AttachmentProgress {
+                id: id.to_string(),
+                received,
+                total,
+            },
+        );
+    };
+    emit(received);
+    let mut last_emit = Instant::now();
+    while let Some(chunk) = response.chunk().await? {
+        file.write_all(&chunk).await?;
+        received += chunk.len() as u64;
+        if last_emit.elapsed() >= PROGRESS_INTERVAL {
+            last_emit = Instant::now();
+            emit(received);
+        }
+    }
+    file.flush().await?;
+    emit(received);
+
+    match total {
+        Some(total) if received < total => Err(AttachmentError::Network(format!(
+            "Connection closed after {} of {} bytes",
+            received, total
+        ))),
+        Some(total) if received > total => Err(AttachmentError::LengthMismatch(format!(
+            "Received {} bytes, expected {}",
+            received, total
+        ))),
+        _ => Ok(()),
+    }
+}
+
+async fn download(
+    app: &AppHandle,
+    id: &str,
+    url: &Url,
+    part: &Path,
+) -> Result<(), AttachmentError> {
+    let client = net::client(app);
+    let mut validator = None;
+    let mut attempt = 1;
+    loop {
+        match fetch_once(app, &client, id, url, part, &mut validator).await {
+            Err(e) if e.is_retryable() && attempt < MAX_ATTEMPTS => {
+                log::warn!("Attachment download failed, resuming: {:?}", e);
+                tokio::time::sleep(RETRY_DELAY * attempt).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Download `url` to a location picked in the save dialog. Returns the saved
+/// path, or `None` if the dialog was dismissed.
+#[tauri::command]
+pub async fn save_attachment(
+    app: AppHandle,
+    state: State<'_, AttachmentState>,
+    url: String,
+    suggested_name: String,
+) -> Result<Option<String>, AttachmentError> {
+    let url = Url::parse(&url).map_err(|e| AttachmentError::InvalidUrl(e.to_string()))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(AttachmentError::InvalidUrl(
+            "Attachment URL must use http or https".to_string(),
+        ));
+    }
+    let name = sanitize_file_name(&suggested_name, cfg!(windows));
+    let Some(dest) = pick_destination(&app, &name).await? else {
+        return Ok(None);
+    };
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let cancel = Arc::new(Notify::new());
+    state
+        .downloads
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(id.clone(), cancel.clone());
+
+    let part = part_path(&dest);
+    let result = tokio::select! {
+        result = download(&app, &id, &url, &part) => result,
+        _ = cancel.notified() => Err(AttachmentError::Cancelled("Download cancelled".to_string())),
+    };
+    state
+        .downloads
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(&id);
+
+    match result {
+        Ok(()) => {
+            tokio::fs::rename(&part, &dest).await?;
+            Ok(Some(dest.to_string_lossy().into_owned()))
+        }
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&part).await;
+            Err(e)
+        }
+    }
+}
+
+/// Stop a running `save_attachment`. Returns false if no such download is running.
+#[tauri::command]
+pub fn cancel_download(state: State<'_, AttachmentState>, id: String) -> bool {
+    match state
+        .downloads
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&id)
+    {
+        Some(cancel) => {
+            cancel.notify_one();
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_names_lose_directories_and_control_characters() {
+        assert_eq!(sanitize_file_name("../../etc/passwd", false), "passwd");
+        assert_eq!(sanitize_file_name("..\\..\\boot.ini", false), "boot.ini");
+        assert_eq!(sanitize_file_name("photo\u{0}\n.jpg", false), "photo.jpg");
+        assert_eq!(sanitize_file_name("..", false), FALLBACK_NAME);
+        assert_eq!(sanitize_file_name("dir/", false), FALLBACK_NAME);
+        assert_eq!(sanitize_file_name("what?.png", false), "what?.png");
+    }
+
+    #[test]
+    fn windows_names_avoid_reserved_characters_and_devices() {
+        assert_eq!(sanitize_file_name("what?.png", true), "what.png");
+        assert_eq!(sanitize_file_name("a<b>:c|d*.txt", true), "abcd.txt");
+        assert_eq!(sanitize_file_name("notes. ", true), "notes");
+        assert_eq!(sanitize_file_name("con.txt", true), "_con.txt");
+        assert_eq!(sanitize_file_name("console.txt", true), "console.txt");
+    }
+
+    #[test]
+    fn long_names_keep_their_extension() {
+        let name = format!("{}.mp4", "v".repeat(300));
+        let clean = sanitize_file_name(&name, false);
+        assert_eq!(clean.len(), MAX_NAME_BYTES);
+        assert!(clean.ends_with("v.mp4"));
+    }
+
+    #[test]
+    fn content_range_total_is_parsed() {
+        assert_eq!(content_range_total("bytes 100-999/1000"), Some(1000));
+        assert_eq!(content_range_total("bytes 100-999/*"), None);
+    }
+}
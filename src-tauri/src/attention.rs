@@ -0,0 +1,186 @@
+//! Taskbar flashing (Windows, X11) and dock bouncing (macOS) to draw the user
+//! back to a window in the background.
+//!
+//! `request_attention` only asks while the main window is unfocused, and the
+//! request is withdrawn as soon as the window gets focus. Mentions request it
+//! automatically from [`crate::notifications::dispatch`] unless the
+//! `attentionOnMention` setting is off. Native Wayland compositors ignore the
+//! urgency hint and mobile has no equivalent, which the result's `supported`
+//! flag reports so the UI doesn't offer the option there.
+
+use crate::error::CommandError;
+use crate::fcm::NavigationKind;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::Mutex;
+use tauri::AppHandle;
+#[cfg(desktop)]
+use tauri::{Manager, UserAttentionType, Window};
+
+/// How insistent an attention request is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttentionLevel {
+    /// A single bounce or a flash until focused
+    Informational,
+    /// Bounce or flash until the window is focused
+    Critical,
+}
+
+impl AttentionLevel {
+    fn parse(level: &str) -> Result<Self, CommandError> {
+        match level {
+            "informational" => Ok(Self::Informational),
+            "critical" => Ok(Self::Critical),
+            _ => Err(CommandError::invalid_argument(format!(
+                "Unknown attention level: {}",
+                level
+            ))),
+        }
+    }
+}
+
+#[cfg(desktop)]
+impl From<AttentionLevel> for UserAttentionType {
+    fn from(level: AttentionLevel) -> Self {
+        match level {
+            AttentionLevel::Informational => UserAttentionType::Informational,
+            AttentionLevel::Critical => UserAttentionType::Critical,
+        }
+    }
+}
+
+/// Result of `request_attention`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttentionResult {
+    /// False when the window was focused or the request failed
+    pub requested: bool,
+    /// False where the platform ignores attention requests
+    pub supported: bool,
+}
+
+/// Managed state remembering the windows with an outstanding request
+#[cfg_attr(mobile, allow(dead_code))]
+#[derive(Debug, Default)]
+pub struct AttentionState {
+    pending: Mutex<HashSet<String>>,
+}
+
+#[cfg_attr(mobile, allow(dead_code))]
+impl AttentionState {
+    fn pending(&self) -> std::sync::MutexGuard<'_, HashSet<String>> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// The level a notification of `kind` asks for, given the
+/// `attentionOnMention` setting (on by default).
+pub fn for_notification(kind: NavigationKind, enabled: Option<bool>) -> Option<AttentionLevel> {
+    match kind {
+        NavigationKind::Mention if enabled.unwrap_or(true) => Some(AttentionLevel::Informational),
+        _ => None,
+    }
+}
+
+#[cfg(desktop)]
+fn supported() -> bool {
+    // The urgency hint is an X11 hint, ignored like keep-above on Wayland
+    #[cfg(target_os = "linux")]
+    {
+        let var = |name| std::env::var(name).ok();
+        crate::window::keep_above_honored(
+            var("WAYLAND_DISPLAY").as_deref(),
+            var("GDK_BACKEND").as_deref(),
+        )
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        true
+    }
+}
+
+/// Ask for attention to the window `label` if it isn't focused.
+#[cfg(desktop)]
+pub fn request(app: &AppHandle, label: &str, level: AttentionLevel) -> AttentionResult {
+    let mut result = AttentionResult {
+        requested: false,
+        supported: supported(),
+    };
+    let Some(window) = app.get_webview_window(label) else {
+        return result;
+    };
+    if !result.supported || window.is_focused().unwrap_or(false) {
+        return result;
+    }
+    match window.request_user_attention(Some(level.into())) {
+        Ok(()) => {
+            if let Some(state) = app.try_state::<AttentionState>() {
+                state.pending().insert(label.to_string());
+            }
+            result.requested = true;
+        }
+        Err(e) => log::debug!("Failed to request attention: {}", e),
+    }
+    result
+}
+
+#[cfg(mobile)]
+pub fn request(_app: &AppHandle, _label: &str, _level: AttentionLevel) -> AttentionResult {
+    AttentionResult {
+        requested: false,
+        supported: false,
+    }
+}
+
+/// Withdraw the outstanding request of a window that just got focus. Called
+/// from [`crate::window::on_window_event`].
+#[cfg(desktop)]
+pub fn on_focused(window: &Window) {
+    let Some(state) = window.try_state::<AttentionState>() else {
+        return;
+    };
+    if state.pending().remove(window.label()) {
+        if let Err(e) = window.request_user_attention(None) {
+            log::debug!("Failed to clear attention request: {}", e);
+        }
+    }
+}
+
+/// Flash the taskbar button or bounce the dock icon until the main window is
+/// focused. `level` is "informational" or "critical". Nothing happens while
+/// the window has focus; `supported` is false where the platform ignores
+/// the request.
+#[tauri::command]
+pub fn request_attention(app: AppHandle, level: String) -> Result<AttentionResult, CommandError> {
+    let level = AttentionLevel::parse(&level)?;
+    Ok(request(&app, "main", level))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_parse() {
+        assert_eq!(
+            AttentionLevel::parse("informational").unwrap(),
+            AttentionLevel::Informational
+        );
+        assert_eq!(
+            AttentionLevel::parse("critical").unwrap(),
+            AttentionLevel::Critical
+        );
+        assert!(AttentionLevel::parse("Critical").is_err());
+    }
+
+    #[test]
+    fn only_mentions_ask_for_attention() {
+        assert_eq!(
+            for_notification(NavigationKind::Mention, None),
+            Some(AttentionLevel::Informational)
+        );
+        assert_eq!(for_notification(NavigationKind::Mention, Some(false)), None);
+        assert_eq!(for_notification(NavigationKind::Dm, Some(true)), None);
+        assert_eq!(for_notification(NavigationKind::Post, None), None);
+    }
+}
@@ -0,0 +1,485 @@
+//! Starting Hush when the user logs in.
+//!
+//! `set_autostart` registers a login entry for this install: a value under the
+//! `Run` registry key on Windows, a LaunchAgent plist on macOS, and an XDG
+//! autostart `.desktop` file on Linux. Inside a Flatpak the sandbox can't
+//! write those, so the request goes to the Background portal instead, which
+//! may ask the user first; the portal can't be queried, so the last request is
+//! remembered in `autostart.json` and reported by `get_autostart`.
+//!
+//! Entries are named after a hash of the executable path, so disabling only
+//! removes the entry this install created and leaves other installs (a
+//! portable copy next to an installed one, say) alone. With `minimized` the
+//! entry passes `--minimized`, which starts Hush in the tray.
+
+use crate::error::{self, CommandError};
+use serde::Serialize;
+#[cfg(desktop)]
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+#[cfg(desktop)]
+const ENTRY_PREFIX: &str = "social.hushnetwork";
+#[cfg(desktop)]
+const MINIMIZED_FLAG: &str = "--minimized";
+#[cfg(target_os = "linux")]
+const STATE_FILE: &str = "autostart.json";
+#[cfg(target_os = "windows")]
+const RUN_KEY: &str = r"HKCU\Software\Microsoft\Windows\CurrentVersion\Run";
+
+/// How the login entry is registered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AutostartMechanism {
+    Registry,
+    LaunchAgent,
+    XdgAutostart,
+    FlatpakPortal,
+    Unsupported,
+}
+
+/// Result of `get_autostart` and `set_autostart`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AutostartStatus {
+    pub enabled: bool,
+    /// Whether the entry starts Hush in the tray
+    pub minimized: bool,
+    pub mechanism: AutostartMechanism,
+}
+
+/// What is registered for this install: None, or whether it starts minimized
+#[cfg(desktop)]
+type Entry = Option<bool>;
+
+/// Name of this install's entry, unique per executable path.
+#[cfg(desktop)]
+fn entry_name(program: &Path) -> String {
+    use sha2::{Digest, Sha256};
+    let digest = Sha256::digest(program.to_string_lossy().as_bytes());
+    let id: String = digest[..4]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("{}.{}", ENTRY_PREFIX, id)
+}
+
+/// The executable the entry launches; on Linux the AppImage rather than its
+/// temporary mount.
+#[cfg(desktop)]
+fn program() -> Result<PathBuf, CommandError> {
+    #[cfg(target_os = "linux")]
+    if let Some(appimage) = std::env::var_os("APPIMAGE") {
+        return Ok(PathBuf::from(appimage));
+    }
+    Ok(std::env::current_exe()?)
+}
+
+// ============= Linux =============
+
+#[cfg(target_os = "linux")]
+fn in_flatpak() -> bool {
+    Path::new("/.flatpak-info").exists()
+}
+
+/// Quote an argument for a desktop entry's `Exec` key.
+#[cfg(target_os = "linux")]
+fn exec_arg(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "/-_.+,:@%".contains(c));
+    if plain {
+        return arg.to_string();
+    }
+    let mut quoted = String::from("\"");
+    for c in arg.chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(target_os = "linux")]
+fn desktop_entry(program: &Path, minimized: bool) -> String {
+    let mut exec = exec_arg(&program.to_string_lossy());
+    if minimized {
+        exec.push(' ');
+        exec.push_str(MINIMIZED_FLAG);
+    }
+    format!(
+        "[Desktop Entry]\nType=Application\nName=Hush Feeds\nExec={}\nTerminal=false\nX-GNOME-Autostart-enabled=true\n",
+        exec
+    )
+}
+
+/// Read a desktop entry we wrote; disabled entries (`Hidden=true`, or turned
+/// off in GNOME's startup settings) count as absent.
+#[cfg(target_os = "linux")]
+fn parse_desktop_entry(contents: &str) -> Entry {
+    let mut exec = None;
+    for line in contents.lines().map(str::trim) {
+        match line.split_once('=') {
+            Some(("Exec", value)) => exec = Some(value),
+            Some(("Hidden", "true")) | Some(("X-GNOME-Autostart-enabled", "false")) => return None,
+            _ => {}
+        }
+    }
+    exec.map(|exec| exec.split_whitespace().any(|arg| arg == MINIMIZED_FLAG))
+}
+
+#[cfg(target_os = "linux")]
+fn desktop_file(app: &AppHandle, program: &Path) -> Result<PathBuf, CommandError> {
+    use tauri::Manager;
+    let dir = app
+        .path()
+        .config_dir()
+        .map_err(|e| CommandError::new(error::UNAVAILABLE, e.to_string()))?;
+    Ok(dir
+        .join("autostart")
+        .join(format!("{}.desktop", entry_name(program))))
+}
+
+/// Quote a string as a GVariant text literal.
+#[cfg(target_os = "linux")]
+fn gvariant_str(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// Ask the Background portal to (un)register autostart for the Flatpak.
+#[cfg(target_os = "linux")]
+fn request_portal_autostart(
+    program: &Path,
+    enabled: bool,
+    minimized: bool,
+) -> Result<(), CommandError> {
+    let mut commandline = vec![gvariant_str(&program.to_string_lossy())];
+    if minimized {
+        commandline.push(gvariant_str(MINIMIZED_FLAG));
+    }
+    let options = format!(
+        "{{'reason': <{}>, 'autostart': <{}>, 'commandline': <[{}]>, 'dbus-activatable': <false>}}",
+        gvariant_str("Start Hush Feeds when you log in"),
+        enabled,
+        commandline.join(", ")
+    );
+    let output = std::process::Command::new("gdbus")
+        .args([
+            "call",
+            "--session",
+            "--dest",
+            "org.freedesktop.portal.Desktop",
+            "--object-path",
+            "/org/freedesktop/portal/desktop",
+            "--method",
+            "org.freedesktop.portal.Background.RequestBackground",
+            "",
+            &options,
+        ])
+        .output()?;
+    if !output.status.success() {
+        return Err(CommandError::new(
+            error::UNAVAILABLE,
+            format!(
+                "Background portal request failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Last request sent to the portal
+#[cfg(target_os = "linux")]
+#[derive(Debug, Default, Serialize, serde::Deserialize)]
+struct PortalState {
+    enabled: bool,
+    minimized: bool,
+}
+
+// ============= macOS =============
+
+#[cfg(target_os = "macos")]
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(target_os = "macos")]
+fn launch_agent(label: &str, program: &Path, minimized: bool) -> String {
+    let mut arguments = format!(
+        "        <string>{}</string>\n",
+        xml_escape(&program.to_string_lossy())
+    );
+    if minimized {
+        arguments.push_str(&format!("        <string>{}</string>\n", MINIMIZED_FLAG));
+    }
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+         <plist version=\"1.0\">\n\
+         <dict>\n\
+         \x20   <key>Label</key>\n\
+         \x20   <string>{}</string>\n\
+         \x20   <key>ProgramArguments</key>\n\
+         \x20   <array>\n\
+         {}\
+         \x20   </array>\n\
+         \x20   <key>RunAtLoad</key>\n\
+         \x20   <true/>\n\
+         </dict>\n\
+         </plist>\n",
+        label, arguments
+    )
+}
+
+#[cfg(target_os = "macos")]
+fn launch_agent_file(app: &AppHandle, label: &str) -> Result<PathBuf, CommandError> {
+    use tauri::Manager;
+    let home = app
+        .path()
+        .home_dir()
+        .map_err(|e| CommandError::new(error::UNAVAILABLE, e.to_string()))?;
+    Ok(home
+        .join("Library/LaunchAgents")
+        .join(format!("{}.plist", label)))
+}
+
+// ============= Windows =============
+
+#[cfg(target_os = "windows")]
+fn reg(args: &[&str]) -> std::io::Result<std::process::Output> {
+    std::process::Command::new("reg").args(args).output()
+}
+
+/// The data of `name` in `reg query` output.
+#[cfg(target_os = "windows")]
+fn parse_reg_query<'a>(output: &'a str, name: &str) -> Option<&'a str> {
+    output.lines().find_map(|line| {
+        let (value_name, rest) = line.trim().split_once("REG_SZ")?;
+        (value_name.trim() == name).then(|| rest.trim())
+    })
+}
+
+// ============= Commands =============
+
+/// What is registered for this install, and how.
+#[cfg(desktop)]
+fn read_entry(
+    app: &AppHandle,
+    program: &Path,
+) -> Result<(Entry, AutostartMechanism), CommandError> {
+    #[cfg(target_os = "linux")]
+    {
+        if in_flatpak() {
+            let state = crate::storage::config_file(app, STATE_FILE)?;
+            let state: PortalState = crate::storage::read_json(&state).unwrap_or_default();
+            let entry = state.enabled.then_some(state.minimized);
+            return Ok((entry, AutostartMechanism::FlatpakPortal));
+        }
+        let entry = match std::fs::read_to_string(desktop_file(app, program)?) {
+            Ok(contents) => parse_desktop_entry(&contents),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        Ok((entry, AutostartMechanism::XdgAutostart))
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let path = launch_agent_file(app, &entry_name(program))?;
+        let entry = match std::fs::read_to_string(path) {
+            Ok(contents) => {
+                Some(contents.contains(&format!("<string>{}</string>", MINIMIZED_FLAG)))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        Ok((entry, AutostartMechanism::LaunchAgent))
+    }
+    #[cfg(target_os = "windows")]
+    {
+        let _ = app;
+        let name = entry_name(program);
+        let output = reg(&["query", RUN_KEY, "/v", &name])?;
+        // reg exits with 1 when the value doesn't exist
+        let entry = output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+            .and_then(|stdout| {
+                parse_reg_query(&stdout, &name)
+                    .map(|command| command.split_whitespace().any(|arg| arg == MINIMIZED_FLAG))
+            });
+        Ok((entry, AutostartMechanism::Registry))
+    }
+}
+
+/// Register (or with `entry` None, remove) this install's entry.
+#[cfg(desktop)]
+fn write_entry(app: &AppHandle, program: &Path, entry: Entry) -> Result<(), CommandError> {
+    #[cfg(target_os = "linux")]
+    {
+        if in_flatpak() {
+            request_portal_autostart(program, entry.is_some(), entry.unwrap_or(false))?;
+            let state = PortalState {
+                enabled: entry.is_some(),
+                minimized: entry.unwrap_or(false),
+            };
+            let path = crate::storage::config_file(app, STATE_FILE)?;
+            return Ok(crate::storage::write_json_atomic(&path, &state)?);
+        }
+        let path = desktop_file(app, program)?;
+        match entry {
+            Some(minimized) => Ok(crate::storage::write_atomic(
+                &path,
+                desktop_entry(program, minimized).as_bytes(),
+            )?),
+            None => Ok(crate::storage::remove_file(&path)?),
+        }
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let label = entry_name(program);
+        let path = launch_agent_file(app, &label)?;
+        match entry {
+            Some(minimized) => Ok(crate::storage::write_atomic(
+                &path,
+                launch_agent(&label, program, minimized).as_bytes(),
+            )?),
+            None => Ok(crate::storage::remove_file(&path)?),
+        }
+    }
+    #[cfg(target_os = "windows")]
+    {
+        let name = entry_name(program);
+        let output = match entry {
+            Some(minimized) => {
+                let mut command = format!("\"{}\"", program.to_string_lossy());
+                if minimized {
+                    command.push(' ');
+                    command.push_str(MINIMIZED_FLAG);
+                }
+                reg(&[
+                    "add", RUN_KEY, "/v", &name, "/t", "REG_SZ", "/d", &command, "/f",
+                ])?
+            }
+            None => {
+                if read_entry(app, program)?.0.is_none() {
+                    return Ok(());
+                }
+                reg(&["delete", RUN_KEY, "/v", &name, "/f"])?
+            }
+        };
+        if !output.status.success() {
+            return Err(CommandError::new(
+                error::IO,
+                format!(
+                    "Failed to update the Run key: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Whether Hush starts at login, and how the entry is registered.
+#[tauri::command]
+pub fn get_autostart(app: AppHandle) -> Result<AutostartStatus, CommandError> {
+    #[cfg(desktop)]
+    {
+        let (entry, mechanism) = read_entry(&app, &program()?)?;
+        Ok(AutostartStatus {
+            enabled: entry.is_some(),
+            minimized: entry.unwrap_or(false),
+            mechanism,
+        })
+    }
+    #[cfg(mobile)]
+    {
+        let _ = app;
+        Ok(AutostartStatus {
+            enabled: false,
+            minimized: false,
+            mechanism: AutostartMechanism::Unsupported,
+        })
+    }
+}
+
+/// Start Hush at login, in the tray if `minimized`, or stop doing so.
+///
+/// Disabling removes only this install's entry. Fails with `not_supported`
+/// on mobile.
+#[tauri::command]
+pub fn set_autostart(
+    app: AppHandle,
+    enabled: bool,
+    minimized: bool,
+) -> Result<AutostartStatus, CommandError> {
+    #[cfg(desktop)]
+    {
+        let program = program()?;
+        write_entry(&app, &program, enabled.then_some(minimized))?;
+        log::info!("Autostart {}", if enabled { "enabled" } else { "disabled" });
+        get_autostart(app)
+    }
+    #[cfg(mobile)]
+    {
+        let _ = (app, enabled, minimized);
+        Err(CommandError::new(
+            error::NOT_SUPPORTED,
+            "Autostart is not available on this platform",
+        ))
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entry_names_differ_per_install() {
+        let installed = entry_name(Path::new("/usr/bin/hush"));
+        let portable = entry_name(Path::new("/home/alice/Apps/Hush.AppImage"));
+        assert!(installed.starts_with("social.hushnetwork."));
+        assert_eq!(installed.len(), "social.hushnetwork.".len() + 8);
+        assert_ne!(installed, portable);
+        assert_eq!(installed, entry_name(Path::new("/usr/bin/hush")));
+    }
+
+    #[test]
+    fn desktop_entry_round_trips() {
+        let program = Path::new("/home/alice/My Apps/hush");
+        let entry = desktop_entry(program, true);
+        assert!(entry.contains("Exec=\"/home/alice/My Apps/hush\" --minimized\n"));
+        assert_eq!(parse_desktop_entry(&entry), Some(true));
+        assert_eq!(
+            parse_desktop_entry(&desktop_entry(Path::new("/usr/bin/hush"), false)),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn disabled_desktop_entries_count_as_absent() {
+        let entry = desktop_entry(Path::new("/usr/bin/hush"), false);
+        assert_eq!(
+            parse_desktop_entry(&format!("{}Hidden=true\n", entry)),
+            None
+        );
+        let gnome_off = entry.replace(
+            "X-GNOME-Autostart-enabled=true",
+            "X-GNOME-Autostart-enabled=false",
+        );
+        assert_eq!(parse_desktop_entry(&gnome_off), None);
+    }
+
+    #[test]
+    fn exec_and_gvariant_quoting() {
+        assert_eq!(exec_arg("/usr/bin/hush"), "/usr/bin/hush");
+        assert_eq!(exec_arg("/opt/$HOME \"x\""), "\"/opt/\\$HOME \\\"x\\\"\"");
+        assert_eq!(gvariant_str("it's"), "'it\\'s'");
+    }
+}
@@ -0,0 +1,350 @@
+//! Periodic background refresh of feeds while the app sits in the tray.
+//!
+//! Every `syncIntervalMinutes` (set with `set_sync_interval`; 0 disables) a
+//! task fetches posts newer than the newest cached one for each feed set with
+//! `configure_background_sync`, stores them in the offline cache and emits
+//! `background-sync-complete` with the number of new posts per feed. Feeds
+//! marked `notify` get a desktop notification through
+//! [`crate::notifications::dispatch`] unless the window is focused.
+//!
+//! Syncs are skipped while [`crate::connectivity`] reports the server
+//! unreachable, failed syncs stretch the interval exponentially, and on
+//! battery the interval is stretched by the [`crate::power`] throttle. Timers
+//! don't advance while the machine sleeps, so nothing runs during suspend.
+//! The feed configuration is persisted to `background-sync.json`.
+
+use crate::backoff::Backoff;
+use crate::cache::{self, CacheState};
+use crate::connectivity::{Connectivity, ConnectivityState};
+use crate::fcm::NavigationKind;
+use crate::logging::redact;
+use crate::net;
+use crate::notifications::{self, FeedNotification};
+use crate::power;
+use crate::settings::{self, SettingsState};
+use crate::storage;
+use crate::window;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State, Url};
+use tokio::sync::Notify;
+
+const CONFIG_FILE: &str = "background-sync.json";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Failed syncs stretch the interval up to this many times its length
+const MAX_BACKOFF_FACTOR: u32 = 8;
+
+/// A feed refreshed in the background
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncFeed {
+    pub feed_id: String,
+    /// Shown as the notification title
+    pub name: String,
+    /// Notify about new posts in this feed
+    #[serde(default)]
+    pub notify: bool,
+}
+
+/// What to sync, provided by the frontend
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncConfig {
+    /// URL returning a feed's posts as a JSON array, with `{feedId}` and
+    /// `{since}` (timestamp of the newest cached post) placeholders
+    pub posts_url: String,
+    #[serde(default)]
+    pub auth_token: Option<String>,
+    pub feeds: Vec<SyncFeed>,
+}
+
+/// Payload of the `background-sync-complete` event
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncCompletePayload {
+    /// New posts per feed id
+    pub new_posts: BTreeMap<String, usize>,
+}
+
+/// Managed state shared between the commands and the background task
+#[derive(Default)]
+pub struct BackgroundSyncState {
+    config: Mutex<Option<SyncConfig>>,
+    /// Signalled when the interval or configuration changes
+    wake: Notify,
+    /// Set with `wake` to sync right away instead of restarting the wait
+    sync_now: AtomicBool,
+}
+
+impl BackgroundSyncState {
+    pub fn load(app: &AppHandle) -> Self {
+        let config = storage::config_file(app, CONFIG_FILE)
+            .ok()
+            .and_then(|path| storage::read_json::<SyncConfig>(&path));
+        Self {
+            config: Mutex::new(config),
+            wake: Notify::new(),
+            sync_now: AtomicBool::new(false),
+        }
+    }
+
+    fn config(&self) -> Option<SyncConfig> {
+        self.config
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Restart the wait with a freshly computed interval.
+    pub fn reschedule(&self) {
+        self.wake.notify_one();
+    }
+
+    /// Sync now (if background sync is on), e.g. after the system wakes.
+    pub fn sync_now(&self) {
+        self.sync_now.store(true, Ordering::SeqCst);
+        self.wake.notify_one();
+    }
+}
+
+/// Percent-encode a URL path or query component.
+fn encode_component(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+fn posts_url(template: &str, feed_id: &str, since: i64) -> String {
+    template
+        .replace("{feedId}", &encode_component(feed_id))
+        .replace("{since}", &since.to_string())
+}
+
+/// Posts newer than `since`, and the id of the newest one.
+fn new_posts(posts: &[Value], since: i64) -> (usize, Option<String>) {
+    let newer = posts
+        .iter()
+        .filter_map(|post| Some((post.get("timestamp")?.as_i64()?, post)))
+        .filter(|(timestamp, _)| *timestamp > since);
+    let mut count = 0;
+    let mut newest: Option<(i64, &Value)> = None;
+    for (timestamp, post) in newer {
+        count += 1;
+        if newest.map_or(true, |(newest, _)| timestamp > newest) {
+            newest = Some((timestamp, post));
+        }
+    }
+    let newest_id = newest
+        .and_then(|(_, post)| post.get("id")?.as_str())
+        .map(str::to_string);
+    (count, newest_id)
+}
+
+fn notify_new_posts(app: &AppHandle, feed: &SyncFeed, count: usize, post_id: Option<String>) {
+    let body = if count == 1 {
+        "1 new post".to_string()
+    } else {
+        format!("{} new posts", count)
+    };
+    notifications::dispatch(
+        app,
+        FeedNotification {
+            title: feed.name.clone(),
+            body,
+            feed_id: Some(feed.feed_id.clone()),
+            post_id,
+            kind: NavigationKind::Feed,
+            feed_name: Some(feed.name.clone()),
+        },
+    );
+}
+
+/// Fetch and cache new posts for every configured feed.
+///
+/// Fails if the server couldn't be reached; individual feeds the server
+/// rejects are logged and skipped.
+async fn sync_once(
+    app: &AppHandle,
+    client: &reqwest::Client,
+    config: &SyncConfig,
+) -> Result<BTreeMap<String, usize>, String> {
+    let cache = app.state::<CacheState>();
+    let notify = !window::is_main_window_focused(app);
+    let mut counts = BTreeMap::new();
+
+    for feed in &config.feeds {
+        let since = cache.newest_timestamp(&feed.feed_id)?.unwrap_or(0);
+        let url = posts_url(&config.posts_url, &feed.feed_id, since);
+        let mut request = client.get(&url).timeout(REQUEST_TIMEOUT);
+        if let Some(token) = &config.auth_token {
+            request = request.bearer_auth(token);
+        }
+        let response = request.send().await.map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            log::warn!(
+                "Background sync of {} failed: HTTP {}",
+                feed.feed_id,
+                response.status()
+            );
+            continue;
+        }
+        let posts: Vec<Value> = match response.json().await {
+            Ok(posts) => posts,
+            Err(e) => {
+                log::warn!(
+                    "Background sync of {} returned bad JSON: {}",
+                    feed.feed_id,
+                    e
+                );
+                continue;
+            }
+        };
+
+        let (count, newest_id) = new_posts(&posts, since);
+        cache.upsert_posts(&feed.feed_id, &posts, cache::max_bytes(app))?;
+        if count > 0 && feed.notify && notify {
+            notify_new_posts(app, feed, count, newest_id);
+        }
+        counts.insert(feed.feed_id.clone(), count);
+    }
+    Ok(counts)
+}
+
+/// The configured interval, stretched by the power throttle.
+fn interval(app: &AppHandle) -> Option<Duration> {
+    let minutes = app.state::<SettingsState>().get().sync_interval_minutes?;
+    (minutes > 0)
+        .then(|| Duration::from_secs(u64::from(minutes) * 60) * power::throttle(app).factor())
+}
+
+async fn run(app: AppHandle) {
+    let state = app.state::<BackgroundSyncState>();
+    let mut base = Duration::ZERO;
+    let mut backoff = Backoff::new(base, base);
+    let mut delay = None;
+
+    loop {
+        let Some(interval) = interval(&app) else {
+            state.wake.notified().await;
+            state.sync_now.store(false, Ordering::SeqCst);
+            continue;
+        };
+        // A new interval restarts the backoff from it
+        if interval != base {
+            base = interval;
+            backoff = Backoff::new(interval * 2, interval * MAX_BACKOFF_FACTOR);
+            delay = None;
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(delay.unwrap_or(interval)) => {}
+            _ = state.wake.notified() => {
+                if !state.sync_now.swap(false, Ordering::SeqCst) {
+                    continue;
+                }
+            }
+        }
+
+        let Some(config) = state.config() else {
+            continue;
+        };
+        if app.state::<ConnectivityState>().get() != Connectivity::Online {
+            log::debug!("Skipping background sync while offline");
+            continue;
+        }
+
+        match sync_once(&app, &net::client(&app), &config).await {
+            Ok(new_posts) => {
+                backoff.reset();
+                delay = None;
+                let _ = app.emit(
+                    "background-sync-complete",
+                    SyncCompletePayload { new_posts },
+                );
+            }
+            Err(e) => {
+                log::warn!("Background sync failed: {}", redact(&e.to_string()));
+                delay = Some(backoff.next_delay());
+            }
+        }
+    }
+}
+
+/// Spawn the scheduler. Called from `setup` after [`BackgroundSyncState`] is managed.
+pub fn init(app: &AppHandle) {
+    tauri::async_runtime::spawn(run(app.clone()));
+}
+
+/// Set the minutes between background syncs; 0 disables them.
+#[tauri::command]
+pub fn set_sync_interval(
+    app: AppHandle,
+    state: State<'_, BackgroundSyncState>,
+    minutes: u32,
+) -> Result<(), String> {
+    settings::set(&app, "syncIntervalMinutes", minutes.into())?;
+    state.wake.notify_one();
+    Ok(())
+}
+
+/// Set which feeds are synced in the background, or stop syncing with `None`.
+#[tauri::command]
+pub fn configure_background_sync(
+    app: AppHandle,
+    state: State<'_, BackgroundSyncState>,
+    config: Option<SyncConfig>,
+) -> Result<(), String> {
+    if let Some(config) = &config {
+        let example = posts_url(&config.posts_url, "feed", 0);
+        let url = Url::parse(&example).map_err(|e| format!("Invalid posts URL: {}", e))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err("Posts URL must use http or https".to_string());
+        }
+    }
+
+    let path = storage::config_file(&app, CONFIG_FILE)?;
+    match &config {
+        Some(config) => storage::write_json_atomic(&path, config)?,
+        None => storage::remove_file(&path)?,
+    }
+    *state.config.lock().unwrap_or_else(|e| e.into_inner()) = config;
+    state.wake.notify_one();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn posts_url_fills_placeholders() {
+        assert_eq!(
+            posts_url(
+                "https://chat.hushnetwork.social/api/feeds/{feedId}/posts?since={since}",
+                "a/b c",
+                42
+            ),
+            "https://chat.hushnetwork.social/api/feeds/a%2Fb%20c/posts?since=42"
+        );
+    }
+
+    #[test]
+    fn counts_only_posts_newer_than_since() {
+        let posts = vec![
+            json!({"id": "old", "timestamp": 5}),
+            json!({"id": "b", "timestamp": 12}),
+            json!({"id": "a", "timestamp": 11}),
+            json!({"id": "no-timestamp"}),
+        ];
+        assert_eq!(new_posts(&posts, 10), (2, Some("b".to_string())));
+        assert_eq!(new_posts(&posts, 20), (0, None));
+    }
+}
@@ -0,0 +1,95 @@
+//! Exponential retry backoff shared by the push listeners and the outbox.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// Doubling retry delay between `initial` and `max`
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    attempt: u32,
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            attempt: 0,
+        }
+    }
+
+    /// Delay before the next retry; each call doubles the following one.
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self
+            .initial
+            .saturating_mul(2u32.saturating_pow(self.attempt))
+            .min(self.max);
+        self.attempt = self.attempt.saturating_add(1);
+        delay
+    }
+
+    /// Like [`Backoff::next_delay`] but randomly shortened by up to half, so
+    /// many clients dropped at once don't reconnect in lockstep.
+    pub fn next_jittered_delay(&mut self) -> Duration {
+        let delay = self.next_delay();
+        delay / 2 + delay.mul_f64(random_fraction() / 2.0)
+    }
+
+    /// Start over from the initial delay, e.g. after a successful connection.
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+}
+
+/// A random number in `[0, 1)`, good enough for jitter.
+fn random_fraction() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos(),
+    );
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delay_doubles_up_to_cap() {
+        let mut backoff = Backoff::new(Duration::from_secs(2), Duration::from_secs(30));
+        let delays: Vec<u64> = (0..6).map(|_| backoff.next_delay().as_secs()).collect();
+        assert_eq!(delays, vec![2, 4, 8, 16, 30, 30]);
+    }
+
+    #[test]
+    fn reset_starts_over() {
+        let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(60));
+        backoff.next_delay();
+        backoff.next_delay();
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn large_attempt_counts_do_not_overflow() {
+        let mut backoff = Backoff::new(Duration::from_secs(2), Duration::from_secs(300));
+        for _ in 0..100 {
+            assert!(backoff.next_delay() <= Duration::from_secs(300));
+        }
+    }
+
+    #[test]
+    fn jittered_delay_stays_within_half_to_full() {
+        let mut backoff = Backoff::new(Duration::from_secs(8), Duration::from_secs(8));
+        for _ in 0..50 {
+            let delay = backoff.next_jittered_delay();
+            assert!(delay >= Duration::from_secs(4) && delay <= Duration::from_secs(8));
+        }
+    }
+}
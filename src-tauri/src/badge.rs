@@ -0,0 +1,169 @@
+//! Unread badge on the dock (macOS), taskbar (Windows), or launcher (Linux).
+//!
+//! macOS uses the dock badge and Windows a taskbar overlay icon with the count
+//! drawn in. On Linux the badge goes through the Unity launcher API, which only
+//! some desktop environments implement; elsewhere the update is logged and
+//! ignored. Mobile launchers get their badges from push notifications instead.
+//!
+//! `set_unread_count` updates everything showing the unread count at once:
+//! this badge, the tray icon and tooltip, and the main window title, which
+//! becomes "(3) Hush Feeds" for taskbar-only users. The base title is the
+//! one configured for the main window in `tauri.conf.json`. Calls in quick
+//! succession are coalesced over [`UNREAD_COALESCE`] so the title doesn't
+//! flicker.
+
+use crate::error::CommandError;
+#[cfg(desktop)]
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::time::Duration;
+use tauri::AppHandle;
+#[cfg(desktop)]
+use tauri::{Manager, WebviewWindow};
+#[cfg(target_os = "windows")]
+use tauri::image::Image;
+
+/// Show `count` as the app's badge; `None` or 0 clears it.
+///
+/// Safe to call before the main window is shown (or created): without a
+/// window the update is skipped.
+#[tauri::command]
+pub fn set_badge_count(app: AppHandle, count: Option<u32>) -> Result<(), CommandError> {
+    let count = count.filter(|count| *count > 0);
+    #[cfg(desktop)]
+    {
+        let Some(window) = app.get_webview_window("main") else {
+            log::debug!("Main window not available, skipping badge update");
+            return Ok(());
+        };
+        Ok(apply_badge(&window, count)?)
+    }
+    #[cfg(mobile)]
+    {
+        let _ = (app, count);
+        Ok(())
+    }
+}
+
+/// How long `set_unread_count` waits for further calls before applying
+#[cfg_attr(mobile, allow(dead_code))]
+pub const UNREAD_COALESCE: Duration = Duration::from_millis(200);
+/// Title used if the main window has none configured
+#[cfg_attr(mobile, allow(dead_code))]
+const FALLBACK_TITLE: &str = "Hush Feeds";
+
+/// Managed state coalescing `set_unread_count` calls
+#[cfg(desktop)]
+#[derive(Debug, Default)]
+pub struct UnreadCountState {
+    /// The count most recently asked for
+    requested: AtomicU32,
+    scheduled: AtomicBool,
+}
+
+/// The main window title carrying `count`, e.g. "(3) Hush Feeds".
+#[cfg_attr(mobile, allow(dead_code))]
+fn unread_title(base: &str, count: u32) -> String {
+    match count {
+        0 => base.to_string(),
+        count => format!("({}) {}", count, base),
+    }
+}
+
+/// The main window's title as configured in `tauri.conf.json`.
+#[cfg(desktop)]
+fn base_title(app: &AppHandle) -> String {
+    app.config()
+        .app
+        .windows
+        .iter()
+        .find(|window| window.label == "main")
+        .map(|window| window.title.clone())
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| FALLBACK_TITLE.to_string())
+}
+
+/// Show `count` in the window title, the tray and the badge.
+#[cfg(desktop)]
+fn apply_unread_count(app: &AppHandle, count: u32) {
+    if let Some(window) = app.get_webview_window("main") {
+        if let Err(e) = window.set_title(&unread_title(&base_title(app), count)) {
+            log::warn!("Failed to update window title: {}", e);
+        }
+        if let Err(e) = apply_badge(&window, Some(count).filter(|count| *count > 0)) {
+            log::warn!("Failed to update badge: {}", e);
+        }
+    }
+    if let Some(tray) = app.try_state::<crate::tray::TrayManager>() {
+        if let Err(e) = tray.set_unread_count(count) {
+            log::warn!("Failed to update tray unread count: {}", e);
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn apply_badge(window: &WebviewWindow, count: Option<u32>) -> Result<(), String> {
+    use crate::tray::{badge_label, overlay_badge, OVERLAY_SIZE};
+
+    let icon = count
+        .and_then(badge_label)
+        .map(|label| Image::new_owned(overlay_badge(&label), OVERLAY_SIZE, OVERLAY_SIZE));
+    window.set_overlay_icon(icon).map_err(|e| e.to_string())
+}
+
+#[cfg(target_os = "macos")]
+fn apply_badge(window: &WebviewWindow, count: Option<u32>) -> Result<(), String> {
+    window
+        .set_badge_count(count.map(i64::from))
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(target_os = "linux")]
+fn apply_badge(window: &WebviewWindow, count: Option<u32>) -> Result<(), String> {
+    if let Err(e) = window.set_badge_count(count.map(i64::from)) {
+        log::debug!("Launcher badge not supported: {}", e);
+    }
+    Ok(())
+}
+
+/// Show `count` unread posts in the main window title ("(3) Hush Feeds"),
+/// the tray icon and tooltip, and the badge; 0 restores the plain title.
+/// Applied after [`UNREAD_COALESCE`] with the latest count. No-op on mobile.
+#[tauri::command]
+pub fn set_unread_count(app: AppHandle, count: u32) -> Result<(), CommandError> {
+    #[cfg(desktop)]
+    {
+        let state = app
+            .try_state::<UnreadCountState>()
+            .ok_or_else(|| CommandError::new(crate::error::UNAVAILABLE, "Not ready"))?;
+        state.requested.store(count, Ordering::SeqCst);
+        if state.scheduled.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        let app = app.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(UNREAD_COALESCE);
+            let state = app.state::<UnreadCountState>();
+            state.scheduled.store(false, Ordering::SeqCst);
+            apply_unread_count(&app, state.requested.load(Ordering::SeqCst));
+        });
+        Ok(())
+    }
+    #[cfg(mobile)]
+    {
+        let _ = (app, count);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unread_title_prefixes_the_count() {
+        assert_eq!(unread_title("Hush Feeds", 0), "Hush Feeds");
+        assert_eq!(unread_title("Hush Feeds", 3), "(3) Hush Feeds");
+        assert_eq!(unread_title("Hush Feeds!", 120), "(120) Hush Feeds!");
+    }
+}
@@ -0,0 +1,252 @@
+//! Biometric unlock for the app lock.
+//!
+//! `unlock_with_biometrics` asks the OS to verify the user: BiometricPrompt on
+//! Android (through `BiometricHelper`), Touch ID through LocalAuthentication on
+//! macOS and Windows Hello on Windows, where the prompt is owned by the main
+//! window so it opens in front of the app. On success the app lock's unlock secret
+//! is read from the secure store and the app unlocks through
+//! [`crate::lock`], exactly as with the passphrase; biometrics are never a
+//! separate way in. Elsewhere biometrics are [`BiometricResult::Unavailable`].
+
+use crate::error::CommandError;
+use crate::lock::{self, LockState};
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+/// Text shown in the OS prompt
+#[cfg_attr(
+    not(any(target_os = "android", target_os = "macos", target_os = "windows")),
+    allow(dead_code)
+)]
+const PROMPT_REASON: &str = "Unlock Hush";
+
+/// Outcome of a biometric check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BiometricResult {
+    Success,
+    /// The user dismissed the prompt or chose the passphrase instead
+    UserCancel,
+    /// No biometric hardware, nothing enrolled, or not supported here
+    Unavailable,
+    /// Too many failed reads; the OS wants the device passcode first
+    Lockout,
+}
+
+impl BiometricResult {
+    /// Map the `BiometricHelper` result codes.
+    #[cfg_attr(not(target_os = "android"), allow(dead_code))]
+    fn from_android(code: i32) -> Self {
+        match code {
+            0 => BiometricResult::Success,
+            1 => BiometricResult::UserCancel,
+            3 => BiometricResult::Lockout,
+            _ => BiometricResult::Unavailable,
+        }
+    }
+
+    /// Map an `LAError` code from LocalAuthentication.
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+    fn from_la_error(code: isize) -> Self {
+        match code {
+            // userCancel, userFallback, systemCancel, appCancel
+            -2 | -3 | -4 | -9 => BiometricResult::UserCancel,
+            // biometryLockout
+            -8 => BiometricResult::Lockout,
+            _ => BiometricResult::Unavailable,
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::{BiometricResult, PROMPT_REASON};
+    use block2::RcBlock;
+    use objc2::runtime::Bool;
+    use objc2_foundation::{NSError, NSString};
+    use objc2_local_authentication::{LAContext, LAPolicy};
+    use std::sync::mpsc;
+    use tauri::AppHandle;
+
+    const POLICY: LAPolicy = LAPolicy::DeviceOwnerAuthenticationWithBiometrics;
+
+    pub fn available() -> bool {
+        // SAFETY: plain LocalAuthentication calls on a fresh context
+        unsafe { LAContext::new().canEvaluatePolicy_error(POLICY).is_ok() }
+    }
+
+    pub fn authenticate(_app: &AppHandle) -> BiometricResult {
+        let context = unsafe { LAContext::new() };
+        if let Err(error) = unsafe { context.canEvaluatePolicy_error(POLICY) } {
+            return BiometricResult::from_la_error(error.code());
+        }
+        let (sender, receiver) = mpsc::channel();
+        let reply = RcBlock::new(move |success: Bool, error: *mut NSError| {
+            let result = if success.as_bool() {
+                BiometricResult::Success
+            } else {
+                // SAFETY: LocalAuthentication passes a valid error or null
+                unsafe { error.as_ref() }.map_or(BiometricResult::UserCancel, |error| {
+                    BiometricResult::from_la_error(error.code())
+                })
+            };
+            let _ = sender.send(result);
+        });
+        // SAFETY: the reply block is called exactly once, on a private queue
+        unsafe {
+            context.evaluatePolicy_localizedReason_reply(
+                POLICY,
+                &NSString::from_str(PROMPT_REASON),
+                &reply,
+            );
+        }
+        receiver.recv().unwrap_or(BiometricResult::UserCancel)
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::{BiometricResult, PROMPT_REASON};
+    use tauri::{AppHandle, Manager};
+    use windows::core::{factory, HSTRING};
+    use windows::Foundation::IAsyncOperation;
+    use windows::Security::Credentials::UI::{
+        UserConsentVerificationResult, UserConsentVerifier, UserConsentVerifierAvailability,
+    };
+    use windows::Win32::Foundation::HWND;
+    use windows::Win32::System::WinRT::IUserConsentVerifierInterop;
+
+    pub fn available() -> bool {
+        UserConsentVerifier::CheckAvailabilityAsync()
+            .and_then(|operation| operation.get())
+            .is_ok_and(|availability| availability == UserConsentVerifierAvailability::Available)
+    }
+
+    /// Show the prompt owned by `window`. `RequestVerificationAsync` has no
+    /// owner, so from a desktop app it can open behind the main window.
+    fn request_for_window(window: HWND) -> windows::core::Result<UserConsentVerificationResult> {
+        let interop = factory::<UserConsentVerifier, IUserConsentVerifierInterop>()?;
+        // SAFETY: `window` is the main window's live handle
+        let operation: IAsyncOperation<UserConsentVerificationResult> = unsafe {
+            interop.RequestVerificationForWindowAsync(window, &HSTRING::from(PROMPT_REASON))
+        }?;
+        operation.get()
+    }
+
+    pub fn authenticate(app: &AppHandle) -> BiometricResult {
+        let window = match app.get_webview_window("main").map(|window| window.hwnd()) {
+            Some(Ok(hwnd)) => HWND(hwnd.0 as _),
+            Some(Err(e)) => {
+                log::warn!("Windows Hello needs the main window handle: {}", e);
+                return BiometricResult::Unavailable;
+            }
+            None => {
+                log::warn!("Windows Hello needs the main window, which isn't open");
+                return BiometricResult::Unavailable;
+            }
+        };
+        match request_for_window(window) {
+            Ok(UserConsentVerificationResult::Verified) => BiometricResult::Success,
+            Ok(UserConsentVerificationResult::Canceled) => BiometricResult::UserCancel,
+            Ok(UserConsentVerificationResult::RetriesExhausted) => BiometricResult::Lockout,
+            Ok(_) => BiometricResult::Unavailable,
+            Err(e) => {
+                log::warn!("Windows Hello failed: {}", e);
+                BiometricResult::Unavailable
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "android")]
+mod platform {
+    use super::{BiometricResult, PROMPT_REASON};
+    use tauri::AppHandle;
+
+    pub fn available() -> bool {
+        crate::android::biometric_availability().is_ok_and(|code| code == 0)
+    }
+
+    pub fn authenticate(_app: &AppHandle) -> BiometricResult {
+        match crate::android::biometric_authenticate(PROMPT_REASON, "") {
+            Ok(code) => BiometricResult::from_android(code),
+            Err(e) => {
+                log::warn!("Biometric prompt failed: {}", e);
+                BiometricResult::Unavailable
+            }
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "android", target_os = "macos", target_os = "windows")))]
+mod platform {
+    use super::BiometricResult;
+    use tauri::AppHandle;
+
+    pub fn available() -> bool {
+        false
+    }
+
+    pub fn authenticate(_app: &AppHandle) -> BiometricResult {
+        BiometricResult::Unavailable
+    }
+}
+
+/// Whether the OS can verify the user with a biometric right now.
+#[tauri::command]
+pub async fn is_biometric_available() -> Result<bool, CommandError> {
+    Ok(tauri::async_runtime::spawn_blocking(platform::available)
+        .await
+        .map_err(|e| e.to_string())?)
+}
+
+/// Ask the OS to verify the user and, if it does, unlock the app lock. When
+/// the app isn't locked this still verifies, so it can confirm sensitive
+/// actions.
+#[tauri::command]
+pub async fn unlock_with_biometrics(app: AppHandle) -> Result<BiometricResult, CommandError> {
+    let handle = app.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || platform::authenticate(&handle))
+        .await
+        .map_err(|e| e.to_string())?;
+    if result == BiometricResult::Success && app.state::<LockState>().is_locked() {
+        lock::unlock_with_secret(&app)?;
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn results_serialize_in_kebab_case() {
+        assert_eq!(
+            serde_json::to_value(BiometricResult::UserCancel).unwrap(),
+            "user-cancel"
+        );
+        assert_eq!(
+            serde_json::to_value(BiometricResult::Lockout).unwrap(),
+            "lockout"
+        );
+    }
+
+    #[test]
+    fn native_codes_map_to_results() {
+        assert_eq!(BiometricResult::from_android(0), BiometricResult::Success);
+        assert_eq!(BiometricResult::from_android(3), BiometricResult::Lockout);
+        assert_eq!(
+            BiometricResult::from_android(42),
+            BiometricResult::Unavailable
+        );
+        assert_eq!(
+            BiometricResult::from_la_error(-2),
+            BiometricResult::UserCancel
+        );
+        assert_eq!(BiometricResult::from_la_error(-8), BiometricResult::Lockout);
+        assert_eq!(
+            BiometricResult::from_la_error(-7),
+            BiometricResult::Unavailable
+        );
+    }
+}
@@ -0,0 +1,764 @@
+//! Offline cache of feeds and posts in SQLite, so the app has something to
+//! show without a connection.
+//!
+//! The database is `cache.db` in the app data dir, opened once into
+//! [`CacheState`]. Posts are stored as the frontend's JSON; only the id and
+//! timestamp are pulled out for keys and ordering. The total size of cached
+//! posts is capped by the `cacheMaxBytes` setting (default
+//! [`DEFAULT_MAX_BYTES`]); when over, whole feeds are evicted, least recently
+//! used first. A corrupt database is moved aside and replaced with an empty one.
+//!
+//! Each feed also keeps an unread count, set by the frontend and by
+//! notifications, so the tray badge can be updated (e.g. by a notification's
+//! "Mark read" action) while the webview isn't running.
+//!
+//! The database is encrypted with SQLCipher when a key is available
+//! ([`crate::encrypted_store`]). A plaintext cache from an older build is
+//! copied into an encrypted one by [`init`] in the background, emitting
+//! `encryption-migration-progress` every [`MIGRATION_BATCH`] posts; cache
+//! calls wait until it is done.
+
+use crate::encrypted_store::{self, StoreKey};
+use crate::error::CommandError;
+use crate::fcm::now_unix_ms;
+use crate::lock::LockState;
+use crate::settings::SettingsState;
+use crate::storage;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use serde_json::Value;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+pub(crate) const CACHE_FILE: &str = "cache.db";
+/// Cache size cap used when `cacheMaxBytes` isn't set (50 MB)
+pub const DEFAULT_MAX_BYTES: u64 = 50 * 1024 * 1024;
+/// Posts copied per step when encrypting a plaintext cache
+pub const MIGRATION_BATCH: i64 = 500;
+/// First bytes of an unencrypted SQLite database
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS feeds (
+        feed_id TEXT PRIMARY KEY,
+        last_access INTEGER NOT NULL,
+        unread INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE TABLE IF NOT EXISTS posts (
+        feed_id TEXT NOT NULL REFERENCES feeds(feed_id) ON DELETE CASCADE,
+        post_id TEXT NOT NULL,
+        timestamp INTEGER NOT NULL,
+        data TEXT NOT NULL,
+        PRIMARY KEY (feed_id, post_id)
+    );
+    CREATE INDEX IF NOT EXISTS posts_by_time ON posts (feed_id, timestamp);
+";
+
+/// A cached feed, as returned by `cache_get_feeds`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CachedFeed {
+    pub feed_id: String,
+    pub post_count: u64,
+    /// Timestamp of the newest cached post
+    pub newest_timestamp: Option<i64>,
+    /// Unix timestamp (ms) the feed was last read or written
+    pub last_access: u64,
+    pub unread: u64,
+}
+
+/// Payload of `encryption-migration-progress`
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MigrationProgress {
+    copied: u64,
+    total: u64,
+    done: bool,
+}
+
+/// Managed state holding the cache database connection
+pub struct CacheState {
+    conn: Mutex<Connection>,
+    /// Plaintext cache waiting to be encrypted by [`init`]
+    pending_encryption: Mutex<Option<(PathBuf, StoreKey)>>,
+}
+
+fn open_at(path: &Path, key: Option<&StoreKey>) -> rusqlite::Result<Connection> {
+    let conn = Connection::open(path)?;
+    if let Some(key) = key {
+        key.apply(&conn)?;
+    }
+    init_schema(&conn)?;
+    Ok(conn)
+}
+
+/// How the database file is stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DbFile {
+    /// Missing or empty, so it can be created either way
+    Missing,
+    Plaintext,
+    /// Encrypted (or damaged)
+    Encrypted,
+}
+
+fn db_file(path: &Path) -> DbFile {
+    let mut header = [0u8; 16];
+    match std::fs::File::open(path).and_then(|mut file| file.read_exact(&mut header)) {
+        Ok(()) if &header == SQLITE_HEADER => DbFile::Plaintext,
+        Ok(()) => DbFile::Encrypted,
+        Err(_) => DbFile::Missing,
+    }
+}
+
+fn init_schema(conn: &Connection) -> rusqlite::Result<()> {
+    conn.pragma_update(None, "foreign_keys", true)?;
+    let status: String = conn.query_row("PRAGMA quick_check", [], |row| row.get(0))?;
+    if status != "ok" {
+        return Err(rusqlite::Error::SqliteFailure(
+            rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CORRUPT),
+            Some(status),
+        ));
+    }
+    conn.execute_batch(SCHEMA)?;
+    migrate(conn)
+}
+
+/// Bring a database created by an older build up to [`SCHEMA`].
+fn migrate(conn: &Connection) -> rusqlite::Result<()> {
+    let has_unread = conn
+        .prepare("SELECT 1 FROM pragma_table_info('feeds') WHERE name = 'unread'")?
+        .exists([])?;
+    if !has_unread {
+        conn.execute_batch("ALTER TABLE feeds ADD COLUMN unread INTEGER NOT NULL DEFAULT 0")?;
+    }
+    Ok(())
+}
+
+fn open_in_memory() -> Connection {
+    let conn = Connection::open_in_memory().expect("failed to open in-memory cache");
+    init_schema(&conn).expect("failed to create cache schema");
+    conn
+}
+
+/// Copies of unusable caches moved aside by [`open_or_recreate`], next to
+/// the cache at `path`.
+fn corrupt_copies(path: &Path) -> Vec<PathBuf> {
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return Vec::new();
+    };
+    let prefix = format!("{}.corrupt-", name.to_string_lossy());
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+                .map(|entry| entry.path())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Delete the copies of unusable caches. Returns the bytes freed.
+pub(crate) fn remove_corrupt_copies(app: &AppHandle) -> Result<u64, String> {
+    let path = storage::data_file(app, CACHE_FILE)?;
+    corrupt_copies(&path)
+        .iter()
+        .try_fold(0, |freed, copy| Ok(freed + storage::remove_all(copy)?))
+}
+
+/// Open the cache at `path`; if it can't be used, move it aside and start fresh.
+fn open_or_recreate(path: &Path, key: Option<&StoreKey>) -> Connection {
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    match open_at(path, key) {
+        Ok(conn) => return conn,
+        Err(e) => log::warn!("Offline cache is unusable, starting fresh: {}", e),
+    }
+
+    let mut aside = path.as_os_str().to_owned();
+    aside.push(format!(".corrupt-{}", now_unix_ms()));
+    if let Err(e) = std::fs::rename(path, &aside) {
+        log::warn!("Failed to move corrupt cache aside: {}", e);
+        let _ = std::fs::remove_file(path);
+    }
+    match open_at(path, key) {
+        Ok(conn) => conn,
+        Err(e) => {
+            log::warn!("Falling back to an in-memory cache: {}", e);
+            open_in_memory()
+        }
+    }
+}
+
+/// Copy the plaintext database at `plain` into a new encrypted one at
+/// `target`, a batch of posts at a time.
+fn copy_encrypted(
+    plain: &Path,
+    target: &Path,
+    key: &StoreKey,
+    progress: &mut impl FnMut(MigrationProgress),
+) -> rusqlite::Result<()> {
+    let conn = Connection::open(target)?;
+    key.apply(&conn)?;
+    conn.execute_batch(SCHEMA)?;
+    // An empty key attaches the old file unencrypted
+    conn.execute(
+        "ATTACH DATABASE ?1 AS plain KEY ''",
+        [plain.to_string_lossy().as_ref()],
+    )?;
+    let total: i64 = conn.query_row("SELECT COUNT(*) FROM plain.posts", [], |row| row.get(0))?;
+
+    conn.execute_batch(
+        "BEGIN;
+         INSERT INTO main.feeds (feed_id, last_access, unread)
+         SELECT feed_id, last_access, unread FROM plain.feeds;",
+    )?;
+    let mut copied = 0u64;
+    let mut after = 0i64;
+    loop {
+        let last: Option<i64> = conn.query_row(
+            "SELECT MAX(rowid) FROM (
+                 SELECT rowid FROM plain.posts WHERE rowid > ?1 ORDER BY rowid LIMIT ?2
+             )",
+            params![after, MIGRATION_BATCH],
+            |row| row.get(0),
+        )?;
+        let Some(last) = last else {
+            break;
+        };
+        copied += conn.execute(
+            "INSERT INTO main.posts (feed_id, post_id, timestamp, data)
+             SELECT feed_id, post_id, timestamp, data FROM plain.posts
+             WHERE rowid > ?1 AND rowid <= ?2",
+            params![after, last],
+        )? as u64;
+        after = last;
+        progress(MigrationProgress {
+            copied,
+            total: total as u64,
+            done: false,
+        });
+    }
+    conn.execute_batch("COMMIT; DETACH DATABASE plain;")?;
+    progress(MigrationProgress {
+        copied,
+        total: total as u64,
+        done: true,
+    });
+    Ok(())
+}
+
+/// Replace the plaintext cache at `path` with an encrypted copy.
+fn encrypt_plaintext(
+    path: &Path,
+    key: &StoreKey,
+    progress: &mut impl FnMut(MigrationProgress),
+) -> Result<(), String> {
+    // Bring an older schema up to date so the copy can name its columns
+    drop(open_at(path, None).map_err(|e| e.to_string())?);
+
+    let mut target = path.as_os_str().to_owned();
+    target.push(".encrypting");
+    let target = PathBuf::from(target);
+    storage::remove_file(&target)?;
+    if let Err(e) = copy_encrypted(path, &target, key, progress) {
+        let _ = std::fs::remove_file(&target);
+        return Err(e.to_string());
+    }
+    std::fs::rename(&target, path).map_err(|e| e.to_string())
+}
+
+/// Id and timestamp of a post object
+fn post_key(post: &Value) -> Option<(String, i64)> {
+    let id = post.get("id")?.as_str()?.to_string();
+    let timestamp = post.get("timestamp")?.as_i64()?;
+    Some((id, timestamp))
+}
+
+impl CacheState {
+    fn new(conn: Connection) -> Self {
+        Self {
+            conn: Mutex::new(conn),
+            pending_encryption: Mutex::new(None),
+        }
+    }
+
+    /// Open the cache in the app data dir. Must be managed after
+    /// [`encrypted_store::EncryptionState`].
+    pub fn load(app: &AppHandle) -> Self {
+        let path = match storage::data_file(app, CACHE_FILE) {
+            Ok(path) => path,
+            Err(e) => {
+                log::warn!("No app data dir for the offline cache: {}", e);
+                return Self::new(open_in_memory());
+            }
+        };
+        match (db_file(&path), encrypted_store::key(app)) {
+            (DbFile::Plaintext, Some(key)) => {
+                // Swapped for the encrypted copy by `init`
+                let state = Self::new(open_in_memory());
+                *state.pending_encryption() = Some((path, key));
+                state
+            }
+            (DbFile::Encrypted, None) => {
+                log::warn!("Offline cache is encrypted but the key can't be read, using memory");
+                Self::new(open_in_memory())
+            }
+            (_, key) => Self::new(open_or_recreate(&path, key.as_ref())),
+        }
+    }
+
+    fn pending_encryption(&self) -> MutexGuard<'_, Option<(PathBuf, StoreKey)>> {
+        self.pending_encryption
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn touch(conn: &Connection, feed_id: &str) -> rusqlite::Result<()> {
+        conn.execute(
+            "INSERT INTO feeds (feed_id, last_access) VALUES (?1, ?2)
+             ON CONFLICT(feed_id) DO UPDATE SET last_access = excluded.last_access",
+            params![feed_id, now_unix_ms() as i64],
+        )?;
+        Ok(())
+    }
+
+    /// Insert or replace posts in a feed, then evict feeds beyond `max_bytes`.
+    ///
+    /// Posts without a string `id` and numeric `timestamp` are skipped.
+    /// Returns the number of posts stored.
+    pub fn upsert_posts(
+        &self,
+        feed_id: &str,
+        posts: &[Value],
+        max_bytes: u64,
+    ) -> Result<usize, String> {
+        let mut conn = self.lock();
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        Self::touch(&tx, feed_id).map_err(|e| e.to_string())?;
+
+        let mut stored = 0;
+        {
+            let mut insert = tx
+                .prepare(
+                    "INSERT OR REPLACE INTO posts (feed_id, post_id, timestamp, data)
+                     VALUES (?1, ?2, ?3, ?4)",
+                )
+                .map_err(|e| e.to_string())?;
+            for post in posts {
+                let Some((post_id, timestamp)) = post_key(post) else {
+                    log::warn!("Not caching a post without id/timestamp in {}", feed_id);
+                    continue;
+                };
+                insert
+                    .execute(params![feed_id, post_id, timestamp, post.to_string()])
+                    .map_err(|e| e.to_string())?;
+                stored += 1;
+            }
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+
+        Self::enforce_limit(&conn, feed_id, max_bytes).map_err(|e| e.to_string())?;
+        Ok(stored)
+    }
+
+    /// Up to `limit` posts older than `before` (if given), newest first.
+    pub fn posts(
+        &self,
+        feed_id: &str,
+        limit: u32,
+        before: Option<i64>,
+    ) -> Result<Vec<Value>, String> {
+        let conn = self.lock();
+        let mut query = conn
+            .prepare(
+                "SELECT data FROM posts
+                 WHERE feed_id = ?1 AND timestamp < ?2
+                 ORDER BY timestamp DESC LIMIT ?3",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = query
+            .query_map(params![feed_id, before.unwrap_or(i64::MAX), limit], |row| {
+                row.get::<_, String>(0)
+            })
+            .map_err(|e| e.to_string())?;
+
+        let posts = rows
+            .filter_map(Result::ok)
+            .filter_map(|data| serde_json::from_str(&data).ok())
+            .collect::<Vec<Value>>();
+        if !posts.is_empty() {
+            Self::touch(&conn, feed_id).map_err(|e| e.to_string())?;
+        }
+        Ok(posts)
+    }
+
+    /// Timestamp of the newest cached post in a feed.
+    pub fn newest_timestamp(&self, feed_id: &str) -> Result<Option<i64>, String> {
+        self.lock()
+            .query_row(
+                "SELECT MAX(timestamp) FROM posts WHERE feed_id = ?1",
+                params![feed_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())
+    }
+
+    /// Every cached feed, most recently used first.
+    pub fn feeds(&self) -> Result<Vec<CachedFeed>, String> {
+        let conn = self.lock();
+        let mut query = conn
+            .prepare(
+                "SELECT f.feed_id, COUNT(p.post_id), MAX(p.timestamp), f.last_access, f.unread
+                 FROM feeds f LEFT JOIN posts p ON p.feed_id = f.feed_id
+                 GROUP BY f.feed_id ORDER BY f.last_access DESC",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = query
+            .query_map([], |row| {
+                Ok(CachedFeed {
+                    feed_id: row.get(0)?,
+                    post_count: row.get::<_, i64>(1)? as u64,
+                    newest_timestamp: row.get(2)?,
+                    last_access: row.get::<_, i64>(3)? as u64,
+                    unread: row.get::<_, i64>(4)? as u64,
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<rusqlite::Result<_>>()
+            .map_err(|e| e.to_string())
+    }
+
+    /// Set a feed's unread count, or add to it when `add` is true.
+    pub fn set_unread(&self, feed_id: &str, count: u64, add: bool) -> Result<(), String> {
+        let update = if add {
+            "unread = unread + excluded.unread"
+        } else {
+            "unread = excluded.unread"
+        };
+        self.lock()
+            .execute(
+                &format!(
+                    "INSERT INTO feeds (feed_id, last_access, unread) VALUES (?1, ?2, ?3)
+                     ON CONFLICT(feed_id) DO UPDATE SET {}",
+                    update
+                ),
+                params![feed_id, now_unix_ms() as i64, count as i64],
+            )
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    /// Unread count summed over every feed.
+    pub fn total_unread(&self) -> Result<u64, String> {
+        self.lock()
+            .query_row("SELECT COALESCE(SUM(unread), 0) FROM feeds", [], |row| {
+                row.get::<_, i64>(0)
+            })
+            .map(|total| total.max(0) as u64)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Drop every cached feed and post and shrink the database file.
+    pub fn clear(&self) -> Result<(), String> {
+        let conn = self.lock();
+        conn.execute_batch("DELETE FROM posts; DELETE FROM feeds; VACUUM;")
+            .map_err(|e| e.to_string())
+    }
+
+    /// Remove a feed and its posts.
+    pub fn evict(&self, feed_id: &str) -> Result<(), String> {
+        self.lock()
+            .execute("DELETE FROM feeds WHERE feed_id = ?1", params![feed_id])
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    fn total_bytes(conn: &Connection) -> rusqlite::Result<u64> {
+        conn.query_row(
+            "SELECT COALESCE(SUM(LENGTH(data)), 0) FROM posts",
+            [],
+            |row| row.get::<_, i64>(0),
+        )
+        .map(|bytes| bytes as u64)
+    }
+
+    /// Evict least recently used feeds other than `keep` until cached posts
+    /// fit in `max_bytes`, so one oversized feed still stays cached.
+    fn enforce_limit(conn: &Connection, keep: &str, max_bytes: u64) -> rusqlite::Result<()> {
+        while Self::total_bytes(conn)? > max_bytes {
+            let oldest: Option<String> = conn
+                .query_row(
+                    "SELECT feed_id FROM feeds WHERE feed_id != ?1
+                     ORDER BY last_access ASC LIMIT 1",
+                    params![keep],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            let Some(oldest) = oldest else {
+                break;
+            };
+            log::info!("Evicting feed {} from the offline cache", oldest);
+            conn.execute("DELETE FROM feeds WHERE feed_id = ?1", params![oldest])?;
+        }
+        Ok(())
+    }
+}
+
+/// Cache size cap from the `cacheMaxBytes` setting.
+pub(crate) fn max_bytes(app: &AppHandle) -> u64 {
+    app.state::<SettingsState>()
+        .get()
+        .cache_max_bytes
+        .unwrap_or(DEFAULT_MAX_BYTES)
+}
+
+/// Encrypt a plaintext cache left by an older build, in the background.
+/// Called from `setup` after [`CacheState`] is managed.
+pub fn init(app: &AppHandle) {
+    let Some((path, key)) = app.state::<CacheState>().pending_encryption().take() else {
+        return;
+    };
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let state = app.state::<CacheState>();
+        // Held throughout, so cache calls wait instead of using the placeholder
+        let mut conn = state.lock();
+        log::info!("Encrypting the offline cache");
+        let mut emit = |progress: MigrationProgress| {
+            let _ = app.emit("encryption-migration-progress", progress);
+        };
+        if let Err(e) = encrypt_plaintext(&path, &key, &mut emit) {
+            log::warn!("Failed to encrypt the offline cache, starting fresh: {}", e);
+            // Don't leave the plaintext behind
+            let _ = std::fs::remove_file(&path);
+        }
+        *conn = open_or_recreate(&path, Some(&key));
+    });
+}
+
+/// Store posts (a JSON array) in a feed's cache; returns how many were stored.
+#[tauri::command]
+pub fn cache_upsert_posts(
+    app: AppHandle,
+    state: State<'_, CacheState>,
+    feed_id: String,
+    posts_json: String,
+) -> Result<usize, String> {
+    let posts: Vec<Value> = serde_json::from_str(&posts_json).map_err(|e| e.to_string())?;
+    state.upsert_posts(&feed_id, &posts, max_bytes(&app))
+}
+
+/// Cached posts of a feed, newest first, optionally only those before `before_ts`.
+/// Fails while the app is locked.
+#[tauri::command]
+pub fn cache_get_posts(
+    state: State<'_, CacheState>,
+    lock: State<'_, LockState>,
+    feed_id: String,
+    limit: u32,
+    before_ts: Option<i64>,
+) -> Result<Vec<Value>, CommandError> {
+    lock.ensure_unlocked()?;
+    Ok(state.posts(&feed_id, limit, before_ts)?)
+}
+
+/// Cached feeds; fails while the app is locked.
+#[tauri::command]
+pub fn cache_get_feeds(
+    state: State<'_, CacheState>,
+    lock: State<'_, LockState>,
+) -> Result<Vec<CachedFeed>, CommandError> {
+    lock.ensure_unlocked()?;
+    Ok(state.feeds()?)
+}
+
+/// Set a feed's unread count, which `cache_get_feeds` reports and the tray
+/// badge falls back to.
+#[tauri::command]
+pub fn cache_set_unread(
+    state: State<'_, CacheState>,
+    feed_id: String,
+    count: u64,
+) -> Result<(), CommandError> {
+    Ok(state.set_unread(&feed_id, count, false)?)
+}
+
+#[tauri::command]
+pub fn cache_evict(state: State<'_, CacheState>, feed_id: String) -> Result<(), CommandError> {
+    Ok(state.evict(&feed_id)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn memory_cache() -> CacheState {
+        CacheState::new(open_in_memory())
+    }
+
+    fn post(id: &str, timestamp: i64) -> Value {
+        json!({"id": id, "feedId": "f", "content": "hello", "timestamp": timestamp})
+    }
+
+    #[test]
+    fn upsert_and_page_posts() {
+        let cache = memory_cache();
+        let posts = vec![
+            post("a", 1),
+            post("b", 2),
+            post("c", 3),
+            json!({"no": "id"}),
+        ];
+        assert_eq!(cache.upsert_posts("f", &posts, u64::MAX).unwrap(), 3);
+        cache.upsert_posts("f", &[post("b", 2)], u64::MAX).unwrap();
+
+        let ids = |posts: Vec<Value>| -> Vec<String> {
+            posts
+                .iter()
+                .map(|p| p["id"].as_str().unwrap().to_string())
+                .collect()
+        };
+        assert_eq!(
+            ids(cache.posts("f", 10, None).unwrap()),
+            vec!["c", "b", "a"]
+        );
+        assert_eq!(ids(cache.posts("f", 1, Some(3)).unwrap()), vec!["b"]);
+
+        assert_eq!(cache.newest_timestamp("f").unwrap(), Some(3));
+        assert_eq!(cache.newest_timestamp("other").unwrap(), None);
+
+        let feeds = cache.feeds().unwrap();
+        assert_eq!(feeds.len(), 1);
+        assert_eq!(feeds[0].post_count, 3);
+        assert_eq!(feeds[0].newest_timestamp, Some(3));
+    }
+
+    #[test]
+    fn unread_counts_are_set_added_and_summed() {
+        let cache = memory_cache();
+        cache.upsert_posts("a", &[post("p", 1)], u64::MAX).unwrap();
+        cache.set_unread("a", 2, false).unwrap();
+        cache.set_unread("a", 1, true).unwrap();
+        cache.set_unread("b", 4, true).unwrap();
+        assert_eq!(cache.total_unread().unwrap(), 7);
+
+        cache.set_unread("a", 0, false).unwrap();
+        assert_eq!(cache.total_unread().unwrap(), 4);
+        let feeds = cache.feeds().unwrap();
+        let a = feeds.iter().find(|feed| feed.feed_id == "a").unwrap();
+        assert_eq!((a.unread, a.post_count), (0, 1));
+    }
+
+    #[test]
+    fn old_databases_gain_the_unread_column() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE feeds (feed_id TEXT PRIMARY KEY, last_access INTEGER NOT NULL)",
+        )
+        .unwrap();
+        init_schema(&conn).unwrap();
+        init_schema(&conn).unwrap();
+        let cache = CacheState::new(conn);
+        cache.set_unread("f", 3, false).unwrap();
+        assert_eq!(cache.total_unread().unwrap(), 3);
+    }
+
+    #[test]
+    fn evict_removes_feed_and_posts() {
+        let cache = memory_cache();
+        cache.upsert_posts("f", &[post("a", 1)], u64::MAX).unwrap();
+        cache.evict("f").unwrap();
+        assert!(cache.feeds().unwrap().is_empty());
+        assert!(cache.posts("f", 10, None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn size_cap_evicts_least_recently_used_feed() {
+        let cache = memory_cache();
+        let one_post = post("a", 1).to_string().len() as u64;
+        cache
+            .upsert_posts("old", &[post("a", 1)], u64::MAX)
+            .unwrap();
+        cache
+            .lock()
+            .execute("UPDATE feeds SET last_access = 0 WHERE feed_id = 'old'", [])
+            .unwrap();
+        cache
+            .upsert_posts("new", &[post("a", 1)], one_post)
+            .unwrap();
+
+        let feeds: Vec<String> = cache
+            .feeds()
+            .unwrap()
+            .into_iter()
+            .map(|f| f.feed_id)
+            .collect();
+        assert_eq!(feeds, vec!["new"]);
+
+        // A single feed over the cap is kept
+        cache
+            .upsert_posts("new", &[post("b", 2)], one_post)
+            .unwrap();
+        assert_eq!(cache.posts("new", 10, None).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn plaintext_cache_is_encrypted_in_place() {
+        let dir = std::env::temp_dir().join(format!("hush-cache-enc-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(CACHE_FILE);
+        let plain = CacheState::new(open_at(&path, None).unwrap());
+        let posts: Vec<Value> = (0..MIGRATION_BATCH + 5)
+            .map(|i| post(&i.to_string(), i))
+            .collect();
+        plain.upsert_posts("f", &posts, u64::MAX).unwrap();
+        plain.set_unread("f", 2, false).unwrap();
+        drop(plain);
+        assert_eq!(db_file(&path), DbFile::Plaintext);
+
+        let key = StoreKey::generate();
+        let mut reports = Vec::new();
+        encrypt_plaintext(&path, &key, &mut |progress| reports.push(progress)).unwrap();
+        assert_eq!(db_file(&path), DbFile::Encrypted);
+        assert_eq!(reports.len(), 3);
+        assert!(reports[2].done && reports[2].copied == reports[2].total);
+
+        let cache = CacheState::new(open_at(&path, Some(&key)).unwrap());
+        assert_eq!(cache.feeds().unwrap()[0].post_count, posts.len() as u64);
+        assert_eq!(cache.total_unread().unwrap(), 2);
+        assert!(open_at(&path, None).is_err());
+
+        drop(cache);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn corrupt_database_is_moved_aside() {
+        let dir = std::env::temp_dir().join(format!("hush-cache-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(CACHE_FILE);
+        std::fs::write(
+            &path,
+            b"definitely not a sqlite database, just garbage bytes",
+        )
+        .unwrap();
+
+        let cache = CacheState::new(open_or_recreate(&path, None));
+        cache.upsert_posts("f", &[post("a", 1)], u64::MAX).unwrap();
+        let copies = corrupt_copies(&path);
+        assert_eq!(copies.len(), 1);
+        assert!(copies[0].exists());
+
+        drop(cache);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
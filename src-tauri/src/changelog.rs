@@ -0,0 +1,210 @@
+//! Release notes for updates, cached in `changelog.json` in the app data dir
+//! keyed by version.
+//!
+//! Each update check records the notes from the update manifest and, on a
+//! best-effort basis, the channel's companion `changelog.json` (an array of
+//! `{version, notes}`), which also covers releases a user skipped.
+//! `get_changelog` joins the notes of every version newer than the running
+//! one into a single markdown document.
+
+use crate::logging::redact;
+use crate::net;
+use crate::storage;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+const CHANGELOG_FILE: &str = "changelog.json";
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// One release's notes, as listed in a companion changelog
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangelogEntry {
+    pub version: String,
+    /// Markdown
+    pub notes: String,
+}
+
+/// Managed state holding the cached notes
+#[derive(Debug, Default)]
+pub struct ChangelogState {
+    notes: Mutex<BTreeMap<String, String>>,
+    /// Backing file; `None` keeps the cache in memory only
+    path: Option<PathBuf>,
+}
+
+impl ChangelogState {
+    pub fn load(app: &AppHandle) -> Self {
+        let path = storage::data_file(app, CHANGELOG_FILE).ok();
+        let notes = path
+            .as_deref()
+            .and_then(storage::read_json)
+            .unwrap_or_default();
+        Self {
+            notes: Mutex::new(notes),
+            path,
+        }
+    }
+
+    /// Cache notes per version, skipping empty ones and unparseable versions.
+    fn record(&self, entries: impl IntoIterator<Item = ChangelogEntry>) {
+        let mut notes = self.notes.lock().unwrap_or_else(|e| e.into_inner());
+        let mut changed = false;
+        for entry in entries {
+            let notes_text = entry.notes.trim();
+            if notes_text.is_empty() || Version::parse(&entry.version).is_err() {
+                continue;
+            }
+            if notes.get(&entry.version).map(String::as_str) != Some(notes_text) {
+                notes.insert(entry.version, notes_text.to_string());
+                changed = true;
+            }
+        }
+        if let (true, Some(path)) = (changed, &self.path) {
+            if let Err(e) = storage::write_json_atomic(path, &*notes) {
+                log::warn!("Failed to save changelog cache: {}", e);
+            }
+        }
+    }
+
+    pub fn notes(&self, version: &str) -> Option<String> {
+        self.notes
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(version)
+            .cloned()
+    }
+
+    /// Cached entries newer than `since`, newest first.
+    fn entries_since(&self, since: &Version) -> Vec<ChangelogEntry> {
+        let notes = self.notes.lock().unwrap_or_else(|e| e.into_inner());
+        let mut entries: Vec<(Version, ChangelogEntry)> = notes
+            .iter()
+            .filter_map(|(version, text)| {
+                let parsed = Version::parse(version).ok()?;
+                (parsed > *since).then(|| {
+                    (
+                        parsed,
+                        ChangelogEntry {
+                            version: version.clone(),
+                            notes: text.clone(),
+                        },
+                    )
+                })
+            })
+            .collect();
+        entries.sort_by(|a, b| b.0.cmp(&a.0));
+        entries.into_iter().map(|(_, entry)| entry).collect()
+    }
+}
+
+fn to_markdown(entries: &[ChangelogEntry]) -> String {
+    entries
+        .iter()
+        .map(|entry| format!("## {}\n\n{}", entry.version, entry.notes))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+async fn fetch(app: &AppHandle, url: &str) -> Result<Vec<ChangelogEntry>, String> {
+    net::client(app)
+        .get(url)
+        .timeout(FETCH_TIMEOUT)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.to_string())?
+        .json()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Cache the notes for `version` and, if `companion_url` is given, all notes
+/// listed there. Returns the notes for `version`. Never fails; fetch errors
+/// are logged.
+pub async fn refresh(
+    app: &AppHandle,
+    version: &str,
+    manifest_notes: Option<&str>,
+    companion_url: Option<&str>,
+) -> Option<String> {
+    let state = app.state::<ChangelogState>();
+    if let Some(notes) = manifest_notes {
+        state.record([ChangelogEntry {
+            version: version.to_string(),
+            notes: notes.to_string(),
+        }]);
+    }
+    if let Some(url) = companion_url {
+        match fetch(app, url).await {
+            Ok(entries) => state.record(entries),
+            Err(e) => log::warn!("Failed to fetch changelog: {}", redact(&e.to_string())),
+        }
+    }
+    state.notes(version)
+}
+
+/// Release notes for every cached version newer than `since_version`
+/// (default: the running version), newest first, as markdown.
+#[tauri::command]
+pub fn get_changelog(
+    app: AppHandle,
+    state: State<'_, ChangelogState>,
+    since_version: Option<String>,
+) -> Result<String, String> {
+    let since = match since_version {
+        Some(version) => Version::parse(version.trim_start_matches(['v', 'V']))
+            .map_err(|e| format!("Invalid version {}: {}", version, e))?,
+        None => app.package_info().version.clone(),
+    };
+    Ok(to_markdown(&state.entries_since(&since)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(version: &str, notes: &str) -> ChangelogEntry {
+        ChangelogEntry {
+            version: version.to_string(),
+            notes: notes.to_string(),
+        }
+    }
+
+    #[test]
+    fn changelog_covers_versions_after_since() {
+        let state = ChangelogState::default();
+        state.record([
+            entry("0.2.15", "Current"),
+            entry("0.3.0", "Threads"),
+            entry("0.10.0", "Calls"),
+            entry("0.2.9", "Old"),
+        ]);
+        let since = Version::parse("0.2.15").unwrap();
+        let versions: Vec<_> = state
+            .entries_since(&since)
+            .into_iter()
+            .map(|entry| entry.version)
+            .collect();
+        assert_eq!(versions, ["0.10.0", "0.3.0"]);
+        assert_eq!(
+            to_markdown(&state.entries_since(&since)),
+            "## 0.10.0\n\nCalls\n\n## 0.3.0\n\nThreads"
+        );
+    }
+
+    #[test]
+    fn empty_notes_and_bad_versions_are_not_cached() {
+        let state = ChangelogState::default();
+        state.record([entry("0.3.0", "  "), entry("latest", "Notes")]);
+        assert_eq!(state.notes("0.3.0"), None);
+        assert_eq!(state.notes("latest"), None);
+
+        state.record([entry("0.3.0", "Threads\n")]);
+        assert_eq!(state.notes("0.3.0").as_deref(), Some("Threads"));
+    }
+}
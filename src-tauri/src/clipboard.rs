@@ -0,0 +1,245 @@
+//! Clipboard copies of secrets (invite codes, recovery phrases) that wipe
+//! themselves.
+//!
+//! `copy_sensitive` writes through the clipboard plugin and schedules a wipe.
+//! When it fires, the clipboard is cleared only if it still holds the copied
+//! text, so something the user copied since is left alone; where the
+//! clipboard can't be read back (Wayland without focus, macOS privacy
+//! prompts) it is cleared without the check. Each wipe emits
+//! `clipboard-cleared`. A new `copy_sensitive` cancels the pending wipe.
+//!
+//! `get_clipboard_image` reads a pasted image on desktop (through `arboard`,
+//! since the webview can't reliably) and saves it as PNG under
+//! `attachments/` in the app cache dir for the composer to attach.
+//! `clear_temp_attachments` empties that directory, which also holds
+//! [`crate::file_drop`] thumbnails.
+
+use serde::Serialize;
+#[cfg(desktop)]
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+const ATTACHMENT_DIR: &str = "attachments";
+/// Largest clipboard image accepted, as decoded RGBA (50 MB)
+const MAX_IMAGE_BYTES: u64 = 50 * 1024 * 1024;
+
+/// An image saved from the clipboard
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ClipboardImage {
+    pub path: String,
+    pub width: u32,
+    pub height: u32,
+    /// Size of the PNG file
+    pub size: u64,
+}
+
+/// Why `get_clipboard_image` failed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum ClipboardImageError {
+    /// The clipboard holds no image; not worth reporting to the user
+    NoImage(String),
+    TooLarge(String),
+    Unsupported(String),
+    Io(String),
+    Other(String),
+}
+
+impl From<std::io::Error> for ClipboardImageError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+/// Managed state holding the pending wipe
+#[derive(Default)]
+pub struct ClipboardState {
+    pending: Mutex<Option<JoinHandle<()>>>,
+}
+
+/// Whether the wipe should go ahead given what reading the clipboard back
+/// returned.
+fn should_clear<E: std::fmt::Display>(current: Result<String, E>, copied: &str) -> bool {
+    match current {
+        Ok(current) => current == copied,
+        Err(e) => {
+            log::debug!("Clipboard not readable, clearing unchecked: {}", e);
+            true
+        }
+    }
+}
+
+async fn clear_later(app: AppHandle, copied: String, delay: Duration) {
+    tokio::time::sleep(delay).await;
+    if !should_clear(app.clipboard().read_text(), &copied) {
+        log::debug!("Clipboard changed since sensitive copy, leaving it");
+        return;
+    }
+    match app.clipboard().clear() {
+        Ok(()) => {
+            let _ = app.emit("clipboard-cleared", ());
+        }
+        Err(e) => log::warn!("Failed to clear clipboard: {}", e),
+    }
+}
+
+/// Copy `text` and clear it from the clipboard after `clear_after_secs`.
+#[tauri::command]
+pub fn copy_sensitive(
+    app: AppHandle,
+    state: State<'_, ClipboardState>,
+    text: String,
+    clear_after_secs: u32,
+) -> Result<(), String> {
+    if clear_after_secs == 0 {
+        return Err("clear_after_secs must be at least 1".to_string());
+    }
+    let mut pending = state.pending.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(task) = pending.take() {
+        task.abort();
+    }
+    app.clipboard()
+        .write_text(text.clone())
+        .map_err(|e| e.to_string())?;
+    *pending = Some(tauri::async_runtime::spawn(clear_later(
+        app.clone(),
+        text,
+        Duration::from_secs(u64::from(clear_after_secs)),
+    )));
+    Ok(())
+}
+
+pub(crate) fn attachment_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_cache_dir()
+        .map(|dir| dir.join(ATTACHMENT_DIR))
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(desktop)]
+fn check_size(width: usize, height: usize) -> Result<(), ClipboardImageError> {
+    let decoded = (width as u64)
+        .saturating_mul(height as u64)
+        .saturating_mul(4);
+    if decoded > MAX_IMAGE_BYTES {
+        return Err(ClipboardImageError::TooLarge(format!(
+            "{}x{} image exceeds {} MB",
+            width,
+            height,
+            MAX_IMAGE_BYTES / (1024 * 1024)
+        )));
+    }
+    Ok(())
+}
+
+/// Encode 8-bit RGBA pixels as a PNG file.
+#[cfg(desktop)]
+fn write_png(path: &Path, width: u32, height: u32, rgba: &[u8]) -> Result<(), String> {
+    let file = std::fs::File::create(path).map_err(|e| e.to_string())?;
+    let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    writer.write_image_data(rgba).map_err(|e| e.to_string())?;
+    writer.finish().map_err(|e| e.to_string())
+}
+
+#[cfg(desktop)]
+fn read_clipboard_image(dir: &Path) -> Result<ClipboardImage, ClipboardImageError> {
+    let image = arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.get_image())
+        .map_err(|e| match e {
+            arboard::Error::ContentNotAvailable => ClipboardImageError::NoImage(e.to_string()),
+            e => ClipboardImageError::Other(e.to_string()),
+        })?;
+    check_size(image.width, image.height)?;
+    let (Ok(width), Ok(height)) = (u32::try_from(image.width), u32::try_from(image.height)) else {
+        return Err(ClipboardImageError::TooLarge(
+            "Image dimensions too large".to_string(),
+        ));
+    };
+
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(format!("clipboard-{}.png", uuid::Uuid::new_v4()));
+    if let Err(e) = write_png(&path, width, height, &image.bytes) {
+        let _ = std::fs::remove_file(&path);
+        return Err(ClipboardImageError::Other(format!(
+            "Failed to encode PNG: {}",
+            e
+        )));
+    }
+    Ok(ClipboardImage {
+        size: std::fs::metadata(&path)?.len(),
+        path: path.to_string_lossy().into_owned(),
+        width,
+        height,
+    })
+}
+
+/// Save the image on the clipboard as a temporary PNG.
+#[tauri::command]
+pub async fn get_clipboard_image(app: AppHandle) -> Result<ClipboardImage, ClipboardImageError> {
+    let dir = attachment_dir(&app).map_err(ClipboardImageError::Io)?;
+    #[cfg(desktop)]
+    {
+        tauri::async_runtime::spawn_blocking(move || read_clipboard_image(&dir))
+            .await
+            .map_err(|e| ClipboardImageError::Other(e.to_string()))?
+    }
+    #[cfg(mobile)]
+    {
+        let _ = dir;
+        Err(ClipboardImageError::Unsupported(
+            "Clipboard images are not supported on this platform".to_string(),
+        ))
+    }
+}
+
+/// Delete the temporary files saved by `get_clipboard_image`.
+#[tauri::command]
+pub fn clear_temp_attachments(app: AppHandle) -> Result<(), String> {
+    let dir = attachment_dir(&app)?;
+    match std::fs::remove_dir_all(&dir) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("Failed to clear {}: {}", dir.display(), e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(desktop)]
+    #[test]
+    fn oversized_images_are_rejected() {
+        assert!(check_size(1920, 1080).is_ok());
+        assert!(matches!(
+            check_size(8000, 8000),
+            Err(ClipboardImageError::TooLarge(_))
+        ));
+    }
+
+    #[cfg(desktop)]
+    #[test]
+    fn png_is_written() {
+        let path =
+            std::env::temp_dir().join(format!("hush-clipboard-{}.png", uuid::Uuid::new_v4()));
+        write_png(&path, 2, 1, &[255, 0, 0, 255, 0, 0, 255, 128]).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(&bytes[..8], b"\x89PNG\r\n\x1a\n");
+    }
+
+    #[test]
+    fn clears_only_our_own_copy_unless_unreadable() {
+        assert!(should_clear::<String>(Ok("code".to_string()), "code"));
+        assert!(!should_clear::<String>(Ok("other".to_string()), "code"));
+        assert!(should_clear(Err("not permitted"), "code"));
+    }
+}
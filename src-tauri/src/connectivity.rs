@@ -0,0 +1,325 @@
+//! Connectivity watcher, since `navigator.onLine` isn't reliable in the webview.
+//!
+//! A task spawned in `setup` probes the Hush server (the `serverUrl` setting)
+//! every [`PROBE_INTERVAL`], and right away when [`ConnectivityState::probe_now`]
+//! is called (on app resume). A probe that gets any answer from the server
+//! means online; a redirect to another host means a captive portal. A changed
+//! result has to be seen [`CONFIRMATIONS`] times in a row before the status
+//! flips, so one lost probe doesn't flap the UI. Transitions are emitted as
+//! `connectivity-changed`; coming back online also nudges the outbox, live
+//! stream and push stream to retry.
+//!
+//! [`is_metered`] tells background work such as update downloads whether the
+//! connection is metered. Only NetworkManager on Linux is asked; elsewhere
+//! connections count as unmetered.
+
+use crate::live_stream::LiveStreamState;
+use crate::logging::redact;
+use crate::net;
+use crate::outbox::{self, OutboxState};
+use crate::settings::SettingsState;
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State, Url};
+use tokio::sync::Notify;
+
+const PROBE_INTERVAL: Duration = Duration::from_secs(30);
+/// Delay before re-probing to confirm a changed result
+const CONFIRM_INTERVAL: Duration = Duration::from_secs(5);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Consecutive probes that must agree before the status changes
+const CONFIRMATIONS: u32 = 2;
+
+/// Network reachability of the Hush server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Connectivity {
+    Online,
+    Offline,
+    /// Requests are intercepted, typically by a hotel or train Wi-Fi login page
+    Captive,
+}
+
+/// Turns raw probe results into debounced status changes
+#[derive(Debug)]
+struct Debouncer {
+    current: Option<Connectivity>,
+    candidate: Option<(Connectivity, u32)>,
+}
+
+impl Debouncer {
+    fn new() -> Self {
+        Self {
+            current: None,
+            candidate: None,
+        }
+    }
+
+    /// Record a probe result; returns the new status if it changed.
+    ///
+    /// The first result is taken as-is.
+    fn observe(&mut self, result: Connectivity) -> Option<Connectivity> {
+        if self.current.is_none() || self.current == Some(result) {
+            self.candidate = None;
+            let changed = self.current.is_none();
+            self.current = Some(result);
+            return changed.then_some(result);
+        }
+
+        let seen = match self.candidate {
+            Some((candidate, seen)) if candidate == result => seen + 1,
+            _ => 1,
+        };
+        if seen >= CONFIRMATIONS {
+            self.candidate = None;
+            self.current = Some(result);
+            Some(result)
+        } else {
+            self.candidate = Some((result, seen));
+            None
+        }
+    }
+
+    fn is_confirming(&self) -> bool {
+        self.candidate.is_some()
+    }
+}
+
+/// Managed state holding the current connectivity
+pub struct ConnectivityState {
+    status: Mutex<Debouncer>,
+    probe: Notify,
+}
+
+impl Default for ConnectivityState {
+    fn default() -> Self {
+        Self {
+            status: Mutex::new(Debouncer::new()),
+            probe: Notify::new(),
+        }
+    }
+}
+
+impl ConnectivityState {
+    /// Current status; online until the first probe says otherwise.
+    pub fn get(&self) -> Connectivity {
+        self.status
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .current
+            .unwrap_or(Connectivity::Online)
+    }
+
+    /// Probe now instead of waiting for the next interval.
+    pub fn probe_now(&self) {
+        self.probe.notify_one();
+    }
+}
+
+/// Classify a probe response from `server`.
+fn classify_response(server: &Url, status: u16, location: Option<&str>) -> Connectivity {
+    if !(300..400).contains(&status) {
+        return Connectivity::Online;
+    }
+    let target = location.and_then(|location| server.join(location).ok());
+    match target {
+        Some(target) if target.host_str() != server.host_str() => Connectivity::Captive,
+        _ => Connectivity::Online,
+    }
+}
+
+async fn probe(client: &reqwest::Client, server: &Url) -> Connectivity {
+    match client.head(server.clone()).send().await {
+        Ok(response) => {
+            let location = response
+                .headers()
+                .get(reqwest::header::LOCATION)
+                .and_then(|value| value.to_str().ok());
+            classify_response(server, response.status().as_u16(), location)
+        }
+        Err(e) => {
+            log::debug!("Connectivity probe failed: {}", e);
+            Connectivity::Offline
+        }
+    }
+}
+
+/// Client for probes: the shared network settings, but without following redirects.
+fn probe_client(app: &AppHandle) -> Option<reqwest::Client> {
+    net::builder(app)
+        .and_then(|builder| {
+            builder
+                .redirect(reqwest::redirect::Policy::none())
+                .timeout(PROBE_TIMEOUT)
+                .build()
+                .map_err(|e| e.to_string())
+        })
+        .map_err(|e| log::warn!("Connectivity probe client unavailable: {}", e))
+        .ok()
+}
+
+fn server_url(app: &AppHandle) -> Option<Url> {
+    let url = app
+        .state::<SettingsState>()
+        .get()
+        .server_url
+        .unwrap_or_else(|| outbox::DEFAULT_SERVER_URL.to_string());
+    match Url::parse(&url) {
+        Ok(url) => Some(url),
+        Err(e) => {
+            log::warn!("Invalid server URL {}: {}", redact(&url), e);
+            None
+        }
+    }
+}
+
+fn on_change(app: &AppHandle, status: Connectivity) {
+    log::info!("Connectivity changed: {:?}", status);
+    let _ = app.emit("connectivity-changed", status);
+    if status == Connectivity::Online {
+        app.state::<OutboxState>().resume();
+        app.state::<LiveStreamState>().resume();
+        #[cfg(desktop)]
+        if let Some(push) = app.try_state::<crate::push_stream::PushStreamState>() {
+            push.resume();
+        }
+    }
+}
+
+async fn run(app: AppHandle) {
+    let state = app.state::<ConnectivityState>();
+
+    loop {
+        let mut confirming = false;
+        if let (Some(server), Some(client)) = (server_url(&app), probe_client(&app)) {
+            let result = probe(&client, &server).await;
+            let mut debouncer = state.status.lock().unwrap_or_else(|e| e.into_inner());
+            let changed = debouncer.observe(result);
+            confirming = debouncer.is_confirming();
+            drop(debouncer);
+            if let Some(status) = changed {
+                on_change(&app, status);
+            }
+        }
+
+        let delay = if confirming {
+            CONFIRM_INTERVAL
+        } else {
+            PROBE_INTERVAL
+        };
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = state.probe.notified() => {}
+        }
+    }
+}
+
+/// Spawn the watcher. Called from `setup` after [`ConnectivityState`] is managed.
+pub fn init(app: &AppHandle) {
+    tauri::async_runtime::spawn(run(app.clone()));
+}
+
+/// Whether the OS reports the active connection as metered. Blocking.
+pub fn is_metered() -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        std::process::Command::new("gdbus")
+            .args([
+                "call",
+                "--system",
+                "--dest",
+                "org.freedesktop.NetworkManager",
+                "--object-path",
+                "/org/freedesktop/NetworkManager",
+                "--method",
+                "org.freedesktop.DBus.Properties.Get",
+                "org.freedesktop.NetworkManager",
+                "Metered",
+            ])
+            .output()
+            .ok()
+            .and_then(|output| parse_nm_metered(&String::from_utf8_lossy(&output.stdout)))
+            .unwrap_or(false)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        false
+    }
+}
+
+/// Parse NetworkManager's `Metered` property as printed by gdbus, e.g.
+/// `(<uint32 4>,)`. 1 and 3 are "yes" and "guessed yes".
+#[cfg(target_os = "linux")]
+fn parse_nm_metered(output: &str) -> Option<bool> {
+    let value: u32 = output
+        .split("uint32")
+        .nth(1)?
+        .trim_start()
+        .split(|c: char| !c.is_ascii_digit())
+        .next()?
+        .parse()
+        .ok()?;
+    Some(matches!(value, 1 | 3))
+}
+
+#[tauri::command]
+pub fn get_connectivity(state: State<'_, ConnectivityState>) -> Connectivity {
+    state.get()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_result_is_taken_immediately() {
+        let mut debouncer = Debouncer::new();
+        assert_eq!(
+            debouncer.observe(Connectivity::Offline),
+            Some(Connectivity::Offline)
+        );
+        assert_eq!(debouncer.observe(Connectivity::Offline), None);
+    }
+
+    #[test]
+    fn single_failed_probe_does_not_flip() {
+        let mut debouncer = Debouncer::new();
+        debouncer.observe(Connectivity::Online);
+
+        assert_eq!(debouncer.observe(Connectivity::Offline), None);
+        assert!(debouncer.is_confirming());
+        assert_eq!(debouncer.observe(Connectivity::Online), None);
+        assert!(!debouncer.is_confirming());
+
+        assert_eq!(debouncer.observe(Connectivity::Offline), None);
+        assert_eq!(
+            debouncer.observe(Connectivity::Offline),
+            Some(Connectivity::Offline)
+        );
+    }
+
+    #[test]
+    fn redirect_to_another_host_is_captive() {
+        let server = Url::parse("https://chat.hushnetwork.social").unwrap();
+        assert_eq!(classify_response(&server, 200, None), Connectivity::Online);
+        assert_eq!(classify_response(&server, 404, None), Connectivity::Online);
+        assert_eq!(
+            classify_response(&server, 302, Some("/login")),
+            Connectivity::Online
+        );
+        assert_eq!(
+            classify_response(&server, 302, Some("http://portal.example/login")),
+            Connectivity::Captive
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn network_manager_metered_values() {
+        assert_eq!(parse_nm_metered("(<uint32 1>,)\n"), Some(true));
+        assert_eq!(parse_nm_metered("(<uint32 3>,)"), Some(true));
+        assert_eq!(parse_nm_metered("(<uint32 4>,)"), Some(false));
+        assert_eq!(parse_nm_metered(""), None);
+    }
+}
@@ -0,0 +1,236 @@
+//! Crash reports from Rust panics, offered to the user on the next launch.
+//!
+//! [`install_hook`] runs first thing in `run()`. Once `setup` has called
+//! [`init`] with the app data dir, a panic writes `crashes/crash-<unix ms>.txt`
+//! with the message, location, backtrace, app version and OS, then hands over
+//! to the previous hook as before. The hook only writes straight to the file
+//! and ignores every error, so it can't panic itself; a panic while it runs
+//! (or before `init`) just falls through to the previous hook.
+//!
+//! `get_last_crash_report` returns the newest report not yet dismissed and
+//! `dismiss_crash_report` marks it seen. `init` keeps the newest
+//! [`MAX_REPORTS`] files.
+
+use crate::storage;
+use serde::Serialize;
+use std::any::Any;
+use std::io::Write;
+use std::panic::Location;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+const CRASH_DIR: &str = "crashes";
+const MAX_REPORTS: usize = 10;
+const REPORT_PREFIX: &str = "crash-";
+const REPORT_EXTENSION: &str = "txt";
+/// Extension for reports the user has seen
+const DISMISSED_EXTENSION: &str = "dismissed";
+
+static DIR: OnceLock<PathBuf> = OnceLock::new();
+static IN_HOOK: AtomicBool = AtomicBool::new(false);
+
+/// A crash report left by a previous run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashReport {
+    pub path: String,
+    /// Unix timestamp (ms) of the crash
+    pub crashed_at: u64,
+    pub contents: String,
+}
+
+fn write_report(
+    dir: &Path,
+    location: Option<&Location<'_>>,
+    payload: &(dyn Any + Send),
+) -> std::io::Result<()> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64);
+    let mut file =
+        std::fs::File::create(dir.join(format!("{}{}.{}", REPORT_PREFIX, now, REPORT_EXTENSION)))?;
+
+    writeln!(
+        file,
+        "Hush {} crashed at {}",
+        env!("CARGO_PKG_VERSION"),
+        now
+    )?;
+    writeln!(
+        file,
+        "OS: {} {}",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    )?;
+    if let Some(thread) = std::thread::current().name() {
+        writeln!(file, "Thread: {}", thread)?;
+    }
+    if let Some(location) = location {
+        writeln!(file, "Location: {}", location)?;
+    }
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        writeln!(file, "Message: {}", message)?;
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        writeln!(file, "Message: {}", message)?;
+    }
+    writeln!(file)?;
+    writeln!(file, "{}", std::backtrace::Backtrace::force_capture())?;
+    file.sync_all()
+}
+
+/// Install the panic hook. Reports are written from [`init`] on.
+pub fn install_hook() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if !IN_HOOK.swap(true, Ordering::SeqCst) {
+            if let Some(dir) = DIR.get() {
+                let _ = write_report(dir, info.location(), info.payload());
+            }
+            IN_HOOK.store(false, Ordering::SeqCst);
+        }
+        previous(info);
+    }));
+}
+
+/// Reports in `dir` with their timestamps, newest first.
+fn reports(dir: &Path) -> Vec<(u64, PathBuf)> {
+    let mut reports: Vec<(u64, PathBuf)> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| {
+                    let path = entry.path();
+                    let crashed_at = path
+                        .file_stem()?
+                        .to_str()?
+                        .strip_prefix(REPORT_PREFIX)?
+                        .split('.')
+                        .next()?
+                        .parse()
+                        .ok()?;
+                    Some((crashed_at, path))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    reports.sort_by(|a, b| b.0.cmp(&a.0));
+    reports
+}
+
+fn is_dismissed(path: &Path) -> bool {
+    path.extension().and_then(|extension| extension.to_str()) == Some(DISMISSED_EXTENSION)
+}
+
+fn prune(dir: &Path) {
+    for (_, path) in reports(dir).into_iter().skip(MAX_REPORTS) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+fn crash_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(CRASH_DIR))
+        .map_err(|e| e.to_string())
+}
+
+/// Delete every crash report, keeping the directory for the next one.
+/// Returns the bytes freed.
+pub(crate) fn clear(app: &AppHandle) -> Result<u64, String> {
+    let dir = crash_dir(app)?;
+    let mut freed = 0;
+    for (_, path) in reports(&dir) {
+        freed += storage::remove_all(&path)?;
+    }
+    Ok(freed)
+}
+
+/// Point the hook at the crash dir and prune old reports. Called from `setup`.
+pub fn init(app: &AppHandle) {
+    let dir = match crash_dir(app) {
+        Ok(dir) => dir,
+        Err(e) => {
+            log::warn!("Crash reports disabled: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        log::warn!("Crash reports disabled: {}", e);
+        return;
+    }
+    prune(&dir);
+    let _ = DIR.set(dir);
+}
+
+fn last_report(dir: &Path) -> Option<(u64, PathBuf)> {
+    reports(dir)
+        .into_iter()
+        .find(|(_, path)| !is_dismissed(path))
+}
+
+/// The newest crash report the user hasn't dismissed.
+#[tauri::command]
+pub fn get_last_crash_report(app: AppHandle) -> Result<Option<CrashReport>, String> {
+    let dir = crash_dir(&app)?;
+    let Some((crashed_at, path)) = last_report(&dir) else {
+        return Ok(None);
+    };
+    let contents = std::fs::read(&path).map_err(|e| e.to_string())?;
+    Ok(Some(CrashReport {
+        path: path.to_string_lossy().into_owned(),
+        crashed_at,
+        contents: String::from_utf8_lossy(&contents).into_owned(),
+    }))
+}
+
+/// Mark the newest crash report as seen; it is kept until pruned.
+#[tauri::command]
+pub fn dismiss_crash_report(app: AppHandle) -> Result<(), String> {
+    let dir = crash_dir(&app)?;
+    if let Some((_, path)) = last_report(&dir) {
+        std::fs::rename(&path, path.with_extension(DISMISSED_EXTENSION))
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("hush-crash-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn newest_undismissed_report_is_found() {
+        let dir = temp_dir();
+        std::fs::write(dir.join("crash-100.txt"), "old").unwrap();
+        std::fs::write(dir.join("crash-300.dismissed"), "seen").unwrap();
+        std::fs::write(dir.join("crash-200.txt"), "new").unwrap();
+        std::fs::write(dir.join("notes.txt"), "ignored").unwrap();
+
+        let (crashed_at, path) = last_report(&dir).unwrap();
+        assert_eq!(crashed_at, 200);
+        assert_eq!(path, dir.join("crash-200.txt"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn only_the_newest_reports_are_kept() {
+        let dir = temp_dir();
+        for i in 0..(MAX_REPORTS as u64 + 3) {
+            std::fs::write(dir.join(format!("crash-{}.txt", i)), "x").unwrap();
+        }
+        prune(&dir);
+        let kept = reports(&dir);
+        assert_eq!(kept.len(), MAX_REPORTS);
+        assert_eq!(kept.last().unwrap().0, 3);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,157 @@
+//! Deep link handling for `hush://` (and legacy `hushfeeds://`) feed links.
+//!
+//! Supported forms:
+//! - `hush://feed/<feedId>`
+//! - `hush://feed/<feedId>/post/<postId>`
+//!
+//! A link that launches the app cold is queued as a pending navigation for
+//! `get_pending_navigation`; a link opened while the app is running is emitted
+//! as a `deep-link-navigation` event instead, to the feed's pop-out window if
+//! it has one.
+
+use crate::fcm::NavigationKind;
+use crate::feed_windows;
+use serde::Serialize;
+use tauri::{AppHandle, Url};
+use tauri_plugin_deep_link::DeepLinkExt;
+
+const SCHEMES: [&str; 2] = ["hush", "hushfeeds"];
+
+/// Navigation target extracted from a deep link
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeepLinkTarget {
+    pub feed_id: String,
+    pub post_id: Option<String>,
+    pub kind: NavigationKind,
+}
+
+/// Parse a deep link URL into a navigation target.
+///
+/// Returns None for URLs that are not feed links for one of our schemes.
+pub fn parse_target(url: &Url) -> Option<DeepLinkTarget> {
+    if !SCHEMES.contains(&url.scheme()) || url.host_str() != Some("feed") {
+        return None;
+    }
+
+    let segments: Vec<&str> = url
+        .path_segments()
+        .map(|segments| segments.filter(|s| !s.is_empty()).collect())
+        .unwrap_or_default();
+
+    match segments.as_slice() {
+        [feed_id] => Some(DeepLinkTarget {
+            feed_id: feed_id.to_string(),
+            post_id: None,
+            kind: NavigationKind::Feed,
+        }),
+        [feed_id, "post", post_id] => Some(DeepLinkTarget {
+            feed_id: feed_id.to_string(),
+            post_id: Some(post_id.to_string()),
+            kind: NavigationKind::Post,
+        }),
+        _ => None,
+    }
+}
+
+/// Payload of the `deep-link-navigation` event
+#[derive(Debug, Clone, Serialize)]
+pub struct DeepLinkNavigationPayload {
+    pub feed_id: String,
+    pub post_id: Option<String>,
+    pub kind: NavigationKind,
+}
+
+/// Parse `url`, logging and dropping anything that isn't a feed link.
+fn target_or_log(url: &Url) -> Option<DeepLinkTarget> {
+    let target = parse_target(url);
+    if target.is_none() {
+        if SCHEMES.contains(&url.scheme()) {
+            // Only scheme and host: the path may carry invite codes
+            log::warn!(
+                "Dropping malformed deep link {}://{}",
+                url.scheme(),
+                url.host_str().unwrap_or("")
+            );
+        } else {
+            log::debug!("Ignoring non-feed deep link with scheme {}", url.scheme());
+        }
+    }
+    target
+}
+
+/// Register the deep link handlers. Called from `setup`.
+pub fn init(app: &AppHandle) {
+    // Links that launched the app go into the pending navigation queue
+    match app.deep_link().get_current() {
+        Ok(Some(urls)) => {
+            for target in urls.iter().filter_map(target_or_log) {
+                crate::fcm::queue_navigation(app, target.feed_id, target.post_id, target.kind);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => log::warn!("Failed to read launch deep link: {}", e),
+    }
+
+    // Links opened while running are delivered straight to the frontend
+    let handle = app.clone();
+    app.deep_link().on_open_url(move |event| {
+        for target in event.urls().iter().filter_map(target_or_log) {
+            // A feed open in a pop-out is shown there instead of the main window
+            feed_windows::show_for_feed(&handle, &target.feed_id);
+
+            let payload = DeepLinkNavigationPayload {
+                feed_id: target.feed_id.clone(),
+                post_id: target.post_id,
+                kind: target.kind,
+            };
+            feed_windows::emit_for_feed(&handle, &target.feed_id, "deep-link-navigation", payload);
+        }
+    });
+}
+
+/// Find the first argument that parses as a supported deep link.
+pub fn target_from_args<S: AsRef<str>>(args: &[S]) -> Option<DeepLinkTarget> {
+    args.iter()
+        .filter_map(|arg| Url::parse(arg.as_ref()).ok())
+        .find_map(|url| parse_target(&url))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(url: &str) -> Option<DeepLinkTarget> {
+        parse_target(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn parses_feed_links() {
+        let target = parse("hush://feed/abc123").unwrap();
+        assert_eq!(target.feed_id, "abc123");
+        assert_eq!(target.post_id, None);
+        assert_eq!(target.kind, NavigationKind::Feed);
+    }
+
+    #[test]
+    fn parses_post_links() {
+        let target = parse("hush://feed/abc123/post/p9").unwrap();
+        assert_eq!(target.post_id.as_deref(), Some("p9"));
+        assert_eq!(target.kind, NavigationKind::Post);
+    }
+
+    #[test]
+    fn rejects_other_links() {
+        assert!(parse("hushfeeds://join/CODE").is_none());
+        assert!(parse("https://feed/abc").is_none());
+        assert!(parse("hush://feed/").is_none());
+        assert!(parse("hush://feed/abc/comments").is_none());
+    }
+
+    #[test]
+    fn finds_deep_link_in_argv() {
+        let args = ["/usr/bin/hush", "--minimized", "hush://feed/abc"];
+        assert_eq!(target_from_args(&args).unwrap().feed_id, "abc");
+        assert!(target_from_args(&["hushfeeds://feed/legacy"]).is_some());
+        assert!(target_from_args(&["/usr/bin/hush"]).is_none());
+    }
+}
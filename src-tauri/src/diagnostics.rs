@@ -0,0 +1,266 @@
+//! A single zip for support: logs, system and app info, push diagnostics,
+//! settings and cache stats.
+//!
+//! `export_diagnostics` writes the bundle to the downloads dir (or a given
+//! path) on a blocking thread. Everything is redacted on the way in: JSON keys
+//! that look like credentials are blanked, strings and log lines go through
+//! [`crate::logging::redact`], and the machine's hostname is replaced
+//! wherever it appears. When the logs are over [`PROGRESS_MIN_BYTES`],
+//! `diagnostics-export-progress` reports each log file written.
+
+use crate::cache::CachedFeed;
+use crate::logging::redact;
+use crate::settings::SettingsState;
+use crate::{app_info, cache, image_cache, push_diagnostics, system_info};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// Logs smaller than this are bundled without progress events (1 MB)
+const PROGRESS_MIN_BYTES: u64 = 1024 * 1024;
+/// JSON keys whose string values are dropped, matched case-insensitively as
+/// substrings
+const SECRET_KEYS: &[&str] = &["token", "password", "secret", "credential", "authorization"];
+const REDACTED: &str = "[redacted]";
+const HOSTNAME_PLACEHOLDER: &str = "[hostname]";
+
+/// Returned by `export_diagnostics`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiagnosticsExport {
+    pub path: String,
+    /// Size of the zip in bytes
+    pub size: u64,
+}
+
+/// Payload of `diagnostics-export-progress`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProgressPayload {
+    files_done: usize,
+    files_total: usize,
+    bytes_done: u64,
+    bytes_total: u64,
+}
+
+/// Scrubs credentials and the hostname from text
+struct Redactor {
+    hostname: Option<String>,
+}
+
+impl Redactor {
+    fn text(&self, text: &str) -> String {
+        let text = redact(text);
+        match &self.hostname {
+            Some(hostname) => text.replace(hostname.as_str(), HOSTNAME_PLACEHOLDER),
+            None => text,
+        }
+    }
+
+    fn json(&self, value: Value) -> Value {
+        match value {
+            Value::String(text) => Value::String(self.text(&text)),
+            Value::Array(items) => {
+                Value::Array(items.into_iter().map(|item| self.json(item)).collect())
+            }
+            Value::Object(map) => Value::Object(
+                map.into_iter()
+                    .map(|(key, value)| {
+                        let lower = key.to_ascii_lowercase();
+                        let secret = SECRET_KEYS.iter().any(|secret| lower.contains(secret));
+                        let value = if secret && value.is_string() {
+                            Value::String(REDACTED.to_string())
+                        } else {
+                            self.json(value)
+                        };
+                        (key, value)
+                    })
+                    .collect(),
+            ),
+            value => value,
+        }
+    }
+}
+
+fn to_value<T: Serialize>(value: &T) -> Result<Value, String> {
+    serde_json::to_value(value).map_err(|e| e.to_string())
+}
+
+/// The documents put in the bundle next to the logs, gathered up front.
+fn collect_documents(app: &AppHandle) -> Result<Vec<(&'static str, Value)>, String> {
+    // Only counts, so this is fine while the app is locked
+    let feeds: Vec<CachedFeed> = app.state::<cache::CacheState>().feeds().unwrap_or_default();
+    let cache_stats = json!({
+        "offlineCache": {
+            "feeds": feeds.len(),
+            "posts": feeds.iter().map(|feed| feed.post_count).sum::<u64>(),
+            "maxBytes": cache::max_bytes(app),
+        },
+        "imageCache": to_value(&image_cache::get_image_cache_stats(app.clone(), app.state()))?,
+    });
+    let push = push_diagnostics::get_push_diagnostics(app.state(), app.state(), app.state());
+
+    Ok(vec![
+        ("system-info.json", to_value(&system_info::collect(false))?),
+        (
+            "app-info.json",
+            to_value(&app_info::get_app_info(app.clone()))?,
+        ),
+        ("push-diagnostics.json", to_value(&push)?),
+        (
+            "settings.json",
+            to_value(&app.state::<SettingsState>().get())?,
+        ),
+        ("cache-stats.json", cache_stats),
+    ])
+}
+
+fn log_files(dir: &Path) -> Vec<(PathBuf, u64)> {
+    let mut files: Vec<(PathBuf, u64)> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| {
+                    let meta = entry.metadata().ok()?;
+                    meta.is_file().then(|| (entry.path(), meta.len()))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+fn write_bundle(
+    app: &AppHandle,
+    dest: &Path,
+    documents: Vec<(&'static str, Value)>,
+    log_dir: Option<PathBuf>,
+    redactor: &Redactor,
+) -> Result<(), String> {
+    let file = std::fs::File::create(dest).map_err(|e| e.to_string())?;
+    let mut zip = ZipWriter::new(std::io::BufWriter::new(file));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    for (name, document) in documents {
+        zip.start_file(name, options).map_err(|e| e.to_string())?;
+        let document = redactor.json(document);
+        serde_json::to_writer_pretty(&mut zip, &document).map_err(|e| e.to_string())?;
+    }
+
+    let logs = log_dir.as_deref().map(log_files).unwrap_or_default();
+    let bytes_total: u64 = logs.iter().map(|(_, size)| size).sum();
+    let report = bytes_total >= PROGRESS_MIN_BYTES;
+    let mut bytes_done = 0;
+    for (index, (path, size)) in logs.iter().enumerate() {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        zip.start_file(format!("logs/{}", name), options)
+            .map_err(|e| e.to_string())?;
+        let reader = BufReader::new(std::fs::File::open(path).map_err(|e| e.to_string())?);
+        for line in reader.split(b'\n') {
+            let line = line.map_err(|e| e.to_string())?;
+            let line = redactor.text(&String::from_utf8_lossy(&line));
+            zip.write_all(line.as_bytes()).map_err(|e| e.to_string())?;
+            zip.write_all(b"\n").map_err(|e| e.to_string())?;
+        }
+        bytes_done += size;
+        if report {
+            let _ = app.emit(
+                "diagnostics-export-progress",
+                ProgressPayload {
+                    files_done: index + 1,
+                    files_total: logs.len(),
+                    bytes_done,
+                    bytes_total,
+                },
+            );
+        }
+    }
+
+    zip.finish()
+        .map_err(|e| e.to_string())?
+        .flush()
+        .map_err(|e| e.to_string())
+}
+
+fn default_dest(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().download_dir().map_err(|e| e.to_string())?;
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    Ok(dir.join(format!("hush-diagnostics-{}.zip", stamp)))
+}
+
+/// Bundle diagnostics into a zip at `dest_path`, or in the downloads dir.
+#[tauri::command]
+pub async fn export_diagnostics(
+    app: AppHandle,
+    dest_path: Option<String>,
+) -> Result<DiagnosticsExport, String> {
+    let dest = match dest_path {
+        Some(path) => PathBuf::from(path),
+        None => default_dest(&app)?,
+    };
+    let documents = collect_documents(&app)?;
+    let log_dir = app.path().app_log_dir().ok();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let redactor = Redactor {
+            hostname: sysinfo::System::host_name().filter(|name| !name.is_empty()),
+        };
+        if let Some(parent) = dest.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        if let Err(e) = write_bundle(&app, &dest, documents, log_dir, &redactor) {
+            let _ = std::fs::remove_file(&dest);
+            return Err(e);
+        }
+        Ok(DiagnosticsExport {
+            size: std::fs::metadata(&dest).map_err(|e| e.to_string())?.len(),
+            path: dest.to_string_lossy().into_owned(),
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redactor() -> Redactor {
+        Redactor {
+            hostname: Some("alices-laptop".to_string()),
+        }
+    }
+
+    #[test]
+    fn json_secrets_and_hostname_are_redacted() {
+        let value = json!({
+            "tokenPreview": "abcd…wxyz",
+            "proxy": {"url": "socks5://proxy:1080", "password": "hunter2"},
+            "deviceName": "alices-laptop",
+            "tokenRefreshedAt": 1700000000000u64,
+            "pushes": 3,
+        });
+        assert_eq!(
+            redactor().json(value),
+            json!({
+                "tokenPreview": "[redacted]",
+                "proxy": {"url": "socks5://proxy:1080", "password": "[redacted]"},
+                "deviceName": "[hostname]",
+                "tokenRefreshedAt": 1700000000000u64,
+                "pushes": 3,
+            })
+        );
+    }
+
+    #[test]
+    fn log_lines_are_redacted() {
+        assert_eq!(
+            redactor().text("[INFO] registered alices-laptop with token=abc123"),
+            "[INFO] registered [hostname] with token=[redacted]"
+        );
+    }
+}
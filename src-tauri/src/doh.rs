@@ -0,0 +1,426 @@
+//! Optional DNS-over-HTTPS for Rust-side requests, so plain DNS doesn't leak
+//! which servers the app talks to.
+//!
+//! `set_doh` stores a [`DohConfig`] in the `doh` setting. While present, the
+//! shared [`crate::net`] client and WebSocket connector resolve host names
+//! through [`DohResolver`]: Cloudflare and Quad9 by default, or a custom
+//! `https://host/dns-query` resolver. Answers are cached for their TTL. If
+//! the resolver fails, system DNS is used only when `fallbackAllowed` is set.
+//!
+//! Names resolved by a proxy (socks5h, HTTP CONNECT, Tor) never reach this
+//! resolver. The resolver itself connects directly, not through the proxy.
+
+use crate::net;
+use crate::settings::{self, SettingsState};
+use hickory_resolver::config::{NameServerConfigGroup, ResolverConfig, ResolverOpts};
+use hickory_resolver::TokioAsyncResolver;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, Runtime, State, Url};
+
+/// hickory only speaks to resolvers at this path
+const DOH_PATH: &str = "/dns-query";
+const MAX_CACHE_ENTRIES: usize = 1024;
+/// Cache lifetime for answers whose TTL is too short to be useful
+const MIN_TTL: Duration = Duration::from_secs(5);
+
+/// Persisted DoH settings; DoH is on while this is set
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DohConfig {
+    /// Custom resolver; Cloudflare and Quad9 when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolver_url: Option<String>,
+    /// Addresses of the custom resolver's host, looked up once when it's set
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bootstrap_ips: Vec<IpAddr>,
+    /// Use system DNS when the resolver fails
+    #[serde(default)]
+    pub fallback_allowed: bool,
+}
+
+/// Lookup counters, for the diagnostics screen
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct DohStats {
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+    pub failures: u64,
+    /// Failed lookups answered by system DNS instead
+    pub fallbacks: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DohStatus {
+    pub enabled: bool,
+    /// The active resolver's URL, or `cloudflare+quad9`
+    pub resolver: Option<String>,
+    pub fallback_allowed: bool,
+    pub cache_entries: usize,
+    pub stats: DohStats,
+}
+
+/// Answers cached until their TTL runs out
+#[derive(Debug, Default)]
+struct DnsCache {
+    entries: HashMap<String, (Vec<IpAddr>, Instant)>,
+}
+
+impl DnsCache {
+    fn get(&self, name: &str, now: Instant) -> Option<Vec<IpAddr>> {
+        self.entries
+            .get(name)
+            .filter(|(_, expires)| *expires > now)
+            .map(|(addrs, _)| addrs.clone())
+    }
+
+    fn insert(&mut self, name: &str, addrs: Vec<IpAddr>, expires: Instant, now: Instant) {
+        if self.entries.len() >= MAX_CACHE_ENTRIES {
+            self.entries.retain(|_, (_, expires)| *expires > now);
+            if self.entries.len() >= MAX_CACHE_ENTRIES {
+                self.entries.clear();
+            }
+        }
+        self.entries.insert(name.to_string(), (addrs, expires));
+    }
+}
+
+/// Check a custom resolver URL, returning its host and port.
+pub(crate) fn parse_resolver_url(url: &str) -> Result<(String, u16), String> {
+    let url = Url::parse(url).map_err(|e| format!("Invalid resolver URL: {}", e))?;
+    if url.scheme() != "https" {
+        return Err("Resolver URL must use https".to_string());
+    }
+    if !matches!(url.path(), "" | "/" | DOH_PATH) {
+        return Err(format!("Resolver URL path must be {}", DOH_PATH));
+    }
+    let host = url
+        .host_str()
+        .ok_or_else(|| "Resolver URL has no host".to_string())?
+        .trim_matches(|c| c == '[' || c == ']')
+        .to_string();
+    Ok((host, url.port().unwrap_or(443)))
+}
+
+fn resolver_config(config: &DohConfig) -> Result<ResolverConfig, String> {
+    let servers = match &config.resolver_url {
+        Some(url) => {
+            let (host, port) = parse_resolver_url(url)?;
+            let ips = match host.parse::<IpAddr>() {
+                Ok(ip) => vec![ip],
+                Err(_) if config.bootstrap_ips.is_empty() => {
+                    return Err(format!("No known address for {}", host))
+                }
+                Err(_) => config.bootstrap_ips.clone(),
+            };
+            NameServerConfigGroup::from_ips_https(&ips, port, host, true)
+        }
+        None => {
+            let mut servers = NameServerConfigGroup::cloudflare_https();
+            servers.merge(NameServerConfigGroup::quad9_https());
+            servers
+        }
+    };
+    Ok(ResolverConfig::from_parts(None, vec![], servers))
+}
+
+struct Inner {
+    resolver: TokioAsyncResolver,
+    config: DohConfig,
+    cache: Mutex<DnsCache>,
+    stats: Mutex<DohStats>,
+}
+
+/// A DoH resolver with its own TTL cache, usable as reqwest's resolver.
+/// Clones share the cache.
+#[derive(Clone)]
+pub struct DohResolver {
+    inner: Arc<Inner>,
+}
+
+impl DohResolver {
+    fn new(config: DohConfig) -> Result<Self, String> {
+        let mut opts = ResolverOpts::default();
+        // Caching is done here so hits can be counted
+        opts.cache_size = 0;
+        let resolver = TokioAsyncResolver::tokio(resolver_config(&config)?, opts);
+        Ok(Self {
+            inner: Arc::new(Inner {
+                resolver,
+                config,
+                cache: Mutex::new(DnsCache::default()),
+                stats: Mutex::new(DohStats::default()),
+            }),
+        })
+    }
+
+    fn count(&self, update: impl FnOnce(&mut DohStats)) {
+        update(&mut self.inner.stats.lock().unwrap_or_else(|e| e.into_inner()));
+    }
+
+    /// Resolve `host`, from the cache if possible.
+    pub async fn lookup(&self, host: &str) -> Result<Vec<IpAddr>, String> {
+        let name = host.trim_end_matches('.').to_ascii_lowercase();
+        let cached = self
+            .inner
+            .cache
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&name, Instant::now());
+        if let Some(addrs) = cached {
+            self.count(|stats| stats.cache_hits += 1);
+            return Ok(addrs);
+        }
+        self.count(|stats| stats.cache_misses += 1);
+
+        match self.inner.resolver.lookup_ip(format!("{}.", name)).await {
+            Ok(lookup) => {
+                let addrs: Vec<IpAddr> = lookup.iter().collect();
+                let now = Instant::now();
+                let expires = lookup.valid_until().max(now + MIN_TTL);
+                self.inner
+                    .cache
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .insert(&name, addrs.clone(), expires, now);
+                Ok(addrs)
+            }
+            Err(e) => {
+                self.count(|stats| stats.failures += 1);
+                if !self.inner.config.fallback_allowed {
+                    return Err(format!("DNS-over-HTTPS lookup of {} failed: {}", name, e));
+                }
+                log::warn!("DoH lookup of {} failed, using system DNS: {}", name, e);
+                self.count(|stats| stats.fallbacks += 1);
+                let addrs = tokio::net::lookup_host((name.as_str(), 0))
+                    .await
+                    .map_err(|e| e.to_string())?;
+                Ok(addrs.map(|addr| addr.ip()).collect())
+            }
+        }
+    }
+
+    fn status(&self) -> DohStatus {
+        let config = &self.inner.config;
+        DohStatus {
+            enabled: true,
+            resolver: Some(
+                config
+                    .resolver_url
+                    .clone()
+                    .unwrap_or_else(|| "cloudflare+quad9".to_string()),
+            ),
+            fallback_allowed: config.fallback_allowed,
+            cache_entries: self
+                .inner
+                .cache
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .entries
+                .len(),
+            stats: *self.inner.stats.lock().unwrap_or_else(|e| e.into_inner()),
+        }
+    }
+}
+
+impl reqwest::dns::Resolve for DohResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let resolver = self.clone();
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs = resolver.lookup(&host).await?;
+            let addrs: reqwest::dns::Addrs =
+                Box::new(addrs.into_iter().map(|ip| SocketAddr::new(ip, 0)));
+            Ok(addrs)
+        })
+    }
+}
+
+/// Managed state holding the active resolver
+#[derive(Default)]
+pub struct DohState {
+    resolver: RwLock<Option<DohResolver>>,
+}
+
+/// The resolver the saved settings ask for, if any.
+fn saved_resolver(app: &AppHandle) -> Option<DohResolver> {
+    app.state::<SettingsState>()
+        .get()
+        .doh
+        .and_then(|config| match DohResolver::new(config) {
+            Ok(resolver) => Some(resolver),
+            Err(e) => {
+                log::warn!("DNS-over-HTTPS disabled: {}", e);
+                None
+            }
+        })
+}
+
+impl DohState {
+    /// Build the resolver from settings. Call before [`net::NetworkState`] is managed.
+    pub fn load(app: &AppHandle) -> Self {
+        Self {
+            resolver: RwLock::new(saved_resolver(app)),
+        }
+    }
+}
+
+/// Rebuild the resolver from settings, e.g. after they were imported. Call
+/// [`net::rebuild`] afterwards so the client uses it.
+pub(crate) fn reload(app: &AppHandle) {
+    let Some(state) = app.try_state::<DohState>() else {
+        return;
+    };
+    *state.resolver.write().unwrap_or_else(|e| e.into_inner()) = saved_resolver(app);
+}
+
+/// The active resolver, if DoH is on.
+pub fn resolver<R: Runtime>(app: &AppHandle<R>) -> Option<DohResolver> {
+    app.try_state::<DohState>()?
+        .resolver
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+/// Resolve `host` for a direct connection: through DoH when it's on, else system DNS.
+pub async fn lookup_host(
+    resolver: Option<&DohResolver>,
+    host: &str,
+    port: u16,
+) -> Result<Vec<SocketAddr>, String> {
+    if let Ok(ip) = host
+        .trim_matches(|c| c == '[' || c == ']')
+        .parse::<IpAddr>()
+    {
+        return Ok(vec![SocketAddr::new(ip, port)]);
+    }
+    match resolver {
+        Some(resolver) => Ok(resolver
+            .lookup(host)
+            .await?
+            .into_iter()
+            .map(|ip| SocketAddr::new(ip, port))
+            .collect()),
+        None => tokio::net::lookup_host((host, port))
+            .await
+            .map(Iterator::collect)
+            .map_err(|e| e.to_string()),
+    }
+}
+
+/// Turn DNS-over-HTTPS on or off.
+///
+/// `resolver_url` must be an `https://host/dns-query` URL; unset uses
+/// Cloudflare and Quad9. With `fallback_allowed`, failed lookups fall back to
+/// system DNS.
+#[tauri::command]
+pub async fn set_doh(
+    app: AppHandle,
+    enabled: bool,
+    resolver_url: Option<String>,
+    fallback_allowed: Option<bool>,
+) -> Result<(), String> {
+    let resolver = if enabled {
+        let resolver_url = resolver_url.filter(|url| !url.trim().is_empty());
+        let mut bootstrap_ips = Vec::new();
+        if let Some(url) = &resolver_url {
+            let (host, port) = parse_resolver_url(url)?;
+            if host.parse::<IpAddr>().is_err() {
+                bootstrap_ips = tokio::net::lookup_host((host.as_str(), port))
+                    .await
+                    .map_err(|e| format!("Couldn't resolve {}: {}", host, e))?
+                    .map(|addr| addr.ip())
+                    .collect();
+            }
+        }
+        let config = DohConfig {
+            resolver_url,
+            bootstrap_ips,
+            fallback_allowed: fallback_allowed.unwrap_or(false),
+        };
+        let resolver = DohResolver::new(config.clone())?;
+        let value = serde_json::to_value(&config).map_err(|e| e.to_string())?;
+        settings::set(&app, "doh", value)?;
+        Some(resolver)
+    } else {
+        settings::set(&app, "doh", serde_json::Value::Null)?;
+        None
+    };
+
+    *app.state::<DohState>()
+        .resolver
+        .write()
+        .unwrap_or_else(|e| e.into_inner()) = resolver;
+    net::rebuild(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_doh_status(state: State<'_, DohState>) -> DohStatus {
+    match &*state.resolver.read().unwrap_or_else(|e| e.into_inner()) {
+        Some(resolver) => resolver.status(),
+        None => DohStatus {
+            enabled: false,
+            resolver: None,
+            fallback_allowed: false,
+            cache_entries: 0,
+            stats: DohStats::default(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolver_urls_are_validated() {
+        assert_eq!(
+            parse_resolver_url("https://dns.example/dns-query").unwrap(),
+            ("dns.example".to_string(), 443)
+        );
+        assert_eq!(
+            parse_resolver_url("https://[2606:4700::1111]:8443/").unwrap(),
+            ("2606:4700::1111".to_string(), 8443)
+        );
+        assert!(parse_resolver_url("http://dns.example/dns-query").is_err());
+        assert!(parse_resolver_url("https://dns.example/resolve").is_err());
+    }
+
+    #[test]
+    fn custom_resolver_needs_an_address() {
+        let named = DohConfig {
+            resolver_url: Some("https://dns.example/dns-query".to_string()),
+            ..DohConfig::default()
+        };
+        assert!(resolver_config(&named).is_err());
+        let bootstrapped = DohConfig {
+            bootstrap_ips: vec!["192.0.2.1".parse().unwrap()],
+            ..named
+        };
+        assert!(resolver_config(&bootstrapped).is_ok());
+        assert!(resolver_config(&DohConfig::default()).is_ok());
+    }
+
+    #[test]
+    fn cache_entries_expire_with_their_ttl() {
+        let now = Instant::now();
+        let addrs = vec!["192.0.2.1".parse().unwrap()];
+        let mut cache = DnsCache::default();
+        cache.insert(
+            "example.com",
+            addrs.clone(),
+            now + Duration::from_secs(60),
+            now,
+        );
+        assert_eq!(cache.get("example.com", now), Some(addrs));
+        assert_eq!(
+            cache.get("example.com", now + Duration::from_secs(61)),
+            None
+        );
+        assert_eq!(cache.get("other.com", now), None);
+    }
+}
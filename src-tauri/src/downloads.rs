@@ -0,0 +1,626 @@
+//! Download manager for large attachments.
+//!
+//! `download_start` queues a file and returns its id. The queue is persisted
+//! to `downloads.json` in the app data dir, so downloads interrupted by a quit
+//! or crash continue on the next start. At most `maxConcurrentDownloads`
+//! (default [`DEFAULT_MAX_CONCURRENT`]) run at once; the rest wait in order.
+//!
+//! Data goes to `<dest>.part` and is renamed into place when complete. Pausing,
+//! a dropped connection or a restart resumes from the part file with a `Range`
+//! request guarded by `If-Range` and the ETag or Last-Modified of the first
+//! response, so a file that changed on the server is downloaded again from the
+//! start instead of being spliced. Progress, completion and failure are
+//! emitted as `download-progress`, `download-complete` and `download-failed`
+//! with the [`Download`] entry. Their combined progress is also shown on the
+//! taskbar or dock ([`crate::task_progress`]).
+
+use crate::attachments::{content_range_total, part_path};
+use crate::backoff::Backoff;
+use crate::fcm::now_unix_ms;
+use crate::logging::redact;
+use crate::net;
+use crate::settings::{self, SettingsState};
+use crate::storage;
+use crate::task_progress::{self, Source, TaskProgress};
+use reqwest::header::{CONTENT_RANGE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager, State, Url};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Notify;
+
+const QUEUE_FILE: &str = "downloads.json";
+const DEFAULT_MAX_CONCURRENT: u32 = 3;
+const MAX_CONCURRENT_LIMIT: u32 = 10;
+/// Connection failures in a row before a download is marked failed
+const MAX_ATTEMPTS: u32 = 5;
+const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(2);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+/// How often progress is written to the queue file while downloading
+const SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DownloadStatus {
+    Queued,
+    Active,
+    Paused,
+    Completed,
+    Failed,
+}
+
+/// A queued, running or finished download
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Download {
+    pub id: u64,
+    pub url: String,
+    pub dest: String,
+    pub status: DownloadStatus,
+    pub received: u64,
+    /// `None` until the server sends a length
+    pub total: Option<u64>,
+    /// ETag or Last-Modified of the first response, sent as `If-Range`
+    #[serde(default)]
+    pub validator: Option<String>,
+    #[serde(default)]
+    pub error: Option<String>,
+    /// Unix timestamp (ms) when the download was queued
+    pub created_at: u64,
+}
+
+/// Queue downloads that were running when the app last stopped.
+fn requeue_interrupted(downloads: &mut [Download]) {
+    for download in downloads {
+        if download.status == DownloadStatus::Active {
+            download.status = DownloadStatus::Queued;
+        }
+    }
+}
+
+/// Managed state holding the queue, oldest first
+#[derive(Default)]
+pub struct DownloadsState {
+    downloads: Mutex<Vec<Download>>,
+    running: Mutex<HashMap<u64, JoinHandle<()>>>,
+    /// Signalled when a slot frees up or something was queued
+    wake: Notify,
+    /// Backing file; `None` keeps the queue in memory only
+    path: Option<PathBuf>,
+}
+
+impl DownloadsState {
+    /// Load the queue from the app data dir; downloads that were running are
+    /// queued again.
+    pub fn load(app: &AppHandle) -> Self {
+        let path = storage::data_file(app, QUEUE_FILE).ok();
+        let mut downloads: Vec<Download> = path
+            .as_deref()
+            .and_then(storage::read_json)
+            .unwrap_or_default();
+        requeue_interrupted(&mut downloads);
+        Self {
+            downloads: Mutex::new(downloads),
+            running: Mutex::default(),
+            wake: Notify::new(),
+            path,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<Download>> {
+        self.downloads.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn running(&self) -> MutexGuard<'_, HashMap<u64, JoinHandle<()>>> {
+        self.running.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn persist(&self, downloads: &[Download]) {
+        if let Some(path) = &self.path {
+            if let Err(e) = storage::write_json_atomic(path, downloads) {
+                log::warn!("Failed to save download queue: {}", e);
+            }
+        }
+    }
+
+    /// Apply `change` to download `id`, optionally persisting. Returns the
+    /// updated entry.
+    fn update(&self, id: u64, save: bool, change: impl FnOnce(&mut Download)) -> Option<Download> {
+        let mut downloads = self.lock();
+        let download = downloads.iter_mut().find(|download| download.id == id)?;
+        change(download);
+        let download = download.clone();
+        if save {
+            self.persist(&downloads);
+        }
+        Some(download)
+    }
+
+    pub fn list(&self) -> Vec<Download> {
+        self.lock().clone()
+    }
+
+    /// Whether any download is running right now.
+    pub fn has_active(&self) -> bool {
+        self.lock()
+            .iter()
+            .any(|download| download.status == DownloadStatus::Active)
+    }
+
+    /// Stop the task running download `id`, if any.
+    fn stop(&self, id: u64) {
+        if let Some(task) = self.running().remove(&id) {
+            task.abort();
+        }
+    }
+}
+
+fn max_concurrent(app: &AppHandle) -> usize {
+    app.state::<SettingsState>()
+        .get()
+        .max_concurrent_downloads
+        .unwrap_or(DEFAULT_MAX_CONCURRENT)
+        .clamp(1, MAX_CONCURRENT_LIMIT) as usize
+}
+
+fn emit(app: &AppHandle, event: &str, download: &Download) {
+    let _ = app.emit(event, download);
+    report_progress(app);
+}
+
+/// Combined progress of the queued and running downloads: the fraction of
+/// the bytes whose size is known, or indeterminate if none is.
+fn aggregate_progress(downloads: &[Download]) -> TaskProgress {
+    let mut in_flight = downloads.iter().filter(|download| {
+        matches!(
+            download.status,
+            DownloadStatus::Queued | DownloadStatus::Active
+        )
+    });
+    let Some(first) = in_flight.next() else {
+        return TaskProgress::None;
+    };
+    let (received, total) = std::iter::once(first)
+        .chain(in_flight)
+        .filter_map(|download| Some((download.received, download.total?)))
+        .fold((0u64, 0u64), |(received, total), (r, t)| {
+            (received + r, total + t)
+        });
+    if total == 0 {
+        TaskProgress::Indeterminate
+    } else {
+        TaskProgress::Normal(received as f64 / total as f64)
+    }
+}
+
+fn report_progress(app: &AppHandle) {
+    if let Some(state) = app.try_state::<DownloadsState>() {
+        let progress = aggregate_progress(&state.lock());
+        task_progress::report(app, Source::Downloads, progress);
+    }
+}
+
+/// Why one request ended early
+enum Interrupted {
+    /// Connection trouble; resume after a backoff
+    Retry(String),
+    Fatal(String),
+}
+
+impl From<reqwest::Error> for Interrupted {
+    fn from(e: reqwest::Error) -> Self {
+        match e.status() {
+            Some(status) if !status.is_server_error() => Self::Fatal(e.to_string()),
+            _ => Self::Retry(e.to_string()),
+        }
+    }
+}
+
+impl From<std::io::Error> for Interrupted {
+    fn from(e: std::io::Error) -> Self {
+        Self::Fatal(e.to_string())
+    }
+}
+
+/// One request, appending to the part file when the server honours the range.
+async fn fetch_once(
+    app: &AppHandle,
+    state: &DownloadsState,
+    client: &reqwest::Client,
+    download: &Download,
+    part: &Path,
+) -> Result<(), Interrupted> {
+    let url = Url::parse(&download.url).map_err(|e| Interrupted::Fatal(e.to_string()))?;
+    let offset = tokio::fs::metadata(part)
+        .await
+        .map(|meta| meta.len())
+        .unwrap_or(0);
+    let mut request = client.get(url);
+    if let (true, Some(validator)) = (offset > 0, download.validator.as_deref()) {
+        request = request
+            .header(RANGE, format!("bytes={}-", offset))
+            .header(IF_RANGE, validator);
+    }
+    let mut response = request.send().await?.error_for_status()?;
+
+    let resumed = offset > 0 && response.status() == StatusCode::PARTIAL_CONTENT;
+    let (mut file, mut received, total, validator) = if resumed {
+        let total = response
+            .headers()
+            .get(CONTENT_RANGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(content_range_total);
+        let file = tokio::fs::OpenOptions::new()
+            .append(true)
+            .open(part)
+            .await?;
+        (file, offset, total, download.validator.clone())
+    } else {
+        if offset > 0 {
+            log::info!("Download {} changed on the server, restarting", download.id);
+        }
+        let validator = [ETAG, LAST_MODIFIED].iter().find_map(|header| {
+            let value = response.headers().get(header)?.to_str().ok()?;
+            (!value.starts_with("W/")).then(|| value.to_string())
+        });
+        let total = response.content_length();
+        (tokio::fs::File::create(part).await?, 0, total, validator)
+    };
+    state.update(download.id, true, |download| {
+        download.received = received;
+        download.total = total;
+        download.validator = validator;
+    });
+
+    let mut last_emit = Instant::now();
+    let mut last_save = Instant::now();
+    while let Some(chunk) = response.chunk().await? {
+        file.write_all(&chunk).await?;
+        received += chunk.len() as u64;
+        if last_emit.elapsed() >= PROGRESS_INTERVAL {
+            last_emit = Instant::now();
+            let save = last_save.elapsed() >= SAVE_INTERVAL;
+            if save {
+                file.flush().await?;
+                last_save = Instant::now();
+            }
+            if let Some(download) =
+                state.update(download.id, save, |download| download.received = received)
+            {
+                emit(app, "download-progress", &download);
+            }
+        }
+    }
+    file.flush().await?;
+    state.update(download.id, true, |download| download.received = received);
+
+    match total {
+        Some(total) if received < total => Err(Interrupted::Retry(format!(
+            "Connection closed after {} of {} bytes",
+            received, total
+        ))),
+        Some(total) if received > total => Err(Interrupted::Fatal(format!(
+            "Received {} bytes, expected {}",
+            received, total
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Download to the part file, resuming after connection failures.
+async fn transfer(app: &AppHandle, download: &Download, part: &Path) -> Result<(), String> {
+    let state = app.state::<DownloadsState>();
+    let mut backoff = Backoff::new(INITIAL_RETRY_DELAY, MAX_RETRY_DELAY);
+    let mut attempt = 1;
+    loop {
+        // Re-read the entry for the validator saved by earlier attempts
+        let current = state
+            .list()
+            .into_iter()
+            .find(|entry| entry.id == download.id)
+            .ok_or_else(|| "Download was removed".to_string())?;
+        match fetch_once(app, &state, &net::client(app), &current, part).await {
+            Ok(()) => return Ok(()),
+            Err(Interrupted::Retry(e)) if attempt < MAX_ATTEMPTS => {
+                log::info!(
+                    "Download {} interrupted, resuming: {}",
+                    download.id,
+                    redact(&e)
+                );
+                tokio::time::sleep(backoff.next_jittered_delay()).await;
+                attempt += 1;
+            }
+            Err(Interrupted::Retry(e) | Interrupted::Fatal(e)) => return Err(e),
+        }
+    }
+}
+
+async fn run_download(app: AppHandle, download: Download) {
+    let state = app.state::<DownloadsState>();
+    let dest = PathBuf::from(&download.dest);
+    let part = part_path(&dest);
+    let result = match transfer(&app, &download, &part).await {
+        Ok(()) => tokio::fs::rename(&part, &dest)
+            .await
+            .map_err(|e| format!("Failed to move download into place: {}", e)),
+        Err(e) => Err(e),
+    };
+
+    state.running().remove(&download.id);
+    let updated = state.update(download.id, true, |download| match &result {
+        Ok(()) => {
+            download.status = DownloadStatus::Completed;
+            download.error = None;
+        }
+        Err(e) => {
+            download.status = DownloadStatus::Failed;
+            download.error = Some(e.clone());
+        }
+    });
+    if let Some(updated) = updated {
+        match &result {
+            Ok(()) => emit(&app, "download-complete", &updated),
+            Err(e) => {
+                log::warn!("Download {} failed: {}", download.id, redact(e));
+                emit(&app, "download-failed", &updated);
+            }
+        }
+    }
+    state.wake.notify_one();
+}
+
+/// Start queued downloads while there are free slots.
+fn start_queued(app: &AppHandle) {
+    let state = app.state::<DownloadsState>();
+    let max = max_concurrent(app);
+    let mut downloads = state.lock();
+    let mut running = state.running();
+    let mut started = false;
+    for download in downloads.iter_mut() {
+        if running.len() >= max {
+            break;
+        }
+        if download.status != DownloadStatus::Queued {
+            continue;
+        }
+        download.status = DownloadStatus::Active;
+        download.error = None;
+        let task = tauri::async_runtime::spawn(run_download(app.clone(), download.clone()));
+        running.insert(download.id, task);
+        started = true;
+    }
+    drop(running);
+    if started {
+        state.persist(&downloads);
+    }
+}
+
+async fn run(app: AppHandle) {
+    let state = app.state::<DownloadsState>();
+    loop {
+        start_queued(&app);
+        state.wake.notified().await;
+    }
+}
+
+/// Spawn the scheduler. Called from `setup` after [`DownloadsState`] is managed.
+pub fn init(app: &AppHandle) {
+    tauri::async_runtime::spawn(run(app.clone()));
+}
+
+/// Queue `url` for download to the absolute path `dest`; returns the id.
+#[tauri::command]
+pub fn download_start(
+    app: AppHandle,
+    state: State<'_, DownloadsState>,
+    url: String,
+    dest: String,
+) -> Result<u64, String> {
+    let parsed = Url::parse(&url).map_err(|e| format!("Invalid download URL: {}", e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err("Download URL must use http or https".to_string());
+    }
+    if !Path::new(&dest).is_absolute() {
+        return Err("Download destination must be an absolute path".to_string());
+    }
+
+    let mut downloads = state.lock();
+    let id = downloads
+        .iter()
+        .map(|download| download.id)
+        .max()
+        .unwrap_or(0)
+        + 1;
+    downloads.push(Download {
+        id,
+        url,
+        dest,
+        status: DownloadStatus::Queued,
+        received: 0,
+        total: None,
+        validator: None,
+        error: None,
+        created_at: now_unix_ms(),
+    });
+    state.persist(&downloads);
+    drop(downloads);
+    state.wake.notify_one();
+    report_progress(&app);
+    Ok(id)
+}
+
+/// Pause a queued or running download, keeping what was received.
+#[tauri::command]
+pub fn download_pause(
+    app: AppHandle,
+    state: State<'_, DownloadsState>,
+    id: u64,
+) -> Result<(), String> {
+    let status = state
+        .update(id, false, |_| {})
+        .ok_or_else(|| format!("No download {}", id))?
+        .status;
+    if !matches!(status, DownloadStatus::Queued | DownloadStatus::Active) {
+        return Err(format!("Download {} is not running", id));
+    }
+    state.stop(id);
+    state.update(id, true, |download| {
+        download.status = DownloadStatus::Paused
+    });
+    state.wake.notify_one();
+    report_progress(&app);
+    Ok(())
+}
+
+/// Queue a paused or failed download again; it resumes where it stopped.
+#[tauri::command]
+pub fn download_resume(
+    app: AppHandle,
+    state: State<'_, DownloadsState>,
+    id: u64,
+) -> Result<(), String> {
+    let mut resumed = false;
+    state
+        .update(id, true, |download| {
+            if matches!(
+                download.status,
+                DownloadStatus::Paused | DownloadStatus::Failed
+            ) {
+                download.status = DownloadStatus::Queued;
+                download.error = None;
+                resumed = true;
+            }
+        })
+        .ok_or_else(|| format!("No download {}", id))?;
+    if !resumed {
+        return Err(format!("Download {} is not paused", id));
+    }
+    state.wake.notify_one();
+    report_progress(&app);
+    Ok(())
+}
+
+/// Stop a download and forget it. Partial data is deleted; a completed file
+/// is kept.
+#[tauri::command]
+pub fn download_cancel(
+    app: AppHandle,
+    state: State<'_, DownloadsState>,
+    id: u64,
+) -> Result<bool, String> {
+    state.stop(id);
+    let mut downloads = state.lock();
+    let Some(index) = downloads.iter().position(|download| download.id == id) else {
+        return Ok(false);
+    };
+    let download = downloads.remove(index);
+    state.persist(&downloads);
+    drop(downloads);
+    if download.status != DownloadStatus::Completed {
+        let _ = std::fs::remove_file(part_path(Path::new(&download.dest)));
+    }
+    state.wake.notify_one();
+    report_progress(&app);
+    Ok(true)
+}
+
+#[tauri::command]
+pub fn download_list(state: State<'_, DownloadsState>) -> Vec<Download> {
+    state.list()
+}
+
+/// Set how many downloads may run at once (1–10).
+#[tauri::command]
+pub fn set_max_concurrent_downloads(
+    app: AppHandle,
+    state: State<'_, DownloadsState>,
+    count: u32,
+) -> Result<(), String> {
+    if !(1..=MAX_CONCURRENT_LIMIT).contains(&count) {
+        return Err(format!(
+            "Concurrent downloads must be between 1 and {}",
+            MAX_CONCURRENT_LIMIT
+        ));
+    }
+    settings::set(&app, "maxConcurrentDownloads", count.into())?;
+    state.wake.notify_one();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn download(id: u64, status: DownloadStatus) -> Download {
+        Download {
+            id,
+            url: "https://cdn.hushnetwork.social/v.mp4".to_string(),
+            dest: "/tmp/v.mp4".to_string(),
+            status,
+            received: 10,
+            total: Some(20),
+            validator: Some("\"abc\"".to_string()),
+            error: None,
+            created_at: 5,
+        }
+    }
+
+    #[test]
+    fn interrupted_downloads_are_queued_again() {
+        let mut downloads = vec![
+            download(1, DownloadStatus::Active),
+            download(2, DownloadStatus::Paused),
+            download(3, DownloadStatus::Completed),
+        ];
+        requeue_interrupted(&mut downloads);
+        let statuses: Vec<_> = downloads.iter().map(|download| download.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                DownloadStatus::Queued,
+                DownloadStatus::Paused,
+                DownloadStatus::Completed
+            ]
+        );
+    }
+
+    #[test]
+    fn progress_combines_running_downloads_with_known_sizes() {
+        let mut downloads = vec![
+            download(1, DownloadStatus::Active),
+            download(2, DownloadStatus::Completed),
+        ];
+        assert_eq!(aggregate_progress(&downloads), TaskProgress::Normal(0.5));
+
+        downloads.push(Download {
+            received: 30,
+            total: Some(60),
+            ..download(3, DownloadStatus::Queued)
+        });
+        downloads.push(Download {
+            total: None,
+            ..download(4, DownloadStatus::Active)
+        });
+        assert_eq!(aggregate_progress(&downloads), TaskProgress::Normal(0.5));
+
+        downloads.retain(|download| download.id == 4);
+        assert_eq!(aggregate_progress(&downloads), TaskProgress::Indeterminate);
+        downloads[0].status = DownloadStatus::Paused;
+        assert_eq!(aggregate_progress(&downloads), TaskProgress::None);
+    }
+
+    #[test]
+    fn queue_round_trips_with_camel_case_fields() {
+        let download = download(1, DownloadStatus::Paused);
+        let json = serde_json::to_value(&download).unwrap();
+        assert_eq!(json["status"], "paused");
+        assert_eq!(json["createdAt"], 5);
+        assert_eq!(serde_json::from_value::<Download>(json).unwrap(), download);
+    }
+}
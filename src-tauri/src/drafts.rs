@@ -0,0 +1,476 @@
+//! Composer drafts kept on the Rust side so they survive webview reloads and crashes.
+//!
+//! Each draft is stored as its own file under `drafts/` in the app data dir.
+//! Saves update memory immediately and are written to disk once typing pauses
+//! (and on exit). At most [`MAX_DRAFTS`] drafts are kept; the least recently
+//! updated ones are evicted first.
+//!
+//! With an encryption key ([`crate::encrypted_store`]) drafts are sealed into
+//! `.enc` files; plaintext `.json` drafts from an older build are rewritten
+//! sealed when they are loaded. Without one they stay `.json`, unless sealed
+//! drafts are already on disk (the key exists but can't be read this run,
+//! e.g. a locked keyring): then drafts are kept in memory for the session,
+//! so nothing is written in plaintext and the sealed files are left alone.
+
+use crate::encrypted_store::{self, StoreKey};
+use crate::error::CommandError;
+use crate::fcm::now_unix_ms;
+use crate::lock::LockState;
+use crate::storage;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+pub(crate) const DRAFTS_DIR: &str = "drafts";
+/// Maximum number of drafts kept
+const MAX_DRAFTS: usize = 200;
+/// Quiet period after the last save before drafts are written to disk
+const DRAFT_SAVE_DEBOUNCE: Duration = Duration::from_millis(750);
+
+/// A saved composer draft
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Draft {
+    pub feed_id: String,
+    pub content: String,
+    #[serde(default)]
+    pub attachments: Vec<String>,
+    /// Unix timestamp (ms) of the last save
+    pub updated_at: u64,
+}
+
+/// Managed state holding all drafts
+#[derive(Debug, Default)]
+pub struct DraftsState {
+    drafts: Mutex<HashMap<String, Draft>>,
+    /// Feeds whose draft changed since the last write
+    dirty: Mutex<HashSet<String>>,
+    generation: AtomicU64,
+    save_scheduled: AtomicBool,
+    /// Backing directory; `None` keeps drafts in memory only
+    dir: Option<PathBuf>,
+    /// Seals draft files when set
+    key: Option<StoreKey>,
+}
+
+/// File name for a feed's draft; the id is hex-encoded so any id is a valid name.
+fn file_name(feed_id: &str, sealed: bool) -> String {
+    let hex: String = feed_id.bytes().map(|b| format!("{:02x}", b)).collect();
+    format!("{}.{}", hex, if sealed { "enc" } else { "json" })
+}
+
+/// Read a draft file, opening it first if it is sealed.
+fn read_draft(path: &Path, key: Option<&StoreKey>) -> Option<Draft> {
+    let bytes = std::fs::read(path).ok()?;
+    let json = match (encrypted_store::is_sealed(&bytes), key) {
+        (false, _) => bytes,
+        (true, Some(key)) => match key.decrypt(&bytes) {
+            Ok(json) => json,
+            Err(e) => {
+                log::warn!("Skipping draft {}: {}", path.display(), e);
+                return None;
+            }
+        },
+        (true, None) => {
+            log::warn!("Skipping encrypted draft {}: no key", path.display());
+            return None;
+        }
+    };
+    match serde_json::from_slice(&json) {
+        Ok(draft) => Some(draft),
+        Err(e) => {
+            log::warn!("Ignoring invalid draft {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+fn read_dir(dir: &Path, key: Option<&StoreKey>) -> HashMap<String, Draft> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return HashMap::new();
+    };
+    entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext == "json" || ext == "enc")
+        })
+        .filter_map(|path| read_draft(&path, key))
+        .map(|draft| (draft.feed_id.clone(), draft))
+        .collect()
+}
+
+/// Whether `dir` holds sealed drafts.
+fn has_sealed(dir: &Path) -> bool {
+    std::fs::read_dir(dir).is_ok_and(|entries| {
+        entries
+            .filter_map(Result::ok)
+            .any(|entry| entry.path().extension().is_some_and(|ext| ext == "enc"))
+    })
+}
+
+/// The directory to keep drafts in: none (memory only) when sealed drafts
+/// exist but there is no key to open or write them.
+fn backing_dir(dir: Option<PathBuf>, key: Option<&StoreKey>) -> Option<PathBuf> {
+    let dir = dir?;
+    if key.is_none() && has_sealed(&dir) {
+        log::warn!("Encrypted drafts found but no key, keeping drafts in memory");
+        return None;
+    }
+    Some(dir)
+}
+
+impl DraftsState {
+    /// Load drafts from the app data dir. Must be managed after
+    /// [`encrypted_store::EncryptionState`].
+    pub fn load(app: &AppHandle) -> Self {
+        let key = encrypted_store::key(app);
+        let dir = backing_dir(storage::data_file(app, DRAFTS_DIR).ok(), key.as_ref());
+        let drafts = dir
+            .as_deref()
+            .map(|dir| read_dir(dir, key.as_ref()))
+            .unwrap_or_default();
+        let state = Self {
+            drafts: Mutex::new(drafts),
+            dir,
+            key,
+            ..Self::default()
+        };
+        state.encrypt_plaintext();
+        state
+    }
+
+    /// Rewrite plaintext drafts sealed, when there is a key.
+    fn encrypt_plaintext(&self) {
+        let (Some(dir), Some(_)) = (&self.dir, &self.key) else {
+            return;
+        };
+        let plaintext: Vec<String> = self
+            .lock()
+            .keys()
+            .filter(|feed_id| dir.join(file_name(feed_id, false)).exists())
+            .cloned()
+            .collect();
+        if plaintext.is_empty() {
+            return;
+        }
+        log::info!("Encrypting {} drafts", plaintext.len());
+        for feed_id in &plaintext {
+            self.mark_dirty(feed_id);
+        }
+        self.flush();
+    }
+
+    /// Serialize a draft, sealing it when there is a key.
+    fn encode(&self, draft: &Draft) -> Result<Vec<u8>, String> {
+        let json = serde_json::to_vec(draft).map_err(|e| e.to_string())?;
+        match &self.key {
+            Some(key) => key.encrypt(&json),
+            None => Ok(json),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Draft>> {
+        self.drafts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn mark_dirty(&self, feed_id: &str) {
+        self.dirty
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(feed_id.to_string());
+    }
+
+    /// Store a draft in memory, evicting the oldest beyond the limit.
+    ///
+    /// An empty draft (no content and no attachments) deletes it instead.
+    /// Call [`DraftsState::flush`] (or [`schedule_save`]) to persist.
+    pub fn save(&self, feed_id: String, content: String, attachments: Vec<String>) {
+        if content.is_empty() && attachments.is_empty() {
+            self.remove(&feed_id);
+            return;
+        }
+
+        let mut drafts = self.lock();
+        self.mark_dirty(&feed_id);
+        drafts.insert(
+            feed_id.clone(),
+            Draft {
+                feed_id,
+                content,
+                attachments,
+                updated_at: now_unix_ms(),
+            },
+        );
+        while drafts.len() > MAX_DRAFTS {
+            let Some(oldest) = drafts
+                .values()
+                .min_by_key(|draft| draft.updated_at)
+                .map(|draft| draft.feed_id.clone())
+            else {
+                break;
+            };
+            drafts.remove(&oldest);
+            self.mark_dirty(&oldest);
+        }
+    }
+
+    fn remove(&self, feed_id: &str) {
+        self.lock().remove(feed_id);
+        self.mark_dirty(feed_id);
+    }
+
+    pub fn get(&self, feed_id: &str) -> Option<Draft> {
+        self.lock().get(feed_id).cloned()
+    }
+
+    /// All drafts, most recently updated first.
+    pub fn list(&self) -> Vec<Draft> {
+        let mut drafts: Vec<Draft> = self.lock().values().cloned().collect();
+        drafts.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+        drafts
+    }
+
+    /// Forget every draft and delete the drafts directory; returns the bytes
+    /// freed.
+    pub fn clear(&self) -> Result<u64, String> {
+        let mut drafts = self.lock();
+        drafts.clear();
+        self.dirty.lock().unwrap_or_else(|e| e.into_inner()).clear();
+        let Some(dir) = &self.dir else {
+            return Ok(0);
+        };
+        let bytes = storage::disk_usage(dir);
+        match std::fs::remove_dir_all(dir) {
+            Ok(()) => Ok(bytes),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(0),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    /// Write every changed draft to disk, deleting files of removed drafts.
+    pub fn flush(&self) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        let dirty = std::mem::take(&mut *self.dirty.lock().unwrap_or_else(|e| e.into_inner()));
+        if dirty.is_empty() {
+            return;
+        }
+
+        let sealed = self.key.is_some();
+        let drafts = self.lock();
+        for feed_id in dirty {
+            let path = dir.join(file_name(&feed_id, sealed));
+            let result = match drafts.get(&feed_id) {
+                Some(draft) => self
+                    .encode(draft)
+                    .and_then(|bytes| storage::write_atomic(&path, &bytes)),
+                None => storage::remove_file(&path),
+            }
+            // A plaintext copy left by an older build; sealed files are
+            // never removed without a key
+            .and_then(|()| {
+                if sealed {
+                    storage::remove_file(&dir.join(file_name(&feed_id, false)))
+                } else {
+                    Ok(())
+                }
+            });
+            if let Err(e) = result {
+                log::warn!("Failed to save draft: {}", e);
+                self.mark_dirty(&feed_id);
+            }
+        }
+    }
+}
+
+/// Flush drafts to disk once saves have settled.
+fn schedule_save(app: &AppHandle) {
+    let state = app.state::<DraftsState>();
+    state.generation.fetch_add(1, Ordering::SeqCst);
+    if state.save_scheduled.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let state = app.state::<DraftsState>();
+        loop {
+            let seen = state.generation.load(Ordering::SeqCst);
+            std::thread::sleep(DRAFT_SAVE_DEBOUNCE);
+            if state.generation.load(Ordering::SeqCst) == seen {
+                break;
+            }
+        }
+        state.save_scheduled.store(false, Ordering::SeqCst);
+        state.flush();
+    });
+}
+
+/// Save the composer draft for a feed; an empty draft deletes it.
+#[tauri::command]
+pub fn save_draft(
+    app: AppHandle,
+    state: State<'_, DraftsState>,
+    feed_id: String,
+    content: String,
+    attachments: Vec<String>,
+) {
+    state.save(feed_id, content, attachments);
+    schedule_save(&app);
+}
+
+/// A feed's draft. Fails while the app is locked.
+#[tauri::command]
+pub fn get_draft(
+    state: State<'_, DraftsState>,
+    lock: State<'_, LockState>,
+    feed_id: String,
+) -> Result<Option<Draft>, CommandError> {
+    lock.ensure_unlocked()?;
+    Ok(state.get(&feed_id))
+}
+
+/// All drafts, most recently updated first. Fails while the app is locked.
+#[tauri::command]
+pub fn list_drafts(
+    state: State<'_, DraftsState>,
+    lock: State<'_, LockState>,
+) -> Result<Vec<Draft>, CommandError> {
+    lock.ensure_unlocked()?;
+    Ok(state.list())
+}
+
+/// Delete a feed's draft now (e.g. once the post is sent).
+#[tauri::command]
+pub fn delete_draft(state: State<'_, DraftsState>, feed_id: String) {
+    state.remove(&feed_id);
+    state.flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_save_deletes_the_draft() {
+        let state = DraftsState::default();
+        state.save("feed".into(), "hello".into(), vec![]);
+        assert_eq!(state.get("feed").unwrap().content, "hello");
+
+        state.save("feed".into(), String::new(), vec![]);
+        assert!(state.get("feed").is_none());
+    }
+
+    #[test]
+    fn list_is_sorted_by_recency() {
+        let state = DraftsState::default();
+        state.save("a".into(), "first".into(), vec![]);
+        state.save("b".into(), "second".into(), vec![]);
+        state.lock().get_mut("a").unwrap().updated_at = 1;
+
+        let ids: Vec<String> = state.list().into_iter().map(|d| d.feed_id).collect();
+        assert_eq!(ids, vec!["b", "a"]);
+    }
+
+    #[test]
+    fn oldest_drafts_are_evicted_beyond_the_cap() {
+        let state = DraftsState::default();
+        for i in 0..MAX_DRAFTS {
+            state.save(format!("feed-{}", i), "text".into(), vec![]);
+            state
+                .lock()
+                .get_mut(&format!("feed-{}", i))
+                .unwrap()
+                .updated_at = i as u64;
+        }
+        state.save("new".into(), "text".into(), vec![]);
+
+        assert_eq!(state.list().len(), MAX_DRAFTS);
+        assert!(state.get("feed-0").is_none());
+        assert!(state.get("feed-1").is_some());
+        assert!(state.get("new").is_some());
+    }
+
+    #[test]
+    fn flush_writes_and_removes_files() {
+        let dir = std::env::temp_dir().join(format!("hush-drafts-{}", std::process::id()));
+        let state = DraftsState {
+            dir: Some(dir.clone()),
+            ..DraftsState::default()
+        };
+
+        state.save("feed/1".into(), "hi".into(), vec!["a.png".into()]);
+        state.flush();
+        let loaded = read_dir(&dir, None);
+        assert_eq!(loaded.get("feed/1").unwrap().attachments, vec!["a.png"]);
+
+        state.remove("feed/1");
+        state.flush();
+        assert!(read_dir(&dir, None).is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn plaintext_drafts_are_rewritten_sealed() {
+        let dir = std::env::temp_dir().join(format!("hush-drafts-enc-{}", std::process::id()));
+        let plain = DraftsState {
+            dir: Some(dir.clone()),
+            ..DraftsState::default()
+        };
+        plain.save("feed".into(), "secret words".into(), vec![]);
+        plain.flush();
+
+        let key = StoreKey::generate();
+        let state = DraftsState {
+            drafts: Mutex::new(read_dir(&dir, Some(&key))),
+            dir: Some(dir.clone()),
+            key: Some(key.clone()),
+            ..DraftsState::default()
+        };
+        state.encrypt_plaintext();
+        assert!(!dir.join(file_name("feed", false)).exists());
+        let sealed = std::fs::read(dir.join(file_name("feed", true))).unwrap();
+        assert!(encrypted_store::is_sealed(&sealed));
+
+        assert!(read_dir(&dir, None).is_empty());
+        let loaded = read_dir(&dir, Some(&key));
+        assert_eq!(loaded.get("feed").unwrap().content, "secret words");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn sealed_drafts_without_a_key_stay_in_memory() {
+        let dir = std::env::temp_dir().join(format!("hush-drafts-nokey-{}", std::process::id()));
+        let key = StoreKey::generate();
+        let sealed = DraftsState {
+            dir: Some(dir.clone()),
+            key: Some(key.clone()),
+            ..DraftsState::default()
+        };
+        sealed.save("feed".into(), "secret words".into(), vec![]);
+        sealed.flush();
+
+        assert_eq!(backing_dir(Some(dir.clone()), None), None);
+        assert_eq!(
+            backing_dir(Some(dir.clone()), Some(&key)),
+            Some(dir.clone())
+        );
+
+        // Without a backing dir a save touches nothing on disk
+        let state = DraftsState::default();
+        state.save("feed".into(), "typed while locked".into(), vec![]);
+        state.flush();
+        assert!(dir.join(file_name("feed", true)).exists());
+        assert!(!dir.join(file_name("feed", false)).exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,260 @@
+//! Encryption at rest for the offline cache and composer drafts.
+//!
+//! On first run a random 256-bit key is generated and kept in the secure
+//! store ([`crate::secure_store`]) under a reserved name. The cache database
+//! is opened through SQLCipher with that key, and each draft file is sealed
+//! with AES-256-GCM, so nothing they write reaches the disk in plaintext.
+//!
+//! Data written by an older build is encrypted on the first launch that has
+//! a key: drafts as they are loaded, the cache by copying it into a new
+//! encrypted database in the background (see [`crate::cache`]), emitting
+//! `encryption-migration-progress` as it goes.
+//!
+//! Without a persistent credential store (e.g. no Secret Service daemon on a
+//! minimal Linux desktop) a key couldn't be found again after a restart, so
+//! data is stored unencrypted instead and [`EncryptionStatus::warning`] says
+//! why. A cache encrypted by an earlier run whose key can't be read right now
+//! (a locked keyring) is left alone and an in-memory cache is used for the
+//! session.
+
+use crate::secure_store::SecureStoreState;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use serde::Serialize;
+use std::fmt;
+use tauri::{AppHandle, Manager, State};
+
+/// Secure store entry holding the hex-encoded key
+pub(crate) const KEY_NAME: &str = "__hush_store_key";
+/// Prefix of every sealed file, followed by the nonce and the ciphertext
+const MAGIC: &[u8; 8] = b"HUSHENC1";
+const NONCE_LEN: usize = 12;
+
+/// The data encryption key
+#[derive(Clone, PartialEq, Eq)]
+pub struct StoreKey([u8; 32]);
+
+impl fmt::Debug for StoreKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("StoreKey(..)")
+    }
+}
+
+impl StoreKey {
+    pub(crate) fn generate() -> Self {
+        Self(Aes256Gcm::generate_key(OsRng).into())
+    }
+
+    fn to_hex(&self) -> String {
+        self.0.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn from_hex(hex: &str) -> Option<Self> {
+        if hex.len() != 64 || !hex.is_ascii() {
+            return None;
+        }
+        let mut key = [0u8; 32];
+        for (i, byte) in key.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+        }
+        Some(Self(key))
+    }
+
+    fn cipher(&self) -> Aes256Gcm {
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&self.0))
+    }
+
+    /// Seal `plaintext` with a fresh nonce.
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, String> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher()
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| "Encryption failed".to_string())?;
+        let mut sealed = Vec::with_capacity(MAGIC.len() + NONCE_LEN + ciphertext.len());
+        sealed.extend_from_slice(MAGIC);
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    /// Open data sealed by [`StoreKey::encrypt`].
+    pub fn decrypt(&self, sealed: &[u8]) -> Result<Vec<u8>, String> {
+        if !is_sealed(sealed) || sealed.len() < MAGIC.len() + NONCE_LEN {
+            return Err("Not encrypted data".to_string());
+        }
+        let (nonce, ciphertext) = sealed[MAGIC.len()..].split_at(NONCE_LEN);
+        self.cipher()
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| "Decryption failed: wrong key or damaged data".to_string())
+    }
+
+    /// Key a freshly opened SQLCipher connection. Must come before any other
+    /// statement.
+    pub fn apply(&self, conn: &rusqlite::Connection) -> rusqlite::Result<()> {
+        // Raw key syntax, so SQLCipher skips its passphrase derivation
+        conn.execute_batch(&format!("PRAGMA key = \"x'{}'\";", self.to_hex()))
+    }
+}
+
+/// Whether `data` was sealed by [`StoreKey::encrypt`].
+pub fn is_sealed(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Result of `get_encryption_status`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptionStatus {
+    pub available: bool,
+    /// Why the cache and drafts are stored unencrypted, when they are
+    pub warning: Option<String>,
+}
+
+/// Managed state holding the data encryption key, if there is one
+#[derive(Debug)]
+pub struct EncryptionState {
+    key: Option<StoreKey>,
+    warning: Option<String>,
+}
+
+impl EncryptionState {
+    /// Read the key from the secure store, creating it on first run. Must be
+    /// managed after [`SecureStoreState`] and before the cache and drafts.
+    pub fn load(app: &AppHandle) -> Self {
+        let state = Self::from_store(&app.state::<SecureStoreState>());
+        if let Some(warning) = &state.warning {
+            log::warn!("Storing the cache and drafts unencrypted: {}", warning);
+        }
+        state
+    }
+
+    fn from_store(store: &SecureStoreState) -> Self {
+        if !store.status().persistent {
+            return Self::unavailable("no credential store to keep the encryption key in");
+        }
+        match store.get(KEY_NAME) {
+            Ok(Some(hex)) => match StoreKey::from_hex(&hex) {
+                Some(key) => Self::with_key(key),
+                None => Self::unavailable("the stored encryption key is invalid"),
+            },
+            Ok(None) => {
+                let key = StoreKey::generate();
+                match store.set(KEY_NAME, &key.to_hex()) {
+                    Ok(()) => Self::with_key(key),
+                    Err(e) => Self::unavailable(&format!("the key could not be stored: {}", e)),
+                }
+            }
+            Err(e) => Self::unavailable(&format!("the key could not be read: {}", e)),
+        }
+    }
+
+    fn with_key(key: StoreKey) -> Self {
+        Self {
+            key: Some(key),
+            warning: None,
+        }
+    }
+
+    fn unavailable(reason: &str) -> Self {
+        Self {
+            key: None,
+            warning: Some(reason.to_string()),
+        }
+    }
+
+    pub fn key(&self) -> Option<&StoreKey> {
+        self.key.as_ref()
+    }
+
+    pub fn status(&self) -> EncryptionStatus {
+        EncryptionStatus {
+            available: self.key.is_some(),
+            warning: self.warning.clone(),
+        }
+    }
+}
+
+/// The key to encrypt local data with, if encryption is available.
+pub fn key(app: &AppHandle) -> Option<StoreKey> {
+    app.try_state::<EncryptionState>()?.key().cloned()
+}
+
+/// Whether the offline cache and drafts are encrypted on disk.
+#[tauri::command]
+pub fn is_encryption_available(state: State<'_, EncryptionState>) -> bool {
+    state.key().is_some()
+}
+
+/// Whether local data is encrypted, and if not, why.
+#[tauri::command]
+pub fn get_encryption_status(state: State<'_, EncryptionState>) -> EncryptionStatus {
+    state.status()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secure_store::BackendKind;
+
+    #[test]
+    fn sealed_data_opens_only_with_its_key() {
+        let key = StoreKey::generate();
+        let sealed = key.encrypt(b"draft text").unwrap();
+        assert!(is_sealed(&sealed));
+        assert!(!sealed.windows(10).any(|window| window == b"draft text"));
+        assert_eq!(key.decrypt(&sealed).unwrap(), b"draft text");
+
+        assert!(StoreKey::generate().decrypt(&sealed).is_err());
+        let mut damaged = sealed.clone();
+        *damaged.last_mut().unwrap() ^= 1;
+        assert!(key.decrypt(&damaged).is_err());
+        assert!(key.decrypt(b"{\"plain\":true}").is_err());
+    }
+
+    #[test]
+    fn key_round_trips_through_hex() {
+        let key = StoreKey::generate();
+        assert_eq!(StoreKey::from_hex(&key.to_hex()), Some(key.clone()));
+        assert!(StoreKey::from_hex("abc").is_none());
+        assert!(StoreKey::from_hex(&"zz".repeat(32)).is_none());
+        assert_eq!(format!("{:?}", key), "StoreKey(..)");
+    }
+
+    #[test]
+    fn database_is_unreadable_without_the_key() {
+        let dir = std::env::temp_dir().join(format!("hush-sqlcipher-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("test.db");
+        let key = StoreKey::generate();
+
+        let conn = rusqlite::Connection::open(&path).unwrap();
+        key.apply(&conn).unwrap();
+        conn.execute_batch("CREATE TABLE t (v TEXT); INSERT INTO t VALUES ('secret');")
+            .unwrap();
+        drop(conn);
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert!(!bytes.starts_with(b"SQLite format 3"));
+        let plain = rusqlite::Connection::open(&path).unwrap();
+        assert!(plain.query_row("SELECT v FROM t", [], |_| Ok(())).is_err());
+
+        let keyed = rusqlite::Connection::open(&path).unwrap();
+        key.apply(&keyed).unwrap();
+        let value: String = keyed
+            .query_row("SELECT v FROM t", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(value, "secret");
+
+        drop(keyed);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn memory_store_leaves_data_unencrypted() {
+        let store = SecureStoreState::with_backend(BackendKind::Memory, Default::default());
+        let state = EncryptionState::from_store(&store);
+        assert!(!state.status().available);
+        assert!(state.status().warning.is_some());
+    }
+}
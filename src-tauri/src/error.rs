@@ -0,0 +1,368 @@
+//! Error type shared by the Tauri commands.
+//!
+//! A failed command rejects with `{"code": ..., "message": ..., "retryable": ...}`.
+//! `code` is one of [`ERROR_CODES`] and is what the frontend should match on;
+//! `message` is for display and may be reworded. `retryable` says whether the
+//! same call may succeed later without the user changing anything.
+//!
+//! Library errors convert with `?`: io, reqwest, keyring, sqlite and secure
+//! store errors map onto the codes below, and plain `String` errors from older
+//! helpers become [`INTERNAL`].
+
+use crate::fcm::FcmError;
+use crate::secure_store::SecureStoreError;
+use serde::Serialize;
+use std::fmt;
+
+pub const INTERNAL: &str = "internal";
+pub const INVALID_ARGUMENT: &str = "invalid_argument";
+pub const NOT_FOUND: &str = "not_found";
+pub const PERMISSION_DENIED: &str = "permission_denied";
+pub const NOT_SUPPORTED: &str = "not_supported";
+pub const NOT_YET_AVAILABLE: &str = "not_yet_available";
+pub const UNAVAILABLE: &str = "unavailable";
+pub const TIMEOUT: &str = "timeout";
+pub const IO: &str = "io";
+pub const NETWORK: &str = "network";
+pub const HTTP_STATUS: &str = "http_status";
+pub const STORAGE: &str = "storage";
+pub const STORAGE_BUSY: &str = "storage_busy";
+pub const SECURE_STORE_LOCKED: &str = "secure_store_locked";
+pub const INVALID_NATIVE_VALUE: &str = "invalid_native_value";
+pub const NATIVE_BRIDGE_FAILURE: &str = "native_bridge_failure";
+pub const APP_LOCKED: &str = "app_locked";
+pub const RATE_LIMITED: &str = "rate_limited";
+pub const WRONG_PASSPHRASE: &str = "wrong_passphrase";
+pub const BACKUP_CORRUPTED: &str = "backup_corrupted";
+pub const QR_DATA_TOO_LONG: &str = "qr_data_too_long";
+
+/// One entry of [`ERROR_CODES`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ErrorCode {
+    pub code: &'static str,
+    /// Whether errors with this code are usually worth retrying
+    pub retryable: bool,
+    pub description: &'static str,
+}
+
+const fn entry(code: &'static str, retryable: bool, description: &'static str) -> ErrorCode {
+    ErrorCode {
+        code,
+        retryable,
+        description,
+    }
+}
+
+/// Every code a command can reject with. The frontend mirrors this list;
+/// codes are only ever added.
+pub const ERROR_CODES: &[ErrorCode] = &[
+    entry(INTERNAL, false, "Unexpected failure inside the app"),
+    entry(
+        INVALID_ARGUMENT,
+        false,
+        "An argument was missing or malformed",
+    ),
+    entry(NOT_FOUND, false, "The file, entry or row doesn't exist"),
+    entry(
+        PERMISSION_DENIED,
+        false,
+        "The OS or the user refused access",
+    ),
+    entry(NOT_SUPPORTED, false, "Not available on this platform"),
+    entry(
+        NOT_YET_AVAILABLE,
+        true,
+        "Not ready yet, e.g. no push token received",
+    ),
+    entry(
+        UNAVAILABLE,
+        true,
+        "A system service is temporarily unreachable",
+    ),
+    entry(TIMEOUT, true, "The operation took too long"),
+    entry(IO, false, "Reading or writing a file failed"),
+    entry(NETWORK, true, "The request couldn't reach the server"),
+    entry(
+        HTTP_STATUS,
+        false,
+        "The server answered with an error status",
+    ),
+    entry(STORAGE, false, "The local database failed"),
+    entry(
+        STORAGE_BUSY,
+        true,
+        "The local database is locked by another operation",
+    ),
+    entry(
+        SECURE_STORE_LOCKED,
+        true,
+        "The OS credential store is locked",
+    ),
+    entry(
+        INVALID_NATIVE_VALUE,
+        false,
+        "The native layer reported an unknown value",
+    ),
+    entry(
+        NATIVE_BRIDGE_FAILURE,
+        true,
+        "A call into the native layer failed",
+    ),
+    entry(
+        APP_LOCKED,
+        false,
+        "The app lock is on; unlock before reading private data",
+    ),
+    entry(RATE_LIMITED, true, "Too many attempts; wait and try again"),
+    entry(
+        WRONG_PASSPHRASE,
+        false,
+        "The passphrase doesn't match the backup",
+    ),
+    entry(
+        BACKUP_CORRUPTED,
+        false,
+        "The backup file is damaged or not a backup",
+    ),
+    entry(
+        QR_DATA_TOO_LONG,
+        false,
+        "The data doesn't fit in a QR code at the requested error correction",
+    ),
+];
+
+/// Whether `code` is retryable by default.
+fn default_retryable(code: &str) -> bool {
+    ERROR_CODES
+        .iter()
+        .find(|entry| entry.code == code)
+        .is_some_and(|entry| entry.retryable)
+}
+
+/// Error returned by the Tauri commands
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CommandError {
+    pub code: &'static str,
+    pub message: String,
+    pub retryable: bool,
+}
+
+impl CommandError {
+    /// An error with `code`, retryable if the code usually is.
+    pub fn new(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            retryable: default_retryable(code),
+        }
+    }
+
+    pub fn invalid_argument(message: impl Into<String>) -> Self {
+        Self::new(INVALID_ARGUMENT, message)
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        Self::new(INTERNAL, message)
+    }
+}
+
+impl From<std::io::Error> for CommandError {
+    fn from(error: std::io::Error) -> Self {
+        use std::io::ErrorKind;
+        let code = match error.kind() {
+            ErrorKind::NotFound => NOT_FOUND,
+            ErrorKind::PermissionDenied => PERMISSION_DENIED,
+            ErrorKind::TimedOut => TIMEOUT,
+            ErrorKind::Interrupted | ErrorKind::WouldBlock => UNAVAILABLE,
+            ErrorKind::InvalidInput | ErrorKind::InvalidData => INVALID_ARGUMENT,
+            _ => IO,
+        };
+        Self::new(code, error.to_string())
+    }
+}
+
+impl From<reqwest::Error> for CommandError {
+    fn from(error: reqwest::Error) -> Self {
+        let message = crate::logging::redact(&error.to_string());
+        if error.is_timeout() {
+            return Self::new(TIMEOUT, message);
+        }
+        if let Some(status) = error.status() {
+            // Server errors and rate limiting usually pass
+            let retryable =
+                status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS;
+            return Self {
+                retryable,
+                ..Self::new(HTTP_STATUS, message)
+            };
+        }
+        let code = if error.is_builder() {
+            INVALID_ARGUMENT
+        } else if error.is_connect() || error.is_request() || error.is_body() {
+            NETWORK
+        } else {
+            INTERNAL
+        };
+        Self::new(code, message)
+    }
+}
+
+#[cfg(not(target_os = "android"))]
+impl From<keyring::Error> for CommandError {
+    fn from(error: keyring::Error) -> Self {
+        let code = match &error {
+            keyring::Error::NoStorageAccess(_) => SECURE_STORE_LOCKED,
+            keyring::Error::PlatformFailure(_) => UNAVAILABLE,
+            keyring::Error::NoEntry => NOT_FOUND,
+            keyring::Error::BadEncoding(_)
+            | keyring::Error::TooLong(..)
+            | keyring::Error::Invalid(..) => INVALID_ARGUMENT,
+            _ => INTERNAL,
+        };
+        Self::new(code, error.to_string())
+    }
+}
+
+impl From<rusqlite::Error> for CommandError {
+    fn from(error: rusqlite::Error) -> Self {
+        use rusqlite::ErrorCode as Sqlite;
+        let code = match &error {
+            rusqlite::Error::QueryReturnedNoRows => NOT_FOUND,
+            rusqlite::Error::SqliteFailure(failure, _) => match failure.code {
+                Sqlite::DatabaseBusy | Sqlite::DatabaseLocked => STORAGE_BUSY,
+                Sqlite::CannotOpen | Sqlite::ReadOnly => IO,
+                _ => STORAGE,
+            },
+            _ => STORAGE,
+        };
+        Self::new(code, error.to_string())
+    }
+}
+
+impl From<FcmError> for CommandError {
+    fn from(error: FcmError) -> Self {
+        Self::new(error.code(), error.to_string())
+    }
+}
+
+impl From<SecureStoreError> for CommandError {
+    fn from(error: SecureStoreError) -> Self {
+        let code = match &error {
+            SecureStoreError::Locked(_) => SECURE_STORE_LOCKED,
+            SecureStoreError::Unavailable(_) => UNAVAILABLE,
+            SecureStoreError::Invalid(_) => INVALID_ARGUMENT,
+            SecureStoreError::AppLocked => APP_LOCKED,
+        };
+        Self::new(code, error.to_string())
+    }
+}
+
+/// The code table, for the frontend to check its copy against.
+#[tauri::command]
+pub fn get_error_codes() -> Vec<ErrorCode> {
+    ERROR_CODES.to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::ErrorKind;
+
+    fn io(kind: ErrorKind) -> CommandError {
+        std::io::Error::new(kind, "boom").into()
+    }
+
+    #[test]
+    fn codes_are_unique_and_serialized_flat() {
+        let mut codes: Vec<_> = ERROR_CODES.iter().map(|entry| entry.code).collect();
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len(), ERROR_CODES.len());
+
+        assert_eq!(
+            serde_json::to_value(CommandError::new(TIMEOUT, "slow")).unwrap(),
+            serde_json::json!({"code": "timeout", "message": "slow", "retryable": true})
+        );
+    }
+
+    #[test]
+    fn io_errors_map_by_kind() {
+        assert_eq!(io(ErrorKind::NotFound).code, NOT_FOUND);
+        assert_eq!(io(ErrorKind::PermissionDenied).code, PERMISSION_DENIED);
+        assert!(io(ErrorKind::TimedOut).retryable);
+        assert_eq!(io(ErrorKind::InvalidData).code, INVALID_ARGUMENT);
+        assert_eq!(io(ErrorKind::Other).code, IO);
+        assert_eq!(CommandError::from("oops".to_string()).code, INTERNAL);
+    }
+
+    #[test]
+    fn reqwest_errors_map_to_network_codes() {
+        let builder = reqwest::Client::new().get("not a url").build().unwrap_err();
+        assert_eq!(CommandError::from(builder).code, INVALID_ARGUMENT);
+
+        let status = |code: u16| {
+            let response = tauri::http::Response::builder()
+                .status(code)
+                .body("")
+                .unwrap();
+            CommandError::from(
+                reqwest::Response::from(response)
+                    .error_for_status()
+                    .unwrap_err(),
+            )
+        };
+        assert_eq!(status(503).code, HTTP_STATUS);
+        assert!(status(503).retryable);
+        assert!(status(429).retryable);
+        assert!(!status(404).retryable);
+    }
+
+    #[cfg(not(target_os = "android"))]
+    #[test]
+    fn keyring_errors_map_to_secure_store_codes() {
+        assert_eq!(CommandError::from(keyring::Error::NoEntry).code, NOT_FOUND);
+        let locked = CommandError::from(keyring::Error::NoStorageAccess("locked".into()));
+        assert_eq!(locked.code, SECURE_STORE_LOCKED);
+        assert!(locked.retryable);
+        let platform = CommandError::from(keyring::Error::PlatformFailure("dbus".into()));
+        assert_eq!(platform.code, UNAVAILABLE);
+    }
+
+    #[test]
+    fn sqlite_errors_map_to_storage_codes() {
+        let failure = |code| {
+            CommandError::from(rusqlite::Error::SqliteFailure(
+                rusqlite::ffi::Error::new(code),
+                None,
+            ))
+        };
+        assert_eq!(failure(rusqlite::ffi::SQLITE_BUSY).code, STORAGE_BUSY);
+        assert_eq!(failure(rusqlite::ffi::SQLITE_CORRUPT).code, STORAGE);
+        assert_eq!(
+            CommandError::from(rusqlite::Error::QueryReturnedNoRows).code,
+            NOT_FOUND
+        );
+    }
+
+    #[test]
+    fn fcm_errors_keep_their_codes() {
+        let error = CommandError::from(FcmError::NotYetAvailable);
+        assert_eq!(error.code, NOT_YET_AVAILABLE);
+        assert!(error.retryable);
+        assert_eq!(error.message, crate::fcm::TOKEN_NOT_YET_RECEIVED);
+        assert_eq!(
+            CommandError::from(FcmError::NotSupported).code,
+            NOT_SUPPORTED
+        );
+    }
+}
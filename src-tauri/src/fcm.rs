@@ -3,18 +3,248 @@
 //! This module provides Tauri commands for FCM operations.
 //! On Android, these commands interface with the Kotlin FCM implementation.
 //! On desktop, they return appropriate placeholder values.
+//!
+//! The current push token is held in [`FcmState`], which the native layer (or the
+//! frontend after a Kotlin bridge call) updates through `set_fcm_token`. Token
+//! rotations reported through `notify_fcm_token_refreshed` also emit
+//! `fcm-token-refreshed` so the frontend can re-register.
+
+use crate::error::{self, CommandError};
+use crate::storage;
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::VecDeque;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::plugin::PermissionState;
+use tauri::{AppHandle, Emitter, Manager, State};
+#[cfg(mobile)]
+use tauri_plugin_notification::NotificationExt;
+
+/// Message of [`FcmError::NotYetAvailable`], and the legacy `get_fcm_token`
+/// error text the frontend used to match on.
+pub const TOKEN_NOT_YET_RECEIVED: &str = "Push token not yet received";
+
+/// Message of [`FcmError::NotSupported`], on platforms without push support.
+pub const PUSH_NOT_SUPPORTED: &str = "Push notifications not available on desktop";
+
+/// Errors from the push commands, sent to the frontend as
+/// `{"code": ..., "message": ...}`: as is by `get_fcm_token` and
+/// `clear_pending_navigation`, as a [`CommandError`] by the others.
+///
+/// `code` is stable and meant to be matched on; `message` is for display and
+/// may be reworded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FcmError {
+    /// The platform has no push channel
+    NotSupported,
+    /// Push is supported but the native layer hasn't reported a token yet
+    NotYetAvailable,
+    /// The user permanently denied notifications
+    PermissionDenied,
+    /// The native layer reported something this build doesn't understand
+    InvalidNativeValue { detail: String },
+    /// A call into the native layer failed
+    #[cfg_attr(desktop, allow(dead_code))]
+    NativeBridgeFailure { detail: String },
+}
 
-use serde::{Deserialize, Serialize};
+impl FcmError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            FcmError::NotSupported => error::NOT_SUPPORTED,
+            FcmError::NotYetAvailable => error::NOT_YET_AVAILABLE,
+            FcmError::PermissionDenied => error::PERMISSION_DENIED,
+            FcmError::InvalidNativeValue { .. } => error::INVALID_NATIVE_VALUE,
+            FcmError::NativeBridgeFailure { .. } => error::NATIVE_BRIDGE_FAILURE,
+        }
+    }
+}
+
+impl fmt::Display for FcmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FcmError::NotSupported => f.write_str(PUSH_NOT_SUPPORTED),
+            FcmError::NotYetAvailable => f.write_str(TOKEN_NOT_YET_RECEIVED),
+            FcmError::PermissionDenied => f.write_str("Notification permission was denied"),
+            FcmError::InvalidNativeValue { detail } => {
+                write!(f, "Unexpected value from the native layer: {}", detail)
+            }
+            FcmError::NativeBridgeFailure { detail } => {
+                write!(f, "Native push bridge failed: {}", detail)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FcmError {}
+
+impl Serialize for FcmError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut error = serializer.serialize_struct("FcmError", 2)?;
+        error.serialize_field("code", self.code())?;
+        error.serialize_field("message", &self.to_string())?;
+        error.end()
+    }
+}
+
+/// Push service a token belongs to, so the server registers it correctly
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenType {
+    #[default]
+    Fcm,
+    Apns,
+}
+
+#[derive(Debug, Clone)]
+struct PushToken {
+    value: String,
+    token_type: TokenType,
+}
+
+/// Managed state holding the most recent push token reported by the native layer,
+/// and on iOS the last notification authorization it reported
+#[derive(Debug, Default)]
+pub struct FcmState {
+    token: Mutex<Option<PushToken>>,
+    authorization: Mutex<Option<PermissionResult>>,
+}
+
+impl FcmState {
+    /// Store a new FCM token, treating an empty string as "no token".
+    pub fn set_token(&self, token: String) {
+        self.store_token(token, TokenType::Fcm);
+    }
+
+    /// Store a new APNs device token, treating an empty string as "no token".
+    pub fn set_apns_token(&self, token: String) {
+        self.store_token(token, TokenType::Apns);
+    }
+
+    fn store_token(&self, token: String, token_type: TokenType) {
+        let token = Some(token)
+            .filter(|token| !token.is_empty())
+            .map(|value| PushToken { value, token_type });
+        *self.token.lock().unwrap_or_else(|e| e.into_inner()) = token;
+    }
+
+    /// Store a rotated FCM token, returning the change unless it equals the stored one.
+    ///
+    /// Empty tokens are ignored: a rotation always produces a new token.
+    pub fn refresh_token(&self, token: String) -> Option<TokenRefreshedPayload> {
+        if token.is_empty() {
+            return None;
+        }
+        let mut current = self.token.lock().unwrap_or_else(|e| e.into_inner());
+        if current.as_ref().map(|current| current.value.as_str()) == Some(token.as_str()) {
+            return None;
+        }
+        let old_token = current
+            .replace(PushToken {
+                value: token.clone(),
+                token_type: TokenType::Fcm,
+            })
+            .map(|old| old.value);
+        Some(TokenRefreshedPayload {
+            old_token,
+            new_token: token,
+        })
+    }
+
+    /// Current token, if one has been received.
+    pub fn token(&self) -> Option<String> {
+        self.push_token().map(|token| token.value)
+    }
+
+    fn push_token(&self) -> Option<PushToken> {
+        self.token.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Record the authorization status reported by the native layer.
+    pub fn set_authorization(&self, permission: PermissionResult) {
+        *self.authorization.lock().unwrap_or_else(|e| e.into_inner()) = Some(permission);
+    }
+
+    /// Notification permission as last reported by the native layer, falling
+    /// back to the platform default.
+    pub fn permission(&self) -> PermissionResult {
+        self.authorization
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+            .unwrap_or_else(default_permission)
+    }
+
+    /// Build the command result for the current token.
+    ///
+    /// With no token yet, a permanent denial is reported instead of
+    /// [`FcmError::NotYetAvailable`] so the frontend stops waiting for one.
+    pub fn token_result(&self) -> Result<FcmTokenResult, FcmError> {
+        if !native_push_supported() {
+            return Err(FcmError::NotSupported);
+        }
+
+        match self.push_token() {
+            Some(token) => Ok(FcmTokenResult {
+                token: Some(token.value),
+                token_type: Some(token.token_type),
+                error: None,
+                error_code: None,
+            }),
+            None => {
+                let permission = self.permission();
+                if !permission.granted && !permission.can_request {
+                    Err(FcmError::PermissionDenied)
+                } else {
+                    Err(FcmError::NotYetAvailable)
+                }
+            }
+        }
+    }
+}
+
+/// Payload of the `fcm-token-refreshed` event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TokenRefreshedPayload {
+    pub old_token: Option<String>,
+    pub new_token: String,
+}
 
 /// Result type for FCM token operations
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FcmTokenResult {
     pub token: Option<String>,
+    /// Which push service `token` is for; None when there is no token
+    #[serde(default)]
+    pub token_type: Option<TokenType>,
+    /// Deprecated: match on `error_code` instead. Still carries the old
+    /// "not yet received" message for one release so older callers keep
+    /// waiting for a token.
     pub error: Option<String>,
+    /// "not_yet_available" while there is no token yet, else None
+    #[serde(default)]
+    pub error_code: Option<String>,
+}
+
+impl FcmTokenResult {
+    /// No token yet: the one failure that still resolves, as it did before
+    /// [`FcmError`] existed.
+    fn not_yet_available() -> Self {
+        FcmTokenResult {
+            token: None,
+            token_type: None,
+            error: Some(TOKEN_NOT_YET_RECEIVED.to_string()),
+            error_code: Some(FcmError::NotYetAvailable.code().to_string()),
+        }
+    }
 }
 
 /// Result type for permission check
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PermissionResult {
     pub granted: bool,
     pub can_request: bool,
@@ -51,9 +281,12 @@ pub fn get_platform() -> String {
 pub fn get_device_name() -> String {
     #[cfg(target_os = "android")]
     {
-        // On Android, the Kotlin layer provides device name via MainActivity.getDeviceName()
-        // For now, return a placeholder - TypeScript will call the Kotlin bridge directly
-        "Android Device".to_string()
+        // Read Build.MANUFACTURER / Build.MODEL over JNI, falling back to the
+        // placeholder if the JVM call fails for any reason
+        crate::android::device_name().unwrap_or_else(|error| {
+            log::warn!("Failed to read Android device name: {}", error);
+            "Android Device".to_string()
+        })
     }
     #[cfg(target_os = "ios")]
     {
@@ -69,14 +302,8 @@ pub fn get_device_name() -> String {
     }
 }
 
-/// Check if notification permission is granted
-///
-/// On Android 13+: Checks POST_NOTIFICATIONS permission
-/// On Android <13: Always returns true (implicit permission)
-/// On iOS: Checks notification authorization status
-/// On desktop: Always returns true (no permission needed)
-#[tauri::command]
-pub fn has_notification_permission() -> PermissionResult {
+/// Notification permission assumed before the native layer reports anything
+fn default_permission() -> PermissionResult {
     #[cfg(target_os = "android")]
     {
         // On Android, the actual permission check is done in Kotlin
@@ -89,9 +316,9 @@ pub fn has_notification_permission() -> PermissionResult {
     }
     #[cfg(target_os = "ios")]
     {
-        // Similar pattern for iOS
+        // Nothing reported yet: treat as not determined
         PermissionResult {
-            granted: true,
+            granted: false,
             can_request: true,
         }
     }
@@ -105,125 +332,762 @@ pub fn has_notification_permission() -> PermissionResult {
     }
 }
 
-/// Get the FCM token for push notifications
+/// Map an iOS `UNAuthorizationStatus` name onto [`PermissionResult`].
+fn permission_from_authorization(status: &str) -> Result<PermissionResult, FcmError> {
+    match status {
+        "authorized" | "provisional" | "ephemeral" => Ok(PermissionResult {
+            granted: true,
+            can_request: false,
+        }),
+        "denied" => Ok(PermissionResult {
+            granted: false,
+            can_request: false,
+        }),
+        "notDetermined" => Ok(PermissionResult {
+            granted: false,
+            can_request: true,
+        }),
+        other => Err(FcmError::InvalidNativeValue {
+            detail: format!("unknown authorization status {}", other),
+        }),
+    }
+}
+
+/// Check if notification permission is granted
 ///
-/// On Android: Returns the FCM token stored by the Kotlin layer
-/// On iOS: Returns the APNs token (future implementation)
-/// On desktop: Returns None (push notifications not supported)
+/// On Android 13+: Checks POST_NOTIFICATIONS permission
+/// On Android <13: Always returns true (implicit permission)
+/// On iOS: The last authorization status reported via `set_notification_authorization`
+/// (not granted, but requestable, until one arrives)
+/// On desktop: Always returns true (no permission needed)
 #[tauri::command]
-pub fn get_fcm_token() -> FcmTokenResult {
-    #[cfg(target_os = "android")]
+pub fn has_notification_permission(state: State<'_, FcmState>) -> PermissionResult {
+    state.permission()
+}
+
+/// Record the notification authorization status reported by the iOS layer.
+///
+/// `status` is the `UNAuthorizationStatus` case name: "authorized",
+/// "provisional", "ephemeral", "denied", or "notDetermined".
+#[tauri::command]
+pub fn set_notification_authorization(
+    state: State<'_, FcmState>,
+    status: String,
+) -> Result<(), CommandError> {
+    state.set_authorization(permission_from_authorization(&status)?);
+    Ok(())
+}
+
+/// Map the notification plugin's permission state onto [`PermissionResult`].
+///
+/// `Denied` is reported by the OS after a permanent denial, so it can no longer
+/// be requested from inside the app.
+#[cfg_attr(desktop, allow(dead_code))]
+fn permission_from_state(state: PermissionState) -> PermissionResult {
+    match state {
+        PermissionState::Granted => PermissionResult {
+            granted: true,
+            can_request: false,
+        },
+        PermissionState::Denied => PermissionResult {
+            granted: false,
+            can_request: false,
+        },
+        PermissionState::Prompt | PermissionState::PromptWithRationale => PermissionResult {
+            granted: false,
+            can_request: true,
+        },
+    }
+}
+
+/// Ask the OS for notification permission, returning the resulting state.
+///
+/// On Android 13+ and iOS this shows the system prompt via the notification
+/// plugin; the call runs off the main thread while the dialog is open.
+/// It is idempotent: if permission is already granted (or permanently denied)
+/// no prompt is shown.
+/// On desktop: Immediately reports granted (no permission needed)
+#[tauri::command]
+pub async fn request_notification_permission(
+    app: AppHandle,
+) -> Result<PermissionResult, CommandError> {
+    #[cfg(mobile)]
     {
-        // On Android, the FCM token is managed by FcmService.kt
-        // It's stored in SharedPreferences and accessed via MainActivity.getFcmToken()
-        // TypeScript should call the Kotlin bridge directly for the actual token
-        // This command provides the interface structure
-        FcmTokenResult {
-            token: None, // TypeScript retrieves from Kotlin bridge
-            error: Some("Use native bridge to retrieve FCM token".to_string()),
-        }
+        let bridge_failure = |e: &dyn fmt::Display| FcmError::NativeBridgeFailure {
+            detail: e.to_string(),
+        };
+        tauri::async_runtime::spawn_blocking(move || {
+            let notification = app.notification();
+            let current = notification
+                .permission_state()
+                .map_err(|e| bridge_failure(&e))?;
+            if matches!(current, PermissionState::Granted | PermissionState::Denied) {
+                return Ok(permission_from_state(current));
+            }
+
+            notification
+                .request_permission()
+                .map(permission_from_state)
+                .map_err(|e| bridge_failure(&e))
+        })
+        .await
+        .map_err(|e| bridge_failure(&e))?
+        .map_err(CommandError::from)
     }
-    #[cfg(target_os = "ios")]
+    #[cfg(desktop)]
     {
-        // iOS uses APNs, which will be implemented in FEAT-034
-        FcmTokenResult {
-            token: None,
-            error: Some("iOS push notifications not yet implemented".to_string()),
+        let _ = app;
+        Ok(PermissionResult {
+            granted: true,
+            can_request: false,
+        })
+    }
+}
+
+/// Payload of the `notification-permission-changed` event
+#[derive(Debug, Clone, Serialize)]
+pub struct PermissionChangedPayload {
+    pub previous: PermissionResult,
+    pub current: PermissionResult,
+}
+
+/// Managed state caching the last observed notification permission
+#[derive(Debug, Default)]
+pub struct PermissionWatchState {
+    last: Mutex<Option<PermissionResult>>,
+}
+
+impl PermissionWatchState {
+    /// Record `current`, returning the change if it differs from the last value.
+    ///
+    /// The first observation only establishes a baseline and never reports a change.
+    pub fn observe(&self, current: PermissionResult) -> Option<PermissionChangedPayload> {
+        let mut last = self.last.lock().unwrap_or_else(|e| e.into_inner());
+        let previous = last.replace(current.clone())?;
+        (previous != current).then_some(PermissionChangedPayload { previous, current })
+    }
+}
+
+/// Read the current permission from the OS.
+fn current_permission(app: &AppHandle) -> PermissionResult {
+    #[cfg(mobile)]
+    {
+        match app.notification().permission_state() {
+            Ok(state) => permission_from_state(state),
+            Err(e) => {
+                log::warn!("Failed to read notification permission: {}", e);
+                app.state::<FcmState>().permission()
+            }
         }
     }
-    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    #[cfg(desktop)]
     {
-        // Desktop doesn't support FCM/APNs push notifications
-        FcmTokenResult {
-            token: None,
-            error: Some("Push notifications not available on desktop".to_string()),
+        app.state::<FcmState>().permission()
+    }
+}
+
+/// Re-check notification permission and emit `notification-permission-changed`
+/// if it flipped since the last check.
+///
+/// Called on window focus and app resume so the UI learns about changes made in
+/// system settings without polling. The check runs off the calling thread.
+pub fn recheck_notification_permission(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let current = current_permission(&app);
+        let Some(state) = app.try_state::<PermissionWatchState>() else {
+            return;
+        };
+        if let Some(change) = state.observe(current) {
+            log::info!(
+                "Notification permission changed: granted {} -> {}",
+                change.previous.granted,
+                change.current.granted
+            );
+            let _ = app.emit("notification-permission-changed", change);
         }
+    });
+}
+
+/// Get the FCM token for push notifications
+///
+/// Reads the token stored in [`FcmState`] on all platforms.
+/// On Android: Returns the FCM token pushed in via `set_fcm_token`
+/// On iOS: Returns the APNs device token pushed in via `set_apns_token`
+/// (or an FCM token, if the Swift layer uses Firebase); `token_type` says which
+/// On desktop: Rejects with [`FcmError::NotSupported`]
+///
+/// If push is supported but no token has arrived yet, this still resolves,
+/// with `error_code` "not_yet_available" and the old message in `error`, so
+/// the frontend can retry later. Other failures reject with an [`FcmError`].
+#[tauri::command]
+pub fn get_fcm_token(state: State<'_, FcmState>) -> Result<FcmTokenResult, FcmError> {
+    match state.token_result() {
+        Err(FcmError::NotYetAvailable) => Ok(FcmTokenResult::not_yet_available()),
+        result => result,
     }
 }
 
-/// Check if push notifications are supported on this platform
+/// Store the current FCM token in managed state.
+///
+/// Called by the native layer, or by TypeScript after reading the token from the
+/// Kotlin bridge, so that `get_fcm_token` has a single source of truth.
+#[tauri::command]
+pub fn set_fcm_token(app: AppHandle, state: State<'_, FcmState>, token: String) {
+    state.set_token(token);
+    crate::push_diagnostics::record_token_refresh(&app);
+}
+
+/// Store the APNs device token reported by the Swift layer.
+///
+/// `get_fcm_token` then returns it with `token_type` "apns" so the server
+/// registers it with APNs rather than FCM.
 #[tauri::command]
-pub fn is_push_supported() -> bool {
-    #[cfg(any(target_os = "android", target_os = "ios"))]
+pub fn set_apns_token(app: AppHandle, state: State<'_, FcmState>, token: String) {
+    state.set_apns_token(token);
+    crate::push_diagnostics::record_token_refresh(&app);
+}
+
+/// Record a rotated push token and emit `fcm-token-refreshed` so the frontend
+/// can re-register with the server.
+///
+/// Nothing is emitted if the token is unchanged.
+pub fn notify_token_refreshed(app: &AppHandle, token: String) {
+    let Some(change) = app.state::<FcmState>().refresh_token(token) else {
+        return;
+    };
+    log::info!("Push token refreshed");
+    crate::push_diagnostics::record_token_refresh(app);
+    let _ = app.emit("fcm-token-refreshed", change);
+}
+
+/// Report a rotated FCM token from the native bridge.
+///
+/// Unlike `set_fcm_token`, this emits `fcm-token-refreshed` when the token changes.
+#[tauri::command]
+pub fn notify_fcm_token_refreshed(app: AppHandle, token: String) {
+    notify_token_refreshed(&app, token);
+}
+
+/// Outcome of one step of [`invalidate_push_registration`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", content = "error", rename_all = "lowercase")]
+pub enum StepOutcome {
+    Ok,
+    /// Nothing to do on this platform
+    Skipped,
+    Failed(String),
+}
+
+impl From<Result<(), String>> for StepOutcome {
+    fn from(result: Result<(), String>) -> Self {
+        match result {
+            Ok(()) => StepOutcome::Ok,
+            Err(e) => StepOutcome::Failed(e),
+        }
+    }
+}
+
+/// Per-step result of [`invalidate_push_registration`]
+#[derive(Debug, Clone, Serialize)]
+pub struct PushInvalidationResult {
+    pub token_cleared: StepOutcome,
+    pub native_token_deleted: StepOutcome,
+    pub pending_navigation_cleared: StepOutcome,
+}
+
+/// Ask the native layer to delete this device's push token.
+async fn delete_native_token() -> StepOutcome {
+    #[cfg(target_os = "android")]
+    {
+        tauri::async_runtime::spawn_blocking(crate::android::delete_fcm_token)
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|result| result)
+            .into()
+    }
+    #[cfg(not(target_os = "android"))]
+    {
+        StepOutcome::Skipped
+    }
+}
+
+/// Forget this device's push registration, e.g. on logout.
+///
+/// Clears the stored token, deletes the FCM token natively (Android only), and
+/// drops pending navigations. Every step runs even if an earlier one fails, and
+/// each outcome is reported; calling it again is harmless.
+#[tauri::command]
+pub async fn invalidate_push_registration(app: AppHandle) -> PushInvalidationResult {
+    app.state::<FcmState>().set_token(String::new());
+    let token_cleared = StepOutcome::Ok;
+
+    let native_token_deleted = delete_native_token().await;
+    if let StepOutcome::Failed(e) = &native_token_deleted {
+        log::warn!("Failed to delete native push token: {}", e);
+    }
+
+    app.state::<PendingNavigationState>().clear(None);
+    #[cfg(target_os = "android")]
+    let pending_navigation_cleared = crate::android::clear_pending_navigation().into();
+    #[cfg(not(target_os = "android"))]
+    let pending_navigation_cleared = StepOutcome::Ok;
+
+    PushInvalidationResult {
+        token_cleared,
+        native_token_deleted,
+        pending_navigation_cleared,
+    }
+}
+
+/// Whether the platform has native push (FCM on Android, APNs on iOS)
+///
+/// On Android FCM needs Google Play Services, which de-Googled builds
+/// (GrapheneOS, LineageOS without gapps) lack.
+pub fn native_push_supported() -> bool {
+    #[cfg(target_os = "android")]
+    {
+        play_services_available()
+    }
+    #[cfg(target_os = "ios")]
     {
         true
     }
-    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    #[cfg(desktop)]
     {
         false
     }
 }
 
+/// Play Services availability, checked once per launch over JNI.
+///
+/// If the check itself fails, FCM is assumed to work as before.
+#[cfg(target_os = "android")]
+fn play_services_available() -> bool {
+    static AVAILABLE: std::sync::OnceLock<bool> = std::sync::OnceLock::new();
+    *AVAILABLE.get_or_init(|| {
+        crate::android::play_services_available().unwrap_or_else(|e| {
+            log::warn!("Failed to check Google Play Services: {}", e);
+            true
+        })
+    })
+}
+
+/// Whether a desktop push channel (event stream or UnifiedPush) is configured.
+fn alternative_push_configured(app: &AppHandle) -> bool {
+    #[cfg(all(target_os = "linux", feature = "unifiedpush"))]
+    if app
+        .try_state::<crate::push_unifiedpush::UnifiedPushState>()
+        .is_some_and(|state| state.is_registered())
+    {
+        return true;
+    }
+    #[cfg(desktop)]
+    if app
+        .try_state::<crate::push_stream::PushStreamState>()
+        .is_some_and(|state| state.is_configured())
+    {
+        return true;
+    }
+    let _ = app;
+    false
+}
+
+/// Check if push notifications are supported on this platform
+///
+/// True on iOS and on Android with Play Services; on desktop when the
+/// background event stream is configured, or on Linux when a UnifiedPush
+/// endpoint is registered.
+#[tauri::command]
+pub fn is_push_supported(app: AppHandle) -> bool {
+    native_push_supported() || alternative_push_configured(&app)
+}
+
+/// Why push is or isn't available
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PushSupportReason {
+    Ok,
+    NoPlayServices,
+    PermissionDenied,
+    PlatformUnsupported,
+}
+
+/// Result of `get_push_support_details`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PushSupportDetails {
+    pub supported: bool,
+    pub reason: PushSupportReason,
+}
+
+/// Combine availability and permission into a support verdict.
+///
+/// `unavailable` is the reason no push channel exists, or None if one does.
+fn support_details(
+    unavailable: Option<PushSupportReason>,
+    permission: &PermissionResult,
+) -> PushSupportDetails {
+    let reason = match unavailable {
+        Some(reason) => reason,
+        None if !permission.granted => PushSupportReason::PermissionDenied,
+        None => PushSupportReason::Ok,
+    };
+    PushSupportDetails {
+        supported: reason == PushSupportReason::Ok,
+        reason,
+    }
+}
+
+/// Explain whether push works on this device, so the UI can tell the user why not.
+#[tauri::command]
+pub fn get_push_support_details(app: AppHandle, state: State<'_, FcmState>) -> PushSupportDetails {
+    let unavailable = if native_push_supported() || alternative_push_configured(&app) {
+        None
+    } else if cfg!(target_os = "android") {
+        Some(PushSupportReason::NoPlayServices)
+    } else {
+        Some(PushSupportReason::PlatformUnsupported)
+    };
+    support_details(unavailable, &state.permission())
+}
+
+/// What a pending navigation points at
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NavigationKind {
+    #[default]
+    Feed,
+    Post,
+    Mention,
+    Dm,
+}
+
+impl NavigationKind {
+    /// Parse the notification `type` string used by the native layer.
+    ///
+    /// Unknown or missing values fall back to `Post` when a post id is present and `Feed` otherwise.
+    pub fn from_type(kind: Option<&str>, has_post: bool) -> Self {
+        match kind.map(|kind| kind.trim().to_ascii_lowercase()).as_deref() {
+            Some("feed") => NavigationKind::Feed,
+            Some("post") | Some("reply") => NavigationKind::Post,
+            Some("mention") => NavigationKind::Mention,
+            Some("dm") => NavigationKind::Dm,
+            _ if has_post => NavigationKind::Post,
+            _ => NavigationKind::Feed,
+        }
+    }
+}
+
 /// Result type for pending navigation operations
+///
+/// `post_id` and `kind` were added after `feed_id`; payloads without them still
+/// deserialize with both set to None.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PendingNavigationResult {
     pub feed_id: Option<String>,
+    #[serde(default)]
+    pub post_id: Option<String>,
+    #[serde(default)]
+    pub kind: Option<NavigationKind>,
+    #[serde(default)]
+    pub seq: Option<u64>,
 }
 
-/// Get pending feed navigation from notification tap.
+/// A single queued navigation request, e.g. from a notification tap
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingNavigation {
+    pub feed_id: String,
+    #[serde(default)]
+    pub post_id: Option<String>,
+    #[serde(default)]
+    pub kind: NavigationKind,
+    /// Unix timestamp (ms) at which the navigation was queued
+    pub received_at: u64,
+    /// Increases with every navigation queued; the `pending-navigation` event
+    /// carries the same value, so the frontend can drop a navigation it gets
+    /// both from the event and from a poll
+    #[serde(default)]
+    pub seq: u64,
+}
+
+pub(crate) const PENDING_NAVIGATION_FILE: &str = "pending-navigation.json";
+/// Persisted navigations older than this (24 hours) are dropped on startup
+const PENDING_NAVIGATION_MAX_AGE_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// Managed state holding pending navigations in FIFO order.
 ///
-/// When a user taps a push notification, the feedId is stored by the native layer.
-/// This command retrieves that feedId so TypeScript can navigate to the correct feed.
+/// Each feed appears at most once: queueing a feed that is already pending moves
+/// it to the back of the queue with a fresh timestamp.
 ///
-/// On Android: Returns feedId stored by MainActivity when handling notification tap
-/// On iOS: Similar pattern (future implementation)
-/// On desktop: Always returns None (no push notifications)
-#[tauri::command]
-pub fn get_pending_navigation() -> PendingNavigationResult {
-    #[cfg(target_os = "android")]
-    {
-        // On Android, the pending navigation is stored in SharedPreferences
-        // by MainActivity when a notification is tapped.
-        // The Kotlin layer provides FcmService.getPendingNavigation(context)
-        // TypeScript should call this via the Kotlin bridge
-        PendingNavigationResult {
-            feed_id: None, // TypeScript retrieves from Kotlin bridge directly
+/// On desktop the queue is mirrored to `pending-navigation.json` in the app
+/// data dir, so a navigation from a notification click survives a crash before
+/// the frontend handles it. On mobile the native layer keeps its own copy.
+#[derive(Debug, Default)]
+pub struct PendingNavigationState {
+    pub queue: Mutex<VecDeque<PendingNavigation>>,
+    /// Backing file; `None` keeps the queue in memory only
+    path: Option<PathBuf>,
+    /// `seq` of the last navigation queued
+    last_seq: AtomicU64,
+}
+
+impl PendingNavigationState {
+    /// Load the queue persisted by a previous run, dropping corrupt entries
+    /// and ones older than 24 hours.
+    pub fn load(app: &AppHandle) -> Self {
+        #[cfg(desktop)]
+        let path = storage::data_file(app, PENDING_NAVIGATION_FILE).ok();
+        #[cfg(mobile)]
+        let path: Option<PathBuf> = {
+            let _ = app;
+            None
+        };
+        let stored: Vec<serde_json::Value> = path
+            .as_deref()
+            .and_then(storage::read_json)
+            .unwrap_or_default();
+        let before = stored.len();
+        let queue = restore_navigations(stored, now_unix_ms());
+        let last_seq = queue.iter().map(|entry| entry.seq).max().unwrap_or(0);
+
+        let state = Self {
+            queue: Mutex::new(queue),
+            path,
+            last_seq: AtomicU64::new(last_seq),
+        };
+        let dropped = before - state.lock().len();
+        if dropped > 0 {
+            log::info!("Dropped {} stale or invalid pending navigation(s)", dropped);
+            state.persist(&state.lock());
         }
+        state
     }
-    #[cfg(target_os = "ios")]
-    {
-        // iOS implementation will follow similar pattern
-        PendingNavigationResult {
-            feed_id: None,
+
+    fn lock(&self) -> MutexGuard<'_, VecDeque<PendingNavigation>> {
+        self.queue.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn persist(&self, queue: &VecDeque<PendingNavigation>) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if let Err(e) = storage::write_json_atomic(path, queue) {
+            log::warn!("Failed to save pending navigation: {}", e);
         }
     }
-    #[cfg(not(any(target_os = "android", target_os = "ios")))]
-    {
-        // Desktop doesn't have push notification navigation
-        PendingNavigationResult {
-            feed_id: None,
+
+    /// Queue a navigation to `feed_id`, optionally targeting a specific post.
+    /// Returns the queued entry. See [`queue_navigation`] to also notify a
+    /// running frontend.
+    pub fn enqueue(
+        &self,
+        feed_id: String,
+        post_id: Option<String>,
+        kind: NavigationKind,
+    ) -> PendingNavigation {
+        let mut queue = self.lock();
+        queue.retain(|entry| entry.feed_id != feed_id);
+        let entry = PendingNavigation {
+            feed_id,
+            post_id,
+            kind,
+            received_at: now_unix_ms(),
+            seq: self.last_seq.fetch_add(1, Ordering::SeqCst) + 1,
+        };
+        queue.push_back(entry.clone());
+        self.persist(&queue);
+        entry
+    }
+
+    /// Oldest pending navigation, if any.
+    pub fn head(&self) -> Option<PendingNavigation> {
+        self.lock().front().cloned()
+    }
+
+    /// All pending navigations, oldest first.
+    pub fn entries(&self) -> Vec<PendingNavigation> {
+        self.lock().iter().cloned().collect()
+    }
+
+    /// Remove and return the oldest pending navigation, under the same lock,
+    /// so an entry queued meanwhile is never dropped unseen.
+    pub fn take(&self) -> Option<PendingNavigation> {
+        let mut queue = self.lock();
+        let entry = queue.pop_front()?;
+        self.persist(&queue);
+        Some(entry)
+    }
+
+    /// Remove the entry for `feed_id`, or every entry when `feed_id` is None.
+    pub fn clear(&self, feed_id: Option<&str>) {
+        let mut queue = self.lock();
+        let before = queue.len();
+        match feed_id {
+            Some(feed_id) => queue.retain(|entry| entry.feed_id != feed_id),
+            None => queue.clear(),
+        }
+        if queue.len() != before {
+            self.persist(&queue);
         }
     }
 }
 
-/// Clear pending feed navigation after TypeScript has processed it.
-///
-/// After navigating to the feed, TypeScript calls this to clear the pending navigation
-/// so subsequent app opens don't re-navigate to the same feed.
+/// Queue a navigation and, if the app is already running, emit it as a
+/// `pending-navigation` event to the window handling the feed so it can
+/// navigate right away. The entry stays queued for the poll-based fallback.
+pub fn queue_navigation(
+    app: &AppHandle,
+    feed_id: String,
+    post_id: Option<String>,
+    kind: NavigationKind,
+) {
+    let Some(state) = app.try_state::<PendingNavigationState>() else {
+        return;
+    };
+    let entry = state.enqueue(feed_id, post_id, kind);
+    if app.get_webview_window("main").is_some() {
+        let feed_id = entry.feed_id.clone();
+        crate::feed_windows::emit_for_feed(app, &feed_id, "pending-navigation", entry);
+    }
+}
+
+/// Parse persisted navigations, skipping corrupt entries and ones queued more
+/// than 24 hours before `now`.
+fn restore_navigations(stored: Vec<serde_json::Value>, now: u64) -> VecDeque<PendingNavigation> {
+    stored
+        .into_iter()
+        .filter_map(|value| {
+            serde_json::from_value::<PendingNavigation>(value)
+                .map_err(|e| log::warn!("Ignoring invalid pending navigation: {}", e))
+                .ok()
+        })
+        .filter(|entry| now.saturating_sub(entry.received_at) <= PENDING_NAVIGATION_MAX_AGE_MS)
+        .collect()
+}
+
+pub(crate) fn now_unix_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Get pending feed navigation from notification tap.
 ///
-/// On Android: Clears the SharedPreferences value via FcmService.clearPendingNavigation()
-/// On iOS: Similar pattern (future implementation)
-/// On desktop: No-op
+/// Kept for backwards compatibility: returns the head of the pending navigation
+/// queue. New code should use `get_pending_navigations`.
 #[tauri::command]
-pub fn clear_pending_navigation() -> Result<(), String> {
-    #[cfg(target_os = "android")]
-    {
-        // On Android, the Kotlin layer handles clearing via SharedPreferences
-        // TypeScript should call this via the Kotlin bridge
-        Ok(())
+pub fn get_pending_navigation(state: State<'_, PendingNavigationState>) -> PendingNavigationResult {
+    import_native(&state);
+    match state.head() {
+        Some(entry) => PendingNavigationResult {
+            feed_id: Some(entry.feed_id),
+            post_id: entry.post_id,
+            kind: Some(entry.kind),
+            seq: Some(entry.seq),
+        },
+        None => PendingNavigationResult {
+            feed_id: None,
+            post_id: None,
+            kind: None,
+            seq: None,
+        },
     }
-    #[cfg(target_os = "ios")]
-    {
-        // iOS implementation will follow similar pattern
-        Ok(())
+}
+
+/// Get every pending navigation, oldest first.
+///
+/// When several notifications are tapped while the app is closed, each one is
+/// queued so none are lost. TypeScript acknowledges them individually via
+/// `clear_pending_navigation(id)`.
+#[tauri::command]
+pub fn get_pending_navigations(state: State<'_, PendingNavigationState>) -> Vec<PendingNavigation> {
+    import_native(&state);
+    state.entries()
+}
+
+/// One navigation queued by `FcmService` on Android, as handed over by
+/// `takePendingNavigation`
+#[cfg_attr(not(target_os = "android"), allow(dead_code))]
+#[derive(Debug, Deserialize)]
+struct NativeNavigation {
+    feed_id: String,
+    #[serde(default)]
+    post_id: Option<String>,
+    #[serde(default)]
+    kind: Option<String>,
+}
+
+/// Parse the JSON array `takePendingNavigation` hands over.
+#[cfg_attr(not(target_os = "android"), allow(dead_code))]
+fn parse_native_navigations(json: &str) -> Result<Vec<NativeNavigation>, String> {
+    serde_json::from_str(json).map_err(|e| e.to_string())
+}
+
+/// Move the navigations queued by the Android side into this queue, oldest
+/// first. Kotlin reads and deletes its queue under the lock taps are queued
+/// under, so a tap landing meanwhile is either taken now or kept for the
+/// next call.
+#[cfg(target_os = "android")]
+fn import_native_navigation(state: &PendingNavigationState) -> Result<(), String> {
+    let Some(json) = crate::android::take_pending_navigation()? else {
+        return Ok(());
+    };
+    for native in parse_native_navigations(&json)? {
+        let kind = NavigationKind::from_type(native.kind.as_deref(), native.post_id.is_some());
+        state.enqueue(native.feed_id, native.post_id, kind);
     }
-    #[cfg(not(any(target_os = "android", target_os = "ios")))]
-    {
-        // Desktop doesn't have pending navigation to clear
-        Ok(())
+    Ok(())
+}
+
+/// Pick up Android taps before reading the queue.
+#[cfg_attr(not(target_os = "android"), allow(unused_variables))]
+fn import_native(state: &PendingNavigationState) {
+    #[cfg(target_os = "android")]
+    if let Err(e) = import_native_navigation(state) {
+        log::warn!("Failed to take native pending navigation: {}", e);
     }
 }
 
+/// Whether a navigation is waiting. An Android tap is picked up first, so it
+/// counts even before the frontend has asked for it.
+pub fn has_pending_navigation(app: &AppHandle) -> bool {
+    let Some(state) = app.try_state::<PendingNavigationState>() else {
+        return false;
+    };
+    import_native(&state);
+    !state.entries().is_empty()
+}
+
+/// Remove and return the oldest pending navigation, or None if there is none.
+///
+/// Use this instead of `get_pending_navigation` followed by
+/// `clear_pending_navigation`: a tap landing between those two calls would be
+/// cleared without ever being seen.
+#[tauri::command]
+pub fn take_pending_navigation(
+    state: State<'_, PendingNavigationState>,
+) -> Option<PendingNavigation> {
+    import_native(&state);
+    state.take()
+}
+
+/// Clear pending feed navigation after TypeScript has processed it.
+///
+/// With an `id` (the entry's feed id) only that entry is removed; without one the
+/// whole queue is cleared, matching the previous single-value behaviour.
+///
+/// Deprecated for consuming navigations, which `take_pending_navigation` does
+/// atomically; still used to drop entries without handling them.
+#[tauri::command]
+pub fn clear_pending_navigation(
+    state: State<'_, PendingNavigationState>,
+    id: Option<String>,
+) -> Result<(), FcmError> {
+    state.clear(id.as_deref());
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -237,32 +1101,355 @@ mod tests {
 
     #[test]
     fn test_has_notification_permission_on_desktop() {
-        let result = has_notification_permission();
+        let result = FcmState::default().permission();
         assert!(result.granted);
         assert!(!result.can_request);
     }
 
+    #[test]
+    fn test_permission_state_mapping() {
+        let granted = permission_from_state(PermissionState::Granted);
+        assert!(granted.granted && !granted.can_request);
+
+        let denied = permission_from_state(PermissionState::Denied);
+        assert!(!denied.granted && !denied.can_request);
+
+        let prompt = permission_from_state(PermissionState::PromptWithRationale);
+        assert!(!prompt.granted && prompt.can_request);
+    }
+
+    #[test]
+    fn test_permission_watch_reports_only_changes() {
+        let state = PermissionWatchState::default();
+        let granted = permission_from_state(PermissionState::Granted);
+        let denied = permission_from_state(PermissionState::Denied);
+
+        // Baseline
+        assert!(state.observe(granted.clone()).is_none());
+        // Unchanged
+        assert!(state.observe(granted.clone()).is_none());
+        // Flipped
+        let change = state.observe(denied.clone()).unwrap();
+        assert_eq!(change.previous, granted);
+        assert_eq!(change.current, denied);
+        // Reported once
+        assert!(state.observe(denied).is_none());
+    }
+
     #[test]
     fn test_get_fcm_token_on_desktop() {
-        let result = get_fcm_token();
-        assert!(result.token.is_none());
-        assert!(result.error.is_some());
+        let state = FcmState::default();
+        assert_eq!(state.token_result().unwrap_err(), FcmError::NotSupported);
+    }
+
+    #[test]
+    fn test_fcm_error_serializes_with_code_and_message() {
+        assert_eq!(
+            serde_json::to_value(FcmError::NotSupported).unwrap(),
+            serde_json::json!({
+                "code": "not_supported",
+                "message": "Push notifications not available on desktop",
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(FcmError::NotYetAvailable).unwrap(),
+            serde_json::json!({
+                "code": "not_yet_available",
+                "message": "Push token not yet received",
+            })
+        );
+        assert_eq!(
+            serde_json::to_string(&FcmError::NativeBridgeFailure {
+                detail: "no activity".to_string(),
+            })
+            .unwrap(),
+            r#"{"code":"native_bridge_failure","message":"Native push bridge failed: no activity"}"#
+        );
+        assert_eq!(
+            serde_json::to_value(permission_from_authorization("maybe").unwrap_err()).unwrap()
+                ["code"],
+            "invalid_native_value"
+        );
+    }
+
+    #[test]
+    fn test_token_result_keeps_legacy_error_field() {
+        let result = FcmTokenResult {
+            token: Some("token-abc".to_string()),
+            token_type: Some(TokenType::Fcm),
+            error: None,
+            error_code: None,
+        };
+        assert_eq!(
+            serde_json::to_value(result).unwrap(),
+            serde_json::json!({
+                "token": "token-abc",
+                "token_type": "fcm",
+                "error": null,
+                "error_code": null,
+            })
+        );
+
+        assert_eq!(
+            serde_json::to_value(FcmTokenResult::not_yet_available()).unwrap(),
+            serde_json::json!({
+                "token": null,
+                "token_type": null,
+                "error": "Push token not yet received",
+                "error_code": "not_yet_available",
+            })
+        );
+    }
+
+    #[test]
+    fn test_set_then_get_token_through_state() {
+        let state = FcmState::default();
+        assert!(state.token().is_none());
+
+        state.set_token("token-abc".to_string());
+        assert_eq!(state.token().as_deref(), Some("token-abc"));
+
+        state.set_token("token-def".to_string());
+        assert_eq!(state.token().as_deref(), Some("token-def"));
+    }
+
+    #[test]
+    fn test_token_type_follows_setter() {
+        let state = FcmState::default();
+        state.set_apns_token("apns-abc".to_string());
+        let token = state.push_token().unwrap();
+        assert_eq!(token.value, "apns-abc");
+        assert_eq!(token.token_type, TokenType::Apns);
+
+        state.set_token("fcm-abc".to_string());
+        assert_eq!(state.push_token().unwrap().token_type, TokenType::Fcm);
+        assert_eq!(serde_json::to_value(TokenType::Apns).unwrap(), "apns");
+    }
+
+    #[test]
+    fn test_reported_authorization_overrides_default() {
+        let state = FcmState::default();
+        state.set_authorization(permission_from_authorization("denied").unwrap());
+        assert_eq!(
+            state.permission(),
+            PermissionResult {
+                granted: false,
+                can_request: false,
+            }
+        );
+
+        assert!(permission_from_authorization("provisional").unwrap().granted);
+        assert!(permission_from_authorization("notDetermined").unwrap().can_request);
+        assert!(permission_from_authorization("maybe").is_err());
+    }
+
+    #[test]
+    fn test_empty_token_clears_state() {
+        let state = FcmState::default();
+        state.set_token("token-abc".to_string());
+        state.set_token(String::new());
+        assert!(state.token().is_none());
+    }
+
+    #[test]
+    fn test_refresh_token_reports_old_and_new() {
+        let state = FcmState::default();
+        let first = state.refresh_token("token-abc".to_string()).unwrap();
+        assert_eq!(first.old_token, None);
+        assert_eq!(first.new_token, "token-abc");
+
+        let second = state.refresh_token("token-def".to_string()).unwrap();
+        assert_eq!(second.old_token.as_deref(), Some("token-abc"));
+        assert_eq!(second.new_token, "token-def");
+        assert_eq!(state.token().as_deref(), Some("token-def"));
+    }
+
+    #[test]
+    fn test_refresh_with_same_token_is_deduplicated() {
+        let state = FcmState::default();
+        state.set_token("token-abc".to_string());
+
+        assert!(state.refresh_token("token-abc".to_string()).is_none());
+        assert!(state.refresh_token(String::new()).is_none());
+        assert_eq!(state.token().as_deref(), Some("token-abc"));
+    }
+
+    #[test]
+    fn test_step_outcome_serializes_with_status() {
+        let ok = serde_json::to_value(StepOutcome::Ok).unwrap();
+        assert_eq!(ok, serde_json::json!({"status": "ok"}));
+
+        let failed = serde_json::to_value(StepOutcome::from(Err("boom".to_string()))).unwrap();
+        assert_eq!(failed, serde_json::json!({"status": "failed", "error": "boom"}));
     }
 
     #[test]
     fn test_is_push_supported_on_desktop() {
-        assert!(!is_push_supported());
+        assert!(!native_push_supported());
+    }
+
+    #[test]
+    fn test_support_details_reasons() {
+        let granted = PermissionResult {
+            granted: true,
+            can_request: false,
+        };
+        let denied = PermissionResult {
+            granted: false,
+            can_request: false,
+        };
+
+        let ok = support_details(None, &granted);
+        assert!(ok.supported);
+        assert_eq!(ok.reason, PushSupportReason::Ok);
+
+        let no_permission = support_details(None, &denied);
+        assert!(!no_permission.supported);
+        assert_eq!(no_permission.reason, PushSupportReason::PermissionDenied);
+
+        // Missing push channel wins over permission
+        let no_gms = support_details(Some(PushSupportReason::NoPlayServices), &denied);
+        assert!(!no_gms.supported);
+        assert_eq!(
+            serde_json::to_value(no_gms.reason).unwrap(),
+            "no_play_services"
+        );
+        assert_eq!(
+            serde_json::to_value(PushSupportReason::PlatformUnsupported).unwrap(),
+            "platform_unsupported"
+        );
+    }
+
+    #[test]
+    fn test_pending_navigation_empty_by_default() {
+        let state = PendingNavigationState::default();
+        assert!(state.head().is_none());
+        assert!(state.entries().is_empty());
+    }
+
+    #[test]
+    fn test_pending_navigations_are_fifo() {
+        let state = PendingNavigationState::default();
+        state.enqueue("feed-a".to_string(), None, NavigationKind::Feed);
+        state.enqueue("feed-b".to_string(), None, NavigationKind::Feed);
+
+        let feeds: Vec<_> = state.entries().into_iter().map(|e| e.feed_id).collect();
+        assert_eq!(feeds, vec!["feed-a", "feed-b"]);
+        assert_eq!(state.head().unwrap().feed_id, "feed-a");
+    }
+
+    #[test]
+    fn test_requeueing_feed_moves_it_to_back() {
+        let state = PendingNavigationState::default();
+        state.enqueue("feed-a".to_string(), None, NavigationKind::Feed);
+        state.enqueue("feed-b".to_string(), None, NavigationKind::Feed);
+        state.enqueue("feed-a".to_string(), None, NavigationKind::Feed);
+
+        let feeds: Vec<_> = state.entries().into_iter().map(|e| e.feed_id).collect();
+        assert_eq!(feeds, vec!["feed-b", "feed-a"]);
+    }
+
+    #[test]
+    fn test_clear_pending_navigation_by_id() {
+        let state = PendingNavigationState::default();
+        state.enqueue("feed-a".to_string(), None, NavigationKind::Feed);
+        state.enqueue("feed-b".to_string(), None, NavigationKind::Feed);
+
+        state.clear(Some("feed-a"));
+        assert_eq!(state.head().unwrap().feed_id, "feed-b");
+
+        state.clear(None);
+        assert!(state.head().is_none());
+    }
+
+    #[test]
+    fn test_pending_navigation_carries_post_and_kind() {
+        let state = PendingNavigationState::default();
+        state.enqueue(
+            "feed-a".to_string(),
+            Some("post-1".to_string()),
+            NavigationKind::Mention,
+        );
+
+        let head = state.head().unwrap();
+        assert_eq!(head.post_id.as_deref(), Some("post-1"));
+        assert_eq!(head.kind, NavigationKind::Mention);
+    }
+
+    #[test]
+    fn test_take_pending_navigation_pops_oldest() {
+        let state = PendingNavigationState::default();
+        state.enqueue("feed-a".to_string(), None, NavigationKind::Feed);
+        state.enqueue("feed-b".to_string(), None, NavigationKind::Feed);
+
+        assert_eq!(state.take().unwrap().feed_id, "feed-a");
+        assert_eq!(state.take().unwrap().feed_id, "feed-b");
+        assert!(state.take().is_none());
+    }
+
+    #[test]
+    fn test_pending_navigation_seq_increases() {
+        let state = PendingNavigationState::default();
+        let first = state.enqueue("feed-a".to_string(), None, NavigationKind::Feed);
+        let second = state.enqueue("feed-b".to_string(), None, NavigationKind::Feed);
+        let requeued = state.enqueue("feed-a".to_string(), None, NavigationKind::Feed);
+        assert!(first.seq < second.seq && second.seq < requeued.seq);
+
+        let seqs: Vec<_> = state.entries().into_iter().map(|e| e.seq).collect();
+        assert_eq!(seqs, vec![second.seq, requeued.seq]);
     }
 
     #[test]
-    fn test_get_pending_navigation_on_desktop() {
-        let result = get_pending_navigation();
-        assert!(result.feed_id.is_none());
+    fn test_restore_drops_corrupt_and_stale_navigations() {
+        let now = PENDING_NAVIGATION_MAX_AGE_MS + 10_000;
+        let stored = vec![
+            serde_json::json!({"feed_id": "fresh", "received_at": now - 1_000}),
+            serde_json::json!({"feed_id": "stale", "received_at": 1_000}),
+            serde_json::json!({"received_at": now}),
+            serde_json::json!("garbage"),
+        ];
+        let queue = restore_navigations(stored, now);
+        let feeds: Vec<_> = queue.into_iter().map(|e| e.feed_id).collect();
+        assert_eq!(feeds, vec!["fresh"]);
     }
 
     #[test]
-    fn test_clear_pending_navigation_on_desktop() {
-        let result = clear_pending_navigation();
-        assert!(result.is_ok());
+    fn test_native_navigations_deserialize_in_order() {
+        let json = r#"[{"feed_id":"feed-a","post_id":null,"kind":"dm"},
+            {"feed_id":"feed-b","post_id":"post-1","kind":null}]"#;
+        let native = parse_native_navigations(json).unwrap();
+        assert_eq!(native.len(), 2);
+        assert_eq!(native[0].feed_id, "feed-a");
+        assert_eq!(native[0].kind.as_deref(), Some("dm"));
+        assert!(native[0].post_id.is_none());
+        assert_eq!(native[1].feed_id, "feed-b");
+        assert_eq!(native[1].post_id.as_deref(), Some("post-1"));
+
+        assert!(parse_native_navigations(r#"{"feed_id":"feed-a"}"#).is_err());
+    }
+
+    #[test]
+    fn test_navigation_kind_serializes_as_string() {
+        assert_eq!(serde_json::to_string(&NavigationKind::Dm).unwrap(), "\"dm\"");
+        assert_eq!(
+            NavigationKind::from_type(Some("mention"), true),
+            NavigationKind::Mention
+        );
+        assert_eq!(NavigationKind::from_type(None, true), NavigationKind::Post);
+        assert_eq!(NavigationKind::from_type(Some("other"), false), NavigationKind::Feed);
+    }
+
+    #[test]
+    fn test_legacy_pending_navigation_payload_deserializes() {
+        let result: PendingNavigationResult =
+            serde_json::from_str(r#"{"feed_id":"feed-a"}"#).unwrap();
+        assert_eq!(result.feed_id.as_deref(), Some("feed-a"));
+        assert!(result.post_id.is_none());
+        assert!(result.kind.is_none());
+
+        let entry: PendingNavigation =
+            serde_json::from_str(r#"{"feed_id":"feed-a","received_at":1}"#).unwrap();
+        assert_eq!(entry.kind, NavigationKind::Feed);
+        assert!(entry.post_id.is_none());
     }
 }
@@ -0,0 +1,334 @@
+//! Pop-out feed windows: a single feed kept open in its own small window.
+//!
+//! `open_feed_window` creates a webview window labeled `feed-<id>` that loads
+//! the app with `?feed=<id>`, or focuses it if it is already open. Each
+//! feed's window geometry is kept in the `feedWindowGeometry` setting and
+//! restored the same way as the main window's (see [`crate::window`]).
+//!
+//! Events about one feed go to its pop-out when one is open: notification
+//! taps and deep links focus the pop-out instead of the main window, and
+//! `stream-message`s naming a feed reach the main window and that feed's
+//! pop-out only. Opening or closing a pop-out emits `feed-windows-changed`
+//! with the current list. Pop-outs are desktop only.
+
+use crate::error::CommandError;
+#[cfg(desktop)]
+use crate::settings::{self, SettingsState};
+#[cfg(desktop)]
+use crate::window::{self, MonitorArea, WindowGeometry};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, EventTarget, Manager, WebviewWindow, Window, WindowEvent};
+
+/// Label prefix of pop-out windows; the rest of the label is the feed id
+pub const LABEL_PREFIX: &str = "feed-";
+/// Size of a pop-out with no saved geometry, in logical pixels
+#[cfg(desktop)]
+const DEFAULT_SIZE: (f64, f64) = (420.0, 720.0);
+#[cfg(desktop)]
+const MIN_SIZE: (f64, f64) = (320.0, 400.0);
+const MAX_FEED_ID_LEN: usize = 128;
+
+/// An open pop-out window
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedWindowInfo {
+    pub feed_id: String,
+    pub label: String,
+}
+
+/// Feed ids end up in window labels and URLs, so they are limited to
+/// characters that are safe in both.
+fn validate_feed_id(feed_id: &str) -> Result<(), CommandError> {
+    let valid = !feed_id.is_empty()
+        && feed_id.len() <= MAX_FEED_ID_LEN
+        && feed_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(CommandError::invalid_argument(format!(
+            "Invalid feed id: {}",
+            feed_id
+        )))
+    }
+}
+
+fn label(feed_id: &str) -> String {
+    format!("{}{}", LABEL_PREFIX, feed_id)
+}
+
+/// The feed a window label belongs to, if it is a pop-out's.
+pub fn feed_of_label(label: &str) -> Option<&str> {
+    label.strip_prefix(LABEL_PREFIX)
+}
+
+/// The pop-out showing `feed_id`, if one is open.
+pub fn window_for_feed(app: &AppHandle, feed_id: &str) -> Option<WebviewWindow> {
+    app.get_webview_window(&label(feed_id))
+}
+
+/// Every open pop-out, ordered by feed id.
+pub fn list(app: &AppHandle) -> Vec<FeedWindowInfo> {
+    let mut windows: Vec<FeedWindowInfo> = app
+        .webview_windows()
+        .into_keys()
+        .filter_map(|label| {
+            let feed_id = feed_of_label(&label)?.to_string();
+            Some(FeedWindowInfo { feed_id, label })
+        })
+        .collect();
+    windows.sort_by(|a, b| a.feed_id.cmp(&b.feed_id));
+    windows
+}
+
+/// Emit `feed-windows-changed`, leaving out `closed` which may still be
+/// registered while it is being destroyed.
+fn changed(app: &AppHandle, closed: Option<&str>) {
+    let mut windows = list(app);
+    windows.retain(|info| Some(info.label.as_str()) != closed);
+    let _ = app.emit("feed-windows-changed", windows);
+}
+
+/// Label of the window that should handle an event about `feed_id`: its
+/// pop-out when open, otherwise the main window.
+pub fn target_for_feed(app: &AppHandle, feed_id: &str) -> String {
+    match window_for_feed(app, feed_id) {
+        Some(window) => window.label().to_string(),
+        None => "main".to_string(),
+    }
+}
+
+/// Emit `event` to the window handling `feed_id`.
+pub fn emit_for_feed<S: Serialize + Clone>(
+    app: &AppHandle,
+    feed_id: &str,
+    event: &str,
+    payload: S,
+) {
+    let _ = app.emit_to(
+        EventTarget::webview_window(target_for_feed(app, feed_id)),
+        event,
+        payload,
+    );
+}
+
+/// Bring up the window handling `feed_id`, e.g. after a notification tap.
+pub fn show_for_feed(app: &AppHandle, feed_id: &str) {
+    match window_for_feed(app, feed_id) {
+        Some(window) => {
+            let _ = window.unminimize();
+            let _ = window.show();
+            let _ = window.set_focus();
+        }
+        #[cfg(desktop)]
+        None => crate::tray::show_main_window(app),
+        #[cfg(mobile)]
+        None => {}
+    }
+}
+
+/// The feed a live stream message is about, read from its top-level `feedId`
+/// (or `feed_id`) field.
+fn feed_of_message(text: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(text).ok()?;
+    ["feedId", "feed_id"]
+        .iter()
+        .find_map(|key| value.get(key)?.as_str())
+        .map(str::to_string)
+}
+
+/// Emit a live stream message to the main window, and to the pop-out of the
+/// feed it names. Pop-outs don't see other feeds' messages.
+pub fn emit_stream_message(app: &AppHandle, text: String) {
+    let feed_label = feed_of_message(&text).map(|feed_id| label(&feed_id));
+    let _ = app.emit_filter("stream-message", text, |target| match target {
+        EventTarget::WebviewWindow { label } | EventTarget::Webview { label } => {
+            feed_of_label(label).is_none() || Some(label) == feed_label.as_ref()
+        }
+        _ => true,
+    });
+}
+
+/// Saved geometry of `feed_id`'s pop-out.
+#[cfg(desktop)]
+fn saved_geometry(app: &AppHandle, feed_id: &str) -> Option<WindowGeometry> {
+    app.try_state::<SettingsState>()?
+        .get()
+        .feed_window_geometry?
+        .remove(feed_id)
+}
+
+/// Persist the geometry of the pop-out `window`.
+#[cfg(desktop)]
+fn save_geometry(app: &AppHandle, window: &WebviewWindow) {
+    let Some(feed_id) = feed_of_label(window.label()) else {
+        return;
+    };
+    if window.is_minimized().unwrap_or(false) {
+        return;
+    }
+    let Ok(geometry) = window::current_geometry(window) else {
+        return;
+    };
+    let Some(state) = app.try_state::<SettingsState>() else {
+        return;
+    };
+    let mut saved = state.get().feed_window_geometry.unwrap_or_default();
+    if saved.get(feed_id) == Some(&geometry) {
+        return;
+    }
+    saved.insert(feed_id.to_string(), geometry);
+    let value = match serde_json::to_value(saved) {
+        Ok(value) => value,
+        Err(e) => {
+            log::warn!("Failed to save feed window state: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = settings::set(app, "feedWindowGeometry", value) {
+        log::warn!("Failed to save feed window state: {}", e);
+    }
+}
+
+/// Persist the geometry of every open pop-out. Called on exit.
+#[cfg(desktop)]
+pub fn save_all(app: &AppHandle) {
+    for info in list(app) {
+        if let Some(window) = app.get_webview_window(&info.label) {
+            save_geometry(app, &window);
+        }
+    }
+}
+
+#[cfg(desktop)]
+fn restore_geometry(app: &AppHandle, window: &WebviewWindow, saved: &WindowGeometry) {
+    let monitors: Vec<MonitorArea> = app
+        .available_monitors()
+        .unwrap_or_default()
+        .iter()
+        .map(window::monitor_area)
+        .collect();
+    let primary = app
+        .primary_monitor()
+        .ok()
+        .flatten()
+        .map(|monitor| window::monitor_area(&monitor));
+    if let Some(geometry) = window::resolve_geometry(saved, &monitors, primary.as_ref()) {
+        window::apply_geometry(window, &geometry);
+    }
+}
+
+/// Window event hook for pop-outs, called from [`crate::window::on_window_event`].
+pub fn on_window_event(window: &Window, event: &WindowEvent) {
+    match event {
+        #[cfg(desktop)]
+        WindowEvent::CloseRequested { .. } => {
+            let app = window.app_handle();
+            if let Some(window) = app.get_webview_window(window.label()) {
+                save_geometry(app, &window);
+            }
+        }
+        WindowEvent::Destroyed => changed(window.app_handle(), Some(window.label())),
+        _ => {}
+    }
+}
+
+#[cfg(mobile)]
+fn unsupported() -> CommandError {
+    CommandError::new(
+        crate::error::NOT_SUPPORTED,
+        "Feed windows are not available on this platform",
+    )
+}
+
+/// Open `feed_id` in its own window, or focus its window if it is open.
+#[tauri::command]
+pub fn open_feed_window(app: AppHandle, feed_id: String) -> Result<FeedWindowInfo, CommandError> {
+    validate_feed_id(&feed_id)?;
+    #[cfg(desktop)]
+    {
+        let label = label(&feed_id);
+        if let Some(window) = app.get_webview_window(&label) {
+            show_for_feed(&app, &feed_id);
+            return Ok(FeedWindowInfo {
+                feed_id,
+                label: window.label().to_string(),
+            });
+        }
+
+        let url = tauri::WebviewUrl::App(format!("index.html?feed={}", feed_id).into());
+        let window = tauri::WebviewWindowBuilder::new(&app, &label, url)
+            .title("Hush Feeds")
+            .inner_size(DEFAULT_SIZE.0, DEFAULT_SIZE.1)
+            .min_inner_size(MIN_SIZE.0, MIN_SIZE.1)
+            .visible(false)
+            .build()
+            .map_err(|e| CommandError::new(crate::error::UNAVAILABLE, e.to_string()))?;
+        match saved_geometry(&app, &feed_id) {
+            Some(saved) => restore_geometry(&app, &window, &saved),
+            None => {
+                let _ = window.center();
+            }
+        }
+        let _ = window.show();
+        let _ = window.set_focus();
+        changed(&app, None);
+        Ok(FeedWindowInfo { feed_id, label })
+    }
+    #[cfg(mobile)]
+    {
+        let _ = app;
+        Err(unsupported())
+    }
+}
+
+/// Close `feed_id`'s pop-out. Returns false if none was open.
+#[tauri::command]
+pub fn close_feed_window(app: AppHandle, feed_id: String) -> Result<bool, CommandError> {
+    validate_feed_id(&feed_id)?;
+    let Some(window) = window_for_feed(&app, &feed_id) else {
+        return Ok(false);
+    };
+    window.close().map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+#[tauri::command]
+pub fn list_feed_windows(app: AppHandle) -> Vec<FeedWindowInfo> {
+    list(&app)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn feed_ids_must_be_label_safe() {
+        assert!(validate_feed_id("feed_01-abc").is_ok());
+        assert!(validate_feed_id("").is_err());
+        assert!(validate_feed_id("../etc").is_err());
+        assert!(validate_feed_id("a&b=c").is_err());
+        assert!(validate_feed_id(&"x".repeat(MAX_FEED_ID_LEN + 1)).is_err());
+    }
+
+    #[test]
+    fn labels_round_trip_to_feed_ids() {
+        assert_eq!(feed_of_label(&label("abc")), Some("abc"));
+        assert_eq!(feed_of_label("main"), None);
+    }
+
+    #[test]
+    fn stream_messages_name_their_feed() {
+        assert_eq!(
+            feed_of_message(r#"{"type":"post","feedId":"f1"}"#).as_deref(),
+            Some("f1")
+        );
+        assert_eq!(
+            feed_of_message(r#"{"feed_id":"f2"}"#).as_deref(),
+            Some("f2")
+        );
+        assert_eq!(feed_of_message(r#"{"type":"ping"}"#), None);
+        assert_eq!(feed_of_message("not json"), None);
+    }
+}
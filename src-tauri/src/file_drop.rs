@@ -0,0 +1,243 @@
+//! Files dragged onto the main window, handed to the composer.
+//!
+//! The window's drop handler (wired in [`crate::window::on_window_event`])
+//! passes dropped paths here. Each file is checked, its MIME type sniffed from
+//! its contents, and previewable images get a PNG thumbnail under the
+//! clipboard attachments dir (`clear_temp_attachments` removes them). Accepted
+//! files are emitted as `files-dropped`; directories, files over the
+//! `dropMaxBytes` setting (default [`DEFAULT_MAX_BYTES`]) and unreadable files
+//! as `files-drop-rejected` with the reason.
+//!
+//! While the frontend has flagged an upload in progress (`set_uploading`),
+//! drops are ignored and the taskbar or dock shows indeterminate progress.
+
+use crate::clipboard::attachment_dir;
+use crate::settings::SettingsState;
+use crate::task_progress::{self, Source, TaskProgress};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+/// Drop size limit used when `dropMaxBytes` isn't set (100 MB)
+pub const DEFAULT_MAX_BYTES: u64 = 100 * 1024 * 1024;
+/// Longest side of a drop thumbnail, in pixels
+const THUMBNAIL_SIZE: u32 = 256;
+/// Image types `image` can decode for a thumbnail
+const PREVIEWABLE: &[&str] = &["image/png", "image/jpeg", "image/gif", "image/webp"];
+const FALLBACK_MIME: &str = "application/octet-stream";
+
+/// An accepted file, as sent in `files-dropped`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DroppedFile {
+    pub path: String,
+    pub size: u64,
+    pub mime_type: String,
+    /// PNG preview for images; `None` for other types or if decoding failed
+    pub thumbnail_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RejectReason {
+    Directory,
+    TooLarge,
+    Unreadable,
+}
+
+/// A refused file, as sent in `files-drop-rejected`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RejectedFile {
+    pub path: String,
+    pub reason: RejectReason,
+    pub message: String,
+}
+
+/// Managed state holding the uploading flag
+#[derive(Debug, Default)]
+pub struct FileDropState {
+    uploading: AtomicBool,
+}
+
+/// Accept or refuse a file from its metadata.
+fn check(path: &Path, meta: &std::fs::Metadata, max_bytes: u64) -> Result<(), RejectedFile> {
+    let reject = |reason, message: String| RejectedFile {
+        path: path.to_string_lossy().into_owned(),
+        reason,
+        message,
+    };
+    if meta.is_dir() {
+        return Err(reject(
+            RejectReason::Directory,
+            "Folders can't be attached".to_string(),
+        ));
+    }
+    if meta.len() > max_bytes {
+        return Err(reject(
+            RejectReason::TooLarge,
+            format!(
+                "File is larger than the {} MB limit",
+                max_bytes / (1024 * 1024)
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// MIME type from the file's leading bytes.
+fn sniff_mime(path: &Path) -> String {
+    match infer::get_from_path(path) {
+        Ok(Some(kind)) => kind.mime_type().to_string(),
+        _ => FALLBACK_MIME.to_string(),
+    }
+}
+
+fn write_thumbnail(source: &Path, dir: &Path) -> Result<PathBuf, String> {
+    let image = image::open(source).map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let path = dir.join(format!("drop-{}.png", uuid::Uuid::new_v4()));
+    image
+        .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+        .save_with_format(&path, image::ImageFormat::Png)
+        .map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+fn inspect(
+    path: &Path,
+    max_bytes: u64,
+    thumbnail_dir: Option<&Path>,
+) -> Result<DroppedFile, RejectedFile> {
+    let meta = std::fs::metadata(path).map_err(|e| RejectedFile {
+        path: path.to_string_lossy().into_owned(),
+        reason: RejectReason::Unreadable,
+        message: e.to_string(),
+    })?;
+    check(path, &meta, max_bytes)?;
+
+    let mime_type = sniff_mime(path);
+    let thumbnail_path = match thumbnail_dir {
+        Some(dir) if PREVIEWABLE.contains(&mime_type.as_str()) => {
+            match write_thumbnail(path, dir) {
+                Ok(thumbnail) => Some(thumbnail.to_string_lossy().into_owned()),
+                Err(e) => {
+                    log::debug!("No thumbnail for {}: {}", path.display(), e);
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
+    Ok(DroppedFile {
+        path: path.to_string_lossy().into_owned(),
+        size: meta.len(),
+        mime_type,
+        thumbnail_path,
+    })
+}
+
+fn process(app: &AppHandle, paths: &[PathBuf]) {
+    let max_bytes = app
+        .state::<SettingsState>()
+        .get()
+        .drop_max_bytes
+        .unwrap_or(DEFAULT_MAX_BYTES);
+    let thumbnail_dir = attachment_dir(app).ok();
+
+    let mut accepted = Vec::new();
+    let mut rejected = Vec::new();
+    for path in paths {
+        match inspect(path, max_bytes, thumbnail_dir.as_deref()) {
+            Ok(file) => accepted.push(file),
+            Err(file) => rejected.push(file),
+        }
+    }
+    if !accepted.is_empty() {
+        let _ = app.emit("files-dropped", accepted);
+    }
+    if !rejected.is_empty() {
+        let _ = app.emit("files-drop-rejected", rejected);
+    }
+}
+
+impl FileDropState {
+    /// Whether the frontend has flagged an upload in progress.
+    pub fn is_uploading(&self) -> bool {
+        self.uploading.load(Ordering::SeqCst)
+    }
+}
+
+/// Handle files dropped on the main window.
+pub fn on_drop(app: &AppHandle, paths: Vec<PathBuf>) {
+    if paths.is_empty() {
+        return;
+    }
+    if app
+        .state::<FileDropState>()
+        .uploading
+        .load(Ordering::SeqCst)
+    {
+        log::debug!("Ignoring {} dropped files during upload", paths.len());
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || process(&app, &paths));
+}
+
+/// Flag an upload in progress; drops are ignored until it's cleared.
+#[tauri::command]
+pub fn set_uploading(app: AppHandle, state: State<'_, FileDropState>, active: bool) {
+    state.uploading.store(active, Ordering::SeqCst);
+    let progress = if active {
+        TaskProgress::Indeterminate
+    } else {
+        TaskProgress::None
+    };
+    task_progress::report(&app, Source::Uploads, progress);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("hush-drop-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn directories_and_large_files_are_rejected() {
+        let dir = temp_dir();
+        let file = dir.join("note.txt");
+        std::fs::write(&file, b"hello").unwrap();
+
+        let rejected = inspect(&dir, 1024, None).unwrap_err();
+        assert_eq!(rejected.reason, RejectReason::Directory);
+        let rejected = inspect(&file, 4, None).unwrap_err();
+        assert_eq!(rejected.reason, RejectReason::TooLarge);
+        let rejected = inspect(&dir.join("missing"), 1024, None).unwrap_err();
+        assert_eq!(rejected.reason, RejectReason::Unreadable);
+
+        let accepted = inspect(&file, 1024, None).unwrap();
+        assert_eq!(accepted.size, 5);
+        assert_eq!(accepted.mime_type, FALLBACK_MIME);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn images_are_sniffed_and_thumbnailed() {
+        let dir = temp_dir();
+        let source = dir.join("photo.bin");
+        image::RgbaImage::from_pixel(600, 300, image::Rgba([10, 20, 30, 255]))
+            .save_with_format(&source, image::ImageFormat::Png)
+            .unwrap();
+
+        let accepted = inspect(&source, DEFAULT_MAX_BYTES, Some(&dir)).unwrap();
+        assert_eq!(accepted.mime_type, "image/png");
+        let thumbnail = image::open(accepted.thumbnail_path.unwrap()).unwrap();
+        assert_eq!((thumbnail.width(), thumbnail.height()), (256, 128));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,715 @@
+//! HTTP requests made from Rust on behalf of the frontend, for endpoints the
+//! webview can't reach because of CORS (link previews, third-party media).
+//!
+//! `http_fetch` runs on the pooled fetch client in [`crate::net`], built with
+//! the same proxy and certificate pins as the shared client, and with a
+//! redirect policy of its own: only schemes in the `httpFetchSchemes` setting
+//! are allowed (`https` by default), and a redirect to any other scheme is
+//! refused before it is followed. Timeouts, connection failures and 408, 429
+//! and 5xx responses are only retried for idempotent methods unless the
+//! request opts in.
+//!
+//! Responses are capped at `maxBytes`; bodies up to [`INLINE_MAX_BYTES`] come
+//! back inline as text or base64, larger ones are streamed to a file under
+//! the app cache dir and returned as a path. Those files are cleared on the
+//! next start.
+//!
+//! Failures are returned as a [`FetchError`] with a `kind` the frontend can
+//! branch on.
+
+use crate::backoff::Backoff;
+use crate::net;
+use crate::settings::SettingsState;
+use base64::Engine;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, Url};
+use tokio::io::AsyncWriteExt;
+
+const DOWNLOAD_DIR: &str = "fetch";
+const DEFAULT_SCHEMES: &[&str] = &["https"];
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_TIMEOUT: Duration = Duration::from_secs(300);
+const DEFAULT_MAX_BYTES: u64 = 20 * 1024 * 1024;
+/// Upper bound for `maxBytes`
+const MAX_RESPONSE_BYTES: u64 = 200 * 1024 * 1024;
+/// Bodies larger than this are written to a file instead of returned inline
+pub const INLINE_MAX_BYTES: u64 = 1024 * 1024;
+const MAX_ATTEMPTS: u32 = 5;
+const DEFAULT_RETRY_DELAY: Duration = Duration::from_millis(500);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(10);
+/// Same limit as reqwest's default policy
+const MAX_REDIRECTS: usize = 10;
+
+/// When to retry a failed request
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryPolicy {
+    /// Total attempts including the first, at most 5
+    #[serde(default)]
+    pub max_attempts: Option<u32>,
+    /// Delay before the first retry, doubling after each
+    #[serde(default)]
+    pub initial_delay_ms: Option<u64>,
+    /// Also retry timeouts, connection failures and retryable statuses for
+    /// methods that aren't idempotent (POST, PATCH), which the server may
+    /// already have processed
+    #[serde(default)]
+    pub non_idempotent: bool,
+}
+
+/// A request from the frontend
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FetchRequest {
+    /// Defaults to GET
+    #[serde(default)]
+    pub method: Option<String>,
+    pub url: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub body: Option<String>,
+    /// `body` is base64 rather than text
+    #[serde(default)]
+    pub body_base64: bool,
+    /// Per attempt
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub retry: Option<RetryPolicy>,
+    #[serde(default)]
+    pub max_bytes: Option<u64>,
+}
+
+/// A response body
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "encoding", rename_all = "lowercase")]
+pub enum FetchBody {
+    Text {
+        text: String,
+    },
+    Base64 {
+        data: String,
+    },
+    /// Streamed to `path`; too large to return inline
+    File {
+        path: PathBuf,
+        size: u64,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FetchResponse {
+    pub status: u16,
+    /// Final URL after redirects
+    pub url: String,
+    /// Header names are lowercase; repeated headers are joined with ", "
+    pub headers: BTreeMap<String, String>,
+    pub body: FetchBody,
+}
+
+/// Why a fetch failed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum FetchError {
+    InvalidRequest(String),
+    SchemeNotAllowed(String),
+    Timeout(String),
+    Dns(String),
+    Tls(String),
+    Connect(String),
+    TooLarge(String),
+    Io(String),
+    Other(String),
+}
+
+impl FetchError {
+    /// `transport` allows retrying failures after which the request may
+    /// have reached the server.
+    fn is_retryable(&self, transport: bool) -> bool {
+        match self {
+            Self::Dns(_) => true,
+            Self::Timeout(_) | Self::Connect(_) => transport,
+            _ => false,
+        }
+    }
+}
+
+/// Raised by the redirect policy for a redirect to a scheme not allowed
+#[derive(Debug)]
+struct RedirectRefused(String);
+
+impl std::fmt::Display for RedirectRefused {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Redirect to {} URLs is not allowed", self.0)
+    }
+}
+
+impl std::error::Error for RedirectRefused {}
+
+impl From<reqwest::Error> for FetchError {
+    fn from(e: reqwest::Error) -> Self {
+        let message = e.to_string();
+        if e.is_timeout() {
+            return Self::Timeout(message);
+        }
+        // reqwest doesn't expose the cause directly; look for it in the chain
+        let mut source = std::error::Error::source(&e);
+        while let Some(cause) = source {
+            if let Some(refused) = cause.downcast_ref::<RedirectRefused>() {
+                return Self::SchemeNotAllowed(refused.to_string());
+            }
+            let is_tls = cause.downcast_ref::<rustls::Error>().is_some()
+                || cause
+                    .downcast_ref::<std::io::Error>()
+                    .and_then(|io| io.get_ref())
+                    .is_some_and(|inner| inner.is::<rustls::Error>());
+            if is_tls {
+                return Self::Tls(cause.to_string());
+            }
+            // hyper-util reports resolver failures as "dns error: ..."
+            if cause.to_string().starts_with("dns error") {
+                return Self::Dns(cause.to_string());
+            }
+            source = cause.source();
+        }
+        if e.is_connect() {
+            Self::Connect(message)
+        } else if e.is_builder() || (e.is_request() && e.url().is_none()) {
+            Self::InvalidRequest(message)
+        } else {
+            Self::Other(message)
+        }
+    }
+}
+
+/// The request after validation, ready to send (and resend)
+#[derive(Debug)]
+struct Prepared {
+    method: Method,
+    url: Url,
+    headers: HeaderMap,
+    body: Option<Vec<u8>>,
+    timeout: Duration,
+    attempts: u32,
+    retry_delay: Duration,
+    /// Retry timeouts and connection failures too
+    retry_transport: bool,
+    max_bytes: u64,
+}
+
+fn check_scheme(url: &Url, schemes: &[String]) -> Result<(), FetchError> {
+    if schemes.iter().any(|scheme| scheme == url.scheme()) {
+        Ok(())
+    } else {
+        Err(FetchError::SchemeNotAllowed(format!(
+            "{} URLs are not allowed",
+            url.scheme()
+        )))
+    }
+}
+
+fn prepare(request: FetchRequest, schemes: &[String]) -> Result<Prepared, FetchError> {
+    let invalid = FetchError::InvalidRequest;
+    let method = request
+        .method
+        .as_deref()
+        .unwrap_or("GET")
+        .to_ascii_uppercase();
+    let method = Method::from_bytes(method.as_bytes())
+        .map_err(|_| invalid(format!("Invalid method {}", method)))?;
+    let url = Url::parse(&request.url).map_err(|e| invalid(format!("Invalid URL: {}", e)))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(FetchError::SchemeNotAllowed(format!(
+            "{} URLs can't be fetched",
+            url.scheme()
+        )));
+    }
+    check_scheme(&url, schemes)?;
+
+    let mut headers = HeaderMap::new();
+    for (name, value) in &request.headers {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| invalid(format!("Invalid header name {}", name)))?;
+        let value = HeaderValue::from_str(value)
+            .map_err(|_| invalid(format!("Invalid value for header {}", name)))?;
+        headers.append(name, value);
+    }
+
+    let body = match request.body {
+        Some(body) if request.body_base64 => Some(
+            base64::engine::general_purpose::STANDARD
+                .decode(body)
+                .map_err(|_| invalid("Body is not valid base64".to_string()))?,
+        ),
+        Some(body) => Some(body.into_bytes()),
+        None => None,
+    };
+
+    let retry = request.retry.unwrap_or_default();
+    Ok(Prepared {
+        retry_transport: method.is_idempotent() || retry.non_idempotent,
+        method,
+        url,
+        headers,
+        body,
+        timeout: request
+            .timeout_ms
+            .map_or(DEFAULT_TIMEOUT, Duration::from_millis)
+            .min(MAX_TIMEOUT),
+        attempts: retry.max_attempts.unwrap_or(1).clamp(1, MAX_ATTEMPTS),
+        retry_delay: retry
+            .initial_delay_ms
+            .map_or(DEFAULT_RETRY_DELAY, Duration::from_millis),
+        max_bytes: request
+            .max_bytes
+            .unwrap_or(DEFAULT_MAX_BYTES)
+            .min(MAX_RESPONSE_BYTES),
+    })
+}
+
+fn retryable_status(status: u16) -> bool {
+    matches!(status, 408 | 429 | 500..=599)
+}
+
+fn is_textual(content_type: Option<&str>) -> bool {
+    let Some(content_type) = content_type else {
+        return false;
+    };
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    mime.starts_with("text/")
+        || mime.ends_with("+json")
+        || mime.ends_with("+xml")
+        || matches!(
+            mime.as_str(),
+            "application/json" | "application/xml" | "application/javascript"
+        )
+}
+
+fn inline_body(bytes: Vec<u8>, textual: bool) -> FetchBody {
+    let bytes = if textual {
+        match String::from_utf8(bytes) {
+            Ok(text) => return FetchBody::Text { text },
+            Err(e) => e.into_bytes(),
+        }
+    } else {
+        bytes
+    };
+    FetchBody::Base64 {
+        data: base64::engine::general_purpose::STANDARD.encode(bytes),
+    }
+}
+
+fn too_large(max_bytes: u64) -> FetchError {
+    FetchError::TooLarge(format!("Response is larger than {} bytes", max_bytes))
+}
+
+/// Read the body, spilling to a file in `download_dir` past [`INLINE_MAX_BYTES`].
+async fn read_body(
+    mut response: reqwest::Response,
+    max_bytes: u64,
+    download_dir: &Path,
+) -> Result<FetchBody, FetchError> {
+    if response
+        .content_length()
+        .is_some_and(|length| length > max_bytes)
+    {
+        return Err(too_large(max_bytes));
+    }
+    let textual = is_textual(
+        response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok()),
+    );
+    let io = |e: std::io::Error| FetchError::Io(e.to_string());
+
+    let mut buffer = Vec::new();
+    let mut file: Option<(PathBuf, tokio::fs::File)> = None;
+    let mut size = 0u64;
+    while let Some(chunk) = response.chunk().await? {
+        size += chunk.len() as u64;
+        if size > max_bytes {
+            if let Some((path, file)) = file {
+                drop(file);
+                let _ = tokio::fs::remove_file(path).await;
+            }
+            return Err(too_large(max_bytes));
+        }
+        if file.is_none() && size > INLINE_MAX_BYTES {
+            tokio::fs::create_dir_all(download_dir).await.map_err(io)?;
+            let path = download_dir.join(uuid::Uuid::new_v4().to_string());
+            let mut created = tokio::fs::File::create(&path).await.map_err(io)?;
+            created.write_all(&buffer).await.map_err(io)?;
+            buffer = Vec::new();
+            file = Some((path, created));
+        }
+        match &mut file {
+            Some((_, file)) => file.write_all(&chunk).await.map_err(io)?,
+            None => buffer.extend_from_slice(&chunk),
+        }
+    }
+
+    match file {
+        Some((path, mut file)) => {
+            file.flush().await.map_err(io)?;
+            Ok(FetchBody::File { path, size })
+        }
+        None => Ok(inline_body(buffer, textual)),
+    }
+}
+
+fn response_headers(headers: &HeaderMap) -> BTreeMap<String, String> {
+    let mut joined = BTreeMap::<String, String>::new();
+    for (name, value) in headers {
+        let value = String::from_utf8_lossy(value.as_bytes());
+        joined
+            .entry(name.as_str().to_string())
+            .and_modify(|existing| {
+                existing.push_str(", ");
+                existing.push_str(&value);
+            })
+            .or_insert_with(|| value.into_owned());
+    }
+    joined
+}
+
+/// Send `request` with retries, returning the last response or error.
+async fn execute(
+    client: &reqwest::Client,
+    request: Prepared,
+    schemes: &[String],
+    download_dir: &Path,
+) -> Result<FetchResponse, FetchError> {
+    let mut backoff = Backoff::new(request.retry_delay, MAX_RETRY_DELAY);
+    let mut attempt = 1;
+    loop {
+        let mut builder = client
+            .request(request.method.clone(), request.url.clone())
+            .headers(request.headers.clone())
+            .timeout(request.timeout);
+        if let Some(body) = &request.body {
+            builder = builder.body(body.clone());
+        }
+
+        let retry = attempt < request.attempts;
+        let response = match builder.send().await.map_err(FetchError::from) {
+            Ok(response)
+                if retry
+                    && request.retry_transport
+                    && retryable_status(response.status().as_u16()) =>
+            {
+                log::debug!("Fetch got HTTP {}, retrying", response.status());
+                None
+            }
+            Ok(response) => Some(response),
+            Err(e) if retry && e.is_retryable(request.retry_transport) => {
+                log::debug!("Fetch failed, retrying: {:?}", e);
+                None
+            }
+            Err(e) => return Err(e),
+        };
+
+        if let Some(response) = response {
+            check_scheme(response.url(), schemes)?;
+            return Ok(FetchResponse {
+                status: response.status().as_u16(),
+                url: response.url().to_string(),
+                headers: response_headers(response.headers()),
+                body: read_body(response, request.max_bytes, download_dir).await?,
+            });
+        }
+        tokio::time::sleep(backoff.next_jittered_delay()).await;
+        attempt += 1;
+    }
+}
+
+/// Follow redirects only to the schemes `schemes` returns at the time, so an
+/// allowed URL can't bounce the request to a scheme the settings rule out.
+pub(crate) fn redirect_policy(
+    schemes: impl Fn() -> Vec<String> + Send + Sync + 'static,
+) -> reqwest::redirect::Policy {
+    reqwest::redirect::Policy::custom(move |attempt| {
+        let scheme = attempt.url().scheme().to_string();
+        if !schemes().contains(&scheme) {
+            attempt.error(RedirectRefused(scheme))
+        } else if attempt.previous().len() >= MAX_REDIRECTS {
+            attempt.error("too many redirects")
+        } else {
+            attempt.follow()
+        }
+    })
+}
+
+pub(crate) fn allowed_schemes(app: &AppHandle) -> Vec<String> {
+    app.state::<SettingsState>()
+        .get()
+        .http_fetch_schemes
+        .unwrap_or_else(|| DEFAULT_SCHEMES.iter().map(|s| s.to_string()).collect())
+}
+
+fn download_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_cache_dir()
+        .map(|dir| dir.join(DOWNLOAD_DIR))
+        .map_err(|e| e.to_string())
+}
+
+/// Remove bodies left from the previous run. Called from `setup`.
+pub fn init(app: &AppHandle) {
+    if let Ok(dir) = download_dir(app) {
+        if dir.exists() {
+            if let Err(e) = std::fs::remove_dir_all(&dir) {
+                log::warn!("Failed to clear {}: {}", dir.display(), e);
+            }
+        }
+    }
+}
+
+/// Make an HTTP request outside the webview.
+#[tauri::command]
+pub async fn http_fetch(
+    app: AppHandle,
+    request: FetchRequest,
+) -> Result<FetchResponse, FetchError> {
+    let schemes = allowed_schemes(&app);
+    let prepared = prepare(request, &schemes)?;
+    let download_dir = download_dir(&app).map_err(FetchError::Io)?;
+    execute(&net::fetch_client(&app), prepared, &schemes, &download_dir).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::{Read, Write};
+
+    fn request(url: &str) -> FetchRequest {
+        serde_json::from_value(json!({ "url": url })).unwrap()
+    }
+
+    fn schemes(schemes: &[&str]) -> Vec<String> {
+        schemes.iter().map(|s| s.to_string()).collect()
+    }
+
+    /// Serve `responses` to consecutive connections on a local port.
+    fn serve(responses: Vec<Vec<u8>>) -> (String, std::thread::JoinHandle<()>) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = [0u8; 4096];
+                let _ = stream.read(&mut request).unwrap();
+                // The client hangs up early on responses over the size cap
+                let _ = stream.write_all(&response);
+            }
+        });
+        (url, server)
+    }
+
+    fn http_response(status: &str, content_type: &str, body: &[u8]) -> Vec<u8> {
+        let mut response = format!(
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            status,
+            content_type,
+            body.len()
+        )
+        .into_bytes();
+        response.extend_from_slice(body);
+        response
+    }
+
+    fn fetch(request: FetchRequest, dir: &Path) -> Result<FetchResponse, FetchError> {
+        let schemes = schemes(&["http"]);
+        let prepared = prepare(request, &schemes)?;
+        let allowed = schemes.clone();
+        let client = reqwest::Client::builder()
+            .redirect(redirect_policy(move || allowed.clone()))
+            .build()
+            .unwrap();
+        tauri::async_runtime::block_on(execute(&client, prepared, &schemes, dir))
+    }
+
+    #[test]
+    fn only_allowed_schemes_are_accepted() {
+        let https = schemes(DEFAULT_SCHEMES);
+        assert!(prepare(request("https://example.com/a"), &https).is_ok());
+        assert!(matches!(
+            prepare(request("http://example.com/a"), &https),
+            Err(FetchError::SchemeNotAllowed(_))
+        ));
+        assert!(matches!(
+            prepare(request("file:///etc/passwd"), &schemes(&["file"])),
+            Err(FetchError::SchemeNotAllowed(_))
+        ));
+        assert!(matches!(
+            prepare(request("not a url"), &https),
+            Err(FetchError::InvalidRequest(_))
+        ));
+    }
+
+    #[test]
+    fn errors_serialize_with_kind() {
+        assert_eq!(
+            serde_json::to_value(FetchError::TooLarge("big".to_string())).unwrap(),
+            json!({"kind": "too_large", "message": "big"})
+        );
+    }
+
+    #[test]
+    fn text_bodies_are_inline_and_binary_is_base64() {
+        let dir = std::env::temp_dir().join(format!("hush-fetch-{}", std::process::id()));
+        let (url, server) = serve(vec![
+            http_response(
+                "200 OK",
+                "application/json; charset=utf-8",
+                b"{\"ok\":true}",
+            ),
+            http_response("200 OK", "image/png", &[0x89, 0x50]),
+        ]);
+
+        let response = fetch(request(&url), &dir).unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(
+            response.headers["content-type"],
+            "application/json; charset=utf-8"
+        );
+        assert_eq!(
+            response.body,
+            FetchBody::Text {
+                text: "{\"ok\":true}".to_string()
+            }
+        );
+
+        let response = fetch(request(&url), &dir).unwrap();
+        assert_eq!(
+            response.body,
+            FetchBody::Base64 {
+                data: "iVA=".to_string()
+            }
+        );
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn large_bodies_stream_to_a_file_within_the_cap() {
+        let dir = std::env::temp_dir().join(format!("hush-fetch-large-{}", std::process::id()));
+        let body = vec![7u8; INLINE_MAX_BYTES as usize + 10];
+        let (url, server) = serve(vec![
+            http_response("200 OK", "application/octet-stream", &body),
+            http_response("200 OK", "application/octet-stream", &body),
+        ]);
+
+        let response = fetch(request(&url), &dir).unwrap();
+        let FetchBody::File { path, size } = response.body else {
+            panic!("expected a file body");
+        };
+        assert_eq!(size, body.len() as u64);
+        assert_eq!(std::fs::read(&path).unwrap(), body);
+
+        let mut capped = request(&url);
+        capped.max_bytes = Some(1024);
+        assert!(matches!(fetch(capped, &dir), Err(FetchError::TooLarge(_))));
+        server.join().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn retryable_statuses_are_retried() {
+        let dir = std::env::temp_dir();
+        let (url, server) = serve(vec![
+            http_response("503 Service Unavailable", "text/plain", b"busy"),
+            http_response("200 OK", "text/plain", b"done"),
+        ]);
+        let mut retried = request(&url);
+        retried.retry = Some(RetryPolicy {
+            max_attempts: Some(2),
+            initial_delay_ms: Some(1),
+            non_idempotent: false,
+        });
+
+        let response = fetch(retried, &dir).unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(
+            response.body,
+            FetchBody::Text {
+                text: "done".to_string()
+            }
+        );
+        server.join().unwrap();
+
+        let (url, server) = serve(vec![http_response(
+            "503 Service Unavailable",
+            "text/plain",
+            b"busy",
+        )]);
+        let mut post = request(&url);
+        post.method = Some("post".to_string());
+        post.retry = Some(RetryPolicy {
+            max_attempts: Some(2),
+            initial_delay_ms: Some(1),
+            non_idempotent: false,
+        });
+        assert_eq!(fetch(post, &dir).unwrap().status, 503);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn redirects_to_schemes_outside_the_allowlist_are_refused() {
+        let dir = std::env::temp_dir();
+        let redirect = [
+            "HTTP/1.1 302 Found",
+            "Location: https://example.com/",
+            "Content-Length: 0",
+            "Connection: close",
+            "",
+            "",
+        ]
+        .join("\r\n");
+        let (url, server) = serve(vec![redirect.into_bytes()]);
+        assert!(matches!(
+            fetch(request(&url), &dir),
+            Err(FetchError::SchemeNotAllowed(_))
+        ));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn transport_failures_are_retried_only_when_idempotent() {
+        let https = schemes(DEFAULT_SCHEMES);
+        let post = |non_idempotent| {
+            let mut request = request("https://example.com/a");
+            request.method = Some("post".to_string());
+            request.retry = Some(RetryPolicy {
+                max_attempts: Some(3),
+                initial_delay_ms: None,
+                non_idempotent,
+            });
+            prepare(request, &https).unwrap()
+        };
+        assert!(
+            prepare(request("https://example.com/a"), &https)
+                .unwrap()
+                .retry_transport
+        );
+        assert!(!post(false).retry_transport);
+        assert!(post(true).retry_transport);
+
+        let timeout = FetchError::Timeout("slow".to_string());
+        assert!(timeout.is_retryable(true));
+        assert!(!timeout.is_retryable(false));
+        assert!(FetchError::Dns("nx".to_string()).is_retryable(false));
+    }
+}
@@ -0,0 +1,183 @@
+//! System-wide user idle time, for presence and auto-away.
+//!
+//! Windows and macOS read it through `user-idle`. Linux asks the desktop over
+//! D-Bus (Mutter's `IdleMonitor`, then `org.freedesktop.ScreenSaver`), which
+//! works under both X11 and Wayland where the compositor offers it; elsewhere,
+//! and on mobile, reads fail with [`UNSUPPORTED`] rather than guessing.
+//!
+//! `start_idle_monitor` polls every [`POLL_INTERVAL`] and emits `user-idle`
+//! when the idle time crosses the threshold and `user-active` when input
+//! resumes.
+
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, State};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// Error returned where idle time can't be read
+pub const UNSUPPORTED: &str = "unsupported";
+
+/// Payload of the `user-idle` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdlePayload {
+    pub idle_seconds: u64,
+}
+
+/// Managed state holding the running monitor
+#[derive(Default)]
+pub struct IdleState {
+    monitor: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl IdleState {
+    fn stop(&self) {
+        if let Some(task) = self
+            .monitor
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+        {
+            task.abort();
+        }
+    }
+}
+
+/// Parse a `(uint64 1234,)` or `(uint32 12,)` gdbus reply.
+#[cfg(target_os = "linux")]
+fn parse_gdbus_uint(output: &str) -> Option<u64> {
+    output
+        .split("uint")
+        .nth(1)?
+        .split_whitespace()
+        .nth(1)?
+        .trim_end_matches([',', ')'])
+        .parse()
+        .ok()
+}
+
+#[cfg(target_os = "linux")]
+fn gdbus_call(dest: &str, path: &str, method: &str) -> Option<u64> {
+    let output = std::process::Command::new("gdbus")
+        .args([
+            "call",
+            "--session",
+            "--dest",
+            dest,
+            "--object-path",
+            path,
+            "--method",
+            method,
+        ])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    parse_gdbus_uint(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Seconds since the last user input. Blocking.
+fn idle_seconds() -> Result<u64, String> {
+    #[cfg(target_os = "linux")]
+    {
+        gdbus_call(
+            "org.gnome.Mutter.IdleMonitor",
+            "/org/gnome/Mutter/IdleMonitor/Core",
+            "org.gnome.Mutter.IdleMonitor.GetIdletime",
+        )
+        .map(|millis| millis / 1000)
+        .or_else(|| {
+            gdbus_call(
+                "org.freedesktop.ScreenSaver",
+                "/org/freedesktop/ScreenSaver",
+                "org.freedesktop.ScreenSaver.GetSessionIdleTime",
+            )
+        })
+        .ok_or_else(|| UNSUPPORTED.to_string())
+    }
+    #[cfg(any(target_os = "windows", target_os = "macos"))]
+    {
+        user_idle::UserIdle::get_time()
+            .map(|idle| idle.as_seconds())
+            .map_err(|e| e.to_string())
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+    {
+        Err(UNSUPPORTED.to_string())
+    }
+}
+
+/// [`idle_seconds`] off the async runtime.
+pub(crate) async fn read() -> Result<u64, String> {
+    tauri::async_runtime::spawn_blocking(idle_seconds)
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+async fn monitor(app: AppHandle, threshold: u64) {
+    let mut idle = false;
+    loop {
+        tokio::time::sleep(POLL_INTERVAL).await;
+        let seconds = match read().await {
+            Ok(seconds) => seconds,
+            Err(e) => {
+                log::debug!("Idle time unavailable: {}", e);
+                continue;
+            }
+        };
+        if !idle && seconds >= threshold {
+            idle = true;
+            let _ = app.emit(
+                "user-idle",
+                IdlePayload {
+                    idle_seconds: seconds,
+                },
+            );
+        } else if idle && seconds < threshold {
+            idle = false;
+            let _ = app.emit("user-active", ());
+        }
+    }
+}
+
+/// Seconds since the last keyboard or mouse input anywhere on the system.
+#[tauri::command]
+pub async fn get_idle_seconds() -> Result<u64, String> {
+    read().await
+}
+
+/// Emit `user-idle`/`user-active` as idle time crosses `threshold_secs`,
+/// replacing any running monitor.
+#[tauri::command]
+pub async fn start_idle_monitor(
+    app: AppHandle,
+    state: State<'_, IdleState>,
+    threshold_secs: u32,
+) -> Result<(), String> {
+    if threshold_secs == 0 {
+        return Err("Idle threshold must be at least one second".to_string());
+    }
+    read().await?;
+    state.stop();
+    let task = tauri::async_runtime::spawn(monitor(app, u64::from(threshold_secs)));
+    *state.monitor.lock().unwrap_or_else(|e| e.into_inner()) = Some(task);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn stop_idle_monitor(state: State<'_, IdleState>) {
+    state.stop();
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gdbus_uints_are_parsed() {
+        assert_eq!(parse_gdbus_uint("(uint64 12345,)\n"), Some(12345));
+        assert_eq!(parse_gdbus_uint("(uint32 7,)\n"), Some(7));
+        assert_eq!(parse_gdbus_uint("Error: GDBus.Error"), None);
+    }
+}
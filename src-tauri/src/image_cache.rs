@@ -0,0 +1,431 @@
+//! Disk cache for avatars and inline images, since the webview's own cache is
+//! unreliable and unbounded.
+//!
+//! `cached_image` returns a local path the frontend loads through the asset
+//! protocol (`convertFileSrc`), downloading the image on a miss. Files live
+//! under `images/` in the app cache dir, named by the SHA-256 of the URL. The
+//! total size is capped by the `imageCacheMaxBytes` setting (default
+//! [`DEFAULT_MAX_BYTES`]), evicting least recently used files first; a sweep
+//! on startup rebuilds the index from the directory and trims it to the cap.
+//!
+//! Responses marked `Cache-Control: no-store` are not cached: they are written
+//! under `images/transient/`, served once, and deleted on the next start.
+
+use crate::fcm::now_unix_ms;
+use crate::net;
+use crate::settings::SettingsState;
+use crate::storage;
+use reqwest::header::CACHE_CONTROL;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager, State, Url};
+use tokio::io::AsyncWriteExt;
+
+const CACHE_DIR: &str = "images";
+const TRANSIENT_DIR: &str = "transient";
+/// Cache size cap used when `imageCacheMaxBytes` isn't set (500 MB)
+pub const DEFAULT_MAX_BYTES: u64 = 500 * 1024 * 1024;
+/// Largest single image downloaded (25 MB)
+const MAX_IMAGE_BYTES: u64 = 25 * 1024 * 1024;
+
+/// Returned by `get_image_cache_stats`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageCacheStats {
+    pub entries: u64,
+    pub bytes: u64,
+    pub max_bytes: u64,
+    pub hits: u64,
+    pub misses: u64,
+    /// Hits over lookups since start or the last clear; 0 before any lookup
+    pub hit_rate: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Entry {
+    size: u64,
+    /// Unix timestamp (ms) of the last read or write
+    last_access: u64,
+}
+
+/// Cached files by key, with their total size
+#[derive(Debug, Default)]
+struct Index {
+    entries: HashMap<String, Entry>,
+    bytes: u64,
+}
+
+impl Index {
+    fn insert(&mut self, key: String, entry: Entry) {
+        if let Some(old) = self.entries.insert(key, entry) {
+            self.bytes -= old.size;
+        }
+        self.bytes += entry.size;
+    }
+
+    fn touch(&mut self, key: &str, now: u64) -> bool {
+        match self.entries.get_mut(key) {
+            Some(entry) => {
+                entry.last_access = now;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drop least recently used entries until the total fits in `max_bytes`;
+    /// returns the evicted keys for their files to be deleted.
+    fn evict_to(&mut self, max_bytes: u64) -> Vec<String> {
+        if self.bytes <= max_bytes {
+            return Vec::new();
+        }
+        let mut by_age: Vec<(String, Entry)> = self
+            .entries
+            .iter()
+            .map(|(key, entry)| (key.clone(), *entry))
+            .collect();
+        by_age.sort_by_key(|(_, entry)| entry.last_access);
+        let mut evicted = Vec::new();
+        for (key, entry) in by_age {
+            if self.bytes <= max_bytes {
+                break;
+            }
+            self.entries.remove(&key);
+            self.bytes -= entry.size;
+            evicted.push(key);
+        }
+        evicted
+    }
+}
+
+/// Managed state holding the index and hit counters
+#[derive(Default)]
+pub struct ImageCacheState {
+    index: Mutex<Index>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl ImageCacheState {
+    fn lock(&self) -> MutexGuard<'_, Index> {
+        self.index.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// File name for `url`: the hex SHA-256 of the URL.
+fn cache_key(url: &str) -> String {
+    Sha256::digest(url.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Whether a `Cache-Control` header forbids storing the response.
+fn is_no_store(cache_control: &str) -> bool {
+    cache_control
+        .split(',')
+        .any(|directive| directive.trim().eq_ignore_ascii_case("no-store"))
+}
+
+pub(crate) fn cache_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_cache_dir()
+        .map(|dir| dir.join(CACHE_DIR))
+        .map_err(|e| e.to_string())
+}
+
+pub(crate) fn max_bytes(app: &AppHandle) -> u64 {
+    app.state::<SettingsState>()
+        .get()
+        .image_cache_max_bytes
+        .unwrap_or(DEFAULT_MAX_BYTES)
+}
+
+fn entry_from(meta: &std::fs::Metadata) -> Entry {
+    let last_access = meta
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |age| age.as_millis() as u64);
+    Entry {
+        size: meta.len(),
+        last_access,
+    }
+}
+
+/// Index the cached files in `dir`, removing leftovers from interrupted
+/// downloads.
+fn scan(dir: &Path) -> Index {
+    let mut index = Index::default();
+    let Ok(files) = std::fs::read_dir(dir) else {
+        return index;
+    };
+    for file in files.flatten() {
+        let Ok(meta) = file.metadata() else { continue };
+        if !meta.is_file() {
+            continue;
+        }
+        let name = file.file_name().to_string_lossy().into_owned();
+        if name.ends_with(".part") {
+            let _ = std::fs::remove_file(file.path());
+            continue;
+        }
+        index.insert(name, entry_from(&meta));
+    }
+    index
+}
+
+fn remove_files(dir: &Path, keys: &[String]) {
+    for key in keys {
+        if let Err(e) = std::fs::remove_file(dir.join(key)) {
+            log::debug!("Failed to evict cached image {}: {}", key, e);
+        }
+    }
+}
+
+/// Record the access in the file's mtime, which the startup sweep reads back.
+fn touch_file(path: &Path) {
+    let touched = std::fs::File::options()
+        .append(true)
+        .open(path)
+        .and_then(|file| file.set_modified(SystemTime::now()));
+    if let Err(e) = touched {
+        log::debug!("Failed to touch {}: {}", path.display(), e);
+    }
+}
+
+fn sweep(app: &AppHandle, dir: &Path) {
+    let _ = std::fs::remove_dir_all(dir.join(TRANSIENT_DIR));
+    let scanned = scan(dir);
+    let state = app.state::<ImageCacheState>();
+    let evicted = {
+        let mut index = state.lock();
+        // Keep entries already added by lookups made during the scan
+        for (key, entry) in scanned.entries {
+            if !index.entries.contains_key(&key) {
+                index.insert(key, entry);
+            }
+        }
+        index.evict_to(max_bytes(app))
+    };
+    remove_files(dir, &evicted);
+    log::info!(
+        "Image cache: {} files, {} evicted",
+        state.lock().entries.len(),
+        evicted.len()
+    );
+}
+
+/// Sweep the cache directory in the background. Called from `setup` after
+/// [`ImageCacheState`] is managed.
+pub fn init(app: &AppHandle) {
+    let Ok(dir) = cache_dir(app) else { return };
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || sweep(&app, &dir));
+}
+
+/// Stream `url` to `path`, returning whether the origin allowed storing it.
+async fn download(client: &reqwest::Client, url: Url, path: &Path) -> Result<bool, String> {
+    let mut response = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.to_string())?;
+    if response
+        .content_length()
+        .is_some_and(|length| length > MAX_IMAGE_BYTES)
+    {
+        return Err("Image is too large to cache".to_string());
+    }
+    let store = !response
+        .headers()
+        .get_all(CACHE_CONTROL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .any(is_no_store);
+
+    let mut file = tokio::fs::File::create(path)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut received = 0u64;
+    while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+        received += chunk.len() as u64;
+        if received > MAX_IMAGE_BYTES {
+            return Err("Image is too large to cache".to_string());
+        }
+        file.write_all(&chunk).await.map_err(|e| e.to_string())?;
+    }
+    file.flush().await.map_err(|e| e.to_string())?;
+    Ok(store)
+}
+
+/// Local path for the image at `url`, downloading it if it isn't cached.
+#[tauri::command]
+pub async fn cached_image(
+    app: AppHandle,
+    state: State<'_, ImageCacheState>,
+    url: String,
+) -> Result<String, String> {
+    let parsed = Url::parse(&url).map_err(|e| format!("Invalid image URL: {}", e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err("Image URL must use http or https".to_string());
+    }
+    let dir = cache_dir(&app)?;
+    let key = cache_key(&url);
+    let path = dir.join(&key);
+
+    let indexed = state.lock().touch(&key, now_unix_ms());
+    // Files not indexed yet can still be on disk while the startup sweep runs
+    let on_disk = if indexed {
+        path.exists()
+    } else {
+        match std::fs::metadata(&path) {
+            Ok(meta) => {
+                state.lock().insert(key.clone(), entry_from(&meta));
+                true
+            }
+            Err(_) => false,
+        }
+    };
+    if on_disk {
+        state.hits.fetch_add(1, Ordering::Relaxed);
+        touch_file(&path);
+        return Ok(path.to_string_lossy().into_owned());
+    }
+    state.misses.fetch_add(1, Ordering::Relaxed);
+
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let part = dir.join(format!("{}.{}.part", key, uuid::Uuid::new_v4()));
+    let store = match download(&net::client(&app), parsed, &part).await {
+        Ok(store) => store,
+        Err(e) => {
+            let _ = std::fs::remove_file(&part);
+            return Err(e);
+        }
+    };
+
+    if !store {
+        let transient = dir.join(TRANSIENT_DIR);
+        std::fs::create_dir_all(&transient).map_err(|e| e.to_string())?;
+        let target = transient.join(uuid::Uuid::new_v4().to_string());
+        std::fs::rename(&part, &target).map_err(|e| e.to_string())?;
+        return Ok(target.to_string_lossy().into_owned());
+    }
+
+    std::fs::rename(&part, &path).map_err(|e| e.to_string())?;
+    let size = std::fs::metadata(&path).map_err(|e| e.to_string())?.len();
+    let evicted = {
+        let mut index = state.lock();
+        index.insert(
+            key.clone(),
+            Entry {
+                size,
+                last_access: now_unix_ms(),
+            },
+        );
+        // Never evict the file about to be returned
+        let mut evicted = index.evict_to(max_bytes(&app).max(size));
+        evicted.retain(|evicted| *evicted != key);
+        evicted
+    };
+    remove_files(&dir, &evicted);
+    Ok(path.to_string_lossy().into_owned())
+}
+
+#[tauri::command]
+pub fn get_image_cache_stats(app: AppHandle, state: State<'_, ImageCacheState>) -> ImageCacheStats {
+    let (entries, bytes) = {
+        let index = state.lock();
+        (index.entries.len() as u64, index.bytes)
+    };
+    let hits = state.hits.load(Ordering::Relaxed);
+    let misses = state.misses.load(Ordering::Relaxed);
+    let lookups = hits + misses;
+    ImageCacheStats {
+        entries,
+        bytes,
+        max_bytes: max_bytes(&app),
+        hits,
+        misses,
+        hit_rate: if lookups == 0 {
+            0.0
+        } else {
+            hits as f64 / lookups as f64
+        },
+    }
+}
+
+/// Delete every cached image and reset the hit counters; returns the bytes
+/// freed.
+pub(crate) fn clear(app: &AppHandle, state: &ImageCacheState) -> Result<u64, String> {
+    let dir = cache_dir(app)?;
+    let mut index = state.lock();
+    let bytes = storage::disk_usage(&dir);
+    match std::fs::remove_dir_all(&dir) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(format!("Failed to clear {}: {}", dir.display(), e)),
+    }
+    *index = Index::default();
+    state.hits.store(0, Ordering::Relaxed);
+    state.misses.store(0, Ordering::Relaxed);
+    Ok(bytes)
+}
+
+/// Delete every cached image and reset the hit counters.
+#[tauri::command]
+pub fn clear_image_cache(app: AppHandle, state: State<'_, ImageCacheState>) -> Result<(), String> {
+    clear(&app, &state).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(size: u64, last_access: u64) -> Entry {
+        Entry { size, last_access }
+    }
+
+    #[test]
+    fn least_recently_used_entries_are_evicted_first() {
+        let mut index = Index::default();
+        index.insert("old".to_string(), entry(40, 1));
+        index.insert("new".to_string(), entry(40, 3));
+        index.insert("mid".to_string(), entry(40, 2));
+        assert!(index.evict_to(120).is_empty());
+
+        assert!(index.touch("old", 4));
+        assert_eq!(index.evict_to(50), ["mid", "new"]);
+        assert_eq!(index.bytes, 40);
+        assert!(index.entries.contains_key("old"));
+    }
+
+    #[test]
+    fn reinserting_replaces_the_size() {
+        let mut index = Index::default();
+        index.insert("a".to_string(), entry(10, 1));
+        index.insert("a".to_string(), entry(25, 2));
+        assert_eq!(index.bytes, 25);
+    }
+
+    #[test]
+    fn no_store_is_detected_among_directives() {
+        assert!(is_no_store("no-store"));
+        assert!(is_no_store("private, No-Store, max-age=0"));
+        assert!(!is_no_store("no-cache, max-age=60"));
+    }
+
+    #[test]
+    fn keys_are_stable_hex_hashes() {
+        let key = cache_key("https://cdn.hushnetwork.social/a.png");
+        assert_eq!(key.len(), 64);
+        assert_eq!(key, cache_key("https://cdn.hushnetwork.social/a.png"));
+        assert_ne!(key, cache_key("https://cdn.hushnetwork.social/b.png"));
+    }
+}
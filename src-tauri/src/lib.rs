@@ -1,12 +1,75 @@
+mod account_backup;
+#[cfg(target_os = "android")]
+mod android;
+mod app_data;
+mod app_info;
+mod app_shortcuts;
+mod attachments;
+mod attention;
+mod autostart;
+mod backoff;
+mod background_sync;
+mod badge;
+mod biometrics;
+mod cache;
+mod changelog;
+mod clipboard;
+mod connectivity;
+mod crash;
+mod deep_link;
+mod diagnostics;
+mod doh;
+mod downloads;
+mod drafts;
+mod encrypted_store;
+mod error;
 mod fcm;
+mod feed_windows;
+mod file_drop;
+mod http_fetch;
+mod idle;
+mod image_cache;
+mod links;
+mod live_stream;
+mod locale;
+mod lock;
+mod logging;
 mod mobile_benchmark;
+mod navigation_state;
+mod net;
+mod notification_history;
+mod notifications;
+mod outbox;
+mod pending_actions;
+mod power;
+mod pinning;
+mod push_diagnostics;
+#[cfg(desktop)]
+mod push_stream;
+#[cfg(all(target_os = "linux", feature = "unifiedpush"))]
+mod push_unifiedpush;
+mod qr;
+mod quit_guard;
+mod screen_security;
+mod secure_store;
+mod settings;
+mod share;
+mod shortcut;
+mod storage;
+mod suspend;
+mod system_info;
+mod task_progress;
+mod theme;
+mod tray;
+mod updates;
+mod wake_lock;
+mod window;
+mod window_focus;
+mod zoom;
 
 #[cfg(desktop)]
-use tauri::{
-    image::Image,
-    tray::{TrayIconBuilder, TrayIconEvent, MouseButton, MouseButtonState},
-    Manager,
-};
+use tauri::image::Image;
+use tauri::Manager;
 #[cfg(all(desktop, any(target_os = "windows", target_os = "linux", target_os = "macos")))]
 use tauri_plugin_deep_link::DeepLinkExt;
 
@@ -17,42 +80,290 @@ fn app_icon() -> tauri::Result<Image<'static>> {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    crash::install_hook();
+
     let mut builder = tauri::Builder::default()
         .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_process::init())
         .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_deep_link::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .plugin(tauri_plugin_dialog::init())
+        .manage(fcm::FcmState::default())
+        .manage(fcm::PermissionWatchState::default())
+        .manage(window::LaunchState::from_args())
+        .manage(shortcut::ToggleShortcutState::default())
+        .manage(connectivity::ConnectivityState::default())
+        .manage(updates::UpdateState::default())
+        .manage(theme::ThemeState::default())
+        .manage(power::PowerState::default())
+        .manage(idle::IdleState::default())
+        .manage(suspend::SuspendState::default())
+        .manage(clipboard::ClipboardState::default())
+        .manage(attachments::AttachmentState::default())
+        .manage(image_cache::ImageCacheState::default())
+        .manage(file_drop::FileDropState::default())
+        .manage(logging::FrontendLogState::default())
+        .manage(wake_lock::WakeLockState::default())
+        .manage(live_stream::LiveStreamState::default())
+        .manage(app_data::StorageUsageState::default())
+        .manage(notifications::NotificationGroupState::default())
+        .manage(notifications::DeliveredNotificationsState::default())
+        .manage(notifications::ActiveFeedState::default())
+        .manage(window_focus::WindowFocusState::default())
+        .manage(task_progress::TaskProgressState::default())
+        .manage(attention::AttentionState::default())
+        .manage(quit_guard::QuitGuardState::default())
+        .manage(navigation_state::NavigationSaveState::default())
         .invoke_handler(tauri::generate_handler![
             fcm::get_platform,
             fcm::get_device_name,
             fcm::has_notification_permission,
+            fcm::request_notification_permission,
             fcm::get_fcm_token,
+            fcm::set_fcm_token,
+            fcm::set_apns_token,
+            fcm::set_notification_authorization,
+            fcm::notify_fcm_token_refreshed,
+            fcm::invalidate_push_registration,
             fcm::is_push_supported,
+            fcm::get_push_support_details,
             fcm::get_pending_navigation,
+            fcm::get_pending_navigations,
+            fcm::take_pending_navigation,
             fcm::clear_pending_navigation,
+            pending_actions::get_pending_actions,
+            pending_actions::ack_pending_action,
+            account_backup::export_account_backup,
+            account_backup::import_account_backup,
+            app_data::clear_app_data,
+            app_data::get_storage_usage,
+            app_info::get_app_info,
+            attachments::save_attachment,
+            attachments::cancel_download,
+            autostart::get_autostart,
+            autostart::set_autostart,
+            background_sync::set_sync_interval,
+            background_sync::configure_background_sync,
+            cache::cache_upsert_posts,
+            cache::cache_get_posts,
+            cache::cache_get_feeds,
+            cache::cache_set_unread,
+            cache::cache_evict,
+            changelog::get_changelog,
+            clipboard::copy_sensitive,
+            clipboard::get_clipboard_image,
+            clipboard::clear_temp_attachments,
+            connectivity::get_connectivity,
+            crash::get_last_crash_report,
+            crash::dismiss_crash_report,
+            diagnostics::export_diagnostics,
+            doh::set_doh,
+            doh::get_doh_status,
+            downloads::download_start,
+            downloads::download_pause,
+            downloads::download_resume,
+            downloads::download_cancel,
+            downloads::download_list,
+            downloads::set_max_concurrent_downloads,
+            drafts::save_draft,
+            drafts::get_draft,
+            drafts::list_drafts,
+            drafts::delete_draft,
+            encrypted_store::is_encryption_available,
+            encrypted_store::get_encryption_status,
+            error::get_error_codes,
+            file_drop::set_uploading,
+            http_fetch::http_fetch,
+            idle::get_idle_seconds,
+            idle::start_idle_monitor,
+            idle::stop_idle_monitor,
+            image_cache::cached_image,
+            image_cache::get_image_cache_stats,
+            image_cache::clear_image_cache,
+            links::open_external,
+            links::open_external_confirmed,
+            links::get_link_allowlist,
+            links::remove_from_link_allowlist,
+            live_stream::stream_connect,
+            live_stream::stream_disconnect,
+            live_stream::stream_send,
+            live_stream::get_stream_status,
+            locale::get_locale_info,
+            logging::get_log_directory,
+            logging::open_log_directory,
+            logging::set_log_level,
+            logging::get_log_level,
+            logging::log_from_frontend,
             mobile_benchmark::get_mobile_benchmark_native_probe,
+            net::set_proxy,
+            net::get_proxy,
+            net::set_tor_mode,
+            net::get_tor_status,
+            notifications::show_feed_notification,
+            notifications::get_notification_capabilities,
+            notifications::set_active_feed,
+            notifications::dismiss_notifications_for_feed,
+            notifications::dismiss_all_notifications,
+            notifications::list_notification_sounds,
+            notifications::get_notification_sound,
+            notifications::set_notification_sound,
+            notifications::get_quiet_hours,
+            notifications::set_quiet_hours,
+            notifications::is_quiet_hours_active,
+            notifications::mute_feed,
+            notifications::unmute_feed,
+            notifications::get_muted_feeds,
+            notification_history::get_notification_history,
+            notification_history::clear_notification_history,
+            outbox::outbox_enqueue,
+            outbox::outbox_list,
+            outbox::outbox_cancel,
+            pinning::set_pinned_certificates,
+            pinning::clear_pinned_certificates,
+            pinning::get_pinned_certificates,
+            power::get_power_status,
+            power::set_power_throttle_threshold,
+            push_diagnostics::get_push_diagnostics,
+            push_diagnostics::reset_push_diagnostics,
+            #[cfg(desktop)]
+            push_stream::configure_push_connection,
+            #[cfg(desktop)]
+            push_stream::get_push_connection_status,
+            #[cfg(all(target_os = "linux", feature = "unifiedpush"))]
+            push_unifiedpush::register_unifiedpush,
+            #[cfg(all(target_os = "linux", feature = "unifiedpush"))]
+            push_unifiedpush::unregister_unifiedpush,
+            qr::generate_qr,
+            secure_store::secure_set,
+            secure_store::secure_get,
+            secure_store::secure_delete,
+            secure_store::secure_list_keys,
+            secure_store::get_secure_store_status,
+            settings::get_setting,
+            settings::set_setting,
+            settings::get_all_settings,
+            settings::export_settings,
+            settings::import_settings,
+            share::share_content,
+            system_info::get_system_info,
+            theme::get_system_theme,
+            theme::set_window_theme,
+            tray::set_tray_unread_count,
+            tray::update_tray_tooltip,
+            tray::set_connection_state,
+            updates::check_for_update,
+            updates::download_and_install_update,
+            updates::install_update_on_exit,
+            updates::get_staged_update,
+            updates::set_auto_update,
+            updates::set_update_channel,
+            updates::get_update_channel,
+            updates::skip_update_version,
+            updates::snooze_update,
+            updates::get_update_preferences,
+            updates::clear_update_preferences,
+            wake_lock::acquire_wake_lock,
+            wake_lock::release_wake_lock,
+            wake_lock::list_wake_locks,
+            badge::set_badge_count,
+            badge::set_unread_count,
+            task_progress::set_task_progress,
+            attention::request_attention,
+            quit_guard::force_quit,
+            navigation_state::save_navigation_state,
+            navigation_state::get_navigation_state,
+            window::set_close_to_tray,
+            window::get_close_to_tray,
+            window::reset_window_state,
+            window::was_started_minimized,
+            window::frontend_ready,
+            window::set_always_on_top,
+            window::enter_compact_mode,
+            window::exit_compact_mode,
+            window_focus::is_window_focused,
+            shortcut::register_toggle_shortcut,
+            shortcut::unregister_toggle_shortcut,
+            zoom::set_zoom,
+            zoom::get_zoom,
+            zoom::zoom_step,
+            screen_security::set_screen_security,
+            screen_security::get_screen_security,
+            feed_windows::open_feed_window,
+            feed_windows::close_feed_window,
+            feed_windows::list_feed_windows,
+            app_shortcuts::register_app_shortcuts,
+            app_shortcuts::get_app_shortcuts,
+            lock::set_app_lock,
+            lock::unlock,
+            lock::lock_now,
+            lock::get_lock_state,
+            biometrics::is_biometric_available,
+            biometrics::unlock_with_biometrics,
         ]);
 
     #[cfg(desktop)]
     {
-        builder = builder.plugin(tauri_plugin_single_instance::init(|app, _argv, _cwd| {
-            if let Some(window) = app.get_webview_window("main") {
-                let _ = window.show();
-                let _ = window.set_focus();
-            }
+        builder = builder.plugin(tauri_plugin_single_instance::init(|app, argv, cwd| {
+            window::on_second_instance(app, argv, cwd);
         }));
+        builder = builder.plugin(
+            tauri_plugin_global_shortcut::Builder::new()
+                .with_handler(shortcut::handle_shortcut)
+                .build(),
+        );
     }
 
     builder
+        .on_window_event(window::on_window_event)
         .setup(|app| {
-            if cfg!(debug_assertions) {
-                app.handle().plugin(
-                    tauri_plugin_log::Builder::default()
-                        .level(log::LevelFilter::Info)
-                        .build(),
-                )?;
-            }
+            app.handle().plugin(logging::plugin())?;
+
+            app.manage(settings::SettingsState::load(app.handle()));
+            logging::init(app.handle());
+            crash::init(app.handle());
+            app.manage(window::WindowBehaviorState::load(app.handle()));
+            app.manage(fcm::PendingNavigationState::load(app.handle()));
+            app.manage(notifications::NotificationSoundState::load(app.handle()));
+            app.manage(notifications::QuietHoursState::load(app.handle()));
+            app.manage(notifications::MutedFeedsState::load(app.handle()));
+            app.manage(notification_history::NotificationHistoryState::load(app.handle()));
+            app.manage(pending_actions::PendingActionsState::load(app.handle()));
+            app.manage(push_diagnostics::PushDiagnosticsState::load(app.handle()));
+            app.manage(secure_store::SecureStoreState::load(app.handle()));
+            app.manage(encrypted_store::EncryptionState::load(app.handle()));
+            app.manage(lock::LockState::load(app.handle()));
+            app.manage(doh::DohState::load(app.handle()));
+            app.manage(links::LinkAllowlistState::load(app.handle()));
+            app.manage(net::NetworkState::load(app.handle()));
+            app.manage(drafts::DraftsState::load(app.handle()));
+            app.manage(cache::CacheState::load(app.handle()));
+            app.manage(changelog::ChangelogState::load(app.handle()));
+            app.manage(outbox::OutboxState::load(app.handle()));
+            app.manage(downloads::DownloadsState::load(app.handle()));
+            app.manage(background_sync::BackgroundSyncState::load(app.handle()));
+            deep_link::init(app.handle());
+            cache::init(app.handle());
+            outbox::init(app.handle());
+            downloads::init(app.handle());
+            connectivity::init(app.handle());
+            background_sync::init(app.handle());
+            http_fetch::init(app.handle());
+            qr::init(app.handle());
+            image_cache::init(app.handle());
+            updates::init(app.handle());
+            locale::init(app.handle());
+            theme::init(app.handle());
+            power::init(app.handle());
+            suspend::init(app.handle());
+            lock::init(app.handle());
+            pending_actions::init(app.handle());
+            #[cfg(all(target_os = "linux", feature = "unifiedpush"))]
+            push_unifiedpush::init(app.handle());
+            #[cfg(desktop)]
+            push_stream::init(app.handle());
 
             #[cfg(all(
                 desktop,
@@ -72,23 +383,50 @@ pub fn run() {
                     window.set_icon(app_icon.clone())?;
                 }
 
-                let _tray = TrayIconBuilder::new()
-                    .icon(app_icon)
-                    .tooltip("Hush Feeds")
-                    .on_tray_icon_event(|tray, event| {
-                        // Show/focus main window on tray click
-                        if let TrayIconEvent::Click { button: MouseButton::Left, button_state: MouseButtonState::Up, .. } = event {
-                            if let Some(window) = tray.app_handle().get_webview_window("main") {
-                                let _ = window.show();
-                                let _ = window.set_focus();
-                            }
-                        }
-                    })
-                    .build(app)?;
+                tray::create(app, app_icon)?;
             }
 
+            shortcut::init(app.handle());
+            zoom::init(app.handle());
+
+            #[cfg(desktop)]
+            app.manage(window::CompactModeState::default());
+            #[cfg(desktop)]
+            app.manage(app_shortcuts::AppShortcutState::default());
+            #[cfg(desktop)]
+            app.manage(badge::UnreadCountState::default());
+            // Restore saved geometry before the (initially hidden) main window is shown
+            #[cfg(desktop)]
+            window::restore_geometry(app.handle());
+            screen_security::init(app.handle());
+
+            // Shown on frontend_ready, so the window never appears blank. With
+            // --minimized only the tray icon appears; a tray click shows the window
+            window::show_when_ready(app.handle());
+
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| match event {
+            tauri::RunEvent::Ready | tauri::RunEvent::Resumed => {
+                fcm::recheck_notification_permission(app);
+                app.state::<connectivity::ConnectivityState>().probe_now();
+            }
+            tauri::RunEvent::ExitRequested { api, .. } => {
+                if quit_guard::hold_exit(app) {
+                    api.prevent_exit();
+                    return;
+                }
+                app.state::<drafts::DraftsState>().flush();
+                navigation_state::flush(app);
+                #[cfg(desktop)]
+                window::save_geometry(app);
+                #[cfg(desktop)]
+                feed_windows::save_all(app);
+                // Last: the Windows installer ends the process
+                updates::install_staged(app);
+            }
+            _ => {}
+        });
 }
@@ -0,0 +1,221 @@
+//! Opening links from posts in the default browser.
+//!
+//! `open_external` only accepts `https`, `http` and `mailto` URLs. Links to
+//! Hush's own domains and to hosts on the user's allowlist (persisted to
+//! `link-allowlist.json` in the app config dir) open straight away; a host is
+//! trusted along with its subdomains. Anything else comes back as
+//! `needs-confirmation` with the normalised URL and the Unicode form of the
+//! host, so the frontend can warn about look-alike domains before calling
+//! `open_external_confirmed`.
+
+use crate::storage;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+use tauri::{AppHandle, State, Url};
+use tauri_plugin_opener::OpenerExt;
+
+pub(crate) const ALLOWLIST_FILE: &str = "link-allowlist.json";
+const ALLOWED_SCHEMES: [&str; 3] = ["https", "http", "mailto"];
+/// Always trusted, and not listed in the allowlist
+const BUILT_IN_HOSTS: [&str; 1] = ["hushnetwork.social"];
+
+/// Outcome of `open_external`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "kebab-case")]
+pub enum OpenResult {
+    Opened,
+    NeedsConfirmation {
+        /// Normalised URL to pass to `open_external_confirmed`
+        url: String,
+        /// Host with punycode decoded, for display
+        host: String,
+    },
+}
+
+/// Managed state holding the user's trusted hosts (ASCII, lowercase)
+pub struct LinkAllowlistState {
+    hosts: Mutex<BTreeSet<String>>,
+}
+
+impl LinkAllowlistState {
+    /// Load the persisted allowlist from the app config dir.
+    pub fn load(app: &AppHandle) -> Self {
+        let hosts = storage::config_file(app, ALLOWLIST_FILE)
+            .ok()
+            .and_then(|path| storage::read_json::<BTreeSet<String>>(&path))
+            .unwrap_or_default();
+        Self {
+            hosts: Mutex::new(hosts),
+        }
+    }
+
+    /// Forget every trusted host and delete the persisted allowlist. Returns
+    /// the bytes freed.
+    pub fn clear(&self, app: &AppHandle) -> Result<u64, String> {
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        hosts.clear();
+        storage::remove_all(&storage::config_file(app, ALLOWLIST_FILE)?)
+    }
+
+    /// Apply `change` to the allowlist and persist it if it changed.
+    fn update(&self, app: &AppHandle, change: impl FnOnce(&mut BTreeSet<String>) -> bool) -> bool {
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        let changed = change(&mut hosts);
+        if changed {
+            let result = storage::config_file(app, ALLOWLIST_FILE)
+                .and_then(|path| storage::write_json_atomic(&path, &*hosts));
+            if let Err(e) = result {
+                log::warn!("Failed to save link allowlist: {}", e);
+            }
+        }
+        changed
+    }
+}
+
+/// Parse `url` and check its scheme.
+fn parse(url: &str) -> Result<Url, String> {
+    let parsed = Url::parse(url.trim()).map_err(|e| format!("Invalid link: {}", e))?;
+    if !ALLOWED_SCHEMES.contains(&parsed.scheme()) {
+        return Err(format!(
+            "Links with scheme {} can't be opened",
+            parsed.scheme()
+        ));
+    }
+    Ok(parsed)
+}
+
+/// Whether `host` is `trusted` or one of its subdomains.
+fn host_matches(host: &str, trusted: &str) -> bool {
+    host == trusted
+        || host
+            .strip_suffix(trusted)
+            .is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+fn is_trusted(host: &str, allowlist: &BTreeSet<String>) -> bool {
+    BUILT_IN_HOSTS
+        .iter()
+        .copied()
+        .chain(allowlist.iter().map(String::as_str))
+        .any(|trusted| host_matches(host, trusted))
+}
+
+/// Normalise a host typed or shown in the UI to the stored ASCII form.
+fn normalize_host(host: &str) -> Result<String, String> {
+    idna::domain_to_ascii(host.trim().trim_end_matches('.'))
+        .map_err(|e| format!("Invalid host {}: {}", host, e))
+}
+
+fn display_host(host: &str) -> String {
+    idna::domain_to_unicode(host).0
+}
+
+fn open(app: &AppHandle, url: &Url) -> Result<(), String> {
+    app.opener()
+        .open_url(url.as_str(), None::<&str>)
+        .map_err(|e| e.to_string())
+}
+
+/// Open `url` if it's trusted, otherwise ask the frontend to confirm.
+#[tauri::command]
+pub fn open_external(
+    app: AppHandle,
+    state: State<'_, LinkAllowlistState>,
+    url: String,
+) -> Result<OpenResult, String> {
+    let url = parse(&url)?;
+    // mailto links have no host to check
+    let Some(host) = url.host_str().map(str::to_ascii_lowercase) else {
+        open(&app, &url)?;
+        return Ok(OpenResult::Opened);
+    };
+
+    let trusted = is_trusted(
+        &host,
+        &state.hosts.lock().unwrap_or_else(|e| e.into_inner()),
+    );
+    if trusted {
+        open(&app, &url)?;
+        return Ok(OpenResult::Opened);
+    }
+    Ok(OpenResult::NeedsConfirmation {
+        url: url.to_string(),
+        host: display_host(&host),
+    })
+}
+
+/// Open `url` after the user confirmed, optionally trusting its host from now on.
+#[tauri::command]
+pub fn open_external_confirmed(
+    app: AppHandle,
+    state: State<'_, LinkAllowlistState>,
+    url: String,
+    remember: bool,
+) -> Result<(), String> {
+    let url = parse(&url)?;
+    open(&app, &url)?;
+    if let (true, Some(host)) = (remember, url.host_str()) {
+        let host = host.to_ascii_lowercase();
+        state.update(&app, |hosts| hosts.insert(host));
+    }
+    Ok(())
+}
+
+/// The user's trusted hosts, Unicode-decoded for display.
+#[tauri::command]
+pub fn get_link_allowlist(state: State<'_, LinkAllowlistState>) -> Vec<String> {
+    state
+        .hosts
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .map(|host| display_host(host))
+        .collect()
+}
+
+/// Stop trusting `host`; returns whether it was on the list.
+#[tauri::command]
+pub fn remove_from_link_allowlist(
+    app: AppHandle,
+    state: State<'_, LinkAllowlistState>,
+    host: String,
+) -> Result<bool, String> {
+    let host = normalize_host(&host)?;
+    Ok(state.update(&app, |hosts| hosts.remove(&host)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_web_and_mail_schemes_are_accepted() {
+        assert!(parse("https://example.com/a").is_ok());
+        assert!(parse(" mailto:hello@example.com ").is_ok());
+        assert!(parse("javascript:alert(1)").is_err());
+        assert!(parse("file:///etc/passwd").is_err());
+        assert!(parse("not a url").is_err());
+    }
+
+    #[test]
+    fn hosts_match_themselves_and_subdomains() {
+        let allowlist = BTreeSet::from(["example.com".to_string()]);
+        assert!(is_trusted("example.com", &allowlist));
+        assert!(is_trusted("www.example.com", &allowlist));
+        assert!(is_trusted("chat.hushnetwork.social", &allowlist));
+        assert!(!is_trusted("badexample.com", &allowlist));
+        assert!(!is_trusted("example.com.evil.net", &allowlist));
+    }
+
+    #[test]
+    fn hosts_are_normalised_and_decoded() {
+        assert_eq!(
+            normalize_host("Bücher.Example.").unwrap(),
+            "xn--bcher-kva.example"
+        );
+        assert_eq!(display_host("xn--bcher-kva.example"), "bücher.example");
+        let url = parse("https://XN--BCHER-KVA.example/path").unwrap();
+        assert_eq!(url.host_str(), Some("xn--bcher-kva.example"));
+    }
+}
@@ -0,0 +1,382 @@
+//! WebSocket client for the live event stream, owned by Rust so it keeps
+//! running while the window is hidden in the tray and the reconnect logic
+//! lives in one place.
+//!
+//! `stream_connect` spawns a task holding the connection. Incoming text
+//! messages are forwarded as `stream-message` events (held back while the app
+//! is locked, see [`crate::lock`]); status changes are
+//! emitted as `stream-status`. Dropped connections are retried with jittered
+//! exponential backoff, and the connection is reopened when the proxy or
+//! other network settings change. Around system sleep ([`crate::suspend`])
+//! the connection is closed and reopened right after wake. A keepalive ping
+//! goes out every [`PING_INTERVAL`], less often on battery (see
+//! [`crate::power`]). When the server rejects the credentials (HTTP 401 on
+//! the handshake, or close code 4001/1008) the task stops and emits
+//! `stream-auth-required` so the frontend can reconnect with a fresh token.
+
+use crate::backoff::Backoff;
+use crate::fcm::now_unix_ms;
+use crate::lock::LockState;
+use crate::logging::redact;
+use crate::net::{self, WsConnectError};
+use crate::power;
+use futures_util::{SinkExt, StreamExt};
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager, State, Url};
+use tokio::sync::{mpsc, watch, Notify};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tokio_tungstenite::tungstenite::{Error as WsError, Message};
+
+const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(120);
+/// Keepalive ping interval, stretched by the power throttle
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+/// Application close code the server uses for an expired token
+const CLOSE_AUTH_EXPIRED: u16 = 4001;
+/// Standard "policy violation" close code, also sent for rejected auth
+const CLOSE_POLICY_VIOLATION: u16 = 1008;
+
+/// Connection status, emitted as `stream-status`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum StreamStatus {
+    Connecting,
+    Connected,
+    Disconnected,
+    /// Waiting to reconnect until `until` (Unix ms)
+    Backoff {
+        until: u64,
+    },
+    /// Stopped until `stream_connect` is called with a new token
+    AuthRequired,
+}
+
+/// Why a connection ended
+#[derive(Debug, PartialEq, Eq)]
+enum Ended {
+    /// Closed or failed; reconnect after a backoff
+    Dropped(String),
+    AuthRequired,
+    /// The system is going to sleep or just woke; reconnect without delay
+    /// once awake
+    Sleep,
+}
+
+/// A running connection task and its outgoing message queue
+struct Connection {
+    task: JoinHandle<()>,
+    outgoing: mpsc::UnboundedSender<String>,
+}
+
+/// Managed state for the live stream
+pub struct LiveStreamState {
+    status: Mutex<Option<StreamStatus>>,
+    connection: Mutex<Option<Connection>>,
+    /// Signalled to skip the remaining backoff, e.g. when the network returns
+    wake: Notify,
+    /// Whether the system is asleep; every send drops the current connection
+    asleep: watch::Sender<bool>,
+}
+
+impl Default for LiveStreamState {
+    fn default() -> Self {
+        Self {
+            status: Mutex::new(None),
+            connection: Mutex::new(None),
+            wake: Notify::new(),
+            asleep: watch::Sender::new(false),
+        }
+    }
+}
+
+impl LiveStreamState {
+    pub fn status(&self) -> StreamStatus {
+        self.status
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+            .unwrap_or(StreamStatus::Disconnected)
+    }
+
+    /// Reconnect now if waiting out a backoff.
+    pub fn resume(&self) {
+        self.wake.notify_one();
+    }
+
+    /// Close the connection and hold off reconnecting until
+    /// [`Self::restart_after_sleep`].
+    pub fn pause_for_sleep(&self) {
+        self.asleep.send_replace(true);
+    }
+
+    /// Reconnect now with a fresh backoff, dropping any connection that may
+    /// have died during sleep.
+    pub fn restart_after_sleep(&self) {
+        self.asleep.send_modify(|asleep| *asleep = false);
+    }
+
+    /// Stop the running connection, if any.
+    fn stop(&self) {
+        if let Some(connection) = self
+            .connection
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+        {
+            connection.task.abort();
+        }
+    }
+}
+
+fn set_status(app: &AppHandle, status: StreamStatus) {
+    let state = app.state::<LiveStreamState>();
+    let mut current = state.status.lock().unwrap_or_else(|e| e.into_inner());
+    if current.as_ref() != Some(&status) {
+        *current = Some(status.clone());
+        drop(current);
+        let _ = app.emit("stream-status", status);
+    }
+}
+
+fn is_auth_close(frame: Option<&CloseFrame>) -> bool {
+    frame.is_some_and(|frame| {
+        matches!(
+            u16::from(frame.code),
+            CLOSE_AUTH_EXPIRED | CLOSE_POLICY_VIOLATION
+        )
+    })
+}
+
+/// Hold one connection until it closes.
+async fn connect_once(
+    app: &AppHandle,
+    url: &str,
+    auth_token: &str,
+    outgoing: &mut mpsc::UnboundedReceiver<String>,
+    asleep: &mut watch::Receiver<bool>,
+    backoff: &mut Backoff,
+) -> Ended {
+    let mut request = match url.into_client_request() {
+        Ok(request) => request,
+        Err(e) => return Ended::Dropped(e.to_string()),
+    };
+    match format!("Bearer {}", auth_token).parse() {
+        Ok(value) => {
+            request.headers_mut().insert("Authorization", value);
+        }
+        Err(_) => return Ended::AuthRequired,
+    }
+
+    let mut socket = match net::connect_websocket(app, request).await {
+        Ok(socket) => socket,
+        Err(WsConnectError::Handshake(WsError::Http(response)))
+            if response.status().as_u16() == 401 =>
+        {
+            return Ended::AuthRequired
+        }
+        Err(e) => return Ended::Dropped(e.to_string()),
+    };
+    set_status(app, StreamStatus::Connected);
+    backoff.reset();
+    log::info!("Live stream connected");
+
+    let ping_interval = || PING_INTERVAL * power::throttle(app).factor();
+    let mut next_ping = tokio::time::Instant::now() + ping_interval();
+    let mut network = net::subscribe(app);
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep_until(next_ping) => {
+                if let Err(e) = socket.send(Message::Ping(Vec::new())).await {
+                    return Ended::Dropped(e.to_string());
+                }
+                next_ping = tokio::time::Instant::now() + ping_interval();
+            }
+            _ = network.changed() => {
+                let _ = socket.close(None).await;
+                backoff.reset();
+                return Ended::Dropped("network settings changed".to_string());
+            }
+            _ = asleep.changed() => {
+                let _ = socket.close(None).await;
+                return Ended::Sleep;
+            }
+            message = socket.next() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    // Held back while the app is locked; see crate::lock
+                    let text = match app.try_state::<LockState>() {
+                        Some(lock) => lock.hold_while_locked(text),
+                        None => Some(text),
+                    };
+                    if let Some(text) = text {
+                        crate::feed_windows::emit_stream_message(&app, text);
+                    }
+                }
+                Some(Ok(Message::Close(frame))) => {
+                    if is_auth_close(frame.as_ref()) {
+                        return Ended::AuthRequired;
+                    }
+                    return Ended::Dropped("closed by server".to_string());
+                }
+                // Pings are answered by tungstenite; binary frames aren't used
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Ended::Dropped(e.to_string()),
+                None => return Ended::Dropped("connection closed".to_string()),
+            },
+            payload = outgoing.recv() => {
+                let Some(payload) = payload else {
+                    let _ = socket.close(None).await;
+                    return Ended::Dropped("sender dropped".to_string());
+                };
+                if let Err(e) = socket.send(Message::Text(payload)).await {
+                    return Ended::Dropped(e.to_string());
+                }
+            }
+        }
+    }
+}
+
+async fn run(
+    app: AppHandle,
+    url: String,
+    auth_token: String,
+    mut outgoing: mpsc::UnboundedReceiver<String>,
+) {
+    let mut backoff = Backoff::new(INITIAL_RETRY_DELAY, MAX_RETRY_DELAY);
+    let mut asleep = app.state::<LiveStreamState>().asleep.subscribe();
+    loop {
+        if *asleep.borrow_and_update() {
+            set_status(&app, StreamStatus::Disconnected);
+            let _ = asleep.wait_for(|asleep| !*asleep).await;
+            backoff.reset();
+        }
+
+        set_status(&app, StreamStatus::Connecting);
+        let ended = connect_once(
+            &app,
+            &url,
+            &auth_token,
+            &mut outgoing,
+            &mut asleep,
+            &mut backoff,
+        )
+        .await;
+
+        // Messages queued while disconnected would go to a stale session
+        while outgoing.try_recv().is_ok() {}
+
+        match ended {
+            Ended::AuthRequired => {
+                log::info!("Live stream needs new credentials");
+                set_status(&app, StreamStatus::AuthRequired);
+                let _ = app.emit("stream-auth-required", ());
+                return;
+            }
+            Ended::Sleep => {
+                log::info!("Live stream closed for system sleep");
+                backoff.reset();
+                continue;
+            }
+            Ended::Dropped(reason) => log::warn!("Live stream dropped: {}", redact(&reason)),
+        }
+
+        let delay = backoff.next_jittered_delay();
+        set_status(
+            &app,
+            StreamStatus::Backoff {
+                until: now_unix_ms() + delay.as_millis() as u64,
+            },
+        );
+        let state = app.state::<LiveStreamState>();
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = state.wake.notified() => backoff.reset(),
+            _ = asleep.changed() => backoff.reset(),
+        }
+    }
+}
+
+/// Connect to the live event stream, replacing any existing connection.
+#[tauri::command]
+pub fn stream_connect(
+    app: AppHandle,
+    state: State<'_, LiveStreamState>,
+    url: String,
+    auth_token: String,
+) -> Result<(), String> {
+    let parsed = Url::parse(&url).map_err(|e| format!("Invalid stream URL: {}", e))?;
+    if !matches!(parsed.scheme(), "ws" | "wss") {
+        return Err("Stream URL must use ws or wss".to_string());
+    }
+
+    state.stop();
+    let (outgoing, receiver) = mpsc::unbounded_channel();
+    let task = tauri::async_runtime::spawn(run(app.clone(), url, auth_token, receiver));
+    *state.connection.lock().unwrap_or_else(|e| e.into_inner()) =
+        Some(Connection { task, outgoing });
+    Ok(())
+}
+
+/// Close the live stream and stop reconnecting.
+#[tauri::command]
+pub fn stream_disconnect(app: AppHandle, state: State<'_, LiveStreamState>) {
+    state.stop();
+    set_status(&app, StreamStatus::Disconnected);
+}
+
+/// Send a text message over the live stream.
+#[tauri::command]
+pub fn stream_send(state: State<'_, LiveStreamState>, payload: String) -> Result<(), String> {
+    if state.status() != StreamStatus::Connected {
+        return Err("Live stream is not connected".to_string());
+    }
+    let connection = state.connection.lock().unwrap_or_else(|e| e.into_inner());
+    connection
+        .as_ref()
+        .ok_or_else(|| "Live stream is not connected".to_string())?
+        .outgoing
+        .send(payload)
+        .map_err(|_| "Live stream is not connected".to_string())
+}
+
+#[tauri::command]
+pub fn get_stream_status(state: State<'_, LiveStreamState>) -> StreamStatus {
+    state.status()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+
+    fn frame(code: u16) -> CloseFrame<'static> {
+        CloseFrame {
+            code: CloseCode::from(code),
+            reason: "".into(),
+        }
+    }
+
+    #[test]
+    fn auth_close_codes_are_recognised() {
+        assert!(is_auth_close(Some(&frame(4001))));
+        assert!(is_auth_close(Some(&frame(1008))));
+        assert!(!is_auth_close(Some(&frame(1000))));
+        assert!(!is_auth_close(Some(&frame(1006))));
+        assert!(!is_auth_close(None));
+    }
+
+    #[test]
+    fn status_serializes_with_state_tag() {
+        assert_eq!(
+            serde_json::to_value(StreamStatus::AuthRequired).unwrap(),
+            serde_json::json!({"state": "auth_required"})
+        );
+        assert_eq!(
+            serde_json::to_value(StreamStatus::Backoff { until: 5 }).unwrap(),
+            serde_json::json!({"state": "backoff", "until": 5})
+        );
+    }
+}
@@ -0,0 +1,140 @@
+//! The OS locale and timezone, since the webview's `Intl` data doesn't
+//! always match them.
+//!
+//! A task started in `setup` checks the timezone name and UTC offset every
+//! [`POLL_INTERVAL`] and emits `timezone-changed` with the new
+//! [`LocaleInfo`] when either changes (DST switch, travel), so relative
+//! timestamps can be re-rendered.
+
+use serde::Serialize;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Weekday {
+    Saturday,
+    Sunday,
+    Monday,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocaleInfo {
+    /// BCP-47 tags in order of preference
+    pub locales: Vec<String>,
+    /// IANA name, e.g. `Europe/Lisbon`
+    pub timezone: Option<String>,
+    /// Minutes east of UTC
+    pub utc_offset_minutes: i32,
+    pub first_day_of_week: Weekday,
+}
+
+/// Normalise an OS locale such as `en_US.UTF-8` to a BCP-47 tag.
+fn to_bcp47(locale: &str) -> Option<String> {
+    let tag = locale
+        .split(['.', '@'])
+        .next()
+        .unwrap_or_default()
+        .replace('_', "-");
+    (!tag.is_empty() && tag != "C" && tag != "POSIX").then_some(tag)
+}
+
+/// The region subtag of a BCP-47 tag, e.g. `CN` in `zh-Hans-CN`.
+fn region(tag: &str) -> Option<String> {
+    tag.split('-')
+        .skip(1)
+        .find(|part| part.len() == 2 && part.chars().all(|c| c.is_ascii_alphabetic()))
+        .map(|part| part.to_ascii_uppercase())
+}
+
+/// First day of the week for a locale's region, after CLDR.
+fn first_day_of_week(tag: &str) -> Weekday {
+    const SUNDAY: &[&str] = &[
+        "AG", "AS", "BD", "BR", "BS", "BT", "BW", "BZ", "CA", "CN", "CO", "DM", "DO", "ET", "GT",
+        "GU", "HK", "HN", "ID", "IL", "IN", "JM", "JP", "KE", "KH", "KR", "LA", "MH", "MM", "MO",
+        "MT", "MX", "MZ", "NI", "NP", "PA", "PE", "PH", "PK", "PR", "PT", "PY", "SA", "SG", "SV",
+        "TH", "TT", "TW", "UM", "US", "VE", "VI", "WS", "YE", "ZA", "ZW",
+    ];
+    const SATURDAY: &[&str] = &[
+        "AE", "AF", "BH", "DJ", "DZ", "EG", "IQ", "IR", "JO", "KW", "LY", "OM", "QA", "SD", "SY",
+    ];
+    match region(tag) {
+        Some(region) if SUNDAY.contains(&region.as_str()) => Weekday::Sunday,
+        Some(region) if SATURDAY.contains(&region.as_str()) => Weekday::Saturday,
+        _ => Weekday::Monday,
+    }
+}
+
+fn collect() -> LocaleInfo {
+    let mut locales: Vec<String> = Vec::new();
+    for tag in sys_locale::get_locales().filter_map(|locale| to_bcp47(&locale)) {
+        if !locales.contains(&tag) {
+            locales.push(tag);
+        }
+    }
+    if locales.is_empty() {
+        locales.push("en-US".to_string());
+    }
+    LocaleInfo {
+        first_day_of_week: first_day_of_week(&locales[0]),
+        locales,
+        timezone: iana_time_zone::get_timezone()
+            .map_err(|e| log::debug!("Timezone name unavailable: {}", e))
+            .ok(),
+        utc_offset_minutes: chrono::Local::now().offset().local_minus_utc() / 60,
+    }
+}
+
+async fn watch_timezone(app: AppHandle) {
+    let mut current = collect();
+    loop {
+        tokio::time::sleep(POLL_INTERVAL).await;
+        let latest = collect();
+        if latest.timezone != current.timezone
+            || latest.utc_offset_minutes != current.utc_offset_minutes
+        {
+            log::info!(
+                "Timezone changed to {:?} (UTC{:+} min)",
+                latest.timezone,
+                latest.utc_offset_minutes
+            );
+            let _ = app.emit("timezone-changed", &latest);
+        }
+        current = latest;
+    }
+}
+
+/// Start the timezone watcher. Called from `setup`.
+pub fn init(app: &AppHandle) {
+    tauri::async_runtime::spawn(watch_timezone(app.clone()));
+}
+
+#[tauri::command]
+pub fn get_locale_info() -> LocaleInfo {
+    collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn os_locales_become_bcp47() {
+        assert_eq!(to_bcp47("en_US.UTF-8").as_deref(), Some("en-US"));
+        assert_eq!(to_bcp47("sr_RS@latin").as_deref(), Some("sr-RS"));
+        assert_eq!(to_bcp47("pt-BR").as_deref(), Some("pt-BR"));
+        assert_eq!(to_bcp47("C"), None);
+    }
+
+    #[test]
+    fn first_day_of_week_follows_region() {
+        assert_eq!(first_day_of_week("en-US"), Weekday::Sunday);
+        assert_eq!(first_day_of_week("zh-Hans-CN"), Weekday::Sunday);
+        assert_eq!(first_day_of_week("ar-EG"), Weekday::Saturday);
+        assert_eq!(first_day_of_week("de-DE"), Weekday::Monday);
+        assert_eq!(first_day_of_week("fr"), Weekday::Monday);
+    }
+}
@@ -0,0 +1,613 @@
+//! App lock: a passphrase (or PIN) the user must enter before the frontend can
+//! read private data again.
+//!
+//! The passphrase is hashed with Argon2id and kept in the secure store under
+//! [`HASH_KEY`]. Whether the lock is on, the auto-lock timeout and the failed
+//! attempt count live in `app-lock.json` rather than in the settings, so
+//! `set_setting` can't switch the lock off. The app starts locked whenever the
+//! lock is on, and the lock counts as on whenever [`HASH_KEY`] is in the
+//! secure store, so deleting or editing `app-lock.json` can't turn it off.
+//!
+//! The auto-lock timer runs here, not in the webview, so a crashed or reloaded
+//! webview can't skip it. A task checks every [`CHECK_INTERVAL`] and locks once
+//! the system has been idle (see [`crate::idle`]) for `autoLockMinutes`, or,
+//! where idle time can't be read, once the main window has been unfocused that
+//! long. Going to sleep locks as well. Each of these emits `app-locked`.
+//!
+//! While locked, `secure_get`, the cache reads, drafts, notification history
+//! and pending notification actions fail with [`crate::error::APP_LOCKED`]
+//! and live stream messages are held back until `unlock`. After
+//! [`FREE_ATTEMPTS`] wrong passphrases every further attempt has to wait
+//! twice as long as the one before, up to [`MAX_RETRY_DELAY`]; the count is
+//! persisted so restarting the app doesn't reset it.
+//!
+//! Turning the lock on also stores a random unlock secret under
+//! [`SECRET_KEY`]. [`crate::biometrics`] reads it back after the OS has
+//! verified the user and unlocks through the same path as a passphrase.
+
+use crate::error::{self, CommandError};
+use crate::fcm::now_unix_ms;
+use crate::secure_store::SecureStoreState;
+use crate::storage;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+const CONFIG_FILE: &str = "app-lock.json";
+/// Secure store key holding the passphrase hash
+pub(crate) const HASH_KEY: &str = "__hush_app_lock__";
+/// Secure store key holding the unlock secret released by biometrics
+pub(crate) const SECRET_KEY: &str = "__hush_app_lock_secret__";
+const MIN_PASSPHRASE_CHARS: usize = 4;
+const CHECK_INTERVAL: Duration = Duration::from_secs(15);
+/// Wrong passphrases allowed before attempts are delayed
+const FREE_ATTEMPTS: u32 = 3;
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(15 * 60);
+/// Stream messages kept while locked; older ones are dropped
+const MAX_HELD_MESSAGES: usize = 500;
+
+/// Contents of [`CONFIG_FILE`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct LockConfig {
+    enabled: bool,
+    /// 0 disables the auto-lock timer
+    auto_lock_minutes: u32,
+    failed_attempts: u32,
+    /// No unlock attempts before this time (Unix ms)
+    retry_after: Option<u64>,
+    /// SHA-256 (base64) of the unlock secret in [`SECRET_KEY`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unlock_secret_hash: Option<String>,
+}
+
+/// What locked the app, in the `app-locked` event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LockReason {
+    Manual,
+    Idle,
+    Suspend,
+}
+
+/// Payload of the `app-locked` event
+#[derive(Debug, Clone, Serialize)]
+pub struct AppLockedPayload {
+    pub reason: LockReason,
+}
+
+/// Result of `get_lock_state` and the other lock commands
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LockStatus {
+    pub enabled: bool,
+    pub locked: bool,
+    pub auto_lock_minutes: u32,
+    pub failed_attempts: u32,
+    /// Unix ms before which `unlock` is refused
+    pub retry_after: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    config: LockConfig,
+    locked: bool,
+    /// When the main window last lost focus, while it still hasn't got it back
+    unfocused_since: Option<Instant>,
+    held_messages: VecDeque<String>,
+}
+
+impl Inner {
+    fn status(&self) -> LockStatus {
+        LockStatus {
+            enabled: self.config.enabled,
+            locked: self.locked,
+            auto_lock_minutes: self.config.auto_lock_minutes,
+            failed_attempts: self.config.failed_attempts,
+            retry_after: self.config.retry_after,
+        }
+    }
+}
+
+/// Managed state of the app lock
+#[derive(Debug, Default)]
+pub struct LockState {
+    inner: Mutex<Inner>,
+}
+
+/// How long to wait after `failed_attempts` wrong passphrases.
+fn retry_delay(failed_attempts: u32) -> Duration {
+    if failed_attempts < FREE_ATTEMPTS {
+        return Duration::ZERO;
+    }
+    let doublings = (failed_attempts - FREE_ATTEMPTS).min(16);
+    Duration::from_secs(1 << doublings).min(MAX_RETRY_DELAY)
+}
+
+fn hash_passphrase(passphrase: &str) -> Result<String, String> {
+    let salt =
+        SaltString::encode_b64(uuid::Uuid::new_v4().as_bytes()).map_err(|e| e.to_string())?;
+    Argon2::default()
+        .hash_password(passphrase.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| e.to_string())
+}
+
+fn verify_passphrase(passphrase: &str, hash: &str) -> bool {
+    PasswordHash::new(hash).is_ok_and(|hash| {
+        Argon2::default()
+            .verify_password(passphrase.as_bytes(), &hash)
+            .is_ok()
+    })
+}
+
+fn secret_hash(secret: &str) -> String {
+    use base64::Engine;
+    use sha2::{Digest, Sha256};
+    base64::engine::general_purpose::STANDARD.encode(Sha256::digest(secret.as_bytes()))
+}
+
+/// Store a fresh random unlock secret and return its hash.
+fn issue_unlock_secret(store: &SecureStoreState) -> Result<String, CommandError> {
+    let secret = format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    );
+    store.set(SECRET_KEY, &secret)?;
+    Ok(secret_hash(&secret))
+}
+
+/// The config to start with, given whether a passphrase hash is stored. A
+/// hash without an enabled config means `app-lock.json` was removed or
+/// edited, so the lock stays on and the next attempt is delayed as if the
+/// free attempts were used up.
+fn reconcile(mut config: LockConfig, hash_stored: bool) -> LockConfig {
+    if hash_stored && !config.enabled {
+        log::warn!("App lock config doesn't match the stored passphrase, keeping the lock on");
+        config.enabled = true;
+        config.failed_attempts = config.failed_attempts.max(FREE_ATTEMPTS);
+    }
+    config
+}
+
+fn save(app: &AppHandle, config: &LockConfig) -> Result<(), String> {
+    storage::write_json_atomic(&storage::config_file(app, CONFIG_FILE)?, config)
+}
+
+impl LockState {
+    /// Load the lock config; the app starts locked if the lock is on. Call
+    /// after [`SecureStoreState`] is managed.
+    pub fn load(app: &AppHandle) -> Self {
+        let config = storage::config_file(app, CONFIG_FILE)
+            .ok()
+            .and_then(|path| storage::read_json::<LockConfig>(&path))
+            .unwrap_or_default();
+        let hash_stored = app
+            .try_state::<SecureStoreState>()
+            .is_some_and(|store| matches!(store.get(HASH_KEY), Ok(Some(_))));
+        Self::with_config(reconcile(config, hash_stored))
+    }
+
+    fn with_config(config: LockConfig) -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                locked: config.enabled,
+                config,
+                ..Inner::default()
+            }),
+        }
+    }
+
+    fn inner(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.inner().locked
+    }
+
+    /// Fail with [`error::APP_LOCKED`] while locked.
+    pub fn ensure_unlocked(&self) -> Result<(), CommandError> {
+        if self.is_locked() {
+            return Err(CommandError::new(error::APP_LOCKED, "The app is locked"));
+        }
+        Ok(())
+    }
+
+    /// Hold `message` back while locked; returns it if it can be delivered now.
+    pub fn hold_while_locked(&self, message: String) -> Option<String> {
+        let mut inner = self.inner();
+        if !inner.locked {
+            return Some(message);
+        }
+        if inner.held_messages.len() >= MAX_HELD_MESSAGES {
+            inner.held_messages.pop_front();
+        }
+        inner.held_messages.push_back(message);
+        None
+    }
+
+    pub fn status(&self) -> LockStatus {
+        self.inner().status()
+    }
+
+    /// Track main window focus for the auto-lock fallback.
+    pub fn on_focus_changed(&self, focused: bool) {
+        let mut inner = self.inner();
+        if focused {
+            inner.unfocused_since = None;
+        } else if inner.unfocused_since.is_none() {
+            inner.unfocused_since = Some(Instant::now());
+        }
+    }
+
+    /// Lock if the lock is on; returns whether this call locked the app.
+    fn lock(&self) -> bool {
+        let mut inner = self.inner();
+        if !inner.config.enabled || inner.locked {
+            return false;
+        }
+        inner.locked = true;
+        true
+    }
+}
+
+/// Lock the app and emit `app-locked`, if the lock is on and not yet locked.
+pub fn lock(app: &AppHandle, reason: LockReason) {
+    let Some(state) = app.try_state::<LockState>() else {
+        return;
+    };
+    if state.lock() {
+        log::info!("App locked ({:?})", reason);
+        let _ = app.emit("app-locked", AppLockedPayload { reason });
+    }
+}
+
+/// Lock when the machine goes to sleep. Called from [`crate::suspend`].
+pub fn on_suspend(app: &AppHandle) {
+    let auto_lock = app
+        .try_state::<LockState>()
+        .is_some_and(|state| state.status().auto_lock_minutes > 0);
+    if auto_lock {
+        lock(app, LockReason::Suspend);
+    }
+}
+
+/// Whether the auto-lock timer should lock the app now.
+fn should_auto_lock(status: &LockStatus, idle: Duration) -> bool {
+    status.enabled
+        && !status.locked
+        && status.auto_lock_minutes > 0
+        && idle >= Duration::from_secs(u64::from(status.auto_lock_minutes) * 60)
+}
+
+async fn watch_idle(app: AppHandle) {
+    loop {
+        tokio::time::sleep(CHECK_INTERVAL).await;
+        let state = app.state::<LockState>();
+        let status = state.status();
+        if !status.enabled || status.locked || status.auto_lock_minutes == 0 {
+            continue;
+        }
+        let idle = match crate::idle::read().await {
+            Ok(seconds) => Duration::from_secs(seconds),
+            Err(_) => state
+                .inner()
+                .unfocused_since
+                .map_or(Duration::ZERO, |since| since.elapsed()),
+        };
+        if should_auto_lock(&status, idle) {
+            lock(&app, LockReason::Idle);
+        }
+    }
+}
+
+/// Start the auto-lock timer. Called from `setup` after [`LockState`] is managed.
+pub fn init(app: &AppHandle) {
+    tauri::async_runtime::spawn(watch_idle(app.clone()));
+}
+
+/// Turn the app lock off and delete its secrets and config, e.g. when all app
+/// data is cleared.
+pub(crate) fn remove(app: &AppHandle) -> Result<(), CommandError> {
+    let store = app.state::<SecureStoreState>();
+    store.delete(HASH_KEY)?;
+    store.delete(SECRET_KEY)?;
+    storage::remove_file(&storage::config_file(app, CONFIG_FILE)?)?;
+    let state = app.state::<LockState>();
+    let mut inner = state.inner();
+    inner.config = LockConfig::default();
+    inner.locked = false;
+    Ok(())
+}
+
+/// Turn the app lock on with `passphrase`, or off when it's None, and set the
+/// auto-lock timeout (0 for none). Only works while unlocked.
+#[tauri::command]
+pub async fn set_app_lock(
+    app: AppHandle,
+    passphrase: Option<String>,
+    auto_lock_minutes: u32,
+) -> Result<LockStatus, CommandError> {
+    let state = app.state::<LockState>();
+    state.ensure_unlocked()?;
+    let store = app.state::<SecureStoreState>();
+
+    let Some(passphrase) = passphrase else {
+        store.delete(HASH_KEY)?;
+        store.delete(SECRET_KEY)?;
+        save(&app, &LockConfig::default())?;
+        state.inner().config = LockConfig::default();
+        return Ok(state.status());
+    };
+
+    if passphrase.chars().count() < MIN_PASSPHRASE_CHARS {
+        return Err(CommandError::invalid_argument(format!(
+            "The passphrase needs at least {} characters",
+            MIN_PASSPHRASE_CHARS
+        )));
+    }
+    if !store.status().persistent {
+        return Err(CommandError::new(
+            error::NOT_SUPPORTED,
+            "The app lock needs a credential store that survives restarts",
+        ));
+    }
+    let hash = tauri::async_runtime::spawn_blocking(move || hash_passphrase(&passphrase))
+        .await
+        .map_err(|e| e.to_string())??;
+    store.set(HASH_KEY, &hash)?;
+
+    let config = LockConfig {
+        enabled: true,
+        auto_lock_minutes,
+        unlock_secret_hash: Some(issue_unlock_secret(&store)?),
+        ..LockConfig::default()
+    };
+    save(&app, &config)?;
+    state.inner().config = config;
+    Ok(state.status())
+}
+
+/// Why `unlock` can't check a passphrase when the hash can't be read. Without
+/// a persistent credential store (e.g. the Secret Service isn't up yet) the
+/// entry may be back after a restart.
+fn missing_hash_error(persistent: bool) -> CommandError {
+    if persistent {
+        CommandError::new(
+            error::UNAVAILABLE,
+            "The app lock passphrase is missing from the credential store",
+        )
+    } else {
+        CommandError::new(
+            error::SECURE_STORE_LOCKED,
+            "The credential store holding the app lock passphrase isn't available",
+        )
+    }
+}
+
+/// Unlock with `passphrase`. Wrong passphrases fail with `permission_denied`;
+/// attempts before `retryAfter` fail with `rate_limited`. The app stays
+/// locked when the passphrase hash can't be read.
+#[tauri::command]
+pub async fn unlock(app: AppHandle, passphrase: String) -> Result<LockStatus, CommandError> {
+    let state = app.state::<LockState>();
+    let status = state.status();
+    if !status.locked {
+        return Ok(status);
+    }
+
+    let store = app.state::<SecureStoreState>();
+    let Some(hash) = store.get(HASH_KEY)? else {
+        // Stay locked: only `set_app_lock` or clearing the app data while
+        // unlocked may turn the lock off
+        log::warn!("App lock passphrase unreadable, staying locked");
+        return Err(missing_hash_error(store.status().persistent));
+    };
+
+    // Check the delay and count the attempt under one guard, before the
+    // passphrase is verified, so parallel calls can't skip the delay
+    let config = {
+        let mut inner = state.inner();
+        if !inner.locked {
+            return Ok(inner.status());
+        }
+        if inner
+            .config
+            .retry_after
+            .is_some_and(|after| after > now_unix_ms())
+        {
+            return Err(CommandError::new(
+                error::RATE_LIMITED,
+                "Too many wrong attempts; try again later",
+            ));
+        }
+        inner.config.failed_attempts += 1;
+        let delay = retry_delay(inner.config.failed_attempts);
+        inner.config.retry_after =
+            (!delay.is_zero()).then(|| now_unix_ms() + delay.as_millis() as u64);
+        inner.config.clone()
+    };
+    save(&app, &config)?;
+
+    let matches =
+        tauri::async_runtime::spawn_blocking(move || verify_passphrase(&passphrase, &hash))
+            .await
+            .map_err(|e| e.to_string())?;
+    if !matches {
+        return Err(CommandError::new(
+            error::PERMISSION_DENIED,
+            "Wrong passphrase",
+        ));
+    }
+
+    // Locks set up before biometric unlock existed get their secret now
+    if config.unlock_secret_hash.is_none() {
+        match issue_unlock_secret(&app.state::<SecureStoreState>()) {
+            Ok(hash) => state.inner().config.unlock_secret_hash = Some(hash),
+            Err(e) => log::warn!("Failed to store the unlock secret: {}", e),
+        }
+    }
+    finish_unlock(&app, &state)
+}
+
+/// Unlock with the secret kept in the secure store, once the OS has verified
+/// the user (see [`crate::biometrics`]).
+pub(crate) fn unlock_with_secret(app: &AppHandle) -> Result<LockStatus, CommandError> {
+    let state = app.state::<LockState>();
+    let Some(expected) = state.inner().config.unlock_secret_hash.clone() else {
+        return Err(CommandError::new(
+            error::NOT_FOUND,
+            "Biometric unlock isn't set up; unlock with the passphrase once",
+        ));
+    };
+    let secret = app.state::<SecureStoreState>().get(SECRET_KEY)?;
+    if secret.as_deref().map(secret_hash).as_deref() != Some(expected.as_str()) {
+        return Err(CommandError::new(
+            error::PERMISSION_DENIED,
+            "The unlock secret doesn't match; unlock with the passphrase",
+        ));
+    }
+    finish_unlock(app, &state)
+}
+
+/// Clear the lock and failed attempts, then deliver what was held back.
+fn finish_unlock(app: &AppHandle, state: &LockState) -> Result<LockStatus, CommandError> {
+    let (config, held) = {
+        let mut inner = state.inner();
+        inner.config.failed_attempts = 0;
+        inner.config.retry_after = None;
+        inner.locked = false;
+        inner.unfocused_since = None;
+        (
+            inner.config.clone(),
+            std::mem::take(&mut inner.held_messages),
+        )
+    };
+    save(app, &config)?;
+    let _ = app.emit("app-unlocked", ());
+    for message in held {
+        crate::feed_windows::emit_stream_message(app, message);
+    }
+    Ok(state.status())
+}
+
+/// Lock right away. Fails if no passphrase is set.
+#[tauri::command]
+pub fn lock_now(app: AppHandle) -> Result<LockStatus, CommandError> {
+    let state = app.state::<LockState>();
+    if !state.status().enabled {
+        return Err(CommandError::new(error::NOT_FOUND, "No app lock is set up"));
+    }
+    lock(&app, LockReason::Manual);
+    Ok(state.status())
+}
+
+#[tauri::command]
+pub fn get_lock_state(state: State<'_, LockState>) -> LockStatus {
+    state.status()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enabled(auto_lock_minutes: u32) -> LockState {
+        LockState::with_config(LockConfig {
+            enabled: true,
+            auto_lock_minutes,
+            ..LockConfig::default()
+        })
+    }
+
+    #[test]
+    fn retry_delay_doubles_after_the_free_attempts() {
+        assert_eq!(retry_delay(FREE_ATTEMPTS - 1), Duration::ZERO);
+        assert_eq!(retry_delay(FREE_ATTEMPTS), Duration::from_secs(1));
+        assert_eq!(retry_delay(FREE_ATTEMPTS + 3), Duration::from_secs(8));
+        assert_eq!(retry_delay(u32::MAX), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn passphrases_verify_against_their_hash() {
+        let hash = hash_passphrase("1234").unwrap();
+        assert!(hash.starts_with("$argon2id$"));
+        assert!(verify_passphrase("1234", &hash));
+        assert!(!verify_passphrase("4321", &hash));
+        assert!(!verify_passphrase("1234", "not a hash"));
+    }
+
+    #[test]
+    fn unlock_secret_hash_matches_only_its_secret() {
+        assert_eq!(secret_hash("abc"), secret_hash("abc"));
+        assert_ne!(secret_hash("abc"), secret_hash("abd"));
+        let json = serde_json::to_value(LockConfig::default()).unwrap();
+        assert!(json.get("unlockSecretHash").is_none());
+    }
+
+    #[test]
+    fn enabled_lock_starts_locked_and_refuses_reads() {
+        let state = enabled(5);
+        assert!(state.is_locked());
+        assert_eq!(state.ensure_unlocked().unwrap_err().code, error::APP_LOCKED);
+        assert!(LockState::default().ensure_unlocked().is_ok());
+        // Locking again reports no change
+        assert!(!state.lock());
+    }
+
+    #[test]
+    fn unreadable_hash_keeps_the_lock_with_a_retryable_error() {
+        let error = missing_hash_error(false);
+        assert_eq!(error.code, error::SECURE_STORE_LOCKED);
+        assert!(error.retryable);
+        assert_eq!(missing_hash_error(true).code, error::UNAVAILABLE);
+    }
+
+    #[test]
+    fn stored_hash_keeps_the_lock_on_without_its_config() {
+        let config = reconcile(LockConfig::default(), true);
+        assert!(config.enabled);
+        assert_eq!(config.failed_attempts, FREE_ATTEMPTS);
+        assert!(LockState::with_config(config).is_locked());
+
+        let kept = LockConfig {
+            enabled: true,
+            failed_attempts: 1,
+            ..LockConfig::default()
+        };
+        assert_eq!(reconcile(kept.clone(), true), kept);
+        assert_eq!(
+            reconcile(LockConfig::default(), false),
+            LockConfig::default()
+        );
+    }
+
+    #[test]
+    fn stream_messages_are_held_while_locked() {
+        let state = enabled(5);
+        for i in 0..MAX_HELD_MESSAGES + 2 {
+            assert_eq!(state.hold_while_locked(i.to_string()), None);
+        }
+        let inner = state.inner();
+        assert_eq!(inner.held_messages.len(), MAX_HELD_MESSAGES);
+        assert_eq!(inner.held_messages.front().map(String::as_str), Some("2"));
+
+        let unlocked = LockState::default();
+        assert_eq!(unlocked.hold_while_locked("m".into()).as_deref(), Some("m"));
+    }
+
+    #[test]
+    fn auto_lock_waits_for_the_timeout() {
+        let mut status = enabled(5).status();
+        status.locked = false;
+        assert!(!should_auto_lock(&status, Duration::from_secs(299)));
+        assert!(should_auto_lock(&status, Duration::from_secs(300)));
+        status.auto_lock_minutes = 0;
+        assert!(!should_auto_lock(&status, Duration::from_secs(3600)));
+    }
+}
@@ -0,0 +1,660 @@
+//! Logging to rotating files in the app log dir, in every build.
+//!
+//! The log plugin writes to stdout and to `<app log dir>/<product>.log`,
+//! rotating at [`MAX_FILE_BYTES`] and keeping the last [`KEEP_FILES`] files.
+//! Release builds log warnings and errors only by default, debug builds from
+//! info up. `set_log_level` changes the level at runtime, globally or for one
+//! module (`hush_web_client::fcm` and `fcm` both name the push module), and
+//! persists it in the `logLevel` and `logModuleLevels` settings so it applies
+//! from the next launch; each change emits `log-level-changed`.
+//!
+//! Anything that came off the network (error strings carrying URLs, response
+//! snippets, server messages) goes through [`redact`] before it is logged, so
+//! tokens and post contents don't end up in files users send to support.
+//!
+//! `log_from_frontend` writes webview log entries to the same files under the
+//! `webview` target, redacted, truncated past [`MAX_FRONTEND_MESSAGE_BYTES`],
+//! and limited to [`FRONTEND_RATE_LIMIT`] entries a minute; entries over the
+//! limit are dropped and counted in a warning once the minute is up.
+
+use crate::settings::{self, SettingsState};
+use crate::storage;
+use log::LevelFilter;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tauri::plugin::TauriPlugin;
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+use tauri_plugin_log::{RotationStrategy, Target, TargetKind};
+use tauri_plugin_opener::OpenerExt;
+
+/// Size at which the log file is rotated (5 MB)
+const MAX_FILE_BYTES: u128 = 5 * 1024 * 1024;
+const KEEP_FILES: usize = 5;
+const REDACTED: &str = "[redacted]";
+/// Keys whose values are never logged, matched case-insensitively in
+/// `key=value` and `"key": "value"` form
+const SENSITIVE_KEYS: &[&str] = &[
+    "access_token",
+    "refresh_token",
+    "id_token",
+    "token",
+    "authorization",
+    "password",
+    "secret",
+    "api_key",
+    "apikey",
+    "signature",
+    "content",
+    "body",
+    "text",
+];
+/// Opaque strings at least this long (tokens, keys) are redacted wholesale
+const OPAQUE_MIN_LEN: usize = 32;
+
+/// Webview entries accepted per [`FRONTEND_RATE_WINDOW`]
+const FRONTEND_RATE_LIMIT: u32 = 100;
+const FRONTEND_RATE_WINDOW: Duration = Duration::from_secs(60);
+/// Longer webview messages are cut to this size (8 KB)
+const MAX_FRONTEND_MESSAGE_BYTES: usize = 8 * 1024;
+const FRONTEND_TARGET: &str = "webview";
+
+/// Names the app's own crate goes by in module overrides
+const CRATE_NAMES: [&str; 2] = [env!("CARGO_CRATE_NAME"), "hush_web_client"];
+
+const fn default_level() -> LevelFilter {
+    if cfg!(debug_assertions) {
+        LevelFilter::Info
+    } else {
+        LevelFilter::Warn
+    }
+}
+
+/// The effective filter: a default level and per-module overrides
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Levels {
+    default: LevelFilter,
+    /// Module path (without the crate name for app modules) → level
+    modules: BTreeMap<String, LevelFilter>,
+}
+
+impl Levels {
+    /// Level for `target`, from the longest matching module override.
+    fn level_for(&self, target: &str) -> LevelFilter {
+        let target = strip_crate_name(target);
+        self.modules
+            .iter()
+            .filter(|(module, _)| {
+                target == module.as_str()
+                    || target
+                        .strip_prefix(module.as_str())
+                        .is_some_and(|rest| rest.starts_with("::"))
+            })
+            .max_by_key(|(module, _)| module.len())
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    fn max(&self) -> LevelFilter {
+        self.modules.values().copied().fold(self.default, Ord::max)
+    }
+}
+
+static LEVELS: RwLock<Levels> = RwLock::new(Levels {
+    default: default_level(),
+    modules: BTreeMap::new(),
+});
+
+/// Current levels, as returned by `get_log_level` and sent in
+/// `log-level-changed`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LogLevels {
+    pub level: String,
+    pub modules: BTreeMap<String, String>,
+}
+
+fn strip_crate_name(module: &str) -> &str {
+    CRATE_NAMES
+        .iter()
+        .find_map(|name| {
+            module
+                .strip_prefix(name)
+                .and_then(|rest| rest.strip_prefix("::"))
+        })
+        .unwrap_or(module)
+}
+
+pub(crate) fn parse_level(level: &str) -> Result<LevelFilter, String> {
+    LevelFilter::from_str(level.trim()).map_err(|_| {
+        format!(
+            "Unknown log level {}; expected off, error, warn, info, debug or trace",
+            level
+        )
+    })
+}
+
+fn level_name(level: LevelFilter) -> String {
+    level.as_str().to_ascii_lowercase()
+}
+
+fn enabled(metadata: &log::Metadata) -> bool {
+    let levels = LEVELS.read().unwrap_or_else(|e| e.into_inner());
+    metadata.level() <= levels.level_for(metadata.target())
+}
+
+fn apply(levels: Levels) {
+    log::set_max_level(levels.max());
+    *LEVELS.write().unwrap_or_else(|e| e.into_inner()) = levels;
+}
+
+fn current() -> LogLevels {
+    let levels = LEVELS.read().unwrap_or_else(|e| e.into_inner());
+    LogLevels {
+        level: level_name(levels.default),
+        modules: levels
+            .modules
+            .iter()
+            .map(|(module, level)| (module.clone(), level_name(*level)))
+            .collect(),
+    }
+}
+
+/// Levels from the saved settings, skipping invalid entries.
+fn saved_levels(app: &AppHandle) -> Levels {
+    let settings = app.state::<SettingsState>().get();
+    let default = settings
+        .log_level
+        .as_deref()
+        .and_then(|level| parse_level(level).ok())
+        .unwrap_or(default_level());
+    let modules = settings
+        .log_module_levels
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|(module, level)| Some((module, parse_level(&level).ok()?)))
+        .collect();
+    Levels { default, modules }
+}
+
+/// Apply the saved log levels. Called from `setup` once settings are loaded.
+pub fn init(app: &AppHandle) {
+    apply(saved_levels(app));
+}
+
+/// Re-apply the saved log levels after settings were imported and emit
+/// `log-level-changed`.
+pub(crate) fn reload(app: &AppHandle) {
+    apply(saved_levels(app));
+    let _ = app.emit("log-level-changed", &current());
+}
+
+/// The log plugin, installed first thing in `setup`. It passes everything to
+/// [`enabled`], which applies the runtime levels.
+pub fn plugin<R: Runtime>() -> TauriPlugin<R> {
+    tauri_plugin_log::Builder::new()
+        .clear_targets()
+        .targets([
+            Target::new(TargetKind::Stdout),
+            Target::new(TargetKind::LogDir { file_name: None }),
+        ])
+        .max_file_size(MAX_FILE_BYTES)
+        .rotation_strategy(RotationStrategy::KeepSome(KEEP_FILES))
+        .level(LevelFilter::Trace)
+        .filter(enabled)
+        .build()
+}
+
+fn is_word_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+/// If a sensitive key starts at `start`, the byte range of its value.
+fn sensitive_value_at(text: &str, lower: &str, start: usize) -> Option<(usize, usize)> {
+    let bytes = text.as_bytes();
+    if start > 0 && is_word_byte(bytes[start - 1]) {
+        return None;
+    }
+    let key = SENSITIVE_KEYS
+        .iter()
+        .find(|key| lower[start..].starts_with(*key))?;
+    let mut i = start + key.len();
+    if i < bytes.len() && is_word_byte(bytes[i]) {
+        return None;
+    }
+    if bytes.get(i) == Some(&b'"') {
+        i += 1;
+    }
+    while bytes.get(i) == Some(&b' ') {
+        i += 1;
+    }
+    if !matches!(bytes.get(i), Some(b'=') | Some(b':')) {
+        return None;
+    }
+    i += 1;
+    while bytes.get(i) == Some(&b' ') {
+        i += 1;
+    }
+    let quoted = bytes.get(i) == Some(&b'"');
+    if quoted {
+        i += 1;
+    }
+    let value_start = i;
+    while i < bytes.len() {
+        let byte = bytes[i];
+        if quoted {
+            if byte == b'\\' {
+                i += 2;
+                continue;
+            }
+            if byte == b'"' {
+                break;
+            }
+        } else if byte.is_ascii_whitespace() || matches!(byte, b'&' | b',' | b';' | b'}' | b'"') {
+            break;
+        }
+        i += 1;
+    }
+    let value_end = i.min(bytes.len());
+    (value_end > value_start).then_some((value_start, value_end))
+}
+
+fn redact_key_values(text: &str) -> String {
+    let lower = text.to_ascii_lowercase();
+    let mut out = String::with_capacity(text.len());
+    let mut copied = 0;
+    let mut i = 0;
+    while i < text.len() {
+        if !text.is_char_boundary(i) {
+            i += 1;
+            continue;
+        }
+        match sensitive_value_at(text, &lower, i) {
+            Some((start, end)) => {
+                out.push_str(&text[copied..start]);
+                out.push_str(REDACTED);
+                copied = end;
+                i = end;
+            }
+            None => i += 1,
+        }
+    }
+    out.push_str(&text[copied..]);
+    out
+}
+
+fn redact_schemes(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut words = text.split(' ').peekable();
+    while let Some(word) = words.next() {
+        out.push_str(word);
+        let word = word.trim_start_matches(|c: char| !c.is_ascii_alphabetic());
+        if word.eq_ignore_ascii_case("bearer") || word.eq_ignore_ascii_case("basic") {
+            if let Some(credential) = words.next() {
+                out.push(' ');
+                let end = credential.find('"').unwrap_or(credential.len());
+                out.push_str(REDACTED);
+                out.push_str(&credential[end..]);
+            }
+        }
+        if words.peek().is_some() {
+            out.push(' ');
+        }
+    }
+    out
+}
+
+fn is_opaque_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '+' | '=' | ':')
+}
+
+fn is_uuid(run: &str) -> bool {
+    let groups: Vec<&str> = run.split('-').collect();
+    groups.len() == 5
+        && groups
+            .iter()
+            .zip([8, 4, 4, 4, 12])
+            .all(|(group, len)| group.len() == len && group.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Whether a run of token characters looks like a credential: long, mixing
+/// letters and digits, and not a plain id such as a UUID.
+fn is_opaque(run: &str) -> bool {
+    run.len() >= OPAQUE_MIN_LEN
+        && run.chars().any(|c| c.is_ascii_digit())
+        && run.chars().any(|c| c.is_ascii_alphabetic())
+        && !is_uuid(run)
+}
+
+fn redact_opaque(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut run_start = None;
+    for (i, c) in text.char_indices().chain([(text.len(), ' ')]) {
+        if is_opaque_char(c) && i < text.len() {
+            run_start.get_or_insert(i);
+            continue;
+        }
+        if let Some(start) = run_start.take() {
+            let run = &text[start..i];
+            out.push_str(if is_opaque(run) { REDACTED } else { run });
+        }
+        if i < text.len() {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// `text` with credentials and content stripped, for logging data that came
+/// from or goes to the network.
+pub fn redact(text: &str) -> String {
+    redact_opaque(&redact_schemes(&redact_key_values(text)))
+}
+
+/// Fixed one-minute window for rate limiting webview entries
+#[derive(Debug, Default)]
+struct RateWindow {
+    started: Option<Instant>,
+    accepted: u32,
+    dropped: u64,
+}
+
+impl RateWindow {
+    /// Count an entry at `now`, returning whether to log it and how many were
+    /// dropped in the window that just closed.
+    fn admit(&mut self, now: Instant) -> (bool, u64) {
+        let mut closed_dropped = 0;
+        if self.started.map_or(true, |started| {
+            now.duration_since(started) >= FRONTEND_RATE_WINDOW
+        }) {
+            closed_dropped = std::mem::take(&mut self.dropped);
+            self.started = Some(now);
+            self.accepted = 0;
+        }
+        if self.accepted < FRONTEND_RATE_LIMIT {
+            self.accepted += 1;
+            (true, closed_dropped)
+        } else {
+            self.dropped += 1;
+            (false, closed_dropped)
+        }
+    }
+}
+
+/// Managed state rate limiting `log_from_frontend`
+#[derive(Debug, Default)]
+pub struct FrontendLogState {
+    window: Mutex<RateWindow>,
+}
+
+/// Cut `message` to [`MAX_FRONTEND_MESSAGE_BYTES`] on a char boundary.
+fn truncate_message(mut message: String) -> String {
+    if message.len() <= MAX_FRONTEND_MESSAGE_BYTES {
+        return message;
+    }
+    let mut end = MAX_FRONTEND_MESSAGE_BYTES;
+    while !message.is_char_boundary(end) {
+        end -= 1;
+    }
+    let cut = message.len() - end;
+    message.truncate(end);
+    message.push_str(&format!("… [truncated {} bytes]", cut));
+    message
+}
+
+pub(crate) fn log_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path().app_log_dir().map_err(|e| e.to_string())
+}
+
+/// Most bytes the rotating log files can take: the current file plus the kept
+/// ones.
+pub(crate) fn max_log_bytes() -> u64 {
+    MAX_FILE_BYTES as u64 * (KEEP_FILES as u64 + 1)
+}
+
+/// Delete the log files, or empty those the logger still has open; returns
+/// the bytes freed.
+pub(crate) fn clear_logs(app: &AppHandle) -> Result<u64, String> {
+    let dir = log_dir(app)?;
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e.to_string()),
+    };
+    let mut freed = 0;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+        let size = storage::disk_usage(&path);
+        let cleared = std::fs::remove_file(&path).or_else(|_| {
+            std::fs::OpenOptions::new()
+                .write(true)
+                .truncate(true)
+                .open(&path)
+                .map(|_| ())
+        });
+        match cleared {
+            Ok(()) => freed += size,
+            Err(e) => return Err(format!("Failed to clear {}: {}", path.display(), e)),
+        }
+    }
+    Ok(freed)
+}
+
+/// Where the log files are written.
+#[tauri::command]
+pub fn get_log_directory(app: AppHandle) -> Result<String, String> {
+    log_dir(&app).map(|dir| dir.to_string_lossy().into_owned())
+}
+
+/// Show the log folder in the system file manager.
+#[tauri::command]
+pub fn open_log_directory(app: AppHandle) -> Result<(), String> {
+    let dir = log_dir(&app)?;
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    app.opener()
+        .open_path(dir.to_string_lossy(), None::<&str>)
+        .map_err(|e| e.to_string())
+}
+
+/// Set the log level, for all targets or, with `module`, for one module and
+/// its submodules. `default` removes the module's override, or resets the
+/// global level to the build's default.
+#[tauri::command]
+pub fn set_log_level(
+    app: AppHandle,
+    level: String,
+    module: Option<String>,
+) -> Result<LogLevels, String> {
+    let reset = level.trim().eq_ignore_ascii_case("default");
+    let parsed = if reset {
+        None
+    } else {
+        Some(parse_level(&level)?)
+    };
+    let mut levels = LEVELS.read().unwrap_or_else(|e| e.into_inner()).clone();
+    match module.as_deref().map(str::trim) {
+        Some("") => return Err("Module must not be empty".to_string()),
+        Some(module) => {
+            let module = strip_crate_name(module).to_string();
+            match parsed {
+                Some(level) => levels.modules.insert(module, level),
+                None => levels.modules.remove(&module),
+            };
+        }
+        None => levels.default = parsed.unwrap_or(default_level()),
+    }
+
+    let saved_modules: BTreeMap<String, String> = levels
+        .modules
+        .iter()
+        .map(|(module, level)| (module.clone(), level_name(*level)))
+        .collect();
+    if module.is_some() {
+        settings::set(
+            &app,
+            "logModuleLevels",
+            if saved_modules.is_empty() {
+                serde_json::Value::Null
+            } else {
+                serde_json::to_value(&saved_modules).map_err(|e| e.to_string())?
+            },
+        )?;
+    } else {
+        settings::set(
+            &app,
+            "logLevel",
+            parsed.map_or(serde_json::Value::Null, |level| level_name(level).into()),
+        )?;
+    }
+    apply(levels);
+
+    let levels = current();
+    let _ = app.emit("log-level-changed", &levels);
+    Ok(levels)
+}
+
+#[tauri::command]
+pub fn get_log_level() -> LogLevels {
+    current()
+}
+
+/// Write a webview log entry to the app log. Returns false if it was dropped
+/// by the rate limit.
+#[tauri::command]
+pub fn log_from_frontend(
+    state: State<'_, FrontendLogState>,
+    level: String,
+    message: String,
+    context: Option<serde_json::Value>,
+) -> Result<bool, String> {
+    let level = log::Level::from_str(level.trim()).map_err(|_| {
+        format!(
+            "Unknown log level {}; expected error, warn, info, debug or trace",
+            level
+        )
+    })?;
+    let (admitted, dropped) = state
+        .window
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .admit(Instant::now());
+    if dropped > 0 {
+        log::warn!(target: FRONTEND_TARGET, "Dropped {} webview log entries over the rate limit", dropped);
+    }
+    if !admitted {
+        return Ok(false);
+    }
+
+    let message = redact(&truncate_message(message));
+    match context.filter(|context| !context.is_null()) {
+        Some(context) => {
+            log::log!(target: FRONTEND_TARGET, level, "{} {}", message, redact(&context.to_string()))
+        }
+        None => log::log!(target: FRONTEND_TARGET, level, "{}", message),
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn module_overrides_use_the_longest_match() {
+        let levels = Levels {
+            default: LevelFilter::Warn,
+            modules: BTreeMap::from([
+                ("fcm".to_string(), LevelFilter::Trace),
+                ("reqwest".to_string(), LevelFilter::Error),
+                ("reqwest::connect".to_string(), LevelFilter::Debug),
+            ]),
+        };
+        let crate_fcm = format!("{}::fcm", env!("CARGO_CRATE_NAME"));
+        assert_eq!(levels.level_for(&crate_fcm), LevelFilter::Trace);
+        assert_eq!(
+            levels.level_for("hush_web_client::fcm::tokens"),
+            LevelFilter::Trace
+        );
+        assert_eq!(levels.level_for("fcmx"), LevelFilter::Warn);
+        assert_eq!(
+            levels.level_for("reqwest::connect::http"),
+            LevelFilter::Debug
+        );
+        assert_eq!(levels.level_for("reqwest::async_impl"), LevelFilter::Error);
+        assert_eq!(levels.max(), LevelFilter::Trace);
+    }
+
+    #[test]
+    fn frontend_entries_are_rate_limited_per_window() {
+        let mut window = RateWindow::default();
+        let start = Instant::now();
+        for _ in 0..FRONTEND_RATE_LIMIT {
+            assert_eq!(window.admit(start), (true, 0));
+        }
+        assert_eq!(window.admit(start), (false, 0));
+        assert_eq!(window.admit(start + Duration::from_secs(30)), (false, 0));
+        assert_eq!(window.admit(start + FRONTEND_RATE_WINDOW), (true, 2));
+    }
+
+    #[test]
+    fn long_messages_are_truncated_on_a_char_boundary() {
+        let message = "é".repeat(MAX_FRONTEND_MESSAGE_BYTES);
+        let truncated = truncate_message(message);
+        assert!(truncated.starts_with(&"é".repeat(MAX_FRONTEND_MESSAGE_BYTES / 2)));
+        assert!(truncated.ends_with(&format!(
+            "… [truncated {} bytes]",
+            MAX_FRONTEND_MESSAGE_BYTES
+        )));
+        assert_eq!(truncate_message("short".to_string()), "short");
+    }
+
+    #[test]
+    fn levels_are_validated() {
+        assert_eq!(parse_level("TRACE"), Ok(LevelFilter::Trace));
+        assert_eq!(parse_level(" off "), Ok(LevelFilter::Off));
+        assert!(parse_level("verbose").is_err());
+        assert_eq!(level_name(LevelFilter::Warn), "warn");
+    }
+
+    #[test]
+    fn sensitive_keys_are_redacted() {
+        assert_eq!(
+            redact("GET https://api.hush.social/feeds?token=abc123&limit=20"),
+            "GET https://api.hush.social/feeds?token=[redacted]&limit=20"
+        );
+        assert_eq!(
+            redact(r#"{"feedId":"f1","content":"hello \"there\"","seq":3}"#),
+            r#"{"feedId":"f1","content":"[redacted]","seq":3}"#
+        );
+        assert_eq!(redact("Password: hunter2"), "Password: [redacted]");
+        // Only whole keys
+        assert_eq!(redact("contents=5 tokens:3"), "contents=5 tokens:3");
+    }
+
+    #[test]
+    fn authorization_schemes_are_redacted() {
+        assert_eq!(
+            redact("header Bearer abc.def.ghi sent"),
+            "header Bearer [redacted] sent"
+        );
+    }
+
+    #[test]
+    fn opaque_tokens_are_redacted_but_ids_kept() {
+        let fcm = "dQw4w9WgXcQ:APA91bHun4MxP5egoKMwt2KZFBaFUH-1RYqx";
+        assert_eq!(
+            redact(&format!("registered {} ok", fcm)),
+            "registered [redacted] ok"
+        );
+        let feed = "3f2b8c1e-9d4a-4b7e-8f6a-1c2d3e4f5a6b";
+        assert_eq!(redact(&format!("feed {}", feed)), format!("feed {}", feed));
+        assert_eq!(
+            redact("Download 3 failed: HTTP 404"),
+            "Download 3 failed: HTTP 404"
+        );
+    }
+}
@@ -0,0 +1,247 @@
+//! The route the user was last on, restored on the next launch.
+//!
+//! The frontend calls `save_navigation_state` on every route change. Saves
+//! are debounced over [`SAVE_DEBOUNCE`] (and flushed on exit) into the
+//! `navigationState` setting. At startup `get_navigation_state` hands the
+//! route back unless a notification tap or deep link is waiting (that
+//! navigation wins), the route was saved more than [`MAX_AGE_MS`] ago, or it
+//! points at a feed that is no longer in the offline cache.
+//!
+//! A route names its feed as a `/feed/<id>` or `/feeds/<id>` path segment,
+//! or a `feedId` query parameter; other routes are restored as they are.
+
+use crate::cache::CacheState;
+use crate::error::CommandError;
+use crate::fcm::now_unix_ms;
+use crate::settings::{self, SettingsState};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State, Url};
+
+/// Quiet time after a route change before it is written
+pub const SAVE_DEBOUNCE: Duration = Duration::from_secs(1);
+/// Saved routes older than this are not restored (7 days)
+pub const MAX_AGE_MS: u64 = 7 * 24 * 60 * 60 * 1000;
+const MAX_ROUTE_LEN: usize = 2048;
+
+/// The last route, as stored in the `navigationState` setting
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NavigationState {
+    /// App path, e.g. `/social?feedId=abc`
+    pub route: String,
+    #[serde(default)]
+    pub scroll_offset: Option<f64>,
+    /// Unix timestamp (ms) of the route change
+    pub saved_at: u64,
+}
+
+/// Managed state holding the route waiting to be written
+#[derive(Debug, Default)]
+pub struct NavigationSaveState {
+    unsaved: Mutex<Option<NavigationState>>,
+    generation: AtomicU64,
+    scheduled: AtomicBool,
+}
+
+impl NavigationSaveState {
+    fn unsaved(&self) -> MutexGuard<'_, Option<NavigationState>> {
+        self.unsaved.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Check a route from the frontend: an app path, not a URL.
+fn validate_route(route: &str) -> Result<(), CommandError> {
+    if !route.starts_with('/') || route.starts_with("//") || route.len() > MAX_ROUTE_LEN {
+        return Err(CommandError::invalid_argument(
+            "Route must be an app path starting with /",
+        ));
+    }
+    Ok(())
+}
+
+/// The feed a route points at, if any.
+fn feed_of_route(route: &str) -> Option<String> {
+    let url = Url::parse("hush-route://app").ok()?.join(route).ok()?;
+    if let Some((_, feed_id)) = url.query_pairs().find(|(key, _)| key == "feedId") {
+        return Some(feed_id.into_owned());
+    }
+    let segments: Vec<&str> = url.path_segments()?.collect();
+    segments
+        .windows(2)
+        .find(|pair| matches!(pair[0], "feed" | "feeds") && !pair[1].is_empty())
+        .map(|pair| pair[1].to_string())
+}
+
+/// Why a saved route can't be restored, if it can't. `cached_feeds` is None
+/// when the cache couldn't be read, which keeps the route.
+fn discard_reason(
+    saved: &NavigationState,
+    now: u64,
+    cached_feeds: Option<&[String]>,
+) -> Option<&'static str> {
+    if now.saturating_sub(saved.saved_at) > MAX_AGE_MS {
+        return Some("older than 7 days");
+    }
+    let feed_id = feed_of_route(&saved.route)?;
+    match cached_feeds {
+        Some(feeds) if !feeds.contains(&feed_id) => Some("feed no longer cached"),
+        _ => None,
+    }
+}
+
+/// Write the route waiting to be saved, if any. Called after the debounce
+/// and from the `RunEvent::ExitRequested` handler.
+pub fn flush(app: &AppHandle) {
+    let Some(state) = app.try_state::<NavigationSaveState>() else {
+        return;
+    };
+    let Some(saved) = state.unsaved().take() else {
+        return;
+    };
+    let value = match serde_json::to_value(&saved) {
+        Ok(value) => value,
+        Err(e) => {
+            log::warn!("Failed to encode navigation state: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = settings::set(app, "navigationState", value) {
+        log::warn!("Failed to save navigation state: {}", e);
+    }
+}
+
+fn schedule_save(app: &AppHandle, state: &NavigationSaveState) {
+    state.generation.fetch_add(1, Ordering::SeqCst);
+    if state.scheduled.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let state = app.state::<NavigationSaveState>();
+        loop {
+            let seen = state.generation.load(Ordering::SeqCst);
+            std::thread::sleep(SAVE_DEBOUNCE);
+            if state.generation.load(Ordering::SeqCst) == seen {
+                break;
+            }
+        }
+        state.scheduled.store(false, Ordering::SeqCst);
+        flush(&app);
+    });
+}
+
+/// Remember the current route and scroll position for the next launch.
+/// Calls in quick succession only write the last one.
+#[tauri::command]
+pub fn save_navigation_state(
+    app: AppHandle,
+    state: State<'_, NavigationSaveState>,
+    route: String,
+    scroll_offset: Option<f64>,
+) -> Result<(), CommandError> {
+    validate_route(&route)?;
+    if scroll_offset.is_some_and(|offset| !offset.is_finite()) {
+        return Err(CommandError::invalid_argument(
+            "Scroll offset must be a finite number",
+        ));
+    }
+    *state.unsaved() = Some(NavigationState {
+        route,
+        scroll_offset,
+        saved_at: now_unix_ms(),
+    });
+    schedule_save(&app, &state);
+    Ok(())
+}
+
+/// The route to restore at startup, or None to start on the home timeline.
+/// None as well while a pending navigation (a notification tap or deep
+/// link) is waiting, which the frontend should follow instead.
+#[tauri::command]
+pub fn get_navigation_state(
+    app: AppHandle,
+    state: State<'_, NavigationSaveState>,
+    settings: State<'_, SettingsState>,
+) -> Option<NavigationState> {
+    if crate::fcm::has_pending_navigation(&app) {
+        log::debug!("Pending navigation waiting, not restoring the last route");
+        return None;
+    }
+    let saved = state
+        .unsaved()
+        .clone()
+        .or_else(|| settings.get().navigation_state)?;
+    let cached_feeds: Option<Vec<String>> = app
+        .try_state::<CacheState>()
+        .and_then(|cache| cache.feeds().ok())
+        .map(|feeds| feeds.into_iter().map(|feed| feed.feed_id).collect());
+    match discard_reason(&saved, now_unix_ms(), cached_feeds.as_deref()) {
+        Some(reason) => {
+            log::info!("Not restoring the last route: {}", reason);
+            None
+        }
+        None => Some(saved),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn saved(route: &str, saved_at: u64) -> NavigationState {
+        NavigationState {
+            route: route.to_string(),
+            scroll_offset: Some(120.0),
+            saved_at,
+        }
+    }
+
+    #[test]
+    fn routes_must_be_app_paths() {
+        assert!(validate_route("/social?feedId=abc").is_ok());
+        assert!(validate_route("social").is_err());
+        assert!(validate_route("//evil.example/x").is_err());
+        assert!(validate_route("https://evil.example/").is_err());
+    }
+
+    #[test]
+    fn feed_is_found_in_path_or_query() {
+        assert_eq!(feed_of_route("/social?feedId=abc").as_deref(), Some("abc"));
+        assert_eq!(feed_of_route("/feeds/abc/post/p1").as_deref(), Some("abc"));
+        assert_eq!(feed_of_route("/feed/xyz").as_deref(), Some("xyz"));
+        assert_eq!(feed_of_route("/account/elections"), None);
+        assert_eq!(feed_of_route("/feeds"), None);
+    }
+
+    #[test]
+    fn stale_or_uncached_routes_are_discarded() {
+        let now = 10 * MAX_AGE_MS;
+        let cached = vec!["abc".to_string()];
+        assert_eq!(
+            discard_reason(&saved("/social?feedId=abc", now - 1000), now, Some(&cached)),
+            None
+        );
+        assert!(discard_reason(&saved("/social", now - MAX_AGE_MS - 1), now, None).is_some());
+        assert!(discard_reason(&saved("/social?feedId=gone", now), now, Some(&cached)).is_some());
+        // Unknown cache contents keep the route
+        assert_eq!(
+            discard_reason(&saved("/social?feedId=gone", now), now, None),
+            None
+        );
+    }
+
+    #[test]
+    fn state_round_trips_in_camel_case() {
+        let state = saved("/social", 5);
+        let json = serde_json::to_value(&state).unwrap();
+        assert_eq!(json["scrollOffset"], 120.0);
+        assert_eq!(json["savedAt"], 5);
+        assert_eq!(
+            serde_json::from_value::<NavigationState>(json).unwrap(),
+            state
+        );
+    }
+}
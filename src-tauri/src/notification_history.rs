@@ -0,0 +1,291 @@
+//! Log of notifications handled by the Rust side.
+//!
+//! Every notification passed to [`crate::notifications::dispatch`] is recorded
+//! here, including ones held back by quiet hours, so nothing is lost while
+//! notifications are suppressed. The last [`MAX_ENTRIES`] entries are kept in
+//! `notification-history.json` in the app data dir; entries older than
+//! [`MAX_AGE_MS`] are pruned on startup. Entries are marked read when their
+//! notifications are dismissed because the feed was read in the app.
+
+use crate::error::CommandError;
+use crate::fcm::{now_unix_ms, NavigationKind};
+use crate::lock::LockState;
+use crate::notifications::FeedNotification;
+use crate::storage;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, State};
+
+pub(crate) const HISTORY_FILE: &str = "notification-history.json";
+/// Maximum number of entries kept
+const MAX_ENTRIES: usize = 200;
+/// Entries older than this (30 days) are pruned on startup
+const MAX_AGE_MS: u64 = 30 * 24 * 60 * 60 * 1000;
+
+/// A recorded notification
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub id: u64,
+    pub feed_id: Option<String>,
+    pub post_id: Option<String>,
+    pub kind: NavigationKind,
+    pub title: String,
+    pub body: String,
+    /// Unix timestamp (ms) when the notification was handled
+    pub timestamp: u64,
+    /// Not shown because quiet hours were active
+    #[serde(default)]
+    pub suppressed: bool,
+    /// The user clicked the notification
+    #[serde(default)]
+    pub tapped: bool,
+    /// The feed was read in the app after the notification
+    #[serde(default)]
+    pub read: bool,
+}
+
+/// Managed state holding the notification history, newest last
+#[derive(Debug, Default)]
+pub struct NotificationHistoryState {
+    entries: Mutex<VecDeque<HistoryEntry>>,
+    /// Backing file; `None` keeps the history in memory only
+    path: Option<PathBuf>,
+}
+
+impl NotificationHistoryState {
+    /// Load the history from the app data dir, pruning entries older than 30 days.
+    pub fn load(app: &AppHandle) -> Self {
+        let path = storage::data_file(app, HISTORY_FILE).ok();
+        let mut entries: VecDeque<HistoryEntry> = path
+            .as_deref()
+            .and_then(storage::read_json)
+            .unwrap_or_default();
+
+        let before = entries.len();
+        prune_older_than(&mut entries, now_unix_ms().saturating_sub(MAX_AGE_MS));
+        let pruned = entries.len() != before;
+
+        let state = Self {
+            entries: Mutex::new(VecDeque::new()),
+            path,
+        };
+        if pruned {
+            state.persist(&entries);
+        }
+        *state.entries.lock().unwrap_or_else(|e| e.into_inner()) = entries;
+        state
+    }
+
+    fn persist(&self, entries: &VecDeque<HistoryEntry>) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if let Err(e) = storage::write_json_atomic(path, entries) {
+            log::warn!("Failed to save notification history: {}", e);
+        }
+    }
+
+    /// Append a notification, dropping the oldest entries beyond the limit.
+    ///
+    /// Returns the new entry's id.
+    pub fn record(&self, notification: &FeedNotification, suppressed: bool) -> u64 {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let id = entries.back().map_or(1, |last| last.id + 1);
+        entries.push_back(HistoryEntry {
+            id,
+            feed_id: notification.feed_id.clone(),
+            post_id: notification.post_id.clone(),
+            kind: notification.kind,
+            title: notification.title.clone(),
+            body: notification.body.clone(),
+            timestamp: now_unix_ms(),
+            suppressed,
+            tapped: false,
+            read: false,
+        });
+        while entries.len() > MAX_ENTRIES {
+            entries.pop_front();
+        }
+        self.persist(&entries);
+        id
+    }
+
+    /// Mark the entry with `id` as tapped.
+    pub fn mark_tapped(&self, id: u64) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(entry) = entries.iter_mut().find(|entry| entry.id == id) {
+            entry.tapped = true;
+            self.persist(&entries);
+        }
+    }
+
+    /// Mark the unread entries of `feed_id`, or of every feed, as read.
+    pub fn mark_read(&self, feed_id: Option<&str>) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let mut changed = false;
+        for entry in entries.iter_mut().filter(|entry| !entry.read) {
+            if feed_id.map_or(true, |feed_id| entry.feed_id.as_deref() == Some(feed_id)) {
+                entry.read = true;
+                changed = true;
+            }
+        }
+        if changed {
+            self.persist(&entries);
+        }
+    }
+
+    /// Up to `limit` entries older than `before` (Unix ms), newest first.
+    pub fn page(&self, limit: usize, before: Option<i64>) -> Vec<HistoryEntry> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries
+            .iter()
+            .rev()
+            .filter(|entry| before.map_or(true, |before| (entry.timestamp as i64) < before))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    /// Drop every entry and delete the backing file. Returns the bytes freed.
+    pub fn clear(&self) -> Result<u64, String> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.clear();
+        match &self.path {
+            Some(path) => storage::remove_all(path),
+            None => Ok(0),
+        }
+    }
+}
+
+fn prune_older_than(entries: &mut VecDeque<HistoryEntry>, cutoff: u64) {
+    entries.retain(|entry| entry.timestamp >= cutoff);
+}
+
+/// Page through the notification history, newest first.
+///
+/// Pass the `timestamp` of the last entry received as `before` to fetch the next page.
+/// Fails while the app is locked.
+#[tauri::command]
+pub fn get_notification_history(
+    state: State<'_, NotificationHistoryState>,
+    lock: State<'_, LockState>,
+    limit: u32,
+    before: Option<i64>,
+) -> Result<Vec<HistoryEntry>, CommandError> {
+    lock.ensure_unlocked()?;
+    Ok(state.page(limit as usize, before))
+}
+
+/// Delete all notification history.
+#[tauri::command]
+pub fn clear_notification_history(state: State<'_, NotificationHistoryState>) {
+    if let Err(e) = state.clear() {
+        log::warn!("Failed to delete notification history: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notification(feed_id: &str) -> FeedNotification {
+        FeedNotification {
+            title: "New message".to_string(),
+            body: "Hello".to_string(),
+            feed_id: Some(feed_id.to_string()),
+            post_id: None,
+            kind: NavigationKind::Feed,
+            feed_name: None,
+        }
+    }
+
+    fn set_timestamps(state: &NotificationHistoryState, timestamps: &[u64]) {
+        let mut entries = state.entries.lock().unwrap();
+        for (entry, timestamp) in entries.iter_mut().zip(timestamps) {
+            entry.timestamp = *timestamp;
+        }
+    }
+
+    #[test]
+    fn record_keeps_suppressed_flag() {
+        let state = NotificationHistoryState::default();
+        state.record(&notification("a"), true);
+        state.record(&notification("b"), false);
+
+        let entries = state.page(10, None);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].feed_id.as_deref(), Some("b"));
+        assert!(!entries[0].suppressed);
+        assert!(entries[1].suppressed);
+    }
+
+    #[test]
+    fn record_drops_oldest_beyond_limit() {
+        let state = NotificationHistoryState::default();
+        for i in 0..MAX_ENTRIES + 5 {
+            state.record(&notification(&i.to_string()), false);
+        }
+
+        let entries = state.page(usize::MAX, None);
+        assert_eq!(entries.len(), MAX_ENTRIES);
+        assert_eq!(entries.last().unwrap().feed_id.as_deref(), Some("5"));
+    }
+
+    #[test]
+    fn page_filters_by_before_and_limit() {
+        let state = NotificationHistoryState::default();
+        for feed in ["a", "b", "c", "d"] {
+            state.record(&notification(feed), false);
+        }
+        set_timestamps(&state, &[100, 200, 300, 400]);
+
+        let page: Vec<u64> = state.page(2, Some(400)).iter().map(|e| e.timestamp).collect();
+        assert_eq!(page, vec![300, 200]);
+        assert_eq!(state.page(10, Some(100)).len(), 0);
+    }
+
+    #[test]
+    fn mark_tapped_and_clear() {
+        let state = NotificationHistoryState::default();
+        let first = state.record(&notification("a"), false);
+        let second = state.record(&notification("b"), false);
+        assert_ne!(first, second);
+
+        state.mark_tapped(first);
+        let entries = state.page(10, None);
+        assert!(!entries[0].tapped);
+        assert!(entries[1].tapped);
+
+        state.clear();
+        assert!(state.page(10, None).is_empty());
+    }
+
+    #[test]
+    fn mark_read_is_per_feed() {
+        let state = NotificationHistoryState::default();
+        state.record(&notification("a"), false);
+        state.record(&notification("b"), false);
+
+        state.mark_read(Some("a"));
+        let read: Vec<bool> = state.page(10, None).iter().map(|e| e.read).collect();
+        assert_eq!(read, vec![false, true]);
+
+        state.mark_read(None);
+        assert!(state.page(10, None).iter().all(|e| e.read));
+    }
+
+    #[test]
+    fn prune_drops_entries_before_cutoff() {
+        let state = NotificationHistoryState::default();
+        state.record(&notification("old"), false);
+        state.record(&notification("new"), false);
+        set_timestamps(&state, &[1_000, 5_000]);
+
+        let mut entries = state.entries.lock().unwrap();
+        prune_older_than(&mut entries, 2_000);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].feed_id.as_deref(), Some("new"));
+    }
+}
@@ -0,0 +1,1333 @@
+//! Rust-side notification dispatch.
+//!
+//! Every notification the Rust layer posts goes through [`dispatch`], so
+//! clicks can be turned into pending navigations the same way notification
+//! taps are on mobile.
+//!
+//! Click callbacks are only available where the OS reports them: on Linux the
+//! notification is posted through D-Bus (notify-rust) and its default action is
+//! awaited on a background thread. On Windows and macOS the notification plugin
+//! is used; clicking simply activates the app.
+//!
+//! The notification sound is chosen by the user and persisted to
+//! `notification-sound.json`. Custom sounds are bundled under `sounds/` in the
+//! app resources; `None` means notifications are posted silently.
+//!
+//! During quiet hours (local time, persisted to `quiet-hours.json`)
+//! notifications are recorded in the history but not shown.
+//!
+//! Feeds can be muted, optionally until a timestamp; their notifications are
+//! dropped. The mute list is persisted to `muted-feeds.json` and mirrored to the
+//! Android FCM service so it can skip muted feeds while the webview is not loaded.
+//!
+//! Where the platform reports them (Linux, through D-Bus), feed notifications
+//! carry "Mark read" and "Reply" actions that emit `notification-action`.
+//! "Mark read" also clears the feed's unread count in the offline cache and
+//! updates the tray badge, so it works while the webview is asleep. Elsewhere
+//! the notification is plain; [`get_notification_capabilities`] tells which.
+//!
+//! Floods are collapsed: once more than `notificationGroupThreshold` (default
+//! [`DEFAULT_GROUP_THRESHOLD`]) notifications for one feed are shown within
+//! `notificationGroupWindowSecs` (default [`DEFAULT_GROUP_WINDOW_SECS`]),
+//! further ones become a single "5 new posts in <feed>" summary that opens
+//! the feed. On Linux the summary replaces itself in place; elsewhere the
+//! notifications carry the feed id as their group, which Android bundles.
+//! A threshold of 0 turns this off.
+//!
+//! Notifications for the feed on screen are not shown: the frontend reports it
+//! through `set_active_feed`, and while the main window has focus (or the
+//! feed's pop-out does) its notifications are only recorded in the history
+//! and counted as unread. Losing focus re-enables them right away.
+//! Mentions that are shown also flash the taskbar or bounce the dock
+//! ([`crate::attention`]) unless `attentionOnMention` is off.
+//!
+//! `dismiss_notifications_for_feed` removes a feed's notifications once it
+//! has been read in the app and marks them read in the history;
+//! `dismiss_all_notifications` does the same for every feed on logout. On
+//! Linux the D-Bus ids of shown notifications are tracked until they close or
+//! [`DELIVERED_TTL_MS`] passes; on Android the native layer cancels the
+//! feed's tagged notification. Windows and macOS offer no way to take a
+//! delivered notification back, so only the history is updated there.
+
+use crate::cache::CacheState;
+use crate::error::CommandError;
+use crate::fcm::{now_unix_ms, NavigationKind};
+use crate::feed_windows;
+use crate::notification_history::NotificationHistoryState;
+use crate::push_diagnostics::PushOutcome;
+use crate::storage;
+#[cfg(desktop)]
+use crate::tray::TrayManager;
+use chrono::Timelike;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+#[cfg(not(target_os = "linux"))]
+use tauri_plugin_notification::NotificationExt;
+
+const APP_NAME: &str = "Hush Feeds";
+const SOUND_FILE: &str = "notification-sound.json";
+/// Sound name meaning "use the system default notification sound"
+pub const DEFAULT_SOUND: &str = "default";
+const SOUNDS_DIR: &str = "sounds";
+const SOUND_EXTENSIONS: [&str; 5] = ["wav", "ogg", "mp3", "aiff", "caf"];
+const QUIET_HOURS_FILE: &str = "quiet-hours.json";
+const MUTED_FEEDS_FILE: &str = "muted-feeds.json";
+/// Notifications per feed shown individually within the grouping window
+pub const DEFAULT_GROUP_THRESHOLD: u32 = 3;
+pub const DEFAULT_GROUP_WINDOW_SECS: u32 = 60;
+/// Shown notifications are forgotten after this (1 day) even if their close
+/// was never reported
+pub const DELIVERED_TTL_MS: u64 = 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SoundSetting {
+    /// Missing means the system default; an explicit `null` means silent
+    #[serde(default = "default_sound")]
+    sound: Option<String>,
+}
+
+fn default_sound() -> Option<String> {
+    Some(DEFAULT_SOUND.to_string())
+}
+
+impl Default for SoundSetting {
+    fn default() -> Self {
+        Self {
+            sound: default_sound(),
+        }
+    }
+}
+
+/// Managed state holding the selected notification sound
+#[derive(Debug)]
+pub struct NotificationSoundState {
+    sound: Mutex<Option<String>>,
+}
+
+impl NotificationSoundState {
+    /// Load the persisted sound from the app config dir.
+    pub fn load(app: &AppHandle) -> Self {
+        let setting = storage::config_file(app, SOUND_FILE)
+            .ok()
+            .and_then(|path| storage::read_json::<SoundSetting>(&path))
+            .unwrap_or_default();
+        Self {
+            sound: Mutex::new(setting.sound),
+        }
+    }
+
+    pub fn sound(&self) -> Option<String> {
+        self.sound.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn set_sound(&self, sound: Option<String>) {
+        *self.sound.lock().unwrap_or_else(|e| e.into_inner()) = sound;
+    }
+}
+
+/// Daily quiet hours schedule, as local "HH:MM" times
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuietHours {
+    pub start: String,
+    pub end: String,
+    pub enabled: bool,
+}
+
+impl Default for QuietHours {
+    fn default() -> Self {
+        Self {
+            start: "22:00".to_string(),
+            end: "07:00".to_string(),
+            enabled: false,
+        }
+    }
+}
+
+impl QuietHours {
+    /// Whether the schedule covers `minute_of_day` (0..1440).
+    ///
+    /// Ranges where `end` is before `start` wrap past midnight; an empty range
+    /// (start == end) never matches.
+    fn covers(&self, minute_of_day: u32) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let (Some(start), Some(end)) = (parse_time(&self.start), parse_time(&self.end)) else {
+            return false;
+        };
+
+        if start <= end {
+            (start..end).contains(&minute_of_day)
+        } else {
+            minute_of_day >= start || minute_of_day < end
+        }
+    }
+}
+
+/// Parse "HH:MM" into minutes since midnight.
+fn parse_time(value: &str) -> Option<u32> {
+    let (hours, minutes) = value.trim().split_once(':')?;
+    let hours: u32 = hours.parse().ok()?;
+    let minutes: u32 = minutes.parse().ok()?;
+    (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+/// Managed state holding the quiet hours schedule
+#[derive(Debug, Default)]
+pub struct QuietHoursState {
+    schedule: Mutex<QuietHours>,
+}
+
+impl QuietHoursState {
+    /// Load the persisted schedule from the app config dir.
+    pub fn load(app: &AppHandle) -> Self {
+        let schedule = storage::config_file(app, QUIET_HOURS_FILE)
+            .ok()
+            .and_then(|path| storage::read_json::<QuietHours>(&path))
+            .unwrap_or_default();
+        Self {
+            schedule: Mutex::new(schedule),
+        }
+    }
+
+    pub fn schedule(&self) -> QuietHours {
+        self.schedule
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Whether quiet hours are in effect at the current local time.
+    pub fn is_active(&self) -> bool {
+        let now = chrono::Local::now();
+        self.schedule().covers(now.hour() * 60 + now.minute())
+    }
+}
+
+/// Feed id → optional mute expiry (Unix ms); `None` mutes indefinitely
+pub type MutedFeeds = HashMap<String, Option<i64>>;
+
+/// Remove mutes that expired at or before `now`, returning whether any were removed.
+fn prune_expired(feeds: &mut MutedFeeds, now: i64) -> bool {
+    let before = feeds.len();
+    feeds.retain(|_, until| until.map_or(true, |until| until > now));
+    feeds.len() != before
+}
+
+/// Managed state holding the per-feed mute list
+#[derive(Debug, Default)]
+pub struct MutedFeedsState {
+    feeds: Mutex<MutedFeeds>,
+}
+
+impl MutedFeedsState {
+    /// Load the persisted mute list from the app config dir.
+    pub fn load(app: &AppHandle) -> Self {
+        let feeds = storage::config_file(app, MUTED_FEEDS_FILE)
+            .ok()
+            .and_then(|path| storage::read_json::<MutedFeeds>(&path))
+            .unwrap_or_default();
+        Self {
+            feeds: Mutex::new(feeds),
+        }
+    }
+
+    /// Whether `feed_id` is muted, dropping expired entries (and persisting
+    /// the pruned list) along the way.
+    pub fn is_muted(&self, app: &AppHandle, feed_id: &str) -> bool {
+        let mut feeds = self.feeds.lock().unwrap_or_else(|e| e.into_inner());
+        if prune_expired(&mut feeds, now_unix_ms() as i64) {
+            persist_muted_feeds(app, &feeds);
+        }
+        feeds.contains_key(feed_id)
+    }
+
+    /// Apply `change` to the list, prune it, and persist the result.
+    fn update(&self, app: &AppHandle, change: impl FnOnce(&mut MutedFeeds)) -> MutedFeeds {
+        let mut feeds = self.feeds.lock().unwrap_or_else(|e| e.into_inner());
+        change(&mut feeds);
+        prune_expired(&mut feeds, now_unix_ms() as i64);
+        persist_muted_feeds(app, &feeds);
+        feeds.clone()
+    }
+}
+
+/// Write the mute list to disk and, on Android, to the FCM service's preferences.
+fn persist_muted_feeds(app: &AppHandle, feeds: &MutedFeeds) {
+    let result = storage::config_file(app, MUTED_FEEDS_FILE)
+        .and_then(|path| storage::write_json_atomic(&path, feeds));
+    if let Err(e) = result {
+        log::warn!("Failed to save muted feeds: {}", e);
+    }
+
+    #[cfg(target_os = "android")]
+    if let Err(e) = crate::android::set_muted_feeds(feeds) {
+        log::warn!("Failed to share muted feeds with the FCM service: {}", e);
+    }
+}
+
+/// A notification about activity in a feed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeedNotification {
+    pub title: String,
+    pub body: String,
+    #[serde(default)]
+    pub feed_id: Option<String>,
+    #[serde(default)]
+    pub post_id: Option<String>,
+    #[serde(default)]
+    pub kind: NavigationKind,
+    /// Display name of the feed, used in flood summaries
+    #[serde(default)]
+    pub feed_name: Option<String>,
+}
+
+/// Push message body published by the Hush server, matching the FCM data
+/// payload handled by the Android `FcmService`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PushPayload {
+    #[serde(default)]
+    pub feed_id: Option<String>,
+    #[serde(default)]
+    pub post_id: Option<String>,
+    #[serde(default, rename = "type")]
+    pub kind: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub feed_name: Option<String>,
+}
+
+impl PushPayload {
+    pub fn into_notification(self) -> FeedNotification {
+        FeedNotification {
+            title: self.title.unwrap_or_else(|| APP_NAME.to_string()),
+            body: self.body.unwrap_or_else(|| "New activity".to_string()),
+            kind: NavigationKind::from_type(self.kind.as_deref(), self.post_id.is_some()),
+            feed_id: self.feed_id,
+            post_id: self.post_id,
+            feed_name: self.feed_name,
+        }
+    }
+}
+
+// ============= Flood grouping =============
+
+/// Notifications recently shown for one feed
+#[derive(Debug, Default)]
+struct Burst {
+    /// When each notification was shown (Unix ms), oldest first
+    shown_at: VecDeque<u64>,
+    /// Notification server id of the summary standing in for this burst
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    summary_id: Option<u32>,
+}
+
+/// Managed state counting recent notifications per feed
+#[derive(Debug, Default)]
+pub struct NotificationGroupState {
+    bursts: Mutex<HashMap<String, Burst>>,
+}
+
+impl NotificationGroupState {
+    fn bursts(&self) -> std::sync::MutexGuard<'_, HashMap<String, Burst>> {
+        self.bursts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Count a notification for `feed_id` shown at `now`. Returns how many
+    /// were shown within `window_ms` if that's over `threshold`, meaning a
+    /// summary should be shown instead.
+    fn arrive(&self, feed_id: &str, threshold: u32, window_ms: u64, now: u64) -> Option<usize> {
+        if threshold == 0 {
+            return None;
+        }
+        let recent = |at: &u64| now.saturating_sub(*at) <= window_ms;
+        let mut bursts = self.bursts();
+        bursts.retain(|_, burst| burst.shown_at.back().is_some_and(recent));
+        let burst = bursts.entry(feed_id.to_string()).or_default();
+        while burst.shown_at.front().is_some_and(|at| !recent(at)) {
+            burst.shown_at.pop_front();
+        }
+        burst.shown_at.push_back(now);
+        let count = burst.shown_at.len();
+        (count > threshold as usize).then_some(count)
+    }
+
+    /// Forget the burst of `feed_id`, or of every feed, e.g. once read.
+    fn reset(&self, feed_id: Option<&str>) {
+        match feed_id {
+            Some(feed_id) => {
+                self.bursts().remove(feed_id);
+            }
+            None => self.bursts().clear(),
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn summary_id(&self, feed_id: &str) -> Option<u32> {
+        self.bursts().get(feed_id)?.summary_id
+    }
+
+    #[cfg(target_os = "linux")]
+    fn set_summary_id(&self, feed_id: &str, id: u32) {
+        if let Some(burst) = self.bursts().get_mut(feed_id) {
+            burst.summary_id = Some(id);
+        }
+    }
+}
+
+/// The notification standing in for `count` recent ones from a feed
+fn summary_notification(notification: &FeedNotification, count: usize) -> FeedNotification {
+    let feed = notification
+        .feed_name
+        .as_deref()
+        .unwrap_or(&notification.title);
+    FeedNotification {
+        title: format!("{} new posts in {}", count, feed),
+        body: notification.body.clone(),
+        feed_id: notification.feed_id.clone(),
+        post_id: None,
+        kind: NavigationKind::Feed,
+        feed_name: notification.feed_name.clone(),
+    }
+}
+
+/// Collapse `notification` into its feed's summary if the feed is flooding.
+/// Returns the notification to show and whether it is a summary.
+fn group(app: &AppHandle, notification: FeedNotification) -> (FeedNotification, bool) {
+    let (Some(feed_id), Some(groups)) = (
+        notification.feed_id.as_deref(),
+        app.try_state::<NotificationGroupState>(),
+    ) else {
+        return (notification, false);
+    };
+    let settings = app
+        .try_state::<crate::settings::SettingsState>()
+        .map(|state| state.get())
+        .unwrap_or_default();
+    let threshold = settings
+        .notification_group_threshold
+        .unwrap_or(DEFAULT_GROUP_THRESHOLD);
+    let window_secs = settings
+        .notification_group_window_secs
+        .unwrap_or(DEFAULT_GROUP_WINDOW_SECS);
+    match groups.arrive(feed_id, threshold, window_secs as u64 * 1000, now_unix_ms()) {
+        Some(count) => (summary_notification(&notification, count), true),
+        None => (notification, false),
+    }
+}
+
+// ============= Active feed =============
+
+/// Managed state holding the feed the frontend shows and whether the main
+/// window has focus
+#[derive(Debug, Default)]
+pub struct ActiveFeedState {
+    feed_id: Mutex<Option<String>>,
+    focused: AtomicBool,
+}
+
+impl ActiveFeedState {
+    fn feed_id(&self) -> std::sync::MutexGuard<'_, Option<String>> {
+        self.feed_id.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn set_feed(&self, feed_id: Option<String>) {
+        *self.feed_id() = feed_id.filter(|feed_id| !feed_id.is_empty());
+    }
+
+    /// Track main window focus; called from [`crate::window::on_window_event`].
+    pub fn set_focused(&self, focused: bool) {
+        self.focused.store(focused, Ordering::SeqCst);
+    }
+
+    /// Whether `feed_id` is on screen in the focused main window.
+    fn is_viewing(&self, feed_id: &str) -> bool {
+        self.focused.load(Ordering::SeqCst) && self.feed_id().as_deref() == Some(feed_id)
+    }
+}
+
+/// Whether the user is looking at `feed_id`, in the main window or its pop-out.
+fn is_feed_on_screen(app: &AppHandle, feed_id: &str) -> bool {
+    app.try_state::<ActiveFeedState>()
+        .is_some_and(|state| state.is_viewing(feed_id))
+        || feed_windows::window_for_feed(app, feed_id)
+            .is_some_and(|window| window.is_focused().unwrap_or(false))
+}
+
+/// Flash the taskbar or bounce the dock for a mention, on the window that
+/// would show its feed.
+fn request_attention(app: &AppHandle, notification: &FeedNotification) {
+    let enabled = app
+        .try_state::<crate::settings::SettingsState>()
+        .and_then(|state| state.get().attention_on_mention);
+    let Some(level) = crate::attention::for_notification(notification.kind, enabled) else {
+        return;
+    };
+    let label = match notification.feed_id.as_deref() {
+        Some(feed_id) => feed_windows::target_for_feed(app, feed_id),
+        None => "main".to_string(),
+    };
+    crate::attention::request(app, &label, level);
+}
+
+// ============= Delivered notifications =============
+
+/// A notification still showing, by its platform id
+#[derive(Debug, Clone, Copy)]
+struct Delivered {
+    id: u32,
+    /// When it was shown (Unix ms)
+    shown_at: u64,
+}
+
+/// Managed state tracking the platform ids of the notifications still
+/// showing, per feed, so they can be dismissed once the feed is read
+#[derive(Debug, Default)]
+pub struct DeliveredNotificationsState {
+    delivered: Mutex<HashMap<String, Vec<Delivered>>>,
+}
+
+impl DeliveredNotificationsState {
+    fn delivered(&self) -> std::sync::MutexGuard<'_, HashMap<String, Vec<Delivered>>> {
+        self.delivered.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Note notification `id` as showing for `feed_id`, dropping entries
+    /// older than [`DELIVERED_TTL_MS`].
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    fn track(&self, feed_id: &str, id: u32, now: u64) {
+        let mut delivered = self.delivered();
+        for entries in delivered.values_mut() {
+            entries.retain(|entry| now.saturating_sub(entry.shown_at) <= DELIVERED_TTL_MS);
+        }
+        delivered.retain(|_, entries| !entries.is_empty());
+        let entries = delivered.entry(feed_id.to_string()).or_default();
+        entries.retain(|entry| entry.id != id);
+        entries.push(Delivered { id, shown_at: now });
+    }
+
+    /// Forget notification `id` once it has been closed or expired.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    fn forget(&self, feed_id: &str, id: u32) {
+        let mut delivered = self.delivered();
+        if let Some(entries) = delivered.get_mut(feed_id) {
+            entries.retain(|entry| entry.id != id);
+            if entries.is_empty() {
+                delivered.remove(feed_id);
+            }
+        }
+    }
+
+    /// Remove and return the ids showing for `feed_id`, or for every feed.
+    fn take(&self, feed_id: Option<&str>) -> Vec<u32> {
+        let mut delivered = self.delivered();
+        let entries: Vec<Delivered> = match feed_id {
+            Some(feed_id) => delivered.remove(feed_id).unwrap_or_default(),
+            None => delivered.drain().flat_map(|(_, entries)| entries).collect(),
+        };
+        entries.into_iter().map(|entry| entry.id).collect()
+    }
+}
+
+/// Close the notifications with the given D-Bus ids.
+#[cfg(target_os = "linux")]
+fn close_native(_feed_id: Option<&str>, ids: Vec<u32>) -> Result<(), String> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+    // The blocking calls wait for the notification server to answer
+    std::thread::spawn(move || {
+        let connection = match zbus::blocking::Connection::session() {
+            Ok(connection) => connection,
+            Err(e) => {
+                log::debug!("Failed to reach the notification server: {}", e);
+                return;
+            }
+        };
+        for id in ids {
+            let result = connection.call_method(
+                Some("org.freedesktop.Notifications"),
+                "/org/freedesktop/Notifications",
+                Some("org.freedesktop.Notifications"),
+                "CloseNotification",
+                &(id,),
+            );
+            if let Err(e) = result {
+                log::debug!("Failed to close notification {}: {}", id, e);
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(target_os = "android")]
+fn close_native(feed_id: Option<&str>, _ids: Vec<u32>) -> Result<(), String> {
+    match feed_id {
+        Some(feed_id) => crate::android::cancel_feed_notifications(feed_id),
+        None => crate::android::cancel_all_notifications(),
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn close_native(_feed_id: Option<&str>, _ids: Vec<u32>) -> Result<(), String> {
+    Ok(())
+}
+
+/// Dismiss the notifications of `feed_id`, or of every feed, and mark them
+/// read in the history.
+fn dismiss(app: &AppHandle, feed_id: Option<&str>) -> Result<(), CommandError> {
+    let ids = app
+        .try_state::<DeliveredNotificationsState>()
+        .map(|state| state.take(feed_id))
+        .unwrap_or_default();
+    if let Some(groups) = app.try_state::<NotificationGroupState>() {
+        groups.reset(feed_id);
+    }
+    if let Some(history) = app.try_state::<NotificationHistoryState>() {
+        history.mark_read(feed_id);
+    }
+    close_native(feed_id, ids)
+        .map_err(|e| CommandError::new(crate::error::NATIVE_BRIDGE_FAILURE, e))
+}
+
+/// An action button on a feed notification
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NotificationAction {
+    MarkRead,
+    Reply,
+}
+
+impl NotificationAction {
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    const ALL: [NotificationAction; 2] = [NotificationAction::MarkRead, NotificationAction::Reply];
+
+    /// Action key used with the platform API
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    fn key(self) -> &'static str {
+        match self {
+            NotificationAction::MarkRead => "mark-read",
+            NotificationAction::Reply => "reply",
+        }
+    }
+
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    fn label(self) -> &'static str {
+        match self {
+            NotificationAction::MarkRead => "Mark read",
+            NotificationAction::Reply => "Reply",
+        }
+    }
+
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.key() == key)
+    }
+}
+
+/// Payload of the `notification-action` event
+#[derive(Debug, Clone, Serialize)]
+pub struct NotificationActionPayload {
+    pub feed_id: String,
+    pub post_id: Option<String>,
+    pub action: NotificationAction,
+    /// Text typed into an inline reply field, where the platform has one
+    pub reply_text: Option<String>,
+}
+
+/// What notifications can do on this platform
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct NotificationCapabilities {
+    /// Clicks are reported back, so a click opens the notification's feed
+    pub click: bool,
+    /// "Mark read" and "Reply" buttons are shown
+    pub actions: bool,
+    /// Replies can be typed in the notification itself
+    pub inline_reply: bool,
+}
+
+const fn capabilities() -> NotificationCapabilities {
+    NotificationCapabilities {
+        click: cfg!(target_os = "linux"),
+        actions: cfg!(target_os = "linux"),
+        // notify-rust doesn't receive the reply signal
+        inline_reply: false,
+    }
+}
+
+/// Payload of the `notification-clicked` event
+#[derive(Debug, Clone, Serialize)]
+pub struct NotificationClickedPayload {
+    pub feed_id: String,
+    pub post_id: Option<String>,
+    pub kind: NavigationKind,
+}
+
+/// Handle a click on a notification we posted.
+///
+/// Queues the feed as a pending navigation, emits `notification-clicked`, and
+/// shows the main window, mirroring the tray click behaviour; a feed open in
+/// a pop-out gets the event and focus there instead. `history_id`
+/// identifies the notification's history entry, which is marked as tapped.
+pub fn handle_click(app: &AppHandle, notification: &FeedNotification, history_id: Option<u64>) {
+    if let (Some(id), Some(history)) = (history_id, app.try_state::<NotificationHistoryState>()) {
+        history.mark_tapped(id);
+    }
+
+    let Some(feed_id) = notification.feed_id.clone() else {
+        #[cfg(desktop)]
+        crate::tray::show_main_window(app);
+        return;
+    };
+
+    // A feed open in a pop-out is shown there instead of the main window
+    feed_windows::show_for_feed(app, &feed_id);
+    crate::fcm::queue_navigation(
+        app,
+        feed_id.clone(),
+        notification.post_id.clone(),
+        notification.kind,
+    );
+    let payload = NotificationClickedPayload {
+        feed_id: feed_id.clone(),
+        post_id: notification.post_id.clone(),
+        kind: notification.kind,
+    };
+    feed_windows::emit_for_feed(app, &feed_id, "notification-clicked", payload);
+}
+
+/// Handle an action button on a notification we posted.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn handle_action(
+    app: &AppHandle,
+    notification: &FeedNotification,
+    action: NotificationAction,
+    reply_text: Option<String>,
+) {
+    let Some(feed_id) = notification.feed_id.clone() else {
+        return;
+    };
+    match action {
+        NotificationAction::MarkRead => mark_feed_read(app, &feed_id),
+        NotificationAction::Reply => feed_windows::show_for_feed(app, &feed_id),
+    }
+    let payload = NotificationActionPayload {
+        feed_id: feed_id.clone(),
+        post_id: notification.post_id.clone(),
+        action,
+        reply_text,
+    };
+    feed_windows::emit_for_feed(app, &feed_id, "notification-action", payload);
+}
+
+/// Clear a feed's cached unread count and show the new total on the tray.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn mark_feed_read(app: &AppHandle, feed_id: &str) {
+    let Some(cache) = app.try_state::<CacheState>() else {
+        return;
+    };
+    if let Err(e) = cache.set_unread(feed_id, 0, false) {
+        log::warn!("Failed to mark feed read: {}", e);
+        return;
+    }
+    #[cfg(desktop)]
+    if let (Ok(total), Some(tray)) = (cache.total_unread(), app.try_state::<TrayManager>()) {
+        let total = u32::try_from(total).unwrap_or(u32::MAX);
+        if let Err(e) = tray.set_unread_count(total) {
+            log::warn!("Failed to update tray badge: {}", e);
+        }
+    }
+}
+
+/// Post a notification, unless its feed is muted or quiet hours are active.
+///
+/// Notifications held back by quiet hours are still recorded in the history;
+/// muted feeds are dropped entirely. Returns what happened so push listeners
+/// can record it in the diagnostics.
+pub fn dispatch(app: &AppHandle, notification: FeedNotification) -> PushOutcome {
+    if let (Some(feed_id), Some(mutes)) =
+        (&notification.feed_id, app.try_state::<MutedFeedsState>())
+    {
+        if mutes.is_muted(app, feed_id) {
+            log::debug!("Feed muted, dropping notification");
+            return PushOutcome::Muted;
+        }
+    }
+
+    if let (Some(feed_id), Some(cache)) = (&notification.feed_id, app.try_state::<CacheState>()) {
+        if let Err(e) = cache.set_unread(feed_id, 1, true) {
+            log::debug!("Failed to count unread notification: {}", e);
+        }
+    }
+
+    let quiet = app
+        .try_state::<QuietHoursState>()
+        .is_some_and(|state| state.is_active());
+    let history_id = app
+        .try_state::<NotificationHistoryState>()
+        .map(|history| history.record(&notification, quiet));
+    if quiet {
+        log::debug!("Quiet hours active, not showing notification");
+        return PushOutcome::QuietHours;
+    }
+    if let Some(feed_id) = notification.feed_id.as_deref() {
+        if is_feed_on_screen(app, feed_id) {
+            log::debug!("Feed on screen, not showing notification");
+            return PushOutcome::Foreground;
+        }
+    }
+
+    request_attention(app, &notification);
+    let (notification, summary) = group(app, notification);
+    if let Err(e) = show(app, notification, history_id, summary) {
+        log::warn!("Failed to show notification: {}", e);
+    }
+    PushOutcome::Shown
+}
+
+/// Post a notification from the app itself (not about a feed), e.g. from the
+/// updater. Quiet hours apply; mutes and the history don't. `on_click` runs
+/// if the user clicks it, which is only reported on Linux.
+pub fn notify_app(
+    app: &AppHandle,
+    title: &str,
+    body: &str,
+    on_click: impl FnOnce(&AppHandle) + Send + 'static,
+) {
+    if app
+        .try_state::<QuietHoursState>()
+        .is_some_and(|state| state.is_active())
+    {
+        log::debug!("Quiet hours active, not showing notification");
+        return;
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let mut native = notify_rust::Notification::new();
+        native
+            .appname(APP_NAME)
+            .summary(title)
+            .body(body)
+            .action("default", "Open");
+        let app = app.clone();
+        std::thread::spawn(move || match native.show() {
+            Ok(handle) => handle.wait_for_action(|action| {
+                if action == "default" {
+                    on_click(&app);
+                }
+            }),
+            Err(e) => log::warn!("Failed to show notification: {}", e),
+        });
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        drop(on_click);
+        if let Err(e) = app.notification().builder().title(title).body(body).show() {
+            log::warn!("Failed to show notification: {}", e);
+        }
+    }
+}
+
+fn selected_sound(app: &AppHandle) -> Option<String> {
+    app.try_state::<NotificationSoundState>()
+        .map(|state| state.sound())
+        .unwrap_or_else(default_sound)
+}
+
+#[cfg(target_os = "linux")]
+fn show(
+    app: &AppHandle,
+    notification: FeedNotification,
+    history_id: Option<u64>,
+    summary: bool,
+) -> Result<(), String> {
+    use notify_rust::Hint;
+
+    let mut native = notify_rust::Notification::new();
+    native
+        .appname(APP_NAME)
+        .summary(&notification.title)
+        .body(&notification.body)
+        .action("default", "Open");
+    // Update the feed's summary in place rather than stacking another one
+    let summary_feed = notification.feed_id.clone().filter(|_| summary);
+    if let Some(id) = summary_feed.as_deref().and_then(|feed_id| {
+        app.try_state::<NotificationGroupState>()?
+            .summary_id(feed_id)
+    }) {
+        native.id(id);
+    }
+    if notification.feed_id.is_some() {
+        for action in NotificationAction::ALL {
+            native.action(action.key(), action.label());
+        }
+    }
+    match selected_sound(app) {
+        None => {
+            native.hint(Hint::SuppressSound(true));
+        }
+        Some(name) if name == DEFAULT_SOUND => {}
+        Some(name) => match sound_path(app, &name) {
+            Some(path) => {
+                native.hint(Hint::SoundFile(path.to_string_lossy().into_owned()));
+            }
+            None => log::warn!("Notification sound {} not found, using default", name),
+        },
+    }
+
+    let app = app.clone();
+    std::thread::spawn(move || match native.show() {
+        Ok(handle) => {
+            let id = handle.id();
+            if let (Some(feed_id), Some(groups)) =
+                (&summary_feed, app.try_state::<NotificationGroupState>())
+            {
+                groups.set_summary_id(feed_id, id);
+            }
+            let delivered = app.try_state::<DeliveredNotificationsState>();
+            if let (Some(feed_id), Some(delivered)) = (&notification.feed_id, &delivered) {
+                delivered.track(feed_id, id, now_unix_ms());
+            }
+            handle.wait_for_action(|action| {
+                if action == "default" {
+                    handle_click(&app, &notification, history_id);
+                } else if let Some(action) = NotificationAction::from_key(action) {
+                    handle_action(&app, &notification, action, None);
+                }
+            });
+            // Closed, clicked or expired
+            if let (Some(feed_id), Some(delivered)) = (&notification.feed_id, &delivered) {
+                delivered.forget(feed_id, id);
+            }
+        }
+        Err(e) => log::warn!("Failed to show notification: {}", e),
+    });
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn show(
+    app: &AppHandle,
+    notification: FeedNotification,
+    _history_id: Option<u64>,
+    summary: bool,
+) -> Result<(), String> {
+    let mut builder = app
+        .notification()
+        .builder()
+        .title(&notification.title)
+        .body(&notification.body);
+    if let Some(feed_id) = &notification.feed_id {
+        builder = builder.group(feed_id);
+        if summary {
+            builder = builder.group_summary();
+        }
+    }
+    builder = match selected_sound(app) {
+        None => builder.silent(),
+        Some(name) if name == DEFAULT_SOUND => builder,
+        Some(name) => builder.sound(name),
+    };
+    builder.show().map_err(|e| e.to_string())
+}
+
+fn sounds_dir(app: &AppHandle) -> Option<std::path::PathBuf> {
+    app.path()
+        .resource_dir()
+        .ok()
+        .map(|dir| dir.join(SOUNDS_DIR))
+}
+
+#[cfg(target_os = "linux")]
+fn sound_path(app: &AppHandle, name: &str) -> Option<std::path::PathBuf> {
+    let dir = sounds_dir(app)?;
+    SOUND_EXTENSIONS
+        .iter()
+        .map(|ext| dir.join(format!("{}.{}", name, ext)))
+        .find(|path| path.is_file())
+}
+
+/// Sound names (file stems) of the supported audio files in `dir`, sorted.
+fn bundled_sounds(dir: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| SOUND_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        })
+        .filter_map(|path| path.file_stem()?.to_str().map(str::to_string))
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Available notification sounds: the system default followed by bundled sounds.
+#[tauri::command]
+pub fn list_notification_sounds(app: AppHandle) -> Vec<String> {
+    let mut sounds = vec![DEFAULT_SOUND.to_string()];
+    if let Some(dir) = sounds_dir(&app) {
+        sounds.extend(bundled_sounds(&dir));
+    }
+    sounds
+}
+
+/// The selected notification sound; `None` means silent.
+#[tauri::command]
+pub fn get_notification_sound(state: State<'_, NotificationSoundState>) -> Option<String> {
+    state.sound()
+}
+
+/// Select the notification sound and persist it.
+///
+/// `name` must be one of [`list_notification_sounds`]; `None` silences notifications.
+#[tauri::command]
+pub fn set_notification_sound(
+    app: AppHandle,
+    state: State<'_, NotificationSoundState>,
+    name: Option<String>,
+) -> Result<(), CommandError> {
+    if let Some(name) = &name {
+        if !list_notification_sounds(app.clone()).contains(name) {
+            return Err(CommandError::invalid_argument(format!(
+                "Unknown notification sound: {}",
+                name
+            )));
+        }
+    }
+
+    let path = storage::config_file(&app, SOUND_FILE)?;
+    storage::write_json_atomic(&path, &SoundSetting { sound: name.clone() })?;
+
+    #[cfg(target_os = "android")]
+    if let Err(e) = crate::android::set_notification_sound(name.as_deref()) {
+        log::warn!("Failed to update notification channel sound: {}", e);
+    }
+
+    state.set_sound(name);
+    Ok(())
+}
+
+/// Show a feed notification through the Rust notification path.
+#[tauri::command]
+pub fn show_feed_notification(app: AppHandle, notification: FeedNotification) {
+    dispatch(&app, notification);
+}
+
+/// Whether clicks and action buttons work on this platform.
+#[tauri::command]
+pub fn get_notification_capabilities() -> NotificationCapabilities {
+    capabilities()
+}
+
+/// Set the feed the frontend is showing, or None when it shows none. Its
+/// notifications aren't shown while the main window has focus.
+#[tauri::command]
+pub fn set_active_feed(state: State<'_, ActiveFeedState>, feed_id: Option<String>) {
+    state.set_feed(feed_id);
+}
+
+/// Remove the notifications of `feed_id` still showing, e.g. after the feed
+/// was read in the app, and mark its history entries read.
+#[tauri::command]
+pub fn dismiss_notifications_for_feed(app: AppHandle, feed_id: String) -> Result<(), CommandError> {
+    if feed_id.is_empty() {
+        return Err(CommandError::invalid_argument("Feed id is empty"));
+    }
+    dismiss(&app, Some(&feed_id))
+}
+
+/// Remove every notification still showing, e.g. on logout.
+#[tauri::command]
+pub fn dismiss_all_notifications(app: AppHandle) -> Result<(), CommandError> {
+    dismiss(&app, None)
+}
+
+/// The quiet hours schedule.
+#[tauri::command]
+pub fn get_quiet_hours(state: State<'_, QuietHoursState>) -> QuietHours {
+    state.schedule()
+}
+
+/// Set and persist the quiet hours schedule. Times are local "HH:MM".
+#[tauri::command]
+pub fn set_quiet_hours(
+    app: AppHandle,
+    state: State<'_, QuietHoursState>,
+    start: String,
+    end: String,
+    enabled: bool,
+) -> Result<(), CommandError> {
+    for time in [&start, &end] {
+        if parse_time(time).is_none() {
+            return Err(CommandError::invalid_argument(format!(
+                "Invalid time {:?}, expected HH:MM",
+                time
+            )));
+        }
+    }
+
+    let schedule = QuietHours {
+        start,
+        end,
+        enabled,
+    };
+    let path = storage::config_file(&app, QUIET_HOURS_FILE)?;
+    storage::write_json_atomic(&path, &schedule)?;
+    *state.schedule.lock().unwrap_or_else(|e| e.into_inner()) = schedule;
+    Ok(())
+}
+
+/// Whether quiet hours are in effect right now, for the UI's moon indicator.
+#[tauri::command]
+pub fn is_quiet_hours_active(state: State<'_, QuietHoursState>) -> bool {
+    state.is_active()
+}
+
+/// Mute notifications for a feed, indefinitely or until `until` (Unix ms).
+#[tauri::command]
+pub fn mute_feed(
+    app: AppHandle,
+    state: State<'_, MutedFeedsState>,
+    feed_id: String,
+    until: Option<i64>,
+) -> MutedFeeds {
+    state.update(&app, |feeds| {
+        feeds.insert(feed_id, until);
+    })
+}
+
+/// Unmute a feed.
+#[tauri::command]
+pub fn unmute_feed(
+    app: AppHandle,
+    state: State<'_, MutedFeedsState>,
+    feed_id: String,
+) -> MutedFeeds {
+    state.update(&app, |feeds| {
+        feeds.remove(&feed_id);
+    })
+}
+
+/// Currently muted feeds, with expired mutes removed.
+#[tauri::command]
+pub fn get_muted_feeds(app: AppHandle, state: State<'_, MutedFeedsState>) -> MutedFeeds {
+    state.update(&app, |_| {})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn feed_notification_defaults_optional_fields() {
+        let notification: FeedNotification =
+            serde_json::from_str(r#"{"title":"Hi","body":"There"}"#).unwrap();
+        assert!(notification.feed_id.is_none());
+        assert_eq!(notification.kind, NavigationKind::Feed);
+        assert_eq!(APP_NAME, "Hush Feeds");
+    }
+
+    #[test]
+    fn floods_collapse_past_the_threshold() {
+        let groups = NotificationGroupState::default();
+        for i in 0..3 {
+            assert_eq!(groups.arrive("f1", 3, 60_000, 1_000 + i), None);
+        }
+        assert_eq!(groups.arrive("f1", 3, 60_000, 2_000), Some(4));
+        assert_eq!(groups.arrive("f1", 3, 60_000, 3_000), Some(5));
+        // Other feeds are counted separately
+        assert_eq!(groups.arrive("f2", 3, 60_000, 3_000), None);
+        // Once the window has passed the feed starts over
+        assert_eq!(groups.arrive("f1", 3, 60_000, 100_000), None);
+        // 0 turns grouping off
+        for _ in 0..10 {
+            assert_eq!(groups.arrive("f3", 0, 60_000, 100_000), None);
+        }
+    }
+
+    #[test]
+    fn active_feed_is_only_on_screen_while_focused() {
+        let active = ActiveFeedState::default();
+        active.set_feed(Some("f1".to_string()));
+        assert!(!active.is_viewing("f1"));
+
+        active.set_focused(true);
+        assert!(active.is_viewing("f1"));
+        assert!(!active.is_viewing("f2"));
+
+        active.set_focused(false);
+        assert!(!active.is_viewing("f1"));
+
+        active.set_focused(true);
+        active.set_feed(Some(String::new()));
+        assert!(!active.is_viewing(""));
+    }
+
+    #[test]
+    fn delivered_ids_are_taken_per_feed_and_expire() {
+        let delivered = DeliveredNotificationsState::default();
+        delivered.track("f1", 1, 0);
+        delivered.track("f1", 2, 10);
+        delivered.track("f2", 3, 10);
+        delivered.forget("f1", 1);
+        assert_eq!(delivered.take(Some("f1")), vec![2]);
+        assert!(delivered.take(Some("f1")).is_empty());
+
+        // Tracking prunes whatever outlived the TTL
+        delivered.track("f3", 4, 10 + DELIVERED_TTL_MS + 1);
+        assert_eq!(delivered.take(None), vec![4]);
+        assert!(delivered.delivered().is_empty());
+    }
+
+    #[test]
+    fn summary_names_the_feed_and_opens_it() {
+        let notification = FeedNotification {
+            title: "Alice".to_string(),
+            body: "New post".to_string(),
+            feed_id: Some("f1".to_string()),
+            post_id: Some("p9".to_string()),
+            kind: NavigationKind::Post,
+            feed_name: Some("Climbing".to_string()),
+        };
+        let summary = summary_notification(&notification, 5);
+        assert_eq!(summary.title, "5 new posts in Climbing");
+        assert_eq!(summary.feed_id.as_deref(), Some("f1"));
+        assert_eq!(summary.post_id, None);
+        assert_eq!(summary.kind, NavigationKind::Feed);
+    }
+
+    fn quiet_hours(start: &str, end: &str) -> QuietHours {
+        QuietHours {
+            start: start.to_string(),
+            end: end.to_string(),
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn parse_time_accepts_hh_mm_only() {
+        assert_eq!(parse_time("07:30"), Some(450));
+        assert_eq!(parse_time("0:00"), Some(0));
+        assert_eq!(parse_time("24:00"), None);
+        assert_eq!(parse_time("12:60"), None);
+        assert_eq!(parse_time("noon"), None);
+    }
+
+    #[test]
+    fn quiet_hours_wrap_past_midnight() {
+        let schedule = quiet_hours("22:00", "07:00");
+        assert!(schedule.covers(23 * 60));
+        assert!(schedule.covers(0));
+        assert!(schedule.covers(6 * 60 + 59));
+        assert!(!schedule.covers(7 * 60));
+        assert!(!schedule.covers(12 * 60));
+        assert!(schedule.covers(22 * 60));
+    }
+
+    #[test]
+    fn quiet_hours_same_day_range_and_disabled() {
+        let schedule = quiet_hours("13:00", "14:00");
+        assert!(schedule.covers(13 * 60 + 30));
+        assert!(!schedule.covers(14 * 60));
+
+        assert!(!quiet_hours("09:00", "09:00").covers(9 * 60));
+        assert!(!QuietHours::default().covers(23 * 60));
+    }
+
+    #[test]
+    fn prune_expired_keeps_indefinite_and_future_mutes() {
+        let mut feeds = MutedFeeds::from([
+            ("forever".to_string(), None),
+            ("later".to_string(), Some(2_000)),
+            ("expired".to_string(), Some(1_000)),
+        ]);
+
+        assert!(prune_expired(&mut feeds, 1_000));
+        assert!(feeds.contains_key("forever"));
+        assert!(feeds.contains_key("later"));
+        assert!(!feeds.contains_key("expired"));
+
+        assert!(!prune_expired(&mut feeds, 1_500));
+    }
+
+    #[test]
+    fn muted_feeds_round_trip_as_json_map() {
+        let feeds: MutedFeeds =
+            serde_json::from_str(r#"{"feed-1":null,"feed-2":1700000000000}"#).unwrap();
+        assert_eq!(feeds["feed-1"], None);
+        assert_eq!(feeds["feed-2"], Some(1_700_000_000_000));
+    }
+
+    #[test]
+    fn notification_actions_round_trip_their_keys() {
+        for action in NotificationAction::ALL {
+            assert_eq!(NotificationAction::from_key(action.key()), Some(action));
+            assert_eq!(serde_json::to_value(action).unwrap(), action.key());
+        }
+        assert_eq!(NotificationAction::from_key("default"), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn linux_reports_actions_without_inline_reply() {
+        let capabilities = capabilities();
+        assert!(capabilities.click && capabilities.actions);
+        assert!(!capabilities.inline_reply);
+    }
+
+    #[test]
+    fn push_payload_maps_type_and_defaults() {
+        let payload: PushPayload =
+            serde_json::from_str(r#"{"feedId":"feed-1","postId":"post-1","title":"Bob"}"#)
+                .unwrap();
+        let notification = payload.into_notification();
+        assert_eq!(notification.feed_id.as_deref(), Some("feed-1"));
+        assert_eq!(notification.kind, NavigationKind::Post);
+        assert_eq!(notification.title, "Bob");
+        assert_eq!(notification.body, "New activity");
+    }
+
+    #[test]
+    fn sound_setting_distinguishes_missing_from_silent() {
+        let missing: SoundSetting = serde_json::from_str("{}").unwrap();
+        assert_eq!(missing.sound.as_deref(), Some(DEFAULT_SOUND));
+
+        let silent: SoundSetting = serde_json::from_str(r#"{"sound":null}"#).unwrap();
+        assert!(silent.sound.is_none());
+
+        let round_trip: SoundSetting =
+            serde_json::from_str(&serde_json::to_string(&SoundSetting { sound: None }).unwrap())
+                .unwrap();
+        assert!(round_trip.sound.is_none());
+    }
+
+    #[test]
+    fn bundled_sounds_lists_audio_files_only() {
+        let dir = std::env::temp_dir().join(format!("hush-sounds-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for file in ["chime.wav", "pop.OGG", "chime.mp3", "README.md"] {
+            std::fs::write(dir.join(file), b"").unwrap();
+        }
+
+        assert_eq!(bundled_sounds(&dir), vec!["chime", "pop"]);
+        assert!(bundled_sounds(&dir.join("missing")).is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,444 @@
+//! Outbox for posts written while offline.
+//!
+//! `outbox_enqueue` persists a request to `outbox.json` in the app data dir
+//! and a background task delivers it to the server configured by the
+//! `serverUrl` setting, retrying with exponential backoff while the network
+//! is unreachable. Entries of the same feed are sent strictly in order.
+//!
+//! Each entry carries an idempotency key generated when it is queued and sent
+//! as `Idempotency-Key`, so resending after a crash mid-send can't post twice.
+//! Progress is reported through `outbox-status` events.
+
+use crate::backoff::Backoff;
+use crate::fcm::now_unix_ms;
+use crate::logging::redact;
+use crate::net;
+use crate::settings::SettingsState;
+use crate::storage;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::Notify;
+
+pub(crate) const OUTBOX_FILE: &str = "outbox.json";
+/// Server used when `serverUrl` isn't set
+pub const DEFAULT_SERVER_URL: &str = "https://chat.hushnetwork.social";
+const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(2);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A request to deliver, as passed to `outbox_enqueue`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutboxRequest {
+    pub feed_id: String,
+    /// Path on the server, e.g. `/api/feeds/messages`
+    pub path: String,
+    pub body: Value,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+/// Delivery state of an outbox entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutboxItemState {
+    Queued,
+    Sending,
+    Sent,
+    /// Rejected by the server; won't be retried
+    FailedPermanent,
+}
+
+/// A queued request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutboxEntry {
+    pub id: u64,
+    pub idempotency_key: String,
+    pub request: OutboxRequest,
+    pub state: OutboxItemState,
+    pub attempts: u32,
+    /// Unix timestamp (ms) when the entry was queued
+    pub created_at: u64,
+    pub last_error: Option<String>,
+}
+
+/// Payload of the `outbox-status` event
+#[derive(Debug, Clone, Serialize)]
+pub struct OutboxStatusPayload {
+    pub id: u64,
+    pub feed_id: String,
+    pub state: OutboxItemState,
+    pub error: Option<String>,
+}
+
+/// Result of one delivery attempt
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Delivery {
+    Sent,
+    /// Worth retrying later (network error, timeout, 5xx, 408, 429)
+    Retry(String),
+    Rejected(String),
+}
+
+fn classify_status(status: u16) -> Delivery {
+    match status {
+        200..=299 => Delivery::Sent,
+        408 | 429 | 500..=599 => Delivery::Retry(format!("HTTP {}", status)),
+        _ => Delivery::Rejected(format!("HTTP {}", status)),
+    }
+}
+
+/// Managed state holding the outbox, oldest entry first
+#[derive(Debug, Default)]
+pub struct OutboxState {
+    entries: Mutex<Vec<OutboxEntry>>,
+    /// Signalled when there's something new to send or the network came back
+    wake: Notify,
+    /// Backing file; `None` keeps the outbox in memory only
+    path: Option<PathBuf>,
+}
+
+impl OutboxState {
+    /// Load the outbox from the app data dir.
+    ///
+    /// Entries left `sending` by a crash are queued again; their idempotency
+    /// key lets the server drop the duplicate if the first attempt arrived.
+    pub fn load(app: &AppHandle) -> Self {
+        let path = storage::data_file(app, OUTBOX_FILE).ok();
+        let mut entries: Vec<OutboxEntry> = path
+            .as_deref()
+            .and_then(storage::read_json)
+            .unwrap_or_default();
+        requeue_interrupted(&mut entries);
+        Self {
+            entries: Mutex::new(entries),
+            wake: Notify::new(),
+            path,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<OutboxEntry>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn persist(&self, entries: &[OutboxEntry]) -> Result<(), String> {
+        match &self.path {
+            Some(path) => storage::write_json_atomic(path, entries),
+            None => Ok(()),
+        }
+    }
+
+    /// Queue a request, persisting it before returning.
+    pub fn enqueue(&self, request: OutboxRequest) -> Result<OutboxEntry, String> {
+        let mut entries = self.lock();
+        let entry = OutboxEntry {
+            id: entries.iter().map(|entry| entry.id).max().unwrap_or(0) + 1,
+            idempotency_key: uuid::Uuid::new_v4().to_string(),
+            request,
+            state: OutboxItemState::Queued,
+            attempts: 0,
+            created_at: now_unix_ms(),
+            last_error: None,
+        };
+        entries.push(entry.clone());
+        if let Err(e) = self.persist(&entries) {
+            entries.pop();
+            return Err(e);
+        }
+        self.wake.notify_one();
+        Ok(entry)
+    }
+
+    pub fn list(&self) -> Vec<OutboxEntry> {
+        self.lock().clone()
+    }
+
+    /// Remove an entry that isn't being sent. Returns whether it was removed.
+    pub fn cancel(&self, id: u64) -> Result<bool, String> {
+        let mut entries = self.lock();
+        let Some(index) = entries.iter().position(|entry| entry.id == id) else {
+            return Ok(false);
+        };
+        if entries[index].state == OutboxItemState::Sending {
+            return Err("Entry is being sent and can't be cancelled".to_string());
+        }
+        entries.remove(index);
+        self.persist(&entries)?;
+        Ok(true)
+    }
+
+    /// Drop every entry, including one being sent, and delete the backing
+    /// file. Returns the bytes freed.
+    pub fn clear(&self) -> Result<u64, String> {
+        let mut entries = self.lock();
+        entries.clear();
+        match &self.path {
+            Some(path) => storage::remove_all(path),
+            None => Ok(0),
+        }
+    }
+
+    /// Whether an entry is being sent right now.
+    pub fn is_sending(&self) -> bool {
+        self.lock()
+            .iter()
+            .any(|entry| entry.state == OutboxItemState::Sending)
+    }
+
+    /// Retry queued entries now, e.g. when connectivity returns.
+    pub fn resume(&self) {
+        self.wake.notify_one();
+    }
+
+    /// Mark the next sendable entry as sending and return it.
+    fn start_next(&self) -> Option<OutboxEntry> {
+        let mut entries = self.lock();
+        let index = next_sendable(&entries)?;
+        entries[index].state = OutboxItemState::Sending;
+        entries[index].attempts += 1;
+        if let Err(e) = self.persist(&entries) {
+            log::warn!("Failed to save outbox: {}", e);
+        }
+        Some(entries[index].clone())
+    }
+
+    /// Record the outcome of sending entry `id`; sent entries are dropped.
+    fn finish(&self, id: u64, delivery: &Delivery) -> Option<OutboxStatusPayload> {
+        let mut entries = self.lock();
+        let index = entries.iter().position(|entry| entry.id == id)?;
+        let (state, error) = match delivery {
+            Delivery::Sent => (OutboxItemState::Sent, None),
+            Delivery::Retry(e) => (OutboxItemState::Queued, Some(e.clone())),
+            Delivery::Rejected(e) => (OutboxItemState::FailedPermanent, Some(e.clone())),
+        };
+        let payload = OutboxStatusPayload {
+            id,
+            feed_id: entries[index].request.feed_id.clone(),
+            state,
+            error: error.clone(),
+        };
+        if state == OutboxItemState::Sent {
+            entries.remove(index);
+        } else {
+            entries[index].state = state;
+            entries[index].last_error = error;
+        }
+        if let Err(e) = self.persist(&entries) {
+            log::warn!("Failed to save outbox: {}", e);
+        }
+        Some(payload)
+    }
+}
+
+fn requeue_interrupted(entries: &mut [OutboxEntry]) {
+    for entry in entries {
+        if entry.state == OutboxItemState::Sending {
+            entry.state = OutboxItemState::Queued;
+        }
+    }
+}
+
+/// Index of the first queued entry with no unsent entry before it in the same feed.
+fn next_sendable(entries: &[OutboxEntry]) -> Option<usize> {
+    let mut blocked = HashSet::new();
+    for (index, entry) in entries.iter().enumerate() {
+        let feed = entry.request.feed_id.as_str();
+        match entry.state {
+            OutboxItemState::Queued if !blocked.contains(feed) => return Some(index),
+            OutboxItemState::Queued | OutboxItemState::Sending => {
+                blocked.insert(feed);
+            }
+            OutboxItemState::Sent | OutboxItemState::FailedPermanent => {}
+        }
+    }
+    None
+}
+
+fn emit_status(app: &AppHandle, payload: OutboxStatusPayload) {
+    let _ = app.emit("outbox-status", payload);
+}
+
+fn server_url(app: &AppHandle) -> String {
+    app.state::<SettingsState>()
+        .get()
+        .server_url
+        .unwrap_or_else(|| DEFAULT_SERVER_URL.to_string())
+}
+
+async fn deliver(client: &reqwest::Client, server: &str, entry: &OutboxEntry) -> Delivery {
+    let url = format!("{}{}", server.trim_end_matches('/'), entry.request.path);
+    let mut request = client
+        .post(&url)
+        .timeout(REQUEST_TIMEOUT)
+        .header("Idempotency-Key", &entry.idempotency_key)
+        .json(&entry.request.body);
+    for (name, value) in &entry.request.headers {
+        request = request.header(name, value);
+    }
+    match request.send().await {
+        Ok(response) => classify_status(response.status().as_u16()),
+        Err(e) if e.is_builder() => Delivery::Rejected(e.to_string()),
+        Err(e) => Delivery::Retry(e.to_string()),
+    }
+}
+
+async fn run(app: AppHandle) {
+    let state = app.state::<OutboxState>();
+    let mut backoff = Backoff::new(INITIAL_RETRY_DELAY, MAX_RETRY_DELAY);
+
+    loop {
+        let Some(entry) = state.start_next() else {
+            state.wake.notified().await;
+            continue;
+        };
+        emit_status(
+            &app,
+            OutboxStatusPayload {
+                id: entry.id,
+                feed_id: entry.request.feed_id.clone(),
+                state: OutboxItemState::Sending,
+                error: None,
+            },
+        );
+
+        let delivery = deliver(&net::client(&app), &server_url(&app), &entry).await;
+        let retry = matches!(delivery, Delivery::Retry(_));
+        match &delivery {
+            Delivery::Sent => backoff.reset(),
+            Delivery::Retry(e) => {
+                log::info!("Outbox entry {} will be retried: {}", entry.id, redact(e))
+            }
+            Delivery::Rejected(e) => {
+                log::warn!("Outbox entry {} rejected: {}", entry.id, redact(e))
+            }
+        }
+        if let Some(payload) = state.finish(entry.id, &delivery) {
+            emit_status(&app, payload);
+        }
+
+        if retry {
+            tokio::select! {
+                _ = tokio::time::sleep(backoff.next_delay()) => {}
+                _ = state.wake.notified() => backoff.reset(),
+            }
+        }
+    }
+}
+
+/// Spawn the delivery task. Called from `setup` after [`OutboxState`] is managed.
+pub fn init(app: &AppHandle) {
+    tauri::async_runtime::spawn(run(app.clone()));
+}
+
+/// Queue a request (JSON [`OutboxRequest`]) for delivery; returns the new entry.
+#[tauri::command]
+pub fn outbox_enqueue(
+    state: State<'_, OutboxState>,
+    request_json: String,
+) -> Result<OutboxEntry, String> {
+    let request: OutboxRequest =
+        serde_json::from_str(&request_json).map_err(|e| format!("Invalid request: {}", e))?;
+    if !request.path.starts_with('/') {
+        return Err("Request path must start with /".to_string());
+    }
+    state.enqueue(request)
+}
+
+/// Entries not yet delivered, oldest first.
+#[tauri::command]
+pub fn outbox_list(state: State<'_, OutboxState>) -> Vec<OutboxEntry> {
+    state.list()
+}
+
+/// Drop a queued or failed entry. Returns false if there's no entry with `id`.
+#[tauri::command]
+pub fn outbox_cancel(state: State<'_, OutboxState>, id: u64) -> Result<bool, String> {
+    state.cancel(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn request(feed_id: &str) -> OutboxRequest {
+        OutboxRequest {
+            feed_id: feed_id.to_string(),
+            path: "/api/feeds/messages".to_string(),
+            body: json!({"content": "hi"}),
+            headers: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn entries_get_unique_ids_and_keys() {
+        let state = OutboxState::default();
+        let a = state.enqueue(request("f")).unwrap();
+        let b = state.enqueue(request("f")).unwrap();
+        assert_ne!(a.id, b.id);
+        assert_ne!(a.idempotency_key, b.idempotency_key);
+        assert_eq!(state.list().len(), 2);
+    }
+
+    #[test]
+    fn feeds_are_sent_in_order() {
+        let state = OutboxState::default();
+        let a1 = state.enqueue(request("a")).unwrap();
+        let a2 = state.enqueue(request("a")).unwrap();
+        let b1 = state.enqueue(request("b")).unwrap();
+
+        // While a1 is sending, a2 waits but b1 can go
+        assert_eq!(state.start_next().unwrap().id, a1.id);
+        assert_eq!(state.start_next().unwrap().id, b1.id);
+        assert!(state.start_next().is_none());
+
+        // A retry keeps a1 at the head of its feed
+        state.finish(a1.id, &Delivery::Retry("offline".into()));
+        assert_eq!(state.start_next().unwrap().id, a1.id);
+        state.finish(a1.id, &Delivery::Sent);
+        assert_eq!(state.start_next().unwrap().id, a2.id);
+    }
+
+    #[test]
+    fn rejected_entries_stay_until_cancelled() {
+        let state = OutboxState::default();
+        let entry = state.enqueue(request("f")).unwrap();
+        state.start_next();
+        let payload = state
+            .finish(entry.id, &Delivery::Rejected("HTTP 400".into()))
+            .unwrap();
+        assert_eq!(payload.state, OutboxItemState::FailedPermanent);
+        assert!(state.start_next().is_none());
+
+        assert!(state.cancel(entry.id).unwrap());
+        assert!(state.list().is_empty());
+        assert!(!state.cancel(entry.id).unwrap());
+    }
+
+    #[test]
+    fn interrupted_sends_are_requeued_with_the_same_key() {
+        let state = OutboxState::default();
+        let entry = state.enqueue(request("f")).unwrap();
+        state.start_next();
+        assert!(state.cancel(entry.id).is_err());
+
+        let mut entries = state.list();
+        requeue_interrupted(&mut entries);
+        assert_eq!(entries[0].state, OutboxItemState::Queued);
+        assert_eq!(entries[0].idempotency_key, entry.idempotency_key);
+    }
+
+    #[test]
+    fn status_codes_are_classified() {
+        assert_eq!(classify_status(201), Delivery::Sent);
+        assert!(matches!(classify_status(503), Delivery::Retry(_)));
+        assert!(matches!(classify_status(429), Delivery::Retry(_)));
+        assert!(matches!(classify_status(400), Delivery::Rejected(_)));
+        assert!(matches!(classify_status(401), Delivery::Rejected(_)));
+    }
+}
@@ -0,0 +1,251 @@
+//! Actions taken from a notification while the app wasn't looking, such as
+//! an Android inline reply, waiting for the frontend to carry them out.
+//!
+//! On Android `ReplyReceiver` queues replies in `PendingActionStore`
+//! (SharedPreferences), since the Rust side may not be running. They are
+//! copied into `pending-actions.json` in the app data dir on startup and on
+//! every `get_pending_actions`, and only then removed on the Kotlin side, so
+//! a crash between the two steps can't lose a reply. The frontend sends the
+//! reply and calls `ack_pending_action`; acknowledged ids are remembered
+//! (the last [`MAX_ACKED`]) so an entry copied twice is never handed out
+//! again.
+
+use crate::error::CommandError;
+use crate::lock::LockState;
+use crate::storage;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+use tauri::{AppHandle, Manager, State};
+
+pub(crate) const PENDING_ACTIONS_FILE: &str = "pending-actions.json";
+/// Number of acknowledged ids remembered
+const MAX_ACKED: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PendingActionKind {
+    InlineReply,
+}
+
+/// A queued notification action
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingAction {
+    pub id: String,
+    pub kind: PendingActionKind,
+    pub feed_id: String,
+    #[serde(default)]
+    pub post_id: Option<String>,
+    pub text: String,
+    /// Unix timestamp (ms) when the action was taken
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PendingActions {
+    #[serde(default)]
+    actions: Vec<PendingAction>,
+    /// Recently acknowledged ids, oldest first
+    #[serde(default)]
+    acked: Vec<String>,
+}
+
+impl PendingActions {
+    /// Add the entries of `incoming` that aren't queued or acknowledged yet.
+    /// Returns whether anything was added.
+    #[cfg_attr(not(target_os = "android"), allow(dead_code))]
+    fn merge(&mut self, incoming: Vec<PendingAction>) -> bool {
+        let before = self.actions.len();
+        for action in incoming {
+            let known = self.actions.iter().any(|queued| queued.id == action.id)
+                || self.acked.contains(&action.id);
+            if !known {
+                self.actions.push(action);
+            }
+        }
+        self.actions.sort_by_key(|action| action.timestamp);
+        self.actions.len() != before
+    }
+
+    /// Remove the entry `id`. Returns false if it wasn't queued.
+    fn ack(&mut self, id: &str) -> bool {
+        let before = self.actions.len();
+        self.actions.retain(|action| action.id != id);
+        if !self.acked.iter().any(|acked| acked == id) {
+            self.acked.push(id.to_string());
+            let excess = self.acked.len().saturating_sub(MAX_ACKED);
+            self.acked.drain(..excess);
+        }
+        self.actions.len() != before
+    }
+}
+
+/// Managed state holding the queued actions
+#[derive(Debug, Default)]
+pub struct PendingActionsState {
+    inner: Mutex<PendingActions>,
+    /// Backing file; `None` keeps the queue in memory only
+    path: Option<PathBuf>,
+}
+
+impl PendingActionsState {
+    /// Load the queue from the app data dir.
+    pub fn load(app: &AppHandle) -> Self {
+        let path = storage::data_file(app, PENDING_ACTIONS_FILE).ok();
+        let inner = path
+            .as_deref()
+            .and_then(storage::read_json)
+            .unwrap_or_default();
+        Self {
+            inner: Mutex::new(inner),
+            path,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, PendingActions> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn persist(&self, inner: &PendingActions) -> Result<(), String> {
+        match &self.path {
+            Some(path) => storage::write_json_atomic(path, inner),
+            None => Ok(()),
+        }
+    }
+
+    /// Drop every queued action and delete the backing file. Returns the
+    /// bytes freed.
+    pub fn clear(&self) -> Result<u64, String> {
+        let mut inner = self.lock();
+        *inner = PendingActions::default();
+        match &self.path {
+            Some(path) => storage::remove_all(path),
+            None => Ok(0),
+        }
+    }
+
+    /// Queued actions, oldest first.
+    pub fn entries(&self) -> Vec<PendingAction> {
+        self.lock().actions.clone()
+    }
+
+    /// Store `incoming` durably. Only once this returns Ok may the source
+    /// forget them.
+    #[cfg_attr(not(target_os = "android"), allow(dead_code))]
+    fn import(&self, incoming: Vec<PendingAction>) -> Result<(), String> {
+        let mut inner = self.lock();
+        if inner.merge(incoming) {
+            self.persist(&inner)?;
+        }
+        Ok(())
+    }
+
+    pub fn ack(&self, id: &str) -> Result<bool, String> {
+        let mut inner = self.lock();
+        let removed = inner.ack(id);
+        self.persist(&inner)?;
+        Ok(removed)
+    }
+}
+
+/// Move the actions queued by the Android side into the Rust store.
+#[cfg(target_os = "android")]
+fn import_native(state: &PendingActionsState) -> Result<(), String> {
+    let json = crate::android::pending_actions_peek()?;
+    let incoming: Vec<PendingAction> = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+    if incoming.is_empty() {
+        return Ok(());
+    }
+    let ids: Vec<&str> = incoming.iter().map(|action| action.id.as_str()).collect();
+    let ids = serde_json::to_string(&ids).map_err(|e| e.to_string())?;
+    state.import(incoming)?;
+    crate::android::pending_actions_remove(&ids)
+}
+
+#[cfg(not(target_os = "android"))]
+fn import_native(_state: &PendingActionsState) -> Result<(), String> {
+    Ok(())
+}
+
+/// Pick up actions queued while the app wasn't running. Called from `setup`.
+pub fn init(app: &AppHandle) {
+    if let Err(e) = import_native(&app.state::<PendingActionsState>()) {
+        log::warn!("Failed to import pending notification actions: {}", e);
+    }
+}
+
+/// Every queued notification action, oldest first. Each stays queued until
+/// `ack_pending_action` is called with its id. Fails while the app is locked.
+#[tauri::command]
+pub fn get_pending_actions(
+    state: State<'_, PendingActionsState>,
+    lock: State<'_, LockState>,
+) -> Result<Vec<PendingAction>, CommandError> {
+    lock.ensure_unlocked()?;
+    if let Err(e) = import_native(&state) {
+        log::warn!("Failed to import pending notification actions: {}", e);
+    }
+    Ok(state.entries())
+}
+
+/// Drop a handled action. Returns false if it wasn't queued.
+#[tauri::command]
+pub fn ack_pending_action(
+    state: State<'_, PendingActionsState>,
+    id: String,
+) -> Result<bool, CommandError> {
+    Ok(state.ack(&id)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reply(id: &str, timestamp: u64) -> PendingAction {
+        PendingAction {
+            id: id.to_string(),
+            kind: PendingActionKind::InlineReply,
+            feed_id: "feed-1".to_string(),
+            post_id: None,
+            text: "on my way".to_string(),
+            timestamp,
+        }
+    }
+
+    #[test]
+    fn kotlin_entries_deserialize() {
+        let json = r#"[{"id":"a","kind":"inline_reply","feed_id":"f","post_id":null,"text":"hi","timestamp":5}]"#;
+        let actions: Vec<PendingAction> = serde_json::from_str(json).unwrap();
+        assert_eq!(actions[0].kind, PendingActionKind::InlineReply);
+        assert_eq!(actions[0].text, "hi");
+    }
+
+    #[test]
+    fn merging_twice_does_not_duplicate() {
+        let mut queue = PendingActions::default();
+        assert!(queue.merge(vec![reply("b", 2), reply("a", 1)]));
+        assert!(!queue.merge(vec![reply("a", 1)]));
+        let ids: Vec<&str> = queue.actions.iter().map(|a| a.id.as_str()).collect();
+        assert_eq!(ids, ["a", "b"]);
+    }
+
+    #[test]
+    fn acknowledged_entries_are_not_imported_again() {
+        let mut queue = PendingActions::default();
+        queue.merge(vec![reply("a", 1)]);
+        assert!(queue.ack("a"));
+        assert!(!queue.ack("a"));
+        assert!(!queue.merge(vec![reply("a", 1)]));
+        assert!(queue.actions.is_empty());
+    }
+
+    #[test]
+    fn acknowledged_ids_are_bounded() {
+        let mut queue = PendingActions::default();
+        for i in 0..MAX_ACKED + 10 {
+            queue.ack(&i.to_string());
+        }
+        assert_eq!(queue.acked.len(), MAX_ACKED);
+        assert_eq!(queue.acked[0], "10");
+    }
+}
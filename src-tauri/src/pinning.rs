@@ -0,0 +1,619 @@
+//! SPKI certificate pinning for hosts the app talks to.
+//!
+//! Pins are SHA-256 hashes of a certificate's SubjectPublicKeyInfo, base64
+//! encoded (optionally prefixed `sha256/`), kept per host in the
+//! `pinnedCertificates` setting. A connection to a pinned host is accepted
+//! only if normal certificate validation passes and one of the certificates
+//! on the validated path (end entity, intermediates, trust anchor) matches a
+//! pin. Extra certificates the server sends that aren't on that path never
+//! count, so a server can't satisfy a pin by appending the (public) pinned
+//! certificate to a chain from another CA. On mismatch a `pin-violation`
+//! event carries the path's fingerprints; hosts in `report` mode then
+//! proceed with a warning, `enforce` mode (the default) refuses the
+//! connection.
+//!
+//! [`PinningVerifier`] is installed in the shared HTTP client and WebSocket
+//! connector by [`crate::net`] whenever any pins are set.
+
+use crate::net;
+use crate::settings::{self, SettingsState};
+use base64::Engine;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::WebPkiServerVerifier;
+use rustls::pki_types::{CertificateDer, ServerName, TrustAnchor, UnixTime};
+use rustls::{
+    ClientConfig, DigitallySignedStruct, Error as TlsError, RootCertStore, SignatureScheme,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+
+/// What happens when a host's pins don't match
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PinMode {
+    #[default]
+    Enforce,
+    /// Warn (and emit `pin-violation`) but allow the connection
+    Report,
+}
+
+/// Pins for one host
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HostPins {
+    pub spki_hashes: Vec<String>,
+    #[serde(default)]
+    pub mode: PinMode,
+}
+
+/// Pinned hosts, keyed by lowercase host name
+pub type PinSet = BTreeMap<String, HostPins>;
+
+/// Payload of the `pin-violation` event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PinViolation {
+    pub host: String,
+    /// SPKI pins of the validated path, end-entity first, in pin format
+    pub presented: Vec<String>,
+    /// Whether the connection was refused
+    pub blocked: bool,
+}
+
+/// Normalise a pin to bare base64 of a 32-byte hash.
+fn normalize_pin(pin: &str) -> Result<String, String> {
+    let encoded = pin.trim().trim_start_matches("sha256/");
+    let hash = base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|_| format!("Pin is not base64: {}", pin))?;
+    if hash.len() != 32 {
+        return Err(format!("Pin is not a SHA-256 hash: {}", pin));
+    }
+    Ok(encoded.to_string())
+}
+
+/// Normalise every host and pin of `pins`, as `set_pinned_certificates`
+/// does for one host. Fails on an invalid pin or a host without pins.
+pub(crate) fn normalize_pins(pins: PinSet) -> Result<PinSet, String> {
+    pins.into_iter()
+        .map(|(host, host_pins)| {
+            if host_pins.spki_hashes.is_empty() {
+                return Err(format!("No pins for {}", host));
+            }
+            let spki_hashes = host_pins
+                .spki_hashes
+                .iter()
+                .map(|pin| normalize_pin(pin))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok((
+                host.trim().to_ascii_lowercase(),
+                HostPins {
+                    spki_hashes,
+                    mode: host_pins.mode,
+                },
+            ))
+        })
+        .collect()
+}
+
+/// The pin of a DER certificate, i.e. base64(SHA-256(SubjectPublicKeyInfo)).
+fn spki_pin(cert: &CertificateDer<'_>) -> Option<String> {
+    let (_, parsed) = x509_parser::parse_x509_certificate(cert.as_ref()).ok()?;
+    let hash = Sha256::digest(parsed.public_key().raw);
+    Some(base64::engine::general_purpose::STANDARD.encode(hash))
+}
+
+/// The pin of a trust anchor. Anchors keep the SubjectPublicKeyInfo without
+/// its outer SEQUENCE, so the header is put back before hashing.
+fn anchor_pin(anchor: &TrustAnchor<'_>) -> String {
+    let content = anchor.subject_public_key_info.as_ref();
+    let mut spki = vec![0x30];
+    match content.len() {
+        len @ 0..=0x7f => spki.push(len as u8),
+        len => {
+            let bytes: Vec<u8> = len
+                .to_be_bytes()
+                .into_iter()
+                .skip_while(|byte| *byte == 0)
+                .collect();
+            spki.push(0x80 | bytes.len() as u8);
+            spki.extend(bytes);
+        }
+    }
+    spki.extend_from_slice(content);
+    base64::engine::general_purpose::STANDARD.encode(Sha256::digest(&spki))
+}
+
+type ViolationHandler = Arc<dyn Fn(PinViolation) + Send + Sync>;
+
+/// Certificate verifier that runs normal WebPKI validation, then checks pins
+pub struct PinningVerifier {
+    inner: Arc<WebPkiServerVerifier>,
+    /// The roots again, to rebuild the validated path for the pin check
+    anchors: Vec<TrustAnchor<'static>>,
+    pins: PinSet,
+    on_violation: ViolationHandler,
+}
+
+impl std::fmt::Debug for PinningVerifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PinningVerifier")
+            .field("pins", &self.pins)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PinningVerifier {
+    fn new(
+        roots: RootCertStore,
+        pins: PinSet,
+        on_violation: ViolationHandler,
+    ) -> Result<Self, String> {
+        let anchors = roots.roots.clone();
+        let inner = WebPkiServerVerifier::builder_with_provider(
+            Arc::new(roots),
+            Arc::new(rustls::crypto::ring::default_provider()),
+        )
+        .build()
+        .map_err(|e| e.to_string())?;
+        Ok(Self {
+            inner,
+            anchors,
+            pins,
+            on_violation,
+        })
+    }
+
+    /// Pins of the path webpki validates from `end_entity` to a root, using
+    /// only the `intermediates` it actually chains through.
+    fn validated_pins(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        now: UnixTime,
+    ) -> Result<Vec<String>, TlsError> {
+        let invalid = |e: webpki::Error| TlsError::General(format!("certificate path: {}", e));
+        let cert = webpki::EndEntityCert::try_from(end_entity).map_err(invalid)?;
+        let path = cert
+            .verify_for_usage(
+                rustls::crypto::ring::default_provider()
+                    .signature_verification_algorithms
+                    .all,
+                &self.anchors,
+                intermediates,
+                now,
+                webpki::KeyUsage::server_auth(),
+                None,
+                None,
+            )
+            .map_err(invalid)?;
+        let mut pins: Vec<String> = spki_pin(end_entity).into_iter().collect();
+        pins.extend(
+            path.intermediate_certificates()
+                .filter_map(|cert| spki_pin(&cert.der())),
+        );
+        pins.push(anchor_pin(path.anchor()));
+        Ok(pins)
+    }
+}
+
+impl ServerCertVerifier for PinningVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, TlsError> {
+        let verified = self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        )?;
+
+        let host = match server_name {
+            ServerName::DnsName(name) => name.as_ref().to_ascii_lowercase(),
+            other => other.to_str().into_owned(),
+        };
+        let Some(host_pins) = self.pins.get(&host) else {
+            return Ok(verified);
+        };
+
+        let presented = self.validated_pins(end_entity, intermediates, now)?;
+        if presented
+            .iter()
+            .any(|pin| host_pins.spki_hashes.contains(pin))
+        {
+            return Ok(verified);
+        }
+
+        let blocked = host_pins.mode == PinMode::Enforce;
+        log::warn!(
+            "Certificate pin mismatch for {} ({})",
+            host,
+            if blocked { "blocked" } else { "report only" }
+        );
+        (self.on_violation)(PinViolation {
+            host,
+            presented,
+            blocked,
+        });
+        if blocked {
+            Err(TlsError::General("certificate pin mismatch".to_string()))
+        } else {
+            Ok(verified)
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, TlsError> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, TlsError> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+/// Current pins from settings.
+pub fn pins<R: Runtime>(app: &AppHandle<R>) -> PinSet {
+    app.try_state::<SettingsState>()
+        .and_then(|settings| settings.get().pinned_certificates)
+        .unwrap_or_default()
+}
+
+/// A TLS config enforcing `pins`, with the given ALPN protocols.
+pub fn tls_config<R: Runtime>(
+    app: &AppHandle<R>,
+    pins: PinSet,
+    alpn: &[&[u8]],
+) -> Result<ClientConfig, String> {
+    let roots = webpki_roots::TLS_SERVER_ROOTS.to_vec();
+    // Tests also trust the CA their local server's certificate comes from
+    #[cfg(test)]
+    let roots = [roots, tests::TRUSTED_CAS.lock().unwrap().clone()].concat();
+    let roots = RootCertStore { roots };
+    let handle = app.clone();
+    let verifier = PinningVerifier::new(
+        roots,
+        pins,
+        Arc::new(move |violation| {
+            let _ = handle.emit("pin-violation", violation);
+        }),
+    )?;
+    let mut config =
+        ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(|e| e.to_string())?
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(verifier))
+            .with_no_client_auth();
+    config.alpn_protocols = alpn.iter().map(|protocol| protocol.to_vec()).collect();
+    Ok(config)
+}
+
+fn save_pins(app: &AppHandle, pins: &PinSet) -> Result<(), String> {
+    let value = if pins.is_empty() {
+        serde_json::Value::Null
+    } else {
+        serde_json::to_value(pins).map_err(|e| e.to_string())?
+    };
+    settings::set(app, "pinnedCertificates", value)?;
+    net::rebuild(app);
+    Ok(())
+}
+
+/// Pin `host` to certificates whose SPKI SHA-256 (base64) is in `spki_hashes`.
+///
+/// With `grace` set, mismatches are reported but not blocked.
+#[tauri::command]
+pub fn set_pinned_certificates(
+    app: AppHandle,
+    host: String,
+    spki_hashes: Vec<String>,
+    grace: Option<bool>,
+) -> Result<(), String> {
+    if spki_hashes.is_empty() {
+        return Err("At least one pin is required".to_string());
+    }
+    let spki_hashes = spki_hashes
+        .iter()
+        .map(|pin| normalize_pin(pin))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mode = if grace.unwrap_or(false) {
+        PinMode::Report
+    } else {
+        PinMode::Enforce
+    };
+
+    let mut pins = pins(&app);
+    pins.insert(
+        host.trim().to_ascii_lowercase(),
+        HostPins { spki_hashes, mode },
+    );
+    save_pins(&app, &pins)
+}
+
+/// Remove the pins for `host`.
+#[tauri::command]
+pub fn clear_pinned_certificates(app: AppHandle, host: String) -> Result<(), String> {
+    let mut pins = pins(&app);
+    if pins.remove(&host.trim().to_ascii_lowercase()).is_none() {
+        return Ok(());
+    }
+    save_pins(&app, &pins)
+}
+
+#[tauri::command]
+pub fn get_pinned_certificates(app: AppHandle) -> PinSet {
+    pins(&app)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rcgen::{BasicConstraints, CertificateParams, IsCa, KeyPair};
+    use rustls::pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer};
+    use std::io::{Read, Write};
+    use std::sync::Mutex;
+    use tauri::Listener;
+
+    /// CAs [`tls_config`] trusts on top of the webpki roots
+    pub(super) static TRUSTED_CAS: Mutex<Vec<TrustAnchor<'static>>> = Mutex::new(Vec::new());
+
+    struct TestChain {
+        ca: CertificateDer<'static>,
+        leaf: CertificateDer<'static>,
+        leaf_key: PrivatePkcs8KeyDer<'static>,
+        leaf_pin: String,
+        ca_pin: String,
+    }
+
+    fn pin_of(key: &KeyPair) -> String {
+        base64::engine::general_purpose::STANDARD.encode(Sha256::digest(key.public_key_der()))
+    }
+
+    /// A self-signed CA and a `localhost` certificate it issued.
+    fn test_chain() -> TestChain {
+        let ca_key = KeyPair::generate().unwrap();
+        let mut ca_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca = ca_params.self_signed(&ca_key).unwrap();
+
+        let leaf_key = KeyPair::generate().unwrap();
+        let leaf = CertificateParams::new(vec!["localhost".to_string()])
+            .unwrap()
+            .signed_by(&leaf_key, &ca, &ca_key)
+            .unwrap();
+
+        TestChain {
+            ca: ca.der().clone(),
+            leaf: leaf.der().clone(),
+            leaf_key: PrivatePkcs8KeyDer::from(leaf_key.serialize_der()),
+            leaf_pin: pin_of(&leaf_key),
+            ca_pin: pin_of(&ca_key),
+        }
+    }
+
+    fn verify(
+        chain: &TestChain,
+        pins: PinSet,
+        intermediates: &[CertificateDer<'static>],
+    ) -> (Result<ServerCertVerified, TlsError>, Vec<PinViolation>) {
+        let mut roots = RootCertStore::empty();
+        roots.add(chain.ca.clone()).unwrap();
+        let violations = Arc::new(Mutex::new(Vec::new()));
+        let sink = violations.clone();
+        let verifier = PinningVerifier::new(
+            roots,
+            pins,
+            Arc::new(move |violation| sink.lock().unwrap().push(violation)),
+        )
+        .unwrap();
+
+        let result = verifier.verify_server_cert(
+            &chain.leaf,
+            intermediates,
+            &ServerName::try_from("localhost").unwrap(),
+            &[],
+            UnixTime::now(),
+        );
+        let violations = violations.lock().unwrap().clone();
+        (result, violations)
+    }
+
+    fn pin_set(pin: &str, mode: PinMode) -> PinSet {
+        BTreeMap::from([(
+            "localhost".to_string(),
+            HostPins {
+                spki_hashes: vec![pin.to_string()],
+                mode,
+            },
+        )])
+    }
+
+    /// Serve `connections` TLS connections on a local port with the chain's
+    /// `localhost` certificate, answering each request with `200 OK`.
+    fn serve_tls(
+        chain: &TestChain,
+        connections: usize,
+    ) -> (std::net::SocketAddr, std::thread::JoinHandle<()>) {
+        let config = rustls::ServerConfig::builder_with_provider(Arc::new(
+            rustls::crypto::ring::default_provider(),
+        ))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_no_client_auth()
+        .with_single_cert(
+            vec![chain.leaf.clone()],
+            PrivateKeyDer::Pkcs8(chain.leaf_key.clone_key()),
+        )
+        .unwrap();
+        let config = Arc::new(config);
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            for _ in 0..connections {
+                let (stream, _) = listener.accept().unwrap();
+                let connection = rustls::ServerConnection::new(config.clone()).unwrap();
+                let mut tls = rustls::StreamOwned::new(connection, stream);
+                let mut request = [0u8; 4096];
+                // A client that refuses the certificate hangs up in the handshake
+                if tls.read(&mut request).is_ok() {
+                    let _ = tls.write_all(
+                        b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+                    );
+                    tls.conn.send_close_notify();
+                    let _ = tls.flush();
+                }
+            }
+        });
+        (addr, server)
+    }
+
+    #[test]
+    fn pins_apply_to_the_shared_client() {
+        let chain = test_chain();
+        TRUSTED_CAS.lock().unwrap().push(
+            webpki::anchor_from_trusted_cert(&chain.ca)
+                .unwrap()
+                .to_owned(),
+        );
+        let app = tauri::test::mock_app();
+        app.manage(SettingsState::default());
+        let violations = Arc::new(Mutex::new(Vec::new()));
+        let sink = violations.clone();
+        app.listen_any("pin-violation", move |event| {
+            let violation: serde_json::Value = serde_json::from_str(event.payload()).unwrap();
+            sink.lock().unwrap().push(violation);
+        });
+
+        let (addr, server) = serve_tls(&chain, 3);
+        let get = |pins: PinSet| {
+            let pins = serde_json::to_value(pins).unwrap();
+            app.state::<SettingsState>()
+                .set("pinnedCertificates", pins)
+                .unwrap();
+            let client = net::builder(app.handle())
+                .unwrap()
+                .no_proxy()
+                .resolve("localhost", addr)
+                .build()
+                .unwrap();
+            let url = format!("https://localhost:{}/", addr.port());
+            tauri::async_runtime::block_on(client.get(url).send())
+        };
+
+        let matching = get(pin_set(&chain.leaf_pin, PinMode::Enforce)).unwrap();
+        assert_eq!(matching.status(), 200);
+        assert!(violations.lock().unwrap().is_empty());
+
+        let wrong = base64::engine::general_purpose::STANDARD.encode([0u8; 32]);
+        assert!(get(pin_set(&wrong, PinMode::Enforce)).is_err());
+        let reported = get(pin_set(&wrong, PinMode::Report)).unwrap();
+        assert_eq!(reported.status(), 200);
+        server.join().unwrap();
+
+        let violations = violations.lock().unwrap().clone();
+        assert_eq!(violations.len(), 2);
+        assert_eq!(violations[0]["host"], "localhost");
+        assert_eq!(violations[0]["blocked"], true);
+        assert_eq!(violations[1]["blocked"], false);
+    }
+
+    #[test]
+    fn spki_pin_matches_the_public_key() {
+        let chain = test_chain();
+        assert_eq!(spki_pin(&chain.leaf), Some(chain.leaf_pin.clone()));
+    }
+
+    #[test]
+    fn matching_pin_is_accepted() {
+        let chain = test_chain();
+        let (result, violations) = verify(&chain, pin_set(&chain.leaf_pin, PinMode::Enforce), &[]);
+        assert!(result.is_ok());
+        assert!(violations.is_empty());
+
+        // The root the path ends at can carry the pin too
+        let (result, _) = verify(&chain, pin_set(&chain.ca_pin, PinMode::Enforce), &[]);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn pinned_cert_appended_outside_the_path_is_rejected() {
+        let chain = test_chain();
+        let pinned = test_chain();
+        let (result, violations) = verify(
+            &chain,
+            pin_set(&pinned.leaf_pin, PinMode::Enforce),
+            &[pinned.leaf.clone(), pinned.ca.clone()],
+        );
+        assert!(result.is_err());
+        assert_eq!(
+            violations[0].presented,
+            vec![chain.leaf_pin.clone(), chain.ca_pin.clone()]
+        );
+    }
+
+    #[test]
+    fn mismatch_is_blocked_when_enforced() {
+        let chain = test_chain();
+        let wrong = base64::engine::general_purpose::STANDARD.encode([0u8; 32]);
+        let (result, violations) = verify(&chain, pin_set(&wrong, PinMode::Enforce), &[]);
+        assert!(result.is_err());
+        assert_eq!(violations.len(), 1);
+        assert!(violations[0].blocked);
+        assert_eq!(
+            violations[0].presented,
+            vec![chain.leaf_pin.clone(), chain.ca_pin.clone()]
+        );
+    }
+
+    #[test]
+    fn mismatch_is_only_reported_in_grace_mode() {
+        let chain = test_chain();
+        let wrong = base64::engine::general_purpose::STANDARD.encode([0u8; 32]);
+        let (result, violations) = verify(&chain, pin_set(&wrong, PinMode::Report), &[]);
+        assert!(result.is_ok());
+        assert_eq!(violations.len(), 1);
+        assert!(!violations[0].blocked);
+    }
+
+    #[test]
+    fn unpinned_hosts_are_not_checked() {
+        let chain = test_chain();
+        let wrong = base64::engine::general_purpose::STANDARD.encode([0u8; 32]);
+        let mut pins = pin_set(&wrong, PinMode::Enforce);
+        let host_pins = pins.remove("localhost").unwrap();
+        pins.insert("chat.hushnetwork.social".to_string(), host_pins);
+        let (result, violations) = verify(&chain, pins, &[]);
+        assert!(result.is_ok());
+        assert!(violations.is_empty());
+    }
+
+    #[test]
+    fn pins_are_normalized() {
+        let pin = base64::engine::general_purpose::STANDARD.encode([7u8; 32]);
+        assert_eq!(normalize_pin(&format!("sha256/{}", pin)).unwrap(), pin);
+        assert!(normalize_pin("not-base64!").is_err());
+        assert!(normalize_pin("AAAA").is_err());
+    }
+}
@@ -0,0 +1,316 @@
+//! Battery and power-source awareness, so background work eases off on
+//! battery.
+//!
+//! A task polls the power source every [`POLL_INTERVAL`] and emits
+//! `power-status-changed` when the machine switches between battery and mains,
+//! battery saver toggles, or the throttle level changes. The throttle level
+//! stretches the background sync interval and the live stream's ping interval
+//! by [`ThrottleLevel::factor`]: battery saver always throttles, and running on
+//! battery below `powerThrottleThreshold` percent (default
+//! [`DEFAULT_THRESHOLD_PERCENT`]) throttles less.
+//!
+//! Desktop reads batteries through the `battery` crate; battery saver comes
+//! from power-profiles-daemon on Linux and `pmset` on macOS, and is unknown on
+//! Windows. Android asks `BatteryManager` and `PowerManager`.
+
+use crate::background_sync::BackgroundSyncState;
+use crate::settings::{self, SettingsState};
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+const DEFAULT_THRESHOLD_PERCENT: u8 = 30;
+
+/// How much background work is being slowed down
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThrottleLevel {
+    #[default]
+    None,
+    /// On battery below the threshold
+    LowBattery,
+    BatterySaver,
+}
+
+impl ThrottleLevel {
+    /// Multiplier applied to background intervals.
+    pub fn factor(self) -> u32 {
+        match self {
+            Self::None => 1,
+            Self::LowBattery => 2,
+            Self::BatterySaver => 4,
+        }
+    }
+}
+
+/// Result of `get_power_status` and payload of `power-status-changed`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PowerStatus {
+    pub on_battery: bool,
+    /// None without a battery
+    pub battery_percent: Option<u8>,
+    /// None where the OS doesn't expose it
+    pub battery_saver: Option<bool>,
+    pub throttle: ThrottleLevel,
+}
+
+impl PowerStatus {
+    /// Whether `other` differs in anything but the battery percentage.
+    fn is_transition(&self, other: &PowerStatus) -> bool {
+        self.on_battery != other.on_battery
+            || self.battery_saver != other.battery_saver
+            || self.throttle != other.throttle
+    }
+}
+
+/// Managed state holding the last reading
+#[derive(Debug, Default)]
+pub struct PowerState {
+    status: Mutex<PowerStatus>,
+}
+
+impl PowerState {
+    pub fn get(&self) -> PowerStatus {
+        self.status
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+fn throttle_level(
+    on_battery: bool,
+    battery_percent: Option<u8>,
+    battery_saver: Option<bool>,
+    threshold: u8,
+) -> ThrottleLevel {
+    if battery_saver == Some(true) {
+        ThrottleLevel::BatterySaver
+    } else if on_battery && battery_percent.is_some_and(|percent| percent < threshold) {
+        ThrottleLevel::LowBattery
+    } else {
+        ThrottleLevel::None
+    }
+}
+
+/// Parse power-profiles-daemon's `ActiveProfile` as printed by gdbus, e.g.
+/// `(<'power-saver'>,)`.
+#[cfg(target_os = "linux")]
+fn parse_active_profile(output: &str) -> Option<bool> {
+    let profile = output.split('\'').nth(1)?;
+    Some(profile == "power-saver")
+}
+
+/// Whether battery saver is on. Blocking.
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+fn battery_saver() -> Option<bool> {
+    #[cfg(target_os = "linux")]
+    {
+        let output = std::process::Command::new("gdbus")
+            .args([
+                "call",
+                "--system",
+                "--dest",
+                "net.hadess.PowerProfiles",
+                "--object-path",
+                "/net/hadess/PowerProfiles",
+                "--method",
+                "org.freedesktop.DBus.Properties.Get",
+                "net.hadess.PowerProfiles",
+                "ActiveProfile",
+            ])
+            .output()
+            .ok()?;
+        parse_active_profile(&String::from_utf8_lossy(&output.stdout))
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let output = std::process::Command::new("pmset")
+            .arg("-g")
+            .output()
+            .ok()?;
+        let settings = String::from_utf8_lossy(&output.stdout);
+        let line = settings
+            .lines()
+            .find(|line| line.trim_start().starts_with("lowpowermode"))?;
+        Some(line.split_whitespace().nth(1) == Some("1"))
+    }
+    #[cfg(target_os = "windows")]
+    {
+        None
+    }
+}
+
+/// On battery, charge percentage and battery saver. Blocking.
+fn read() -> (bool, Option<u8>, Option<bool>) {
+    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    {
+        let batteries: Vec<battery::Battery> = match battery::Manager::new().and_then(|manager| {
+            manager
+                .batteries()
+                .map(|batteries| batteries.flatten().collect())
+        }) {
+            Ok(batteries) => batteries,
+            Err(e) => {
+                log::debug!("Battery state unavailable: {}", e);
+                Vec::new()
+            }
+        };
+        let on_battery = batteries
+            .iter()
+            .any(|battery| battery.state() == battery::State::Discharging);
+        let percent = (!batteries.is_empty()).then(|| {
+            let charge: f32 = batteries
+                .iter()
+                .map(|battery| battery.state_of_charge().value)
+                .sum();
+            (charge / batteries.len() as f32 * 100.0).round() as u8
+        });
+        (on_battery, percent, battery_saver())
+    }
+    #[cfg(target_os = "android")]
+    {
+        match crate::android::power_status() {
+            Ok((charging, capacity, power_save)) => (
+                !charging,
+                u8::try_from(capacity)
+                    .ok()
+                    .filter(|percent| *percent <= 100),
+                Some(power_save),
+            ),
+            Err(e) => {
+                log::debug!("Battery state unavailable: {}", e);
+                (false, None, None)
+            }
+        }
+    }
+    #[cfg(target_os = "ios")]
+    {
+        (false, None, None)
+    }
+}
+
+/// Take a fresh reading, emitting `power-status-changed` on transitions.
+async fn refresh(app: &AppHandle) -> PowerStatus {
+    let (on_battery, battery_percent, battery_saver) = tauri::async_runtime::spawn_blocking(read)
+        .await
+        .unwrap_or((false, None, None));
+    let threshold = app
+        .state::<SettingsState>()
+        .get()
+        .power_throttle_threshold
+        .unwrap_or(DEFAULT_THRESHOLD_PERCENT);
+    let status = PowerStatus {
+        on_battery,
+        battery_percent,
+        battery_saver,
+        throttle: throttle_level(on_battery, battery_percent, battery_saver, threshold),
+    };
+
+    let state = app.state::<PowerState>();
+    let previous = std::mem::replace(
+        &mut *state.status.lock().unwrap_or_else(|e| e.into_inner()),
+        status.clone(),
+    );
+    if previous.is_transition(&status) {
+        log::info!("Power status changed: {:?}", status);
+        if previous.throttle != status.throttle {
+            app.state::<BackgroundSyncState>().reschedule();
+        }
+        let _ = app.emit("power-status-changed", &status);
+    }
+    status
+}
+
+/// The throttle level currently applied to background work.
+pub fn throttle(app: &AppHandle) -> ThrottleLevel {
+    app.try_state::<PowerState>()
+        .map(|state| state.get().throttle)
+        .unwrap_or_default()
+}
+
+/// Start polling the power source. Called from `setup`.
+pub fn init(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            refresh(&app).await;
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn get_power_status(app: AppHandle) -> PowerStatus {
+    refresh(&app).await
+}
+
+/// Set the battery percentage below which background work slows down while
+/// on battery; 0 only throttles in battery saver.
+#[tauri::command]
+pub async fn set_power_throttle_threshold(
+    app: AppHandle,
+    percent: u8,
+) -> Result<PowerStatus, String> {
+    if percent > 100 {
+        return Err("Threshold must be a percentage".to_string());
+    }
+    settings::set(&app, "powerThrottleThreshold", percent.into())?;
+    Ok(refresh(&app).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn throttle_follows_saver_and_threshold() {
+        assert_eq!(
+            throttle_level(false, Some(10), None, 30),
+            ThrottleLevel::None
+        );
+        assert_eq!(
+            throttle_level(true, Some(50), Some(false), 30),
+            ThrottleLevel::None
+        );
+        assert_eq!(
+            throttle_level(true, Some(29), None, 30),
+            ThrottleLevel::LowBattery
+        );
+        assert_eq!(throttle_level(true, Some(29), None, 0), ThrottleLevel::None);
+        assert_eq!(
+            throttle_level(false, None, Some(true), 30),
+            ThrottleLevel::BatterySaver
+        );
+        assert_eq!(ThrottleLevel::BatterySaver.factor(), 4);
+    }
+
+    #[test]
+    fn percentage_alone_is_not_a_transition() {
+        let before = PowerStatus {
+            on_battery: true,
+            battery_percent: Some(80),
+            ..PowerStatus::default()
+        };
+        let after = PowerStatus {
+            battery_percent: Some(79),
+            ..before.clone()
+        };
+        assert!(!before.is_transition(&after));
+        assert!(before.is_transition(&PowerStatus {
+            on_battery: false,
+            ..after
+        }));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn active_profile_is_parsed() {
+        assert_eq!(parse_active_profile("(<'power-saver'>,)\n"), Some(true));
+        assert_eq!(parse_active_profile("(<'balanced'>,)\n"), Some(false));
+        assert_eq!(parse_active_profile(""), None);
+    }
+}
@@ -0,0 +1,276 @@
+//! Push delivery diagnostics for "I didn't get a notification" reports.
+//!
+//! The push listeners record every push they handle, including ones that were
+//! not shown, and the token setters record refreshes. Timestamps and totals are
+//! persisted to `push-diagnostics.json` in the app data dir; the since-launch
+//! count starts from zero on each run.
+
+use crate::fcm::{now_unix_ms, FcmState, PermissionResult};
+use crate::power::{PowerState, ThrottleLevel};
+use crate::storage;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+
+const DIAGNOSTICS_FILE: &str = "push-diagnostics.json";
+/// Suppressions within this window (24 hours) count as recent
+const RECENT_MS: u64 = 24 * 60 * 60 * 1000;
+/// Characters of the token kept at each end when displayed
+const TOKEN_PREVIEW_CHARS: usize = 8;
+
+/// What happened to a push the Rust layer handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushOutcome {
+    Shown,
+    /// Not shown because the app was in the foreground
+    Foreground,
+    QuietHours,
+    Muted,
+}
+
+/// Persisted counters (all timestamps Unix ms)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+struct Counters {
+    token_refreshed_at: Option<u64>,
+    last_push_at: Option<u64>,
+    total_pushes: u64,
+    quiet_hours_suppressed: u64,
+    last_quiet_hours_suppressed_at: Option<u64>,
+    mute_suppressed: u64,
+    last_mute_suppressed_at: Option<u64>,
+}
+
+impl Counters {
+    fn record(&mut self, outcome: PushOutcome, now: u64) {
+        self.last_push_at = Some(now);
+        self.total_pushes += 1;
+        match outcome {
+            PushOutcome::QuietHours => {
+                self.quiet_hours_suppressed += 1;
+                self.last_quiet_hours_suppressed_at = Some(now);
+            }
+            PushOutcome::Muted => {
+                self.mute_suppressed += 1;
+                self.last_mute_suppressed_at = Some(now);
+            }
+            PushOutcome::Shown | PushOutcome::Foreground => {}
+        }
+    }
+}
+
+/// Result of `get_push_diagnostics`
+#[derive(Debug, Clone, Serialize)]
+pub struct PushDiagnostics {
+    /// Start and end of the current token, or None if there is none
+    pub token_preview: Option<String>,
+    pub token_refreshed_at: Option<u64>,
+    pub last_push_at: Option<u64>,
+    pub pushes_since_launch: u64,
+    pub total_pushes: u64,
+    pub permission: PermissionResult,
+    pub quiet_hours_suppressed: u64,
+    pub last_quiet_hours_suppressed_at: Option<u64>,
+    pub quiet_hours_suppressed_recently: bool,
+    pub mute_suppressed: u64,
+    pub last_mute_suppressed_at: Option<u64>,
+    pub mute_suppressed_recently: bool,
+    /// How much background sync and keepalives are being slowed down
+    pub power_throttle: ThrottleLevel,
+}
+
+/// Shorten a token for display, keeping a few characters at each end.
+fn token_preview(token: &str) -> String {
+    let chars: Vec<char> = token.chars().collect();
+    if chars.len() <= TOKEN_PREVIEW_CHARS * 2 {
+        return token.to_string();
+    }
+    let head: String = chars[..TOKEN_PREVIEW_CHARS].iter().collect();
+    let tail: String = chars[chars.len() - TOKEN_PREVIEW_CHARS..].iter().collect();
+    format!("{}…{}", head, tail)
+}
+
+fn is_recent(timestamp: Option<u64>, now: u64) -> bool {
+    timestamp.is_some_and(|timestamp| now.saturating_sub(timestamp) < RECENT_MS)
+}
+
+/// Managed state holding the diagnostics counters
+#[derive(Debug, Default)]
+pub struct PushDiagnosticsState {
+    counters: Mutex<Counters>,
+    since_launch: Mutex<u64>,
+    /// Backing file; `None` keeps the counters in memory only
+    path: Option<PathBuf>,
+}
+
+impl PushDiagnosticsState {
+    /// Load persisted counters from the app data dir.
+    pub fn load(app: &AppHandle) -> Self {
+        let path = storage::data_file(app, DIAGNOSTICS_FILE).ok();
+        let counters = path
+            .as_deref()
+            .and_then(storage::read_json)
+            .unwrap_or_default();
+        Self {
+            counters: Mutex::new(counters),
+            since_launch: Mutex::new(0),
+            path,
+        }
+    }
+
+    fn update(&self, change: impl FnOnce(&mut Counters)) {
+        let mut counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        change(&mut counters);
+        if let Some(path) = &self.path {
+            if let Err(e) = storage::write_json_atomic(path, &*counters) {
+                log::warn!("Failed to save push diagnostics: {}", e);
+            }
+        }
+    }
+
+    /// Record a push handled by the Rust layer.
+    pub fn record_push(&self, outcome: PushOutcome) {
+        *self.since_launch.lock().unwrap_or_else(|e| e.into_inner()) += 1;
+        self.update(|counters| counters.record(outcome, now_unix_ms()));
+    }
+
+    /// Record that the push token was set or rotated.
+    pub fn record_token_refresh(&self) {
+        self.update(|counters| counters.token_refreshed_at = Some(now_unix_ms()));
+    }
+
+    fn reset(&self) {
+        *self.since_launch.lock().unwrap_or_else(|e| e.into_inner()) = 0;
+        self.update(|counters| *counters = Counters::default());
+    }
+
+    fn snapshot(
+        &self,
+        token: Option<&str>,
+        permission: PermissionResult,
+        power_throttle: ThrottleLevel,
+    ) -> PushDiagnostics {
+        let counters = self
+            .counters
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        let now = now_unix_ms();
+        PushDiagnostics {
+            token_preview: token.map(token_preview),
+            token_refreshed_at: counters.token_refreshed_at,
+            last_push_at: counters.last_push_at,
+            pushes_since_launch: *self.since_launch.lock().unwrap_or_else(|e| e.into_inner()),
+            total_pushes: counters.total_pushes,
+            permission,
+            quiet_hours_suppressed: counters.quiet_hours_suppressed,
+            last_quiet_hours_suppressed_at: counters.last_quiet_hours_suppressed_at,
+            quiet_hours_suppressed_recently: is_recent(
+                counters.last_quiet_hours_suppressed_at,
+                now,
+            ),
+            mute_suppressed: counters.mute_suppressed,
+            last_mute_suppressed_at: counters.last_mute_suppressed_at,
+            mute_suppressed_recently: is_recent(counters.last_mute_suppressed_at, now),
+            power_throttle,
+        }
+    }
+}
+
+/// Record a handled push, if diagnostics are available.
+pub fn record_push(app: &AppHandle, outcome: PushOutcome) {
+    if let Some(state) = app.try_state::<PushDiagnosticsState>() {
+        state.record_push(outcome);
+    }
+}
+
+/// Record a token refresh, if diagnostics are available.
+pub fn record_token_refresh(app: &AppHandle) {
+    if let Some(state) = app.try_state::<PushDiagnosticsState>() {
+        state.record_token_refresh();
+    }
+}
+
+/// Snapshot of push delivery state for debugging missed notifications.
+#[tauri::command]
+pub fn get_push_diagnostics(
+    state: State<'_, PushDiagnosticsState>,
+    fcm: State<'_, FcmState>,
+    power: State<'_, PowerState>,
+) -> PushDiagnostics {
+    state.snapshot(
+        fcm.token().as_deref(),
+        fcm.permission(),
+        power.get().throttle,
+    )
+}
+
+/// Clear all push diagnostics counters.
+#[tauri::command]
+pub fn reset_push_diagnostics(state: State<'_, PushDiagnosticsState>) {
+    state.reset();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn granted() -> PermissionResult {
+        PermissionResult {
+            granted: true,
+            can_request: false,
+        }
+    }
+
+    #[test]
+    fn token_preview_keeps_ends_of_long_tokens() {
+        assert_eq!(token_preview("short"), "short");
+        assert_eq!(
+            token_preview("abcdefgh-middle-part-12345678"),
+            "abcdefgh…12345678"
+        );
+    }
+
+    #[test]
+    fn record_push_counts_outcomes() {
+        let state = PushDiagnosticsState::default();
+        state.record_push(PushOutcome::Shown);
+        state.record_push(PushOutcome::QuietHours);
+        state.record_push(PushOutcome::Muted);
+        state.record_push(PushOutcome::Foreground);
+
+        let diagnostics = state.snapshot(None, granted(), ThrottleLevel::None);
+        assert_eq!(diagnostics.pushes_since_launch, 4);
+        assert_eq!(diagnostics.total_pushes, 4);
+        assert_eq!(diagnostics.quiet_hours_suppressed, 1);
+        assert_eq!(diagnostics.mute_suppressed, 1);
+        assert!(diagnostics.quiet_hours_suppressed_recently);
+        assert!(diagnostics.mute_suppressed_recently);
+        assert!(diagnostics.last_push_at.is_some());
+        assert!(diagnostics.token_preview.is_none());
+    }
+
+    #[test]
+    fn reset_clears_everything() {
+        let state = PushDiagnosticsState::default();
+        state.record_push(PushOutcome::Muted);
+        state.record_token_refresh();
+        state.reset();
+
+        let diagnostics = state.snapshot(Some("token"), granted(), ThrottleLevel::None);
+        assert_eq!(diagnostics.pushes_since_launch, 0);
+        assert_eq!(diagnostics.total_pushes, 0);
+        assert!(diagnostics.token_refreshed_at.is_none());
+        assert!(!diagnostics.mute_suppressed_recently);
+        assert_eq!(diagnostics.token_preview.as_deref(), Some("token"));
+    }
+
+    #[test]
+    fn is_recent_uses_24_hour_window() {
+        let now = RECENT_MS * 10;
+        assert!(is_recent(Some(now - 1_000), now));
+        assert!(!is_recent(Some(now - RECENT_MS), now));
+        assert!(!is_recent(None, now));
+    }
+}
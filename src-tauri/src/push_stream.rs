@@ -0,0 +1,297 @@
+//! Background push for desktop via the Hush server's event stream.
+//!
+//! Desktop has no FCM, so while the app runs (including hidden in the tray) a
+//! task spawned in `setup` holds a server-sent events connection to the stream
+//! URL configured by the frontend. Each event becomes a desktop notification
+//! through [`crate::notifications::dispatch`], whose click handler queues the
+//! pending navigation. As on Android, nothing is shown while the main window
+//! is focused, since the in-app connection already surfaces the activity.
+//!
+//! Dropped connections are retried with exponential backoff. On wake from
+//! sleep ([`crate::suspend`]) the backoff is reset and the stream reconnects
+//! immediately. The stream configuration is persisted to
+//! `push-connection.json`.
+
+use crate::backoff::Backoff;
+use crate::fcm::now_unix_ms;
+use crate::logging::redact;
+use crate::net;
+use crate::notifications::{self, PushPayload};
+use crate::push_diagnostics::{self, PushOutcome};
+use crate::storage;
+use crate::window;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State, Url};
+use tokio::sync::{watch, Notify};
+
+const CONFIG_FILE: &str = "push-connection.json";
+const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
+
+/// Event stream endpoint and credentials provided by the frontend
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StreamConfig {
+    pub url: String,
+    #[serde(default)]
+    pub auth_token: Option<String>,
+}
+
+/// Connection status returned by `get_push_connection_status`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "state", rename_all = "lowercase")]
+pub enum PushConnectionStatus {
+    Connected,
+    Disconnected,
+    /// Waiting to reconnect until `until` (Unix ms)
+    Backoff {
+        until: u64,
+    },
+}
+
+/// Managed state shared between the commands and the background task
+pub struct PushStreamState {
+    status: Mutex<PushConnectionStatus>,
+    config: watch::Sender<Option<StreamConfig>>,
+    /// Signalled on wake so the task reconnects without waiting out its backoff
+    wake: Notify,
+}
+
+impl PushStreamState {
+    fn new(config: Option<StreamConfig>) -> Self {
+        Self {
+            status: Mutex::new(PushConnectionStatus::Disconnected),
+            config: watch::Sender::new(config),
+            wake: Notify::new(),
+        }
+    }
+
+    pub fn status(&self) -> PushConnectionStatus {
+        self.status
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    fn set_status(&self, status: PushConnectionStatus) {
+        *self.status.lock().unwrap_or_else(|e| e.into_inner()) = status;
+    }
+
+    /// Whether a stream is configured.
+    pub fn is_configured(&self) -> bool {
+        self.config.borrow().is_some()
+    }
+
+    /// Reconnect immediately, e.g. after the system wakes from sleep.
+    pub fn resume(&self) {
+        self.wake.notify_one();
+    }
+}
+
+/// Accumulates server-sent event lines into complete `data` payloads.
+#[derive(Debug, Default)]
+struct SseParser {
+    data: Vec<String>,
+}
+
+impl SseParser {
+    /// Feed one line (without its line ending); returns the event data when
+    /// the line completes an event.
+    fn push_line(&mut self, line: &str) -> Option<String> {
+        if line.is_empty() {
+            if self.data.is_empty() {
+                return None;
+            }
+            let data = self.data.join("\n");
+            self.data.clear();
+            return Some(data);
+        }
+        if let Some(value) = line.strip_prefix("data:") {
+            self.data
+                .push(value.strip_prefix(' ').unwrap_or(value).to_string());
+        }
+        // Comments (":keepalive"), `event:`, `id:` and `retry:` fields are ignored
+        None
+    }
+}
+
+fn handle_event(app: &AppHandle, data: &str) {
+    let payload = match serde_json::from_str::<PushPayload>(data) {
+        Ok(payload) => payload,
+        Err(e) => {
+            log::debug!(
+                "Ignoring unrecognised push event: {}",
+                redact(&e.to_string())
+            );
+            return;
+        }
+    };
+    if window::is_main_window_focused(app) {
+        log::debug!("App is in foreground, not showing push notification");
+        push_diagnostics::record_push(app, PushOutcome::Foreground);
+        return;
+    }
+    let outcome = notifications::dispatch(app, payload.into_notification());
+    push_diagnostics::record_push(app, outcome);
+}
+
+/// Hold one connection, dispatching events until the server closes it.
+async fn listen(
+    app: &AppHandle,
+    state: &PushStreamState,
+    client: &reqwest::Client,
+    config: &StreamConfig,
+) -> Result<(), String> {
+    let mut request = client
+        .get(&config.url)
+        .header("Accept", "text/event-stream");
+    if let Some(token) = &config.auth_token {
+        request = request.bearer_auth(token);
+    }
+    let mut response = request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.to_string())?;
+    state.set_status(PushConnectionStatus::Connected);
+    log::info!("Push event stream connected");
+
+    let mut parser = SseParser::default();
+    let mut buffer = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+        buffer.extend_from_slice(&chunk);
+        while let Some(end) = buffer.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            if let Some(data) = parser.push_line(line.trim_end_matches(['\r', '\n'])) {
+                handle_event(app, &data);
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn run(app: AppHandle) {
+    let state = app.state::<PushStreamState>();
+    let mut config_rx = state.config.subscribe();
+    let mut network = net::subscribe(&app);
+    let mut backoff = Backoff::new(INITIAL_RETRY_DELAY, MAX_RETRY_DELAY);
+
+    loop {
+        let config = config_rx.borrow_and_update().clone();
+        let Some(config) = config else {
+            state.set_status(PushConnectionStatus::Disconnected);
+            if config_rx.changed().await.is_err() {
+                return;
+            }
+            continue;
+        };
+
+        let client = net::client(&app);
+        let result = tokio::select! {
+            result = listen(&app, &state, &client, &config) => result,
+            _ = network.changed() => {
+                backoff.reset();
+                continue;
+            }
+            _ = config_rx.changed() => {
+                backoff.reset();
+                continue;
+            }
+            _ = state.wake.notified() => {
+                backoff.reset();
+                continue;
+            }
+        };
+
+        match result {
+            Ok(()) => {
+                log::debug!("Push event stream closed, reconnecting");
+                backoff.reset();
+                continue;
+            }
+            Err(e) => log::warn!("Push event stream failed: {}", redact(&e.to_string())),
+        }
+
+        let delay = backoff.next_delay();
+        state.set_status(PushConnectionStatus::Backoff {
+            until: now_unix_ms() + delay.as_millis() as u64,
+        });
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = config_rx.changed() => backoff.reset(),
+            _ = state.wake.notified() => backoff.reset(),
+        }
+    }
+}
+
+/// Load the stream configuration and spawn the background task. Called from `setup`.
+pub fn init(app: &AppHandle) {
+    let config = storage::config_file(app, CONFIG_FILE)
+        .ok()
+        .and_then(|path| storage::read_json::<StreamConfig>(&path));
+    app.manage(PushStreamState::new(config));
+
+    tauri::async_runtime::spawn(run(app.clone()));
+}
+
+/// Point the background connection at the server's event stream, or stop it with `None`.
+#[tauri::command]
+pub fn configure_push_connection(
+    app: AppHandle,
+    state: State<'_, PushStreamState>,
+    config: Option<StreamConfig>,
+) -> Result<(), String> {
+    if let Some(config) = &config {
+        let url = Url::parse(&config.url).map_err(|e| format!("Invalid stream URL: {}", e))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err("Stream URL must use http or https".to_string());
+        }
+    }
+
+    let path = storage::config_file(&app, CONFIG_FILE)?;
+    match &config {
+        Some(config) => storage::write_json_atomic(&path, config)?,
+        None => storage::remove_file(&path)?,
+    }
+    state.config.send_replace(config);
+    Ok(())
+}
+
+/// Status of the background push connection, for the settings screen.
+#[tauri::command]
+pub fn get_push_connection_status(state: State<'_, PushStreamState>) -> PushConnectionStatus {
+    state.status()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sse_parser_joins_data_lines_until_blank_line() {
+        let mut parser = SseParser::default();
+        assert_eq!(parser.push_line(": keepalive"), None);
+        assert_eq!(parser.push_line("event: feed"), None);
+        assert_eq!(parser.push_line("data: {\"feedId\":"), None);
+        assert_eq!(parser.push_line("data:\"feed-1\"}"), None);
+        assert_eq!(
+            parser.push_line("").as_deref(),
+            Some("{\"feedId\":\n\"feed-1\"}")
+        );
+        assert_eq!(parser.push_line(""), None);
+    }
+
+    #[test]
+    fn status_serializes_with_state_tag() {
+        let backoff = serde_json::to_value(PushConnectionStatus::Backoff { until: 42 }).unwrap();
+        assert_eq!(
+            backoff,
+            serde_json::json!({"state": "backoff", "until": 42})
+        );
+
+        let connected = serde_json::to_value(PushConnectionStatus::Connected).unwrap();
+        assert_eq!(connected, serde_json::json!({"state": "connected"}));
+    }
+}
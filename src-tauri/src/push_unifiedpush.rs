@@ -0,0 +1,247 @@
+//! UnifiedPush for Linux desktop.
+//!
+//! With a UnifiedPush distributor such as ntfy, the user's endpoint URL is a
+//! topic the Hush server publishes to. While an endpoint is registered we hold
+//! an HTTP long-poll on the topic's JSON stream (`<endpoint>/json`) and turn
+//! each message into a desktop notification through
+//! [`crate::notifications::dispatch`], so clicks land in pending navigation
+//! just like FCM taps on mobile.
+//!
+//! The endpoint is persisted to `unifiedpush.json`. Compiled only on Linux with
+//! the `unifiedpush` feature.
+
+use crate::backoff::Backoff;
+use crate::fcm::NavigationKind;
+use crate::net;
+use crate::notifications::{self, FeedNotification, PushPayload};
+use crate::push_diagnostics;
+use crate::storage;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Manager, State, Url};
+
+const ENDPOINT_FILE: &str = "unifiedpush.json";
+const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(2);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct EndpointConfig {
+    #[serde(default)]
+    endpoint: Option<String>,
+}
+
+/// One line of an ntfy JSON stream
+#[derive(Debug, Deserialize)]
+struct StreamEvent {
+    event: String,
+    #[serde(default)]
+    message: Option<String>,
+}
+
+/// Parse one line of the stream into a notification, if it carries a message.
+fn parse_stream_line(line: &str) -> Option<FeedNotification> {
+    let event: StreamEvent = serde_json::from_str(line.trim()).ok()?;
+    if event.event != "message" {
+        return None;
+    }
+    let message = event.message?;
+    match serde_json::from_str::<PushPayload>(&message) {
+        Ok(payload) => Some(payload.into_notification()),
+        Err(_) => Some(FeedNotification {
+            title: "Hush Feeds".to_string(),
+            body: message,
+            feed_id: None,
+            post_id: None,
+            kind: NavigationKind::Feed,
+            feed_name: None,
+        }),
+    }
+}
+
+/// URL of the JSON stream for `endpoint`.
+fn stream_url(endpoint: &str) -> Result<Url, String> {
+    let url = Url::parse(endpoint).map_err(|e| format!("Invalid endpoint URL: {}", e))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err("Endpoint URL must use http or https".to_string());
+    }
+    let path = format!("{}/json", url.path().trim_end_matches('/'));
+    let mut stream = url;
+    stream.set_path(&path);
+    Ok(stream)
+}
+
+/// Managed state for the UnifiedPush registration and its listener task
+#[derive(Default)]
+pub struct UnifiedPushState {
+    endpoint: Mutex<Option<String>>,
+    listener: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl UnifiedPushState {
+    /// Whether an endpoint is registered.
+    pub fn is_registered(&self) -> bool {
+        self.endpoint
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_some()
+    }
+
+    /// Replace the endpoint and restart (or stop) the listener.
+    fn set_endpoint(&self, app: &AppHandle, endpoint: Option<String>) {
+        let mut listener = self.listener.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(task) = listener.take() {
+            task.abort();
+        }
+        if let Some(endpoint) = &endpoint {
+            *listener = Some(spawn_listener(app.clone(), endpoint.clone()));
+        }
+        *self.endpoint.lock().unwrap_or_else(|e| e.into_inner()) = endpoint;
+    }
+}
+
+fn spawn_listener(app: AppHandle, endpoint: String) -> JoinHandle<()> {
+    tauri::async_runtime::spawn(async move {
+        let url = match stream_url(&endpoint) {
+            Ok(url) => url,
+            Err(e) => {
+                log::warn!("UnifiedPush listener not started: {}", e);
+                return;
+            }
+        };
+
+        let mut network = net::subscribe(&app);
+        let mut backoff = Backoff::new(INITIAL_RETRY_DELAY, MAX_RETRY_DELAY);
+        loop {
+            let client = net::client(&app);
+            let result = tokio::select! {
+                result = listen(&app, &client, url.clone()) => result,
+                _ = network.changed() => {
+                    backoff.reset();
+                    continue;
+                }
+            };
+            match result {
+                Ok(()) => {
+                    log::debug!("UnifiedPush stream closed, reconnecting");
+                    backoff.reset();
+                }
+                Err(e) => {
+                    let delay = backoff.next_delay();
+                    log::warn!("UnifiedPush stream failed: {}; retrying in {:?}", e, delay);
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+    })
+}
+
+/// Hold one long-poll connection, dispatching messages until the server closes it.
+async fn listen(app: &AppHandle, client: &reqwest::Client, url: Url) -> Result<(), String> {
+    let mut response = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.to_string())?;
+    log::info!("UnifiedPush stream connected");
+
+    let mut buffer = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+        buffer.extend_from_slice(&chunk);
+        while let Some(end) = buffer.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=end).collect();
+            if let Some(notification) = parse_stream_line(&String::from_utf8_lossy(&line)) {
+                let outcome = notifications::dispatch(app, notification);
+                push_diagnostics::record_push(app, outcome);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Restore a registered endpoint and start listening. Called from `setup`.
+pub fn init(app: &AppHandle) {
+    let state = UnifiedPushState::default();
+    let endpoint = storage::config_file(app, ENDPOINT_FILE)
+        .ok()
+        .and_then(|path| storage::read_json::<EndpointConfig>(&path))
+        .and_then(|config| config.endpoint);
+    state.set_endpoint(app, endpoint);
+    app.manage(state);
+}
+
+fn save_endpoint(app: &AppHandle, endpoint: Option<String>) -> Result<(), String> {
+    let path = storage::config_file(app, ENDPOINT_FILE)?;
+    storage::write_json_atomic(&path, &EndpointConfig { endpoint })
+}
+
+/// Register the UnifiedPush endpoint provided by the user's distributor and
+/// start listening for pushes on it.
+///
+/// The frontend then registers `endpoint_url` with the Hush server.
+#[tauri::command]
+pub fn register_unifiedpush(
+    app: AppHandle,
+    state: State<'_, UnifiedPushState>,
+    endpoint_url: String,
+) -> Result<(), String> {
+    stream_url(&endpoint_url)?;
+    save_endpoint(&app, Some(endpoint_url.clone()))?;
+    state.set_endpoint(&app, Some(endpoint_url));
+    Ok(())
+}
+
+/// Stop listening and forget the UnifiedPush endpoint.
+#[tauri::command]
+pub fn unregister_unifiedpush(
+    app: AppHandle,
+    state: State<'_, UnifiedPushState>,
+) -> Result<(), String> {
+    save_endpoint(&app, None)?;
+    state.set_endpoint(&app, None);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stream_url_appends_json_path() {
+        assert_eq!(
+            stream_url("https://ntfy.sh/upAbc123").unwrap().as_str(),
+            "https://ntfy.sh/upAbc123/json"
+        );
+        assert_eq!(
+            stream_url("https://push.example.com/topic/")
+                .unwrap()
+                .as_str(),
+            "https://push.example.com/topic/json"
+        );
+        assert!(stream_url("ftp://example.com/topic").is_err());
+        assert!(stream_url("not a url").is_err());
+    }
+
+    #[test]
+    fn parse_stream_line_reads_hush_payload() {
+        let line = r#"{"event":"message","message":"{\"feedId\":\"feed-1\",\"postId\":\"post-2\",\"type\":\"mention\",\"title\":\"Alice\",\"body\":\"hi\"}"}"#;
+        let notification = parse_stream_line(line).unwrap();
+        assert_eq!(notification.feed_id.as_deref(), Some("feed-1"));
+        assert_eq!(notification.post_id.as_deref(), Some("post-2"));
+        assert_eq!(notification.kind, NavigationKind::Mention);
+        assert_eq!(notification.title, "Alice");
+    }
+
+    #[test]
+    fn parse_stream_line_ignores_control_events() {
+        assert!(parse_stream_line(r#"{"event":"open"}"#).is_none());
+        assert!(parse_stream_line(r#"{"event":"keepalive"}"#).is_none());
+        assert!(parse_stream_line("garbage").is_none());
+
+        let plain = parse_stream_line(r#"{"event":"message","message":"hello"}"#).unwrap();
+        assert_eq!(plain.body, "hello");
+        assert!(plain.feed_id.is_none());
+    }
+}
@@ -0,0 +1,265 @@
+//! QR codes for feed invites and links, drawn natively so the link never
+//! passes through a canvas in the webview.
+//!
+//! `generate_qr` renders the code at a whole number of pixels per module,
+//! centred in a square PNG of the requested size with a white quiet zone.
+//! Images up to [`INLINE_MAX_PX`] come back as base64; larger ones are
+//! written to `qr/` in the app cache dir, which is emptied at startup.
+//!
+//! The bundled app icon can be laid over the middle of the code. It hides
+//! some modules, which only error correction `quartile` or `high` can make
+//! up for, so a logo defaults to (and requires) one of those.
+
+use crate::error::{self, CommandError};
+use crate::storage;
+use base64::Engine;
+use image::{imageops, DynamicImage, ImageFormat, Rgba, RgbaImage};
+use qrcode::{Color, EcLevel, QrCode};
+use serde::Serialize;
+use std::io::Cursor;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+
+const QR_DIR: &str = "qr";
+/// Longest payload accepted, in bytes
+pub const MAX_DATA_BYTES: usize = 2048;
+pub const MIN_SIZE_PX: u32 = 64;
+pub const MAX_SIZE_PX: u32 = 4096;
+/// Larger images are returned as a file
+pub const INLINE_MAX_PX: u32 = 1024;
+/// Blank modules around the code, as the QR spec asks for
+const QUIET_ZONE: u32 = 4;
+/// Width of the logo relative to the code, quiet zone excluded
+const LOGO_FRACTION: f32 = 0.22;
+const LOGO: &[u8] = include_bytes!("../icons/icon.png");
+
+const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+const BLACK: Rgba<u8> = Rgba([0, 0, 0, 255]);
+
+/// A generated code
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "encoding", rename_all = "lowercase")]
+pub enum QrImage {
+    Base64 {
+        /// PNG bytes
+        data: String,
+        size: u32,
+    },
+    /// Written to `path`; too large to return inline
+    File { path: PathBuf, size: u32 },
+}
+
+fn parse_ec_level(level: Option<&str>, logo: bool) -> Result<EcLevel, CommandError> {
+    let level = match level {
+        None if logo => EcLevel::H,
+        None | Some("medium") => EcLevel::M,
+        Some("low") => EcLevel::L,
+        Some("quartile") => EcLevel::Q,
+        Some("high") => EcLevel::H,
+        Some(other) => {
+            return Err(CommandError::invalid_argument(format!(
+                "Unknown error correction level: {}",
+                other
+            )))
+        }
+    };
+    if logo && matches!(level, EcLevel::L | EcLevel::M) {
+        return Err(CommandError::invalid_argument(
+            "A logo needs error correction \"quartile\" or \"high\"",
+        ));
+    }
+    Ok(level)
+}
+
+fn encode(data: &str, level: EcLevel) -> Result<QrCode, CommandError> {
+    if data.is_empty() || data.len() > MAX_DATA_BYTES {
+        return Err(CommandError::invalid_argument(format!(
+            "QR data must be 1 to {} bytes",
+            MAX_DATA_BYTES
+        )));
+    }
+    QrCode::with_error_correction_level(data.as_bytes(), level).map_err(|e| match e {
+        qrcode::types::QrError::DataTooLong => CommandError::new(
+            error::QR_DATA_TOO_LONG,
+            format!(
+                "{} bytes don't fit in a QR code at this error correction level",
+                data.len()
+            ),
+        ),
+        other => CommandError::invalid_argument(other.to_string()),
+    })
+}
+
+/// Draw `code` into a `size`×`size` image, optionally with the logo on top.
+fn render(code: &QrCode, size: u32, logo: bool) -> Result<RgbaImage, CommandError> {
+    let modules = code.width() as u32;
+    let scale = size / (modules + 2 * QUIET_ZONE);
+    if scale == 0 {
+        return Err(CommandError::invalid_argument(format!(
+            "{} px is too small for this code; it needs at least {} px",
+            size,
+            modules + 2 * QUIET_ZONE
+        )));
+    }
+    let mut image = RgbaImage::from_pixel(size, size, WHITE);
+    let origin = (size - modules * scale) / 2;
+    for (i, color) in code.to_colors().into_iter().enumerate() {
+        if color != Color::Dark {
+            continue;
+        }
+        let (x, y) = (i as u32 % modules, i as u32 / modules);
+        for dy in 0..scale {
+            for dx in 0..scale {
+                image.put_pixel(origin + x * scale + dx, origin + y * scale + dy, BLACK);
+            }
+        }
+    }
+    if logo {
+        overlay_logo(&mut image, modules * scale)?;
+    }
+    Ok(image)
+}
+
+/// Lay the app icon over the middle of the code, on a white backing.
+fn overlay_logo(image: &mut RgbaImage, code_px: u32) -> Result<(), CommandError> {
+    let logo_px = ((code_px as f32 * LOGO_FRACTION) as u32).max(1);
+    let backing_px = logo_px + logo_px / 5;
+    let logo = image::load_from_memory_with_format(LOGO, ImageFormat::Png)
+        .map_err(|e| e.to_string())?
+        .to_rgba8();
+    let logo = imageops::resize(&logo, logo_px, logo_px, imageops::FilterType::Lanczos3);
+    let backing = RgbaImage::from_pixel(backing_px, backing_px, WHITE);
+    let width = image.width();
+    let centre = |px: u32| i64::from((width - px) / 2);
+    imageops::overlay(image, &backing, centre(backing_px), centre(backing_px));
+    imageops::overlay(image, &logo, centre(logo_px), centre(logo_px));
+    Ok(())
+}
+
+fn to_png(image: RgbaImage) -> Result<Vec<u8>, CommandError> {
+    let mut png = Vec::new();
+    DynamicImage::ImageRgba8(image)
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|e| e.to_string())?;
+    Ok(png)
+}
+
+fn qr_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_cache_dir()
+        .map(|dir| dir.join(QR_DIR))
+        .map_err(|e| e.to_string())
+}
+
+/// Delete every code written so far. Returns the bytes freed.
+pub(crate) fn clear(app: &AppHandle) -> Result<u64, String> {
+    storage::remove_all(&qr_dir(app)?)
+}
+
+/// Remove codes written by the previous run. Called from `setup`.
+pub fn init(app: &AppHandle) {
+    if let Err(e) = clear(app) {
+        log::warn!("Failed to clear QR codes: {}", e);
+    }
+}
+
+/// Render `data` as a QR code PNG of `size_px` square.
+///
+/// `ec_level` is "low", "medium" (the default), "quartile" or "high";
+/// `logo` puts the app icon in the middle and defaults the level to "high".
+/// Payloads over 2048 bytes are rejected, and a payload that doesn't fit at
+/// the chosen level fails with `qr_data_too_long`. Codes larger than 1024 px
+/// come back as a file path instead of base64.
+#[tauri::command]
+pub async fn generate_qr(
+    app: AppHandle,
+    data: String,
+    size_px: u32,
+    ec_level: Option<String>,
+    logo: Option<bool>,
+) -> Result<QrImage, CommandError> {
+    if !(MIN_SIZE_PX..=MAX_SIZE_PX).contains(&size_px) {
+        return Err(CommandError::invalid_argument(format!(
+            "QR size must be {} to {} px",
+            MIN_SIZE_PX, MAX_SIZE_PX
+        )));
+    }
+    let logo = logo.unwrap_or(false);
+    let level = parse_ec_level(ec_level.as_deref(), logo)?;
+    let code = encode(&data, level)?;
+    let png =
+        tauri::async_runtime::spawn_blocking(move || render(&code, size_px, logo).and_then(to_png))
+            .await
+            .map_err(|e| e.to_string())??;
+
+    if size_px <= INLINE_MAX_PX {
+        return Ok(QrImage::Base64 {
+            data: base64::engine::general_purpose::STANDARD.encode(png),
+            size: size_px,
+        });
+    }
+    let dir = qr_dir(&app)?;
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("qr-{}.png", uuid::Uuid::new_v4()));
+    std::fs::write(&path, png)?;
+    Ok(QrImage::File {
+        path,
+        size: size_px,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INVITE: &str = "https://hushnetwork.social/invite/3f9a1c0e-5d2b-4c7e-9a41-8b6f0d2e7c55";
+
+    #[test]
+    fn ec_levels_parse_and_logos_need_redundancy() {
+        assert_eq!(parse_ec_level(None, false).unwrap(), EcLevel::M);
+        assert_eq!(parse_ec_level(None, true).unwrap(), EcLevel::H);
+        assert_eq!(parse_ec_level(Some("quartile"), true).unwrap(), EcLevel::Q);
+        assert!(parse_ec_level(Some("low"), true).is_err());
+        assert!(parse_ec_level(Some("extreme"), false).is_err());
+    }
+
+    #[test]
+    fn code_renders_to_the_requested_size() {
+        let code = encode(INVITE, EcLevel::M).unwrap();
+        let png = to_png(render(&code, 300, false).unwrap()).unwrap();
+        let image = image::load_from_memory(&png).unwrap().to_rgba8();
+        assert_eq!(image.dimensions(), (300, 300));
+        // Quiet zone, then the top-left finder pattern
+        assert_eq!(*image.get_pixel(0, 0), WHITE);
+        let scale = 300 / (code.width() as u32 + 2 * QUIET_ZONE);
+        let origin = (300 - code.width() as u32 * scale) / 2;
+        assert_eq!(*image.get_pixel(origin, origin), BLACK);
+
+        assert!(render(&code, 20, false).is_err());
+    }
+
+    #[test]
+    fn logo_covers_the_centre() {
+        let code = encode(INVITE, EcLevel::H).unwrap();
+        let plain = render(&code, 400, false).unwrap();
+        let with_logo = render(&code, 400, true).unwrap();
+        assert_ne!(plain, with_logo);
+        assert_eq!(plain.get_pixel(0, 0), with_logo.get_pixel(0, 0));
+    }
+
+    #[test]
+    fn oversized_payloads_are_refused() {
+        let data = "x".repeat(1500);
+        assert!(encode(&data, EcLevel::L).is_ok());
+        assert_eq!(
+            encode(&data, EcLevel::H).unwrap_err().code,
+            error::QR_DATA_TOO_LONG
+        );
+        let too_long = "x".repeat(MAX_DATA_BYTES + 1);
+        assert_eq!(
+            encode(&too_long, EcLevel::L).unwrap_err().code,
+            error::INVALID_ARGUMENT
+        );
+        assert!(encode("", EcLevel::L).is_err());
+    }
+}
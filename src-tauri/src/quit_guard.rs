@@ -0,0 +1,134 @@
+//! Confirmation before quitting while something is still transferring.
+//!
+//! A real exit (the tray's Quit item, `app.exit`, a restart to apply an
+//! update) is held back while an upload is flagged (`set_uploading`), an
+//! outbox entry is being sent or a download is running: the exit is
+//! prevented, the main window is shown and `quit-blocked` is emitted with
+//! what is busy, so the frontend can ask whether to quit anyway. Calling
+//! `force_quit` lets exits through for [`FORCE_QUIT_WINDOW`].
+//!
+//! Closing the window to the tray never gets here. Once the main window is
+//! gone its uploads have died with the webview, so the exit isn't held then.
+
+use crate::attachments::AttachmentState;
+use crate::downloads::DownloadsState;
+use crate::file_drop::FileDropState;
+use crate::outbox::OutboxState;
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+/// How long after `force_quit` exits are let through
+pub const FORCE_QUIT_WINDOW: Duration = Duration::from_secs(10);
+
+/// What is still transferring, as sent in `quit-blocked`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BusyActivities {
+    pub uploading: bool,
+    pub sending_outbox: bool,
+    pub downloading: bool,
+}
+
+impl BusyActivities {
+    fn any(self) -> bool {
+        self.uploading || self.sending_outbox || self.downloading
+    }
+}
+
+/// Managed state remembering the last `force_quit`
+#[derive(Debug, Default)]
+pub struct QuitGuardState {
+    forced_at: Mutex<Option<Instant>>,
+}
+
+impl QuitGuardState {
+    fn forced_at(&self) -> std::sync::MutexGuard<'_, Option<Instant>> {
+        self.forced_at.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn is_forced(&self, now: Instant) -> bool {
+        self.forced_at()
+            .is_some_and(|forced_at| now.saturating_duration_since(forced_at) < FORCE_QUIT_WINDOW)
+    }
+}
+
+fn busy(app: &AppHandle) -> BusyActivities {
+    BusyActivities {
+        uploading: app
+            .try_state::<FileDropState>()
+            .is_some_and(|state| state.is_uploading()),
+        sending_outbox: app
+            .try_state::<OutboxState>()
+            .is_some_and(|state| state.is_sending()),
+        downloading: app
+            .try_state::<DownloadsState>()
+            .is_some_and(|state| state.has_active())
+            || app
+                .try_state::<AttachmentState>()
+                .is_some_and(|state| state.has_active()),
+    }
+}
+
+/// Whether an exit request must be held back. If so, `quit-blocked` has been
+/// emitted and the caller prevents the exit. Called from the
+/// `RunEvent::ExitRequested` handler.
+pub fn hold_exit(app: &AppHandle) -> bool {
+    if app.get_webview_window("main").is_none() {
+        return false;
+    }
+    if app
+        .try_state::<QuitGuardState>()
+        .is_some_and(|state| state.is_forced(Instant::now()))
+    {
+        return false;
+    }
+    let busy = busy(app);
+    if !busy.any() {
+        return false;
+    }
+    log::info!("Quit held back while transferring: {:?}", busy);
+    crate::window::cancel_quit(app);
+    #[cfg(desktop)]
+    crate::tray::show_main_window(app);
+    let _ = app.emit("quit-blocked", busy);
+    true
+}
+
+/// Quit even though something is still transferring. Exits requested within
+/// the next 10 seconds are no longer held back.
+#[tauri::command]
+pub fn force_quit(app: AppHandle, state: State<'_, QuitGuardState>) {
+    *state.forced_at() = Some(Instant::now());
+    crate::window::quit(&app);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn force_quit_lets_exits_through_for_a_while() {
+        let state = QuitGuardState::default();
+        let now = Instant::now();
+        assert!(!state.is_forced(now));
+
+        *state.forced_at() = Some(now);
+        assert!(state.is_forced(now + Duration::from_secs(9)));
+        assert!(!state.is_forced(now + FORCE_QUIT_WINDOW));
+    }
+
+    #[test]
+    fn busy_activities_serialize_in_camel_case() {
+        let busy = BusyActivities {
+            sending_outbox: true,
+            ..BusyActivities::default()
+        };
+        assert!(busy.any());
+        assert!(!BusyActivities::default().any());
+        let json = serde_json::to_value(busy).unwrap();
+        assert_eq!(json["sendingOutbox"], true);
+        assert_eq!(json["uploading"], false);
+    }
+}
@@ -0,0 +1,125 @@
+//! Screen security: keeping private feeds out of screenshots, screen sharing
+//! and the Android recents screen.
+//!
+//! `set_screen_security` persists the choice in the `screenSecurity` setting
+//! and applies it at once. On Android the native layer sets `FLAG_SECURE` and
+//! keeps its own copy of the flag so `MainActivity` can apply it before the
+//! first frame. On desktop the main window's content protection is set in
+//! `setup` while the window is still hidden; Windows
+//! (`SetWindowDisplayAffinity`) and macOS (`sharingType`) honor it, Linux has
+//! no equivalent and [`ScreenSecurityStatus::honored`] is false there.
+
+use crate::error::CommandError;
+use crate::settings::{self, SettingsState};
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+/// Result of the screen security commands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ScreenSecurityStatus {
+    pub enabled: bool,
+    /// Whether this platform actually hides the window; the setting is kept
+    /// but does nothing where it's false
+    pub honored: bool,
+}
+
+/// Whether the platform can protect the window contents.
+const fn honored() -> bool {
+    cfg!(any(
+        target_os = "android",
+        target_os = "windows",
+        target_os = "macos"
+    ))
+}
+
+fn enabled(app: &AppHandle) -> bool {
+    app.try_state::<SettingsState>()
+        .and_then(|state| state.get().screen_security)
+        .unwrap_or(false)
+}
+
+fn status(app: &AppHandle) -> ScreenSecurityStatus {
+    ScreenSecurityStatus {
+        enabled: enabled(app),
+        honored: honored(),
+    }
+}
+
+/// Apply `enabled` to the platform window.
+fn apply(app: &AppHandle, enabled: bool) -> Result<(), CommandError> {
+    #[cfg(target_os = "android")]
+    {
+        let _ = app;
+        crate::android::set_screen_secure(enabled)
+            .map_err(|e| CommandError::new(crate::error::NATIVE_BRIDGE_FAILURE, e))
+    }
+    #[cfg(desktop)]
+    {
+        let Some(window) = app.get_webview_window("main") else {
+            return Ok(());
+        };
+        window
+            .set_content_protected(enabled)
+            .map_err(|e| CommandError::new(crate::error::UNAVAILABLE, e.to_string()))
+    }
+    #[cfg(target_os = "ios")]
+    {
+        let _ = (app, enabled);
+        Ok(())
+    }
+}
+
+/// Apply the saved preference. Called from `setup` before the main window is
+/// first shown.
+pub fn init(app: &AppHandle) {
+    let enabled = enabled(app);
+    // Android applies the saved flag itself in onCreate
+    if !enabled || cfg!(target_os = "android") || !honored() {
+        return;
+    }
+    if let Err(e) = apply(app, true) {
+        log::warn!("Failed to enable screen security: {}", e);
+    }
+}
+
+/// Block screenshots, screen sharing and app-switcher thumbnails of the app
+/// where the platform supports it, and remember the choice.
+#[tauri::command]
+pub fn set_screen_security(
+    app: AppHandle,
+    enabled: bool,
+) -> Result<ScreenSecurityStatus, CommandError> {
+    if honored() {
+        apply(&app, enabled)?;
+    }
+    settings::set(&app, "screenSecurity", enabled.into())?;
+    Ok(status(&app))
+}
+
+#[tauri::command]
+pub fn get_screen_security(app: AppHandle) -> ScreenSecurityStatus {
+    status(&app)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_serializes_flat() {
+        let status = ScreenSecurityStatus {
+            enabled: true,
+            honored: false,
+        };
+        assert_eq!(
+            serde_json::to_value(status).unwrap(),
+            serde_json::json!({"enabled": true, "honored": false})
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn linux_does_not_honor_content_protection() {
+        assert!(!honored());
+    }
+}
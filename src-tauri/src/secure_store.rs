@@ -0,0 +1,384 @@
+//! Secret storage for auth tokens and other credentials.
+//!
+//! Values live in the OS credential store: Keychain on macOS and iOS,
+//! Credential Manager on Windows, Secret Service on Linux (via the `keyring`
+//! crate), and Keystore-backed EncryptedSharedPreferences on Android via the
+//! `SecureStore` Kotlin object. When no backend is usable (e.g. no Secret
+//! Service daemon on a minimal Linux desktop) values are kept in memory for
+//! the session and [`SecureStoreStatus::persistent`] is false.
+//!
+//! The credential stores can't enumerate entries, so key names (never values)
+//! are tracked in `secure-store-keys.json` for `secure_list_keys`.
+
+use crate::lock::LockState;
+use crate::storage;
+use serde::{Serialize, Serializer};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::sync::Mutex;
+use tauri::{AppHandle, State};
+
+/// Service name the entries are stored under
+#[cfg_attr(target_os = "android", allow(dead_code))]
+const SERVICE: &str = "social.hushnetwork";
+const KEYS_FILE: &str = "secure-store-keys.json";
+#[cfg(not(target_os = "android"))]
+const PROBE_KEY: &str = "__hush_probe__";
+/// Keys with this prefix belong to the app (e.g. [`crate::lock`]) and can't be
+/// reached through the commands
+pub(crate) const RESERVED_PREFIX: &str = "__hush_";
+
+/// Errors from the secure store, sent to the frontend as stable strings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecureStoreError {
+    /// The credential store exists but is locked or access was refused
+    Locked(String),
+    /// The credential store failed or is unreachable
+    Unavailable(String),
+    /// The key or value was rejected (too long, bad encoding, …)
+    Invalid(String),
+    /// The app lock is on; see [`crate::lock`]
+    AppLocked,
+}
+
+impl fmt::Display for SecureStoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SecureStoreError::Locked(e) => write!(f, "secure_store_locked: {}", e),
+            SecureStoreError::Unavailable(e) => write!(f, "secure_store_unavailable: {}", e),
+            SecureStoreError::Invalid(e) => write!(f, "secure_store_invalid: {}", e),
+            SecureStoreError::AppLocked => write!(f, "app_locked: the app is locked"),
+        }
+    }
+}
+
+impl std::error::Error for SecureStoreError {}
+
+impl Serialize for SecureStoreError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+#[cfg(not(target_os = "android"))]
+impl From<keyring::Error> for SecureStoreError {
+    fn from(error: keyring::Error) -> Self {
+        match error {
+            keyring::Error::NoStorageAccess(e) => SecureStoreError::Locked(e.to_string()),
+            keyring::Error::PlatformFailure(e) => SecureStoreError::Unavailable(e.to_string()),
+            other => SecureStoreError::Invalid(other.to_string()),
+        }
+    }
+}
+
+/// Where values are kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    Keyring,
+    Android,
+    Memory,
+}
+
+/// Result of `get_secure_store_status`
+#[derive(Debug, Clone, Serialize)]
+pub struct SecureStoreStatus {
+    pub backend: BackendKind,
+    /// False when values only last until the app exits
+    pub persistent: bool,
+}
+
+/// Managed state for the secure store
+#[derive(Debug)]
+pub struct SecureStoreState {
+    backend: BackendKind,
+    /// Values for the in-memory fallback
+    memory: Mutex<HashMap<String, String>>,
+    /// Known key names, persisted to [`KEYS_FILE`]
+    keys: Mutex<BTreeSet<String>>,
+}
+
+impl SecureStoreState {
+    /// Pick a backend and load the key index.
+    pub fn load(app: &AppHandle) -> Self {
+        let keys = storage::config_file(app, KEYS_FILE)
+            .ok()
+            .and_then(|path| storage::read_json::<BTreeSet<String>>(&path))
+            .unwrap_or_default();
+        let backend = detect_backend();
+        if backend == BackendKind::Memory {
+            log::warn!("No credential store available, secrets will not persist");
+        }
+        Self::with_backend(backend, keys)
+    }
+
+    pub(crate) fn with_backend(backend: BackendKind, keys: BTreeSet<String>) -> Self {
+        Self {
+            backend,
+            memory: Mutex::new(HashMap::new()),
+            keys: Mutex::new(keys),
+        }
+    }
+
+    pub fn status(&self) -> SecureStoreStatus {
+        SecureStoreStatus {
+            backend: self.backend,
+            persistent: self.backend != BackendKind::Memory,
+        }
+    }
+
+    fn memory(&self) -> std::sync::MutexGuard<'_, HashMap<String, String>> {
+        self.memory.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn set(&self, key: &str, value: &str) -> Result<(), SecureStoreError> {
+        validate_key(key)?;
+        match self.backend {
+            BackendKind::Memory => {
+                self.memory().insert(key.to_string(), value.to_string());
+                Ok(())
+            }
+            #[cfg(not(target_os = "android"))]
+            BackendKind::Keyring => Ok(keyring::Entry::new(SERVICE, key)?.set_password(value)?),
+            #[cfg(target_os = "android")]
+            BackendKind::Android => crate::android::secure_store_set(key, value)
+                .map_err(SecureStoreError::Unavailable),
+            _ => Err(unsupported(self.backend)),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Result<Option<String>, SecureStoreError> {
+        validate_key(key)?;
+        match self.backend {
+            BackendKind::Memory => Ok(self.memory().get(key).cloned()),
+            #[cfg(not(target_os = "android"))]
+            BackendKind::Keyring => match keyring::Entry::new(SERVICE, key)?.get_password() {
+                Ok(value) => Ok(Some(value)),
+                Err(keyring::Error::NoEntry) => Ok(None),
+                Err(e) => Err(e.into()),
+            },
+            #[cfg(target_os = "android")]
+            BackendKind::Android => {
+                crate::android::secure_store_get(key).map_err(SecureStoreError::Unavailable)
+            }
+            _ => Err(unsupported(self.backend)),
+        }
+    }
+
+    /// Delete `key`; deleting a missing key succeeds.
+    pub fn delete(&self, key: &str) -> Result<(), SecureStoreError> {
+        validate_key(key)?;
+        match self.backend {
+            BackendKind::Memory => {
+                self.memory().remove(key);
+                Ok(())
+            }
+            #[cfg(not(target_os = "android"))]
+            BackendKind::Keyring => match keyring::Entry::new(SERVICE, key)?.delete_credential() {
+                Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+                Err(e) => Err(e.into()),
+            },
+            #[cfg(target_os = "android")]
+            BackendKind::Android => {
+                crate::android::secure_store_delete(key).map_err(SecureStoreError::Unavailable)
+            }
+            _ => Err(unsupported(self.backend)),
+        }
+    }
+
+    pub fn keys(&self) -> Vec<String> {
+        self.keys
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Delete every tracked secret; returns how many were deleted. Stops at the
+    /// first failure, leaving the rest tracked.
+    pub fn clear(&self, app: &AppHandle) -> Result<usize, SecureStoreError> {
+        let keys = self.keys();
+        for key in &keys {
+            self.delete(key)?;
+            self.track_key(Some(app), key, false);
+        }
+        Ok(keys.len())
+    }
+
+    /// Add or remove `key` from the index, persisting it when it changes.
+    pub(crate) fn track_key(&self, app: Option<&AppHandle>, key: &str, present: bool) {
+        let mut keys = self.keys.lock().unwrap_or_else(|e| e.into_inner());
+        let changed = if present {
+            keys.insert(key.to_string())
+        } else {
+            keys.remove(key)
+        };
+        let Some(app) = app.filter(|_| changed) else {
+            return;
+        };
+        let result = storage::config_file(app, KEYS_FILE)
+            .and_then(|path| storage::write_json_atomic(&path, &*keys));
+        if let Err(e) = result {
+            log::warn!("Failed to save secure store key index: {}", e);
+        }
+    }
+}
+
+fn unsupported(backend: BackendKind) -> SecureStoreError {
+    SecureStoreError::Unavailable(format!("{:?} backend not available on this platform", backend))
+}
+
+fn validate_key(key: &str) -> Result<(), SecureStoreError> {
+    if key.is_empty() {
+        return Err(SecureStoreError::Invalid("key must not be empty".to_string()));
+    }
+    Ok(())
+}
+
+/// Keys the frontend may use: anything but the app's own reserved keys.
+fn validate_command_key(key: &str) -> Result<(), SecureStoreError> {
+    if key.starts_with(RESERVED_PREFIX) {
+        return Err(SecureStoreError::Invalid(format!("key '{}' is reserved", key)));
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "android")]
+fn detect_backend() -> BackendKind {
+    BackendKind::Android
+}
+
+/// Use the OS keyring unless it is missing altogether.
+///
+/// A locked keyring still counts as available: it may be unlocked later, and
+/// the frontend gets a `secure_store_locked` error meanwhile.
+#[cfg(not(target_os = "android"))]
+fn detect_backend() -> BackendKind {
+    let probe = keyring::Entry::new(SERVICE, PROBE_KEY).and_then(|entry| entry.get_password());
+    match probe {
+        Ok(_) | Err(keyring::Error::NoEntry) | Err(keyring::Error::NoStorageAccess(_)) => {
+            BackendKind::Keyring
+        }
+        Err(e) => {
+            log::warn!("Credential store unavailable: {}", e);
+            BackendKind::Memory
+        }
+    }
+}
+
+/// Store a secret under `key`.
+#[tauri::command]
+pub fn secure_set(
+    app: AppHandle,
+    state: State<'_, SecureStoreState>,
+    key: String,
+    value: String,
+) -> Result<(), SecureStoreError> {
+    validate_command_key(&key)?;
+    state.set(&key, &value)?;
+    state.track_key(Some(&app), &key, true);
+    Ok(())
+}
+
+/// Read the secret stored under `key`, or None if there is none. Fails while
+/// the app is locked.
+#[tauri::command]
+pub fn secure_get(
+    state: State<'_, SecureStoreState>,
+    lock: State<'_, LockState>,
+    key: String,
+) -> Result<Option<String>, SecureStoreError> {
+    validate_command_key(&key)?;
+    if lock.is_locked() {
+        return Err(SecureStoreError::AppLocked);
+    }
+    state.get(&key)
+}
+
+/// Delete the secret stored under `key`.
+#[tauri::command]
+pub fn secure_delete(
+    app: AppHandle,
+    state: State<'_, SecureStoreState>,
+    key: String,
+) -> Result<(), SecureStoreError> {
+    validate_command_key(&key)?;
+    state.delete(&key)?;
+    state.track_key(Some(&app), &key, false);
+    Ok(())
+}
+
+/// Names of the stored secrets, sorted.
+#[tauri::command]
+pub fn secure_list_keys(state: State<'_, SecureStoreState>) -> Vec<String> {
+    state.keys()
+}
+
+/// Which backend holds secrets and whether they survive a restart.
+#[tauri::command]
+pub fn get_secure_store_status(state: State<'_, SecureStoreState>) -> SecureStoreStatus {
+    state.status()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory_store() -> SecureStoreState {
+        SecureStoreState::with_backend(BackendKind::Memory, BTreeSet::new())
+    }
+
+    #[test]
+    fn memory_backend_round_trips_and_is_not_persistent() {
+        let store = memory_store();
+        assert!(!store.status().persistent);
+
+        store.set("session", "secret").unwrap();
+        assert_eq!(store.get("session").unwrap().as_deref(), Some("secret"));
+
+        store.delete("session").unwrap();
+        assert_eq!(store.get("session").unwrap(), None);
+        // Deleting again is fine
+        store.delete("session").unwrap();
+    }
+
+    #[test]
+    fn key_index_tracks_set_and_delete() {
+        let store = memory_store();
+        store.track_key(None, "b", true);
+        store.track_key(None, "a", true);
+        store.track_key(None, "a", true);
+        assert_eq!(store.keys(), vec!["a", "b"]);
+
+        store.track_key(None, "b", false);
+        assert_eq!(store.keys(), vec!["a"]);
+    }
+
+    #[test]
+    fn empty_keys_are_rejected() {
+        let store = memory_store();
+        assert!(matches!(
+            store.set("", "value"),
+            Err(SecureStoreError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn reserved_keys_are_rejected_for_commands() {
+        assert!(validate_command_key("session").is_ok());
+        assert!(matches!(
+            validate_command_key(crate::lock::HASH_KEY),
+            Err(SecureStoreError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn errors_serialize_as_prefixed_strings() {
+        let error = SecureStoreError::Locked("collection is locked".to_string());
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            "secure_store_locked: collection is locked"
+        );
+    }
+}
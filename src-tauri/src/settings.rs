@@ -0,0 +1,598 @@
+//! Typed app settings persisted to `settings.json` in the app config dir.
+//!
+//! [`Settings`] has typed fields for everything Rust code reads; all other
+//! keys (set by the frontend, or by a newer build) are kept in
+//! [`Settings::other`] so a round-trip through an older build doesn't drop
+//! them. Writes go through a Mutex and are atomic (temp file + rename), and
+//! every change emits `settings-changed`.
+//!
+//! Settings can be exported to and imported from a versioned JSON file to set
+//! up another machine. Secrets never live here (they are in the secure store),
+//! so exports contain none, and neither do the machine-local keys in
+//! [`MACHINE_LOCAL_KEYS`]. Imported network and log settings are checked the
+//! way their own setters check them, and take effect right away.
+
+use crate::doh::{self, DohConfig};
+use crate::error::CommandError;
+use crate::navigation_state::NavigationState;
+use crate::net::{self, ProxyConfig};
+use crate::pinning::{self, PinSet};
+use crate::theme::ThemeKind;
+use crate::updates::{AutoUpdateMode, UpdateChannel};
+use crate::window::WindowGeometry;
+use crate::{logging, storage};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+pub(crate) const SETTINGS_FILE: &str = "settings.json";
+/// Version written to (and required in) settings export files
+const EXPORT_VERSION: u32 = 1;
+/// Keys that only make sense on the machine that wrote them, left out of
+/// exports and ignored on import. `torSocksPort` also has to pass the
+/// handshake check in `set_tor_mode`.
+const MACHINE_LOCAL_KEYS: &[&str] = &[
+    "navigationState",
+    "feedWindowGeometry",
+    "torSocksPort",
+    "skippedUpdateVersion",
+    "updateSnoozedUntil",
+];
+/// Keys the shared HTTP client is built from
+const NETWORK_KEYS: &[&str] = &["proxy", "pinnedCertificates", "doh", "httpFetchSchemes"];
+const LOG_KEYS: &[&str] = &["logLevel", "logModuleLevels"];
+
+/// App settings
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Settings {
+    /// Size cap for the offline cache in bytes; None uses the built-in default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_max_bytes: Option<u64>,
+    /// Base URL of the Hush server the outbox delivers to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server_url: Option<String>,
+    /// Minutes between background syncs; 0 or unset disables them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sync_interval_minutes: Option<u32>,
+    /// How Rust-side traffic reaches the network; see [`crate::net`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<ProxyConfig>,
+    /// Local Tor SOCKS port while Tor mode is on; overrides `proxy`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tor_socks_port: Option<u16>,
+    /// SPKI pins per host; see [`crate::pinning`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pinned_certificates: Option<PinSet>,
+    /// URL schemes `http_fetch` may request; `["https"]` when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_fetch_schemes: Option<Vec<String>>,
+    /// DNS-over-HTTPS, on while set; see [`crate::doh`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub doh: Option<DohConfig>,
+    /// Release channel the updater checks
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub update_channel: Option<UpdateChannel>,
+    /// Update version the user chose not to be prompted about
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skipped_update_version: Option<String>,
+    /// No update prompts until this time (Unix ms)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub update_snoozed_until: Option<u64>,
+    /// Background update checks and downloads
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_update: Option<AutoUpdateMode>,
+    /// Explicit theme for the native window chrome; follows the OS while unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub window_theme: Option<ThemeKind>,
+    /// Battery percentage below which background work slows down on battery
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub power_throttle_threshold: Option<u8>,
+    /// Downloads the download manager runs at once
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_concurrent_downloads: Option<u32>,
+    /// Size cap for the image cache in bytes; None uses the built-in default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_cache_max_bytes: Option<u64>,
+    /// Largest file accepted by drag-and-drop, in bytes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub drop_max_bytes: Option<u64>,
+    /// Log level for all targets without an override (`off` to `trace`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_level: Option<String>,
+    /// Per-module log levels, keyed by module path
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_module_levels: Option<BTreeMap<String, String>>,
+    /// Zoom factor of the main webview; see [`crate::zoom`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub zoom_factor: Option<f64>,
+    /// Hide the window from screenshots and screen sharing; see
+    /// [`crate::screen_security`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub screen_security: Option<bool>,
+    /// Geometry of each feed's pop-out window, keyed by feed id; see
+    /// [`crate::feed_windows`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub feed_window_geometry: Option<BTreeMap<String, WindowGeometry>>,
+    /// Accelerator per action where it differs from the default; see
+    /// [`crate::app_shortcuts`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub app_shortcuts: Option<BTreeMap<String, String>>,
+    /// Notifications per feed shown before the rest are collapsed into a
+    /// summary; 0 turns grouping off. See [`crate::notifications`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notification_group_threshold: Option<u32>,
+    /// Window in seconds over which `notificationGroupThreshold` counts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notification_group_window_secs: Option<u32>,
+    /// Flash the taskbar or bounce the dock for mentions (default on); see
+    /// [`crate::attention`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attention_on_mention: Option<bool>,
+    /// Route to reopen on the next launch; see [`crate::navigation_state`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub navigation_state: Option<NavigationState>,
+    /// Keys without a typed field, preserved verbatim
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+impl Settings {
+    fn to_map(&self) -> Result<Map<String, Value>, String> {
+        match serde_json::to_value(self).map_err(|e| e.to_string())? {
+            Value::Object(map) => Ok(map),
+            _ => Err("Settings did not serialize to an object".to_string()),
+        }
+    }
+
+    fn from_map(map: Map<String, Value>) -> Result<Self, String> {
+        serde_json::from_value(Value::Object(map)).map_err(|e| e.to_string())
+    }
+
+    /// The value of `key`, or None if it isn't set.
+    pub fn get(&self, key: &str) -> Option<Value> {
+        self.to_map()
+            .ok()?
+            .remove(key)
+            .filter(|value| !value.is_null())
+    }
+
+    /// A copy with `key` set to `value`; `null` resets the key to its default.
+    ///
+    /// Fails if `value` has the wrong type for a typed field.
+    pub fn with(&self, key: &str, value: Value) -> Result<Self, String> {
+        let mut map = self.to_map()?;
+        if value.is_null() {
+            map.remove(key);
+        } else {
+            map.insert(key.to_string(), value);
+        }
+        Self::from_map(map).map_err(|e| format!("Invalid value for setting {}: {}", key, e))
+    }
+}
+
+/// Contents of a settings export file
+#[derive(Debug, Serialize, Deserialize)]
+struct SettingsExport {
+    version: u32,
+    settings: Map<String, Value>,
+}
+
+/// Result of `import_settings`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ImportSummary {
+    pub applied: usize,
+    /// Keys whose value had the wrong type for this build, or that their
+    /// setter would have rejected
+    pub skipped: Vec<String>,
+}
+
+/// Payload of the `settings-changed` event
+#[derive(Debug, Clone, Serialize)]
+pub struct SettingsChangedPayload {
+    pub key: String,
+    pub value: Value,
+}
+
+/// Managed state holding the current settings
+#[derive(Debug, Default)]
+pub struct SettingsState {
+    settings: Mutex<Settings>,
+    /// Backing file; `None` keeps settings in memory only
+    path: Option<PathBuf>,
+}
+
+impl SettingsState {
+    /// Load settings from the app config dir.
+    pub fn load(app: &AppHandle) -> Self {
+        let path = storage::config_file(app, SETTINGS_FILE).ok();
+        let settings = path
+            .as_deref()
+            .and_then(storage::read_json)
+            .unwrap_or_default();
+        Self {
+            settings: Mutex::new(settings),
+            path,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Settings> {
+        self.settings.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn get(&self) -> Settings {
+        self.lock().clone()
+    }
+
+    /// Set `key`, persisting the result before it becomes current.
+    ///
+    /// Returns the stored value (`null` if the key was reset).
+    pub fn set(&self, key: &str, value: Value) -> Result<Value, String> {
+        let mut settings = self.lock();
+        let updated = settings.with(key, value)?;
+        if let Some(path) = &self.path {
+            storage::write_json_atomic(path, &updated)?;
+        }
+        *settings = updated;
+        Ok(settings.get(key).unwrap_or(Value::Null))
+    }
+
+    /// Forget every setting and delete the settings file.
+    pub fn reset(&self) -> Result<(), String> {
+        let mut settings = self.lock();
+        if let Some(path) = &self.path {
+            storage::remove_file(path)?;
+        }
+        *settings = Settings::default();
+        Ok(())
+    }
+
+    /// Merge `imported` into the current settings key by key, persisting the result.
+    ///
+    /// Keys whose value is invalid are skipped; keys not in the import are kept,
+    /// and so are the machine-local ones. Returns the applied keys with their
+    /// stored values, and the skipped keys.
+    fn merge(
+        &self,
+        imported: Map<String, Value>,
+    ) -> Result<(Vec<(String, Value)>, Vec<String>), String> {
+        let mut settings = self.lock();
+        let mut updated = settings.clone();
+        let mut applied = Vec::new();
+        let mut skipped = Vec::new();
+        for (key, value) in imported {
+            if MACHINE_LOCAL_KEYS.contains(&key.as_str()) {
+                continue;
+            }
+            match validate_import(&key, value).and_then(|value| updated.with(&key, value)) {
+                Ok(next) => {
+                    updated = next;
+                    applied.push(key);
+                }
+                Err(e) => {
+                    log::warn!("Skipping imported setting: {}", e);
+                    skipped.push(key);
+                }
+            }
+        }
+
+        if let Some(path) = &self.path {
+            storage::write_json_atomic(path, &updated)?;
+        }
+        *settings = updated;
+        let applied = applied
+            .into_iter()
+            .map(|key| {
+                let value = settings.get(&key).unwrap_or(Value::Null);
+                (key, value)
+            })
+            .collect();
+        Ok((applied, skipped))
+    }
+}
+
+/// Check an imported network or log setting the way its setter does,
+/// returning the value to store. Other keys are returned unchanged.
+fn validate_import(key: &str, value: Value) -> Result<Value, String> {
+    fn parse<T: DeserializeOwned>(key: &str, value: Value) -> Result<T, String> {
+        serde_json::from_value(value)
+            .map_err(|e| format!("Invalid value for setting {}: {}", key, e))
+    }
+    if value.is_null() {
+        return Ok(value);
+    }
+    let invalid = |e: String| format!("Invalid value for setting {}: {}", key, e);
+    match key {
+        "proxy" => {
+            let config: ProxyConfig = parse(key, value.clone())?;
+            net::check_proxy_config(&config).map_err(invalid)?;
+            Ok(value)
+        }
+        "pinnedCertificates" => {
+            let pins: PinSet = parse(key, value)?;
+            let pins = pinning::normalize_pins(pins).map_err(invalid)?;
+            serde_json::to_value(pins).map_err(|e| e.to_string())
+        }
+        "doh" => {
+            let config: DohConfig = parse(key, value.clone())?;
+            if let Some(url) = &config.resolver_url {
+                doh::parse_resolver_url(url).map_err(invalid)?;
+            }
+            Ok(value)
+        }
+        "httpFetchSchemes" => {
+            let schemes: Vec<String> = parse(key, value)?;
+            let schemes: Vec<String> = schemes
+                .iter()
+                .map(|scheme| scheme.trim().to_ascii_lowercase())
+                .collect();
+            if let Some(scheme) = schemes
+                .iter()
+                .find(|s| !matches!(s.as_str(), "http" | "https"))
+            {
+                return Err(invalid(format!("{} URLs can't be fetched", scheme)));
+            }
+            Ok(schemes.into())
+        }
+        "logLevel" => {
+            let level: String = parse(key, value.clone())?;
+            logging::parse_level(&level).map_err(invalid)?;
+            Ok(value)
+        }
+        "logModuleLevels" => {
+            let levels: BTreeMap<String, String> = parse(key, value.clone())?;
+            for level in levels.values() {
+                logging::parse_level(level).map_err(invalid)?;
+            }
+            Ok(value)
+        }
+        _ => Ok(value),
+    }
+}
+
+/// Parse an export file, checking its version.
+fn parse_export(contents: &str) -> Result<Map<String, Value>, String> {
+    let export: SettingsExport =
+        serde_json::from_str(contents).map_err(|e| format!("Not a settings export: {}", e))?;
+    if export.version != EXPORT_VERSION {
+        return Err(format!(
+            "Unsupported settings export version {} (expected {})",
+            export.version, EXPORT_VERSION
+        ));
+    }
+    Ok(export.settings)
+}
+
+/// Set a setting from Rust code and emit `settings-changed`.
+pub fn set(app: &AppHandle, key: &str, value: Value) -> Result<(), String> {
+    let value = app.state::<SettingsState>().set(key, value)?;
+    let _ = app.emit(
+        "settings-changed",
+        SettingsChangedPayload {
+            key: key.to_string(),
+            value,
+        },
+    );
+    Ok(())
+}
+
+/// Read one setting; None if it isn't set.
+#[tauri::command]
+pub fn get_setting(state: State<'_, SettingsState>, key: String) -> Option<Value> {
+    state.get().get(&key)
+}
+
+/// Change one setting, or reset it to its default with `null`.
+///
+/// A value of the wrong type fails with `invalid_argument`.
+#[tauri::command]
+pub fn set_setting(app: AppHandle, key: String, value: Value) -> Result<(), CommandError> {
+    app.state::<SettingsState>()
+        .get()
+        .with(&key, value.clone())
+        .map_err(CommandError::invalid_argument)?;
+    Ok(set(&app, &key, value)?)
+}
+
+/// All settings as a JSON object.
+#[tauri::command]
+pub fn get_all_settings(
+    state: State<'_, SettingsState>,
+) -> Result<Map<String, Value>, CommandError> {
+    Ok(state.get().to_map()?)
+}
+
+/// The settings to export: everything but the machine-local keys.
+fn exported(settings: &Settings) -> Result<Map<String, Value>, String> {
+    let mut map = settings.to_map()?;
+    map.retain(|key, _| !MACHINE_LOCAL_KEYS.contains(&key.as_str()));
+    Ok(map)
+}
+
+/// Write all settings but the machine-local ones to `path` as
+/// pretty-printed, versioned JSON.
+#[tauri::command]
+pub fn export_settings(state: State<'_, SettingsState>, path: String) -> Result<(), CommandError> {
+    let export = SettingsExport {
+        version: EXPORT_VERSION,
+        settings: exported(&state.get())?,
+    };
+    Ok(storage::write_json_atomic(
+        std::path::Path::new(&path),
+        &export,
+    )?)
+}
+
+/// Merge settings from an export file at `path` into the current settings.
+/// Imported network and log settings are applied right away.
+///
+/// A file that isn't a settings export fails with `invalid_argument`.
+#[tauri::command]
+pub fn import_settings(
+    app: AppHandle,
+    state: State<'_, SettingsState>,
+    path: String,
+) -> Result<ImportSummary, CommandError> {
+    let contents = std::fs::read_to_string(&path)?;
+    let imported = parse_export(&contents).map_err(CommandError::invalid_argument)?;
+    let (applied, skipped) = state.merge(imported)?;
+
+    let summary = ImportSummary {
+        applied: applied.len(),
+        skipped,
+    };
+    let applied_any = |keys: &[&str]| applied.iter().any(|(key, _)| keys.contains(&key.as_str()));
+    if applied_any(NETWORK_KEYS) {
+        if applied_any(&["doh"]) {
+            doh::reload(&app);
+        }
+        net::rebuild(&app);
+    }
+    if applied_any(LOG_KEYS) {
+        logging::reload(&app);
+    }
+    for (key, value) in applied {
+        let _ = app.emit("settings-changed", SettingsChangedPayload { key, value });
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn unknown_keys_survive_round_trip() {
+        let settings: Settings =
+            serde_json::from_value(json!({"futureFlag": true, "nested": {"a": 1}})).unwrap();
+        let round_trip = serde_json::to_value(&settings).unwrap();
+        assert_eq!(round_trip, json!({"futureFlag": true, "nested": {"a": 1}}));
+    }
+
+    #[test]
+    fn set_and_reset_a_key() {
+        let state = SettingsState::default();
+        assert_eq!(
+            state.set("sendOnEnter", json!(false)).unwrap(),
+            json!(false)
+        );
+        assert_eq!(state.get().get("sendOnEnter"), Some(json!(false)));
+
+        assert_eq!(state.set("sendOnEnter", Value::Null).unwrap(), Value::Null);
+        assert_eq!(state.get().get("sendOnEnter"), None);
+    }
+
+    #[test]
+    fn set_persists_atomically_to_disk() {
+        let dir = std::env::temp_dir().join(format!("hush-settings-{}", std::process::id()));
+        let path = dir.join(SETTINGS_FILE);
+        let state = SettingsState {
+            settings: Mutex::new(Settings::default()),
+            path: Some(path.clone()),
+        };
+
+        state.set("a", json!(1)).unwrap();
+        state.set("b", json!("two")).unwrap();
+
+        let saved: Settings = storage::read_json(&path).unwrap();
+        assert_eq!(saved.get("a"), Some(json!(1)));
+        assert_eq!(saved.get("b"), Some(json!("two")));
+        assert!(!dir.join("settings.json.tmp").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn typed_fields_are_validated() {
+        let state = SettingsState::default();
+        state.set("cacheMaxBytes", json!(1024)).unwrap();
+        assert_eq!(state.get().cache_max_bytes, Some(1024));
+
+        assert!(state.set("cacheMaxBytes", json!("big")).is_err());
+        assert_eq!(state.get().cache_max_bytes, Some(1024));
+
+        let imported = json!({"cacheMaxBytes": "big", "theme": "dark"});
+        let (applied, skipped) = state.merge(imported.as_object().unwrap().clone()).unwrap();
+        assert_eq!(applied, vec![("theme".to_string(), json!("dark"))]);
+        assert_eq!(skipped, vec!["cacheMaxBytes"]);
+        assert_eq!(state.get().cache_max_bytes, Some(1024));
+    }
+
+    #[test]
+    fn export_import_round_trip_merges() {
+        let source = SettingsState::default();
+        source.set("theme", json!("dark")).unwrap();
+        source.set("futureFlag", json!({"x": 1})).unwrap();
+        let export = serde_json::to_string_pretty(&SettingsExport {
+            version: EXPORT_VERSION,
+            settings: source.get().to_map().unwrap(),
+        })
+        .unwrap();
+
+        let target = SettingsState::default();
+        target.set("localOnly", json!(true)).unwrap();
+        let (applied, skipped) = target.merge(parse_export(&export).unwrap()).unwrap();
+
+        assert_eq!(applied.len(), 2);
+        assert!(skipped.is_empty());
+        let merged = target.get();
+        assert_eq!(merged.get("theme"), Some(json!("dark")));
+        assert_eq!(merged.get("futureFlag"), Some(json!({"x": 1})));
+        assert_eq!(merged.get("localOnly"), Some(json!(true)));
+    }
+
+    #[test]
+    fn imported_network_settings_are_checked_like_their_setters() {
+        let state = SettingsState::default();
+        let pin = "sha256/".to_string() + &"A".repeat(43) + "=";
+        let imported = json!({
+            "proxy": {"mode": "custom", "url": "ftp://proxy.corp:21"},
+            "pinnedCertificates": {" API.Hush.Example ": {"spkiHashes": [pin]}},
+            "logLevel": "chatty",
+            "httpFetchSchemes": ["HTTPS"],
+        });
+        let (applied, mut skipped) = state.merge(imported.as_object().unwrap().clone()).unwrap();
+        skipped.sort();
+        assert_eq!(skipped, vec!["logLevel", "proxy"]);
+        assert_eq!(applied.len(), 2);
+
+        let settings = state.get();
+        assert_eq!(settings.proxy, None);
+        assert_eq!(settings.http_fetch_schemes, Some(vec!["https".to_string()]));
+        let pins = settings.pinned_certificates.unwrap();
+        assert_eq!(
+            pins["api.hush.example"].spki_hashes,
+            vec!["A".repeat(43) + "="]
+        );
+    }
+
+    #[test]
+    fn machine_local_keys_stay_on_their_machine() {
+        let state = SettingsState::default();
+        state.set("torSocksPort", json!(9050)).unwrap();
+        state.set("skippedUpdateVersion", json!("1.2.3")).unwrap();
+        state.set("theme", json!("dark")).unwrap();
+        let export = exported(&state.get()).unwrap();
+        assert_eq!(export.keys().collect::<Vec<_>>(), vec!["theme"]);
+
+        let target = SettingsState::default();
+        let imported = json!({"torSocksPort": 9150, "theme": "dark"});
+        let (applied, skipped) = target.merge(imported.as_object().unwrap().clone()).unwrap();
+        assert_eq!(applied, vec![("theme".to_string(), json!("dark"))]);
+        assert!(skipped.is_empty());
+        assert_eq!(target.get().tor_socks_port, None);
+    }
+
+    #[test]
+    fn import_rejects_wrong_version() {
+        let error = parse_export(r#"{"version": 99, "settings": {}}"#).unwrap_err();
+        assert!(error.contains("version 99"));
+        assert!(parse_export(r#"{"settings": {}}"#).is_err());
+        assert!(parse_export("not json").is_err());
+    }
+}
@@ -0,0 +1,100 @@
+//! Sharing posts to other apps.
+//!
+//! On Android `share_content` opens the system share sheet (`ShareHelper`,
+//! an `ACTION_SEND` chooser); files go through the app's FileProvider, so
+//! they must be under the app's files or cache dir. Elsewhere the text and
+//! URL are copied to the clipboard instead. There is no native iOS project in
+//! this tree yet, so iOS takes the clipboard path too.
+//!
+//! The result says which mechanism was used. Android's chooser doesn't report
+//! whether the user picked a target or dismissed it, so `completed` is `None`
+//! there; a clipboard copy always completes.
+
+use serde::Serialize;
+use tauri::AppHandle;
+#[cfg(not(target_os = "android"))]
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShareMethod {
+    ShareSheet,
+    Clipboard,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ShareResult {
+    pub method: ShareMethod,
+    /// Whether the user finished sharing; `None` where the platform doesn't say
+    pub completed: Option<bool>,
+}
+
+/// Text placed on the clipboard: the text and URL, one per line.
+#[cfg(not(target_os = "android"))]
+fn clipboard_text(text: Option<&str>, url: Option<&str>) -> Option<String> {
+    let parts: Vec<&str> = [text, url]
+        .into_iter()
+        .flatten()
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect();
+    (!parts.is_empty()).then(|| parts.join("\n"))
+}
+
+/// Share text, a link and/or a file through the platform's share mechanism.
+#[tauri::command]
+pub fn share_content(
+    app: AppHandle,
+    text: Option<String>,
+    url: Option<String>,
+    file_path: Option<String>,
+) -> Result<ShareResult, String> {
+    if text.is_none() && url.is_none() && file_path.is_none() {
+        return Err("Nothing to share".to_string());
+    }
+
+    #[cfg(target_os = "android")]
+    {
+        let _ = app;
+        if crate::android::share(text.as_deref(), url.as_deref(), file_path.as_deref())? {
+            Ok(ShareResult {
+                method: ShareMethod::ShareSheet,
+                completed: None,
+            })
+        } else {
+            Err("Could not open the share sheet".to_string())
+        }
+    }
+    #[cfg(not(target_os = "android"))]
+    {
+        let Some(contents) = clipboard_text(text.as_deref(), url.as_deref()) else {
+            let _ = file_path;
+            return Err("Sharing files is not supported on this platform".to_string());
+        };
+        app.clipboard()
+            .write_text(contents)
+            .map_err(|e| e.to_string())?;
+        Ok(ShareResult {
+            method: ShareMethod::Clipboard,
+            completed: Some(true),
+        })
+    }
+}
+
+#[cfg(all(test, not(target_os = "android")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clipboard_text_joins_non_empty_parts() {
+        assert_eq!(
+            clipboard_text(Some("Look at this"), Some("https://hush.social/p/1")),
+            Some("Look at this\nhttps://hush.social/p/1".to_string())
+        );
+        assert_eq!(
+            clipboard_text(Some("  "), Some("https://hush.social/p/1")),
+            Some("https://hush.social/p/1".to_string())
+        );
+        assert_eq!(clipboard_text(None, None), None);
+    }
+}
@@ -0,0 +1,217 @@
+//! System-wide shortcut that toggles the main window.
+//!
+//! The chosen accelerator (e.g. "CommandOrControl+Shift+H") is persisted to the
+//! app config dir and registered on startup. Global shortcuts only exist on
+//! desktop; on mobile the commands report that they are unsupported.
+
+use crate::error::CommandError;
+use crate::storage;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::{AppHandle, State};
+#[cfg(desktop)]
+use tauri::Manager;
+#[cfg(desktop)]
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutEvent, ShortcutState};
+
+const SHORTCUT_FILE: &str = "global-shortcut.json";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ShortcutConfig {
+    #[serde(default)]
+    toggle_window: Option<String>,
+}
+
+/// Managed state holding the currently registered toggle accelerator
+#[derive(Debug, Default)]
+pub struct ToggleShortcutState {
+    accelerator: Mutex<Option<String>>,
+}
+
+impl ToggleShortcutState {
+    pub fn accelerator(&self) -> Option<String> {
+        self.accelerator
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    fn set_accelerator(&self, accelerator: Option<String>) {
+        *self.accelerator.lock().unwrap_or_else(|e| e.into_inner()) = accelerator;
+    }
+}
+
+fn persist(app: &AppHandle, accelerator: Option<&str>) -> Result<(), String> {
+    let path = storage::config_file(app, SHORTCUT_FILE)?;
+    storage::write_json_atomic(
+        &path,
+        &ShortcutConfig {
+            toggle_window: accelerator.map(str::to_string),
+        },
+    )
+}
+
+#[cfg(desktop)]
+fn parse_shortcut(accelerator: &str) -> Result<Shortcut, CommandError> {
+    accelerator.parse::<Shortcut>().map_err(|e| {
+        CommandError::invalid_argument(format!("Invalid shortcut '{}': {}", accelerator, e))
+    })
+}
+
+/// Show and focus the main window if it is hidden or unfocused, otherwise hide it.
+#[cfg(desktop)]
+fn toggle_main_window(app: &AppHandle) {
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+
+    let visible = window.is_visible().unwrap_or(false);
+    let minimized = window.is_minimized().unwrap_or(false);
+    let focused = window.is_focused().unwrap_or(false);
+
+    if visible && !minimized && focused {
+        let _ = window.hide();
+    } else {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+/// Global shortcut handler installed with the plugin.
+#[cfg(desktop)]
+pub fn handle_shortcut(app: &AppHandle, shortcut: &Shortcut, event: ShortcutEvent) {
+    if event.state() != ShortcutState::Pressed {
+        return;
+    }
+    if crate::zoom::handle_shortcut(app, shortcut) {
+        return;
+    }
+    if crate::app_shortcuts::handle_shortcut(app, shortcut) {
+        return;
+    }
+
+    let is_toggle = app
+        .try_state::<ToggleShortcutState>()
+        .and_then(|state| state.accelerator())
+        .and_then(|accelerator| parse_shortcut(&accelerator).ok())
+        .is_some_and(|toggle| &toggle == shortcut);
+
+    if is_toggle {
+        toggle_main_window(app);
+    }
+}
+
+/// Register `accelerator` as the toggle shortcut, replacing any previous one.
+#[cfg(desktop)]
+fn register(
+    app: &AppHandle,
+    state: &ToggleShortcutState,
+    accelerator: &str,
+) -> Result<(), CommandError> {
+    let shortcut = parse_shortcut(accelerator)?;
+    let previous = state.accelerator();
+    if previous.as_deref() == Some(accelerator) && app.global_shortcut().is_registered(shortcut) {
+        return Ok(());
+    }
+
+    if let Some(previous) = previous.as_deref().and_then(|p| parse_shortcut(p).ok()) {
+        let _ = app.global_shortcut().unregister(previous);
+    }
+
+    if let Err(e) = app.global_shortcut().register(shortcut) {
+        // Put the old shortcut back so a failed change doesn't leave none registered
+        if let Some(previous) = previous.as_deref().and_then(|p| parse_shortcut(p).ok()) {
+            let _ = app.global_shortcut().register(previous);
+        }
+        return Err(CommandError::invalid_argument(format!(
+            "Could not register shortcut '{}'; it may already be in use by another application ({})",
+            accelerator, e
+        )));
+    }
+
+    state.set_accelerator(Some(accelerator.to_string()));
+    Ok(())
+}
+
+/// Register the persisted toggle shortcut, if any. Called from `setup`.
+pub fn init(app: &AppHandle) {
+    let config = storage::config_file(app, SHORTCUT_FILE)
+        .ok()
+        .and_then(|path| storage::read_json::<ShortcutConfig>(&path))
+        .unwrap_or_default();
+
+    #[cfg(desktop)]
+    if let Some(accelerator) = config.toggle_window {
+        let state = app.state::<ToggleShortcutState>();
+        if let Err(e) = register(app, &state, &accelerator) {
+            log::warn!("Failed to register saved toggle shortcut: {}", e);
+        }
+    }
+    #[cfg(mobile)]
+    let _ = config;
+}
+
+/// Register a system-wide shortcut that toggles the main window and persist it.
+#[tauri::command]
+pub fn register_toggle_shortcut(
+    app: AppHandle,
+    state: State<'_, ToggleShortcutState>,
+    accelerator: String,
+) -> Result<(), CommandError> {
+    #[cfg(desktop)]
+    {
+        let accelerator = accelerator.trim();
+        register(&app, &state, accelerator)?;
+        Ok(persist(&app, Some(accelerator))?)
+    }
+    #[cfg(mobile)]
+    {
+        let _ = (app, state, accelerator);
+        Err(CommandError::new(
+            crate::error::NOT_SUPPORTED,
+            "Global shortcuts are not supported on this platform",
+        ))
+    }
+}
+
+/// Remove the toggle shortcut and forget it.
+#[tauri::command]
+pub fn unregister_toggle_shortcut(
+    app: AppHandle,
+    state: State<'_, ToggleShortcutState>,
+) -> Result<(), CommandError> {
+    #[cfg(desktop)]
+    if let Some(shortcut) = state
+        .accelerator()
+        .and_then(|accelerator| parse_shortcut(&accelerator).ok())
+    {
+        app.global_shortcut()
+            .unregister(shortcut)
+            .map_err(|e| e.to_string())?;
+    }
+
+    state.set_accelerator(None);
+    Ok(persist(&app, None)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shortcut_config_round_trips() {
+        let config = ShortcutConfig {
+            toggle_window: Some("CommandOrControl+Shift+H".to_string()),
+        };
+        let json = serde_json::to_string(&config).unwrap();
+        let parsed: ShortcutConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.toggle_window, config.toggle_window);
+    }
+
+    #[test]
+    fn invalid_accelerator_is_rejected() {
+        assert!(parse_shortcut("Ctrl+Shift+H").is_ok());
+        assert!(parse_shortcut("NotAKey+++").is_err());
+    }
+}
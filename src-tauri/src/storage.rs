@@ -0,0 +1,166 @@
+//! JSON file helpers for small pieces of persisted app state.
+//!
+//! Files live in the app config or data directory and are always written
+//! atomically (temp file + rename) so a crash mid-write leaves the previous
+//! contents intact.
+
+use serde::{de::DeserializeOwned, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+/// Path of `file_name` inside the app config directory.
+pub fn config_file(app: &AppHandle, file_name: &str) -> Result<PathBuf, String> {
+    app.path()
+        .app_config_dir()
+        .map(|dir| dir.join(file_name))
+        .map_err(|e| e.to_string())
+}
+
+/// Path of `file_name` inside the app data directory.
+pub fn data_file(app: &AppHandle, file_name: &str) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(file_name))
+        .map_err(|e| e.to_string())
+}
+
+/// Read and parse a JSON file, returning None if it is missing or invalid.
+///
+/// Parse failures are logged so a corrupt file is visible without failing startup.
+pub fn read_json<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+        Err(e) => {
+            log::warn!("Failed to read {}: {}", path.display(), e);
+            return None;
+        }
+    };
+
+    match serde_json::from_str(&contents) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            log::warn!("Ignoring invalid JSON in {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Serialize `value` to `path` atomically, creating parent directories as needed.
+pub fn write_json_atomic<T: Serialize + ?Sized>(path: &Path, value: &T) -> Result<(), String> {
+    let contents = serde_json::to_vec_pretty(value).map_err(|e| e.to_string())?;
+    write_atomic(path, &contents)
+}
+
+/// Write raw bytes to `path` atomically, creating parent directories as needed.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    fs::write(&tmp, contents).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, path).map_err(|e| {
+        let _ = fs::remove_file(&tmp);
+        e.to_string()
+    })
+}
+
+/// Bytes taken by the file at `path`, or by every file below it if it's a
+/// directory. Missing paths and unreadable entries count as 0.
+pub fn disk_usage(path: &Path) -> u64 {
+    let Ok(meta) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !meta.is_dir() {
+        return meta.len();
+    }
+    fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| disk_usage(&entry.path()))
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// Delete `path`, treating a missing file as success.
+pub fn remove_file(path: &Path) -> Result<(), String> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Delete the file or directory at `path` and return the bytes it took.
+/// A missing path frees nothing.
+pub fn remove_all(path: &Path) -> Result<u64, String> {
+    let Ok(meta) = fs::symlink_metadata(path) else {
+        return Ok(0);
+    };
+    let bytes = disk_usage(path);
+    let result = if meta.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    };
+    match result {
+        Ok(()) => Ok(bytes),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(0),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Sample {
+        value: u32,
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("hush-storage-test-{}", std::process::id()))
+            .join(name)
+    }
+
+    #[test]
+    fn write_then_read_round_trips() {
+        let path = temp_path("round-trip.json");
+        write_json_atomic(&path, &Sample { value: 7 }).unwrap();
+        assert_eq!(read_json::<Sample>(&path), Some(Sample { value: 7 }));
+        remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn missing_or_invalid_files_read_as_none() {
+        let path = temp_path("invalid.json");
+        assert_eq!(read_json::<Sample>(&path), None);
+
+        write_atomic(&path, b"{ not json").unwrap();
+        assert_eq!(read_json::<Sample>(&path), None);
+        remove_file(&path).unwrap();
+        // Removing twice is fine
+        remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn remove_all_reports_the_bytes_freed() {
+        let dir = temp_path("remove-all");
+        write_atomic(&dir.join("a"), &[0u8; 10]).unwrap();
+        write_atomic(&dir.join("nested").join("b"), &[0u8; 5]).unwrap();
+        assert_eq!(remove_all(&dir.join("a")).unwrap(), 10);
+        assert_eq!(remove_all(&dir).unwrap(), 5);
+        assert!(!dir.exists());
+        assert_eq!(remove_all(&dir).unwrap(), 0);
+    }
+}
@@ -0,0 +1,188 @@
+//! System suspend and resume, so connections don't sit dead after wake.
+//!
+//! On Linux, logind's `PrepareForSleep` signal (read from `gdbus monitor`)
+//! reports both edges. Everywhere, a task started in `setup` also compares the
+//! wall clock with the monotonic clock every [`CLOCK_CHECK_INTERVAL`]: a jump
+//! of more than [`SLEEP_THRESHOLD`] means the machine slept and has just woken.
+//!
+//! Suspend emits `system-suspend`, engages the app lock and closes the live
+//! stream. Resume locks too, in case only the clock noticed the sleep, emits
+//! `system-resume`, reconnects the live and push streams with a fresh backoff,
+//! re-probes connectivity, retries the outbox and, after [`RESUME_SYNC_DELAY`]
+//! for the network to come back, forces a background sync.
+
+use crate::background_sync::BackgroundSyncState;
+use crate::connectivity::ConnectivityState;
+use crate::live_stream::LiveStreamState;
+use crate::outbox::OutboxState;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+use tauri::{AppHandle, Emitter, Manager};
+
+/// How often the fallback detector compares clocks
+const CLOCK_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+/// Wall-clock drift beyond the check interval that counts as having slept
+const SLEEP_THRESHOLD: Duration = Duration::from_secs(2 * 60);
+/// A second resume report within this window is the same wake
+const RESUME_DEBOUNCE: Duration = Duration::from_secs(30);
+const RESUME_SYNC_DELAY: Duration = Duration::from_secs(5);
+
+/// Managed state used to merge native and detected resumes
+#[derive(Debug, Default)]
+pub struct SuspendState {
+    last_resume: Mutex<Option<Instant>>,
+}
+
+/// Whether the wall clock moved further than the monotonic clock by more than
+/// [`SLEEP_THRESHOLD`], meaning the machine slept in between.
+fn slept(wall_elapsed: Duration, monotonic_elapsed: Duration) -> bool {
+    wall_elapsed.saturating_sub(monotonic_elapsed) > SLEEP_THRESHOLD
+}
+
+fn on_suspend(app: &AppHandle) {
+    log::info!("System is going to sleep");
+    let _ = app.emit("system-suspend", ());
+    crate::lock::on_suspend(app);
+    app.state::<LiveStreamState>().pause_for_sleep();
+}
+
+fn on_resume(app: &AppHandle) {
+    {
+        let state = app.state::<SuspendState>();
+        let mut last = state.last_resume.lock().unwrap_or_else(|e| e.into_inner());
+        if last.is_some_and(|last| last.elapsed() < RESUME_DEBOUNCE) {
+            return;
+        }
+        *last = Some(Instant::now());
+    }
+
+    log::info!("System woke from sleep");
+    // Only the clock detector may have noticed the sleep
+    crate::lock::on_suspend(app);
+    let _ = app.emit("system-resume", ());
+    app.state::<LiveStreamState>().restart_after_sleep();
+    #[cfg(desktop)]
+    if let Some(push) = app.try_state::<crate::push_stream::PushStreamState>() {
+        push.resume();
+    }
+    app.state::<ConnectivityState>().probe_now();
+    app.state::<OutboxState>().resume();
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(RESUME_SYNC_DELAY).await;
+        app.state::<BackgroundSyncState>().sync_now();
+    });
+}
+
+/// Detect wake from the wall clock jumping ahead of the monotonic clock.
+async fn watch_clock(app: AppHandle) {
+    let mut wall = SystemTime::now();
+    let mut monotonic = Instant::now();
+    loop {
+        tokio::time::sleep(CLOCK_CHECK_INTERVAL).await;
+        let wall_elapsed = wall.elapsed().unwrap_or_default();
+        if slept(wall_elapsed, monotonic.elapsed()) {
+            on_resume(&app);
+        }
+        wall = SystemTime::now();
+        monotonic = Instant::now();
+    }
+}
+
+/// Parse a `PrepareForSleep` line from `gdbus monitor`: `Some(true)` before
+/// sleep, `Some(false)` after wake.
+#[cfg(target_os = "linux")]
+fn parse_prepare_for_sleep(line: &str) -> Option<bool> {
+    let args = line.split("PrepareForSleep").nth(1)?;
+    if args.contains("true") {
+        Some(true)
+    } else if args.contains("false") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Follow logind's `PrepareForSleep` signal. Blocks until `gdbus` exits.
+#[cfg(target_os = "linux")]
+fn watch_logind(app: AppHandle) {
+    use std::io::{BufRead, BufReader};
+    use std::process::{Command, Stdio};
+
+    let child = Command::new("gdbus")
+        .args([
+            "monitor",
+            "--system",
+            "--dest",
+            "org.freedesktop.login1",
+            "--object-path",
+            "/org/freedesktop/login1",
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            log::info!("logind sleep signals unavailable: {}", e);
+            return;
+        }
+    };
+    if let Some(stdout) = child.stdout.take() {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            match parse_prepare_for_sleep(&line) {
+                Some(true) => on_suspend(&app),
+                Some(false) => on_resume(&app),
+                None => {}
+            }
+        }
+    }
+    let _ = child.wait();
+    log::info!("Stopped following logind sleep signals");
+}
+
+/// Start watching for sleep and wake. Called from `setup` after the stream,
+/// outbox, connectivity and background sync state are managed.
+pub fn init(app: &AppHandle) {
+    tauri::async_runtime::spawn(watch_clock(app.clone()));
+    #[cfg(target_os = "linux")]
+    {
+        let app = app.clone();
+        std::thread::spawn(move || watch_logind(app));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slept_only_when_wall_clock_runs_well_ahead() {
+        let interval = CLOCK_CHECK_INTERVAL;
+        assert!(!slept(interval, interval));
+        assert!(!slept(interval + Duration::from_secs(60), interval));
+        assert!(slept(interval + Duration::from_secs(600), interval));
+        // Wall clock set backwards
+        assert!(!slept(Duration::ZERO, interval));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn prepare_for_sleep_lines_are_parsed() {
+        let line = |arg: &str| {
+            format!(
+                "/org/freedesktop/login1: org.freedesktop.login1.Manager.PrepareForSleep ({},)",
+                arg
+            )
+        };
+        assert_eq!(parse_prepare_for_sleep(&line("true")), Some(true));
+        assert_eq!(parse_prepare_for_sleep(&line("false")), Some(false));
+        assert_eq!(
+            parse_prepare_for_sleep(
+                "/org/freedesktop/login1: org.freedesktop.login1.Manager.SessionNew ('3', objectpath '/x')"
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,118 @@
+//! What the app is running on, for the diagnostics screen.
+//!
+//! The hostname is left out unless asked for, so the output can be pasted
+//! into a public issue.
+
+use serde::Serialize;
+use sysinfo::System;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemInfo {
+    pub os_name: Option<String>,
+    pub os_version: Option<String>,
+    pub kernel_version: Option<String>,
+    pub arch: &'static str,
+    /// Logical cores
+    pub cpu_cores: usize,
+    /// Bytes
+    pub total_memory: u64,
+    /// Bytes
+    pub available_memory: u64,
+    /// Linux only, from `XDG_CURRENT_DESKTOP`
+    pub desktop_environment: Option<String>,
+    /// Linux only: `wayland` or `x11`
+    pub display_server: Option<String>,
+    /// Only with `include_hostname`
+    pub hostname: Option<String>,
+}
+
+/// The Linux display server from the session environment.
+fn display_server(var: impl Fn(&str) -> Option<String>) -> Option<String> {
+    match var("XDG_SESSION_TYPE").as_deref() {
+        Some("wayland") => return Some("wayland".to_string()),
+        Some("x11") => return Some("x11".to_string()),
+        _ => {}
+    }
+    if var("WAYLAND_DISPLAY").is_some() {
+        Some("wayland".to_string())
+    } else if var("DISPLAY").is_some() {
+        Some("x11".to_string())
+    } else {
+        None
+    }
+}
+
+pub(crate) fn collect(include_hostname: bool) -> SystemInfo {
+    let mut system = System::new();
+    system.refresh_memory();
+    let env = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+    let linux = cfg!(target_os = "linux");
+
+    SystemInfo {
+        os_name: System::name(),
+        os_version: System::long_os_version().or_else(System::os_version),
+        kernel_version: System::kernel_version(),
+        arch: std::env::consts::ARCH,
+        cpu_cores: std::thread::available_parallelism().map_or(1, |cores| cores.get()),
+        total_memory: system.total_memory(),
+        available_memory: system.available_memory(),
+        desktop_environment: linux
+            .then(|| env("XDG_CURRENT_DESKTOP").or_else(|| env("DESKTOP_SESSION")))
+            .flatten(),
+        display_server: linux.then(|| display_server(env)).flatten(),
+        hostname: include_hostname.then(System::host_name).flatten(),
+    }
+}
+
+#[tauri::command]
+pub async fn get_system_info(include_hostname: Option<bool>) -> Result<SystemInfo, String> {
+    let include_hostname = include_hostname.unwrap_or(false);
+    tauri::async_runtime::spawn_blocking(move || collect(include_hostname))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn display_server_with(vars: &[(&str, &str)]) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        display_server(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn display_server_prefers_session_type() {
+        assert_eq!(
+            display_server_with(&[
+                ("XDG_SESSION_TYPE", "x11"),
+                ("WAYLAND_DISPLAY", "wayland-0")
+            ])
+            .as_deref(),
+            Some("x11")
+        );
+        assert_eq!(
+            display_server_with(&[
+                ("XDG_SESSION_TYPE", "tty"),
+                ("WAYLAND_DISPLAY", "wayland-0")
+            ])
+            .as_deref(),
+            Some("wayland")
+        );
+        assert_eq!(
+            display_server_with(&[("DISPLAY", ":0")]).as_deref(),
+            Some("x11")
+        );
+        assert_eq!(display_server_with(&[]), None);
+    }
+
+    #[test]
+    fn hostname_is_omitted_by_default() {
+        assert_eq!(collect(false).hostname, None);
+    }
+}
@@ -0,0 +1,253 @@
+//! Progress shown on the taskbar button (Windows) or dock icon (macOS).
+//!
+//! The download manager and the upload flag ([`crate::file_drop`]) report
+//! their aggregate progress here automatically: the downloads' combined
+//! fraction when their sizes are known, indeterminate otherwise, and
+//! indeterminate while an upload is flagged, since its total isn't known on
+//! this side. `set_task_progress` lets the frontend show its own progress
+//! instead; a manual call wins over the automatic one for
+//! [`MANUAL_OVERRIDE`], after which automation resumes. Other platforms have
+//! no progress indicator, so nothing is shown there.
+
+use crate::error::CommandError;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State};
+
+/// How long a `set_task_progress` call overrides the automatic progress
+pub const MANUAL_OVERRIDE: Duration = Duration::from_secs(60);
+
+/// What the progress indicator shows
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TaskProgress {
+    None,
+    Indeterminate,
+    /// Fraction done, 0.0–1.0
+    Normal(f64),
+    Paused(f64),
+    Error(f64),
+}
+
+impl TaskProgress {
+    /// Parse a `set_task_progress` call. `fraction` is clamped to 0.0–1.0;
+    /// without one the indicator is cleared, except in indeterminate mode.
+    fn parse(kind: &str, fraction: Option<f64>) -> Result<Self, CommandError> {
+        if fraction.is_some_and(f64::is_nan) {
+            return Err(CommandError::invalid_argument("Progress fraction is NaN"));
+        }
+        let fraction = fraction.map(|fraction| fraction.clamp(0.0, 1.0));
+        match (kind, fraction) {
+            ("indeterminate", _) => Ok(Self::Indeterminate),
+            ("normal" | "paused" | "error", None) => Ok(Self::None),
+            ("normal", Some(fraction)) => Ok(Self::Normal(fraction)),
+            ("paused", Some(fraction)) => Ok(Self::Paused(fraction)),
+            ("error", Some(fraction)) => Ok(Self::Error(fraction)),
+            _ => Err(CommandError::invalid_argument(format!(
+                "Unknown progress kind: {}",
+                kind
+            ))),
+        }
+    }
+
+    /// The same progress in whole percent, so tiny changes don't redraw.
+    fn percent(self) -> Option<u64> {
+        match self {
+            Self::None | Self::Indeterminate => None,
+            Self::Normal(fraction) | Self::Paused(fraction) | Self::Error(fraction) => {
+                Some((fraction * 100.0).round() as u64)
+            }
+        }
+    }
+}
+
+/// Something reporting progress automatically
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Downloads,
+    Uploads,
+}
+
+#[derive(Debug)]
+struct Progress {
+    downloads: TaskProgress,
+    uploads: TaskProgress,
+    /// The frontend's progress and when it stops overriding the automatic one
+    manual: Option<(TaskProgress, Instant)>,
+    shown: TaskProgress,
+}
+
+impl Default for Progress {
+    fn default() -> Self {
+        Self {
+            downloads: TaskProgress::None,
+            uploads: TaskProgress::None,
+            manual: None,
+            shown: TaskProgress::None,
+        }
+    }
+}
+
+impl Progress {
+    /// What should be shown at `now`: the manual progress while it lasts,
+    /// then downloads, then uploads.
+    fn current(&mut self, now: Instant) -> TaskProgress {
+        if let Some((progress, until)) = self.manual {
+            if now < until {
+                return progress;
+            }
+            self.manual = None;
+        }
+        match self.downloads {
+            TaskProgress::None => self.uploads,
+            downloads => downloads,
+        }
+    }
+}
+
+/// Managed state combining the manual and automatic progress
+#[derive(Debug, Default)]
+pub struct TaskProgressState {
+    progress: Mutex<Progress>,
+}
+
+impl TaskProgressState {
+    fn lock(&self) -> MutexGuard<'_, Progress> {
+        self.progress.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+fn show(app: &AppHandle, progress: TaskProgress) {
+    use tauri::window::{ProgressBarState, ProgressBarStatus};
+
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+    let status = match progress {
+        TaskProgress::None => ProgressBarStatus::None,
+        TaskProgress::Indeterminate => ProgressBarStatus::Indeterminate,
+        TaskProgress::Normal(_) => ProgressBarStatus::Normal,
+        TaskProgress::Paused(_) => ProgressBarStatus::Paused,
+        TaskProgress::Error(_) => ProgressBarStatus::Error,
+    };
+    let state = ProgressBarState {
+        status: Some(status),
+        progress: progress.percent(),
+    };
+    if let Err(e) = window.set_progress_bar(state) {
+        log::debug!("Failed to set task progress: {}", e);
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn show(_app: &AppHandle, _progress: TaskProgress) {}
+
+/// Show whatever should be shown now, if it changed.
+fn refresh(app: &AppHandle, state: &TaskProgressState) {
+    let mut progress = state.lock();
+    let current = progress.current(Instant::now());
+    let shown = progress.shown;
+    let redraw = std::mem::discriminant(&current) != std::mem::discriminant(&shown)
+        || current.percent() != shown.percent();
+    if redraw {
+        progress.shown = current;
+        show(app, current);
+    }
+}
+
+/// Report the aggregate progress of `source`.
+pub fn report(app: &AppHandle, source: Source, value: TaskProgress) {
+    let Some(state) = app.try_state::<TaskProgressState>() else {
+        return;
+    };
+    {
+        let mut progress = state.lock();
+        match source {
+            Source::Downloads => progress.downloads = value,
+            Source::Uploads => progress.uploads = value,
+        }
+    }
+    refresh(app, &state);
+}
+
+/// Show the frontend's own progress on the taskbar or dock for the next
+/// minute, overriding the automatic download and upload progress.
+///
+/// `kind` is "normal", "paused", "error" or "indeterminate"; `fraction`
+/// (clamped to 0.0–1.0) is ignored in indeterminate mode, and leaving it out
+/// otherwise clears the indicator. Nothing is shown outside Windows and macOS.
+#[tauri::command]
+pub fn set_task_progress(
+    app: AppHandle,
+    state: State<'_, TaskProgressState>,
+    kind: String,
+    fraction: Option<f64>,
+) -> Result<(), CommandError> {
+    let value = TaskProgress::parse(&kind, fraction)?;
+    let until = Instant::now() + MANUAL_OVERRIDE;
+    state.lock().manual = Some((value, until));
+    refresh(&app, &state);
+
+    // Hand back to the automatic progress once the override runs out
+    std::thread::spawn(move || {
+        std::thread::sleep(MANUAL_OVERRIDE);
+        let state = app.state::<TaskProgressState>();
+        let expired = state
+            .lock()
+            .manual
+            .is_some_and(|(_, manual_until)| manual_until <= Instant::now());
+        if expired {
+            refresh(&app, &state);
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_clamps_and_clears() {
+        assert_eq!(
+            TaskProgress::parse("normal", Some(1.7)).unwrap(),
+            TaskProgress::Normal(1.0)
+        );
+        assert_eq!(
+            TaskProgress::parse("error", Some(-0.2)).unwrap(),
+            TaskProgress::Error(0.0)
+        );
+        assert_eq!(
+            TaskProgress::parse("normal", None).unwrap(),
+            TaskProgress::None
+        );
+        assert_eq!(
+            TaskProgress::parse("indeterminate", None).unwrap(),
+            TaskProgress::Indeterminate
+        );
+        assert!(TaskProgress::parse("spinning", Some(0.5)).is_err());
+        assert!(TaskProgress::parse("normal", Some(f64::NAN)).is_err());
+    }
+
+    #[test]
+    fn manual_progress_overrides_automation_until_it_expires() {
+        let now = Instant::now();
+        let mut progress = Progress {
+            downloads: TaskProgress::Normal(0.4),
+            uploads: TaskProgress::Indeterminate,
+            ..Progress::default()
+        };
+        assert_eq!(progress.current(now), TaskProgress::Normal(0.4));
+
+        progress.manual = Some((TaskProgress::Paused(0.9), now + MANUAL_OVERRIDE));
+        assert_eq!(progress.current(now), TaskProgress::Paused(0.9));
+        assert_eq!(
+            progress.current(now + MANUAL_OVERRIDE),
+            TaskProgress::Normal(0.4)
+        );
+        assert!(progress.manual.is_none());
+
+        progress.downloads = TaskProgress::None;
+        assert_eq!(progress.current(now), TaskProgress::Indeterminate);
+    }
+}
@@ -0,0 +1,336 @@
+//! The OS light/dark theme and accent color, and the native window theme.
+//!
+//! The webview's `prefers-color-scheme` doesn't update reliably everywhere,
+//! so the frontend follows the OS through `get_system_theme` and the
+//! `system-theme-changed` event instead. On desktop the theme comes from the
+//! main window (`WindowEvent::ThemeChanged`); on Android from the activity's
+//! `uiMode`, re-read whenever the window regains focus.
+//!
+//! `set_window_theme` forces the native chrome (title bar) to an explicit
+//! theme. The choice is saved as the `windowTheme` setting and reapplied at
+//! startup. While forced, the window reports the forced theme, so the last
+//! OS theme seen before is reported instead.
+
+use crate::settings::{self, SettingsState};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeKind {
+    Light,
+    Dark,
+}
+
+impl From<tauri::Theme> for ThemeKind {
+    fn from(theme: tauri::Theme) -> Self {
+        match theme {
+            tauri::Theme::Dark => Self::Dark,
+            _ => Self::Light,
+        }
+    }
+}
+
+impl From<ThemeKind> for tauri::Theme {
+    fn from(theme: ThemeKind) -> Self {
+        match theme {
+            ThemeKind::Light => Self::Light,
+            ThemeKind::Dark => Self::Dark,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemTheme {
+    pub theme: ThemeKind,
+    /// `#rrggbb`, where the OS exposes one
+    pub accent_color: Option<String>,
+}
+
+/// Managed state for theme tracking
+#[derive(Debug, Default)]
+pub struct ThemeState {
+    /// Last OS theme the main window reported
+    system: Mutex<Option<ThemeKind>>,
+    /// Whether `set_window_theme` forced an explicit theme
+    forced: AtomicBool,
+    /// Last value emitted, to only emit changes
+    last: Mutex<Option<SystemTheme>>,
+}
+
+fn hex_rgb(r: u8, g: u8, b: u8) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// Parse the portal's `accent-color` as printed by gdbus, e.g.
+/// `(<<(0.2078, 0.5176, 0.8941)>>,)`. Components outside 0..=1 mean unset.
+#[cfg(target_os = "linux")]
+fn parse_portal_accent(output: &str) -> Option<String> {
+    let start = output.rfind('(')? + 1;
+    let end = start + output[start..].find(')')?;
+    let components: Vec<f64> = output[start..end]
+        .split(',')
+        .map(|part| part.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    let [r, g, b] = components[..] else {
+        return None;
+    };
+    let channel = |value: f64| {
+        (0.0..=1.0)
+            .contains(&value)
+            .then(|| (value * 255.0).round() as u8)
+    };
+    Some(hex_rgb(channel(r)?, channel(g)?, channel(b)?))
+}
+
+/// Parse `reg query` output for the DWM `AccentColor` DWORD (ABGR).
+#[cfg(target_os = "windows")]
+fn parse_dwm_accent(output: &str) -> Option<String> {
+    let value = output
+        .lines()
+        .find(|line| line.contains("AccentColor"))?
+        .split_whitespace()
+        .last()?;
+    let abgr = u32::from_str_radix(value.trim_start_matches("0x"), 16).ok()?;
+    Some(hex_rgb(abgr as u8, (abgr >> 8) as u8, (abgr >> 16) as u8))
+}
+
+/// Map `AppleAccentColor` to the color macOS draws for it.
+#[cfg(target_os = "macos")]
+fn macos_accent(value: &str) -> Option<String> {
+    let hex = match value.trim() {
+        "-1" => "#8c8c8c",
+        "0" => "#ff5257",
+        "1" => "#f7821b",
+        "2" => "#ffc600",
+        "3" => "#62ba46",
+        "4" => "#007aff",
+        "5" => "#a550a7",
+        "6" => "#f74f9e",
+        _ => return None,
+    };
+    Some(hex.to_string())
+}
+
+/// The OS accent color. Blocking.
+fn accent_color() -> Option<String> {
+    #[cfg(any(target_os = "linux", target_os = "windows", target_os = "macos"))]
+    let output = |program: &str, args: &[&str]| {
+        std::process::Command::new(program)
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+
+    #[cfg(target_os = "linux")]
+    {
+        output(
+            "gdbus",
+            &[
+                "call",
+                "--session",
+                "--dest",
+                "org.freedesktop.portal.Desktop",
+                "--object-path",
+                "/org/freedesktop/portal/desktop",
+                "--method",
+                "org.freedesktop.portal.Settings.Read",
+                "org.freedesktop.appearance",
+                "accent-color",
+            ],
+        )
+        .and_then(|stdout| parse_portal_accent(&stdout))
+    }
+    #[cfg(target_os = "windows")]
+    {
+        output(
+            "reg",
+            &[
+                "query",
+                r"HKCU\Software\Microsoft\Windows\DWM",
+                "/v",
+                "AccentColor",
+            ],
+        )
+        .and_then(|stdout| parse_dwm_accent(&stdout))
+    }
+    #[cfg(target_os = "macos")]
+    {
+        output("defaults", &["read", "-g", "AppleAccentColor"])
+            .and_then(|stdout| macos_accent(&stdout))
+    }
+    #[cfg(target_os = "android")]
+    {
+        crate::android::accent_color()
+            .map_err(|e| log::debug!("Accent color unavailable: {}", e))
+            .ok()
+            .map(|argb| hex_rgb((argb >> 16) as u8, (argb >> 8) as u8, argb as u8))
+    }
+    #[cfg(target_os = "ios")]
+    {
+        None
+    }
+}
+
+/// The OS theme, falling back to the last one seen while a theme is forced.
+fn os_theme(app: &AppHandle, state: &ThemeState) -> ThemeKind {
+    #[cfg(target_os = "android")]
+    {
+        let _ = (app, state);
+        match crate::android::is_night_mode() {
+            Ok(true) => ThemeKind::Dark,
+            Ok(false) => ThemeKind::Light,
+            Err(e) => {
+                log::debug!("Night mode unavailable: {}", e);
+                ThemeKind::Light
+            }
+        }
+    }
+    #[cfg(not(target_os = "android"))]
+    {
+        let mut system = state.system.lock().unwrap_or_else(|e| e.into_inner());
+        if !state.forced.load(Ordering::SeqCst) {
+            if let Some(theme) = app
+                .get_webview_window("main")
+                .and_then(|window| window.theme().ok())
+            {
+                *system = Some(theme.into());
+            }
+        }
+        system.unwrap_or(ThemeKind::Light)
+    }
+}
+
+/// The current OS theme and accent. Blocking.
+fn current(app: &AppHandle) -> SystemTheme {
+    let state = app.state::<ThemeState>();
+    SystemTheme {
+        theme: os_theme(app, &state),
+        accent_color: accent_color(),
+    }
+}
+
+/// Re-read the OS theme and emit `system-theme-changed` if it changed.
+pub fn refresh(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let theme = current(&app);
+        let state = app.state::<ThemeState>();
+        let mut last = state.last.lock().unwrap_or_else(|e| e.into_inner());
+        if last.as_ref() != Some(&theme) {
+            *last = Some(theme.clone());
+            drop(last);
+            let _ = app.emit("system-theme-changed", &theme);
+        }
+    });
+}
+
+/// Handle `WindowEvent::ThemeChanged` on the main window.
+pub fn on_theme_changed(app: &AppHandle, theme: tauri::Theme) {
+    let state = app.state::<ThemeState>();
+    if state.forced.load(Ordering::SeqCst) {
+        return;
+    }
+    *state.system.lock().unwrap_or_else(|e| e.into_inner()) = Some(theme.into());
+    refresh(app);
+}
+
+fn apply_window_theme(app: &AppHandle, theme: Option<ThemeKind>) {
+    let state = app.state::<ThemeState>();
+    if theme.is_some() && !state.forced.load(Ordering::SeqCst) {
+        // Remember the OS theme before the window starts reporting the forced one
+        os_theme(app, &state);
+    }
+    state.forced.store(theme.is_some(), Ordering::SeqCst);
+
+    #[cfg(desktop)]
+    for window in app.webview_windows().values() {
+        if let Err(e) = window.set_theme(theme.map(Into::into)) {
+            log::warn!("Failed to set theme of window {}: {}", window.label(), e);
+        }
+    }
+}
+
+/// Apply the saved window theme and record the starting OS theme. Called from
+/// `setup`.
+pub fn init(app: &AppHandle) {
+    let saved = app.state::<SettingsState>().get().window_theme;
+    if saved.is_some() {
+        apply_window_theme(app, saved);
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let theme = current(&app);
+        *app.state::<ThemeState>()
+            .last
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = Some(theme);
+    });
+}
+
+#[tauri::command]
+pub async fn get_system_theme(app: AppHandle) -> Result<SystemTheme, String> {
+    tauri::async_runtime::spawn_blocking(move || current(&app))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Force the native window chrome to `"light"` or `"dark"`; `None` or
+/// `"system"` follows the OS again.
+#[tauri::command]
+pub fn set_window_theme(
+    app: AppHandle,
+    state: State<'_, ThemeState>,
+    theme: Option<String>,
+) -> Result<(), String> {
+    let theme = match theme.as_deref() {
+        None | Some("system") => None,
+        Some("light") => Some(ThemeKind::Light),
+        Some("dark") => Some(ThemeKind::Dark),
+        Some(other) => return Err(format!("Unknown theme: {}", other)),
+    };
+    let was_forced = state.forced.load(Ordering::SeqCst);
+    apply_window_theme(&app, theme);
+    settings::set(
+        &app,
+        "windowTheme",
+        serde_json::to_value(theme).map_err(|e| e.to_string())?,
+    )?;
+    if was_forced && theme.is_none() {
+        refresh(&app);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_is_lowercase_rgb() {
+        assert_eq!(hex_rgb(0x35, 0x84, 0xe4), "#3584e4");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn portal_accent_is_parsed() {
+        assert_eq!(
+            parse_portal_accent("(<<(0.20784, 0.51765, 0.89412)>>,)\n").as_deref(),
+            Some("#3584e4")
+        );
+        assert_eq!(parse_portal_accent("(<<(-1.0, -1.0, -1.0)>>,)"), None);
+        assert_eq!(parse_portal_accent(""), None);
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn dwm_accent_is_parsed() {
+        let output = "\r\nHKEY_CURRENT_USER\\Software\\Microsoft\\Windows\\DWM\r\n    AccentColor    REG_DWORD    0xffd77800\r\n";
+        assert_eq!(parse_dwm_accent(output).as_deref(), Some("#0078d7"));
+    }
+}
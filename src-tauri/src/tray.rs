@@ -0,0 +1,653 @@
+//! System tray icon and context menu.
+//!
+//! Left click shows the main window; right click opens a menu with
+//! "Show Hush", "Compact Mode", "Check for Updates", and "Quit". Everything
+//! the tray shows (icon variant, unread badge, tooltip) is owned by
+//! [`TrayManager`], which is kept in managed state so commands can reach it.
+//! The tray only exists on desktop; the commands below are no-ops on mobile.
+
+use serde::{Deserialize, Serialize};
+#[cfg(desktop)]
+use std::collections::HashMap;
+#[cfg(desktop)]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(desktop)]
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use tauri::AppHandle;
+#[cfg(desktop)]
+use tauri::{
+    image::Image,
+    menu::{CheckMenuItem, Menu, MenuEvent, MenuItem, PredefinedMenuItem},
+    tray::{MouseButton, MouseButtonState, TrayIcon, TrayIconBuilder, TrayIconEvent},
+    App, Emitter, Manager, Wry,
+};
+
+use crate::error::{self, CommandError};
+#[cfg(desktop)]
+use crate::updates::{self, CheckOutcome};
+
+#[cfg(desktop)]
+const MENU_SHOW: &str = "tray-show";
+#[cfg(desktop)]
+const MENU_COMPACT: &str = "tray-compact";
+#[cfg(desktop)]
+const MENU_CHECK_UPDATES: &str = "tray-check-updates";
+#[cfg(desktop)]
+const MENU_QUIT: &str = "tray-quit";
+
+/// Connection state reflected by the tray icon variant and tooltip
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConnectionState {
+    #[default]
+    Connected,
+    Disconnected,
+    Syncing,
+}
+
+impl ConnectionState {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "connected" => Ok(ConnectionState::Connected),
+            "disconnected" => Ok(ConnectionState::Disconnected),
+            "syncing" => Ok(ConnectionState::Syncing),
+            other => Err(format!(
+                "Unknown connection state '{}', expected connected, disconnected, or syncing",
+                other
+            )),
+        }
+    }
+
+    /// Text appended to the tooltip title, if any.
+    fn tooltip_suffix(self) -> Option<&'static str> {
+        match self {
+            ConnectionState::Connected => None,
+            ConnectionState::Disconnected => Some("offline"),
+            ConnectionState::Syncing => Some("syncing…"),
+        }
+    }
+}
+
+/// Bundled icon for a non-default connection state, decoded on first use.
+#[cfg(desktop)]
+fn variant_icon(state: ConnectionState) -> Option<&'static Image<'static>> {
+    static DISCONNECTED: OnceLock<Option<Image<'static>>> = OnceLock::new();
+    static SYNCING: OnceLock<Option<Image<'static>>> = OnceLock::new();
+
+    fn load(bytes: &'static [u8]) -> Option<Image<'static>> {
+        Image::from_bytes(bytes)
+            .map(|icon| icon.to_owned())
+            .map_err(|e| log::warn!("Failed to decode tray icon variant: {}", e))
+            .ok()
+    }
+
+    match state {
+        ConnectionState::Connected => None,
+        ConnectionState::Disconnected => DISCONNECTED
+            .get_or_init(|| load(include_bytes!("../icons/tray/disconnected.png")))
+            .as_ref(),
+        ConnectionState::Syncing => SYNCING
+            .get_or_init(|| load(include_bytes!("../icons/tray/syncing.png")))
+            .as_ref(),
+    }
+}
+
+/// What the tray is currently showing
+#[cfg(desktop)]
+struct TrayDisplay {
+    connection: ConnectionState,
+    unread_count: u32,
+    tooltip_lines: Vec<String>,
+    badge_cache: HashMap<(ConnectionState, String), Image<'static>>,
+}
+
+/// Managed state owning the tray icon handle and everything it displays
+#[cfg(desktop)]
+pub struct TrayManager {
+    tray: TrayIcon<Wry>,
+    base_icon: Image<'static>,
+    compact: CheckMenuItem<Wry>,
+    check_updates: MenuItem<Wry>,
+    update_check_in_flight: AtomicBool,
+    display: Mutex<TrayDisplay>,
+}
+
+#[cfg(desktop)]
+impl TrayManager {
+    fn display(&self) -> MutexGuard<'_, TrayDisplay> {
+        self.display.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Set the unread count drawn as a badge (0 removes the badge) and shown
+    /// in the tooltip.
+    pub fn set_unread_count(&self, count: u32) -> Result<(), String> {
+        let mut display = self.display();
+        if display.unread_count == count {
+            return Ok(());
+        }
+        display.unread_count = count;
+        self.refresh_icon(&mut display)?;
+        self.refresh_tooltip(&display)
+    }
+
+    /// Set the feed names listed in the tooltip.
+    pub fn set_tooltip_lines(&self, lines: Vec<String>) -> Result<(), String> {
+        let mut display = self.display();
+        display.tooltip_lines = lines;
+        self.refresh_tooltip(&display)
+    }
+
+    /// Switch the icon variant and tooltip suffix for a new connection state.
+    pub fn set_connection_state(&self, state: ConnectionState) -> Result<(), String> {
+        let mut display = self.display();
+        if display.connection == state {
+            return Ok(());
+        }
+        display.connection = state;
+        self.refresh_icon(&mut display)?;
+        self.refresh_tooltip(&display)
+    }
+
+    fn refresh_icon(&self, display: &mut TrayDisplay) -> Result<(), String> {
+        let base = variant_icon(display.connection).unwrap_or(&self.base_icon);
+        let icon = match badge_label(display.unread_count) {
+            None => base.clone(),
+            Some(label) => display
+                .badge_cache
+                .entry((display.connection, label))
+                .or_insert_with_key(|(_, label)| {
+                    let rgba = compose_badge(base.rgba(), base.width(), base.height(), label);
+                    Image::new_owned(rgba, base.width(), base.height())
+                })
+                .clone(),
+        };
+
+        self.tray.set_icon(Some(icon)).map_err(|e| e.to_string())
+    }
+
+    fn refresh_tooltip(&self, display: &TrayDisplay) -> Result<(), String> {
+        let tooltip = format_tooltip(
+            &display.tooltip_lines,
+            display.unread_count,
+            display.connection.tooltip_suffix(),
+        );
+        self.tray.set_tooltip(Some(tooltip)).map_err(|e| e.to_string())
+    }
+}
+
+/// Payload of the `update-available` event emitted after a tray-triggered check
+#[cfg(desktop)]
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateAvailablePayload {
+    pub version: String,
+    pub current_version: String,
+}
+
+/// Show and focus the main window.
+#[cfg(desktop)]
+pub fn show_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+/// Tick or untick the tray's compact mode item.
+#[cfg(desktop)]
+pub fn set_compact_checked(app: &AppHandle, checked: bool) {
+    if let Some(tray) = app.try_state::<TrayManager>() {
+        let _ = tray.compact.set_checked(checked);
+    }
+}
+
+/// Create the tray icon and its context menu, and register the [`TrayManager`].
+#[cfg(desktop)]
+pub fn create(app: &mut App, icon: Image<'static>) -> tauri::Result<()> {
+    let show = MenuItem::with_id(app, MENU_SHOW, "Show Hush", true, None::<&str>)?;
+    let compact =
+        CheckMenuItem::with_id(app, MENU_COMPACT, "Compact Mode", true, false, None::<&str>)?;
+    let check_updates = MenuItem::with_id(
+        app,
+        MENU_CHECK_UPDATES,
+        "Check for Updates",
+        true,
+        None::<&str>,
+    )?;
+    let separator = PredefinedMenuItem::separator(app)?;
+    let quit = MenuItem::with_id(app, MENU_QUIT, "Quit", true, None::<&str>)?;
+    let menu = Menu::with_items(app, &[&show, &compact, &check_updates, &separator, &quit])?;
+
+    let tray = TrayIconBuilder::new()
+        .icon(icon.clone())
+        .tooltip("Hush Feeds")
+        .menu(&menu)
+        .show_menu_on_left_click(false)
+        .on_menu_event(handle_menu_event)
+        .on_tray_icon_event(|tray, event| {
+            // Show/focus main window on tray click
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                show_main_window(tray.app_handle());
+            }
+        })
+        .build(app)?;
+
+    app.manage(TrayManager {
+        tray,
+        base_icon: icon,
+        compact,
+        check_updates,
+        update_check_in_flight: AtomicBool::new(false),
+        display: Mutex::new(TrayDisplay {
+            connection: ConnectionState::Connected,
+            unread_count: 0,
+            tooltip_lines: Vec::new(),
+            badge_cache: HashMap::new(),
+        }),
+    });
+
+    Ok(())
+}
+
+#[cfg(desktop)]
+fn handle_menu_event(app: &AppHandle, event: MenuEvent) {
+    match event.id.as_ref() {
+        MENU_SHOW => show_main_window(app),
+        MENU_COMPACT => crate::window::toggle_compact_mode(app),
+        MENU_CHECK_UPDATES => check_for_updates(app),
+        MENU_QUIT => crate::window::quit(app),
+        _ => {}
+    }
+}
+
+/// Run an update check from the tray, greying out the menu item until it finishes.
+#[cfg(desktop)]
+fn check_for_updates(app: &AppHandle) {
+    let tray = app.state::<TrayManager>();
+    if tray.update_check_in_flight.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let _ = tray.check_updates.set_enabled(false);
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        match updates::check(&app).await {
+            Ok(CheckOutcome::Available(update)) => {
+                log::info!("Update available from tray check: {}", update.version);
+                show_main_window(&app);
+                let _ = app.emit(
+                    "update-available",
+                    UpdateAvailablePayload {
+                        version: update.version.clone(),
+                        current_version: update.current_version.clone(),
+                    },
+                );
+            }
+            Ok(CheckOutcome::UpToDate) => log::info!("Tray update check: already up to date"),
+            Ok(CheckOutcome::AheadOfChannel(version)) => {
+                log::info!("Tray update check: running build is newer than {}", version)
+            }
+            Err(error) => log::warn!("Tray update check failed: {:?}", error),
+        }
+
+        let tray = app.state::<TrayManager>();
+        tray.update_check_in_flight.store(false, Ordering::SeqCst);
+        let _ = tray.check_updates.set_enabled(true);
+    });
+}
+
+/// Run `f` against the tray manager, or fail if the tray was never created.
+#[cfg(desktop)]
+fn with_tray(
+    app: &AppHandle,
+    f: impl FnOnce(&TrayManager) -> Result<(), String>,
+) -> Result<(), CommandError> {
+    let tray = app
+        .try_state::<TrayManager>()
+        .ok_or_else(|| CommandError::new(error::UNAVAILABLE, "Tray icon not available"))?;
+    Ok(f(&tray)?)
+}
+
+/// Set the unread count shown as a badge on the tray icon.
+///
+/// Counts above 99 render as "99+"; 0 restores the plain icon.
+/// On mobile there is no tray, so this is a no-op.
+#[tauri::command]
+pub fn set_tray_unread_count(app: AppHandle, count: u32) -> Result<(), CommandError> {
+    #[cfg(desktop)]
+    {
+        with_tray(&app, |tray| tray.set_unread_count(count))
+    }
+    #[cfg(mobile)]
+    {
+        let _ = (app, count);
+        Ok(())
+    }
+}
+
+/// Show the most recent unread feeds in the tray tooltip.
+///
+/// `lines` are feed names, most recent first. Up to five are listed, each
+/// truncated to ~40 characters. Returns an error if the tray was never created,
+/// including on mobile where there is no tray.
+#[tauri::command]
+pub fn update_tray_tooltip(app: AppHandle, lines: Vec<String>) -> Result<(), CommandError> {
+    #[cfg(desktop)]
+    {
+        with_tray(&app, |tray| tray.set_tooltip_lines(lines))
+    }
+    #[cfg(mobile)]
+    {
+        let _ = (app, lines);
+        Err(CommandError::new(
+            error::NOT_SUPPORTED,
+            "Tray icon not available on this platform",
+        ))
+    }
+}
+
+/// Reflect the connection state in the tray icon and tooltip.
+///
+/// Accepts "connected", "disconnected", or "syncing". On mobile this only
+/// validates the value, since there is no tray.
+#[tauri::command]
+pub fn set_connection_state(app: AppHandle, state: String) -> Result<(), CommandError> {
+    let state = ConnectionState::parse(&state).map_err(CommandError::invalid_argument)?;
+    #[cfg(desktop)]
+    {
+        with_tray(&app, |tray| tray.set_connection_state(state))
+    }
+    #[cfg(mobile)]
+    {
+        let _ = (app, state);
+        Ok(())
+    }
+}
+
+// ============= Tooltip formatting =============
+
+const TOOLTIP_TITLE: &str = "Hush Feeds";
+const TOOLTIP_MAX_ENTRIES: usize = 5;
+const TOOLTIP_MAX_NAME_CHARS: usize = 40;
+
+fn truncate_name(name: &str) -> String {
+    let name = name.trim();
+    if name.chars().count() <= TOOLTIP_MAX_NAME_CHARS {
+        return name.to_string();
+    }
+    let truncated: String = name.chars().take(TOOLTIP_MAX_NAME_CHARS - 1).collect();
+    format!("{}…", truncated.trim_end())
+}
+
+/// Build a tooltip like "Hush Feeds — 3 unread\n• rust-dev\n• family".
+///
+/// The count is `unread` when set, otherwise the number of `lines`. `suffix`
+/// (e.g. "offline") is appended to the title line in parentheses.
+pub fn format_tooltip(lines: &[String], unread: u32, suffix: Option<&str>) -> String {
+    let title = match suffix {
+        Some(suffix) => format!("{} ({})", TOOLTIP_TITLE, suffix),
+        None => TOOLTIP_TITLE.to_string(),
+    };
+    let count = if unread > 0 { unread as usize } else { lines.len() };
+    if count == 0 {
+        return title;
+    }
+
+    let mut tooltip = format!("{} — {} unread", title, count);
+    for line in lines.iter().take(TOOLTIP_MAX_ENTRIES) {
+        tooltip.push_str("\n• ");
+        tooltip.push_str(&truncate_name(line));
+    }
+    tooltip
+}
+
+// ============= Badge rendering =============
+
+const GLYPH_WIDTH: u32 = 3;
+const GLYPH_HEIGHT: u32 = 5;
+const BADGE_COLOR: [u8; 4] = [0xE5, 0x3E, 0x3E, 0xFF];
+const BADGE_TEXT_COLOR: [u8; 4] = [0xFF, 0xFF, 0xFF, 0xFF];
+
+/// Text shown in the badge for `count`, or None when no badge should be drawn.
+pub fn badge_label(count: u32) -> Option<String> {
+    match count {
+        0 => None,
+        1..=99 => Some(count.to_string()),
+        _ => Some("99+".to_string()),
+    }
+}
+
+/// 3x5 bitmap glyphs for the characters a badge can contain (MSB = leftmost pixel).
+fn glyph(c: char) -> Option<[u8; GLYPH_HEIGHT as usize]> {
+    Some(match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        _ => return None,
+    })
+}
+
+fn put_pixel(rgba: &mut [u8], width: u32, x: u32, y: u32, color: [u8; 4]) {
+    let offset = ((y * width + x) * 4) as usize;
+    if let Some(pixel) = rgba.get_mut(offset..offset + 4) {
+        pixel.copy_from_slice(&color);
+    }
+}
+
+/// Pixel sizes of a badge drawn on a `width`x`height` image
+struct BadgeLayout {
+    badge_w: u32,
+    badge_h: u32,
+    text_w: u32,
+    scale: u32,
+}
+
+fn badge_layout(width: u32, height: u32, label: &str) -> BadgeLayout {
+    let size = width.min(height);
+    let badge_h = (size * 11 / 20).max(GLYPH_HEIGHT + 2).min(height);
+    let scale = (badge_h * 3 / 5 / GLYPH_HEIGHT).max(1);
+    let chars = label.chars().count() as u32;
+    let text_w = chars * GLYPH_WIDTH * scale + chars.saturating_sub(1) * scale;
+    let badge_w = (text_w + badge_h / 2).max(badge_h).min(width);
+    BadgeLayout {
+        badge_w,
+        badge_h,
+        text_w,
+        scale,
+    }
+}
+
+/// Side length of [`overlay_badge`] icons
+pub const OVERLAY_SIZE: u32 = 32;
+
+/// A square, transparent RGBA icon with the `label` badge centred in it,
+/// for overlays such as the Windows taskbar badge.
+pub fn overlay_badge(label: &str) -> Vec<u8> {
+    let size = OVERLAY_SIZE;
+    let row = (size * 4) as usize;
+    let badge = compose_badge(&vec![0; row * size as usize], size, size, label);
+    let layout = badge_layout(size, size, label);
+
+    // The badge is drawn in the top-right corner; move it to the centre.
+    let top = ((size - layout.badge_h) / 2) as usize;
+    let shift = ((size - layout.badge_w) / 2 * 4) as usize;
+    let mut out = vec![0; row * size as usize];
+    for y in 0..layout.badge_h as usize {
+        let src = &badge[y * row + shift..(y + 1) * row];
+        let dst = (top + y) * row;
+        out[dst..dst + src.len()].copy_from_slice(src);
+    }
+    out
+}
+
+/// Draw a red pill-shaped badge containing `label` in the top-right corner of an RGBA image.
+pub fn compose_badge(rgba: &[u8], width: u32, height: u32, label: &str) -> Vec<u8> {
+    let mut out = rgba.to_vec();
+    let size = width.min(height);
+    if size == 0 {
+        return out;
+    }
+
+    let BadgeLayout {
+        badge_w,
+        badge_h,
+        text_w,
+        scale,
+    } = badge_layout(width, height, label);
+    let left = width - badge_w;
+
+    // Pill: every pixel within `radius` of the horizontal centre segment
+    let radius = badge_h as f32 / 2.0;
+    let centre_min = radius;
+    let centre_max = (badge_w as f32 - radius).max(centre_min);
+    for y in 0..badge_h {
+        for x in 0..badge_w {
+            let px = x as f32 + 0.5;
+            let py = y as f32 + 0.5;
+            let nearest = px.clamp(centre_min, centre_max);
+            let distance = ((px - nearest).powi(2) + (py - radius).powi(2)).sqrt();
+            if distance <= radius {
+                put_pixel(&mut out, width, left + x, y, BADGE_COLOR);
+            }
+        }
+    }
+
+    let text_h = GLYPH_HEIGHT * scale;
+    let text_left = left + badge_w.saturating_sub(text_w) / 2;
+    let text_top = badge_h.saturating_sub(text_h) / 2;
+    for (index, c) in label.chars().enumerate() {
+        let Some(rows) = glyph(c) else { continue };
+        let glyph_left = text_left + index as u32 * (GLYPH_WIDTH + 1) * scale;
+        for (row, bits) in rows.iter().enumerate() {
+            for col in 0..GLYPH_WIDTH {
+                if (bits >> (GLYPH_WIDTH - 1 - col)) & 1 == 0 {
+                    continue;
+                }
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        let x = glyph_left + col * scale + dx;
+                        let y = text_top + row as u32 * scale + dy;
+                        if x < width && y < height {
+                            put_pixel(&mut out, width, x, y, BADGE_TEXT_COLOR);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn badge_label_caps_at_99_plus() {
+        assert_eq!(badge_label(0), None);
+        assert_eq!(badge_label(7).as_deref(), Some("7"));
+        assert_eq!(badge_label(99).as_deref(), Some("99"));
+        assert_eq!(badge_label(100).as_deref(), Some("99+"));
+    }
+
+    #[test]
+    fn tooltip_without_unread_feeds_is_plain_title() {
+        assert_eq!(format_tooltip(&[], 0, None), "Hush Feeds");
+    }
+
+    #[test]
+    fn tooltip_lists_at_most_five_feeds() {
+        let lines: Vec<String> = (1..=7).map(|i| format!("feed-{}", i)).collect();
+        let tooltip = format_tooltip(&lines, 0, None);
+
+        assert!(tooltip.starts_with("Hush Feeds — 7 unread\n• feed-1"));
+        assert!(tooltip.contains("• feed-5"));
+        assert!(!tooltip.contains("feed-6"));
+    }
+
+    #[test]
+    fn tooltip_prefers_unread_count_over_listed_feeds() {
+        assert_eq!(format_tooltip(&[], 12, None), "Hush Feeds — 12 unread");
+        let tooltip = format_tooltip(&["family".to_string()], 3, None);
+        assert_eq!(tooltip, "Hush Feeds — 3 unread\n• family");
+    }
+
+    #[test]
+    fn tooltip_truncates_long_feed_names() {
+        let long_name = "a".repeat(60);
+        let tooltip = format_tooltip(&[long_name], 0, None);
+        let entry = tooltip.lines().nth(1).unwrap();
+
+        assert!(entry.ends_with('…'));
+        assert_eq!(entry.trim_start_matches("• ").chars().count(), 40);
+    }
+
+    #[test]
+    fn tooltip_includes_connection_suffix() {
+        let suffix = ConnectionState::Disconnected.tooltip_suffix();
+        assert_eq!(format_tooltip(&[], 0, suffix), "Hush Feeds (offline)");
+        assert_eq!(ConnectionState::Connected.tooltip_suffix(), None);
+    }
+
+    #[test]
+    fn connection_state_parses_known_values_only() {
+        assert_eq!(
+            ConnectionState::parse("syncing").unwrap(),
+            ConnectionState::Syncing
+        );
+        assert!(ConnectionState::parse("broken").is_err());
+    }
+
+    #[test]
+    fn compose_badge_only_touches_top_right_corner() {
+        let (width, height) = (32, 32);
+        let base = vec![0u8; (width * height * 4) as usize];
+        let out = compose_badge(&base, width, height, "5");
+
+        assert_eq!(out.len(), base.len());
+        // Bottom-left pixel untouched
+        let bottom_left = (((height - 1) * width) * 4) as usize;
+        assert_eq!(&out[bottom_left..bottom_left + 4], &[0, 0, 0, 0]);
+        // Something was drawn in the top-right quadrant
+        let drawn = (0..height / 2).any(|y| {
+            (width / 2..width).any(|x| {
+                let offset = ((y * width + x) * 4) as usize;
+                out[offset + 3] != 0
+            })
+        });
+        assert!(drawn);
+    }
+
+    #[test]
+    fn overlay_badge_is_centred_and_square() {
+        let rgba = overlay_badge("7");
+        assert_eq!(rgba.len(), (OVERLAY_SIZE * OVERLAY_SIZE * 4) as usize);
+
+        let alpha = |x: u32, y: u32| rgba[((y * OVERLAY_SIZE + x) * 4 + 3) as usize];
+        let centre = OVERLAY_SIZE / 2;
+        assert_eq!(alpha(centre, centre), 0xFF);
+        assert_eq!(alpha(0, 0), 0);
+        assert_eq!(alpha(OVERLAY_SIZE - 1, 0), 0);
+        assert_eq!(alpha(0, OVERLAY_SIZE - 1), 0);
+    }
+
+    #[test]
+    fn compose_badge_handles_tiny_icons() {
+        let base = vec![0u8; 4 * 4 * 4];
+        let out = compose_badge(&base, 4, 4, "99+");
+        assert_eq!(out.len(), base.len());
+    }
+}
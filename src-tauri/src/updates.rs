@@ -0,0 +1,763 @@
+//! App updates driven from the frontend (and the tray's "Check for Updates").
+//!
+//! `check_for_update` asks the updater plugin for a newer release, through
+//! the proxy from [`crate::net::updater_proxy`]. `download_and_install_update`
+//! downloads the release found by the last check, emitting
+//! `update-download-progress` as it goes and `update-ready` once the
+//! signature is verified, then installs it. Checks are serialized and only
+//! one download runs at a time. Failures are returned as an [`UpdateError`]
+//! with a `kind` the frontend can branch on.
+//!
+//! The `updateChannel` setting picks the manifest checked: stable (the
+//! endpoint in `tauri.conf.json`), beta or nightly. When the running build is
+//! newer than its channel's latest release, e.g. after moving from beta back
+//! to stable, the check reports `ahead-of-channel` rather than offering a
+//! downgrade.
+//!
+//! Automatic checks don't prompt for a version skipped with
+//! `skip_update_version` or while snoozed with `snooze_update`; they report
+//! `suppressed` instead. Checks with `manual` set ignore both. Offered
+//! updates carry release notes from [`crate::changelog`].
+//!
+//! `install_update_on_exit` downloads the update to disk instead, and
+//! [`install_staged`] installs it when the app quits, however it quits.
+//!
+//! With the `autoUpdate` setting on, a background task checks daily. In
+//! `check-only` mode it emits `update-available`; in `download` mode it
+//! stages the update quietly (not on metered connections), emits
+//! `update-staged` and posts a notification that installs and restarts when
+//! clicked. Staged files are per run and removed on the next start.
+
+use crate::changelog;
+use crate::connectivity::{self, Connectivity, ConnectivityState};
+use crate::fcm::now_unix_ms;
+use crate::net;
+use crate::notifications;
+use crate::settings::{self, SettingsState};
+use crate::storage;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State, Url};
+use tauri_plugin_updater::{Update, UpdaterExt};
+use tokio::sync::Notify;
+
+/// Minimum time between `update-download-progress` events
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+/// Where `install_update_on_exit` keeps the download, in the app data dir
+const STAGED_FILE: &str = "staged-update.bin";
+const FIRST_AUTO_CHECK_DELAY: Duration = Duration::from_secs(2 * 60);
+const AUTO_CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+const AUTO_RETRY_DELAY: Duration = Duration::from_secs(60 * 60);
+const BETA_ENDPOINT: &str = "https://downloads.hushnetwork.social/updates/beta/latest.json";
+const NIGHTLY_ENDPOINT: &str = "https://downloads.hushnetwork.social/updates/nightly/latest.json";
+const STABLE_CHANGELOG: &str = "https://downloads.hushnetwork.social/updates/stable/changelog.json";
+const BETA_CHANGELOG: &str = "https://downloads.hushnetwork.social/updates/beta/changelog.json";
+const NIGHTLY_CHANGELOG: &str =
+    "https://downloads.hushnetwork.social/updates/nightly/changelog.json";
+
+/// What the background update task does
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AutoUpdateMode {
+    #[default]
+    Off,
+    /// Emit `update-available`, leaving the download to the user
+    CheckOnly,
+    /// Download quietly and notify once staged
+    Download,
+}
+
+/// Which releases to update to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateChannel {
+    #[default]
+    Stable,
+    Beta,
+    Nightly,
+}
+
+impl UpdateChannel {
+    /// The channel's manifest, or `None` for the configured endpoint.
+    fn endpoint(self) -> Option<&'static str> {
+        match self {
+            Self::Stable => None,
+            Self::Beta => Some(BETA_ENDPOINT),
+            Self::Nightly => Some(NIGHTLY_ENDPOINT),
+        }
+    }
+
+    /// The channel's companion changelog; see [`crate::changelog`].
+    fn changelog_url(self) -> &'static str {
+        match self {
+            Self::Stable => STABLE_CHANGELOG,
+            Self::Beta => BETA_CHANGELOG,
+            Self::Nightly => NIGHTLY_CHANGELOG,
+        }
+    }
+}
+
+/// A release newer than the running version
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateInfo {
+    pub version: String,
+    pub current_version: String,
+    /// Release date as given in the manifest (RFC 3339)
+    pub date: Option<String>,
+    /// Release notes (markdown)
+    pub notes: Option<String>,
+}
+
+impl From<&Update> for UpdateInfo {
+    fn from(update: &Update) -> Self {
+        Self {
+            version: update.version.clone(),
+            current_version: update.current_version.clone(),
+            date: update
+                .raw_json
+                .get("pub_date")
+                .and_then(|date| date.as_str())
+                .map(str::to_string),
+            notes: update.body.clone(),
+        }
+    }
+}
+
+/// Result of `check_for_update`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "kebab-case")]
+pub enum UpdateCheck {
+    Available(UpdateInfo),
+    UpToDate,
+    /// The running build is newer than the channel's latest release
+    #[serde(rename_all = "camelCase")]
+    AheadOfChannel {
+        channel: UpdateChannel,
+        current_version: String,
+        channel_version: String,
+    },
+    /// An update exists but the user asked not to be prompted about it
+    Suppressed {
+        version: String,
+        reason: SuppressReason,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SuppressReason {
+    Skipped,
+    Snoozed,
+}
+
+/// Skip and snooze state, for the settings screen
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdatePreferences {
+    pub skipped_version: Option<String>,
+    /// Unix ms
+    pub snoozed_until: Option<u64>,
+}
+
+impl UpdatePreferences {
+    fn load(app: &AppHandle) -> Self {
+        let settings = app.state::<SettingsState>().get();
+        Self {
+            skipped_version: settings.skipped_update_version,
+            snoozed_until: settings.update_snoozed_until,
+        }
+    }
+
+    /// Why `version` shouldn't be prompted for at `now`, if it shouldn't.
+    fn suppression(&self, version: &str, now: u64) -> Option<SuppressReason> {
+        if self.skipped_version.as_deref() == Some(version) {
+            Some(SuppressReason::Skipped)
+        } else if self.snoozed_until.is_some_and(|until| now < until) {
+            Some(SuppressReason::Snoozed)
+        } else {
+            None
+        }
+    }
+}
+
+/// What a check found
+pub enum CheckOutcome {
+    Available(Update),
+    UpToDate,
+    /// The channel's latest version, older than the running one
+    AheadOfChannel(String),
+}
+
+/// Why an update operation failed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum UpdateError {
+    /// `download_and_install_update` without an available update
+    NoUpdate(String),
+    Network(String),
+    /// The download didn't match the updater public key
+    Signature(String),
+    /// A download is already running
+    InProgress(String),
+    /// Updates can't run here, e.g. in Tor mode or on mobile
+    Unavailable(String),
+    Other(String),
+}
+
+impl From<tauri_plugin_updater::Error> for UpdateError {
+    fn from(e: tauri_plugin_updater::Error) -> Self {
+        use tauri_plugin_updater::Error;
+        let message = e.to_string();
+        match e {
+            Error::Reqwest(_) | Error::Network(_) => Self::Network(message),
+            Error::Minisign(_) | Error::Base64(_) | Error::SignatureUtf8(_) => {
+                Self::Signature(message)
+            }
+            _ => Self::Other(message),
+        }
+    }
+}
+
+/// Managed state guarding against overlapping checks and downloads
+#[derive(Default)]
+pub struct UpdateState {
+    /// Held for the duration of a check
+    checking: tokio::sync::Mutex<()>,
+    downloading: AtomicBool,
+    /// The release found by the last check
+    available: Mutex<Option<Update>>,
+    staged: Mutex<Option<Staged>>,
+    /// Signalled when the automatic update mode changes
+    wake: Notify,
+}
+
+/// Clears the `downloading` flag when the download ends, however it ends.
+struct DownloadGuard<'a>(&'a AtomicBool);
+
+impl<'a> DownloadGuard<'a> {
+    fn acquire(flag: &'a AtomicBool) -> Option<Self> {
+        (!flag.swap(true, Ordering::SeqCst)).then_some(Self(flag))
+    }
+}
+
+impl Drop for DownloadGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+/// An update downloaded and waiting to be installed on exit
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StagedUpdate {
+    pub version: String,
+    pub size: u64,
+    /// Unix ms
+    pub staged_at: u64,
+}
+
+struct Staged {
+    info: StagedUpdate,
+    path: PathBuf,
+    /// Checked before installing, in case the file changed since staging
+    sha256: [u8; 32],
+    update: Update,
+}
+
+/// Payload of the `update-download-progress` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadProgress {
+    pub downloaded: u64,
+    /// `None` if the server didn't send a length
+    pub total: Option<u64>,
+}
+
+fn channel(app: &AppHandle) -> UpdateChannel {
+    app.state::<SettingsState>()
+        .get()
+        .update_channel
+        .unwrap_or_default()
+}
+
+/// Ask the channel's update endpoint for a newer release, remembering it for download.
+pub async fn check(app: &AppHandle) -> Result<CheckOutcome, UpdateError> {
+    let state = app.state::<UpdateState>();
+    let _checking = state.checking.lock().await;
+
+    let proxy = net::updater_proxy(app).map_err(UpdateError::Unavailable)?;
+    let unavailable = |e: tauri_plugin_updater::Error| UpdateError::Unavailable(e.to_string());
+    // Older releases are never offered, but are noted to report `AheadOfChannel`
+    let older = Arc::new(Mutex::new(None));
+    let seen = older.clone();
+    let mut builder = app
+        .updater_builder()
+        .version_comparator(move |current, release| {
+            if release.version < current {
+                let mut seen = seen.lock().unwrap_or_else(|e| e.into_inner());
+                *seen = Some(release.version.to_string());
+            }
+            release.version > current
+        });
+    if let Some(endpoint) = channel(app).endpoint() {
+        let url = Url::parse(endpoint).map_err(|e| UpdateError::Other(e.to_string()))?;
+        builder = builder.endpoints(vec![url]).map_err(unavailable)?;
+    }
+    if let Some(proxy) = proxy {
+        builder = builder.proxy(proxy);
+    }
+    let update = builder.build().map_err(unavailable)?.check().await?;
+
+    *state.available.lock().unwrap_or_else(|e| e.into_inner()) = update.clone();
+    let older = older.lock().unwrap_or_else(|e| e.into_inner()).take();
+    Ok(match (update, older) {
+        (Some(update), _) => CheckOutcome::Available(update),
+        (None, Some(version)) => CheckOutcome::AheadOfChannel(version),
+        (None, None) => CheckOutcome::UpToDate,
+    })
+}
+
+/// Check for an update. Skipped and snoozed updates are only offered when
+/// `manual` is set.
+#[tauri::command]
+pub async fn check_for_update(
+    app: AppHandle,
+    manual: Option<bool>,
+) -> Result<UpdateCheck, UpdateError> {
+    Ok(match check(&app).await? {
+        CheckOutcome::Available(update) => {
+            let suppressed = match manual {
+                Some(true) => None,
+                _ => UpdatePreferences::load(&app).suppression(&update.version, now_unix_ms()),
+            };
+            match suppressed {
+                Some(reason) => UpdateCheck::Suppressed {
+                    version: update.version.clone(),
+                    reason,
+                },
+                None => {
+                    let notes = changelog::refresh(
+                        &app,
+                        &update.version,
+                        update.body.as_deref(),
+                        Some(channel(&app).changelog_url()),
+                    )
+                    .await;
+                    UpdateCheck::Available(UpdateInfo {
+                        notes,
+                        ..UpdateInfo::from(&update)
+                    })
+                }
+            }
+        }
+        CheckOutcome::UpToDate => UpdateCheck::UpToDate,
+        CheckOutcome::AheadOfChannel(channel_version) => UpdateCheck::AheadOfChannel {
+            channel: channel(&app),
+            current_version: app.package_info().version.to_string(),
+            channel_version,
+        },
+    })
+}
+
+fn available(state: &UpdateState) -> Result<Update, UpdateError> {
+    state
+        .available
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .ok_or_else(|| UpdateError::NoUpdate("No update available".to_string()))
+}
+
+/// Download and verify `update`, emitting `update-download-progress`.
+async fn download(app: &AppHandle, update: &Update) -> Result<Vec<u8>, UpdateError> {
+    let mut downloaded = 0u64;
+    let mut last_emit: Option<Instant> = None;
+    let progress_app = app.clone();
+    let bytes = update
+        .download(
+            move |chunk, total| {
+                downloaded += chunk as u64;
+                let done = total.is_some_and(|total| downloaded >= total);
+                if done || last_emit.map_or(true, |at| at.elapsed() >= PROGRESS_INTERVAL) {
+                    last_emit = Some(Instant::now());
+                    let _ = progress_app.emit(
+                        "update-download-progress",
+                        DownloadProgress { downloaded, total },
+                    );
+                }
+            },
+            || {},
+        )
+        .await?;
+    log::info!("Update {} downloaded and verified", update.version);
+    Ok(bytes)
+}
+
+/// Download, verify and install the update found by the last check.
+///
+/// On Windows the installer takes over and the app exits; elsewhere the new
+/// version runs after a restart.
+#[tauri::command]
+pub async fn download_and_install_update(
+    app: AppHandle,
+    state: State<'_, UpdateState>,
+) -> Result<(), UpdateError> {
+    let Some(_downloading) = DownloadGuard::acquire(&state.downloading) else {
+        return Err(UpdateError::InProgress(
+            "An update is already downloading".to_string(),
+        ));
+    };
+    let update = available(&state)?;
+    let bytes = download(&app, &update).await?;
+    let _ = app.emit("update-ready", UpdateInfo::from(&update));
+    update.install(bytes)?;
+    Ok(())
+}
+
+/// Download `update` to disk for [`install_staged`], unless it's already staged.
+async fn stage(app: &AppHandle, update: &Update) -> Result<StagedUpdate, UpdateError> {
+    let state = app.state::<UpdateState>();
+    let Some(_downloading) = DownloadGuard::acquire(&state.downloading) else {
+        return Err(UpdateError::InProgress(
+            "An update is already downloading".to_string(),
+        ));
+    };
+    if let Some(staged) = &*state.staged.lock().unwrap_or_else(|e| e.into_inner()) {
+        if staged.info.version == update.version {
+            return Ok(staged.info.clone());
+        }
+    }
+    let bytes = download(app, update).await?;
+
+    let path = storage::data_file(app, STAGED_FILE).map_err(UpdateError::Other)?;
+    storage::write_atomic(&path, &bytes).map_err(UpdateError::Other)?;
+    let info = StagedUpdate {
+        version: update.version.clone(),
+        size: bytes.len() as u64,
+        staged_at: now_unix_ms(),
+    };
+    *state.staged.lock().unwrap_or_else(|e| e.into_inner()) = Some(Staged {
+        info: info.clone(),
+        path,
+        sha256: Sha256::digest(&bytes).into(),
+        update: update.clone(),
+    });
+    log::info!("Update {} staged for exit", update.version);
+    Ok(info)
+}
+
+/// Download the update found by the last check now and install it when the
+/// app quits.
+#[tauri::command]
+pub async fn install_update_on_exit(
+    app: AppHandle,
+    state: State<'_, UpdateState>,
+) -> Result<StagedUpdate, UpdateError> {
+    let update = available(&state)?;
+    let staged = stage(&app, &update).await?;
+    let _ = app.emit("update-ready", UpdateInfo::from(&update));
+    Ok(staged)
+}
+
+fn auto_update_mode(app: &AppHandle) -> AutoUpdateMode {
+    app.state::<SettingsState>()
+        .get()
+        .auto_update
+        .unwrap_or_default()
+}
+
+/// One automatic check, then a quiet download in download mode.
+async fn auto_update_once(app: &AppHandle, mode: AutoUpdateMode) -> Result<(), UpdateError> {
+    let CheckOutcome::Available(update) = check(app).await? else {
+        return Ok(());
+    };
+    if let Some(reason) = UpdatePreferences::load(app).suppression(&update.version, now_unix_ms()) {
+        log::info!("Update {} available but {:?}", update.version, reason);
+        return Ok(());
+    }
+
+    let metered = tauri::async_runtime::spawn_blocking(connectivity::is_metered)
+        .await
+        .unwrap_or(false);
+    if mode == AutoUpdateMode::CheckOnly || metered {
+        if metered {
+            log::info!(
+                "Not downloading update {} on a metered connection",
+                update.version
+            );
+        }
+        let _ = app.emit("update-available", UpdateInfo::from(&update));
+        return Ok(());
+    }
+
+    let staged = stage(app, &update).await?;
+    let _ = app.emit("update-staged", &staged);
+    notifications::notify_app(
+        app,
+        "Hush",
+        &format!("Update {} ready — restart to apply", staged.version),
+        |app| {
+            install_staged(app);
+            app.restart();
+        },
+    );
+    Ok(())
+}
+
+/// Background checks per `autoUpdate`: daily, starting shortly after launch.
+async fn run_auto_update(app: AppHandle) {
+    let state = app.state::<UpdateState>();
+    let mut delay = FIRST_AUTO_CHECK_DELAY;
+    loop {
+        if auto_update_mode(&app) == AutoUpdateMode::Off {
+            state.wake.notified().await;
+            delay = FIRST_AUTO_CHECK_DELAY;
+            continue;
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = state.wake.notified() => continue,
+        }
+
+        let mode = auto_update_mode(&app);
+        if mode == AutoUpdateMode::Off {
+            continue;
+        }
+        if app.state::<ConnectivityState>().get() != Connectivity::Online {
+            delay = AUTO_RETRY_DELAY;
+            continue;
+        }
+        delay = match auto_update_once(&app, mode).await {
+            Ok(()) => AUTO_CHECK_INTERVAL,
+            Err(e) => {
+                log::warn!("Automatic update failed: {:?}", e);
+                AUTO_RETRY_DELAY
+            }
+        };
+    }
+}
+
+/// Remove downloads left by a previous run (their [`Update`] is gone with it)
+/// and start the automatic update task. Called from `setup`.
+pub fn init(app: &AppHandle) {
+    if let Ok(path) = storage::data_file(app, STAGED_FILE) {
+        let mut tmp = path.clone().into_os_string();
+        tmp.push(".tmp");
+        let _ = storage::remove_file(&path);
+        let _ = storage::remove_file(&PathBuf::from(tmp));
+    }
+    tauri::async_runtime::spawn(run_auto_update(app.clone()));
+}
+
+/// Set whether updates are checked for, and downloaded, in the background.
+#[tauri::command]
+pub fn set_auto_update(
+    app: AppHandle,
+    state: State<'_, UpdateState>,
+    mode: AutoUpdateMode,
+) -> Result<(), String> {
+    settings::set(
+        &app,
+        "autoUpdate",
+        serde_json::to_value(mode).map_err(|e| e.to_string())?,
+    )?;
+    state.wake.notify_one();
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_staged_update(state: State<'_, UpdateState>) -> Option<StagedUpdate> {
+    state
+        .staged
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .map(|staged| staged.info.clone())
+}
+
+/// Install the staged update, if any. Called on `ExitRequested`; problems
+/// are logged and never block shutdown.
+pub fn install_staged(app: &AppHandle) {
+    let Some(staged) = app.try_state::<UpdateState>().and_then(|state| {
+        state
+            .staged
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+    }) else {
+        return;
+    };
+
+    match std::fs::read(&staged.path) {
+        Ok(bytes) if <[u8; 32]>::from(Sha256::digest(&bytes)) == staged.sha256 => {
+            log::info!("Installing update {} on exit", staged.info.version);
+            if let Err(e) = staged.update.install(bytes) {
+                log::error!("Installing staged update failed: {}", e);
+            }
+        }
+        Ok(_) => log::error!("Staged update failed verification; skipping install"),
+        Err(e) => log::error!("Staged update unreadable; skipping install: {}", e),
+    }
+    let _ = storage::remove_file(&staged.path);
+}
+
+/// Switch update channel. The next check uses the new channel's manifest.
+#[tauri::command]
+pub fn set_update_channel(
+    app: AppHandle,
+    state: State<'_, UpdateState>,
+    channel: String,
+) -> Result<(), String> {
+    let channel: UpdateChannel = serde_json::from_value(channel.clone().into())
+        .map_err(|_| format!("Unknown update channel {}", channel))?;
+    settings::set(
+        &app,
+        "updateChannel",
+        serde_json::to_value(channel).map_err(|e| e.to_string())?,
+    )?;
+    // The last check's release may not be on the new channel
+    *state.available.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_update_channel(app: AppHandle) -> UpdateChannel {
+    channel(&app)
+}
+
+/// Stop prompting about `version`; newer versions are offered as usual.
+#[tauri::command]
+pub fn skip_update_version(app: AppHandle, version: String) -> Result<(), String> {
+    settings::set(&app, "skippedUpdateVersion", version.into())
+}
+
+/// Don't prompt about updates for `hours`.
+#[tauri::command]
+pub fn snooze_update(app: AppHandle, hours: u32) -> Result<(), String> {
+    let until = now_unix_ms() + u64::from(hours) * 60 * 60 * 1000;
+    settings::set(&app, "updateSnoozedUntil", until.into())
+}
+
+#[tauri::command]
+pub fn get_update_preferences(app: AppHandle) -> UpdatePreferences {
+    UpdatePreferences::load(&app)
+}
+
+/// Forget the skipped version and any snooze.
+#[tauri::command]
+pub fn clear_update_preferences(app: AppHandle) -> Result<(), String> {
+    settings::set(&app, "skippedUpdateVersion", serde_json::Value::Null)?;
+    settings::set(&app, "updateSnoozedUntil", serde_json::Value::Null)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn check_result_serializes_with_status() {
+        assert_eq!(
+            serde_json::to_value(UpdateCheck::UpToDate).unwrap(),
+            json!({"status": "up-to-date"})
+        );
+        let available = UpdateCheck::Available(UpdateInfo {
+            version: "0.3.0".to_string(),
+            current_version: "0.2.15".to_string(),
+            date: None,
+            notes: Some("Fixes".to_string()),
+        });
+        assert_eq!(
+            serde_json::to_value(available).unwrap(),
+            json!({
+                "status": "available",
+                "version": "0.3.0",
+                "currentVersion": "0.2.15",
+                "date": null,
+                "notes": "Fixes"
+            })
+        );
+    }
+
+    #[test]
+    fn ahead_of_channel_is_reported_distinctly() {
+        let ahead = UpdateCheck::AheadOfChannel {
+            channel: UpdateChannel::Stable,
+            current_version: "0.3.0-beta.2".to_string(),
+            channel_version: "0.2.15".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_value(ahead).unwrap(),
+            json!({
+                "status": "ahead-of-channel",
+                "channel": "stable",
+                "currentVersion": "0.3.0-beta.2",
+                "channelVersion": "0.2.15"
+            })
+        );
+    }
+
+    #[test]
+    fn channels_map_to_endpoints() {
+        assert_eq!(UpdateChannel::Stable.endpoint(), None);
+        for channel in [UpdateChannel::Beta, UpdateChannel::Nightly] {
+            assert!(Url::parse(channel.endpoint().unwrap()).is_ok());
+        }
+        assert!(Url::parse(UpdateChannel::Stable.changelog_url()).is_ok());
+        assert_eq!(
+            serde_json::from_value::<UpdateChannel>(json!("nightly")).unwrap(),
+            UpdateChannel::Nightly
+        );
+        assert!(serde_json::from_value::<UpdateChannel>(json!("alpha")).is_err());
+    }
+
+    #[test]
+    fn skipped_and_snoozed_versions_are_suppressed() {
+        let prefs = UpdatePreferences {
+            skipped_version: Some("0.3.0".to_string()),
+            snoozed_until: Some(1_000),
+        };
+        assert_eq!(
+            prefs.suppression("0.3.0", 5_000),
+            Some(SuppressReason::Skipped)
+        );
+        assert_eq!(
+            prefs.suppression("0.3.1", 500),
+            Some(SuppressReason::Snoozed)
+        );
+        assert_eq!(prefs.suppression("0.3.1", 1_000), None);
+        assert_eq!(UpdatePreferences::default().suppression("0.3.0", 0), None);
+    }
+
+    #[test]
+    fn auto_update_modes_use_kebab_case() {
+        assert_eq!(
+            serde_json::from_value::<AutoUpdateMode>(json!("check-only")).unwrap(),
+            AutoUpdateMode::CheckOnly
+        );
+        assert_eq!(AutoUpdateMode::default(), AutoUpdateMode::Off);
+    }
+
+    #[test]
+    fn only_one_download_at_a_time() {
+        let flag = AtomicBool::new(false);
+        let guard = DownloadGuard::acquire(&flag);
+        assert!(guard.is_some());
+        assert!(DownloadGuard::acquire(&flag).is_none());
+        drop(guard);
+        assert!(DownloadGuard::acquire(&flag).is_some());
+    }
+
+    #[test]
+    fn errors_serialize_with_kind() {
+        assert_eq!(
+            serde_json::to_value(UpdateError::Signature("bad".to_string())).unwrap(),
+            json!({"kind": "signature", "message": "bad"})
+        );
+    }
+}
@@ -0,0 +1,409 @@
+//! Keeping the screen on or the machine awake during long playback and uploads.
+//!
+//! `acquire_wake_lock` takes a `display` lock (keep the screen on) or a
+//! `system` lock (prevent suspend) and returns its id for `release_wake_lock`.
+//! Each lock is backed by its own OS assertion, released when the lock is
+//! dropped:
+//!
+//! - Windows: `SetThreadExecutionState` on a thread parked for the lock's life
+//! - macOS: a `caffeinate` child (an IOPMAssertion) tied to our pid
+//! - Linux: a `systemd-inhibit` child, which takes an idle or sleep inhibitor
+//!   on logind for as long as it runs
+//! - Android: a `PowerManager` wake lock held by `WakeLockHelper`
+//!
+//! Locks are released when the main window closes (or hides to the tray) and
+//! after [`MAX_DURATION`] at the latest, emitting `wake-lock-released`, so a
+//! frontend that forgets to release can't keep the machine up for good.
+//! `list_wake_locks` shows what is currently held.
+
+use crate::error::{self, CommandError};
+use crate::fcm::now_unix_ms;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+/// Hard limit on how long a lock is held
+const MAX_DURATION: Duration = Duration::from_secs(4 * 60 * 60);
+/// Longer reasons are cut to this many characters
+const MAX_REASON_CHARS: usize = 200;
+/// How long a spawned inhibitor gets to fail before the lock counts as taken
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+const SPAWN_CHECK_DELAY: Duration = Duration::from_millis(150);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WakeLockKind {
+    /// Keep the screen on
+    Display,
+    /// Keep the machine from suspending; the screen may still turn off
+    System,
+}
+
+impl WakeLockKind {
+    fn parse(kind: &str) -> Result<Self, CommandError> {
+        match kind {
+            "display" => Ok(WakeLockKind::Display),
+            "system" => Ok(WakeLockKind::System),
+            other => Err(CommandError::invalid_argument(format!(
+                "Unknown wake lock kind '{}', expected display or system",
+                other
+            ))),
+        }
+    }
+}
+
+/// A held wake lock
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WakeLock {
+    pub id: u64,
+    pub kind: WakeLockKind,
+    pub reason: String,
+    /// Unix timestamp (ms)
+    pub acquired_at: u64,
+    /// Unix timestamp (ms) at which the lock is released regardless
+    pub expires_at: u64,
+}
+
+/// Why a lock was released without `release_wake_lock`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReleaseReason {
+    Expired,
+    WindowClosed,
+}
+
+/// Payload of `wake-lock-released`
+#[derive(Debug, Clone, Serialize)]
+struct ReleasedPayload {
+    id: u64,
+    reason: ReleaseReason,
+}
+
+/// The OS side of a lock; dropping it releases the assertion.
+#[cfg_attr(target_os = "ios", allow(dead_code))]
+struct Assertion {
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    child: std::process::Child,
+    /// The holding thread restores the execution state once this is dropped
+    #[cfg(target_os = "windows")]
+    _release: std::sync::mpsc::Sender<()>,
+    #[cfg(target_os = "android")]
+    id: u64,
+}
+
+impl Drop for Assertion {
+    fn drop(&mut self) {
+        #[cfg(any(target_os = "linux", target_os = "macos"))]
+        {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+        #[cfg(target_os = "android")]
+        if let Err(e) = crate::android::release_wake_lock(self.id) {
+            log::warn!("Failed to release Android wake lock: {}", e);
+        }
+    }
+}
+
+/// Spawn an inhibitor process and check it didn't exit straight away.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+async fn spawn_inhibitor(program: &str, args: &[String]) -> Result<Assertion, CommandError> {
+    use std::process::{Command, Stdio};
+
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| CommandError::new(error::NOT_SUPPORTED, format!("{}: {}", program, e)))?;
+    tokio::time::sleep(SPAWN_CHECK_DELAY).await;
+    if let Ok(Some(status)) = child.try_wait() {
+        return Err(CommandError::new(
+            error::UNAVAILABLE,
+            format!("{} exited immediately ({})", program, status),
+        ));
+    }
+    Ok(Assertion { child })
+}
+
+/// Arguments for `systemd-inhibit`; the command it runs ends with our process.
+#[cfg(target_os = "linux")]
+fn inhibit_args(kind: WakeLockKind, reason: &str, pid: u32) -> Vec<String> {
+    let what = match kind {
+        WakeLockKind::Display => "idle",
+        WakeLockKind::System => "sleep",
+    };
+    vec![
+        format!("--what={}", what),
+        "--who=Hush Feeds".to_string(),
+        format!("--why={}", reason),
+        "--mode=block".to_string(),
+        "tail".to_string(),
+        format!("--pid={}", pid),
+        "-f".to_string(),
+        "/dev/null".to_string(),
+    ]
+}
+
+/// Take the OS assertion for a lock.
+async fn take_assertion(
+    id: u64,
+    kind: WakeLockKind,
+    reason: &str,
+) -> Result<Assertion, CommandError> {
+    #[cfg(target_os = "linux")]
+    {
+        let _ = id;
+        spawn_inhibitor(
+            "systemd-inhibit",
+            &inhibit_args(kind, reason, std::process::id()),
+        )
+        .await
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let _ = (id, reason);
+        let flag = match kind {
+            WakeLockKind::Display => "-d",
+            WakeLockKind::System => "-i",
+        };
+        let args = [
+            flag.to_string(),
+            "-w".to_string(),
+            std::process::id().to_string(),
+        ];
+        spawn_inhibitor("caffeinate", &args).await
+    }
+    #[cfg(target_os = "windows")]
+    {
+        use windows_sys::Win32::System::Power::{
+            SetThreadExecutionState, ES_CONTINUOUS, ES_DISPLAY_REQUIRED, ES_SYSTEM_REQUIRED,
+        };
+
+        let _ = (id, reason);
+        let mut flags = ES_CONTINUOUS | ES_SYSTEM_REQUIRED;
+        if kind == WakeLockKind::Display {
+            flags |= ES_DISPLAY_REQUIRED;
+        }
+        let (release, released) = std::sync::mpsc::channel::<()>();
+        let (ready, ready_rx) = std::sync::mpsc::channel();
+        // The execution state belongs to the thread that set it
+        std::thread::spawn(move || {
+            // SAFETY: plain FFI call with valid flags
+            let ok = unsafe { SetThreadExecutionState(flags) } != 0;
+            let _ = ready.send(ok);
+            if ok {
+                // Returns once the Assertion (and its Sender) is dropped
+                let _ = released.recv();
+                // SAFETY: as above
+                unsafe { SetThreadExecutionState(ES_CONTINUOUS) };
+            }
+        });
+        match ready_rx.recv() {
+            Ok(true) => Ok(Assertion { _release: release }),
+            _ => Err(CommandError::new(
+                error::UNAVAILABLE,
+                "SetThreadExecutionState failed",
+            )),
+        }
+    }
+    #[cfg(target_os = "android")]
+    {
+        let display = kind == WakeLockKind::Display;
+        let timeout_ms = MAX_DURATION.as_millis() as i64;
+        match crate::android::acquire_wake_lock(id, display, reason, timeout_ms) {
+            Ok(true) => Ok(Assertion { id }),
+            Ok(false) => Err(CommandError::new(
+                error::UNAVAILABLE,
+                "Could not acquire a wake lock",
+            )),
+            Err(e) => Err(CommandError::new(error::NATIVE_BRIDGE_FAILURE, e)),
+        }
+    }
+    #[cfg(target_os = "ios")]
+    {
+        let _ = (id, kind, reason);
+        Err(CommandError::new(
+            error::NOT_SUPPORTED,
+            "Wake locks are not available on this platform",
+        ))
+    }
+}
+
+struct Held {
+    lock: WakeLock,
+    _assertion: Assertion,
+    expiry: JoinHandle<()>,
+}
+
+/// Managed state holding the current wake locks by id
+#[derive(Default)]
+pub struct WakeLockState {
+    held: Mutex<BTreeMap<u64, Held>>,
+    next_id: AtomicU64,
+}
+
+impl WakeLockState {
+    fn held(&self) -> MutexGuard<'_, BTreeMap<u64, Held>> {
+        self.held.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Release the lock with `id`; returns whether it was held.
+    fn release(&self, id: u64) -> bool {
+        let Some(held) = self.held().remove(&id) else {
+            return false;
+        };
+        held.expiry.abort();
+        log::info!("Released {:?} wake lock {}", held.lock.kind, id);
+        true
+    }
+
+    fn ids(&self) -> Vec<u64> {
+        self.held().keys().copied().collect()
+    }
+
+    fn list(&self) -> Vec<WakeLock> {
+        self.held().values().map(|held| held.lock.clone()).collect()
+    }
+}
+
+/// Release `id` on the app's behalf and tell the frontend.
+fn auto_release(app: &AppHandle, id: u64, reason: ReleaseReason) {
+    if app.state::<WakeLockState>().release(id) {
+        let _ = app.emit("wake-lock-released", ReleasedPayload { id, reason });
+    }
+}
+
+/// Release every lock, e.g. when the main window closes.
+pub fn release_all(app: &AppHandle, reason: ReleaseReason) {
+    let Some(state) = app.try_state::<WakeLockState>() else {
+        return;
+    };
+    for id in state.ids() {
+        auto_release(app, id, reason);
+    }
+}
+
+fn clean_reason(reason: &str) -> String {
+    reason.trim().chars().take(MAX_REASON_CHARS).collect()
+}
+
+/// Keep the screen on (`kind` "display") or the machine awake ("system").
+///
+/// Returns the lock for `release_wake_lock`. The lock is released anyway when
+/// the window closes or after four hours.
+#[tauri::command]
+pub async fn acquire_wake_lock(
+    app: AppHandle,
+    kind: String,
+    reason: String,
+) -> Result<WakeLock, CommandError> {
+    let kind = WakeLockKind::parse(&kind)?;
+    let reason = clean_reason(&reason);
+    if reason.is_empty() {
+        return Err(CommandError::invalid_argument("A wake lock needs a reason"));
+    }
+
+    let state = app.state::<WakeLockState>();
+    let id = state.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+    let assertion = take_assertion(id, kind, &reason).await?;
+
+    let acquired_at = now_unix_ms();
+    let lock = WakeLock {
+        id,
+        kind,
+        reason,
+        acquired_at,
+        expires_at: acquired_at + MAX_DURATION.as_millis() as u64,
+    };
+    let expiry = {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(MAX_DURATION).await;
+            log::warn!("Wake lock {} reached the time limit", id);
+            auto_release(&app, id, ReleaseReason::Expired);
+        })
+    };
+    log::info!("Acquired {:?} wake lock {}: {}", kind, id, lock.reason);
+    state.held().insert(
+        id,
+        Held {
+            lock: lock.clone(),
+            _assertion: assertion,
+            expiry,
+        },
+    );
+    Ok(lock)
+}
+
+/// Release a lock from `acquire_wake_lock`; false if it was already released.
+#[tauri::command]
+pub fn release_wake_lock(state: State<'_, WakeLockState>, id: u64) -> bool {
+    state.release(id)
+}
+
+/// Every lock currently held, oldest first, for tracking down leaks.
+#[tauri::command]
+pub fn list_wake_locks(state: State<'_, WakeLockState>) -> Vec<WakeLock> {
+    state.list()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kinds_and_reasons_are_validated() {
+        assert_eq!(
+            WakeLockKind::parse("display").unwrap(),
+            WakeLockKind::Display
+        );
+        assert_eq!(WakeLockKind::parse("system").unwrap(), WakeLockKind::System);
+        assert_eq!(
+            WakeLockKind::parse("screen").unwrap_err().code,
+            error::INVALID_ARGUMENT
+        );
+        assert_eq!(clean_reason("  Playing video  "), "Playing video");
+        assert_eq!(clean_reason(&"x".repeat(500)).len(), MAX_REASON_CHARS);
+    }
+
+    #[test]
+    fn locks_serialize_for_the_frontend() {
+        let lock = WakeLock {
+            id: 1,
+            kind: WakeLockKind::Display,
+            reason: "Playing video".to_string(),
+            acquired_at: 10,
+            expires_at: 20,
+        };
+        assert_eq!(
+            serde_json::to_value(lock).unwrap(),
+            serde_json::json!({
+                "id": 1,
+                "kind": "display",
+                "reason": "Playing video",
+                "acquiredAt": 10,
+                "expiresAt": 20,
+            })
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn inhibitor_takes_the_lock_for_our_lifetime() {
+        let args = inhibit_args(WakeLockKind::System, "Uploading video", 42);
+        assert_eq!(args[0], "--what=sleep");
+        assert_eq!(args[2], "--why=Uploading video");
+        assert_eq!(&args[4..], ["tail", "--pid=42", "-f", "/dev/null"]);
+        assert_eq!(
+            inhibit_args(WakeLockKind::Display, "x", 1)[0],
+            "--what=idle"
+        );
+    }
+}
@@ -0,0 +1,887 @@
+//! Main window behaviour: close-to-tray, saved geometry and file drops.
+//!
+//! When close-to-tray is enabled, clicking the window's X hides it instead of
+//! exiting so the app keeps running in the tray. The flag is persisted to the
+//! app config dir. With it off, closing the main window quits the app even
+//! while feed pop-outs ([`crate::feed_windows`]) are open.
+//!
+//! On desktop the main window's position, size, and maximized flag are saved
+//! to `window-state.json` (debounced on move/resize, and on exit) and restored
+//! in `setup` before the window is first shown.
+//!
+//! The main window starts hidden and is shown once the frontend calls
+//! `frontend_ready` after its first render, so it never appears as a blank
+//! white rectangle. If that call doesn't come within [`FRONTEND_READY_TIMEOUT`]
+//! (a broken bundle, say) the window is shown anyway. With `--minimized`
+//! neither shows it; a tray click does.
+//!
+//! Compact mode shrinks the main window into a small undecorated window kept
+//! on top, and puts it back as it was on exit. It lasts for the session only;
+//! geometry isn't saved while it is on.
+
+use crate::error::CommandError;
+use crate::storage;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(desktop)]
+use std::sync::atomic::AtomicU64;
+use std::time::Duration;
+#[cfg(desktop)]
+use tauri::{PhysicalPosition, PhysicalSize, WebviewWindow};
+use tauri::{AppHandle, DragDropEvent, Manager, State, Window, WindowEvent};
+#[cfg(desktop)]
+use tauri::Emitter;
+
+const BEHAVIOR_FILE: &str = "window-behavior.json";
+const GEOMETRY_FILE: &str = "window-state.json";
+#[cfg(desktop)]
+const GEOMETRY_SAVE_DEBOUNCE: Duration = Duration::from_millis(500);
+/// Minimum overlap (in pixels, per axis) for a saved window to count as on-screen
+const MIN_VISIBLE_PX: i64 = 50;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct WindowBehavior {
+    #[serde(default)]
+    close_to_tray: bool,
+}
+
+/// Managed state for main window behaviour
+#[derive(Debug, Default)]
+pub struct WindowBehaviorState {
+    close_to_tray: AtomicBool,
+    /// Set by an explicit quit so close requests are no longer intercepted
+    quitting: AtomicBool,
+}
+
+impl WindowBehaviorState {
+    /// Load persisted behaviour from the app config dir.
+    pub fn load(app: &AppHandle) -> Self {
+        let behavior = storage::config_file(app, BEHAVIOR_FILE)
+            .ok()
+            .and_then(|path| storage::read_json::<WindowBehavior>(&path))
+            .unwrap_or_default();
+
+        Self {
+            close_to_tray: AtomicBool::new(behavior.close_to_tray),
+            quitting: AtomicBool::new(false),
+        }
+    }
+
+    pub fn close_to_tray(&self) -> bool {
+        self.close_to_tray.load(Ordering::SeqCst)
+    }
+
+    /// Whether a close request should hide the window rather than close it.
+    pub fn should_hide_on_close(&self) -> bool {
+        self.close_to_tray() && !self.quitting.load(Ordering::SeqCst)
+    }
+}
+
+/// Command-line flags that start the app hidden in the tray
+const MINIMIZED_FLAGS: [&str; 2] = ["--minimized", "--hidden"];
+/// How long the hidden main window waits for `frontend_ready`
+pub const FRONTEND_READY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Whether `args` request starting hidden, e.g. from an autostart entry.
+pub fn is_minimized_launch<S: AsRef<str>>(args: &[S]) -> bool {
+    args.iter()
+        .any(|arg| MINIMIZED_FLAGS.contains(&arg.as_ref()))
+}
+
+/// Managed state describing how this process was launched
+#[derive(Debug, Default)]
+pub struct LaunchState {
+    pub started_minimized: bool,
+    /// Set once the main window has been shown at startup
+    initially_shown: AtomicBool,
+}
+
+impl LaunchState {
+    pub fn from_args() -> Self {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        Self {
+            started_minimized: is_minimized_launch(&args),
+            initially_shown: AtomicBool::new(false),
+        }
+    }
+}
+
+/// Show and focus the hidden main window the first time this is called,
+/// unless the app was launched with `--minimized`.
+fn show_initially(app: &AppHandle, reason: &str) {
+    let Some(launch) = app.try_state::<LaunchState>() else {
+        return;
+    };
+    if launch.started_minimized || launch.initially_shown.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    log::debug!("Showing main window: {}", reason);
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        #[cfg(desktop)]
+        let _ = window.set_focus();
+    }
+}
+
+/// Show the main window if the frontend hasn't reported ready within
+/// [`FRONTEND_READY_TIMEOUT`]. Called from `setup` after the saved geometry
+/// is restored.
+pub fn show_when_ready(app: &AppHandle) {
+    if app.state::<LaunchState>().started_minimized {
+        return;
+    }
+    let app = app.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(FRONTEND_READY_TIMEOUT);
+        show_initially(&app, "frontend_ready timed out");
+    });
+}
+
+/// Report that the first render is done, so the main window can be shown.
+/// Calls after the first, or with `--minimized`, do nothing.
+#[tauri::command]
+pub fn frontend_ready(app: AppHandle) {
+    show_initially(&app, "frontend ready");
+}
+
+/// Whether the app was launched with `--minimized`/`--hidden`.
+///
+/// The frontend can defer expensive initial rendering until the window is first shown.
+#[tauri::command]
+pub fn was_started_minimized(state: State<'_, LaunchState>) -> bool {
+    state.started_minimized
+}
+
+/// Payload of the `second-instance` event
+#[derive(Debug, Clone, Serialize)]
+pub struct SecondInstancePayload {
+    pub args: Vec<String>,
+    pub cwd: String,
+}
+
+/// Handle a second launch forwarded by the single-instance plugin.
+///
+/// Brings the window forward (unless the new launch asked to stay minimized),
+/// queues any deep link found in its arguments, and tells the frontend so it
+/// can react to flags such as `--compose`.
+#[cfg(desktop)]
+pub fn on_second_instance(app: &AppHandle, args: Vec<String>, cwd: String) {
+    if let Some(target) = crate::deep_link::target_from_args(&args) {
+        crate::fcm::queue_navigation(app, target.feed_id, target.post_id, target.kind);
+    }
+
+    if !is_minimized_launch(&args) {
+        crate::tray::show_main_window(app);
+    }
+
+    let _ = app.emit("second-instance", SecondInstancePayload { args, cwd });
+}
+
+/// Exit the app, bypassing close-to-tray.
+pub fn quit(app: &AppHandle) {
+    if let Some(state) = app.try_state::<WindowBehaviorState>() {
+        state.quitting.store(true, Ordering::SeqCst);
+    }
+    app.exit(0);
+}
+
+/// Undo [`quit`] after the exit was held back, so closing the window goes to
+/// the tray again.
+pub fn cancel_quit(app: &AppHandle) {
+    if let Some(state) = app.try_state::<WindowBehaviorState>() {
+        state.quitting.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Whether the main window is visible and focused.
+pub fn is_main_window_focused(app: &AppHandle) -> bool {
+    app.get_webview_window("main").is_some_and(|window| {
+        window.is_visible().unwrap_or(false) && window.is_focused().unwrap_or(false)
+    })
+}
+
+/// Window event hook registered in `run()`.
+pub fn on_window_event(window: &Window, event: &WindowEvent) {
+    crate::window_focus::on_window_event(window, event);
+    if window.label() != "main" {
+        if crate::feed_windows::feed_of_label(window.label()).is_some() {
+            crate::feed_windows::on_window_event(window, event);
+        }
+        return;
+    }
+
+    match event {
+        WindowEvent::CloseRequested { api, .. } => {
+            #[cfg(desktop)]
+            save_geometry(window.app_handle());
+            crate::wake_lock::release_all(
+                window.app_handle(),
+                crate::wake_lock::ReleaseReason::WindowClosed,
+            );
+
+            let hide = window
+                .try_state::<WindowBehaviorState>()
+                .map(|state| state.should_hide_on_close())
+                .unwrap_or(false);
+
+            if hide {
+                api.prevent_close();
+                let _ = window.hide();
+            } else if !crate::feed_windows::list(window.app_handle()).is_empty() {
+                // Pop-outs would otherwise keep a windowless main UI running
+                api.prevent_close();
+                quit(window.app_handle());
+            }
+        }
+        WindowEvent::Focused(true) => {
+            if let Some(active) = window.try_state::<crate::notifications::ActiveFeedState>() {
+                active.set_focused(true);
+            }
+            #[cfg(desktop)]
+            crate::attention::on_focused(window);
+            crate::fcm::recheck_notification_permission(window.app_handle());
+            crate::theme::refresh(window.app_handle());
+            if let Some(lock) = window.try_state::<crate::lock::LockState>() {
+                lock.on_focus_changed(true);
+            }
+            #[cfg(desktop)]
+            crate::zoom::register_accelerators(window.app_handle());
+            #[cfg(desktop)]
+            crate::app_shortcuts::register_accelerators(window.app_handle());
+        }
+        WindowEvent::Focused(false) => {
+            if let Some(active) = window.try_state::<crate::notifications::ActiveFeedState>() {
+                active.set_focused(false);
+            }
+            if let Some(lock) = window.try_state::<crate::lock::LockState>() {
+                lock.on_focus_changed(false);
+            }
+            #[cfg(desktop)]
+            crate::zoom::unregister_accelerators(window.app_handle());
+            #[cfg(desktop)]
+            crate::app_shortcuts::unregister_accelerators(window.app_handle());
+        }
+        WindowEvent::ThemeChanged(theme) => {
+            crate::theme::on_theme_changed(window.app_handle(), *theme);
+        }
+        WindowEvent::DragDrop(DragDropEvent::Drop { paths, .. }) => {
+            crate::file_drop::on_drop(window.app_handle(), paths.clone());
+        }
+        #[cfg(desktop)]
+        WindowEvent::Moved(_) | WindowEvent::Resized(_) => {
+            schedule_geometry_save(window.app_handle());
+        }
+        _ => {}
+    }
+}
+
+// ============= Window geometry =============
+
+/// Saved geometry of the main window, in physical pixels
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    #[serde(default)]
+    pub maximized: bool,
+    /// Name of the monitor the window was on when saved
+    #[serde(default)]
+    pub monitor: Option<String>,
+}
+
+/// A monitor's work area, reduced to what geometry resolution needs
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonitorArea {
+    pub name: Option<String>,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl MonitorArea {
+    fn overlap(&self, geometry: &WindowGeometry) -> (i64, i64) {
+        let overlap = |a_start: i32, a_len: u32, b_start: i32, b_len: u32| {
+            let start = (a_start as i64).max(b_start as i64);
+            let end = (a_start as i64 + a_len as i64).min(b_start as i64 + b_len as i64);
+            (end - start).max(0)
+        };
+        (
+            overlap(self.x, self.width, geometry.x, geometry.width),
+            overlap(self.y, self.height, geometry.y, geometry.height),
+        )
+    }
+
+    /// Move and shrink `geometry` so it fits entirely inside this monitor.
+    fn clamp(&self, geometry: &WindowGeometry) -> WindowGeometry {
+        let width = geometry.width.min(self.width);
+        let height = geometry.height.min(self.height);
+        let max_x = self.x as i64 + (self.width - width) as i64;
+        let max_y = self.y as i64 + (self.height - height) as i64;
+        WindowGeometry {
+            x: (geometry.x as i64).clamp(self.x as i64, max_x) as i32,
+            y: (geometry.y as i64).clamp(self.y as i64, max_y) as i32,
+            width,
+            height,
+            maximized: geometry.maximized,
+            monitor: self.name.clone(),
+        }
+    }
+}
+
+/// Decide where a saved window should be restored, if anywhere.
+///
+/// - If the monitor it was saved on is gone, clamp it into the primary monitor.
+/// - If it would be entirely (or almost entirely) off-screen, ignore it.
+/// - Otherwise restore it as saved.
+pub fn resolve_geometry(
+    saved: &WindowGeometry,
+    monitors: &[MonitorArea],
+    primary: Option<&MonitorArea>,
+) -> Option<WindowGeometry> {
+    if saved.width == 0 || saved.height == 0 {
+        return None;
+    }
+
+    let monitor_missing = saved.monitor.as_ref().is_some_and(|name| {
+        !monitors
+            .iter()
+            .any(|monitor| monitor.name.as_ref() == Some(name))
+    });
+    if monitor_missing {
+        return primary.map(|primary| primary.clamp(saved));
+    }
+
+    let visible = monitors.iter().any(|monitor| {
+        let (overlap_x, overlap_y) = monitor.overlap(saved);
+        overlap_x >= MIN_VISIBLE_PX && overlap_y >= MIN_VISIBLE_PX
+    });
+    visible.then(|| saved.clone())
+}
+
+/// Managed state used to debounce geometry saves
+#[cfg(desktop)]
+#[derive(Debug, Default)]
+pub struct WindowGeometryState {
+    generation: AtomicU64,
+    save_scheduled: AtomicBool,
+}
+
+#[cfg(desktop)]
+pub(crate) fn monitor_area(monitor: &tauri::Monitor) -> MonitorArea {
+    let area = monitor.work_area();
+    MonitorArea {
+        name: monitor.name().cloned(),
+        x: area.position.x,
+        y: area.position.y,
+        width: area.size.width,
+        height: area.size.height,
+    }
+}
+
+/// Restore the saved geometry onto the main window. Call before showing it.
+#[cfg(desktop)]
+pub fn restore_geometry(app: &AppHandle) {
+    app.manage(WindowGeometryState::default());
+
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+    let Some(saved) = storage::config_file(app, GEOMETRY_FILE)
+        .ok()
+        .and_then(|path| storage::read_json::<WindowGeometry>(&path))
+    else {
+        return;
+    };
+
+    let monitors: Vec<MonitorArea> = window
+        .available_monitors()
+        .unwrap_or_default()
+        .iter()
+        .map(monitor_area)
+        .collect();
+    let primary = window
+        .primary_monitor()
+        .ok()
+        .flatten()
+        .map(|monitor| monitor_area(&monitor));
+
+    match resolve_geometry(&saved, &monitors, primary.as_ref()) {
+        Some(geometry) => apply_geometry(&window, &geometry),
+        None => log::info!("Ignoring saved window state: window would be off-screen"),
+    }
+}
+
+#[cfg(desktop)]
+pub(crate) fn apply_geometry(window: &WebviewWindow, geometry: &WindowGeometry) {
+    let _ = window.set_size(PhysicalSize::new(geometry.width, geometry.height));
+    let _ = window.set_position(PhysicalPosition::new(geometry.x, geometry.y));
+    if geometry.maximized {
+        let _ = window.maximize();
+    }
+}
+
+/// Save the main window geometry after moves/resizes settle.
+#[cfg(desktop)]
+fn schedule_geometry_save(app: &AppHandle) {
+    let Some(state) = app.try_state::<WindowGeometryState>() else {
+        return;
+    };
+    state.generation.fetch_add(1, Ordering::SeqCst);
+    if state.save_scheduled.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let state = app.state::<WindowGeometryState>();
+        loop {
+            let seen = state.generation.load(Ordering::SeqCst);
+            std::thread::sleep(GEOMETRY_SAVE_DEBOUNCE);
+            if state.generation.load(Ordering::SeqCst) == seen {
+                break;
+            }
+        }
+        state.save_scheduled.store(false, Ordering::SeqCst);
+        save_geometry(&app);
+    });
+}
+
+/// Persist the main window geometry now.
+///
+/// While maximized or minimized only the maximized flag is updated, so the
+/// restored (un-maximized) bounds stay what the user last chose.
+#[cfg(desktop)]
+pub fn save_geometry(app: &AppHandle) {
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+    let Ok(path) = storage::config_file(app, GEOMETRY_FILE) else {
+        return;
+    };
+
+    // The compact window isn't what the user wants back next launch
+    let compact = app
+        .try_state::<CompactModeState>()
+        .is_some_and(|state| state.is_active());
+    if compact || window.is_minimized().unwrap_or(false) {
+        return;
+    }
+    let maximized = window.is_maximized().unwrap_or(false);
+    let previous = storage::read_json::<WindowGeometry>(&path);
+
+    let geometry = match (maximized, previous) {
+        (true, Some(previous)) => WindowGeometry {
+            maximized: true,
+            ..previous
+        },
+        _ => {
+            let (Ok(position), Ok(size)) = (window.outer_position(), window.inner_size()) else {
+                return;
+            };
+            WindowGeometry {
+                x: position.x,
+                y: position.y,
+                width: size.width,
+                height: size.height,
+                maximized,
+                monitor: window
+                    .current_monitor()
+                    .ok()
+                    .flatten()
+                    .and_then(|monitor| monitor.name().cloned()),
+            }
+        }
+    };
+
+    if let Err(e) = storage::write_json_atomic(&path, &geometry) {
+        log::warn!("Failed to save window state: {}", e);
+    }
+}
+
+/// Forget the saved window geometry and re-centre the main window.
+///
+/// For users whose window ended up somewhere unreachable.
+#[tauri::command]
+pub fn reset_window_state(app: AppHandle) -> Result<(), CommandError> {
+    let path = storage::config_file(&app, GEOMETRY_FILE)?;
+    storage::remove_file(&path)?;
+
+    #[cfg(desktop)]
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unmaximize();
+        let _ = window.center();
+    }
+
+    Ok(())
+}
+
+/// Enable or disable close-to-tray, persisting the choice.
+#[tauri::command]
+pub fn set_close_to_tray(
+    app: AppHandle,
+    state: State<'_, WindowBehaviorState>,
+    enabled: bool,
+) -> Result<(), CommandError> {
+    let path = storage::config_file(&app, BEHAVIOR_FILE)?;
+    storage::write_json_atomic(
+        &path,
+        &WindowBehavior {
+            close_to_tray: enabled,
+        },
+    )?;
+    state.close_to_tray.store(enabled, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Whether closing the main window hides it to the tray.
+#[tauri::command]
+pub fn get_close_to_tray(state: State<'_, WindowBehaviorState>) -> bool {
+    state.close_to_tray()
+}
+
+// ============= Compact mode =============
+
+/// Default size of compact mode when toggled from the tray, in logical pixels
+#[cfg(desktop)]
+pub const DEFAULT_COMPACT_SIZE: (u32, u32) = (360, 640);
+/// Smallest compact window accepted, in logical pixels
+#[cfg(desktop)]
+const MIN_COMPACT_SIZE: (u32, u32) = (240, 160);
+
+/// Result of the always-on-top and compact mode commands
+#[cfg_attr(mobile, allow(dead_code))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WindowModeStatus {
+    pub compact: bool,
+    pub always_on_top: bool,
+    /// False where the window manager is known to ignore the always-on-top
+    /// hint (native Wayland), so the UI shouldn't promise it
+    pub always_on_top_honored: bool,
+}
+
+/// Window state saved on entering compact mode, restored on exit
+#[cfg(desktop)]
+#[derive(Debug, Clone)]
+struct SavedMode {
+    geometry: WindowGeometry,
+    decorated: bool,
+    always_on_top: bool,
+}
+
+/// Managed state for compact mode. Kept in memory only: the next launch
+/// always starts in the normal window.
+#[cfg(desktop)]
+#[derive(Debug, Default)]
+pub struct CompactModeState {
+    saved: std::sync::Mutex<Option<SavedMode>>,
+}
+
+#[cfg(desktop)]
+impl CompactModeState {
+    fn saved(&self) -> std::sync::MutexGuard<'_, Option<SavedMode>> {
+        self.saved.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.saved().is_some()
+    }
+}
+
+/// Whether keep-above hints are honoured, given `WAYLAND_DISPLAY` and
+/// `GDK_BACKEND`. Wayland compositors ignore them unless GTK runs on XWayland.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) fn keep_above_honored(wayland_display: Option<&str>, gdk_backend: Option<&str>) -> bool {
+    let wayland = wayland_display.is_some_and(|display| !display.is_empty());
+    !wayland || gdk_backend.is_some_and(|backend| backend.starts_with("x11"))
+}
+
+#[cfg(desktop)]
+fn always_on_top_honored() -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        let var = |name| std::env::var(name).ok();
+        keep_above_honored(
+            var("WAYLAND_DISPLAY").as_deref(),
+            var("GDK_BACKEND").as_deref(),
+        )
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        true
+    }
+}
+
+#[cfg(desktop)]
+fn main_window(app: &AppHandle) -> Result<WebviewWindow, CommandError> {
+    app.get_webview_window("main").ok_or_else(|| {
+        CommandError::new(crate::error::UNAVAILABLE, "Main window not available")
+    })
+}
+
+#[cfg(desktop)]
+fn window_mode(app: &AppHandle, window: &WebviewWindow) -> WindowModeStatus {
+    let always_on_top = window.is_always_on_top().unwrap_or(false);
+    WindowModeStatus {
+        compact: app.state::<CompactModeState>().is_active(),
+        always_on_top,
+        always_on_top_honored: always_on_top_honored(),
+    }
+}
+
+#[cfg(mobile)]
+fn window_modes_unsupported() -> CommandError {
+    CommandError::new(
+        crate::error::NOT_SUPPORTED,
+        "Window modes are not available on this platform",
+    )
+}
+
+/// Current geometry of `window`, for restoring later.
+#[cfg(desktop)]
+pub(crate) fn current_geometry(window: &WebviewWindow) -> Result<WindowGeometry, CommandError> {
+    let position = window.outer_position().map_err(|e| e.to_string())?;
+    let size = window.inner_size().map_err(|e| e.to_string())?;
+    Ok(WindowGeometry {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+        maximized: window.is_maximized().unwrap_or(false),
+        monitor: window
+            .current_monitor()
+            .ok()
+            .flatten()
+            .and_then(|monitor| monitor.name().cloned()),
+    })
+}
+
+#[cfg(desktop)]
+fn mode_changed(app: &AppHandle, status: &WindowModeStatus) {
+    crate::tray::set_compact_checked(app, status.compact);
+    let _ = app.emit("window-mode-changed", status.clone());
+}
+
+/// Keep the main window above other windows, or stop doing so.
+///
+/// `always_on_top_honored` in the result is false where the window manager
+/// ignores the request.
+#[tauri::command]
+pub fn set_always_on_top(
+    app: AppHandle,
+    enabled: bool,
+) -> Result<WindowModeStatus, CommandError> {
+    #[cfg(desktop)]
+    {
+        let window = main_window(&app)?;
+        window
+            .set_always_on_top(enabled)
+            .map_err(|e| e.to_string())?;
+        let status = window_mode(&app, &window);
+        mode_changed(&app, &status);
+        Ok(status)
+    }
+    #[cfg(mobile)]
+    {
+        let _ = (app, enabled);
+        Err(window_modes_unsupported())
+    }
+}
+
+/// Shrink the main window to `width` x `height` logical pixels, without
+/// decorations and on top of other windows.
+///
+/// The previous size, position and decorations are restored by
+/// `exit_compact_mode`. Calling it again while compact only resizes.
+#[tauri::command]
+pub fn enter_compact_mode(
+    app: AppHandle,
+    width: u32,
+    height: u32,
+) -> Result<WindowModeStatus, CommandError> {
+    #[cfg(desktop)]
+    {
+        enter_compact(&app, width, height)
+    }
+    #[cfg(mobile)]
+    {
+        let _ = (app, width, height);
+        Err(window_modes_unsupported())
+    }
+}
+
+#[cfg(desktop)]
+fn enter_compact(
+    app: &AppHandle,
+    width: u32,
+    height: u32,
+) -> Result<WindowModeStatus, CommandError> {
+    if width < MIN_COMPACT_SIZE.0 || height < MIN_COMPACT_SIZE.1 {
+        return Err(CommandError::invalid_argument(format!(
+            "Compact mode needs at least {}x{}",
+            MIN_COMPACT_SIZE.0, MIN_COMPACT_SIZE.1
+        )));
+    }
+    let window = main_window(app)?;
+    let state = app.state::<CompactModeState>();
+    {
+        let mut saved = state.saved();
+        if saved.is_none() {
+            *saved = Some(SavedMode {
+                geometry: current_geometry(&window)?,
+                decorated: window.is_decorated().unwrap_or(true),
+                always_on_top: window.is_always_on_top().unwrap_or(false),
+            });
+        }
+    }
+
+    if window.is_maximized().unwrap_or(false) {
+        let _ = window.unmaximize();
+    }
+    let _ = window.set_decorations(false);
+    window
+        .set_size(tauri::LogicalSize::new(width, height))
+        .map_err(|e| e.to_string())?;
+    let _ = window.set_always_on_top(true);
+
+    let status = window_mode(app, &window);
+    mode_changed(app, &status);
+    Ok(status)
+}
+
+/// Leave compact mode, restoring the window as it was before.
+#[tauri::command]
+pub fn exit_compact_mode(app: AppHandle) -> Result<WindowModeStatus, CommandError> {
+    #[cfg(desktop)]
+    {
+        exit_compact(&app)
+    }
+    #[cfg(mobile)]
+    {
+        let _ = app;
+        Err(window_modes_unsupported())
+    }
+}
+
+#[cfg(desktop)]
+fn exit_compact(app: &AppHandle) -> Result<WindowModeStatus, CommandError> {
+    let window = main_window(app)?;
+    let saved = app.state::<CompactModeState>().saved().take();
+    if let Some(saved) = saved {
+        let _ = window.set_always_on_top(saved.always_on_top);
+        let _ = window.set_decorations(saved.decorated);
+        apply_geometry(&window, &saved.geometry);
+    }
+    let status = window_mode(app, &window);
+    mode_changed(app, &status);
+    Ok(status)
+}
+
+/// Enter compact mode at the default size, or leave it. Used by the tray.
+#[cfg(desktop)]
+pub fn toggle_compact_mode(app: &AppHandle) {
+    let result = if app.state::<CompactModeState>().is_active() {
+        exit_compact(app)
+    } else {
+        crate::tray::show_main_window(app);
+        enter_compact(app, DEFAULT_COMPACT_SIZE.0, DEFAULT_COMPACT_SIZE.1)
+    };
+    if let Err(e) = result {
+        log::warn!("Failed to toggle compact mode: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn close_is_not_intercepted_by_default() {
+        let state = WindowBehaviorState::default();
+        assert!(!state.should_hide_on_close());
+    }
+
+    #[test]
+    fn quitting_overrides_close_to_tray() {
+        let state = WindowBehaviorState::default();
+        state.close_to_tray.store(true, Ordering::SeqCst);
+        assert!(state.should_hide_on_close());
+
+        state.quitting.store(true, Ordering::SeqCst);
+        assert!(!state.should_hide_on_close());
+    }
+
+    #[test]
+    fn keep_above_is_ignored_on_native_wayland_only() {
+        assert!(keep_above_honored(None, None));
+        assert!(keep_above_honored(Some(""), None));
+        assert!(!keep_above_honored(Some("wayland-0"), None));
+        assert!(!keep_above_honored(Some("wayland-0"), Some("wayland")));
+        assert!(keep_above_honored(Some("wayland-0"), Some("x11")));
+    }
+
+    fn monitor(name: &str, x: i32, y: i32) -> MonitorArea {
+        MonitorArea {
+            name: Some(name.to_string()),
+            x,
+            y,
+            width: 1920,
+            height: 1080,
+        }
+    }
+
+    fn geometry(x: i32, y: i32, monitor: Option<&str>) -> WindowGeometry {
+        WindowGeometry {
+            x,
+            y,
+            width: 1200,
+            height: 800,
+            maximized: false,
+            monitor: monitor.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn on_screen_geometry_is_restored_as_saved() {
+        let monitors = [monitor("A", 0, 0)];
+        let saved = geometry(100, 100, Some("A"));
+        assert_eq!(
+            resolve_geometry(&saved, &monitors, monitors.first()),
+            Some(saved)
+        );
+    }
+
+    #[test]
+    fn missing_monitor_clamps_to_primary() {
+        let monitors = [monitor("A", 0, 0)];
+        let saved = geometry(2500, 300, Some("B"));
+        let resolved = resolve_geometry(&saved, &monitors, monitors.first()).unwrap();
+
+        assert_eq!(resolved.x, 1920 - 1200);
+        assert_eq!(resolved.y, 300);
+        assert_eq!(resolved.monitor.as_deref(), Some("A"));
+    }
+
+    #[test]
+    fn off_screen_geometry_is_ignored() {
+        let monitors = [monitor("A", 0, 0)];
+        let saved = geometry(5000, 5000, Some("A"));
+        assert_eq!(resolve_geometry(&saved, &monitors, monitors.first()), None);
+    }
+
+    #[test]
+    fn minimized_flags_are_detected() {
+        assert!(is_minimized_launch(&["--minimized"]));
+        assert!(is_minimized_launch(&["--foo", "--hidden"]));
+        assert!(!is_minimized_launch(&["--compose"]));
+        assert!(!is_minimized_launch::<&str>(&[]));
+    }
+
+    #[test]
+    fn missing_flag_deserializes_as_disabled() {
+        let behavior: WindowBehavior = serde_json::from_str("{}").unwrap();
+        assert!(!behavior.close_to_tray);
+    }
+}
@@ -0,0 +1,206 @@
+//! Native window focus, for read receipts.
+//!
+//! The webview's visibility API doesn't notice a window being covered by
+//! another app or minimized, so focus and minimize/restore transitions of
+//! every window are emitted as `window-focus-changed` with the window label
+//! (pop-outs report separately) and the time of the change.
+//! `is_window_focused` gives the state to start from.
+//!
+//! Some Linux window managers flap focus several times while switching
+//! workspaces, so a change is only reported once it has been stable for
+//! [`FOCUS_DEBOUNCE`], and only if it differs from the last one reported.
+
+use crate::error::CommandError;
+use crate::fcm::now_unix_ms;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, Window, WindowEvent};
+
+/// How long a focus change must hold before it is reported
+pub const FOCUS_DEBOUNCE: Duration = Duration::from_millis(150);
+
+/// Focus state of a window
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct WindowFocus {
+    focused: bool,
+    minimized: bool,
+}
+
+/// Payload of the `window-focus-changed` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WindowFocusChanged {
+    label: String,
+    focused: bool,
+    minimized: bool,
+    /// Unix timestamp (ms) of the change
+    timestamp: u64,
+}
+
+/// A raw focus or minimize event
+#[derive(Debug, Clone, Copy)]
+enum Change {
+    Focused(bool),
+    Minimized(bool),
+}
+
+/// Focus changes of one window waiting to settle
+#[derive(Debug, Default)]
+struct Tracked {
+    observed: WindowFocus,
+    /// When `observed` last changed (Unix ms)
+    changed_at: u64,
+    reported: Option<WindowFocus>,
+    /// Bumped on every event, so the settle timer can tell it must wait on
+    generation: u64,
+    scheduled: bool,
+}
+
+impl Tracked {
+    /// Apply an event. Returns whether a settle timer has to be started.
+    fn observe(&mut self, change: Change, now: u64) -> bool {
+        let before = self.observed;
+        match change {
+            Change::Focused(focused) => self.observed.focused = focused,
+            Change::Minimized(minimized) => self.observed.minimized = minimized,
+        }
+        if self.observed != before {
+            self.changed_at = now;
+        }
+        self.generation += 1;
+        !std::mem::replace(&mut self.scheduled, true)
+    }
+
+    /// The state to report once events stopped, unless it was reported already.
+    fn settle(&mut self) -> Option<WindowFocus> {
+        self.scheduled = false;
+        if self.reported == Some(self.observed) {
+            return None;
+        }
+        self.reported = Some(self.observed);
+        Some(self.observed)
+    }
+}
+
+/// Managed state debouncing focus changes per window label
+#[derive(Debug, Default)]
+pub struct WindowFocusState {
+    windows: Mutex<HashMap<String, Tracked>>,
+}
+
+impl WindowFocusState {
+    fn windows(&self) -> std::sync::MutexGuard<'_, HashMap<String, Tracked>> {
+        self.windows.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn generation(&self, label: &str) -> Option<u64> {
+        self.windows().get(label).map(|tracked| tracked.generation)
+    }
+}
+
+/// Report the state of `label` after its events have settled.
+fn schedule_report(app: &AppHandle, label: String) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let state = app.state::<WindowFocusState>();
+        loop {
+            let Some(seen) = state.generation(&label) else {
+                return;
+            };
+            std::thread::sleep(FOCUS_DEBOUNCE);
+            if state.generation(&label) == Some(seen) {
+                break;
+            }
+        }
+        let settled = state.windows().get_mut(&label).and_then(|tracked| {
+            let focus = tracked.settle()?;
+            Some((focus, tracked.changed_at))
+        });
+        if let Some((focus, timestamp)) = settled {
+            let payload = WindowFocusChanged {
+                label,
+                focused: focus.focused,
+                minimized: focus.minimized,
+                timestamp,
+            };
+            let _ = app.emit("window-focus-changed", payload);
+        }
+    });
+}
+
+/// Window event hook, called from [`crate::window::on_window_event`] for
+/// every window.
+pub fn on_window_event(window: &Window, event: &WindowEvent) {
+    let change = match event {
+        WindowEvent::Focused(focused) => Change::Focused(*focused),
+        // Minimizing and restoring show up as resizes
+        WindowEvent::Resized(_) => Change::Minimized(window.is_minimized().unwrap_or(false)),
+        WindowEvent::Destroyed => {
+            if let Some(state) = window.try_state::<WindowFocusState>() {
+                state.windows().remove(window.label());
+            }
+            return;
+        }
+        _ => return,
+    };
+    let Some(state) = window.try_state::<WindowFocusState>() else {
+        return;
+    };
+    let start = state
+        .windows()
+        .entry(window.label().to_string())
+        .or_default()
+        .observe(change, now_unix_ms());
+    if start {
+        schedule_report(window.app_handle(), window.label().to_string());
+    }
+}
+
+/// Whether the window `label` (default `main`) has focus and is not
+/// minimized right now.
+#[tauri::command]
+pub fn is_window_focused(app: AppHandle, label: Option<String>) -> Result<bool, CommandError> {
+    let label = label.as_deref().unwrap_or("main");
+    let window = app.get_webview_window(label).ok_or_else(|| {
+        CommandError::new(crate::error::NOT_FOUND, format!("No window {}", label))
+    })?;
+    Ok(window.is_focused().unwrap_or(false) && !window.is_minimized().unwrap_or(false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flapping_settles_to_the_last_state() {
+        let mut tracked = Tracked::default();
+        assert!(tracked.observe(Change::Focused(true), 1));
+        assert!(!tracked.observe(Change::Focused(false), 2));
+        assert!(!tracked.observe(Change::Focused(true), 3));
+        assert_eq!(
+            tracked.settle(),
+            Some(WindowFocus {
+                focused: true,
+                minimized: false,
+            })
+        );
+        assert_eq!(tracked.changed_at, 3);
+    }
+
+    #[test]
+    fn unchanged_state_is_not_reported_again() {
+        let mut tracked = Tracked::default();
+        tracked.observe(Change::Focused(true), 1);
+        assert!(tracked.settle().is_some());
+
+        // Blur and refocus within the debounce window
+        assert!(tracked.observe(Change::Focused(false), 2));
+        tracked.observe(Change::Focused(true), 3);
+        assert_eq!(tracked.settle(), None);
+
+        tracked.observe(Change::Minimized(true), 4);
+        assert_eq!(tracked.settle().map(|state| state.minimized), Some(true));
+    }
+}
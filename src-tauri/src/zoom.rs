@@ -0,0 +1,216 @@
+//! Zoom level of the main webview, kept across launches.
+//!
+//! The factor lives in the `zoomFactor` setting and is applied again in
+//! `setup`. `set_zoom`, `zoom_step` and, on desktop, the Ctrl+Plus/Minus/0
+//! accelerators all go through [`apply`], which clamps to
+//! [`MIN_ZOOM`]..=[`MAX_ZOOM`], persists the factor and emits `zoom-changed`.
+//!
+//! The accelerators are global shortcuts, so they are only registered while
+//! the main window has focus and don't take the keys from other apps.
+
+use crate::error::CommandError;
+use crate::settings::{self, SettingsState};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+#[cfg(desktop)]
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut};
+
+pub const MIN_ZOOM: f64 = 0.5;
+pub const MAX_ZOOM: f64 = 3.0;
+const DEFAULT_ZOOM: f64 = 1.0;
+/// Change per accelerator press
+#[cfg_attr(mobile, allow(dead_code))]
+const STEP: f64 = 0.1;
+
+/// What a zoom accelerator does
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(mobile, allow(dead_code))]
+enum ZoomAction {
+    Step(f64),
+    Reset,
+}
+
+/// Accelerators registered while the main window is focused. Plus is listed
+/// with and without Shift since it shares a key with Equal on most layouts.
+#[cfg(desktop)]
+const ACCELERATORS: &[(&str, ZoomAction)] = &[
+    ("CommandOrControl+Equal", ZoomAction::Step(STEP)),
+    ("CommandOrControl+Shift+Equal", ZoomAction::Step(STEP)),
+    ("CommandOrControl+NumpadAdd", ZoomAction::Step(STEP)),
+    ("CommandOrControl+Minus", ZoomAction::Step(-STEP)),
+    ("CommandOrControl+NumpadSubtract", ZoomAction::Step(-STEP)),
+    ("CommandOrControl+Digit0", ZoomAction::Reset),
+    ("CommandOrControl+Numpad0", ZoomAction::Reset),
+];
+
+/// Payload of the `zoom-changed` event and result of the zoom commands
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ZoomLevel {
+    pub factor: f64,
+    /// `factor` as a rounded percentage, for display
+    pub percent: u32,
+}
+
+impl ZoomLevel {
+    fn new(factor: f64) -> Self {
+        Self {
+            factor,
+            percent: (factor * 100.0).round() as u32,
+        }
+    }
+}
+
+/// `factor` clamped to the supported range and rounded to whole percent, so
+/// repeated steps don't drift. Non-finite values fall back to the default.
+fn clamp(factor: f64) -> f64 {
+    if !factor.is_finite() {
+        return DEFAULT_ZOOM;
+    }
+    (factor.clamp(MIN_ZOOM, MAX_ZOOM) * 100.0).round() / 100.0
+}
+
+fn current(app: &AppHandle) -> f64 {
+    app.try_state::<SettingsState>()
+        .and_then(|state| state.get().zoom_factor)
+        .map_or(DEFAULT_ZOOM, clamp)
+}
+
+/// Zoom the main webview to `factor`, persist it and tell the frontend.
+fn apply(app: &AppHandle, factor: f64) -> Result<ZoomLevel, CommandError> {
+    let level = ZoomLevel::new(clamp(factor));
+    if let Some(window) = app.get_webview_window("main") {
+        window.set_zoom(level.factor).map_err(|e| e.to_string())?;
+    }
+    // The default is stored as unset so it follows any future default
+    let value = if level.factor == DEFAULT_ZOOM {
+        serde_json::Value::Null
+    } else {
+        level.factor.into()
+    };
+    settings::set(app, "zoomFactor", value)?;
+    let _ = app.emit("zoom-changed", level);
+    Ok(level)
+}
+
+/// Apply the saved zoom level. Called from `setup` once the main window exists.
+pub fn init(app: &AppHandle) {
+    let factor = current(app);
+    if factor == DEFAULT_ZOOM {
+        return;
+    }
+    if let Some(window) = app.get_webview_window("main") {
+        if let Err(e) = window.set_zoom(factor) {
+            log::warn!("Failed to restore zoom level: {}", e);
+        }
+    }
+}
+
+/// The zoom accelerators, so other shortcuts can avoid them.
+#[cfg(desktop)]
+pub fn accelerators() -> impl Iterator<Item = &'static str> {
+    ACCELERATORS.iter().map(|(accelerator, _)| *accelerator)
+}
+
+/// Register the zoom accelerators; called when the main window gains focus.
+#[cfg(desktop)]
+pub fn register_accelerators(app: &AppHandle) {
+    for (accelerator, _) in ACCELERATORS {
+        let Ok(shortcut) = accelerator.parse::<Shortcut>() else {
+            continue;
+        };
+        if app.global_shortcut().is_registered(shortcut) {
+            continue;
+        }
+        if let Err(e) = app.global_shortcut().register(shortcut) {
+            log::debug!("Zoom shortcut {} unavailable: {}", accelerator, e);
+        }
+    }
+}
+
+/// Release the zoom accelerators; called when the main window loses focus.
+#[cfg(desktop)]
+pub fn unregister_accelerators(app: &AppHandle) {
+    for (accelerator, _) in ACCELERATORS {
+        if let Ok(shortcut) = accelerator.parse::<Shortcut>() {
+            let _ = app.global_shortcut().unregister(shortcut);
+        }
+    }
+}
+
+/// Handle a pressed zoom accelerator. Returns false if `shortcut` isn't one.
+#[cfg(desktop)]
+pub fn handle_shortcut(app: &AppHandle, shortcut: &Shortcut) -> bool {
+    let Some(action) = ACCELERATORS.iter().find_map(|(accelerator, action)| {
+        (accelerator.parse::<Shortcut>().ok().as_ref() == Some(shortcut)).then_some(*action)
+    }) else {
+        return false;
+    };
+    let factor = match action {
+        ZoomAction::Step(delta) => current(app) + delta,
+        ZoomAction::Reset => DEFAULT_ZOOM,
+    };
+    if let Err(e) = apply(app, factor) {
+        log::warn!("Failed to change zoom level: {}", e);
+    }
+    true
+}
+
+/// Zoom the main webview to `factor` (1.0 is 100%), clamped to 0.5–3.0.
+#[tauri::command]
+pub fn set_zoom(app: AppHandle, factor: f64) -> Result<ZoomLevel, CommandError> {
+    if !factor.is_finite() {
+        return Err(CommandError::invalid_argument(
+            "Zoom factor must be a number",
+        ));
+    }
+    apply(&app, factor)
+}
+
+#[tauri::command]
+pub fn get_zoom(app: AppHandle) -> ZoomLevel {
+    ZoomLevel::new(current(&app))
+}
+
+/// Change the zoom factor by `delta`, e.g. 0.1 for 10 percentage points.
+#[tauri::command]
+pub fn zoom_step(app: AppHandle, delta: f64) -> Result<ZoomLevel, CommandError> {
+    if !delta.is_finite() {
+        return Err(CommandError::invalid_argument("Zoom step must be a number"));
+    }
+    apply(&app, current(&app) + delta)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn factors_are_clamped_and_rounded() {
+        assert_eq!(clamp(0.1), MIN_ZOOM);
+        assert_eq!(clamp(10.0), MAX_ZOOM);
+        assert_eq!(clamp(f64::NAN), DEFAULT_ZOOM);
+        assert_eq!(clamp(1.0 + STEP + STEP + STEP), 1.3);
+        assert_eq!(ZoomLevel::new(clamp(1.254)).percent, 125);
+    }
+
+    #[test]
+    fn stepping_stops_at_the_limits() {
+        let mut factor = DEFAULT_ZOOM;
+        for _ in 0..40 {
+            factor = clamp(factor + STEP);
+        }
+        assert_eq!(factor, MAX_ZOOM);
+        for _ in 0..40 {
+            factor = clamp(factor - STEP);
+        }
+        assert_eq!(factor, MIN_ZOOM);
+    }
+
+    #[cfg(desktop)]
+    #[test]
+    fn accelerators_parse() {
+        for (accelerator, _) in ACCELERATORS {
+            assert!(accelerator.parse::<Shortcut>().is_ok(), "{}", accelerator);
+        }
+    }
+}
@@ -10,6 +10,7 @@ export * from './useUnreadBadge';
 export * from './useAutoUpdate';
 export * from './usePushInitializer';
 export * from './useDeepLinkNavigation';
+export * from './useFrontendReady';
 export * from './useVirtualKeyboard';
 export * from './useVisualViewportHeight';
 export * from './useMentionDataLossCheck';
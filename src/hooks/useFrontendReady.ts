@@ -0,0 +1,28 @@
+import { useEffect } from "react";
+import { debugError } from "@/lib/debug-logger";
+import { isTauri } from "@/lib/platform";
+
+/**
+ * Tells the desktop shell that the first render has painted, so it can show
+ * the main window. The window starts hidden to avoid a white flash.
+ */
+export function useFrontendReady(): void {
+  useEffect(() => {
+    if (!isTauri()) {
+      return;
+    }
+
+    // The frame after mount is the first one with content
+    const frame = requestAnimationFrame(() => {
+      import("@tauri-apps/api/core")
+        .then(({ invoke }) => invoke("frontend_ready"))
+        .catch((error) => {
+          debugError("[FrontendReady] Failed to report ready:", error);
+        });
+    });
+
+    return () => {
+      cancelAnimationFrame(frame);
+    };
+  }, []);
+}
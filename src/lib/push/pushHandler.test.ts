@@ -75,32 +75,28 @@ describe('pushHandler', () => {
 
     it('returns false when no pending navigation exists on Android', async () => {
       vi.mocked(detectPlatformAsync).mockResolvedValue('tauri-android');
-      vi.mocked(invoke).mockResolvedValue({ feed_id: null });
+      vi.mocked(invoke).mockResolvedValue(null);
 
       const result = await checkPendingNavigation();
 
       expect(result).toBe(false);
-      expect(invoke).toHaveBeenCalledWith('get_pending_navigation');
+      expect(invoke).toHaveBeenCalledWith('take_pending_navigation');
     });
 
     it('navigates to feed when pending navigation exists on Android', async () => {
       const mockFeedId = 'test-feed-id-12345';
       vi.mocked(detectPlatformAsync).mockResolvedValue('tauri-android');
       vi.mocked(invoke).mockImplementation((cmd: string) => {
-        if (cmd === 'get_pending_navigation') {
+        if (cmd === 'take_pending_navigation') {
           return Promise.resolve({ feed_id: mockFeedId });
         }
-        if (cmd === 'clear_pending_navigation') {
-          return Promise.resolve(undefined);
-        }
         return Promise.reject(new Error(`Unknown command: ${cmd}`));
       });
 
       const result = await checkPendingNavigation();
 
       expect(result).toBe(true);
-      expect(invoke).toHaveBeenCalledWith('get_pending_navigation');
-      expect(invoke).toHaveBeenCalledWith('clear_pending_navigation');
+      expect(invoke).toHaveBeenCalledWith('take_pending_navigation');
       expect(window.location.href).toBe(`/feeds?feed=${encodeURIComponent(mockFeedId)}`);
     });
 
@@ -108,12 +104,9 @@ describe('pushHandler', () => {
       const mockFeedId = 'ios-feed-id-67890';
       vi.mocked(detectPlatformAsync).mockResolvedValue('tauri-ios');
       vi.mocked(invoke).mockImplementation((cmd: string) => {
-        if (cmd === 'get_pending_navigation') {
+        if (cmd === 'take_pending_navigation') {
           return Promise.resolve({ feed_id: mockFeedId });
         }
-        if (cmd === 'clear_pending_navigation') {
-          return Promise.resolve(undefined);
-        }
         return Promise.reject(new Error(`Unknown command: ${cmd}`));
       });
 
@@ -123,33 +116,26 @@ describe('pushHandler', () => {
       expect(window.location.href).toBe(`/feeds?feed=${encodeURIComponent(mockFeedId)}`);
     });
 
-    it('clears pending navigation before navigating', async () => {
+    it('takes pending navigation in a single call', async () => {
       const mockFeedId = 'test-feed-id';
       vi.mocked(detectPlatformAsync).mockResolvedValue('tauri-android');
 
       const invokeOrder: string[] = [];
       vi.mocked(invoke).mockImplementation((cmd: string) => {
         invokeOrder.push(cmd);
-        if (cmd === 'get_pending_navigation') {
+        if (cmd === 'take_pending_navigation') {
           return Promise.resolve({ feed_id: mockFeedId });
         }
-        if (cmd === 'clear_pending_navigation') {
-          return Promise.resolve(undefined);
-        }
         return Promise.reject(new Error(`Unknown command: ${cmd}`));
       });
 
       await checkPendingNavigation();
 
-      // Verify clear is called before navigation (which sets window.location.href)
-      expect(invokeOrder).toContain('get_pending_navigation');
-      expect(invokeOrder).toContain('clear_pending_navigation');
-      expect(invokeOrder.indexOf('clear_pending_navigation')).toBeGreaterThan(
-        invokeOrder.indexOf('get_pending_navigation')
-      );
+      // Reading and removing happen in one command, so no tap can be lost in between
+      expect(invokeOrder).toEqual(['take_pending_navigation']);
     });
 
-    it('handles get_pending_navigation errors gracefully', async () => {
+    it('handles take_pending_navigation errors gracefully', async () => {
       vi.mocked(detectPlatformAsync).mockResolvedValue('tauri-android');
       vi.mocked(invoke).mockRejectedValue(new Error('Native error'));
 
@@ -162,12 +148,9 @@ describe('pushHandler', () => {
       const mockFeedId = 'feed with spaces & special=chars';
       vi.mocked(detectPlatformAsync).mockResolvedValue('tauri-android');
       vi.mocked(invoke).mockImplementation((cmd: string) => {
-        if (cmd === 'get_pending_navigation') {
+        if (cmd === 'take_pending_navigation') {
           return Promise.resolve({ feed_id: mockFeedId });
         }
-        if (cmd === 'clear_pending_navigation') {
-          return Promise.resolve(undefined);
-        }
         return Promise.reject(new Error(`Unknown command: ${cmd}`));
       });
 
@@ -216,7 +199,7 @@ describe('pushHandler', () => {
 
     it('checks pending navigation when visibility changes to visible', async () => {
       vi.mocked(detectPlatformAsync).mockResolvedValue('tauri-android');
-      vi.mocked(invoke).mockResolvedValue({ feed_id: null });
+      vi.mocked(invoke).mockResolvedValue(null);
 
       // Setup listener
       setupVisibilityChangeListener();
@@ -231,7 +214,7 @@ describe('pushHandler', () => {
       // Allow async operations to complete
       await new Promise(resolve => setTimeout(resolve, 10));
 
-      expect(invoke).toHaveBeenCalledWith('get_pending_navigation');
+      expect(invoke).toHaveBeenCalledWith('take_pending_navigation');
     });
 
     it('does not check pending navigation when visibility changes to hidden', async () => {
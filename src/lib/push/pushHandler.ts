@@ -11,7 +11,9 @@
  * Architecture:
  * - Native code stores pending feedId/path when notification/App Link is tapped
  * - TypeScript layer checks for pending navigation on app startup/resume
- * - Navigates to the appropriate page and clears the pending state
+ * - Notification navigation is taken (read and removed in one step) by Rust,
+ *   so a tap landing while we navigate is kept for the next check
+ * - Navigates to the appropriate page
  */
 
 import { detectPlatformAsync } from '@/lib/platform';
@@ -19,16 +21,16 @@ import { debugLog, debugError } from '@/lib/debug-logger';
 import { getPendingDeepLink, clearPendingDeepLink } from './pushManager';
 import { getFeedNavigationRoute } from '@/lib/navigation/appRoutes';
 
-// Type matching Rust PendingNavigationResult
-interface PendingNavigationResult {
-  feed_id: string | null;
+// Type matching Rust PendingNavigation
+interface PendingNavigation {
+  feed_id: string;
 }
 
 /**
- * Get pending navigation feedId from notification tap.
+ * Take the pending navigation feedId from a notification tap, removing it.
  * Returns null if no pending navigation or on non-mobile platforms.
  */
-async function getPendingNavigation(): Promise<string | null> {
+async function takePendingNavigation(): Promise<string | null> {
   const platform = await detectPlatformAsync();
 
   if (platform !== 'tauri-android' && platform !== 'tauri-ios') {
@@ -37,34 +39,14 @@ async function getPendingNavigation(): Promise<string | null> {
 
   try {
     const { invoke } = await import('@tauri-apps/api/core');
-    const result = await invoke<PendingNavigationResult>('get_pending_navigation');
-    return result.feed_id;
+    const result = await invoke<PendingNavigation | null>('take_pending_navigation');
+    return result?.feed_id ?? null;
   } catch (error) {
-    debugError('[PushHandler] Failed to get pending navigation:', error);
+    debugError('[PushHandler] Failed to take pending navigation:', error);
     return null;
   }
 }
 
-/**
- * Clear pending navigation after processing.
- * Should be called after successfully navigating or after determining navigation isn't possible.
- */
-async function clearPendingNavigation(): Promise<void> {
-  const platform = await detectPlatformAsync();
-
-  if (platform !== 'tauri-android' && platform !== 'tauri-ios') {
-    return;
-  }
-
-  try {
-    const { invoke } = await import('@tauri-apps/api/core');
-    await invoke('clear_pending_navigation');
-    debugLog('[PushHandler] Pending navigation cleared');
-  } catch (error) {
-    debugError('[PushHandler] Failed to clear pending navigation:', error);
-  }
-}
-
 /**
  * Check for and handle pending deep link navigation.
  * Called as part of checkPendingNavigation.
@@ -121,7 +103,7 @@ export async function checkPendingNavigation(): Promise<boolean> {
   }
 
   // Check for notification tap navigation
-  const feedId = await getPendingNavigation();
+  const feedId = await takePendingNavigation();
 
   if (!feedId) {
     debugLog('[PushHandler] No pending navigation');
@@ -130,9 +112,6 @@ export async function checkPendingNavigation(): Promise<boolean> {
 
   debugLog(`[PushHandler] Found pending navigation to feed: ${feedId.substring(0, 8)}...`);
 
-  // Clear before navigating to prevent loops
-  await clearPendingNavigation();
-
   // Navigate to the feed
   try {
     // Use window.location for simplicity and reliability
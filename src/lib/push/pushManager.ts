@@ -18,7 +18,26 @@ import { debugLog, debugError } from '@/lib/debug-logger';
 // Types for Tauri command responses (matching Rust types)
 interface FcmTokenResult {
   token: string | null;
+  token_type?: 'fcm' | 'apns' | null;
+  /** Deprecated: match on `error_code` instead */
   error: string | null;
+  error_code?: FcmError['code'] | null;
+}
+
+/** Error rejected by the push commands; match on `code`, not `message`. */
+interface FcmError {
+  code:
+    | 'not_supported'
+    | 'not_yet_available'
+    | 'permission_denied'
+    | 'invalid_native_value'
+    | 'native_bridge_failure';
+  message: string;
+  retryable?: boolean;
+}
+
+function isFcmError(error: unknown): error is FcmError {
+  return typeof error === 'object' && error !== null && 'code' in error && 'message' in error;
 }
 
 interface PermissionResult {
@@ -159,6 +178,9 @@ async function getNativeToken(): Promise<FcmTokenResult> {
     return await invoke<FcmTokenResult>('get_fcm_token');
   } catch (error) {
     debugError('[PushManager] Failed to get FCM token:', error);
+    if (isFcmError(error)) {
+      return { token: null, error: error.message };
+    }
     return { token: null, error: error instanceof Error ? error.message : 'Unknown error' };
   }
 }
@@ -2,7 +2,13 @@
 
 import { type ReactNode } from 'react';
 import { SyncProvider } from '@/lib/sync';
-import { useUnreadBadge, useAutoUpdate, usePushInitializer, useDeepLinkNavigation } from '@/hooks';
+import {
+  useUnreadBadge,
+  useAutoUpdate,
+  usePushInitializer,
+  useDeepLinkNavigation,
+  useFrontendReady,
+} from '@/hooks';
 import { UpdateOverlay } from '@/components/updates';
 
 // Sync interval in milliseconds (configurable via environment variable)
@@ -48,6 +54,15 @@ function DeepLinkInitializer(): null {
   return null;
 }
 
+/**
+ * Component that reports the first render to Tauri, which then shows the
+ * initially hidden window.
+ */
+function FrontendReadyInitializer(): null {
+  useFrontendReady();
+  return null;
+}
+
 export function Providers({ children }: ProvidersProps) {
   return (
     <SyncProvider config={{ intervalMs: SYNC_INTERVAL_MS }}>
@@ -55,6 +70,7 @@ export function Providers({ children }: ProvidersProps) {
       <AutoUpdateInitializer />
       <PushInitializer />
       <DeepLinkInitializer />
+      <FrontendReadyInitializer />
       {children}
       <UpdateOverlay />
     </SyncProvider>